@@ -10,31 +10,57 @@ pub enum ADIFType {
 }
 
 impl ADIFType {
-    pub fn serialize(&self, field_name: &str) -> Result<String> {
-        let value = match self {
+    /// The data type indicator written after the length. Strings are the default and go without one.
+    pub fn type_indicator(&self) -> Option<char> {
+        match self {
+            ADIFType::Str(_) => None,
+            ADIFType::Bool(_) => Some('B'),
+            ADIFType::Num(_) => Some('N'),
+        }
+    }
+
+    /// The value as written to the file: booleans are Y or N
+    fn value(&self) -> String {
+        match self {
             ADIFType::Str(val) => val.to_string(),
-            ADIFType::Bool(_) => todo!(),
-            ADIFType::Num(_) => todo!(),
-        };
+            ADIFType::Bool(val) => if *val { "Y" } else { "N" }.to_string(),
+            ADIFType::Num(val) => val.to_string(),
+        }
+    }
+
+    /// Writes the field as `<NAME:LENGTH:TYPE>VALUE`. The length counts characters, not
+    /// UTF-8 bytes, and the parser reads values by it, so they may contain `<` or line breaks.
+    pub fn serialize(&self, field_name: &str) -> Result<String> {
+        let name = field_name.to_uppercase().replace(" ", "_");
+        // the characters the parser accepts in a tag name
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            Err(util::Error::ADIFSerializeError {
+                message: "Invalid ADIF field name".to_string(),
+                offender: field_name.to_string(),
+            })?
+        }
+        if let ADIFType::Num(val) = self
+            && !val.is_finite()
+        {
+            Err(util::Error::ADIFSerializeError {
+                message: format!("{} is not a number", name),
+                offender: self.to_string(),
+            })?
+        }
+        let value = self.value();
         Ok(format!(
             "<{}:{}{}>{}",
-            field_name.to_uppercase().replace(" ", "_"),
-            value.len(),
-            String::new(),
+            name,
+            value.chars().count(),
+            self.type_indicator()
+                .map(|ty| format!(":{}", ty))
+                .unwrap_or_default(),
             value
         ))
     }
 
     pub fn extract_value(&self) -> Result<String> {
-        match self {
-            ADIFType::Str(v) => Ok(v.to_string()),
-            _ => {
-                Err(util::Error::ADIFSerializeError {
-                    message: "Cannot handle ADIF record with type".to_string(),
-                    offender: self.to_string(),
-                })?
-            }
-        }
+        Ok(self.value())
     }
 }
 
@@ -131,3 +157,45 @@ impl ADIFFile {
         Ok(output)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        data::{ADIFFile, ADIFHeader, ADIFRecord, ADIFType},
+        parse,
+    };
+
+    #[test]
+    pub fn test_serialize() {
+        let str = |v: &str| ADIFType::Str(v.to_string());
+        assert_eq!("<NAME:4>Jörg", str("Jörg").serialize("name").unwrap());
+        assert_eq!(
+            "<SWL:1:B>N",
+            ADIFType::Bool(false).serialize("swl").unwrap()
+        );
+        assert_eq!(
+            "<FREQ:6:N>14.074",
+            ADIFType::Num(14.074).serialize("freq").unwrap()
+        );
+        assert!(str("x").serialize("BAD:NAME").is_err());
+        assert!(str("x").serialize("MY-FIELD").is_err());
+        assert!(str("x").serialize("NAMEÄ").is_err());
+        assert_eq!("<MY_FIELD:1>x", str("x").serialize("my field").unwrap());
+        assert!(ADIFType::Num(f64::NAN).serialize("FREQ").is_err());
+
+        let file = ADIFFile::new(
+            ADIFHeader(vec![("ADIF_VER".to_string(), str("3.1.4"))]),
+            vec![
+                ADIFRecord(vec![
+                    ("CALL".to_string(), str("DL1ABC")),
+                    ("NAME".to_string(), str("Jörg Müller")),
+                    ("NOTES".to_string(), str("line one\r\n<b>line two</b>")),
+                    ("QSO_RANDOM".to_string(), ADIFType::Bool(true)),
+                    ("FREQ".to_string(), ADIFType::Num(7.0255)),
+                ]),
+                ADIFRecord(vec![("CALL".to_string(), str("JA1XYZ"))]),
+            ],
+        );
+        assert_eq!(file, parse::parse_adif(&file.serialize().unwrap()));
+    }
+}
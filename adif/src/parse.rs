@@ -2,7 +2,7 @@
 
 use regex::Regex;
 
-use crate::data::{self, ADIFFile, ADIFRecord, ADIFType};
+use crate::data::{self, ADIFFile, ADIFType};
 
 pub struct Token {
     pub key: String,
@@ -11,64 +11,84 @@ pub struct Token {
     pub val: String,
 }
 
+/// Reads the tags of `data` in order. Values are the `len` characters after a tag, so
+/// they may contain anything. Tags without a length, like EOH and EOR, have an empty value.
+/// Tags with a length that does not fit a usize are left out.
 pub fn parse_tokens(data: &str) -> Vec<Token> {
-    Regex::new(r"<([a-zA-Z|_]+):(\d+)(?::([a-z]))?>([^<\n]+)")
-        .unwrap()
-        .captures_iter(data)
-        .map(|cap| Token {
+    let tag = Regex::new(r"<([a-zA-Z0-9_]+)(?::(\d+)(?::([a-zA-Z]))?)?>").unwrap();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while let Some(cap) = tag.captures_at(data, pos) {
+        let start = cap.get(0).unwrap().end();
+        let len = match cap.get(2).map(|m| m.as_str().parse()) {
+            Some(Ok(len)) => len,
+            // a length too big for a usize, skip the tag
+            Some(Err(_)) => {
+                pos = start;
+                continue;
+            }
+            None => 0,
+        };
+        let end = data[start..]
+            .char_indices()
+            .nth(len)
+            .map(|(i, _)| start + i)
+            .unwrap_or(data.len());
+        tokens.push(Token {
             key: cap[1].to_string().to_uppercase(),
-            len: cap[2].parse().expect("len in token parse error"),
+            len,
             ty: cap
                 .get(3)
                 .map(|m| m.as_str().chars().next().unwrap().to_ascii_uppercase()),
-            val: cap[4].trim_end().to_string(),
-        })
-        .collect()
+            val: data[start..end].to_string(),
+        });
+        pos = end;
+    }
+    tokens
 }
 
 pub fn build_token_list(tokens: Vec<Token>) -> Vec<(String, ADIFType)> {
     let mut tuples = Vec::new();
     for token in tokens {
-        tuples.push((
-            token.key.clone(),
-            match token.ty {
-                Some(ty) => match ty {
-                    _ => ADIFType::Str(token.val),
-                },
-                None => ADIFType::Str(token.val),
+        let val = match (token.ty, token.val.as_str()) {
+            (Some('B'), "Y" | "y") => ADIFType::Bool(true),
+            (Some('B'), "N" | "n") => ADIFType::Bool(false),
+            (Some('N'), v) => match v.parse() {
+                Ok(v) => ADIFType::Num(v),
+                Err(_) => ADIFType::Str(token.val),
             },
-        ));
+            _ => ADIFType::Str(token.val),
+        };
+        tuples.push((token.key, val));
     }
     tuples
 }
 
+/// Parses an ADIF file. A file without `<EOH>` has no header, fields after the
+/// last `<EOR>` make up one more record.
 pub fn parse_adif(data: &str) -> ADIFFile {
-    let data = data.replace("<eoh>", "<EOH>").replace("<eor>", "<EOR>");
-    let data = data.split("<EOH>");
-    let data = data.collect::<Vec<&str>>();
-
-    let header = match data.len() {
-        1 => {
-            todo!()
-        }
-        2 => build_token_list(parse_tokens(data.first().unwrap_or(&""))),
-        _ => {
-            // bad file (multiple headers or blank)
-            todo!()
+    let tokens = parse_tokens(data);
+    let mut in_header = tokens.iter().any(|t| t.key == "EOH");
+    let mut header = Vec::new();
+    let mut body = Vec::new();
+    let mut record = Vec::new();
+    for token in tokens {
+        match token.key.as_str() {
+            "EOH" => in_header = false,
+            "EOR" => body.push(data::ADIFRecord(build_token_list(std::mem::take(
+                &mut record,
+            )))),
+            _ if in_header => header.push(token),
+            _ => record.push(token),
         }
-    };
+    }
+    if !record.is_empty() {
+        body.push(data::ADIFRecord(build_token_list(record)));
+    }
 
     ADIFFile {
-        header: data::ADIFHeader(header),
-        body: data
-            .last()
-            .unwrap_or(&"")
-            .replace("\n", "")
-            .split_terminator("<EOR>")
-            .collect::<Vec<&str>>()
-            .iter()
-            .map(|l| data::ADIFRecord(build_token_list(parse_tokens(l))))
-            .collect::<Vec<ADIFRecord>>(),
+        header: data::ADIFHeader(build_token_list(header)),
+        body,
     }
 }
 
@@ -101,4 +121,27 @@ mod tests {
         );
         println!("{}", file.serialize().unwrap());
     }
+
+    #[test]
+    pub fn parse_pasted_records() {
+        let data = "here you go: <call:4>W1AW <band:3>20m <eor>\n\
+            <call:6>DL1ABC <band:3>40m";
+        let file = parse::parse_adif(data);
+        assert!(file.header.0.is_empty());
+        assert_eq!(2, file.body.len());
+        assert_eq!(
+            ("CALL".to_string(), ADIFType::Str("DL1ABC".to_string())),
+            file.body[1].0[0]
+        );
+    }
+
+    #[test]
+    pub fn parse_oversized_length() {
+        let data = "<call:99999999999999999999999>W1AW <band:3>20m <eor>";
+        let file = parse::parse_adif(data);
+        assert_eq!(
+            vec![("BAND".to_string(), ADIFType::Str("20m".to_string()))],
+            file.body[0].0
+        );
+    }
 }
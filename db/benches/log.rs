@@ -0,0 +1,119 @@
+//! Benchmarks of writing and reading a log, run with `cargo bench -p db`
+
+use std::hint::black_box;
+
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use db::{
+    data::{FieldType, Log, LogHeader, LogRecord},
+    filter::Filter,
+};
+use jiff::{SignedDuration, Timestamp};
+use util::{band::Band, freq::Frequency};
+
+/// Records in the log the reading benchmarks run against
+const LOG_SIZE: usize = 10_000;
+
+const SPOTS: &[(u64, &str)] = &[
+    (3_573_000, "FT8"),
+    (7_030_000, "CW"),
+    (14_074_000, "FT8"),
+    (14_250_000, "SSB"),
+    (21_074_000, "FT8"),
+    (28_450_000, "SSB"),
+];
+
+/// An empty log in a temporary database. Benches are built without cfg(test), so they
+/// can't use the crate's `test_log`.
+fn empty_log() -> Log {
+    let db = sled::Config::new().temporary(true).open().unwrap();
+    Log::new_init(db, LogHeader::new("N0CALL", "")).unwrap()
+}
+
+/// QSO number `i`, a minute after the one before
+fn qso(i: usize) -> LogRecord {
+    let (hz, mode) = SPOTS[i % SPOTS.len()];
+    let time = Timestamp::from_second(1_750_000_000).unwrap() + SignedDuration::from_mins(i as i64);
+    let mut record = LogRecord::new();
+    record
+        .insert_timestamp(time)
+        .insert_field(
+            FieldType::WorkedCall,
+            &format!("{}{}{}", ["W", "DL", "JA"][i % 3], i % 10, i % 997),
+        )
+        .insert_frequency(FieldType::Frequency, Frequency::from_hz(hz))
+        .insert_field(FieldType::Mode, mode)
+        .insert_field(FieldType::SentRST, "599")
+        .insert_field(FieldType::RcvdRST, "579")
+        .insert_field(FieldType::Name, "Bob");
+    record
+}
+
+fn qsos(count: usize) -> Vec<LogRecord> {
+    (0..count).map(qso).collect()
+}
+
+fn insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    group.sample_size(10);
+    for count in [1_000, LOG_SIZE] {
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_function(format!("insert_records/{}", count), |b| {
+            b.iter_batched(
+                || (empty_log(), qsos(count)),
+                |(log, records)| log.insert_records(records).unwrap(),
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    // one at a time, as QSOs are logged by hand
+    group.throughput(Throughput::Elements(1_000));
+    group.bench_function("insert_record/1000", |b| {
+        b.iter_batched(
+            || (empty_log(), qsos(1_000)),
+            |(log, records)| {
+                for record in records {
+                    log.insert_record(record).unwrap();
+                }
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+fn get(c: &mut Criterion) {
+    let log = empty_log();
+    log.insert_records(qsos(LOG_SIZE)).unwrap();
+    let mut group = c.benchmark_group("get");
+    let mut idx = 0;
+    group.bench_function("get_record", |b| {
+        b.iter(|| {
+            idx = (idx + 7919) % LOG_SIZE;
+            black_box(log.get_record(idx).unwrap())
+        })
+    });
+    group.throughput(Throughput::Elements(LOG_SIZE as u64));
+    group.bench_function("get_records", |b| b.iter(|| log.get_records()));
+    group.finish();
+}
+
+fn query(c: &mut Criterion) {
+    let log = empty_log();
+    log.insert_records(qsos(LOG_SIZE)).unwrap();
+    let mut group = c.benchmark_group("query");
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(LOG_SIZE as u64));
+    group.bench_function("search", |b| b.iter(|| log.search(black_box("ja3"))));
+    let filter = Filter {
+        bands: vec![Band::M20],
+        modes: vec!["FT8".to_string()],
+        ..Filter::default()
+    };
+    group.bench_function("filter_records", |b| {
+        b.iter(|| log.filter_records(black_box(&filter)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, insert, get, query);
+criterion_main!(benches);
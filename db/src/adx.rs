@@ -0,0 +1,209 @@
+use std::{fs, path::Path};
+
+use adif::data::{ADIFFile, ADIFHeader, ADIFRecord, ADIFType};
+use anyhow::{Result, bail};
+use jiff::tz::TimeZone;
+
+use crate::{
+    data::{ImportPolicy, Log},
+    filter::Filter,
+    provenance::Source,
+};
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Writes a field as an ADX element. Application defined fields, named like
+/// `APP_N1MM_EXCHANGE1` in ADIF, become `APP` elements naming the program and field.
+fn write_field(out: &mut String, name: &str, value: &ADIFType) -> Result<()> {
+    let name = name.to_ascii_uppercase();
+    let value = escape(&value.extract_value()?);
+    match name.strip_prefix("APP_").and_then(|n| n.split_once('_')) {
+        Some((program, field)) => out.push_str(&format!(
+            "<APP PROGRAMID=\"{}\" FIELDNAME=\"{}\" TYPE=\"S\">{}</APP>\n",
+            escape(program),
+            escape(field),
+            value
+        )),
+        None => out.push_str(&format!("<{}>{}</{}>\n", name, value, name)),
+    }
+    Ok(())
+}
+
+/// Serializes an ADIF file as ADX, the XML flavour of ADIF
+pub fn adx_string(adif: &ADIFFile) -> Result<String> {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<ADX>\n<HEADER>\n");
+    for (name, value) in &adif.header.0 {
+        write_field(&mut out, name, value)?;
+    }
+    out.push_str("</HEADER>\n<RECORDS>\n");
+    for record in &adif.body {
+        out.push_str("<RECORD>\n");
+        for (name, value) in &record.0 {
+            write_field(&mut out, name, value)?;
+        }
+        out.push_str("</RECORD>\n");
+    }
+    out.push_str("</RECORDS>\n</ADX>\n");
+    Ok(out)
+}
+
+/// The value of attribute `name` in the attributes of a start tag. Names match exactly,
+/// `FIELDNAME` is not found in `MYFIELDNAME="..."` nor in another attribute's value.
+fn attribute(mut attrs: &str, name: &str) -> Option<String> {
+    loop {
+        let (key, rest) = attrs.split_once('=')?;
+        let rest = rest.trim_start();
+        let quote = rest.chars().next().filter(|q| matches!(q, '"' | '\''))?;
+        let (value, rest) = rest[1..].split_once(quote)?;
+        if key.trim() == name {
+            return Some(unescape(value));
+        }
+        attrs = rest;
+    }
+}
+
+/// Reads the fields of an ADX element holding one element per field, such as `HEADER`
+fn parse_fields(mut xml: &str) -> Result<Vec<(String, ADIFType)>> {
+    let mut fields = Vec::new();
+    while let Some(start) = xml.find('<') {
+        let Some(end) = xml[start..].find('>').map(|e| start + e) else {
+            bail!("Unterminated ADX tag: {}", &xml[start..]);
+        };
+        let tag = &xml[start + 1..end];
+        xml = &xml[end + 1..];
+        // empty elements hold no value
+        if tag.ends_with('/') {
+            continue;
+        }
+        let (element, attrs) = tag.split_once(' ').unwrap_or((tag, ""));
+        let close = format!("</{}>", element);
+        let Some(len) = xml.find(&close) else {
+            bail!("ADX element {} is not closed", element);
+        };
+        let value = unescape(&xml[..len]);
+        xml = &xml[len + close.len()..];
+        let name = match element.to_ascii_uppercase().as_str() {
+            "APP" => match (attribute(attrs, "PROGRAMID"), attribute(attrs, "FIELDNAME")) {
+                (Some(program), Some(field)) => format!("APP_{}_{}", program, field),
+                _ => bail!("ADX APP element without PROGRAMID and FIELDNAME"),
+            },
+            // in records, user defined fields name themselves in an attribute
+            "USERDEF" => match attribute(attrs, "FIELDNAME") {
+                Some(field) => field,
+                None => continue,
+            },
+            name => name.to_string(),
+        };
+        fields.push((name.to_ascii_uppercase(), ADIFType::Str(value)));
+    }
+    Ok(fields)
+}
+
+/// The content of the first `element` in `xml`
+fn element<'a>(xml: &'a str, element: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", element))? + element.len() + 2;
+    let len = xml[start..].find(&format!("</{}>", element))?;
+    Some(&xml[start..start + len])
+}
+
+/// Parses an ADX file into the same records an ADIF file would give
+pub fn parse_adx(xml: &str) -> Result<ADIFFile> {
+    let Some(adx) = element(xml, "ADX") else {
+        bail!("Not an ADX file");
+    };
+    // USERDEF elements in the header only declare fields and are skipped
+    let header = match element(adx, "HEADER") {
+        Some(header) => parse_fields(header)?,
+        None => Vec::new(),
+    };
+    let mut body = Vec::new();
+    let mut records = element(adx, "RECORDS").unwrap_or_default();
+    while let Some(record) = element(records, "RECORD") {
+        body.push(ADIFRecord(parse_fields(record)?));
+        let end = records.find("</RECORD>").unwrap_or_default() + "</RECORD>".len();
+        records = &records[end..];
+    }
+    Ok(ADIFFile::new(ADIFHeader(header), body))
+}
+
+impl Log {
+    /// Writes the records selected by `filter` to an ADX file, then moves the export
+    /// watermark if the filter names one
+    pub fn export_adx(&self, path: &Path, filter: &Filter) -> Result<()> {
+        let (adif, end) = self.export_adif_until(filter)?;
+        fs::write(path, adx_string(&adif)?)?;
+        self.mark_exported(filter, end)
+    }
+
+    /// Imports an ADX file like an ADIF file, atomically, with its times in `tz`
+    pub fn import_adx(&self, path: &Path, policy: ImportPolicy, tz: &TimeZone) -> Result<()> {
+        let adif = parse_adx(&fs::read_to_string(path)?)?;
+        self.import_adif_from(adif, policy, tz, &Source::import(path))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use adif::data::{ADIFFile, ADIFHeader, ADIFRecord, ADIFType};
+
+    use super::{adx_string, attribute, parse_adx};
+
+    #[test]
+    pub fn test_adx_round_trip() {
+        let str = |v: &str| ADIFType::Str(v.to_string());
+        let file = ADIFFile::new(
+            ADIFHeader(vec![("ADIF_VER".to_string(), str("3.1.5"))]),
+            vec![
+                ADIFRecord(vec![
+                    ("CALL".to_string(), str("DL1ABC")),
+                    ("NAME".to_string(), str("Jörg & <Co>")),
+                    ("APP_N1MM_EXCHANGE1".to_string(), str("14")),
+                ]),
+                ADIFRecord(vec![("CALL".to_string(), str("JA1XYZ"))]),
+            ],
+        );
+        let adx = adx_string(&file).unwrap();
+        assert!(adx.contains("<NAME>Jörg &amp; &lt;Co&gt;</NAME>"));
+        assert!(
+            adx.contains("<APP PROGRAMID=\"N1MM\" FIELDNAME=\"EXCHANGE1\" TYPE=\"S\">14</APP>")
+        );
+        assert_eq!(file, parse_adx(&adx).unwrap());
+
+        let userdef = "<ADX><HEADER><USERDEF FIELDID=\"1\" TYPE=\"S\">EPC</USERDEF></HEADER>\
+                       <RECORDS><RECORD><call>W1AW</call><GRIDSQUARE/>\
+                       <USERDEF FIELDNAME=\"EPC\">32123</USERDEF></RECORD></RECORDS></ADX>";
+        let parsed = parse_adx(userdef).unwrap();
+        assert!(parsed.header.0.is_empty());
+        assert_eq!(
+            vec![
+                ("CALL".to_string(), str("W1AW")),
+                ("EPC".to_string(), str("32123"))
+            ],
+            parsed.body[0].0
+        );
+        assert!(parse_adx("<ADIF></ADIF>").is_err());
+    }
+
+    #[test]
+    pub fn test_attribute() {
+        let attrs = "MYFIELDNAME=\"A\" NOTE='FIELDNAME=\"B\"' FIELDNAME = \"C &amp; D\"";
+        assert_eq!(Some("C & D".to_string()), attribute(attrs, "FIELDNAME"));
+        assert_eq!(Some("A".to_string()), attribute(attrs, "MYFIELDNAME"));
+        assert_eq!(None, attribute(attrs, "NAME"));
+        assert_eq!(None, attribute("FIELDNAME=\"C", "FIELDNAME"));
+    }
+}
@@ -0,0 +1,380 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write,
+};
+
+use anyhow::Result;
+
+use util::{
+    band::Band,
+    dxcc::PrefixDb,
+    fields,
+    geo::{GridStrictness, prettyvalidate_gridsquare},
+    mode::ModeClass,
+};
+
+use crate::data::{FieldType, Log, LogRecord};
+
+/// How badly a station is needed for DXCC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Need {
+    /// Entity never worked
+    NewOne,
+    /// Entity worked, but not on this band
+    NewBand,
+    /// Entity worked on this band, but not in this mode class
+    NewMode,
+    Worked,
+}
+
+/// Entities worked per band and mode class, keyed by the entity's primary prefix
+#[derive(Debug, Default)]
+pub struct DxccProgress {
+    entities: HashSet<String>,
+    bands: HashSet<(String, Band)>,
+    slots: HashSet<(String, Band, ModeClass)>,
+    /// Entities with a QSO confirmed on LoTW, which count for DXCC credit
+    confirmed: HashSet<String>,
+}
+
+impl DxccProgress {
+    pub fn from_records<'a>(
+        records: impl IntoIterator<Item = &'a LogRecord>,
+        prefixes: &PrefixDb,
+    ) -> Self {
+        let mut progress = Self::default();
+        for record in records {
+            let Some(call) = record.get_field(&FieldType::WorkedCall) else {
+                continue;
+            };
+            let Some(m) = prefixes.lookup(&call) else {
+                continue;
+            };
+            let band = record.frequency().and_then(Band::from_freq);
+            let mode = record
+                .get_field(&FieldType::Mode)
+                .map(|m| ModeClass::from_mode(&m));
+            progress.add(&m.entity.prefix, band, mode);
+            if record.get_field(&FieldType::LotwRcvd).as_deref() == Some("Y") {
+                progress.confirmed.insert(m.entity.prefix.clone());
+            }
+        }
+        progress
+    }
+
+    pub fn add(&mut self, entity: &str, band: Option<Band>, mode: Option<ModeClass>) {
+        self.entities.insert(entity.to_string());
+        if let Some(band) = band {
+            self.bands.insert((entity.to_string(), band));
+            if let Some(mode) = mode {
+                self.slots.insert((entity.to_string(), band, mode));
+            }
+        }
+    }
+
+    /// Classifies a contact with `entity`. Unknown band or mode skip the respective check.
+    pub fn need(&self, entity: &str, band: Option<Band>, mode: Option<ModeClass>) -> Need {
+        let entity = entity.to_string();
+        if !self.entities.contains(&entity) {
+            return Need::NewOne;
+        }
+        let Some(band) = band else {
+            return Need::Worked;
+        };
+        if !self.bands.contains(&(entity.clone(), band)) {
+            return Need::NewBand;
+        }
+        match mode {
+            Some(mode) if !self.slots.contains(&(entity, band, mode)) => Need::NewMode,
+            _ => Need::Worked,
+        }
+    }
+
+    pub fn entities_worked(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn entities_confirmed(&self) -> usize {
+        self.confirmed.len()
+    }
+}
+
+/// US counties worked, for county hunting awards such as USA-CA. Counties are told apart
+/// regardless of case, each is listed as first logged.
+#[derive(Debug, Default)]
+pub struct CountyProgress {
+    /// Whether each county is confirmed, by state and county
+    counties: BTreeMap<String, BTreeMap<String, bool>>,
+}
+
+impl CountyProgress {
+    pub fn from_records<'a>(records: impl IntoIterator<Item = &'a LogRecord>) -> Self {
+        let mut progress = Self::default();
+        for record in records {
+            let Some(county) = record
+                .get_field(&FieldType::SecondaryAdminSubdiv)
+                .and_then(|c| fields::validate_county(&c).ok())
+            else {
+                continue;
+            };
+            let confirmed = record.get_field(&FieldType::LotwRcvd).as_deref() == Some("Y");
+            progress.add(&county, confirmed);
+        }
+        progress
+    }
+
+    /// Adds a QSO with `county`, as validated by `fields::validate_county`
+    pub fn add(&mut self, county: &str, confirmed: bool) {
+        let Some((state, name)) = county.split_once(',') else {
+            return;
+        };
+        let counties = self.counties.entry(state.to_string()).or_default();
+        let known = counties
+            .keys()
+            .find(|c| c.eq_ignore_ascii_case(name))
+            .cloned();
+        *counties
+            .entry(known.unwrap_or_else(|| name.to_string()))
+            .or_default() |= confirmed;
+    }
+
+    pub fn counties_worked(&self) -> usize {
+        self.counties.values().map(BTreeMap::len).sum()
+    }
+
+    pub fn counties_confirmed(&self) -> usize {
+        self.counties
+            .values()
+            .flat_map(BTreeMap::values)
+            .filter(|c| **c)
+            .count()
+    }
+
+    /// The counties worked in each state, confirmed ones marked with `*`
+    pub fn report(&self) -> Result<String> {
+        let mut text = format!(
+            "{} counties worked, {} confirmed, in {} states\n",
+            self.counties_worked(),
+            self.counties_confirmed(),
+            self.counties.len()
+        );
+        for (state, counties) in &self.counties {
+            let names: Vec<String> = counties
+                .iter()
+                .map(|(name, confirmed)| match confirmed {
+                    true => format!("{}*", name),
+                    false => name.clone(),
+                })
+                .collect();
+            write!(
+                text,
+                "\n{} ({}): {}\n",
+                state,
+                counties.len(),
+                names.join(", ")
+            )?;
+        }
+        Ok(text)
+    }
+}
+
+/// Grids worked per band for VUCC, counted by their four character square. QSOs on a
+/// grid line or corner count for every square listed in their VUCC_GRIDS field.
+#[derive(Debug, Default)]
+pub struct VuccProgress {
+    /// Whether each square is confirmed, by band
+    grids: BTreeMap<Band, BTreeMap<String, bool>>,
+}
+
+/// The four character square of a grid such as `FN31pr`
+fn square(grid: &str) -> Option<String> {
+    let grid = prettyvalidate_gridsquare(grid.trim(), GridStrictness::Strict).ok()?;
+    Some(grid.get(..4)?.to_ascii_uppercase())
+}
+
+impl VuccProgress {
+    pub fn from_records<'a>(records: impl IntoIterator<Item = &'a LogRecord>) -> Self {
+        let mut progress = Self::default();
+        for record in records {
+            let Some(band) = record.frequency().and_then(Band::from_freq) else {
+                continue;
+            };
+            let grids = record
+                .get_field(&FieldType::from_adif_field("VUCC_GRIDS"))
+                .or_else(|| record.get_field(&FieldType::GridSquare))
+                .unwrap_or_default();
+            let confirmed = record.get_field(&FieldType::LotwRcvd).as_deref() == Some("Y");
+            for square in grids.split(',').filter_map(square) {
+                progress.add(band, &square, confirmed);
+            }
+        }
+        progress
+    }
+
+    /// Adds a QSO with the four character `square`. Bands below 6m don't count for VUCC.
+    pub fn add(&mut self, band: Band, square: &str, confirmed: bool) {
+        if band < Band::M6 {
+            return;
+        }
+        *self
+            .grids
+            .entry(band)
+            .or_default()
+            .entry(square.to_ascii_uppercase())
+            .or_default() |= confirmed;
+    }
+
+    /// Bands with grids worked, lowest first
+    pub fn bands(&self) -> impl Iterator<Item = Band> + '_ {
+        self.grids.keys().copied()
+    }
+
+    pub fn grids_worked(&self, band: Band) -> usize {
+        self.grids.get(&band).map_or(0, BTreeMap::len)
+    }
+
+    pub fn grids_confirmed(&self, band: Band) -> usize {
+        self.grids
+            .get(&band)
+            .map_or(0, |grids| grids.values().filter(|c| **c).count())
+    }
+
+    /// Confirmed grids the award needs on `band`: 100 on 6m and 2m, 50 on 1.25m and 70cm
+    /// and 25 above
+    pub fn target(band: Band) -> usize {
+        match band {
+            Band::M6 | Band::M2 => 100,
+            Band::M1_25 | Band::Cm70 => 50,
+            _ => 25,
+        }
+    }
+}
+
+impl Log {
+    pub fn dxcc_progress(&self, prefixes: &PrefixDb) -> DxccProgress {
+        DxccProgress::from_records(&self.get_records(), prefixes)
+    }
+
+    pub fn county_progress(&self) -> CountyProgress {
+        CountyProgress::from_records(&self.get_records())
+    }
+
+    pub fn vucc_progress(&self) -> VuccProgress {
+        VuccProgress::from_records(&self.get_records())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use util::{band::Band, dxcc::PrefixDb, mode::ModeClass};
+
+    use super::{CountyProgress, DxccProgress, Need, VuccProgress};
+    use crate::data::{FieldType, LogRecord};
+
+    #[test]
+    pub fn test_dxcc_need() {
+        let prefixes = PrefixDb::parse(
+            "Japan: 25: 45: AS: 36.40: -138.38: -9.0: JA:\n JA;\
+             Germany: 14: 28: EU: 51.00: -10.00: -1.0: DL:\n DL;",
+        )
+        .unwrap();
+        let mut record = LogRecord::new();
+        record
+            .insert_field(FieldType::WorkedCall, "JA1ABC")
+            .insert_field(FieldType::Frequency, "14.025")
+            .insert_field(FieldType::Mode, "CW");
+        let mut confirmed = LogRecord::new();
+        confirmed
+            .insert_field(FieldType::WorkedCall, "DL1ABC")
+            .insert_field(FieldType::LotwRcvd, "Y");
+        let progress = DxccProgress::from_records([&record], &prefixes);
+
+        assert_eq!(1, progress.entities_worked());
+        assert_eq!(0, progress.entities_confirmed());
+        assert_eq!(Need::NewOne, progress.need("DL", Some(Band::M20), None));
+        assert_eq!(Need::NewBand, progress.need("JA", Some(Band::M40), None));
+        assert_eq!(
+            Need::NewMode,
+            progress.need("JA", Some(Band::M20), Some(ModeClass::Phone))
+        );
+        assert_eq!(
+            Need::Worked,
+            progress.need("JA", Some(Band::M20), Some(ModeClass::Cw))
+        );
+        assert_eq!(Need::Worked, progress.need("JA", None, None));
+
+        let progress = DxccProgress::from_records([&record, &confirmed], &prefixes);
+        assert_eq!(2, progress.entities_worked());
+        assert_eq!(1, progress.entities_confirmed());
+    }
+
+    #[test]
+    pub fn test_county_progress() {
+        let qso = |county: &str, lotw: &str| {
+            let mut record = LogRecord::new();
+            record
+                .insert_field(FieldType::SecondaryAdminSubdiv, county)
+                .insert_field(FieldType::LotwRcvd, lotw);
+            record
+        };
+        let records = [
+            qso("MA,Middlesex", "N"),
+            qso("ma,MIDDLESEX", "Y"),
+            qso("MA,Essex", "N"),
+            qso("CT,Hartford", "N"),
+            qso("Hartford", "Y"),
+            // a JA city, which isn't a US county
+            qso("100101", "Y"),
+        ];
+        let progress = CountyProgress::from_records(&records);
+        assert_eq!(3, progress.counties_worked());
+        assert_eq!(1, progress.counties_confirmed());
+        assert_eq!(
+            "3 counties worked, 1 confirmed, in 2 states\n\
+             \n\
+             CT (1): Hartford\n\
+             \n\
+             MA (2): Essex, Middlesex*\n",
+            progress.report().unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_vucc_progress() {
+        let qso = |freq: &str, grid: &str, lotw: &str| {
+            let mut record = LogRecord::new();
+            record
+                .insert_field(FieldType::Frequency, freq)
+                .insert_field(FieldType::GridSquare, grid)
+                .insert_field(FieldType::LotwRcvd, lotw);
+            record
+        };
+        let mut corner = qso("144.200", "FN31", "N");
+        corner.insert_field(
+            FieldType::from_adif_field("VUCC_GRIDS"),
+            "FN31,FN32,FN41,FN42",
+        );
+        let records = [
+            qso("50.125", "FN31pr", "N"),
+            qso("50.313", "fn31", "Y"),
+            qso("50.313", "FN42", "N"),
+            qso("144.200", "FN31", "N"),
+            corner,
+            // HF and broken grids don't count
+            qso("14.074", "JN58", "Y"),
+            qso("50.313", "ZZ99", "Y"),
+        ];
+        let progress = VuccProgress::from_records(&records);
+        assert_eq!(
+            vec![Band::M6, Band::M2],
+            progress.bands().collect::<Vec<_>>()
+        );
+        assert_eq!(2, progress.grids_worked(Band::M6));
+        assert_eq!(1, progress.grids_confirmed(Band::M6));
+        assert_eq!(4, progress.grids_worked(Band::M2));
+        assert_eq!(0, progress.grids_confirmed(Band::M2));
+        assert_eq!(0, progress.grids_worked(Band::M20));
+        assert_eq!(100, VuccProgress::target(Band::M6));
+        assert_eq!(25, VuccProgress::target(Band::Cm23));
+    }
+}
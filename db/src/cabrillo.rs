@@ -0,0 +1,184 @@
+use std::{fmt::Write, fs, path::Path};
+
+use anyhow::{Result, bail};
+use jiff::Timestamp;
+use util::{band::Band, mode::ModeClass};
+
+use crate::{
+    data::{FieldType, Log, LogRecord},
+    filter::Filter,
+};
+
+/// ADIF fields making up the exchange sent and received, in the order they are written.
+/// Fields a QSO does not have, as the contest does not use them, are left out.
+const SENT: &[&str] = &["RST_SENT", "STX", "STX_STRING", "MY_CQ_ZONE"];
+const RCVD: &[&str] = &[
+    "RST_RCVD",
+    "SRX",
+    "SRX_STRING",
+    "CQZ",
+    "PRECEDENCE",
+    "CHECK",
+    "CLASS",
+    "ARRL_SECT",
+];
+
+/// The frequency column: kHz below 30 MHz, the band from 6m up, e.g. `14025` or `144`
+fn frequency(record: &LogRecord) -> Option<String> {
+    let freq = record.frequency();
+    let band = match freq {
+        Some(f) => Band::from_freq(f)?,
+        None => Band::from_name(&record.get_field(&FieldType::from_adif_field("BAND"))?)?,
+    };
+    let code = match band {
+        Band::M6 => "50",
+        Band::M4 => "70",
+        Band::M2 => "144",
+        Band::M1_25 => "222",
+        Band::Cm70 => "432",
+        Band::Cm33 => "902",
+        Band::Cm23 => "1.2G",
+        Band::Cm13 => "2.3G",
+        Band::Cm9 => "3.4G",
+        Band::Cm6 => "5.7G",
+        Band::Cm3 => "10G",
+        Band::Cm1_25 => "24G",
+        Band::Mm6 => "47G",
+        Band::Mm4 => "75G",
+        Band::Mm2_5 => "122G",
+        Band::Mm2 => "134G",
+        Band::Mm1 => "241G",
+        // logs only noting the band give its lower edge
+        _ => {
+            return Some(format!(
+                "{:.0}",
+                freq.map_or(band.range_mhz().0, |f| f.mhz()) * 1e3
+            ));
+        }
+    };
+    Some(code.to_string())
+}
+
+fn mode(record: &LogRecord) -> Option<&'static str> {
+    let mode = record.get_field(&FieldType::Mode)?;
+    Some(match ModeClass::from_mode(&mode) {
+        ModeClass::Cw => "CW",
+        ModeClass::Phone => "PH",
+        ModeClass::Digital if mode.eq_ignore_ascii_case("RTTY") => "RY",
+        ModeClass::Digital => "DG",
+    })
+}
+
+fn exchange(record: &LogRecord, fields: &[&str]) -> String {
+    fields
+        .iter()
+        .filter_map(|name| record.get_field(&FieldType::from_adif_field(name)))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+impl Log {
+    /// The records `filter` selects as a Cabrillo 3 log for contest robots, with the
+    /// watermark the export moves to. The contest is taken from the CONTEST_ID of the QSOs.
+    fn write_cabrillo(&self, filter: &Filter) -> Result<(String, usize)> {
+        let (records, end) = self.filter_records(filter)?;
+        let header = self.get_header()?;
+        let mut out = String::from("START-OF-LOG: 3.0\n");
+        if let Some(contest) = records
+            .iter()
+            .find_map(|r| r.get_field(&FieldType::from_adif_field("CONTEST_ID")))
+        {
+            writeln!(out, "CONTEST: {}", contest)?;
+        }
+        writeln!(out, "CALLSIGN: {}", header.op_call())?;
+        writeln!(out, "CREATED-BY: veelog {}", env!("CARGO_PKG_VERSION"))?;
+        for record in &records {
+            let call = record.get_field(&FieldType::WorkedCall).unwrap_or_default();
+            let (Some(freq), Some(mode), Some(time)) = (
+                frequency(record),
+                mode(record),
+                record
+                    .get_field(&FieldType::Timestamp)
+                    .and_then(|t| t.parse::<Timestamp>().ok()),
+            ) else {
+                bail!("The QSO with {} has no band, mode or time", call);
+            };
+            let my_call = record
+                .get_field(&FieldType::from_adif_field("STATION_CALLSIGN"))
+                .unwrap_or_else(|| header.op_call().to_string());
+            writeln!(
+                out,
+                "QSO: {:>5} {} {} {:<13} {:<10} {:<13} {}",
+                freq,
+                mode,
+                time.strftime("%Y-%m-%d %H%M"),
+                my_call,
+                exchange(record, SENT),
+                call,
+                exchange(record, RCVD),
+            )?;
+        }
+        out += "END-OF-LOG:\n";
+        Ok((out, end))
+    }
+
+    /// The records `filter` selects as a Cabrillo log
+    pub fn cabrillo(&self, filter: &Filter) -> Result<String> {
+        Ok(self.write_cabrillo(filter)?.0)
+    }
+
+    /// Writes a Cabrillo log to `path`, then moves the export watermark if the filter
+    /// names one
+    pub fn export_cabrillo(&self, path: &Path, filter: &Filter) -> Result<()> {
+        let (cabrillo, end) = self.write_cabrillo(filter)?;
+        fs::write(path, cabrillo)?;
+        self.mark_exported(filter, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        data::{FieldType, LogRecord},
+        filter::Filter,
+        test_log,
+    };
+
+    #[test]
+    pub fn test_cabrillo() {
+        let log = test_log();
+        let qso = |call: &str, freq: &str, mode: &str| {
+            let mut record = LogRecord::new();
+            record
+                .insert_timestamp("2025-11-29T00:02:00Z".parse().unwrap())
+                .insert_field(FieldType::WorkedCall, call)
+                .insert_field(FieldType::Frequency, freq)
+                .insert_field(FieldType::Mode, mode)
+                .insert_field(FieldType::SentRST, "599")
+                .insert_field(FieldType::RcvdRST, "599")
+                .insert_field(FieldType::from_adif_field("CONTEST_ID"), "CQ-WW-CW");
+            record
+        };
+        let mut ja = qso("JA1XYZ", "14.025", "CW");
+        ja.insert_field(FieldType::CQZ, "25")
+            .insert_field(FieldType::from_adif_field("MY_CQ_ZONE"), "5");
+        log.insert_records(vec![ja, qso("W1AW", "144.050", "RTTY")])
+            .unwrap();
+
+        let cabrillo = log.cabrillo(&Filter::default()).unwrap();
+        assert!(cabrillo.starts_with("START-OF-LOG: 3.0\nCONTEST: CQ-WW-CW\nCALLSIGN: N0CALL\n"));
+        assert!(cabrillo.contains(
+            "QSO: 14025 CW 2025-11-29 0002 N0CALL        599 5      JA1XYZ        599 25\n"
+        ));
+        assert!(cabrillo.contains("QSO:   144 RY 2025-11-29 0002 N0CALL"));
+        assert!(cabrillo.ends_with("END-OF-LOG:\n"));
+
+        let mut no_mode = LogRecord::new();
+        no_mode
+            .insert_timestamp("2025-11-29T00:03:00Z".parse().unwrap())
+            .insert_field(FieldType::WorkedCall, "K1ABC")
+            .insert_field(FieldType::Frequency, "7.025");
+        log.insert_record(no_mode).unwrap();
+        assert!(log.cabrillo(&Filter::default()).is_err());
+    }
+}
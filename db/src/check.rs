@@ -0,0 +1,113 @@
+use anyhow::Result;
+use util::band::Band;
+
+use crate::{
+    data::{FieldType, Log, LogRecord},
+    filter::Filter,
+};
+
+/// A QSO an export would write that upload services or contest robots would reject
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportProblem {
+    pub idx: usize,
+    pub call: Option<String>,
+    /// What is wrong with it, e.g. `no mode`
+    pub reasons: Vec<String>,
+}
+
+/// Why a record is not fit for export, empty if it is
+pub fn export_problems(record: &LogRecord) -> Vec<String> {
+    let mut reasons = Vec::new();
+    if record.get_field(&FieldType::WorkedCall).is_none() {
+        reasons.push("no call".to_string());
+    }
+    if record.get_field(&FieldType::Timestamp).is_none() {
+        reasons.push("no date and time".to_string());
+    }
+    // imported logs may only carry the band
+    let band = record.get_field(&FieldType::from_adif_field("BAND"));
+    match record.frequency() {
+        Some(freq) if Band::from_freq(freq).is_none() => {
+            reasons.push(format!("frequency {} is in no band", freq))
+        }
+        None if band.is_none() => reasons.push("no band or frequency".to_string()),
+        _ => {}
+    }
+    if record.get_field(&FieldType::Mode).is_none() {
+        reasons.push("no mode".to_string());
+    }
+    for (ty, reason) in [
+        (FieldType::SentRST, "no sent report"),
+        (FieldType::RcvdRST, "no received report"),
+    ] {
+        if record.get_field(&ty).is_none() {
+            reasons.push(reason.to_string());
+        }
+    }
+    reasons
+}
+
+impl Log {
+    /// Checks the records `filter` selects before exporting them. Excluding the
+    /// problems' indices in the filter exports only the QSOs that pass.
+    pub fn check_export(&self, filter: &Filter) -> Result<Vec<ExportProblem>> {
+        let (records, _) = self.filter_indexed(filter)?;
+        let mut problems = Vec::new();
+        for (idx, record) in records {
+            let reasons = export_problems(&record);
+            if !reasons.is_empty() {
+                problems.push(ExportProblem {
+                    idx,
+                    call: record.get_field(&FieldType::WorkedCall),
+                    reasons,
+                });
+            }
+        }
+        Ok(problems)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        data::{FieldType, LogRecord},
+        filter::Filter,
+        test_log,
+    };
+
+    #[test]
+    pub fn test_check_export() {
+        let log = test_log();
+        let qso = |freq: &str, mode: Option<&str>| {
+            let mut record = LogRecord::new();
+            record
+                .insert_timestamp("2025-07-01T12:00:00Z".parse().unwrap())
+                .insert_field(FieldType::WorkedCall, "W1AW")
+                .insert_field(FieldType::Frequency, freq)
+                .insert_field(FieldType::SentRST, "-10")
+                .insert_field(FieldType::RcvdRST, "-12");
+            if let Some(mode) = mode {
+                record.insert_field(FieldType::Mode, mode);
+            }
+            record
+        };
+        log.insert_records(vec![qso("14.074", Some("FT8")), qso("14.5", None)])
+            .unwrap();
+
+        let problems = log.check_export(&Filter::default()).unwrap();
+        assert_eq!(1, problems.len());
+        assert_eq!(1, problems[0].idx);
+        assert_eq!(Some("W1AW".to_string()), problems[0].call);
+        assert_eq!(
+            vec!["frequency 14.500 is in no band", "no mode"],
+            problems[0].reasons
+        );
+
+        let without = Filter {
+            exclude: vec![1],
+            ..Default::default()
+        };
+        assert!(log.check_export(&without).unwrap().is_empty());
+        assert_eq!(1, log.export_adif(&without).unwrap().body.len());
+    }
+}
@@ -0,0 +1,238 @@
+use anyhow::Result;
+
+use crate::data::{FieldType, Log, LogRecord, RecordId};
+
+/// Club Log uploads keyed by record id: the record as Club Log last received it, or None
+/// for a record queued before its first upload. Kept beside the records, so edits and
+/// deletions since the upload can be told apart.
+const CLUBLOG_TREE: &[u8] = b"CLUBLOG";
+
+/// ADIF fields Club Log reads from an upload, besides QSO_DATE and TIME_ON. Edits of
+/// other fields, e.g. the eQSL sent status, are not uploaded again.
+const CLUBLOG_FIELDS: &[&str] = &[
+    "CALL",
+    "OPERATOR",
+    "STATION_CALLSIGN",
+    "MODE",
+    "BAND",
+    "BAND_RX",
+    "FREQ",
+    "FREQ_RX",
+    "RST_SENT",
+    "RST_RCVD",
+    "QSL_SENT",
+    "QSL_RCVD",
+    "QSLSDATE",
+    "QSLRDATE",
+    "LOTW_QSL_RCVD",
+    "DXCC",
+    "CREDIT_GRANTED",
+    "PROP_MODE",
+    "SAT_NAME",
+    "GRIDSQUARE",
+    "MY_GRIDSQUARE",
+    "NOTES",
+];
+
+/// The part of a record Club Log receives, its time and `CLUBLOG_FIELDS`
+pub fn clublog_record(record: &LogRecord) -> LogRecord {
+    let mut sent = LogRecord::new();
+    for (ty, val) in record.iter() {
+        let keep = match ty {
+            FieldType::Timestamp => true,
+            ty => ty.adif_name().is_some_and(|n| CLUBLOG_FIELDS.contains(&n)),
+        };
+        if keep {
+            sent.insert_field(ty.clone(), &val);
+        }
+    }
+    sent
+}
+
+/// Where a queued record stands with Club Log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClublogStatus {
+    /// Waiting for its first upload
+    Queued,
+    Uploaded,
+    /// Changed since it was uploaded
+    Modified,
+    /// Deleted from the log since it was uploaded
+    Deleted,
+}
+
+/// What Club Log needs to catch up with a record, holding only what Club Log receives
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClublogChange {
+    /// Upload `record`, replacing the QSO uploaded as `replaces`
+    Upload {
+        record: LogRecord,
+        replaces: Option<LogRecord>,
+    },
+    /// Delete the QSO uploaded as this record
+    Delete(LogRecord),
+}
+
+impl Log {
+    /// Queues a record for its first Club Log upload. Later edits and its deletion are
+    /// uploaded too.
+    pub fn queue_clublog(&self, id: RecordId) -> Result<()> {
+        self.check_writable()?;
+        let tree = self.db.open_tree(CLUBLOG_TREE)?;
+        if !tree.contains_key(id.to_bytes())? {
+            tree.insert(id.to_bytes(), self.encode_log_record(None::<LogRecord>)?)?;
+        }
+        Ok(())
+    }
+
+    fn clublog_entry(&self, id: RecordId) -> Result<Option<Option<LogRecord>>> {
+        match self.db.open_tree(CLUBLOG_TREE)?.get(id.to_bytes())? {
+            Some(enc) => Ok(Some(self.decode_log_record(&enc)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Where a record stands with Club Log, None for records never queued
+    pub fn clublog_status(&self, id: RecordId) -> Result<Option<ClublogStatus>> {
+        let Some(uploaded) = self.clublog_entry(id)? else {
+            return Ok(None);
+        };
+        Ok(match (self.get_record_by_id(id), uploaded) {
+            (Some(_), None) => Some(ClublogStatus::Queued),
+            (Some(record), Some(uploaded))
+                if clublog_record(&record) == clublog_record(&uploaded) =>
+            {
+                Some(ClublogStatus::Uploaded)
+            }
+            (Some(_), Some(_)) => Some(ClublogStatus::Modified),
+            (None, Some(_)) => Some(ClublogStatus::Deleted),
+            // deleted before it was uploaded, Club Log never knew it
+            (None, None) => None,
+        })
+    }
+
+    /// The change Club Log is missing for a record, None if it is up to date
+    pub fn clublog_change(&self, id: RecordId) -> Result<Option<ClublogChange>> {
+        let Some(uploaded) = self.clublog_entry(id)? else {
+            return Ok(None);
+        };
+        let uploaded = uploaded.as_ref().map(clublog_record);
+        Ok(match (self.get_record_by_id(id), uploaded) {
+            (Some(record), uploaded) if Some(clublog_record(&record)) != uploaded => {
+                Some(ClublogChange::Upload {
+                    record: clublog_record(&record),
+                    replaces: uploaded,
+                })
+            }
+            (None, Some(uploaded)) => Some(ClublogChange::Delete(uploaded)),
+            _ => None,
+        })
+    }
+
+    /// Records Club Log is missing a change of, oldest first. This reads every record
+    /// queued, use `clublog_change` to look at the one just changed.
+    pub fn clublog_pending(&self) -> Result<Vec<RecordId>> {
+        let tree = self.db.open_tree(CLUBLOG_TREE)?;
+        let mut pending = Vec::new();
+        for key in tree.iter().keys() {
+            let id = RecordId::from_bytes(&key?)?;
+            match self.clublog_status(id)? {
+                None => {
+                    tree.remove(id.to_bytes())?;
+                }
+                Some(ClublogStatus::Uploaded) => {}
+                Some(_) => pending.push(id),
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Notes that Club Log accepted `change` of a record
+    pub fn clublog_sent(&self, id: RecordId, change: &ClublogChange) -> Result<()> {
+        self.check_writable()?;
+        let tree = self.db.open_tree(CLUBLOG_TREE)?;
+        match change {
+            ClublogChange::Upload { record, .. } => {
+                tree.insert(id.to_bytes(), self.encode_log_record(Some(record))?)?;
+            }
+            ClublogChange::Delete(_) => {
+                tree.remove(id.to_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClublogChange, ClublogStatus, clublog_record};
+    use crate::{
+        data::{FieldType, LogRecord},
+        test_log,
+    };
+
+    #[test]
+    pub fn test_clublog_queue() {
+        let log = test_log();
+        let mut ids = Vec::new();
+        for call in ["W1AW", "DL1ABC", "JA1XYZ"] {
+            let mut record = LogRecord::new();
+            record
+                .insert_timestamp("2025-07-28T02:48:00Z".parse().unwrap())
+                .insert_field(FieldType::WorkedCall, call);
+            let idx = log.insert_record(record).unwrap();
+            ids.push(log.record_id(idx).unwrap());
+        }
+        // W1AW was logged before uploads were turned on
+        for id in &ids[1..] {
+            log.queue_clublog(*id).unwrap();
+        }
+        assert_eq!(None, log.clublog_status(ids[0]).unwrap());
+        assert_eq!(ids[1..], log.clublog_pending().unwrap());
+
+        let change = log.clublog_change(ids[1]).unwrap().unwrap();
+        let ClublogChange::Upload { record, replaces } = &change else {
+            panic!("{:?} is not an upload", change);
+        };
+        assert_eq!(None, *replaces);
+        log.clublog_sent(ids[1], &change).unwrap();
+        assert_eq!(
+            Some(ClublogStatus::Uploaded),
+            log.clublog_status(ids[1]).unwrap()
+        );
+        assert_eq!(None, log.clublog_change(ids[1]).unwrap());
+        // queueing again does not forget the upload
+        log.queue_clublog(ids[1]).unwrap();
+        assert_eq!(vec![ids[2]], log.clublog_pending().unwrap());
+
+        // Club Log is not sent the name
+        log.set_field(1, FieldType::Name, "Jörg").unwrap();
+        assert_eq!(None, log.clublog_change(ids[1]).unwrap());
+        log.set_field(1, FieldType::RcvdRST, "579").unwrap();
+        assert_eq!(
+            Some(ClublogStatus::Modified),
+            log.clublog_status(ids[1]).unwrap()
+        );
+        assert_eq!(
+            Some(ClublogChange::Upload {
+                record: clublog_record(&log.get_record(1).unwrap()),
+                replaces: Some(record.clone()),
+            }),
+            log.clublog_change(ids[1]).unwrap()
+        );
+
+        log.delete_record(1).unwrap();
+        assert_eq!(
+            Some(ClublogStatus::Deleted),
+            log.clublog_status(ids[1]).unwrap()
+        );
+        let change = log.clublog_change(ids[1]).unwrap().unwrap();
+        assert_eq!(ClublogChange::Delete(record.clone()), change);
+        log.clublog_sent(ids[1], &change).unwrap();
+        assert_eq!(None, log.clublog_status(ids[1]).unwrap());
+
+        // deleted before its upload, there is nothing to tell Club Log
+        log.delete_record(2).unwrap();
+        assert!(log.clublog_pending().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,604 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+
+use jiff::{SignedDuration, Timestamp};
+use serde::{Deserialize, Serialize};
+use util::{band::Band, dxcc::PrefixDb, freq::Frequency, mode::ModeClass};
+
+use crate::data::{FieldType, Log, LogRecord};
+
+/// Window of the short term rate, in minutes, besides the hourly one
+pub const SHORT_RATE_MINUTES: i64 = 10;
+/// Last QSOs the run frequency is judged from
+const RUN_QSOS: usize = 10;
+/// How many of them must be on one frequency for it to be a run, S&P QSOs are spread out
+const RUN_MIN_QSOS: usize = 3;
+/// QSOs this close count as made on the same frequency, as callers are rarely zero beat
+const RUN_TOLERANCE_HZ: u64 = 500;
+
+/// ARRL sections of entities that are a section of their own, by primary prefix
+const ENTITY_SECTIONS: &[(&str, &str)] =
+    &[("KL", "AK"), ("KH6", "PAC"), ("KP4", "PR"), ("KP2", "VI")];
+/// ARRL sections of Canadian call areas a single section covers. Ontario is split into
+/// several and the Maritimes share prefixes, so those are left to the operator.
+const CANADIAN_SECTIONS: &[(&str, &str)] = &[
+    ("VE2", "QC"),
+    ("VA2", "QC"),
+    ("VE4", "MB"),
+    ("VA4", "MB"),
+    ("VE5", "SK"),
+    ("VA5", "SK"),
+    ("VE6", "AB"),
+    ("VA6", "AB"),
+    ("VE7", "BC"),
+    ("VA7", "BC"),
+    ("VO1", "NL"),
+    ("VO2", "NL"),
+    ("VE8", "TER"),
+    ("VY0", "TER"),
+    ("VY1", "TER"),
+];
+
+/// Contests with built in exchange and scoring rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Contest {
+    /// CQ World Wide DX: RST and CQ zone, zones and countries per band
+    CqWw,
+    /// ARRL Sweepstakes: serial, precedence, check and section, sections once
+    Sweepstakes,
+    /// ARRL Field Day: class and section, no multipliers
+    FieldDay,
+}
+
+impl Contest {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Contest::CqWw => "CQ WW DX",
+            Contest::Sweepstakes => "ARRL Sweepstakes",
+            Contest::FieldDay => "ARRL Field Day",
+        }
+    }
+
+    /// The ADIF CONTEST_ID of QSOs made in the contest in a mode
+    pub fn adif_id(&self, mode: ModeClass) -> &'static str {
+        match (self, mode) {
+            (Contest::CqWw, ModeClass::Cw) => "CQ-WW-CW",
+            (Contest::CqWw, ModeClass::Phone) => "CQ-WW-SSB",
+            (Contest::CqWw, ModeClass::Digital) => "CQ-WW-RTTY",
+            (Contest::Sweepstakes, ModeClass::Cw) => "ARRL-SS-CW",
+            (Contest::Sweepstakes, _) => "ARRL-SS-SSB",
+            (Contest::FieldDay, _) => "ARRL-FD",
+        }
+    }
+
+    /// The received exchange, in the order it is sent
+    pub fn exchange(&self) -> Vec<FieldType> {
+        match self {
+            Contest::CqWw => vec![FieldType::RcvdRST, FieldType::CQZ],
+            Contest::Sweepstakes => vec![
+                FieldType::RcvdSerial,
+                FieldType::Other("PRECEDENCE".into()),
+                FieldType::Other("CHECK".into()),
+                FieldType::Other("ARRL_SECT".into()),
+            ],
+            Contest::FieldDay => vec![
+                FieldType::Other("CLASS".into()),
+                FieldType::Other("ARRL_SECT".into()),
+            ],
+        }
+    }
+
+    /// The exchange `call` likely sends, guessed from its prefix: the CQ zone in CQ WW and
+    /// the ARRL section where the prefix tells it, `DX` for stations abroad on Field Day.
+    /// Pre-fills the entry, as the operator corrects a wrong guess faster than typing it.
+    pub fn guess_exchange(&self, call: &str, prefixes: &PrefixDb) -> Vec<(FieldType, String)> {
+        let Some(m) = prefixes.lookup(call) else {
+            return Vec::new();
+        };
+        let section = FieldType::Other("ARRL_SECT".into());
+        let call = call.trim().to_ascii_uppercase();
+        let area_section = || {
+            // portable calls may be anywhere in the entity
+            CANADIAN_SECTIONS
+                .iter()
+                .find(|(prefix, _)| !call.contains('/') && call.starts_with(prefix))
+        };
+        let known = ENTITY_SECTIONS
+            .iter()
+            .find(|(entity, _)| *entity == m.entity.prefix)
+            .or_else(|| match m.entity.prefix.as_str() {
+                "VE" => area_section(),
+                _ => None,
+            })
+            .map(|(_, section)| section.to_string());
+        match self {
+            Contest::CqWw => vec![(FieldType::CQZ, m.cq_zone.to_string())],
+            Contest::Sweepstakes => known.map(|s| (section, s)).into_iter().collect(),
+            Contest::FieldDay => match (known, m.entity.prefix.as_str()) {
+                (Some(s), _) => vec![(section, s)],
+                (None, "K" | "VE") => Vec::new(),
+                (None, _) => vec![(section, "DX".to_string())],
+            },
+        }
+    }
+
+    /// Bands QSOs count on, others are logged but score nothing
+    pub fn allows_band(&self, band: Band) -> bool {
+        match self {
+            Contest::CqWw | Contest::Sweepstakes => matches!(
+                band,
+                Band::M160 | Band::M80 | Band::M40 | Band::M20 | Band::M15 | Band::M10
+            ),
+            Contest::FieldDay => !matches!(band, Band::M60 | Band::M30 | Band::M17 | Band::M12),
+        }
+    }
+
+    /// Length of the contest period, which ends this long after `Settings::contest_start`
+    /// unless `contest_end` is set
+    pub fn duration(&self) -> SignedDuration {
+        match self {
+            Contest::CqWw => SignedDuration::from_hours(48),
+            Contest::Sweepstakes => SignedDuration::from_hours(30),
+            Contest::FieldDay => SignedDuration::from_hours(27),
+        }
+    }
+
+    /// What makes a QSO a dupe: the same call on the same band for CQ WW,
+    /// anywhere in Sweepstakes, and on the same band and mode on Field Day.
+    /// Rovers add the grid they worked the station from.
+    fn dupe_key(
+        &self,
+        call: &str,
+        band: Option<Band>,
+        mode: ModeClass,
+        my_grid: Option<&str>,
+    ) -> DupeKey {
+        let call = call.to_ascii_uppercase();
+        // stations count again from each 4 character grid
+        let grid = my_grid.map(|g| g.get(..4).unwrap_or(g).to_ascii_uppercase());
+        match self {
+            Contest::CqWw => (call, band, None, grid),
+            Contest::Sweepstakes => (call, None, None, grid),
+            Contest::FieldDay => (call, band, Some(mode), grid),
+        }
+    }
+}
+
+type DupeKey = (String, Option<Band>, Option<ModeClass>, Option<String>);
+
+/// What a single QSO contributed to the score
+#[derive(Debug, Default, PartialEq)]
+pub struct QsoScore {
+    pub dupe: bool,
+    pub points: u64,
+    pub new_mults: Vec<String>,
+}
+
+/// Running score of a contest, updated as QSOs are logged
+#[derive(Debug)]
+pub struct ContestScore {
+    pub contest: Contest,
+    /// Primary prefix and continent of our own entity, needed for CQ WW points
+    my_entity: Option<(String, String)>,
+    worked: HashSet<DupeKey>,
+    mults: HashSet<String>,
+    /// Our grid when operating as a rover, None otherwise. Rovers may work a station again
+    /// from every grid, so only QSOs with the same MY_GRIDSQUARE are dupes.
+    pub rover_grid: Option<String>,
+    /// Start and end of the contest period, QSOs made outside it are not scored.
+    /// None scores every QSO.
+    pub period: Option<(Timestamp, Timestamp)>,
+    /// Times of the QSOs scored in ascending order, for the rate
+    times: Vec<Timestamp>,
+    /// Frequencies of the last `RUN_QSOS` QSOs, oldest first
+    recent_freqs: VecDeque<Frequency>,
+    /// Calls worked on each band, dupes left out
+    sheet: BTreeMap<Option<Band>, BTreeSet<String>>,
+    pub qsos: usize,
+    pub dupes: usize,
+    pub points: u64,
+}
+
+impl ContestScore {
+    pub fn new(contest: Contest, my_call: &str, prefixes: Option<&PrefixDb>) -> Self {
+        let my_entity = prefixes
+            .and_then(|p| p.lookup(my_call))
+            .map(|m| (m.entity.prefix.clone(), m.continent.to_string()));
+        Self {
+            contest,
+            my_entity,
+            worked: HashSet::new(),
+            mults: HashSet::new(),
+            rover_grid: None,
+            period: None,
+            times: Vec::new(),
+            recent_freqs: VecDeque::new(),
+            sheet: BTreeMap::new(),
+            qsos: 0,
+            dupes: 0,
+            points: 0,
+        }
+    }
+
+    /// Whether working `call` now would be a dupe
+    pub fn is_dupe(&self, call: &str, band: Option<Band>, mode: ModeClass) -> bool {
+        let grid = self.rover_grid.as_deref();
+        self.worked
+            .contains(&self.contest.dupe_key(call, band, mode, grid))
+    }
+
+    /// Scores a logged QSO
+    pub fn add(&mut self, record: &LogRecord, prefixes: Option<&PrefixDb>) -> QsoScore {
+        let mut score = QsoScore::default();
+        let Some(call) = record.get_field(&FieldType::WorkedCall) else {
+            return score;
+        };
+        let time = record
+            .get_field(&FieldType::Timestamp)
+            .and_then(|t| t.parse::<Timestamp>().ok());
+        if let Some((start, end)) = self.period
+            && !time.is_some_and(|t| t >= start && t < end)
+        {
+            return score;
+        }
+        let freq = record.frequency();
+        let band = freq.and_then(Band::from_freq);
+        let mode = record
+            .get_field(&FieldType::Mode)
+            .map(|m| ModeClass::from_mode(&m))
+            .unwrap_or(ModeClass::Phone);
+        let my_grid = match self.rover_grid {
+            Some(_) => record.get_field(&FieldType::from_adif_field("MY_GRIDSQUARE")),
+            None => None,
+        };
+        self.qsos += 1;
+        // dupes are QSOs too as far as the rate is concerned
+        if let Some(time) = time {
+            let pos = self.times.partition_point(|t| *t <= time);
+            self.times.insert(pos, time);
+        }
+        if let Some(freq) = freq {
+            if self.recent_freqs.len() == RUN_QSOS {
+                self.recent_freqs.pop_front();
+            }
+            self.recent_freqs.push_back(freq);
+        }
+        if !self
+            .worked
+            .insert(self.contest.dupe_key(&call, band, mode, my_grid.as_deref()))
+        {
+            self.dupes += 1;
+            score.dupe = true;
+            return score;
+        }
+        self.sheet
+            .entry(band)
+            .or_default()
+            .insert(call.to_ascii_uppercase());
+        let Some(band) = band.filter(|b| self.contest.allows_band(*b)) else {
+            return score;
+        };
+        let mut mults = Vec::new();
+        match self.contest {
+            Contest::CqWw => {
+                let Some(m) = prefixes.and_then(|p| p.lookup(&call)) else {
+                    return score;
+                };
+                let zone = record
+                    .get_field(&FieldType::CQZ)
+                    .and_then(|z| z.trim().parse::<u8>().ok())
+                    .unwrap_or(m.cq_zone);
+                score.points = match &self.my_entity {
+                    Some((prefix, _)) if *prefix == m.entity.prefix => 0,
+                    Some((_, continent)) if continent == m.continent => match continent.as_str() {
+                        "NA" => 2,
+                        _ => 1,
+                    },
+                    Some(_) => 3,
+                    None => 0,
+                };
+                mults.push(format!("Zone {} {}", zone, band));
+                mults.push(format!("{} {}", m.entity.prefix, band));
+            }
+            Contest::Sweepstakes => {
+                score.points = 2;
+                if let Some(section) = record.get_field(&FieldType::Other("ARRL_SECT".into())) {
+                    mults.push(section.trim().to_ascii_uppercase());
+                }
+            }
+            Contest::FieldDay => {
+                score.points = match mode {
+                    ModeClass::Phone => 1,
+                    ModeClass::Cw | ModeClass::Digital => 2,
+                };
+            }
+        }
+        self.points += score.points;
+        for mult in mults {
+            if self.mults.insert(mult.clone()) {
+                score.new_mults.push(mult);
+            }
+        }
+        score
+    }
+
+    pub fn mults(&self) -> usize {
+        self.mults.len()
+    }
+
+    /// QSOs per hour over the `minutes` before `now`
+    pub fn rate(&self, now: Timestamp, minutes: i64) -> u64 {
+        let since = now - SignedDuration::from_mins(minutes);
+        let qsos =
+            self.times.partition_point(|t| *t <= now) - self.times.partition_point(|t| *t <= since);
+        qsos as u64 * 60 / minutes.max(1) as u64
+    }
+
+    /// The frequency we are running on: the one most of the last QSOs were made on,
+    /// None while searching and pouncing
+    pub fn run_frequency(&self) -> Option<Frequency> {
+        let near = |f: &Frequency| {
+            self.recent_freqs
+                .iter()
+                .filter(|o| o.hz().abs_diff(f.hz()) <= RUN_TOLERANCE_HZ)
+                .count()
+        };
+        // the latest of equally used frequencies, after moving the run
+        self.recent_freqs
+            .iter()
+            .max_by_key(|f| near(f))
+            .filter(|f| near(f) >= RUN_MIN_QSOS)
+            .copied()
+    }
+
+    /// Calls worked on each band, sorted, for the dupe sheet. QSOs without a known band
+    /// are under None.
+    pub fn dupe_sheet(&self) -> &BTreeMap<Option<Band>, BTreeSet<String>> {
+        &self.sheet
+    }
+
+    /// Claimed score: QSO points times multipliers, or just the points on Field Day
+    pub fn score(&self) -> u64 {
+        match self.contest {
+            Contest::FieldDay => self.points,
+            Contest::CqWw | Contest::Sweepstakes => self.points * self.mults() as u64,
+        }
+    }
+}
+
+impl Log {
+    /// Scores the QSOs logged in the contest `period`, see `Settings::contest_period`.
+    /// `rover_grid` is our current grid when roving, see `ContestScore::rover_grid`.
+    pub fn contest_score(
+        &self,
+        contest: Contest,
+        my_call: &str,
+        period: (Timestamp, Timestamp),
+        prefixes: Option<&PrefixDb>,
+        rover_grid: Option<&str>,
+    ) -> ContestScore {
+        let mut score = ContestScore::new(contest, my_call, prefixes);
+        score.rover_grid = rover_grid.map(str::to_string);
+        score.period = Some(period);
+        for record in self.iter_records() {
+            score.add(&record, prefixes);
+        }
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::{SignedDuration, Timestamp};
+    use util::{band::Band, dxcc::PrefixDb, mode::ModeClass};
+
+    use super::{Contest, ContestScore};
+    use crate::data::{FieldType, Log, LogHeader, LogRecord};
+
+    fn qso(call: &str, freq: &str, mode: &str, exchange: &[(FieldType, &str)]) -> LogRecord {
+        let mut record = LogRecord::new();
+        record
+            .insert_field(FieldType::WorkedCall, call)
+            .insert_field(FieldType::Frequency, freq)
+            .insert_field(FieldType::Mode, mode);
+        for (ty, val) in exchange {
+            record.insert_field(ty.clone(), val);
+        }
+        record
+    }
+
+    #[test]
+    pub fn test_cqww_score() {
+        let prefixes = PrefixDb::parse(
+            "Japan: 25: 45: AS: 36.40: -138.38: -9.0: JA:\n JA;\
+             Germany: 14: 28: EU: 51.00: -10.00: -1.0: DL:\n DL;\
+             Austria: 15: 28: EU: 47.33: -13.33: -1.0: OE:\n OE;",
+        )
+        .unwrap();
+        let mut score = ContestScore::new(Contest::CqWw, "DL1ABC", Some(&prefixes));
+        let zone = |z| [(FieldType::CQZ, z)];
+
+        let first = score.add(&qso("JA1XYZ", "14.025", "CW", &zone("25")), Some(&prefixes));
+        assert_eq!(3, first.points);
+        assert_eq!(vec!["Zone 25 20m", "JA 20m"], first.new_mults);
+        // same continent, other country
+        assert_eq!(
+            1,
+            score
+                .add(&qso("OE2ABC", "14.030", "CW", &zone("15")), Some(&prefixes))
+                .points
+        );
+        // own country counts as a multiplier only
+        let own = score.add(&qso("DL2ABC", "14.035", "CW", &zone("14")), Some(&prefixes));
+        assert_eq!(0, own.points);
+        assert_eq!(2, own.new_mults.len());
+        assert!(
+            score
+                .add(&qso("ja1xyz", "14.040", "CW", &zone("25")), Some(&prefixes))
+                .dupe
+        );
+        assert!(!score.is_dupe("JA1XYZ", Some(Band::M40), ModeClass::Cw));
+        // WARC bands do not count
+        let warc = score.add(&qso("JA2XYZ", "10.110", "CW", &zone("25")), Some(&prefixes));
+        assert_eq!((0, 0), (warc.points, warc.new_mults.len()));
+
+        assert_eq!((5, 1), (score.qsos, score.dupes));
+        assert_eq!(4 * 6, score.score());
+    }
+
+    #[test]
+    pub fn test_sweepstakes_and_field_day() {
+        let sect = FieldType::Other("ARRL_SECT".into());
+        let mut ss = ContestScore::new(Contest::Sweepstakes, "W1AW", None);
+        ss.add(&qso("K1ABC", "14.030", "CW", &[(sect.clone(), "ct")]), None);
+        ss.add(&qso("K2ABC", "7.030", "CW", &[(sect.clone(), "CT")]), None);
+        // one contact per station, whatever the band
+        assert!(ss.add(&qso("K1ABC", "7.035", "CW", &[]), None).dupe);
+        assert_eq!((4, 1, 4), (ss.points, ss.mults(), ss.score()));
+
+        let mut fd = ContestScore::new(Contest::FieldDay, "W1AW", None);
+        fd.add(
+            &qso("K1ABC", "14.250", "SSB", &[(sect.clone(), "CT")]),
+            None,
+        );
+        fd.add(&qso("K1ABC", "14.030", "CW", &[(sect.clone(), "CT")]), None);
+        assert!(fd.is_dupe("K1ABC", Some(Band::M20), ModeClass::Cw));
+        assert!(!fd.is_dupe("K1ABC", Some(Band::M20), ModeClass::Digital));
+        assert_eq!((0, 3), (fd.mults(), fd.score()));
+    }
+
+    #[test]
+    pub fn test_rate_and_run() {
+        let mut score = ContestScore::new(Contest::CqWw, "DL1ABC", None);
+        let start: Timestamp = "2025-11-29T00:00:00Z".parse().unwrap();
+        let log = |score: &mut ContestScore, call: &str, freq: &str, min: i64| {
+            let mut record = qso(call, freq, "CW", &[]);
+            record.insert_timestamp(start + SignedDuration::from_mins(min));
+            score.add(&record, None);
+        };
+        log(&mut score, "JA1XYZ", "14.0255", 5);
+        log(&mut score, "OE2ABC", "14.031", 50);
+        assert_eq!(None, score.run_frequency());
+        log(&mut score, "K1ABC", "14.0251", 52);
+        log(&mut score, "K2ABC", "14.025", 55);
+        log(&mut score, "K2ABC", "7.025", 58);
+        log(&mut score, "K2ABC", "7.025", 59);
+        assert_eq!(
+            Some("14.025".to_string()),
+            score.run_frequency().map(|f| f.to_string())
+        );
+
+        let now = start + SignedDuration::from_mins(60);
+        // 4 QSOs in the last 10 minutes, one a dupe
+        assert_eq!(24, score.rate(now, 10));
+        assert_eq!(6, score.rate(now, 60));
+
+        let sheet = score.dupe_sheet();
+        assert_eq!(
+            vec!["JA1XYZ", "K1ABC", "K2ABC", "OE2ABC"],
+            sheet[&Some(Band::M20)].iter().collect::<Vec<_>>()
+        );
+        assert_eq!(1, sheet[&Some(Band::M40)].len());
+    }
+
+    #[test]
+    pub fn test_contest_period() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let log = Log::new_init(db, LogHeader::new("W1AW", "")).unwrap();
+        for (call, time) in [
+            ("K1ABC", "2025-06-28T17:59:00Z"),
+            ("K2ABC", "2025-06-28T18:00:00Z"),
+            ("K3ABC", "2025-06-29T20:59:00Z"),
+            ("K4ABC", "2025-06-29T21:00:00Z"),
+        ] {
+            let mut record = qso(call, "14.030", "CW", &[]);
+            record.insert_timestamp(time.parse().unwrap());
+            log.insert_record(record).unwrap();
+        }
+        let start = "2025-06-28T18:00:00Z".parse().unwrap();
+        let end = start + Contest::FieldDay.duration();
+        let score = log.contest_score(Contest::FieldDay, "W1AW", (start, end), None, None);
+        // the score stays put however long after the contest it is counted
+        assert_eq!(2, score.qsos);
+        assert!(score.is_dupe("K3ABC", Some(Band::M20), ModeClass::Cw));
+        assert!(!score.is_dupe("K1ABC", Some(Band::M20), ModeClass::Cw));
+    }
+
+    #[test]
+    pub fn test_rover_dupes() {
+        let my_grid = FieldType::from_adif_field("MY_GRIDSQUARE");
+        let mut fd = ContestScore::new(Contest::FieldDay, "W1AW", None);
+        fd.rover_grid = Some("FN31".to_string());
+        fd.add(
+            &qso("K1ABC", "50.125", "SSB", &[(my_grid.clone(), "FN31pr")]),
+            None,
+        );
+        assert!(fd.is_dupe("K1ABC", Some(Band::M6), ModeClass::Phone));
+        // after crossing into the next grid, everyone counts again
+        fd.rover_grid = Some("FN32aa".to_string());
+        assert!(!fd.is_dupe("K1ABC", Some(Band::M6), ModeClass::Phone));
+        let again = fd.add(&qso("K1ABC", "50.125", "SSB", &[(my_grid, "FN32")]), None);
+        assert!(!again.dupe);
+        assert!(fd.is_dupe("K1ABC", Some(Band::M6), ModeClass::Phone));
+    }
+
+    #[test]
+    pub fn test_guess_exchange() {
+        let prefixes = PrefixDb::parse(
+            "United States: 05: 08: NA: 37.53: 91.67: 5.0: K:\n K,W,=W1AW(5)[8];\
+             Alaska: 01: 01: NA: 61.40: 148.87: 8.0: KL:\n KL;\
+             Canada: 05: 09: NA: 44.35: 78.75: 5.0: VE:\n VA,VE,VO,VY;\
+             Japan: 25: 45: AS: 36.40: -138.38: -9.0: JA:\n JA;",
+        )
+        .unwrap();
+        let sect = FieldType::Other("ARRL_SECT".into());
+        assert_eq!(
+            vec![(FieldType::CQZ, "25".to_string())],
+            Contest::CqWw.guess_exchange("JA1XYZ", &prefixes)
+        );
+        assert_eq!(
+            vec![(FieldType::CQZ, "5".to_string())],
+            Contest::CqWw.guess_exchange("w1aw", &prefixes)
+        );
+        assert!(Contest::CqWw.guess_exchange("XX9XX", &prefixes).is_empty());
+
+        let ss = |call| Contest::Sweepstakes.guess_exchange(call, &prefixes);
+        assert_eq!(vec![(sect.clone(), "BC".to_string())], ss("VA7ABC"));
+        assert_eq!(vec![(sect.clone(), "AK".to_string())], ss("KL7ABC"));
+        // Ontario has several sections, and the call area says nothing in the US
+        assert!(ss("VE3ABC").is_empty());
+        assert!(ss("VE7/K1ABC").is_empty());
+        assert!(ss("K1ABC").is_empty());
+
+        let fd = |call| Contest::FieldDay.guess_exchange(call, &prefixes);
+        assert_eq!(vec![(sect.clone(), "DX".to_string())], fd("JA1XYZ"));
+        assert_eq!(vec![(sect, "QC".to_string())], fd("VE2ABC"));
+        assert!(fd("W1AW").is_empty());
+    }
+
+    #[test]
+    pub fn test_contest_export() {
+        assert_eq!("CQ-WW-SSB", Contest::CqWw.adif_id(ModeClass::Phone));
+        assert_eq!("ARRL-SS-CW", Contest::Sweepstakes.adif_id(ModeClass::Cw));
+        assert_eq!("ARRL-FD", Contest::FieldDay.adif_id(ModeClass::Digital));
+
+        let record = qso(
+            "K1ABC",
+            "7.035",
+            "CW",
+            &[
+                (FieldType::ContestId, "NAQP-CW"),
+                (FieldType::SentExchange, "ANN CT"),
+                (FieldType::RcvdExchange, "BOB MA"),
+            ],
+        );
+        let adif = record.to_adif().unwrap().serialize().unwrap();
+        for field in [
+            "<CONTEST_ID:7>NAQP-CW",
+            "<STX_STRING:6>ANN CT",
+            "<SRX_STRING:6>BOB MA",
+        ] {
+            assert!(adif.contains(field), "{} not in {}", field, adif);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use util::dxcc::PrefixDb;
+
+use crate::data::{FieldType, LogRecord};
+
+/// Country and continent of a worked station
+#[derive(Debug, Clone, PartialEq)]
+pub struct Country {
+    /// Entity name, e.g. `Fed. Rep. of Germany`
+    pub name: String,
+    /// Two letter continent, e.g. `EU`
+    pub continent: String,
+}
+
+/// Countries of worked calls, each call looked up in the prefix database once. The log
+/// list shows a country for every QSO on every redraw, which is too slow uncached.
+#[derive(Debug, Default)]
+pub struct CountryCache {
+    calls: RefCell<HashMap<String, Option<Country>>>,
+}
+
+impl CountryCache {
+    /// The country of the station worked in `record`. COUNTRY and CONT fields logged with
+    /// the QSO take precedence over the prefix database, e.g. for stations that moved.
+    pub fn country(&self, record: &LogRecord, prefixes: Option<&PrefixDb>) -> Option<Country> {
+        let field = |name: &str| {
+            record
+                .get_field(&FieldType::from_adif_field(name))
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let (name, continent) = (field("COUNTRY"), field("CONT"));
+        let looked_up = match (&name, &continent) {
+            (Some(_), Some(_)) => None,
+            _ => record
+                .get_field(&FieldType::WorkedCall)
+                .and_then(|call| self.lookup(&call, prefixes?)),
+        };
+        let looked_up = looked_up.as_ref();
+        Some(Country {
+            name: name.or_else(|| looked_up.map(|c| c.name.clone()))?,
+            continent: continent
+                .map(|c| c.to_ascii_uppercase())
+                .or_else(|| looked_up.map(|c| c.continent.clone()))?,
+        })
+    }
+
+    fn lookup(&self, call: &str, prefixes: &PrefixDb) -> Option<Country> {
+        let call = call.trim().to_ascii_uppercase();
+        self.calls
+            .borrow_mut()
+            .entry(call)
+            .or_insert_with_key(|call| {
+                prefixes.lookup(call).map(|m| Country {
+                    name: m.entity.name.clone(),
+                    continent: m.continent.to_string(),
+                })
+            })
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use util::dxcc::PrefixDb;
+
+    use super::{Country, CountryCache};
+    use crate::data::{FieldType, LogRecord};
+
+    #[test]
+    pub fn test_country_cache() {
+        let prefixes = PrefixDb::parse(
+            "Japan: 25: 45: AS: 36.40: -138.38: -9.0: JA:\n JA;\
+             Germany: 14: 28: EU: 51.00: -10.00: -1.0: DL:\n DL;",
+        )
+        .unwrap();
+        let cache = CountryCache::default();
+        let mut record = LogRecord::new();
+        record.insert_field(FieldType::WorkedCall, "dl1abc");
+        assert_eq!(
+            Some(Country {
+                name: "Germany".to_string(),
+                continent: "EU".to_string()
+            }),
+            cache.country(&record, Some(&prefixes))
+        );
+        assert_eq!(1, cache.calls.borrow().len());
+        // without the prefix database only logged fields are known
+        assert_eq!(None, cache.country(&record, None));
+        record
+            .insert_field(FieldType::from_adif_field("COUNTRY"), "Heligoland")
+            .insert_field(FieldType::from_adif_field("CONT"), "eu");
+        assert_eq!(
+            "Heligoland",
+            cache.country(&record, None).unwrap().name.as_str()
+        );
+
+        let mut unknown = LogRecord::new();
+        unknown.insert_field(FieldType::WorkedCall, "XX9XX");
+        assert_eq!(None, cache.country(&unknown, Some(&prefixes)));
+        assert_eq!(2, cache.calls.borrow().len());
+    }
+}
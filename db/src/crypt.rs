@@ -0,0 +1,246 @@
+use std::num::NonZeroU32;
+
+use anyhow::{Result, anyhow, bail};
+use bincode::{Decode, Encode};
+use ring::{
+    aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey},
+    hkdf, pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+
+use crate::data::Log;
+
+/// Key of the `Encryption` settings of an encrypted log
+pub(crate) const ENCRYPTION_KEY: &[u8] = b"ENCRYPTION";
+/// PBKDF2 rounds keys are derived with, as recommended for HMAC-SHA256
+const ROUNDS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+/// Encrypted with the key of a new log, a wrong passphrase fails to decrypt it
+const CHECK: &[u8] = b"veelog";
+/// Context the keys of a sync session are derived in
+const SESSION_INFO: &[u8] = b"veelog sync";
+
+/// How the key of an encrypted log is derived from its passphrase
+#[derive(Debug, Encode, Decode)]
+pub(crate) struct Encryption {
+    salt: Vec<u8>,
+    rounds: u32,
+    /// `CHECK` encrypted with the key
+    check: Vec<u8>,
+}
+
+/// Encrypts values with ChaCha20-Poly1305 under a key derived from a passphrase
+#[derive(Debug)]
+pub(crate) struct Cipher {
+    key: LessSafeKey,
+}
+
+impl Cipher {
+    fn derive(passphrase: &str, salt: &[u8], rounds: u32) -> Result<Self> {
+        let rounds = NonZeroU32::new(rounds).ok_or_else(|| anyhow!("No key derivation rounds"))?;
+        let mut key = [0; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            rounds,
+            salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+        let key = UnboundKey::new(&CHACHA20_POLY1305, &key)
+            .map_err(|_| anyhow!("Could not create the encryption key"))?;
+        Ok(Self {
+            key: LessSafeKey::new(key),
+        })
+    }
+
+    /// A new key for `passphrase` with a random salt, and the settings to derive it again
+    pub(crate) fn create(passphrase: &str) -> Result<(Self, Encryption)> {
+        if passphrase.is_empty() {
+            bail!("The passphrase is empty");
+        }
+        let mut salt = vec![0; SALT_LEN];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| anyhow!("No random numbers for the salt"))?;
+        let cipher = Self::derive(passphrase, &salt, ROUNDS)?;
+        let check = cipher.encrypt(CHECK)?;
+        Ok((
+            cipher,
+            Encryption {
+                salt,
+                rounds: ROUNDS,
+                check,
+            },
+        ))
+    }
+
+    /// The key of a log encrypted with `encryption`, if `passphrase` is the right one
+    pub(crate) fn unlock(passphrase: &str, encryption: &Encryption) -> Result<Self> {
+        let cipher = Self::derive(passphrase, &encryption.salt, encryption.rounds)?;
+        match cipher.decrypt(&encryption.check) {
+            Ok(check) if check == CHECK => Ok(cipher),
+            _ => bail!("Wrong passphrase for the log"),
+        }
+    }
+
+    /// A random nonce followed by the ciphertext and its tag
+    pub(crate) fn encrypt(&self, plain: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("No random numbers for the nonce"))?;
+        let mut sealed = plain.to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow!("Could not encrypt"))?;
+        let mut enc = nonce.to_vec();
+        enc.extend(sealed);
+        Ok(enc)
+    }
+
+    pub(crate) fn decrypt(&self, enc: &[u8]) -> Result<Vec<u8>> {
+        if enc.len() < NONCE_LEN {
+            bail!("The encrypted value is too short");
+        }
+        let (nonce, sealed) = enc.split_at(NONCE_LEN);
+        let nonce =
+            Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("Invalid nonce"))?;
+        let mut sealed = sealed.to_vec();
+        let plain = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut sealed)
+            .map_err(|_| anyhow!("Could not decrypt, the value is damaged"))?;
+        Ok(plain.to_vec())
+    }
+}
+
+/// Encrypts what one side of a sync session sends with ChaCha20-Poly1305. The nonce
+/// counts the frames, so a frame that is dropped, replayed or reordered fails to open.
+pub(crate) struct SessionKey {
+    key: LessSafeKey,
+    frames: u64,
+}
+
+impl SessionKey {
+    /// The key of the side that opened the session with `hello`, derived from the token
+    /// both sides share and the `hellos` of both. Each session gets new keys.
+    pub(crate) fn derive(token: &str, hellos: &[u8], hello: &[u8]) -> Result<Self> {
+        let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, hellos).extract(token.as_bytes());
+        let info = [SESSION_INFO, hello];
+        let okm = prk
+            .expand(&info, &CHACHA20_POLY1305)
+            .map_err(|_| anyhow!("Could not derive the session key"))?;
+        Ok(Self {
+            key: LessSafeKey::new(UnboundKey::from(okm)),
+            frames: 0,
+        })
+    }
+
+    fn nonce(&mut self) -> Nonce {
+        let mut nonce = [0; NONCE_LEN];
+        nonce[NONCE_LEN - 8..].copy_from_slice(&self.frames.to_be_bytes());
+        self.frames += 1;
+        Nonce::assume_unique_for_key(nonce)
+    }
+
+    pub(crate) fn seal(&mut self, plain: &[u8]) -> Result<Vec<u8>> {
+        let nonce = self.nonce();
+        let mut sealed = plain.to_vec();
+        self.key
+            .seal_in_place_append_tag(nonce, Aad::empty(), &mut sealed)
+            .map_err(|_| anyhow!("Could not encrypt"))?;
+        Ok(sealed)
+    }
+
+    pub(crate) fn open(&mut self, sealed: &[u8]) -> Result<Vec<u8>> {
+        let nonce = self.nonce();
+        let mut sealed = sealed.to_vec();
+        let plain = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut sealed)
+            .map_err(|_| anyhow!("Could not decrypt what the other station sent"))?;
+        Ok(plain.to_vec())
+    }
+}
+
+impl Log {
+    /// Encrypts a value to store in an encrypted log, other logs store it as it is
+    pub(crate) fn encrypt(&self, value: Vec<u8>) -> Result<Vec<u8>> {
+        match &self.cipher {
+            Some(cipher) => cipher.encrypt(&value),
+            None => Ok(value),
+        }
+    }
+
+    pub(crate) fn decrypt(&self, value: &[u8]) -> Result<Vec<u8>> {
+        match &self.cipher {
+            Some(cipher) => cipher.decrypt(value),
+            None => Ok(value.to_vec()),
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cipher, Encryption, SessionKey};
+
+    #[test]
+    pub fn test_cipher() {
+        let (cipher, encryption) = Cipher::create("correct horse").unwrap();
+        let enc = cipher.encrypt(b"W1AW lives on Main St").unwrap();
+        assert!(!enc.windows(4).any(|w| w == b"W1AW"));
+        // every value gets its own nonce
+        assert_ne!(enc, cipher.encrypt(b"W1AW lives on Main St").unwrap());
+        assert_eq!(
+            b"W1AW lives on Main St".to_vec(),
+            cipher.decrypt(&enc).unwrap()
+        );
+
+        let mut damaged = enc.clone();
+        *damaged.last_mut().unwrap() ^= 1;
+        assert!(cipher.decrypt(&damaged).is_err());
+        assert!(cipher.decrypt(&enc[..5]).is_err());
+
+        let unlocked = Cipher::unlock("correct horse", &encryption).unwrap();
+        assert_eq!(
+            cipher.decrypt(&enc).unwrap(),
+            unlocked.decrypt(&enc).unwrap()
+        );
+        assert!(Cipher::unlock("wrong horse", &encryption).is_err());
+        assert!(Cipher::create("").is_err());
+        let fast = Encryption {
+            rounds: 0,
+            ..encryption
+        };
+        assert!(Cipher::unlock("correct horse", &fast).is_err());
+    }
+
+    #[test]
+    pub fn test_session_key() {
+        let mut send = SessionKey::derive("token", b"ab", b"a").unwrap();
+        let mut recv = SessionKey::derive("token", b"ab", b"a").unwrap();
+        let first = send.seal(b"W1AW").unwrap();
+        let second = send.seal(b"W1AW").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(b"W1AW".to_vec(), recv.open(&first).unwrap());
+        // replayed
+        assert!(recv.open(&first).is_err());
+
+        let mut other_side = SessionKey::derive("token", b"ab", b"b").unwrap();
+        let mut wrong_token = SessionKey::derive("nekot", b"ab", b"a").unwrap();
+        let sealed = SessionKey::derive("token", b"ab", b"a")
+            .unwrap()
+            .seal(b"W1AW")
+            .unwrap();
+        assert!(other_side.open(&sealed).is_err());
+        assert!(wrong_token.open(&sealed).is_err());
+    }
+}
@@ -1,13 +1,34 @@
-use crate::VEELOG_MAGIC;
-use adif::{data::ADIFFile, parse};
+use crate::{
+    VEELOG_MAGIC,
+    crypt::{Cipher, ENCRYPTION_KEY},
+    equipment::EquipmentIndex,
+    events::{LogEvent, Subscribers},
+    filter::Filter,
+    provenance::Source,
+    sync::Change,
+};
+use adif::{
+    data::{ADIFFile, ADIFHeader, ADIFRecord, ADIFType},
+    parse,
+};
 use serde::{Deserialize, Serialize};
-use util::prettyvalidate_gridsquare;
+use util::{
+    band::Band,
+    callsign, fields,
+    freq::Frequency,
+    geo::{GridStrictness, prettyvalidate_gridsquare},
+};
 
 use anyhow::{Result, bail};
 use bincode::{
     Decode, Encode,
     config::{self, Configuration},
-    decode_from_slice, encode_to_vec,
+    de::{Decoder, read::Reader},
+    decode_from_slice,
+    enc::Encoder,
+    encode_to_vec,
+    error::{DecodeError, EncodeError},
+    serde::Compat,
 };
 use indexmap::IndexMap;
 use jiff::{
@@ -16,12 +37,44 @@ use jiff::{
     fmt::strtime,
     tz::TimeZone,
 };
-use sled::{Db, IVec};
+use sled::{
+    Batch, Db, IVec, Transactional, Tree,
+    transaction::{ConflictableTransactionError, TransactionError, abort},
+};
 use std::{
+    collections::HashSet,
     fmt::Display,
     fs,
+    ops::Range,
     path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
 };
+use ulid::Ulid;
+
+/// Records keyed by their `RecordId`
+const RECORDS_TREE: &[u8] = b"RECORDS";
+/// Ordinal index to `RecordId`, in insertion order
+const ORDINAL_TREE: &[u8] = b"ORDINAL";
+/// `RecordId` to ordinal index, the reverse of ORDINAL
+const INDICES_TREE: &[u8] = b"INDICES";
+/// Revision of each record by `RecordId`, bumped on every modification. Records never
+/// modified have none, which reads as revision 0.
+const REVISIONS_TREE: &[u8] = b"REVISIONS";
+/// Export name of `FieldType::Timestamp`, which has no ADIF name of its own
+const TIMESTAMP_NAME: &str = "TIMESTAMP";
+/// Storage layout version, stored under LAYOUT
+const LAYOUT: u8 = 4;
+/// First byte of a record stored with field tags. Records from before layout 3 start
+/// with the varint length of their field map, which never starts with 0xff.
+const TAGGED_RECORD: u8 = 0xff;
+/// Version of the tagged record encoding, stored after TAGGED_RECORD. Version 1 stored
+/// every value as a string, version 2 stores frequencies in Hz.
+const RECORD_VERSION: u8 = 2;
+/// Tag prefix of `FieldType::Other`, followed by the field's ADIF name
+const OTHER_TAG: &str = "Other:";
+/// Aborts an insert whose record id another clone of the log took first
+const ID_TAKEN: &str = "Record id is taken";
 
 #[derive(Debug)]
 pub struct LogError {
@@ -35,6 +88,43 @@ impl Display for LogError {
     }
 }
 
+/// A record changed since it was read for `Log::modify_record_checked`, e.g. by a WSJT-X
+/// insert or a sync in the background. Apply the edit to `current` again, or show both.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConflictError {
+    pub idx: usize,
+    /// Revision the edit was made to
+    pub expected: u64,
+    /// Revision of `current`
+    pub revision: u64,
+    /// The record as it is in the log now
+    pub current: LogRecord,
+}
+
+impl Display for ConflictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Record {} was changed elsewhere (revision {}, edited {})",
+            self.idx, self.revision, self.expected
+        )
+    }
+}
+
+impl std::error::Error for ConflictError {}
+
+/// Why `Log::write_entries` gave up on its transaction
+enum WriteAbort {
+    Conflict(ConflictError),
+    /// The record at this index was deleted after its id was looked up
+    Missing(usize),
+}
+
+fn parse_revision(v: Option<IVec>) -> u64 {
+    v.and_then(|v| v.as_ref().try_into().ok())
+        .map_or(0, u64::from_le_bytes)
+}
+
 impl From<jiff::Error> for LogError {
     fn from(value: jiff::Error) -> Self {
         LogError {
@@ -44,6 +134,17 @@ impl From<jiff::Error> for LogError {
     }
 }
 
+/// What to do with ADIF fields that have no dedicated `FieldType` or a value that doesn't
+/// validate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportPolicy {
+    /// Fail the import instead of storing unsupported fields or values that don't validate
+    Strict,
+    /// Keep every field as it is, storing unsupported ones as `FieldType::Other`
+    #[default]
+    PreserveAll,
+}
+
 #[non_exhaustive]
 #[derive(
     Debug,
@@ -52,15 +153,14 @@ impl From<jiff::Error> for LogError {
     Eq,
     Hash,
     strum_macros::EnumString,
-    Encode,
-    Decode,
+    strum_macros::IntoStaticStr,
     Deserialize,
     Serialize,
 )]
 pub enum FieldType {
     Timestamp,
     WorkedCall,
-    Frequency, // freq in MHz
+    Frequency, // freq in MHz, the transmit frequency when working split
     Mode,
     SentRST,
     RcvdRST,
@@ -76,44 +176,681 @@ pub enum FieldType {
     Name,
     QTH,
     Other(Box<str>),
+    Submode,
+    EqslSent,
+    RecordId,
+    /// Path of the audio recording of the QSO
+    AudioRef,
+    /// Id of the operating session the QSO was logged in
+    Session,
+    /// Ids of the registered equipment used, separated by commas
+    Equipment,
+    /// Transmit power in watts
+    TxPower,
+    /// Receive frequency in MHz, only set when working split
+    RxFrequency,
+    /// Which of two radios the QSO was made on when operating SO2R, 1 or 2
+    Radio,
+    /// Y once LoTW confirmed the QSO
+    LotwRcvd,
+    /// Receive band when working cross band, e.g. via satellite
+    RxBand,
+    /// How the signal got there, from the ADIF Propagation_Mode enumeration
+    PropMode,
+    Iota,
+    SotaRef,
+    WwffRef,
+    /// Our own IOTA, SOTA and WWFF references, when activating one
+    MyIota,
+    MySotaRef,
+    MyWwffRef,
+    ContestId,
+    /// Call of the operator, when it differs from the station's call
+    Operator,
+    StationCallsign,
+    /// Antenna azimuth in degrees
+    AntAz,
+    /// Contest exchange sent as free text, e.g. `5NN 123 CT`
+    SentExchange,
+    /// Contest exchange received as free text
+    RcvdExchange,
+    /// US county, the state and county separated by a comma, e.g. `MA,Middlesex`
+    SecondaryAdminSubdiv,
+}
+
+/// How a field is named in ADIF files and in the UI, and which values it accepts
+struct FieldInfo {
+    ty: FieldType,
+    /// None for `Timestamp`, which `LogRecord::to_adif` splits into QSO_DATE and TIME_ON
+    adif: Option<&'static str>,
+    label: &'static str,
+    /// Checks a value as it is typed, so partial values pass too
+    valid: fn(&str) -> bool,
+}
+
+const FIELDS: &[FieldInfo] = &[
+    FieldInfo {
+        ty: FieldType::Timestamp,
+        adif: None,
+        label: "Time",
+        valid: |v| {
+            v.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-:.+".contains(c))
+        },
+    },
+    FieldInfo {
+        ty: FieldType::WorkedCall,
+        adif: Some("CALL"),
+        label: "Call",
+        valid: callsign::is_partial_callsign,
+    },
+    FieldInfo {
+        ty: FieldType::Frequency,
+        adif: Some("FREQ"),
+        label: "Freq",
+        valid: |v| v.chars().all(|c| c.is_ascii_digit() || c == '.'),
+    },
+    FieldInfo {
+        ty: FieldType::RxFrequency,
+        adif: Some("FREQ_RX"),
+        label: "Freq RX",
+        valid: |v| v.chars().all(|c| c.is_ascii_digit() || c == '.'),
+    },
+    FieldInfo {
+        ty: FieldType::Radio,
+        adif: Some("APP_VEELOG_RADIO"),
+        label: "Radio",
+        valid: digits,
+    },
+    FieldInfo {
+        ty: FieldType::TxPower,
+        adif: Some("TX_PWR"),
+        label: "Power",
+        valid: |v| v.chars().all(|c| c.is_ascii_digit() || c == '.'),
+    },
+    FieldInfo {
+        ty: FieldType::Mode,
+        adif: Some("MODE"),
+        label: "Mode",
+        valid: alphanumeric,
+    },
+    FieldInfo {
+        ty: FieldType::Submode,
+        adif: Some("SUBMODE"),
+        label: "Submode",
+        valid: alphanumeric,
+    },
+    FieldInfo {
+        ty: FieldType::EqslSent,
+        adif: Some("EQSL_QSL_SENT"),
+        label: "eQSL Sent",
+        valid: alphanumeric,
+    },
+    FieldInfo {
+        ty: FieldType::LotwRcvd,
+        adif: Some("LOTW_QSL_RCVD"),
+        label: "LoTW Rcvd",
+        valid: alphanumeric,
+    },
+    FieldInfo {
+        ty: FieldType::RecordId,
+        adif: Some("APP_VEELOG_ID"),
+        label: "ID",
+        valid: alphanumeric,
+    },
+    FieldInfo {
+        ty: FieldType::AudioRef,
+        adif: Some("APP_VEELOG_AUDIO"),
+        label: "Audio",
+        valid: any,
+    },
+    FieldInfo {
+        ty: FieldType::Session,
+        adif: Some("APP_VEELOG_SESSION"),
+        label: "Session",
+        valid: alphanumeric,
+    },
+    FieldInfo {
+        ty: FieldType::Equipment,
+        adif: Some("APP_VEELOG_EQUIPMENT"),
+        label: "Equipment",
+        valid: |v| v.chars().all(|c| c.is_ascii_digit() || c == ','),
+    },
+    FieldInfo {
+        ty: FieldType::SentRST,
+        adif: Some("RST_SENT"),
+        label: "RST Sent",
+        valid: rst,
+    },
+    FieldInfo {
+        ty: FieldType::RcvdRST,
+        adif: Some("RST_RCVD"),
+        label: "RST Rcvd",
+        valid: rst,
+    },
+    FieldInfo {
+        ty: FieldType::GridSquare,
+        adif: Some("GRIDSQUARE"),
+        label: "Grid",
+        valid: grid,
+    },
+    FieldInfo {
+        ty: FieldType::PrimaryAdminSubdiv,
+        adif: Some("STATE"),
+        label: "State",
+        valid: alphanumeric,
+    },
+    FieldInfo {
+        ty: FieldType::SentSerial,
+        adif: Some("STX"),
+        label: "Sent #",
+        valid: serial,
+    },
+    FieldInfo {
+        ty: FieldType::RcvdSerial,
+        adif: Some("SRX"),
+        label: "Rcvd #",
+        valid: serial,
+    },
+    FieldInfo {
+        ty: FieldType::SentExchange,
+        adif: Some("STX_STRING"),
+        label: "Sent Exch",
+        valid: any,
+    },
+    FieldInfo {
+        ty: FieldType::RcvdExchange,
+        adif: Some("SRX_STRING"),
+        label: "Rcvd Exch",
+        valid: any,
+    },
+    FieldInfo {
+        ty: FieldType::DXCC,
+        adif: Some("DXCC"),
+        label: "DXCC",
+        valid: |v| v.len() <= 3 && digits(v),
+    },
+    FieldInfo {
+        ty: FieldType::CQZ,
+        adif: Some("CQZ"),
+        label: "CQ Zone",
+        valid: |v| v.len() <= 2 && digits(v),
+    },
+    FieldInfo {
+        ty: FieldType::ITUZ,
+        adif: Some("ITUZ"),
+        label: "ITU Zone",
+        valid: |v| v.len() <= 2 && digits(v),
+    },
+    FieldInfo {
+        ty: FieldType::POTARef,
+        adif: Some("POTA_REF"),
+        label: "POTA",
+        valid: |v| {
+            v.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-,@".contains(c))
+        },
+    },
+    FieldInfo {
+        ty: FieldType::RxBand,
+        adif: Some("BAND_RX"),
+        label: "Band RX",
+        valid: |v| v.chars().all(|c| c.is_ascii_alphanumeric() || c == '.'),
+    },
+    FieldInfo {
+        ty: FieldType::PropMode,
+        adif: Some("PROP_MODE"),
+        label: "Prop Mode",
+        valid: alphanumeric,
+    },
+    FieldInfo {
+        ty: FieldType::Iota,
+        adif: Some("IOTA"),
+        label: "IOTA",
+        valid: |v| v.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'),
+    },
+    FieldInfo {
+        ty: FieldType::SotaRef,
+        adif: Some("SOTA_REF"),
+        label: "SOTA",
+        valid: |v| {
+            v.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "/-".contains(c))
+        },
+    },
+    FieldInfo {
+        ty: FieldType::WwffRef,
+        adif: Some("WWFF_REF"),
+        label: "WWFF",
+        valid: |v| v.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'),
+    },
+    FieldInfo {
+        ty: FieldType::MyIota,
+        adif: Some("MY_IOTA"),
+        label: "My IOTA",
+        valid: |v| v.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'),
+    },
+    FieldInfo {
+        ty: FieldType::MySotaRef,
+        adif: Some("MY_SOTA_REF"),
+        label: "My SOTA",
+        valid: |v| {
+            v.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "/-".contains(c))
+        },
+    },
+    FieldInfo {
+        ty: FieldType::MyWwffRef,
+        adif: Some("MY_WWFF_REF"),
+        label: "My WWFF",
+        valid: |v| v.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'),
+    },
+    FieldInfo {
+        ty: FieldType::ContestId,
+        adif: Some("CONTEST_ID"),
+        label: "Contest",
+        valid: |v| {
+            v.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_".contains(c))
+        },
+    },
+    FieldInfo {
+        ty: FieldType::Operator,
+        adif: Some("OPERATOR"),
+        label: "Operator",
+        valid: callsign::is_partial_callsign,
+    },
+    FieldInfo {
+        ty: FieldType::StationCallsign,
+        adif: Some("STATION_CALLSIGN"),
+        label: "Station Call",
+        valid: callsign::is_partial_callsign,
+    },
+    FieldInfo {
+        ty: FieldType::AntAz,
+        adif: Some("ANT_AZ"),
+        label: "Azimuth",
+        valid: |v| v.chars().all(|c| c.is_ascii_digit() || c == '.'),
+    },
+    FieldInfo {
+        ty: FieldType::SecondaryAdminSubdiv,
+        adif: Some("CNTY"),
+        label: "County",
+        valid: |v| {
+            v.matches(',').count() <= 1
+                && v.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || " ,.'-".contains(c))
+        },
+    },
+    FieldInfo {
+        ty: FieldType::Comment,
+        adif: Some("COMMENT"),
+        label: "Comment",
+        valid: any,
+    },
+    FieldInfo {
+        ty: FieldType::Name,
+        adif: Some("NAME"),
+        label: "Name",
+        valid: any,
+    },
+    FieldInfo {
+        ty: FieldType::QTH,
+        adif: Some("QTH"),
+        label: "QTH",
+        valid: any,
+    },
+];
+
+fn any(_: &str) -> bool {
+    true
+}
+
+fn digits(v: &str) -> bool {
+    v.chars().all(|c| c.is_ascii_digit())
+}
+
+fn alphanumeric(v: &str) -> bool {
+    v.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// A Maidenhead locator of up to 10 characters: field letters A-R, square digits,
+/// subsquare letters A-X, then extended square digits and subsquare letters
+fn grid(v: &str) -> bool {
+    v.len() <= 10
+        && v.chars().enumerate().all(|(i, c)| match i {
+            0 | 1 => matches!(c.to_ascii_uppercase(), 'A'..='R'),
+            4 | 5 | 8 | 9 => matches!(c.to_ascii_uppercase(), 'A'..='X'),
+            _ => c.is_ascii_digit(),
+        })
+}
+
+/// RST, or a signal to noise report like -12 on digital modes
+fn rst(v: &str) -> bool {
+    digits(v.strip_prefix(['-', '+']).unwrap_or(v))
+}
+
+fn serial(v: &str) -> bool {
+    v.is_empty() || v.parse::<u32>().is_ok()
+}
+
+/// Normalizes a power in watts, with or without a trailing W, to a plain number
+pub fn parse_power(v: &str) -> Result<String> {
+    let v = v.trim();
+    let watts = v
+        .strip_suffix(['W', 'w'])
+        .unwrap_or(v)
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|w| w.is_finite() && *w >= 0.0);
+    match watts {
+        Some(watts) => Ok(watts.to_string()),
+        None => bail!(util::Error::FieldParseError {
+            field_name: "TX_PWR".to_string(),
+            field_value: v.to_string(),
+            err: "not a power in watts".to_string(),
+        }),
+    }
 }
 
 impl FieldType {
+    fn info(&self) -> Option<&'static FieldInfo> {
+        FIELDS.iter().find(|f| f.ty == *self)
+    }
+
     pub fn from_adif_field(field_name: &str) -> Self {
-        match field_name {
-            "CALL" => Self::WorkedCall,
-            "FREQ" => Self::Frequency,
-            "MODE" => Self::Mode,
-            "RST_SENT" => Self::SentRST,
-            "RST_RCVD" => Self::RcvdRST,
-            "GRIDSQUARE" => Self::GridSquare,
-            "STATE" => Self::PrimaryAdminSubdiv,
-            "STX" => Self::SentSerial,
-            "SRX" => Self::RcvdSerial,
-            "DXCC" => Self::DXCC,
-            "CQZ" => Self::CQZ,
-            "ITUZ" => Self::ITUZ,
-            "POTA_REF" => Self::POTARef,
-            "COMMENT" => Self::Comment,
-            "NAME" => Self::Name,
-            "QTH" => Self::QTH,
-            _ => Self::Other(field_name.into()),
+        FIELDS
+            .iter()
+            .find(|f| f.adif == Some(field_name))
+            .map(|f| f.ty.clone())
+            .unwrap_or_else(|| Self::Other(field_name.into()))
+    }
+
+    /// The ADIF field name this type is exported as. `Timestamp` is split into
+    /// QSO_DATE and TIME_ON by `LogRecord::to_adif` and has no single name.
+    pub fn adif_name(&self) -> Option<&str> {
+        match self {
+            Self::Other(name) => Some(name),
+            _ => self.info().and_then(|f| f.adif),
+        }
+    }
+
+    /// Short human readable name, used as the UI label
+    pub fn label(&self) -> &str {
+        match self {
+            Self::Other(name) => name,
+            _ => self.info().map(|f| f.label).unwrap_or_default(),
+        }
+    }
+
+    /// Whether `val` is, or could be typed into, a valid value of this field.
+    /// Fields without rules of their own, such as `Other`, accept anything.
+    pub fn is_valid(&self, val: &str) -> bool {
+        self.info().is_none_or(|f| (f.valid)(val))
+    }
+
+    /// Checks a complete value against the field's ADIF enumeration or format, returning
+    /// it in its canonical form. Fields without one take any value as it is.
+    pub fn validate(&self, val: &str) -> Result<String> {
+        let checked = match self {
+            Self::RxBand => fields::validate_band(val),
+            Self::PropMode => fields::validate_prop_mode(val),
+            Self::Iota | Self::MyIota => fields::validate_iota(val),
+            Self::SotaRef | Self::MySotaRef => fields::validate_sota_ref(val),
+            Self::WwffRef | Self::MyWwffRef => fields::validate_wwff_ref(val),
+            Self::ContestId => fields::validate_contest_id(val),
+            Self::Operator | Self::StationCallsign => callsign::validate_callsign(val),
+            Self::AntAz => fields::validate_azimuth(val),
+            Self::SecondaryAdminSubdiv => fields::validate_subdivision(val),
+            _ => return Ok(val.to_string()),
+        };
+        match checked {
+            Ok(val) => Ok(val),
+            Err(e) => bail!(util::Error::FieldParseError {
+                field_name: self.export_name(),
+                field_value: val.to_string(),
+                err: e.to_string(),
+            }),
+        }
+    }
+
+    /// Name used for this field in CSV and JSON exports: the ADIF name,
+    /// or TIMESTAMP for the RFC 3339 timestamp
+    pub fn export_name(&self) -> String {
+        self.adif_name().unwrap_or(TIMESTAMP_NAME).to_string()
+    }
+
+    pub fn from_export_name(name: &str) -> Self {
+        match name {
+            TIMESTAMP_NAME => Self::Timestamp,
+            _ => Self::from_adif_field(name),
+        }
+    }
+
+    /// Name this field is stored under in the database: the variant name, or
+    /// `Other:` and the ADIF name. Unlike the variant index it survives reordering.
+    fn tag(&self) -> String {
+        match self {
+            Self::Other(name) => format!("{}{}", OTHER_TAG, name),
+            ty => <&str>::from(ty).to_string(),
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag.strip_prefix(OTHER_TAG) {
+            // fields stored before they got a type of their own
+            Some(name) => Some(Self::from_adif_field(name)),
+            None => Self::from_str(tag)
+                .ok()
+                .filter(|ty| !matches!(ty, Self::Other(_))),
+        }
+    }
+}
+
+/// `FieldType` as it was when records were encoded by variant index, before layout 3.
+/// Only used to read such records, so it must never change.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Eq, Hash, Deserialize)]
+enum LegacyFieldType {
+    Timestamp,
+    WorkedCall,
+    Frequency,
+    Mode,
+    SentRST,
+    RcvdRST,
+    GridSquare,
+    PrimaryAdminSubdiv,
+    SentSerial,
+    RcvdSerial,
+    DXCC,
+    CQZ,
+    ITUZ,
+    POTARef,
+    Comment,
+    Name,
+    QTH,
+    Other(Box<str>),
+    Submode,
+    EqslSent,
+    RecordId,
+    AudioRef,
+}
+
+impl From<LegacyFieldType> for FieldType {
+    fn from(ty: LegacyFieldType) -> Self {
+        match ty {
+            LegacyFieldType::Timestamp => Self::Timestamp,
+            LegacyFieldType::WorkedCall => Self::WorkedCall,
+            LegacyFieldType::Frequency => Self::Frequency,
+            LegacyFieldType::Mode => Self::Mode,
+            LegacyFieldType::SentRST => Self::SentRST,
+            LegacyFieldType::RcvdRST => Self::RcvdRST,
+            LegacyFieldType::GridSquare => Self::GridSquare,
+            LegacyFieldType::PrimaryAdminSubdiv => Self::PrimaryAdminSubdiv,
+            LegacyFieldType::SentSerial => Self::SentSerial,
+            LegacyFieldType::RcvdSerial => Self::RcvdSerial,
+            LegacyFieldType::DXCC => Self::DXCC,
+            LegacyFieldType::CQZ => Self::CQZ,
+            LegacyFieldType::ITUZ => Self::ITUZ,
+            LegacyFieldType::POTARef => Self::POTARef,
+            LegacyFieldType::Comment => Self::Comment,
+            LegacyFieldType::Name => Self::Name,
+            LegacyFieldType::QTH => Self::QTH,
+            LegacyFieldType::Other(name) => Self::from_adif_field(&name),
+            LegacyFieldType::Submode => Self::Submode,
+            LegacyFieldType::EqslSent => Self::EqslSent,
+            LegacyFieldType::RecordId => Self::RecordId,
+            LegacyFieldType::AudioRef => Self::AudioRef,
         }
     }
 }
 
 impl std::fmt::Display for FieldType {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        write!(f, "{}", self.label())
+    }
+}
+
+/// Stable identity of a record, independent of its position in the log, so records can be
+/// deleted, merged and synced. ULIDs sort by creation time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RecordId(Ulid);
+
+impl RecordId {
+    /// A new id that sorts after `last`, even if the clock went backwards
+    fn after(last: Option<RecordId>) -> Result<Self> {
+        let id = Ulid::new();
+        match last {
+            Some(RecordId(last)) if id <= last => match last.increment() {
+                Some(id) => Ok(Self(id)),
+                None => bail!("Ran out of record ids"),
+            },
+            _ => Ok(Self(id)),
+        }
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; 16] {
+        self.0.to_bytes()
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(Self(Ulid::from_bytes(bytes.try_into()?)))
+    }
+}
+
+impl Display for RecordId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for RecordId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self(Ulid::from_string(s)?))
+    }
+}
+
+/// A field value as stored in a record. Frequencies are kept in whole Hz, so equal
+/// frequencies compare equal however they were typed, and MHz strings only appear
+/// at the ADIF boundary.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+enum FieldValue {
+    Text(String),
+    Hz(u64),
+}
+
+impl FieldValue {
+    /// Frequency fields holding a valid MHz value are stored in Hz, anything else as text
+    fn new(ty: &FieldType, val: &str) -> Self {
+        match ty {
+            FieldType::Frequency | FieldType::RxFrequency => match Frequency::parse_mhz(val) {
+                Ok(freq) => Self::Hz(freq.hz()),
+                Err(_) => Self::Text(val.to_string()),
+            },
+            _ => Self::Text(val.to_string()),
+        }
+    }
+}
+
+impl Display for FieldValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Text(val) => write!(f, "{}", val),
+            Self::Hz(hz) => write!(f, "{}", Frequency::from_hz(*hz)),
+        }
     }
 }
 
-#[derive(Debug, PartialEq, Encode, Decode)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LogRecord {
-    #[bincode(with_serde)]
-    map: IndexMap<FieldType, String>,
+    map: IndexMap<FieldType, FieldValue>,
+}
+
+/// Records are stored as TAGGED_RECORD, RECORD_VERSION and the list of
+/// (`FieldType::tag`, value) pairs, so `FieldType` can gain and reorder variants
+impl Encode for LogRecord {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> std::result::Result<(), EncodeError> {
+        TAGGED_RECORD.encode(encoder)?;
+        RECORD_VERSION.encode(encoder)?;
+        let fields = self
+            .map
+            .iter()
+            .map(|(ty, val)| (ty.tag(), val))
+            .collect::<Vec<_>>();
+        fields.encode(encoder)
+    }
 }
 
+impl<Context> Decode<Context> for LogRecord {
+    fn decode<D: Decoder<Context = Context>>(
+        decoder: &mut D,
+    ) -> std::result::Result<Self, DecodeError> {
+        if decoder.reader().peek_read(1) != Some(&[TAGGED_RECORD]) {
+            let Compat(map) = Compat::<IndexMap<LegacyFieldType, String>>::decode(decoder)?;
+            let mut record = Self::new();
+            for (ty, val) in map {
+                record.insert_field(ty.into(), &val);
+            }
+            return Ok(record);
+        }
+        decoder.reader().consume(1);
+        let fields = match u8::decode(decoder)? {
+            1 => Vec::<(String, String)>::decode(decoder)?
+                .into_iter()
+                .map(|(tag, val)| (tag, FieldValue::Text(val)))
+                .collect(),
+            RECORD_VERSION => Vec::<(String, FieldValue)>::decode(decoder)?,
+            version => {
+                return Err(DecodeError::OtherString(format!(
+                    "Unknown record version {}",
+                    version
+                )));
+            }
+        };
+        let mut map = IndexMap::new();
+        for (tag, val) in fields {
+            let Some(ty) = FieldType::from_tag(&tag) else {
+                return Err(DecodeError::OtherString(format!(
+                    "Unknown field tag {}",
+                    tag
+                )));
+            };
+            // version 1 frequencies are MHz strings
+            let val = match val {
+                FieldValue::Text(text) => FieldValue::new(&ty, &text),
+                val => val,
+            };
+            map.insert(ty, val);
+        }
+        Ok(Self { map })
+    }
+}
+
+bincode::impl_borrow_decode!(LogRecord);
+
 impl LogRecord {
     pub fn new() -> Self {
         LogRecord {
@@ -121,25 +858,109 @@ impl LogRecord {
         }
     }
 
+    /// Sets a field, frequencies are given in MHz
     pub fn insert_field(&mut self, ty: FieldType, val: &str) -> &mut Self {
-        self.map.insert(ty, val.to_string());
+        let val = FieldValue::new(&ty, val);
+        self.map.insert(ty, val);
         self
     }
 
     pub fn insert_timestamp(&mut self, ts: Timestamp) -> &mut Self {
-        self.map.insert(FieldType::Timestamp, ts.to_string());
+        self.map
+            .insert(FieldType::Timestamp, FieldValue::Text(ts.to_string()));
         self
     }
 
+    /// Sets `Frequency` or `RxFrequency`
+    pub fn insert_frequency(&mut self, ty: FieldType, freq: Frequency) -> &mut Self {
+        self.map.insert(ty, FieldValue::Hz(freq.hz()));
+        self
+    }
+
+    /// A field's value, frequencies in MHz
     pub fn get_field(&self, ty: &FieldType) -> Option<String> {
-        match self.map.get(ty) {
-            Some(val) => Some(val.to_string()),
-            None => None,
+        self.map.get(ty).map(|val| val.to_string())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&FieldType, String)> {
+        self.map.iter().map(|(ty, val)| (ty, val.to_string()))
+    }
+
+    /// The record's id, assigned when it is inserted into a log
+    pub fn id(&self) -> Option<RecordId> {
+        self.get_field(&FieldType::RecordId)?.parse().ok()
+    }
+
+    /// The QSO frequency, if the record has a valid one
+    pub fn frequency(&self) -> Option<Frequency> {
+        self.hz(&FieldType::Frequency)
+    }
+
+    /// The receive frequency of a split QSO, if the record has a valid one
+    pub fn rx_frequency(&self) -> Option<Frequency> {
+        self.hz(&FieldType::RxFrequency)
+    }
+
+    fn hz(&self, ty: &FieldType) -> Option<Frequency> {
+        match self.map.get(ty)? {
+            FieldValue::Hz(hz) => Some(Frequency::from_hz(*hz)),
+            FieldValue::Text(_) => None,
+        }
+    }
+
+    /// The QSO band from the frequency, else from the BAND field of imported logs
+    pub fn band(&self) -> Option<String> {
+        match self.frequency().and_then(Band::from_freq) {
+            Some(band) => Some(band.name().to_string()),
+            None => self.get_field(&FieldType::from_adif_field("BAND")),
         }
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (&FieldType, &String)> {
-        self.map.iter()
+    /// QSO time for display in `tz`, e.g. `2025-07-28 04:48:13 CEST`
+    pub fn display_time(&self, tz: &TimeZone) -> Option<String> {
+        let ts = self
+            .get_field(&FieldType::Timestamp)?
+            .parse::<Timestamp>()
+            .ok()?;
+        let time = ts.to_zoned(tz.clone());
+        match tz == &TimeZone::UTC {
+            true => Some(time.strftime("%Y-%m-%d %H:%M:%S UTC").to_string()),
+            false => Some(time.strftime("%Y-%m-%d %H:%M:%S %Z").to_string()),
+        }
+    }
+
+    /// Mode for display, including the submode if there is one, e.g. "MFSK/FT4"
+    pub fn display_mode(&self) -> Option<String> {
+        match (
+            self.get_field(&FieldType::Mode),
+            self.get_field(&FieldType::Submode),
+        ) {
+            (Some(mode), Some(submode)) => Some(format!("{}/{}", mode, submode)),
+            (Some(mode), None) => Some(mode),
+            (None, Some(submode)) => Some(submode),
+            (None, None) => None,
+        }
+    }
+
+    pub fn to_adif(&self) -> Result<ADIFRecord> {
+        let mut fields = Vec::new();
+        for (ty, val) in &self.map {
+            match ty.adif_name() {
+                Some(name) => fields.push((name.to_string(), ADIFType::Str(val.to_string()))),
+                None => {
+                    let ts: Timestamp = val.to_string().parse()?;
+                    fields.push((
+                        "QSO_DATE".to_string(),
+                        ADIFType::Str(ts.strftime("%Y%m%d").to_string()),
+                    ));
+                    fields.push((
+                        "TIME_ON".to_string(),
+                        ADIFType::Str(ts.strftime("%H%M%S").to_string()),
+                    ));
+                }
+            }
+        }
+        Ok(ADIFRecord(fields))
     }
 }
 
@@ -152,7 +973,7 @@ impl Display for LogRecord {
     }
 }
 
-#[derive(Debug, Encode, Decode)]
+#[derive(Debug, PartialEq, Encode, Decode, Serialize, Deserialize)]
 pub struct LogHeader {
     version: String,
     op_call: String,
@@ -167,22 +988,77 @@ impl LogHeader {
             comment: comment.to_string(),
         }
     }
+
+    /// Call of the station the log is kept for
+    pub fn op_call(&self) -> &str {
+        &self.op_call
+    }
 }
 
-#[derive(Debug)]
+/// Copies the directory `from` with everything in it to `to`
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let dest = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dest)?;
+        } else {
+            fs::copy(entry.path(), dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads an INDEX value, None if it is not a usize
+fn parse_idx(v: &[u8]) -> Option<usize> {
+    v.try_into().ok().map(usize::from_le_bytes)
+}
+
+/// A log stored in a sled database. Clones share the same database, so a clone can be
+/// handed to a background task. All methods take `&self` and sled is thread safe, so
+/// clones can read and write at the same time without further locking.
+#[derive(Debug, Clone)]
 pub struct Log {
-    db: Db,
+    pub(crate) db: Db,
+    pub(crate) subscribers: Subscribers,
+    /// Opened with `open_read_only`, changes are refused
+    read_only: bool,
+    /// Key of an encrypted log, record values and notes are stored encrypted with it
+    pub(crate) cipher: Option<Arc<Cipher>>,
 }
 
 impl Log {
     /// Creates a new Log object with a passed in sled Db that must be already intialized
     pub fn new(db: Db) -> Result<Self> {
-        let log = Self { db };
+        if db.contains_key(ENCRYPTION_KEY)? {
+            bail!("The log is encrypted, its passphrase is needed to open it");
+        }
+        Self::new_with_cipher(db, None)
+    }
+
+    /// Opens an encrypted log, failing if `passphrase` is not the one it was created with
+    pub fn new_encrypted(db: Db, passphrase: &str) -> Result<Self> {
+        let Some(enc) = db.get(ENCRYPTION_KEY)? else {
+            bail!("The log is not encrypted")
+        };
+        let cipher = Cipher::unlock(passphrase, &Self::decode_record(&enc)?)?;
+        Self::new_with_cipher(db, Some(Arc::new(cipher)))
+    }
+
+    pub(crate) fn new_with_cipher(db: Db, cipher: Option<Arc<Cipher>>) -> Result<Self> {
+        let log = Self {
+            db,
+            subscribers: Subscribers::default(),
+            read_only: false,
+            cipher,
+        };
         let db_value = log.get_key(b"MAGIC")?;
         match db_value {
             Some(val) => {
                 if val.to_ascii_uppercase().as_slice() == VEELOG_MAGIC {
                     // we can presume that this is a safe existing database. continue as normal.
+                    log.migrate()?;
                     Ok(log)
                 } else {
                     // not our magic. error
@@ -197,7 +1073,12 @@ impl Log {
     pub fn new_init(db: Db, header: LogHeader) -> Result<Self> {
         if db.is_empty() {
             // empty database. make a new one
-            let log = Self { db };
+            let log = Self {
+                db,
+                subscribers: Subscribers::default(),
+                read_only: false,
+                cipher: None,
+            };
             log.init_db(header)?;
             Ok(log)
         } else {
@@ -206,15 +1087,87 @@ impl Log {
         }
     }
 
+    /// Creates a new Log whose record values and notes are encrypted at rest with a key
+    /// derived from `passphrase`. The passphrase is not stored, without it the records
+    /// can't be read. Should be used when the db is fresh.
+    pub fn new_init_encrypted(db: Db, header: LogHeader, passphrase: &str) -> Result<Self> {
+        if !db.is_empty() {
+            bail!("Non-empty database used in Log::new_init_encrypted()")
+        }
+        let (cipher, encryption) = Cipher::create(passphrase)?;
+        let log = Self {
+            db,
+            subscribers: Subscribers::default(),
+            read_only: false,
+            cipher: Some(Arc::new(cipher)),
+        };
+        log.set_key(ENCRYPTION_KEY, Self::encode_record(encryption)?)?;
+        log.init_db(header)?;
+        Ok(log)
+    }
+
     pub fn new_from_path(path: &Path, header: LogHeader) -> Result<Self> {
         let db = sled::open(&path)?;
         Self::new_init(db, header)
     }
 
+    /// Opens the log at `path`, creating it with `header` if there is none yet. Existing
+    /// data is never cleared, a database that is not a log is refused. With a
+    /// `passphrase` a new log is encrypted and an existing one must be.
+    pub fn open_path(path: &Path, header: LogHeader, passphrase: Option<&str>) -> Result<Self> {
+        let db = sled::open(path)?;
+        match (db.is_empty(), passphrase) {
+            (true, None) => Self::new_init(db, header),
+            (true, Some(passphrase)) => Self::new_init_encrypted(db, header, passphrase),
+            (false, None) => Self::new(db),
+            (false, Some(passphrase)) => Self::new_encrypted(db, passphrase),
+        }
+    }
+
+    /// Opens the log at `path` for reading only, e.g. a backup or someone else's log.
+    /// Its files are copied and read from the copy, so nothing is written to it, not even
+    /// an upgrade to the current layout, and it opens while the log is open elsewhere.
+    /// Changing the log fails.
+    pub fn open_read_only(path: &Path, passphrase: Option<&str>) -> Result<Self> {
+        // sled would create a database that does not exist
+        if !path.exists() {
+            bail!("There is no log at {}", path.display());
+        }
+        let copy = std::env::temp_dir().join(format!("veelog-read-only-{}", Ulid::new()));
+        copy_dir(path, &copy)?;
+        // the copy is removed when the log is dropped
+        let db = match sled::Config::new().path(&copy).temporary(true).open() {
+            Ok(db) => db,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&copy);
+                return Err(e.into());
+            }
+        };
+        let mut log = match passphrase {
+            Some(passphrase) => Self::new_encrypted(db, passphrase)?,
+            None => Self::new(db)?,
+        };
+        log.read_only = true;
+        Ok(log)
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Fails for a log opened read-only, checked before every change
+    pub(crate) fn check_writable(&self) -> Result<()> {
+        match self.read_only {
+            true => bail!("The log is open read-only"),
+            false => Ok(()),
+        }
+    }
+
     fn init_db(&self, header: LogHeader) -> Result<()> {
         self.set_key(b"MAGIC", VEELOG_MAGIC)?;
         self.set_key(b"INFO", "Database generated by veelog. Visit https://github.com/hf-ikea/veelog for more information.")?;
         self.set_key(b"HEADER", Self::encode_record(header)?)?;
+        self.set_key(b"LAYOUT", &[LAYOUT])?;
         self.set_idx(0) // b"INDEX"
     }
 
@@ -231,7 +1184,26 @@ impl Log {
             .get_key(b"INDEX")
             .expect("Could not get index value")
             .expect("INDEX does not exist");
-        usize::from_le_bytes(v.to_vec().try_into().expect("Invalid INDEX value"))
+        parse_idx(&v).expect("Invalid INDEX value")
+    }
+
+    /// Reserves `count` indices by atomically bumping INDEX, returning the first.
+    /// Clones of the log can do this from several threads at once.
+    fn reserve_idx(&self, count: usize) -> Result<usize> {
+        let old = self.db.fetch_and_update(b"INDEX", |v| {
+            let v = v?;
+            Some(match parse_idx(v) {
+                Some(idx) => (idx + count).to_le_bytes().to_vec(),
+                None => v.to_vec(),
+            })
+        })?;
+        match old {
+            Some(v) => match parse_idx(&v) {
+                Some(idx) => Ok(idx),
+                None => bail!("Invalid INDEX value"),
+            },
+            None => bail!("INDEX does not exist"),
+        }
     }
 
     fn set_idx(&self, idx: usize) -> Result<()> {
@@ -239,6 +1211,7 @@ impl Log {
     }
 
     fn set_key<T: Into<IVec>>(&self, key: &[u8], val: T) -> Result<()> {
+        self.check_writable()?;
         match self.db.insert(key, val) {
             Ok(_) => Ok(()),
             // unknown error in key insertion
@@ -253,112 +1226,604 @@ impl Log {
         }
     }
 
+    /// The id of the record at ordinal `idx`
+    pub fn record_id(&self, idx: usize) -> Option<RecordId> {
+        let id = self.ordinals().ok()?.get(idx.to_le_bytes()).ok()??;
+        RecordId::from_bytes(&id).ok()
+    }
+
     pub fn get_record(&self, idx: usize) -> Option<LogRecord> {
-        match self.db.get(idx.to_le_bytes()) {
-            Ok(val) => match val {
-                Some(enc) => Some(
-                    Self::decode_record::<LogRecord>(&enc)
-                        .expect(&format!("Could not decode record {}", idx)),
-                ),
-                None => None,
+        self.get_record_by_id(self.record_id(idx)?)
+    }
+
+    pub fn get_record_by_id(&self, id: RecordId) -> Option<LogRecord> {
+        let enc = self.records().ok()?.get(id.to_bytes()).ok()??;
+        Some(
+            self.decode_log_record::<LogRecord>(&enc)
+                .unwrap_or_else(|e| panic!("Could not decode record {}: {}", id, e)),
+        )
+    }
+
+    /// Appends a record to the log, returning its index
+    pub fn insert_record(&self, record: LogRecord) -> Result<usize> {
+        Ok(self.insert_records(vec![record])?.start)
+    }
+
+    /// Appends records to the log in a single transaction, returning their indices.
+    /// Either all records and their ordinals are written or nothing is, leaving the
+    /// reserved indices unused like those of deleted records. Clones of the log can
+    /// insert concurrently without locking.
+    /// Records keep an id they already carry, e.g. from an ADIF export, unless it is taken.
+    /// This is also the bulk insert for big imports, which `insert_records_batch` was.
+    pub fn insert_records(&self, records: Vec<LogRecord>) -> Result<Range<usize>> {
+        self.insert_records_at(records, Timestamp::now(), None)
+    }
+
+    /// Appends a record that came from `source`, returning its index
+    pub fn insert_record_from(&self, record: LogRecord, source: &Source) -> Result<usize> {
+        Ok(self.insert_records_from(vec![record], source)?.start)
+    }
+
+    /// Appends records like `insert_records`, noting where they came from in the same
+    /// transaction, so a record is never left without its provenance
+    pub fn insert_records_from(
+        &self,
+        records: Vec<LogRecord>,
+        source: &Source,
+    ) -> Result<Range<usize>> {
+        self.insert_records_at(records, Timestamp::now(), Some(source))
+    }
+
+    /// Inserts records with `modified` as their last change, e.g. the time a sync peer made it.
+    /// The records, their changes, ordinals and provenance are each gathered into a sled batch
+    /// that one transaction applies, which is much faster than single inserts for thousands
+    /// of records.
+    pub(crate) fn insert_records_at(
+        &self,
+        records: Vec<LogRecord>,
+        modified: Timestamp,
+        source: Option<&Source>,
+    ) -> Result<Range<usize>> {
+        self.check_writable()?;
+        let records_tree = self.records()?;
+        let ordinals = self.ordinals()?;
+        let indices = self.indices()?;
+        let idx = self.reserve_idx(records.len())?;
+        let change = Change::written(modified).to_bytes();
+        let provenance = source
+            .map(|source| self.encode_provenance(source))
+            .transpose()?;
+        loop {
+            let mut last = self.last_id()?;
+            let mut seen = HashSet::new();
+            let (mut recs, mut ords, mut chgs, mut idxs, mut provs) = (
+                Batch::default(),
+                Batch::default(),
+                Batch::default(),
+                Batch::default(),
+                Batch::default(),
+            );
+            for (i, record) in records.iter().enumerate() {
+                let mut record = record.clone();
+                let id = match record.id() {
+                    Some(id)
+                        if !seen.contains(&id) && !records_tree.contains_key(id.to_bytes())? =>
+                    {
+                        id
+                    }
+                    _ => {
+                        let id = RecordId::after(last)?;
+                        last = Some(id);
+                        id
+                    }
+                };
+                seen.insert(id);
+                record.insert_field(FieldType::RecordId, &id.to_string());
+                recs.insert(&id.to_bytes(), self.encode_log_record(record)?);
+                chgs.insert(&id.to_bytes(), &change);
+                ords.insert(&(idx + i).to_le_bytes(), &id.to_bytes());
+                idxs.insert(&id.to_bytes(), &(idx + i).to_le_bytes());
+                if let Some(provenance) = &provenance {
+                    provs.insert(&id.to_bytes(), provenance.as_slice());
+                }
+            }
+            let res = (
+                &records_tree,
+                &ordinals,
+                &self.changes()?,
+                &indices,
+                &self.provenance_tree()?,
+            )
+                .transaction(|(tx_recs, tx_ords, tx_chgs, tx_idxs, tx_provs)| {
+                    // another clone may have taken an id since it was picked
+                    for id in &seen {
+                        if tx_recs.get(id.to_bytes())?.is_some() {
+                            return abort(ID_TAKEN);
+                        }
+                    }
+                    tx_recs.apply_batch(&recs)?;
+                    tx_chgs.apply_batch(&chgs)?;
+                    tx_ords.apply_batch(&ords)?;
+                    tx_idxs.apply_batch(&idxs)?;
+                    tx_provs.apply_batch(&provs)?;
+                    Ok::<_, ConflictableTransactionError<&str>>(idx..idx + seen.len())
+                });
+            match res {
+                Ok(range) => {
+                    self.emit(range.clone().map(LogEvent::Inserted));
+                    return Ok(range);
+                }
+                // pick ids after the ones just taken and try again
+                Err(TransactionError::Abort(ID_TAKEN)) => continue,
+                Err(TransactionError::Abort(e)) => bail!(e),
+                Err(TransactionError::Storage(e)) => bail!(e),
+            }
+        }
+    }
+
+    /// Removes a record and its ordinal. The index is not reused.
+    pub fn delete_record(&self, idx: usize) -> Result<()> {
+        self.delete_record_at(idx, Timestamp::now())
+    }
+
+    pub(crate) fn delete_record_at(&self, idx: usize, modified: Timestamp) -> Result<()> {
+        self.check_writable()?;
+        let Some(id) = self.record_id(idx) else {
+            bail!("Record {} does not exist", idx)
+        };
+        let change = Change::deleted(modified).to_bytes();
+        (
+            &self.records()?,
+            &self.ordinals()?,
+            &self.changes()?,
+            &self.revisions()?,
+            &self.indices()?,
+        )
+            .transaction(|(recs, ords, chgs, revs, idxs)| {
+                recs.remove(&id.to_bytes())?;
+                ords.remove(&idx.to_le_bytes())?;
+                idxs.remove(&id.to_bytes())?;
+                revs.remove(&id.to_bytes())?;
+                // kept so a sync peer that still has the record learns it was deleted
+                chgs.insert(&id.to_bytes(), &change)?;
+                Ok::<_, ConflictableTransactionError<sled::Error>>(())
+            })?;
+        self.emit([LogEvent::Deleted(idx)]);
+        self.remove_provenance(id)?;
+        self.remove_notes(id)
+    }
+
+    /// Sets a single field of an existing record, e.g. to update its QSL status.
+    /// Changes made to the record meanwhile are kept.
+    pub fn set_field(&self, idx: usize, ty: FieldType, val: &str) -> Result<()> {
+        loop {
+            let Some((mut record, revision)) = self.get_record_revision(idx) else {
+                bail!("Record {} does not exist", idx)
+            };
+            record.insert_field(ty.clone(), val);
+            match self.modify_record_checked(idx, record, revision) {
+                Err(e) if e.is::<ConflictError>() => continue,
+                res => return res.map(|_| ()),
+            }
+        }
+    }
+
+    /// The record at `idx` with its revision, for `modify_record_checked`
+    pub fn get_record_revision(&self, idx: usize) -> Option<(LogRecord, u64)> {
+        let id = self.record_id(idx)?.to_bytes();
+        let (enc, revision) = (&self.records().ok()?, &self.revisions().ok()?)
+            .transaction(|(recs, revs)| {
+                Ok::<_, ConflictableTransactionError<()>>((recs.get(id)?, revs.get(id)?))
+            })
+            .ok()?;
+        let record = self
+            .decode_log_record::<LogRecord>(&enc?)
+            .unwrap_or_else(|e| panic!("Could not decode record {}: {}", idx, e));
+        Some((record, parse_revision(revision)))
+    }
+
+    /// Replaces the record at `idx`, keeping its id. Whatever changed since it was read is
+    /// overwritten, see `modify_record_checked`.
+    pub fn modify_record(&self, idx: usize, record: LogRecord) -> Result<()> {
+        self.modify_records(vec![(idx, record)])
+    }
+
+    /// Replaces the record at `idx` if it is still at `revision`, as read with
+    /// `get_record_revision`, returning its new revision. Fails with a `ConflictError` if
+    /// the record was changed since, so edits made at the same time are not lost.
+    pub fn modify_record_checked(
+        &self,
+        idx: usize,
+        record: LogRecord,
+        revision: u64,
+    ) -> Result<u64> {
+        let revisions =
+            self.write_records(vec![(idx, record, Some(revision))], Timestamp::now())?;
+        Ok(revisions[0])
+    }
+
+    /// Replaces several records in a single transaction, each only if it is still at the
+    /// revision given with it. Fails with the `ConflictError` of the first record changed
+    /// since, writing none of them.
+    pub(crate) fn modify_records_checked(
+        &self,
+        records: Vec<(usize, LogRecord, u64)>,
+    ) -> Result<Vec<u64>> {
+        let records = records
+            .into_iter()
+            .map(|(idx, r, revision)| (idx, r, Some(revision)))
+            .collect();
+        self.write_records(records, Timestamp::now())
+    }
+
+    /// Replaces several records in a single transaction
+    pub fn modify_records(&self, records: Vec<(usize, LogRecord)>) -> Result<()> {
+        self.modify_records_at(records, Timestamp::now())
+    }
+
+    pub(crate) fn modify_records_at(
+        &self,
+        records: Vec<(usize, LogRecord)>,
+        modified: Timestamp,
+    ) -> Result<()> {
+        let records = records.into_iter().map(|(idx, r)| (idx, r, None)).collect();
+        self.write_records(records, modified)?;
+        Ok(())
+    }
+
+    /// Replaces records in a single transaction, each only if it is at the revision given
+    /// with it, returning their new revisions
+    fn write_records(
+        &self,
+        records: Vec<(usize, LogRecord, Option<u64>)>,
+        modified: Timestamp,
+    ) -> Result<Vec<u64>> {
+        self.check_writable()?;
+        let mut entries = Vec::with_capacity(records.len());
+        for (idx, mut record, expected) in records {
+            let Some(id) = self.record_id(idx) else {
+                bail!("Record {} does not exist", idx)
+            };
+            record.insert_field(FieldType::RecordId, &id.to_string());
+            entries.push((idx, id, self.encode_log_record(record)?, expected));
+        }
+        self.write_entries(entries, modified)
+    }
+
+    /// Writes records whose ids were looked up before, failing if one was deleted since
+    fn write_entries(
+        &self,
+        entries: Vec<(usize, RecordId, Vec<u8>, Option<u64>)>,
+        modified: Timestamp,
+    ) -> Result<Vec<u64>> {
+        let change = Change::written(modified).to_bytes();
+        let res = (&self.records()?, &self.changes()?, &self.revisions()?).transaction(
+            |(recs, chgs, revs)| {
+                let mut revisions = Vec::with_capacity(entries.len());
+                for (idx, id, enc, expected) in &entries {
+                    // a deleted record has no revision left, so it would pass as revision 0
+                    let Some(current) = recs.get(id.to_bytes())? else {
+                        return abort(WriteAbort::Missing(*idx));
+                    };
+                    let revision = parse_revision(revs.get(id.to_bytes())?);
+                    if let Some(expected) = *expected
+                        && expected != revision
+                    {
+                        return abort(WriteAbort::Conflict(ConflictError {
+                            idx: *idx,
+                            expected,
+                            revision,
+                            current: self
+                                .decode_log_record(&current)
+                                .unwrap_or_else(|_| LogRecord::new()),
+                        }));
+                    }
+                    recs.insert(&id.to_bytes(), enc.as_slice())?;
+                    chgs.insert(&id.to_bytes(), &change)?;
+                    revs.insert(&id.to_bytes(), &(revision + 1).to_le_bytes())?;
+                    revisions.push(revision + 1);
+                }
+                Ok(revisions)
             },
-            Err(_) => None,
+        );
+        let revisions = match res {
+            Ok(revisions) => revisions,
+            Err(TransactionError::Abort(WriteAbort::Conflict(conflict))) => {
+                return Err(conflict.into());
+            }
+            Err(TransactionError::Abort(WriteAbort::Missing(idx))) => {
+                bail!("Record {} does not exist", idx)
+            }
+            Err(TransactionError::Storage(e)) => bail!(e),
+        };
+        self.emit(entries.iter().map(|(idx, ..)| LogEvent::Modified(*idx)));
+        Ok(revisions)
+    }
+
+    pub(crate) fn records(&self) -> Result<Tree> {
+        Ok(self.db.open_tree(RECORDS_TREE)?)
+    }
+
+    pub(crate) fn ordinals(&self) -> Result<Tree> {
+        Ok(self.db.open_tree(ORDINAL_TREE)?)
+    }
+
+    fn revisions(&self) -> Result<Tree> {
+        Ok(self.db.open_tree(REVISIONS_TREE)?)
+    }
+
+    pub(crate) fn indices(&self) -> Result<Tree> {
+        Ok(self.db.open_tree(INDICES_TREE)?)
+    }
+
+    /// The highest id in the log. Records are keyed by id, so this is the last key.
+    fn last_id(&self) -> Result<Option<RecordId>> {
+        match self.records()?.last()? {
+            Some((id, _)) => Ok(Some(RecordId::from_bytes(&id)?)),
+            None => Ok(None),
         }
     }
 
-    pub fn insert_record(&mut self, record: LogRecord) -> Result<()> {
-        let idx = self.get_idx();
-        self.modify_record(idx, record)?;
-        self.set_idx(idx + 1)?;
+    /// Brings a database written by an older version up to the current layout
+    fn migrate(&self) -> Result<()> {
+        match self.get_key(b"LAYOUT")?.as_deref() {
+            None => self.migrate_ids(),
+            Some([2]) => {
+                self.migrate_tags()?;
+                self.migrate_indices()
+            }
+            Some([3]) => self.migrate_indices(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Moves records stored directly under their index, as done before records had ids,
+    /// into the records tree
+    fn migrate_ids(&self) -> Result<()> {
+        let mut last = None;
+        let mut entries = Vec::new();
+        for idx in 0..self.get_idx() {
+            let Some(enc) = self.get_key(&idx.to_le_bytes())? else {
+                continue;
+            };
+            let mut record: LogRecord = Self::decode_record(&enc)?;
+            let id = RecordId::after(last)?;
+            last = Some(id);
+            record.insert_field(FieldType::RecordId, &id.to_string());
+            entries.push((idx, id, Self::encode_record(record)?));
+        }
+        (
+            &*self.db,
+            &self.records()?,
+            &self.ordinals()?,
+            &self.indices()?,
+        )
+            .transaction(|(meta, recs, ords, idxs)| {
+                for (idx, id, enc) in &entries {
+                    recs.insert(&id.to_bytes(), enc.as_slice())?;
+                    ords.insert(&idx.to_le_bytes(), &id.to_bytes())?;
+                    idxs.insert(&id.to_bytes(), &idx.to_le_bytes())?;
+                    meta.remove(&idx.to_le_bytes())?;
+                }
+                meta.insert(b"LAYOUT", &[LAYOUT])?;
+                Ok::<_, ConflictableTransactionError<sled::Error>>(())
+            })?;
         Ok(())
     }
 
-    pub fn modify_record(&self, idx: usize, record: LogRecord) -> Result<()> {
-        let enc = Self::encode_record(record)?;
+    /// Re-encodes records stored by `FieldType` variant index with field tags.
+    /// Records that don't decode are left alone for `verify` and `repair` to find.
+    fn migrate_tags(&self) -> Result<()> {
+        let mut entries = Vec::new();
+        for entry in self.records()?.iter() {
+            let (id, enc) = entry?;
+            if let Ok(record) = Self::decode_record::<LogRecord>(&enc) {
+                entries.push((id, Self::encode_record(record)?));
+            }
+        }
+        (&*self.db, &self.records()?).transaction(|(meta, recs)| {
+            for (id, enc) in &entries {
+                recs.insert(id, enc.as_slice())?;
+            }
+            // layout 3, `migrate_indices` brings it up to date
+            meta.insert(b"LAYOUT", &[3])?;
+            Ok::<_, ConflictableTransactionError<sled::Error>>(())
+        })?;
+        Ok(())
+    }
 
-        match self.db.insert(idx.to_le_bytes(), enc) {
-            Ok(_) => Ok(()),
-            Err(_) => todo!(), // some error in inserting to the db, this is not caused by dupes
+    /// Fills the INDICES tree from the ordinals
+    fn migrate_indices(&self) -> Result<()> {
+        let mut entries = Vec::new();
+        for entry in self.ordinals()?.iter() {
+            entries.push(entry?);
         }
+        (&*self.db, &self.indices()?).transaction(|(meta, idxs)| {
+            for (idx, id) in &entries {
+                idxs.insert(id, idx)?;
+            }
+            meta.insert(b"LAYOUT", &[LAYOUT])?;
+            Ok::<_, ConflictableTransactionError<sled::Error>>(())
+        })?;
+        Ok(())
     }
 
-    fn encode_record(record: impl Encode) -> Result<Vec<u8>> {
+    pub(crate) fn encode_record(record: impl Encode) -> Result<Vec<u8>> {
         match encode_to_vec(&record, config::standard()) {
             Ok(val) => Ok(val),
             Err(e) => Err(e.into()),
         }
     }
 
-    fn decode_record<T: bincode::de::Decode<()>>(enc: &[u8]) -> Result<T> {
+    pub(crate) fn decode_record<T: bincode::de::Decode<()>>(enc: &[u8]) -> Result<T> {
         match decode_from_slice::<T, Configuration>(enc, config::standard()) {
             Ok(val) => Ok(val.0),
             Err(e) => Err(e.into()),
         }
     }
 
+    /// Encodes a value of a tree holding personal data, like the records, notes, sessions,
+    /// provenance and upload queue, encrypted in an encrypted log
+    pub(crate) fn encode_log_record(&self, record: impl Encode) -> Result<Vec<u8>> {
+        self.encrypt(Self::encode_record(record)?)
+    }
+
+    pub(crate) fn decode_log_record<T: bincode::de::Decode<()>>(&self, enc: &[u8]) -> Result<T> {
+        Self::decode_record(&self.decrypt(enc)?)
+    }
+
     pub fn get_records(&self) -> Vec<LogRecord> {
-        let mut vec = Vec::new();
-        for i in 0..self.get_idx() {
-            match self.get_record(i) {
-                Some(v) => vec.push(v),
-                None => continue,
-            }
+        self.iter_records().collect()
+    }
+
+    /// Records in insertion order, loaded one at a time
+    pub fn iter_records(&self) -> impl DoubleEndedIterator<Item = LogRecord> + '_ {
+        (0..self.get_idx()).filter_map(|i| self.get_record(i))
+    }
+
+    /// Most recently logged records first, loaded one at a time
+    pub fn iter_records_desc(&self) -> impl Iterator<Item = LogRecord> + '_ {
+        self.iter_records().rev()
+    }
+
+    /// Indices of the records with a field containing `text`, ignoring case, oldest first.
+    /// Every field is searched: calls, names, QTH, comments and the rest.
+    pub fn search(&self, text: &str) -> Vec<usize> {
+        let text = text.trim().to_lowercase();
+        if text.is_empty() {
+            return Vec::new();
         }
-        vec
+        (0..self.get_idx())
+            .filter(|&idx| {
+                self.get_record(idx).is_some_and(|record| {
+                    record
+                        .iter()
+                        .any(|(_, val)| val.to_lowercase().contains(&text))
+                })
+            })
+            .collect()
+    }
+
+    /// The records selected by `filter` as an ADIF file
+    pub fn export_adif(&self, filter: &Filter) -> Result<ADIFFile> {
+        Ok(self.export_adif_until(filter)?.0)
+    }
+
+    /// Like `export_adif`, also returning where the export watermark moves to
+    pub(crate) fn export_adif_until(&self, filter: &Filter) -> Result<(ADIFFile, usize)> {
+        let header = ADIFHeader(vec![
+            ("ADIF_VER".to_string(), ADIFType::Str("3.1.5".to_string())),
+            ("PROGRAMID".to_string(), ADIFType::Str("veelog".to_string())),
+            (
+                "PROGRAMVERSION".to_string(),
+                ADIFType::Str(env!("CARGO_PKG_VERSION").to_string()),
+            ),
+        ]);
+        let (records, end) = self.filter_records(filter)?;
+        let equipment = EquipmentIndex::load(self)?;
+        let body = records
+            .iter()
+            .map(|r| {
+                let mut adif = r.to_adif()?;
+                adif.0.extend(equipment.adif_fields(r));
+                Ok(adif)
+            })
+            .collect::<Result<Vec<ADIFRecord>>>()?;
+        Ok((ADIFFile::new(header, body), end))
     }
 
-    pub fn import_adif_file(&mut self, path: PathBuf) -> Result<()> {
-        let data: String = fs::read_to_string(path)?;
+    /// Writes the records selected by `filter` to an ADIF file, then moves the export
+    /// watermark if the filter names one
+    pub fn export_adif_file(&self, path: PathBuf, filter: &Filter) -> Result<()> {
+        let (adif, end) = self.export_adif_until(filter)?;
+        fs::write(path, adif.serialize()?)?;
+        self.mark_exported(filter, end)
+    }
+
+    /// Imports an ADIF file whose QSO_DATE and TIME_ON are in `tz`, UTC unless the
+    /// logging program used local time
+    pub fn import_adif_file(
+        &self,
+        path: PathBuf,
+        policy: ImportPolicy,
+        tz: &TimeZone,
+    ) -> Result<()> {
+        let data: String = fs::read_to_string(&path)?;
         let adif = parse::parse_adif(&data);
 
-        self.import_adif(adif)?;
+        self.import_adif_from(adif, policy, tz, &Source::import(&path))?;
         Ok(())
     }
 
+    /// Imports ADIF records in UTC, returning their indices
+    pub fn import_adif(&self, adif: ADIFFile, policy: ImportPolicy) -> Result<Range<usize>> {
+        self.import_adif_in(adif, policy, &TimeZone::UTC)
+    }
+
+    /// Imports ADIF records whose times are in `tz`, returning their indices
+    pub fn import_adif_in(
+        &self,
+        adif: ADIFFile,
+        policy: ImportPolicy,
+        tz: &TimeZone,
+    ) -> Result<Range<usize>> {
+        self.insert_records(Self::adif_records(adif, policy, tz)?)
+    }
+
+    /// Imports ADIF records that came from `source`, see `import_adif_in`
+    pub fn import_adif_from(
+        &self,
+        adif: ADIFFile,
+        policy: ImportPolicy,
+        tz: &TimeZone,
+        source: &Source,
+    ) -> Result<Range<usize>> {
+        self.insert_records_from(Self::adif_records(adif, policy, tz)?, source)
+    }
+
     /// this function sucks
-    fn import_adif(&mut self, adif: ADIFFile) -> Result<()> {
+    /// Every record is read before any is written, so a bad record leaves the log untouched.
+    /// The records are then written with one batched transaction for speed with big files.
+    /// Times are read in `tz` and stored in UTC.
+    pub fn adif_records(
+        adif: ADIFFile,
+        policy: ImportPolicy,
+        tz: &TimeZone,
+    ) -> Result<Vec<LogRecord>> {
+        let mut records = Vec::with_capacity(adif.body.len());
         for adif_record in adif.body {
             let mut log_record = LogRecord::new();
             let mut date: Option<Date> = None;
             let mut time: Option<Time> = None;
             for (field_name, value) in adif_record {
                 let val = &value.extract_value()?;
+                // other loggers write fields they have no value for
+                if val.is_empty() {
+                    continue;
+                }
                 let field_name = field_name.as_str();
-                match field_name.get(..3) {
-                    Some("MY_") => continue,
-                    Some("SIG") => continue,
-                    Some("QSL") => continue,
-                    _ => match field_name {
-                        "STATION_CALLSIGN" => continue,
-                        "OPERATOR" => continue,
-                        "BAND" => continue,
-                        "TIME_OFF" => continue,
-                        "QSO_DATE_OFF" => continue,
-                        "TX_PWR" => continue,
-                        "SUBMODE" => continue,
-                        "FREQ" => {
-                            log_record.insert_field(
-                                FieldType::from_adif_field(&field_name),
-                                val.trim_matches('0'),
-                            );
-                        }
-                        "GRIDSQUARE" => {
-                            log_record.insert_field(
-                                FieldType::from_adif_field(&field_name),
-                                &prettyvalidate_gridsquare(val)?,
-                            );
-                        }
-                        "QSO_DATE" => match strtime::parse("%Y%m%d", val) {
-                            Ok(t) => match t.to_date() {
-                                Ok(v) => date = Some(v),
-                                Err(e) => {
-                                    bail!(util::Error::FieldParseError {
-                                        field_name: field_name.to_string(),
-                                        field_value: val.to_string(),
-                                        err: e.to_string(),
-                                    });
-                                }
-                            },
+                match field_name {
+                    // logs converted from other formats sometimes hold kHz
+                    "FREQ" | "FREQ_RX" => {
+                        log_record.insert_frequency(
+                            FieldType::from_adif_field(field_name),
+                            Frequency::parse_khz_or_mhz(val)?,
+                        );
+                    }
+                    // other loggers write units, e.g. 100W
+                    "TX_PWR" => {
+                        log_record.insert_field(FieldType::TxPower, &parse_power(val)?);
+                    }
+                    "GRIDSQUARE" => {
+                        log_record.insert_field(
+                            FieldType::from_adif_field(field_name),
+                            // other loggers' grids are kept unless they are garbled
+                            &prettyvalidate_gridsquare(val, GridStrictness::Lenient)?,
+                        );
+                    }
+                    "QSO_DATE" => match strtime::parse("%Y%m%d", val) {
+                        Ok(t) => match t.to_date() {
+                            Ok(v) => date = Some(v),
                             Err(e) => {
                                 bail!(util::Error::FieldParseError {
                                     field_name: field_name.to_string(),
@@ -367,17 +1832,17 @@ impl Log {
                                 });
                             }
                         },
-                        "TIME_ON" => match strtime::parse("%H%M%S", val) {
-                            Ok(t) => match t.to_time() {
-                                Ok(v) => time = Some(v),
-                                Err(e) => {
-                                    bail!(util::Error::FieldParseError {
-                                        field_name: field_name.to_string(),
-                                        field_value: val.to_string(),
-                                        err: e.to_string(),
-                                    });
-                                }
-                            },
+                        Err(e) => {
+                            bail!(util::Error::FieldParseError {
+                                field_name: field_name.to_string(),
+                                field_value: val.to_string(),
+                                err: e.to_string(),
+                            });
+                        }
+                    },
+                    "TIME_ON" => match strtime::parse("%H%M%S", val) {
+                        Ok(t) => match t.to_time() {
+                            Ok(v) => time = Some(v),
                             Err(e) => {
                                 bail!(util::Error::FieldParseError {
                                     field_name: field_name.to_string(),
@@ -386,26 +1851,68 @@ impl Log {
                                 });
                             }
                         },
-                        _ => {
-                            log_record.insert_field(FieldType::from_adif_field(&field_name), val);
+                        Err(e) => {
+                            bail!(util::Error::FieldParseError {
+                                field_name: field_name.to_string(),
+                                field_value: val.to_string(),
+                                err: e.to_string(),
+                            });
                         }
                     },
+                    _ => {
+                        let ty = FieldType::from_adif_field(field_name);
+                        let val = match policy {
+                            ImportPolicy::Strict if matches!(ty, FieldType::Other(_)) => {
+                                bail!(util::Error::UnsupportedField(field_name.to_string()));
+                            }
+                            ImportPolicy::Strict => ty.validate(val)?,
+                            // kept as the other logger wrote it, e.g. a CONTEST_ID we don't know
+                            ImportPolicy::PreserveAll => val.to_string(),
+                        };
+                        log_record.insert_field(ty, &val);
+                    }
                 }
             }
             if let Some(d) = date {
                 if let Some(t) = time {
-                    let ts = d
-                        .to_datetime(t)
-                        .to_zoned(TimeZone::UTC)
-                        .unwrap()
-                        .timestamp();
+                    let ts = d.to_datetime(t).to_zoned(tz.clone())?.timestamp();
                     log_record.insert_timestamp(ts);
                 }
             } else {
                 bail!("ADIF record had no date and/or time fields");
             }
-            self.insert_record(log_record)?;
+            records.push(log_record);
         }
-        Ok(())
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::Timestamp;
+
+    use super::{FieldType, LogRecord};
+    use crate::test_log;
+
+    #[test]
+    pub fn test_modify_deleted_meanwhile() {
+        let log = test_log();
+        let mut record = LogRecord::new();
+        record.insert_field(FieldType::WorkedCall, "W1AW");
+        let idx = log.insert_record(record.clone()).unwrap();
+        let id = log.record_id(idx).unwrap();
+        let enc = log.encode_log_record(record).unwrap();
+
+        // deleted after the id was looked up but before the write
+        log.delete_record(idx).unwrap();
+        for expected in [None, Some(0)] {
+            assert!(
+                log.write_entries(vec![(idx, id, enc.clone(), expected)], Timestamp::now())
+                    .is_err()
+            );
+        }
+        assert!(log.get_record_by_id(id).is_none());
+        assert_eq!(0, log.records().unwrap().len());
+        assert!(log.modify_record(idx, LogRecord::new()).is_err());
     }
 }
@@ -0,0 +1,226 @@
+use std::{
+    fs::File,
+    io::{Read, Write},
+    ops::Range,
+    path::Path,
+};
+
+use adif::data::{ADIFFile, ADIFHeader, ADIFRecord, ADIFType};
+use anyhow::{Result, bail};
+use csv::{ReaderBuilder, WriterBuilder};
+use jiff::{Timestamp, tz::TimeZone};
+
+use crate::{
+    data::{FieldType, ImportPolicy, Log},
+    filter::Filter,
+    provenance::Source,
+};
+
+/// Maps the columns of a CSV file to record fields. Columns mapped to None are skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvMapping {
+    pub columns: Vec<Option<FieldType>>,
+}
+
+impl CsvMapping {
+    /// Guesses the mapping from a header row of ADIF field names, as written by
+    /// `Log::export_csv`. QSO_DATE and TIME_ON columns are combined like in ADIF imports.
+    pub fn from_header(header: &[String]) -> Self {
+        let columns = header
+            .iter()
+            .map(|name| match name.trim().to_ascii_uppercase().as_str() {
+                "" => None,
+                name => Some(FieldType::from_export_name(name)),
+            })
+            .collect();
+        Self { columns }
+    }
+}
+
+impl Log {
+    /// Writes the records selected by `filter` as CSV, or TSV with a `b'\t'` delimiter,
+    /// with one column per field in the given order. The header row holds ADIF field names.
+    /// Moves the export watermark if the filter names one.
+    pub fn export_csv(
+        &self,
+        path: &Path,
+        columns: &[FieldType],
+        delimiter: u8,
+        filter: &Filter,
+    ) -> Result<()> {
+        let end = self.write_csv(File::create(path)?, columns, delimiter, filter)?;
+        self.mark_exported(filter, end)
+    }
+
+    /// Writes the CSV, returning where the export watermark moves to
+    pub fn write_csv(
+        &self,
+        writer: impl Write,
+        columns: &[FieldType],
+        delimiter: u8,
+        filter: &Filter,
+    ) -> Result<usize> {
+        let (records, end) = self.filter_records(filter)?;
+        let mut writer = WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_writer(writer);
+        writer.write_record(columns.iter().map(FieldType::export_name))?;
+        for record in records {
+            writer.write_record(
+                columns
+                    .iter()
+                    .map(|ty| record.get_field(ty).unwrap_or_default()),
+            )?;
+        }
+        writer.flush()?;
+        Ok(end)
+    }
+
+    /// Reads the header row of a CSV file, the first step of an import.
+    /// Build a `CsvMapping` from it, adjust it, then pass it to `import_csv`.
+    pub fn read_csv_header(path: &Path, delimiter: u8) -> Result<Vec<String>> {
+        let mut reader = ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(File::open(path)?);
+        Ok(reader.headers()?.iter().map(str::to_string).collect())
+    }
+
+    pub fn import_csv(
+        &self,
+        path: &Path,
+        delimiter: u8,
+        mapping: &CsvMapping,
+        policy: ImportPolicy,
+    ) -> Result<()> {
+        let adif = Self::csv_adif(File::open(path)?, delimiter, mapping)?;
+        self.import_adif_from(adif, policy, &TimeZone::UTC, &Source::import(path))?;
+        Ok(())
+    }
+
+    /// Imports CSV rows, skipping the header row. Rows go through the ADIF importer,
+    /// so the same validation applies and the whole file is imported atomically.
+    /// Returns the indices of the imported records.
+    pub fn read_csv(
+        &self,
+        reader: impl Read,
+        delimiter: u8,
+        mapping: &CsvMapping,
+        policy: ImportPolicy,
+    ) -> Result<Range<usize>> {
+        self.import_adif(Self::csv_adif(reader, delimiter, mapping)?, policy)
+    }
+
+    fn csv_adif(reader: impl Read, delimiter: u8, mapping: &CsvMapping) -> Result<ADIFFile> {
+        let mut reader = ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(reader);
+        let mut body = Vec::new();
+        for (row, line) in reader.records().enumerate() {
+            let line = line?;
+            let mut fields = Vec::new();
+            for (ty, value) in mapping.columns.iter().zip(line.iter()) {
+                let Some(ty) = ty else {
+                    continue;
+                };
+                if value.is_empty() {
+                    continue;
+                }
+                match ty.adif_name() {
+                    Some(name) => fields.push((name.to_string(), ADIFType::Str(value.to_string()))),
+                    None => {
+                        let Ok(ts) = value.parse::<Timestamp>() else {
+                            bail!("Invalid timestamp in row {}: {}", row + 1, value)
+                        };
+                        fields.push((
+                            "QSO_DATE".to_string(),
+                            ADIFType::Str(ts.strftime("%Y%m%d").to_string()),
+                        ));
+                        fields.push((
+                            "TIME_ON".to_string(),
+                            ADIFType::Str(ts.strftime("%H%M%S").to_string()),
+                        ));
+                    }
+                }
+            }
+            body.push(ADIFRecord(fields));
+        }
+        Ok(ADIFFile::new(ADIFHeader(Vec::new()), body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CsvMapping;
+    use crate::{
+        data::{FieldType, ImportPolicy, LogRecord},
+        filter::Filter,
+        test_log,
+    };
+
+    #[test]
+    pub fn test_csv_round_trip() {
+        let log = test_log();
+        let mut record = LogRecord::new();
+        record
+            .insert_timestamp("2025-07-28T02:48:13Z".parse().unwrap())
+            .insert_field(FieldType::WorkedCall, "W1AW")
+            .insert_field(FieldType::Frequency, "14.074")
+            .insert_field(FieldType::Comment, "op \"Joe\", nice sig");
+        log.insert_record(record).unwrap();
+
+        let columns = [
+            FieldType::WorkedCall,
+            FieldType::Timestamp,
+            FieldType::Comment,
+            FieldType::Frequency,
+        ];
+        let mut csv = Vec::new();
+        log.write_csv(&mut csv, &columns, b',', &Filter::default())
+            .unwrap();
+        assert_eq!(
+            "CALL,TIMESTAMP,COMMENT,FREQ\n\
+             W1AW,2025-07-28T02:48:13Z,\"op \"\"Joe\"\", nice sig\",14.074\n",
+            String::from_utf8(csv.clone()).unwrap()
+        );
+
+        let header = ["CALL", "TIMESTAMP", "COMMENT", "FREQ"].map(str::to_string);
+        let mapping = CsvMapping::from_header(&header);
+        assert_eq!(columns.clone().map(Some).to_vec(), mapping.columns);
+
+        let imported = test_log();
+        imported
+            .read_csv(csv.as_slice(), b',', &mapping, ImportPolicy::Strict)
+            .unwrap();
+        let record = imported.get_record(0).unwrap();
+        for ty in columns {
+            assert_eq!(
+                log.get_record(0).unwrap().get_field(&ty),
+                record.get_field(&ty)
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_tsv_mapping() {
+        let tsv = "Date\tTime\tCallsign\tNotes\n20250728\t024813\tk1abc\tignored\n";
+        let mut mapping = CsvMapping::from_header(
+            &["QSO_DATE", "time_on", "Callsign", "Notes"].map(str::to_string),
+        );
+        // the mapping step: point the unknown columns at the right fields
+        mapping.columns[2] = Some(FieldType::WorkedCall);
+        mapping.columns[3] = None;
+
+        let log = test_log();
+        log.read_csv(tsv.as_bytes(), b'\t', &mapping, ImportPolicy::Strict)
+            .unwrap();
+        let record = log.get_record(0).unwrap();
+        assert_eq!(
+            Some("k1abc".to_string()),
+            record.get_field(&FieldType::WorkedCall)
+        );
+        assert_eq!(
+            Some("2025-07-28T02:48:13Z".to_string()),
+            record.get_field(&FieldType::Timestamp)
+        );
+    }
+}
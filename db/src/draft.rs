@@ -0,0 +1,116 @@
+use std::{fs, io::ErrorKind, path::Path};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{FieldType, Log};
+
+/// The draft of an encrypted log, kept in the log instead of a file to be encrypted with it
+const DRAFT_TREE: &[u8] = b"DRAFT";
+const DRAFT_KEY: &[u8] = b"draft";
+
+/// What was typed on the entry screen but not logged yet. The UI saves it every few
+/// seconds, so a crash or power cut in the middle of a QSO doesn't lose the exchange.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Draft {
+    /// Entry fields and their text, in the order they are shown
+    pub fields: Vec<(FieldType, String)>,
+    /// Entry field the cursor was in
+    pub focused: Option<FieldType>,
+}
+
+impl Draft {
+    /// The draft saved at `path`, empty if there is none
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(data) => Ok(serde_json::from_str(&data)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Saves the draft to `path`, replacing the last one in one step so a crash while
+    /// writing keeps it whole. An empty draft removes the file.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if self.is_empty() {
+            return match fs::remove_file(path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            };
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Whether no field has text, where the cursor was doesn't matter then
+    pub fn is_empty(&self) -> bool {
+        self.fields.iter().all(|(_, text)| text.is_empty())
+    }
+}
+
+impl Log {
+    /// The draft saved in the log, empty if there is none
+    pub fn draft(&self) -> Result<Draft> {
+        match self.db.open_tree(DRAFT_TREE)?.get(DRAFT_KEY)? {
+            Some(enc) => Ok(serde_json::from_slice(&self.decrypt(&enc)?)?),
+            None => Ok(Draft::default()),
+        }
+    }
+
+    /// Saves the draft in the log, for encrypted logs whose draft must not be written
+    /// to a file in plaintext. An empty draft removes it.
+    pub fn save_draft(&self, draft: &Draft) -> Result<()> {
+        self.check_writable()?;
+        let tree = self.db.open_tree(DRAFT_TREE)?;
+        match draft.is_empty() {
+            true => tree.remove(DRAFT_KEY)?,
+            false => tree.insert(DRAFT_KEY, self.encrypt(serde_json::to_vec(draft)?)?)?,
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::Draft;
+    use crate::data::FieldType;
+
+    #[test]
+    pub fn test_draft() {
+        let dir = env::temp_dir().join(format!("veelog-tests-draft-{}", std::process::id()));
+        let path = dir.join("draft.json");
+        assert_eq!(Draft::default(), Draft::load(&path).unwrap());
+
+        let draft = Draft {
+            fields: vec![
+                (FieldType::WorkedCall, "W1AW".to_string()),
+                (FieldType::Other("MY_FIELD".into()), "x".to_string()),
+                (FieldType::Name, String::new()),
+            ],
+            focused: Some(FieldType::Name),
+        };
+        assert!(!draft.is_empty());
+        draft.save(&path).unwrap();
+        assert_eq!(draft, Draft::load(&path).unwrap());
+
+        let empty = Draft {
+            fields: vec![(FieldType::WorkedCall, String::new())],
+            focused: Some(FieldType::WorkedCall),
+        };
+        assert!(empty.is_empty());
+        empty.save(&path).unwrap();
+        assert!(!path.exists());
+        assert_eq!(Draft::default(), Draft::load(&path).unwrap());
+        // nothing to remove
+        empty.save(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
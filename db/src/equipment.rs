@@ -0,0 +1,260 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    str::FromStr,
+};
+
+use adif::data::ADIFType;
+use anyhow::{Result, bail};
+use bincode::{Decode, Encode};
+
+use crate::{
+    data::{FieldType, Log, LogRecord},
+    session::{Session, SessionId},
+};
+
+/// Registered equipment keyed by big endian `EquipmentId`, encrypted in an encrypted log
+const EQUIPMENT_TREE: &[u8] = b"EQUIPMENT";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Encode, Decode)]
+pub enum EquipmentKind {
+    Rig,
+    Antenna,
+    Amplifier,
+}
+
+/// Identity of a piece of equipment, so it can be renamed without touching the QSOs made with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Encode, Decode)]
+pub struct EquipmentId(u64);
+
+impl Display for EquipmentId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for EquipmentId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self(s.trim().parse()?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct Equipment {
+    pub kind: EquipmentKind,
+    /// As exported to ADIF, e.g. `IC-7300` or `EFHW 40-10m`
+    pub name: String,
+    pub description: String,
+}
+
+impl Equipment {
+    pub fn new(kind: EquipmentKind, name: &str) -> Self {
+        Self {
+            kind,
+            name: name.trim().to_string(),
+            description: String::new(),
+        }
+    }
+}
+
+/// The value of `FieldType::Equipment`: ids separated by commas
+pub fn format_equipment_ids(ids: &[EquipmentId]) -> String {
+    ids.iter()
+        .map(EquipmentId::to_string)
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// The ids in a `FieldType::Equipment` value, skipping any that don't parse
+pub fn parse_equipment_ids(v: &str) -> Vec<EquipmentId> {
+    v.split(',').filter_map(|id| id.parse().ok()).collect()
+}
+
+/// The registry and sessions loaded once, to resolve the equipment of many QSOs
+pub(crate) struct EquipmentIndex {
+    equipment: BTreeMap<EquipmentId, Equipment>,
+    sessions: HashMap<SessionId, Session>,
+}
+
+impl EquipmentIndex {
+    pub(crate) fn load(log: &Log) -> Result<Self> {
+        Ok(Self {
+            equipment: log.equipment()?,
+            sessions: log.sessions()?.into_iter().collect(),
+        })
+    }
+
+    /// The equipment a QSO was made with: its own references, else its session's
+    fn ids(&self, record: &LogRecord) -> Vec<EquipmentId> {
+        if let Some(v) = record.get_field(&FieldType::Equipment) {
+            return parse_equipment_ids(&v);
+        }
+        record
+            .get_field(&FieldType::Session)
+            .and_then(|id| id.parse().ok())
+            .and_then(|id| self.sessions.get(&id))
+            .map(|session| session.equipment.clone())
+            .unwrap_or_default()
+    }
+
+    /// MY_RIG and MY_ANTENNA for a QSO that doesn't have them yet. Amplifiers go into
+    /// MY_RIG after the rig, e.g. `IC-7300 + KPA500`.
+    pub(crate) fn adif_fields(&self, record: &LogRecord) -> Vec<(String, ADIFType)> {
+        let equipment: Vec<&Equipment> = self
+            .ids(record)
+            .iter()
+            .filter_map(|id| self.equipment.get(id))
+            .collect();
+        let names = |kinds: &[EquipmentKind]| {
+            let mut found: Vec<&&Equipment> = equipment
+                .iter()
+                .filter(|e| kinds.contains(&e.kind))
+                .collect();
+            found.sort_by_key(|e| e.kind);
+            found
+                .iter()
+                .map(|e| e.name.as_str())
+                .collect::<Vec<&str>>()
+                .join(" + ")
+        };
+        let mut fields = Vec::new();
+        for (name, kinds) in [
+            (
+                "MY_RIG",
+                [EquipmentKind::Rig, EquipmentKind::Amplifier].as_slice(),
+            ),
+            ("MY_ANTENNA", [EquipmentKind::Antenna].as_slice()),
+        ] {
+            let value = names(kinds);
+            if !value.is_empty()
+                && record
+                    .get_field(&FieldType::from_adif_field(name))
+                    .is_none()
+            {
+                fields.push((name.to_string(), ADIFType::Str(value)));
+            }
+        }
+        fields
+    }
+}
+
+impl Log {
+    /// Adds a rig, antenna or amplifier to the registry
+    pub fn add_equipment(&self, equipment: Equipment) -> Result<EquipmentId> {
+        self.check_writable()?;
+        let id = EquipmentId(self.db.generate_id()?);
+        self.db
+            .open_tree(EQUIPMENT_TREE)?
+            .insert(id.0.to_be_bytes(), self.encode_log_record(equipment)?)?;
+        Ok(id)
+    }
+
+    /// Renames or redescribes registered equipment. QSOs refer to it by id and follow along.
+    pub fn update_equipment(&self, id: EquipmentId, equipment: Equipment) -> Result<()> {
+        self.check_writable()?;
+        let tree = self.db.open_tree(EQUIPMENT_TREE)?;
+        if !tree.contains_key(id.0.to_be_bytes())? {
+            bail!("Equipment {} does not exist", id)
+        }
+        tree.insert(id.0.to_be_bytes(), self.encode_log_record(equipment)?)?;
+        Ok(())
+    }
+
+    /// Every registered piece of equipment, in the order it was added
+    pub fn equipment(&self) -> Result<BTreeMap<EquipmentId, Equipment>> {
+        let mut equipment = BTreeMap::new();
+        for entry in self.db.open_tree(EQUIPMENT_TREE)?.iter() {
+            let (key, enc) = entry?;
+            let id = EquipmentId(u64::from_be_bytes(key.as_ref().try_into()?));
+            equipment.insert(id, self.decode_log_record(&enc)?);
+        }
+        Ok(equipment)
+    }
+
+    /// QSOs made with each registered piece of equipment, e.g. to compare antennas.
+    /// QSOs without equipment of their own count for their session's.
+    pub fn equipment_stats(&self) -> Result<BTreeMap<EquipmentId, usize>> {
+        let index = EquipmentIndex::load(self)?;
+        let mut stats: BTreeMap<EquipmentId, usize> =
+            index.equipment.keys().map(|id| (*id, 0)).collect();
+        for record in self.iter_records() {
+            for id in index.ids(&record) {
+                if let Some(n) = stats.get_mut(&id) {
+                    *n += 1;
+                }
+            }
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use adif::data::ADIFType;
+
+    use super::{Equipment, EquipmentKind, format_equipment_ids};
+    use crate::{
+        data::{FieldType, LogRecord},
+        filter::Filter,
+        session::Session,
+        test_log,
+    };
+
+    #[test]
+    pub fn test_equipment() {
+        let log = test_log();
+        let rig = log
+            .add_equipment(Equipment::new(EquipmentKind::Rig, "IC-7300"))
+            .unwrap();
+        let amp = log
+            .add_equipment(Equipment::new(EquipmentKind::Amplifier, "KPA500"))
+            .unwrap();
+        let dipole = log
+            .add_equipment(Equipment::new(EquipmentKind::Antenna, "Dipole"))
+            .unwrap();
+        let vertical = log
+            .add_equipment(Equipment::new(EquipmentKind::Antenna, "Vertical"))
+            .unwrap();
+        assert_eq!(4, log.equipment().unwrap().len());
+
+        let session = log
+            .start_session(Session {
+                equipment: vec![rig, vertical],
+                ..Default::default()
+            })
+            .unwrap();
+        let mut own = LogRecord::new();
+        own.insert_field(
+            FieldType::Equipment,
+            &format_equipment_ids(&[amp, rig, dipole]),
+        );
+        let mut in_session = LogRecord::new();
+        in_session.insert_field(FieldType::Session, &session.to_string());
+        log.insert_records(vec![own, in_session, LogRecord::new()])
+            .unwrap();
+
+        let stats = log.equipment_stats().unwrap();
+        assert_eq!(
+            vec![(rig, 2), (amp, 1), (dipole, 1), (vertical, 1)],
+            stats.into_iter().collect::<Vec<_>>()
+        );
+
+        log.update_equipment(dipole, Equipment::new(EquipmentKind::Antenna, "80m Dipole"))
+            .unwrap();
+        let adif = log.export_adif(&Filter::default()).unwrap();
+        let field = |i: usize, name: &str| {
+            adif.body[i]
+                .0
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.clone())
+        };
+        let str = |v: &str| Some(ADIFType::Str(v.to_string()));
+        assert_eq!(str("IC-7300 + KPA500"), field(0, "MY_RIG"));
+        assert_eq!(str("80m Dipole"), field(0, "MY_ANTENNA"));
+        assert_eq!(str("Vertical"), field(1, "MY_ANTENNA"));
+        assert_eq!(None, field(2, "MY_RIG"));
+    }
+}
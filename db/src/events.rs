@@ -0,0 +1,74 @@
+use std::sync::{
+    Arc, Mutex,
+    mpsc::{self, Receiver, Sender},
+};
+
+use crate::data::Log;
+
+/// A change to the records of a log, by record index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogEvent {
+    Inserted(usize),
+    Modified(usize),
+    Deleted(usize),
+}
+
+/// Senders of the receivers handed out by `Log::subscribe`, shared by all clones of a log
+pub(crate) type Subscribers = Arc<Mutex<Vec<Sender<LogEvent>>>>;
+
+impl Log {
+    /// Receives an event for every record inserted, modified or deleted through this log
+    /// or any of its clones once the write is done. Dropping the receiver unsubscribes.
+    pub fn subscribe(&self) -> Receiver<LogEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    pub(crate) fn emit(&self, events: impl IntoIterator<Item = LogEvent>) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if subscribers.is_empty() {
+            return;
+        }
+        for event in events {
+            subscribers.retain(|tx| tx.send(event).is_ok());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        data::{FieldType, LogRecord},
+        test_log,
+    };
+
+    use super::LogEvent;
+
+    #[test]
+    pub fn test_subscribe() {
+        let log = test_log();
+        log.insert_record(LogRecord::new()).unwrap();
+        let events = log.subscribe();
+        let dropped = log.subscribe();
+        drop(dropped);
+
+        let clone = log.clone();
+        clone
+            .insert_records(vec![LogRecord::new(), LogRecord::new()])
+            .unwrap();
+        log.set_field(1, FieldType::WorkedCall, "W1AW").unwrap();
+        log.delete_record(0).unwrap();
+        assert!(log.delete_record(0).is_err());
+        assert_eq!(
+            vec![
+                LogEvent::Inserted(1),
+                LogEvent::Inserted(2),
+                LogEvent::Modified(1),
+                LogEvent::Deleted(0),
+            ],
+            events.try_iter().collect::<Vec<LogEvent>>()
+        );
+        assert_eq!(1, log.subscribers.lock().unwrap().len());
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::Result;
+use jiff::Timestamp;
+use util::{band::Band, dxcc::PrefixDb};
+
+use crate::{
+    country::CountryCache,
+    data::{FieldType, Log, LogRecord},
+    session::SessionId,
+};
+
+/// Export watermarks keyed by export name, each the log index the last export went up to
+const EXPORTS_TREE: &[u8] = b"EXPORTS";
+
+/// Selects the records an export writes. The default selects every record.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Filter {
+    /// Only QSOs at or after this time
+    pub start: Option<Timestamp>,
+    /// Only QSOs before this time
+    pub end: Option<Timestamp>,
+    /// Only QSOs on these bands, any band if empty
+    pub bands: Vec<Band>,
+    /// Only QSOs in these modes, any mode if empty. Matches the ADIF mode or submode,
+    /// so both "MFSK" and "FT4" select a MFSK/FT4 QSO.
+    pub modes: Vec<String>,
+    /// Only QSOs logged since the last export with this name, e.g. "lotw".
+    /// Exporting moves the watermark, so the next export starts after this one.
+    pub since_export: Option<String>,
+    /// Only QSOs logged during this session, e.g. for a POTA activation upload
+    pub session: Option<SessionId>,
+    /// Only QSOs with stations on these continents, e.g. "EU", any continent if empty
+    pub continents: Vec<String>,
+    /// Indices of records left out, e.g. those `Log::check_export` found problems with
+    pub exclude: Vec<usize>,
+}
+
+impl Filter {
+    /// Whether the record passes the time, band, mode, session and continent checks.
+    /// Continents are only known from the CONT field, see `matches_with`.
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        self.matches_with(record, &CountryCache::default(), None)
+    }
+
+    /// Like `matches`, looking up the continent of QSOs without a CONT field in `prefixes`
+    pub fn matches_with(
+        &self,
+        record: &LogRecord,
+        countries: &CountryCache,
+        prefixes: Option<&PrefixDb>,
+    ) -> bool {
+        if let Some(id) = self.session
+            && record.get_field(&FieldType::Session) != Some(id.to_string())
+        {
+            return false;
+        }
+        if self.start.is_some() || self.end.is_some() {
+            let Some(time) = record
+                .get_field(&FieldType::Timestamp)
+                .and_then(|t| t.parse::<Timestamp>().ok())
+            else {
+                return false;
+            };
+            if self.start.is_some_and(|start| time < start)
+                || self.end.is_some_and(|end| time >= end)
+            {
+                return false;
+            }
+        }
+        if !self.bands.is_empty()
+            && !record
+                .frequency()
+                .and_then(Band::from_freq)
+                .is_some_and(|band| self.bands.contains(&band))
+        {
+            return false;
+        }
+        if !self.modes.is_empty() {
+            let mode = record.get_field(&FieldType::Mode);
+            let submode = record.get_field(&FieldType::Submode);
+            if !self.modes.iter().any(|m| {
+                [&mode, &submode]
+                    .into_iter()
+                    .flatten()
+                    .any(|v| v.eq_ignore_ascii_case(m))
+            }) {
+                return false;
+            }
+        }
+        if !self.continents.is_empty()
+            && !countries.country(record, prefixes).is_some_and(|country| {
+                self.continents
+                    .iter()
+                    .any(|c| c.eq_ignore_ascii_case(&country.continent))
+            })
+        {
+            return false;
+        }
+        true
+    }
+}
+
+impl Log {
+    /// The log index the last export named `name` went up to, None if there was none
+    pub fn last_export(&self, name: &str) -> Result<Option<usize>> {
+        let v = self.db.open_tree(EXPORTS_TREE)?.get(name)?;
+        Ok(v.and_then(|v| v.as_ref().try_into().ok().map(usize::from_le_bytes)))
+    }
+
+    /// Records selected by `filter` in insertion order, with the index the export
+    /// watermark moves to once they are written
+    pub fn filter_records(&self, filter: &Filter) -> Result<(Vec<LogRecord>, usize)> {
+        let (records, end) = self.filter_indexed(filter)?;
+        Ok((records.into_iter().map(|(_, record)| record).collect(), end))
+    }
+
+    /// Like `filter_records`, with the log index of each record
+    pub(crate) fn filter_indexed(
+        &self,
+        filter: &Filter,
+    ) -> Result<(Vec<(usize, LogRecord)>, usize)> {
+        let end = self.get_idx();
+        let start = match &filter.since_export {
+            Some(name) => self.last_export(name)?.unwrap_or(0),
+            None => 0,
+        };
+        let records = (start..end)
+            .filter(|idx| !filter.exclude.contains(idx))
+            .filter_map(|idx| Some((idx, self.get_record(idx)?)))
+            .filter(|(_, record)| filter.matches(record))
+            .collect();
+        Ok((records, end))
+    }
+
+    /// Moves the watermark of `filter.since_export`, if set, to `end`
+    pub(crate) fn mark_exported(&self, filter: &Filter, end: usize) -> Result<()> {
+        if let Some(name) = &filter.since_export {
+            self.db
+                .open_tree(EXPORTS_TREE)?
+                .insert(name, &end.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use util::{band::Band, dxcc::PrefixDb};
+
+    use super::Filter;
+    use crate::{
+        country::CountryCache,
+        data::{FieldType, LogRecord},
+        test_log,
+    };
+
+    fn qso(time: &str, freq: &str, mode: &str) -> LogRecord {
+        let mut record = LogRecord::new();
+        record
+            .insert_timestamp(time.parse().unwrap())
+            .insert_field(FieldType::Frequency, freq)
+            .insert_field(FieldType::Mode, mode);
+        record
+    }
+
+    #[test]
+    pub fn test_filter() {
+        let log = test_log();
+        log.insert_records(vec![
+            qso("2025-07-01T12:00:00Z", "50.313", "FT8"),
+            qso("2025-07-02T12:00:00Z", "14.025", "CW"),
+            qso("2025-07-03T12:00:00Z", "50.150", "SSB"),
+        ])
+        .unwrap();
+        let freqs = |filter: &Filter| {
+            let (records, _) = log.filter_records(filter).unwrap();
+            records
+                .iter()
+                .map(|r| r.get_field(&FieldType::Frequency).unwrap())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(3, freqs(&Filter::default()).len());
+        let six_ft8 = Filter {
+            bands: vec![Band::M6],
+            modes: vec!["ft8".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(vec!["50.313"], freqs(&six_ft8));
+        let july_2 = Filter {
+            start: Some("2025-07-02T00:00:00Z".parse().unwrap()),
+            end: Some("2025-07-03T00:00:00Z".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(vec!["14.025"], freqs(&july_2));
+        let mut europe = Filter {
+            continents: vec!["eu".to_string()],
+            ..Default::default()
+        };
+        assert!(freqs(&europe).is_empty());
+        let mut record = qso("2025-07-02T12:00:00Z", "14.025", "CW");
+        record.insert_field(FieldType::WorkedCall, "DL1ABC");
+        let prefixes =
+            PrefixDb::parse("Germany: 14: 28: EU: 51.00: -10.00: -1.0: DL:\n DL;").unwrap();
+        let countries = CountryCache::default();
+        assert!(!europe.matches(&record));
+        assert!(europe.matches_with(&record, &countries, Some(&prefixes)));
+        europe.continents = vec!["NA".to_string(), "AS".to_string()];
+        assert!(!europe.matches_with(&record, &countries, Some(&prefixes)));
+
+        let lotw = Filter {
+            since_export: Some("lotw".to_string()),
+            ..Default::default()
+        };
+        let (records, end) = log.filter_records(&lotw).unwrap();
+        assert_eq!(3, records.len());
+        log.mark_exported(&lotw, end).unwrap();
+        assert_eq!(Some(3), log.last_export("lotw").unwrap());
+        assert!(freqs(&lotw).is_empty());
+        log.insert_record(qso("2025-07-04T12:00:00Z", "7.074", "FT8"))
+            .unwrap();
+        assert_eq!(vec!["7.074"], freqs(&lotw));
+        assert_eq!(None, log.last_export("clublog").unwrap());
+    }
+}
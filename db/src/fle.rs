@@ -0,0 +1,341 @@
+use std::{fs, path::Path};
+
+use adif::data::{ADIFFile, ADIFHeader, ADIFRecord, ADIFType};
+use anyhow::{Context, Result, bail};
+use jiff::{
+    SignedDuration, ToSpan,
+    civil::{Date, DateTime, Time},
+    tz::TimeZone,
+};
+use util::{band::Band, callsign, freq::Frequency, mode::ModeClass};
+
+use crate::{
+    data::{ImportPolicy, Log},
+    provenance::Source,
+};
+
+/// Modes FLE accepts, with the ADIF mode and submode each is logged as
+const MODES: &[(&str, &str, Option<&str>)] = &[
+    ("CW", "CW", None),
+    ("SSB", "SSB", None),
+    ("AM", "AM", None),
+    ("FM", "FM", None),
+    ("RTTY", "RTTY", None),
+    ("PSK", "PSK", Some("PSK31")),
+    ("FT8", "FT8", None),
+    ("FT4", "MFSK", Some("FT4")),
+    ("JT65", "JT65", None),
+    ("JT9", "JT9", None),
+    ("DV", "DIGITALVOICE", None),
+];
+
+/// Header keywords and the ADIF field each sets on every following QSO
+const KEYWORDS: &[(&str, &str)] = &[
+    ("MYCALL", "STATION_CALLSIGN"),
+    ("OPERATOR", "OPERATOR"),
+    ("MYGRID", "MY_GRIDSQUARE"),
+    ("MYSOTA", "MY_SOTA_REF"),
+    ("MYPOTA", "MY_POTA_REF"),
+    ("MYWWFF", "MY_WWFF_REF"),
+    ("QSLMSG", "QSLMSG"),
+];
+
+/// A QSO line with the time it was logged at, before interpolation
+struct FleQso {
+    fields: Vec<(String, ADIFType)>,
+    at: DateTime,
+    /// Whether the time was typed for this QSO, as opposed to carried over
+    timed: bool,
+}
+
+fn field(name: &str, value: &str) -> (String, ADIFType) {
+    (name.to_string(), ADIFType::Str(value.to_string()))
+}
+
+/// Applies a typed time to the current one: `1432` sets it, `32` the minutes
+/// and `2` the last digit of the minutes. Minutes earlier than the current time
+/// carry the hour, or the tens of the minutes, forward: `03` after 14:58 is 15:03.
+fn parse_time(token: &str, current: Option<Time>) -> Result<Time> {
+    let digits = |s: &str| s.parse::<i8>().ok();
+    let (time, carry) = match (token.len(), current) {
+        (4, _) => (digits(&token[..2]).zip(digits(&token[2..])), 0.hours()),
+        (2, Some(t)) => (Some(t.hour()).zip(digits(token)), 1.hour()),
+        (1, Some(t)) => (
+            Some(t.hour()).zip(digits(token).map(|m| t.minute() / 10 * 10 + m)),
+            10.minutes(),
+        ),
+        _ => (None, 0.hours()),
+    };
+    let time = time.and_then(|(h, m)| Time::new(h, m, 0, 0).ok());
+    match (time, current) {
+        (Some(time), Some(t)) if time < t => Ok(time.wrapping_add(carry)),
+        (Some(time), _) => Ok(time),
+        (None, _) => bail!("{} is not a time", token),
+    }
+}
+
+fn is_time(token: &str) -> bool {
+    matches!(token.len(), 1 | 2 | 4) && token.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Parses a log in Fast Log Entry (FLE) text format into ADIF records. Band, mode and
+/// header keywords carry over to the following QSOs. QSOs logged without a time of their
+/// own are spread evenly up to the next typed time.
+pub fn parse_fle(text: &str) -> Result<ADIFFile> {
+    let mut header: Vec<(String, ADIFType)> = Vec::new();
+    let mut date: Option<Date> = None;
+    let mut time: Option<Time> = None;
+    let mut timed = false;
+    let mut band: Option<Band> = None;
+    let mut freq: Option<Frequency> = None;
+    let mut mode: Option<(&str, &str, Option<&str>)> = None;
+    let mut qsos = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line_err = || format!("Line {}: {}", n + 1, line.trim());
+        // <comment> may contain spaces, so take it out before splitting
+        let (line, comment) = match (line.find('<'), line.rfind('>')) {
+            (Some(start), Some(end)) if start < end => (
+                format!("{} {}", &line[..start], &line[end + 1..]),
+                Some(line[start + 1..end].trim().to_string()),
+            ),
+            _ => (line.to_string(), None),
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some(first) = tokens.first() else {
+            continue;
+        };
+        let keyword = first.to_ascii_uppercase();
+        if let Some((_, adif)) = KEYWORDS.iter().find(|(k, _)| *k == keyword) {
+            let value = match *adif {
+                "QSLMSG" => tokens[1..].join(" "),
+                _ => tokens[1..].join(" ").to_ascii_uppercase(),
+            };
+            header.retain(|(name, _)| name != adif);
+            header.push(field(adif, &value));
+            continue;
+        }
+        match keyword.as_str() {
+            // names the output file in other FLE tools
+            "NICKNAME" => continue,
+            "DATE" => {
+                let value = tokens.get(1).unwrap_or(&"").replace('/', "-");
+                date = Some(value.parse().with_context(line_err)?);
+                continue;
+            }
+            "DAY" => {
+                let days = tokens.get(1).map_or(0, |v| v.matches('+').count());
+                let Some(d) = date else {
+                    bail!("{}: day without a date before it", line_err());
+                };
+                date = Some(d.checked_add((days as i64).days())?);
+                continue;
+            }
+            _ => {}
+        }
+        if let Ok(d) = first.replace('/', "-").parse::<Date>() {
+            date = Some(d);
+            continue;
+        }
+
+        let mut fields = Vec::new();
+        let mut call: Option<String> = None;
+        let mut reports = Vec::new();
+        for (i, token) in tokens.iter().enumerate() {
+            let upper = token.to_ascii_uppercase();
+            if i == 0 && is_time(token) {
+                let typed = parse_time(token, time).with_context(line_err)?;
+                // minutes carried past midnight are on the next day
+                if token.len() < 4
+                    && time.is_some_and(|t| typed < t)
+                    && let Some(d) = date
+                {
+                    date = Some(d.tomorrow()?);
+                }
+                time = Some(typed);
+                timed = true;
+            } else if let Some(b) = Band::from_name(token) {
+                band = Some(b);
+                freq = None;
+            } else if let Some(m) = MODES.iter().find(|m| m.0 == upper) {
+                mode = Some(*m);
+            } else if call.is_none()
+                && token.contains('.')
+                && let Ok(f) = Frequency::parse_mhz(token)
+                && let Some(b) = Band::from_freq(f)
+            {
+                band = Some(b);
+                freq = Some(f);
+            } else if let Some(name) = token.strip_prefix('@') {
+                fields.push(field("NAME", name));
+            } else if let Some(grid) = token.strip_prefix('#') {
+                fields.push(field("GRIDSQUARE", grid));
+            } else if call.is_none()
+                && let Ok(c) = callsign::validate_callsign(token)
+            {
+                call = Some(c);
+            } else if call.is_some() && reports.len() < 2 {
+                reports.push(token.to_string());
+            } else {
+                bail!("{}: cannot read {}", line_err(), token);
+            }
+        }
+        let Some(call) = call else {
+            if comment.is_some() || !fields.is_empty() || !reports.is_empty() {
+                bail!("{}: no call", line_err());
+            }
+            continue;
+        };
+        let (Some(d), Some(t)) = (date, time) else {
+            bail!("{}: no date and time before the first QSO", line_err());
+        };
+        let Some(b) = band else {
+            bail!("{}: no band before the first QSO", line_err());
+        };
+        let Some((_, adif_mode, submode)) = mode else {
+            bail!("{}: no mode before the first QSO", line_err());
+        };
+        // one report is the one received, two are sent and received
+        let rst = ModeClass::from_mode(adif_mode).default_rst().to_string();
+        let (sent, rcvd) = match reports.as_slice() {
+            [rcvd] => (rst, rcvd.clone()),
+            [sent, rcvd] => (sent.clone(), rcvd.clone()),
+            _ => (rst.clone(), rst),
+        };
+        fields.extend([
+            field("CALL", &call),
+            field("BAND", b.name()),
+            field("MODE", adif_mode),
+            field("RST_SENT", &sent),
+            field("RST_RCVD", &rcvd),
+        ]);
+        if let Some(submode) = submode {
+            fields.push(field("SUBMODE", submode));
+        }
+        if let Some(f) = freq {
+            fields.push(field("FREQ", &f.to_string()));
+        }
+        if let Some(comment) = comment {
+            fields.push(field("COMMENT", &comment));
+        }
+        fields.extend(header.iter().cloned());
+        qsos.push(FleQso {
+            fields,
+            at: d.to_datetime(t),
+            timed,
+        });
+        timed = false;
+    }
+
+    let mut body = Vec::with_capacity(qsos.len());
+    let mut start = 0;
+    while start < qsos.len() {
+        let end = (start + 1..qsos.len())
+            .find(|&i| qsos[i].timed)
+            .unwrap_or(qsos.len());
+        let from = qsos[start].at;
+        let step = match qsos.get(end) {
+            Some(next) if next.at > from => from.duration_until(next.at) / (end - start) as i32,
+            _ => SignedDuration::ZERO,
+        };
+        for (k, qso) in qsos[start..end].iter().enumerate() {
+            let at = from.checked_add(step * k as i32)?;
+            let mut fields = qso.fields.clone();
+            fields.push(field("QSO_DATE", &at.strftime("%Y%m%d").to_string()));
+            fields.push(field("TIME_ON", &at.strftime("%H%M00").to_string()));
+            body.push(ADIFRecord(fields));
+        }
+        start = end;
+    }
+    Ok(ADIFFile::new(ADIFHeader(Vec::new()), body))
+}
+
+impl Log {
+    /// Imports a paper log typed up in FLE format, see `parse_fle`.
+    /// The whole file is imported atomically, like an ADIF import.
+    pub fn import_fle(&self, path: &Path, policy: ImportPolicy) -> Result<()> {
+        let adif = parse_fle(&fs::read_to_string(path)?)?;
+        self.import_adif_from(adif, policy, &TimeZone::UTC, &Source::import(path))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_fle;
+    use crate::{
+        data::{FieldType, ImportPolicy},
+        test_log,
+    };
+
+    #[test]
+    pub fn test_fle_import() {
+        let fle = "mycall DL1ABC/p\n\
+                   mysota dm/ba-001\n\
+                   date 2025-07-28\n\
+                   40m cw\n\
+                   0930 w1aw 579\n\
+                   g4xyz 559 449 <weak, qsb>\n\
+                   k1abc @Joe\n\
+                   36 20m 14.062 f5abc\n\
+                   38 20m ssb ea3xyz 57 #jn11\n\
+                   day +\n\
+                   0010 ok1abc\n";
+        let adif = parse_fle(fle).unwrap();
+        assert_eq!(6, adif.body.len());
+        let get = |i: usize, name: &str| {
+            adif.body[i]
+                .0
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.extract_value().unwrap())
+        };
+        let times: Vec<String> = (0..6).map(|i| get(i, "TIME_ON").unwrap()).collect();
+        assert_eq!(
+            vec!["093000", "093200", "093400", "093600", "093800", "001000"],
+            times
+        );
+        assert_eq!(Some("20250729".to_string()), get(5, "QSO_DATE"));
+        assert_eq!(Some("599".to_string()), get(0, "RST_SENT"));
+        assert_eq!(Some("579".to_string()), get(0, "RST_RCVD"));
+        assert_eq!(Some("449".to_string()), get(1, "RST_RCVD"));
+        assert_eq!(Some("weak, qsb".to_string()), get(1, "COMMENT"));
+        assert_eq!(Some("Joe".to_string()), get(2, "NAME"));
+        assert_eq!(Some("14.062".to_string()), get(3, "FREQ"));
+        assert_eq!(Some("SSB".to_string()), get(4, "MODE"));
+        assert_eq!(Some("59".to_string()), get(4, "RST_SENT"));
+        assert_eq!(None, get(4, "FREQ"));
+        assert_eq!(Some("20m".to_string()), get(5, "BAND"));
+        assert_eq!(Some("DM/BA-001".to_string()), get(5, "MY_SOTA_REF"));
+
+        // partial times earlier than the last one carry the hour forward
+        let carried = parse_fle(
+            "date 2025-07-28\n40m cw\n2358 w1aw\n02 g4xyz\n6 k1abc\n4 f5abc\n1014 ea3xyz\n",
+        )
+        .unwrap();
+        let get_carried = |i: usize, name: &str| {
+            carried.body[i]
+                .0
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.extract_value().unwrap())
+        };
+        let times: Vec<String> = (0..5).map(|i| get_carried(i, "TIME_ON").unwrap()).collect();
+        assert_eq!(
+            vec!["235800", "000200", "000600", "001400", "101400"],
+            times
+        );
+        assert_eq!(Some("20250729".to_string()), get_carried(1, "QSO_DATE"));
+        assert_eq!(Some("20250729".to_string()), get_carried(4, "QSO_DATE"));
+
+        assert!(parse_fle("40m cw\n0930 w1aw").is_err());
+        assert!(parse_fle("date 2025-07-28\n0930 w1aw").is_err());
+
+        let log = test_log();
+        log.import_adif(adif, ImportPolicy::PreserveAll).unwrap();
+        let record = log.get_record(1).unwrap();
+        assert_eq!(
+            Some("2025-07-28T09:32:00Z".to_string()),
+            record.get_field(&FieldType::Timestamp)
+        );
+    }
+}
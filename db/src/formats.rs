@@ -0,0 +1,345 @@
+use std::{fs::File, path::Path};
+
+use anyhow::{Result, anyhow};
+use jiff::tz::TimeZone;
+
+use crate::{
+    data::{FieldType, ImportPolicy, Log},
+    delimited::CsvMapping,
+    filter::Filter,
+};
+
+/// A file format QSOs can be read from
+pub trait LogImporter: Sync {
+    /// Name shown to the user, e.g. `ADIF`
+    fn name(&self) -> &'static str;
+    /// File extensions the format is recognized by, lowercase and without the dot
+    fn extensions(&self) -> &'static [&'static str];
+    /// Imports the file in one transaction, a bad record leaves the log untouched.
+    /// Times without a zone, as in ADIF, are read in `tz`.
+    fn import(&self, log: &Log, path: &Path, policy: ImportPolicy, tz: &TimeZone) -> Result<()>;
+}
+
+/// A file format QSOs can be written to
+pub trait LogExporter: Sync {
+    /// Name shown to the user, e.g. `ADIF`
+    fn name(&self) -> &'static str;
+    /// File extensions of the format, the first is used for new files
+    fn extensions(&self) -> &'static [&'static str];
+    /// Writes the records `filter` selects, then moves the export watermark if the filter
+    /// names one
+    fn export(&self, log: &Log, path: &Path, filter: &Filter) -> Result<()>;
+}
+
+struct Adif;
+struct Adx;
+struct Cabrillo;
+struct Json;
+struct Fle;
+struct Delimited {
+    name: &'static str,
+    extensions: &'static [&'static str],
+    delimiter: u8,
+}
+
+const CSV: Delimited = Delimited {
+    name: "CSV",
+    extensions: &["csv"],
+    delimiter: b',',
+};
+const TSV: Delimited = Delimited {
+    name: "TSV",
+    extensions: &["tsv", "tab"],
+    delimiter: b'\t',
+};
+
+/// Importers by file extension, see `importer_for`
+pub static IMPORTERS: &[&dyn LogImporter] = &[&Adif, &Adx, &CSV, &TSV, &Json, &Fle];
+/// Exporters, looked up by name or extension with `exporter`
+pub static EXPORTERS: &[&dyn LogExporter] = &[&Adif, &Adx, &Cabrillo, &CSV, &TSV, &Json];
+
+/// Columns written by the CSV and TSV exporters
+const CSV_COLUMNS: &[FieldType] = &[
+    FieldType::Timestamp,
+    FieldType::WorkedCall,
+    FieldType::Frequency,
+    FieldType::Mode,
+    FieldType::Submode,
+    FieldType::SentRST,
+    FieldType::RcvdRST,
+    FieldType::Name,
+    FieldType::QTH,
+    FieldType::GridSquare,
+    FieldType::Comment,
+];
+
+fn extension(path: &Path) -> Option<String> {
+    Some(path.extension()?.to_str()?.to_ascii_lowercase())
+}
+
+/// The importer for a file, chosen by its extension
+pub fn importer_for(path: &Path) -> Option<&'static dyn LogImporter> {
+    let ext = extension(path)?;
+    IMPORTERS
+        .iter()
+        .copied()
+        .find(|i| i.extensions().contains(&ext.as_str()))
+}
+
+/// The exporter named `format`, e.g. `ADIF`, or with `format` as extension, e.g. `adi`
+pub fn exporter(format: &str) -> Option<&'static dyn LogExporter> {
+    let ext = format.to_ascii_lowercase();
+    EXPORTERS
+        .iter()
+        .copied()
+        .find(|e| e.name().eq_ignore_ascii_case(format) || e.extensions().contains(&ext.as_str()))
+}
+
+impl LogImporter for Adif {
+    fn name(&self) -> &'static str {
+        "ADIF"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["adi", "adif"]
+    }
+
+    fn import(&self, log: &Log, path: &Path, policy: ImportPolicy, tz: &TimeZone) -> Result<()> {
+        log.import_adif_file(path.to_path_buf(), policy, tz)
+    }
+}
+
+impl LogExporter for Adif {
+    fn name(&self) -> &'static str {
+        "ADIF"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["adi", "adif"]
+    }
+
+    fn export(&self, log: &Log, path: &Path, filter: &Filter) -> Result<()> {
+        log.export_adif_file(path.to_path_buf(), filter)
+    }
+}
+
+impl LogImporter for Adx {
+    fn name(&self) -> &'static str {
+        "ADX"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["adx"]
+    }
+
+    fn import(&self, log: &Log, path: &Path, policy: ImportPolicy, tz: &TimeZone) -> Result<()> {
+        log.import_adx(path, policy, tz)
+    }
+}
+
+impl LogExporter for Adx {
+    fn name(&self) -> &'static str {
+        "ADX"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["adx"]
+    }
+
+    fn export(&self, log: &Log, path: &Path, filter: &Filter) -> Result<()> {
+        log.export_adx(path, filter)
+    }
+}
+
+impl LogExporter for Cabrillo {
+    fn name(&self) -> &'static str {
+        "Cabrillo"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["log", "cbr"]
+    }
+
+    fn export(&self, log: &Log, path: &Path, filter: &Filter) -> Result<()> {
+        log.export_cabrillo(path, filter)
+    }
+}
+
+/// Imports map the columns by their header row, as written by the exporter
+impl LogImporter for Delimited {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        self.extensions
+    }
+
+    fn import(&self, log: &Log, path: &Path, policy: ImportPolicy, _tz: &TimeZone) -> Result<()> {
+        let mapping = CsvMapping::from_header(&Log::read_csv_header(path, self.delimiter)?);
+        log.import_csv(path, self.delimiter, &mapping, policy)
+    }
+}
+
+impl LogExporter for Delimited {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        self.extensions
+    }
+
+    fn export(&self, log: &Log, path: &Path, filter: &Filter) -> Result<()> {
+        log.export_csv(path, CSV_COLUMNS, self.delimiter, filter)
+    }
+}
+
+/// JSON dumps keep record ids, so imports append the records as they are
+impl LogImporter for Json {
+    fn name(&self) -> &'static str {
+        "JSON"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["json"]
+    }
+
+    fn import(&self, log: &Log, path: &Path, _policy: ImportPolicy, _tz: &TimeZone) -> Result<()> {
+        log.import_json(path)
+    }
+}
+
+impl LogExporter for Json {
+    fn name(&self) -> &'static str {
+        "JSON"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["json"]
+    }
+
+    fn export(&self, log: &Log, path: &Path, filter: &Filter) -> Result<()> {
+        let end = log.write_json_filtered(File::create(path)?, filter)?;
+        log.mark_exported(filter, end)
+    }
+}
+
+impl LogImporter for Fle {
+    fn name(&self) -> &'static str {
+        "FLE"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["fle", "txt"]
+    }
+
+    fn import(&self, log: &Log, path: &Path, policy: ImportPolicy, _tz: &TimeZone) -> Result<()> {
+        log.import_fle(path, policy)
+    }
+}
+
+impl Log {
+    /// Imports a file in any format of `IMPORTERS`, recognized by its extension.
+    /// Times without a zone are read in `tz`.
+    pub fn import(&self, path: &Path, policy: ImportPolicy, tz: &TimeZone) -> Result<()> {
+        importer_for(path)
+            .ok_or_else(|| anyhow!("Cannot import {}, the format is unknown", path.display()))?
+            .import(self, path, policy, tz)
+    }
+
+    /// Exports the records `filter` selects to `path` in the format named `format`,
+    /// see `exporter`
+    pub fn export(&self, path: &Path, format: &str, filter: &Filter) -> Result<()> {
+        exporter(format)
+            .ok_or_else(|| anyhow!("Cannot export to {}, the format is unknown", format))?
+            .export(self, path, filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use jiff::tz::{Offset, TimeZone};
+
+    use super::{EXPORTERS, exporter, importer_for};
+    use crate::{
+        data::{FieldType, ImportPolicy, LogRecord},
+        filter::Filter,
+        test_log,
+    };
+
+    #[test]
+    pub fn test_format_registry() {
+        assert_eq!("ADIF", importer_for(Path::new("a/log.ADI")).unwrap().name());
+        assert_eq!("FLE", importer_for(Path::new("pota.txt")).unwrap().name());
+        assert!(importer_for(Path::new("log.cbr")).is_none());
+        assert!(importer_for(Path::new("log")).is_none());
+        assert_eq!("Cabrillo", exporter("cabrillo").unwrap().name());
+        assert_eq!("TSV", exporter("tab").unwrap().name());
+
+        let log = test_log();
+        let mut record = LogRecord::new();
+        record
+            .insert_timestamp("2025-07-28T02:48:00Z".parse().unwrap())
+            .insert_field(FieldType::WorkedCall, "W1AW")
+            .insert_field(FieldType::Frequency, "14.025")
+            .insert_field(FieldType::Mode, "CW")
+            .insert_field(FieldType::SentRST, "599")
+            .insert_field(FieldType::RcvdRST, "579");
+        log.insert_record(record).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("veelog-formats-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for format in EXPORTERS {
+            let path = dir.join(format!("log.{}", format.extensions()[0]));
+            log.export(&path, format.name(), &Filter::default())
+                .unwrap();
+            let Some(importer) = importer_for(&path) else {
+                continue;
+            };
+            let imported = test_log();
+            imported
+                .import(&path, ImportPolicy::PreserveAll, &TimeZone::UTC)
+                .unwrap();
+            let record = imported.get_record(0).unwrap();
+            assert_eq!(
+                Some("W1AW".to_string()),
+                record.get_field(&FieldType::WorkedCall),
+                "{}",
+                importer.name()
+            );
+            assert_eq!(
+                Some("579".to_string()),
+                record.get_field(&FieldType::RcvdRST)
+            );
+        }
+        assert!(
+            log.export(&dir.join("log.x"), "x", &Filter::default())
+                .is_err()
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_import_time_zone() {
+        let dir = std::env::temp_dir().join(format!("veelog-formats-tz-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("local.adi");
+        std::fs::write(
+            &path,
+            "<CALL:4>W1AW<QSO_DATE:8>20250728<TIME_ON:6>220000<EOR>",
+        )
+        .unwrap();
+        let log = test_log();
+        let eastern = TimeZone::fixed(Offset::constant(-5));
+        log.import(&path, ImportPolicy::PreserveAll, &eastern)
+            .unwrap();
+        assert_eq!(
+            Some("2025-07-29T03:00:00Z".to_string()),
+            log.get_record(0).unwrap().get_field(&FieldType::Timestamp)
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use serde::{Deserialize, Serialize};
+use util::band::Band;
+
+use crate::{session::SessionKind, stats::Stats};
+
+/// What a goal counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GoalCount {
+    Qsos,
+    /// Distinct STATE values, e.g. for Worked All States
+    States,
+    /// Distinct DXCC entities
+    Entities,
+}
+
+/// A target for an operating session, e.g. the 10 QSOs a POTA activation needs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Goal {
+    /// What reaching the target means, e.g. `valid activation` or `WAS`
+    pub label: String,
+    /// Kind of session the goal is tracked in, None for every session
+    pub kind: Option<SessionKind>,
+    pub count: GoalCount,
+    /// ADIF name of the only band QSOs count on, e.g. `20m`. Empty counts every band.
+    pub band: String,
+    pub target: usize,
+}
+
+impl Goal {
+    pub fn new(label: &str, kind: Option<SessionKind>, count: GoalCount, target: usize) -> Self {
+        Self {
+            label: label.to_string(),
+            kind,
+            count,
+            band: String::new(),
+            target,
+        }
+    }
+
+    pub fn applies_to(&self, kind: SessionKind) -> bool {
+        self.kind.is_none_or(|k| k == kind)
+    }
+
+    /// How far the QSOs counted in `stats` got towards the goal
+    pub fn progress(&self, stats: &Stats) -> GoalProgress {
+        let band = match self.band.is_empty() {
+            true => None,
+            false => Some(Band::from_name(&self.band)),
+        };
+        let distinct = |sets: &BTreeMap<Option<Band>, BTreeSet<String>>| match band {
+            None => sets.values().flatten().collect::<BTreeSet<_>>().len(),
+            Some(Some(band)) => sets.get(&Some(band)).map_or(0, |set| set.len()),
+            // an unknown band counts nothing rather than the QSOs without a band
+            Some(None) => 0,
+        };
+        let done = match (self.count, band) {
+            (GoalCount::Qsos, None) => stats.qsos,
+            (GoalCount::Qsos, Some(band)) => band
+                .and_then(|b| stats.by_band.get(&b))
+                .copied()
+                .unwrap_or(0),
+            (GoalCount::States, _) => distinct(&stats.states),
+            (GoalCount::Entities, _) => distinct(&stats.entities),
+        };
+        GoalProgress {
+            done,
+            target: self.target,
+            text: match self.band.is_empty() {
+                true => self.label.clone(),
+                false => format!("{} on {}", self.label, self.band),
+            },
+        }
+    }
+}
+
+/// How far a session got towards a goal, shown as e.g. `7/10 for valid activation`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoalProgress {
+    pub done: usize,
+    pub target: usize,
+    text: String,
+}
+
+impl GoalProgress {
+    pub fn reached(&self) -> bool {
+        self.done >= self.target
+    }
+}
+
+impl Display for GoalProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{} for {}", self.done, self.target, self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Goal, GoalCount};
+    use crate::{
+        data::{FieldType, LogRecord},
+        session::SessionKind,
+        stats::Stats,
+    };
+
+    #[test]
+    pub fn test_goal_progress() {
+        let mut records = Vec::new();
+        for (freq, state) in [
+            ("14.062", "CT"),
+            ("14.074", "ct"),
+            ("14.285", "NY"),
+            ("7.074", "TX"),
+        ] {
+            let mut record = LogRecord::new();
+            record
+                .insert_field(FieldType::Frequency, freq)
+                .insert_field(FieldType::from_adif_field("STATE"), state);
+            records.push(record);
+        }
+        // a QSO without a frequency has no band
+        let mut record = LogRecord::new();
+        record.insert_field(FieldType::from_adif_field("STATE"), "ME");
+        records.push(record);
+        let stats = Stats::from_records(&records);
+
+        let activation = Goal::new(
+            "valid activation",
+            Some(SessionKind::Pota),
+            GoalCount::Qsos,
+            10,
+        );
+        assert!(activation.applies_to(SessionKind::Pota));
+        assert!(!activation.applies_to(SessionKind::Contest));
+        let progress = activation.progress(&stats);
+        assert_eq!("5/10 for valid activation", progress.to_string());
+        assert!(!progress.reached());
+
+        let mut was = Goal::new("WAS", None, GoalCount::States, 2);
+        assert_eq!("4/2 for WAS", was.progress(&stats).to_string());
+        was.band = "20m".to_string();
+        let progress = was.progress(&stats);
+        assert_eq!("2/2 for WAS on 20m", progress.to_string());
+        assert!(progress.reached());
+        was.band = "21m".to_string();
+        assert_eq!(0, was.progress(&stats).done);
+        let mut qsos = Goal::new("QSOs", None, GoalCount::Qsos, 10);
+        qsos.band = "21m".to_string();
+        assert_eq!(0, qsos.progress(&stats).done);
+
+        let mut dxcc = Goal::new("DXCC", None, GoalCount::Entities, 100);
+        dxcc.band = "40m".to_string();
+        assert_eq!(0, dxcc.progress(&stats).done);
+    }
+}
@@ -0,0 +1,227 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Result, bail};
+
+use crate::data::{FieldType, Log};
+
+/// Call history columns and the ADIF field each pre-fills. Other columns, such as
+/// `UserText`, are kept when the file is updated but fill nothing.
+const COLUMNS: &[(&str, &str)] = &[
+    ("Name", "NAME"),
+    ("State", "STATE"),
+    ("Sect", "ARRL_SECT"),
+    ("CK", "CHECK"),
+    ("Grid", "GRIDSQUARE"),
+    ("QTH", "QTH"),
+    ("CQZone", "CQZ"),
+    ("ITUZone", "ITUZ"),
+];
+
+fn field_type(column: &str) -> Option<FieldType> {
+    COLUMNS
+        .iter()
+        .find(|(c, _)| c.eq_ignore_ascii_case(column))
+        .map(|(_, adif)| FieldType::from_adif_field(adif))
+}
+
+/// A call history file in the N1MM format: a `!!Order!!,Call,Name,...` line naming the
+/// columns, then one comma separated line per call. Lines starting with `#` are comments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallHistory {
+    /// Column names after `Call`, as in the file
+    columns: Vec<String>,
+    rows: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for CallHistory {
+    fn default() -> Self {
+        Self {
+            columns: COLUMNS.iter().map(|(c, _)| c.to_string()).collect(),
+            rows: BTreeMap::new(),
+        }
+    }
+}
+
+impl CallHistory {
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut columns: Option<Vec<String>> = None;
+        let mut rows = BTreeMap::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(order) = line.strip_prefix("!!Order!!,") {
+                let mut order = order.split(',').map(|c| c.trim().to_string());
+                if !order.next().is_some_and(|c| c.eq_ignore_ascii_case("Call")) {
+                    bail!("The first call history column must be Call: {}", line);
+                }
+                columns = Some(order.collect());
+                continue;
+            }
+            let Some(columns) = &columns else {
+                bail!("Call history without a !!Order!! line before {}", line);
+            };
+            let mut values = line.split(',').map(|v| v.trim().to_string());
+            let call = values.next().unwrap_or_default().to_ascii_uppercase();
+            let mut values: Vec<String> = values.collect();
+            values.resize(columns.len(), String::new());
+            rows.insert(call, values);
+        }
+        match columns {
+            Some(columns) => Ok(Self { columns, rows }),
+            None => bail!("Not a call history file, the !!Order!! line is missing"),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// The fields the history knows for `call`, empty for unknown calls
+    pub fn fields(&self, call: &str) -> Vec<(FieldType, String)> {
+        let Some(values) = self.rows.get(&call.to_ascii_uppercase()) else {
+            return Vec::new();
+        };
+        self.columns
+            .iter()
+            .zip(values)
+            .filter(|(_, v)| !v.is_empty())
+            .filter_map(|(c, v)| Some((field_type(c)?, v.clone())))
+            .collect()
+    }
+
+    /// Sets a column of `call`, adding the column if the file has none
+    fn set(&mut self, call: &str, column: &str, value: &str) {
+        let col = match self
+            .columns
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(column))
+        {
+            Some(col) => col,
+            None => {
+                self.columns.push(column.to_string());
+                for values in self.rows.values_mut() {
+                    values.push(String::new());
+                }
+                self.columns.len() - 1
+            }
+        };
+        let width = self.columns.len();
+        let values = self.rows.entry(call.to_ascii_uppercase()).or_default();
+        values.resize(width, String::new());
+        // commas would split the value into two columns
+        values[col] = value.replace(',', " ");
+    }
+
+    /// Writes the history back in the N1MM format, calls sorted
+    pub fn to_file_string(&self) -> String {
+        let mut out = format!(
+            "# Call history written by veelog\n!!Order!!,Call,{}\n",
+            self.columns.join(",")
+        );
+        for (call, values) in &self.rows {
+            out.push_str(&format!("{},{}\n", call, values.join(",")));
+        }
+        out
+    }
+}
+
+impl Log {
+    /// Brings a call history up to date with the log: every call worked gets the values
+    /// it was last logged with, other calls keep theirs
+    pub fn update_history(&self, history: &mut CallHistory) {
+        for record in self.iter_records() {
+            let Some(call) = record.get_field(&FieldType::WorkedCall) else {
+                continue;
+            };
+            for (column, adif) in COLUMNS {
+                if let Some(value) = record.get_field(&FieldType::from_adif_field(adif))
+                    && !value.is_empty()
+                {
+                    history.set(&call, column, &value);
+                }
+            }
+        }
+    }
+
+    /// Updates the call history file at `path` from the log, creating it if missing,
+    /// and returns the updated history
+    pub fn update_call_history_file(&self, path: &Path) -> Result<CallHistory> {
+        let mut history = match path.exists() {
+            true => CallHistory::load(path)?,
+            false => CallHistory::default(),
+        };
+        self.update_history(&mut history);
+        fs::write(path, history.to_file_string())?;
+        Ok(history)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CallHistory;
+    use crate::{
+        data::{FieldType, LogRecord},
+        test_log,
+    };
+
+    #[test]
+    pub fn test_call_history() {
+        let file = "# SS history\n\
+                    !!Order!!,Call,Name,Sect,CK,UserText\n\
+                    k1abc,Joe,CT,72,met at Dayton\n\
+                    W2XYZ,Ann,,,\n\
+                    N3ZZZ,Bob\n";
+        let history = CallHistory::parse(file).unwrap();
+        assert_eq!(3, history.len());
+        assert_eq!(
+            vec![
+                (FieldType::Name, "Joe".to_string()),
+                (FieldType::from_adif_field("ARRL_SECT"), "CT".to_string()),
+                (FieldType::from_adif_field("CHECK"), "72".to_string()),
+            ],
+            history.fields("K1ABC")
+        );
+        assert_eq!(
+            vec![(FieldType::Name, "Bob".to_string())],
+            history.fields("n3zzz")
+        );
+        assert!(history.fields("DL1ABC").is_empty());
+        assert!(CallHistory::parse("K1ABC,Joe\n").is_err());
+
+        let log = test_log();
+        for (call, name, grid) in [("W2XYZ", "Ann", ""), ("DL1ABC", "Jörg", "JO62")] {
+            let mut record = LogRecord::new();
+            record
+                .insert_timestamp("2025-07-28T02:48:00Z".parse().unwrap())
+                .insert_field(FieldType::WorkedCall, call)
+                .insert_field(FieldType::Name, name)
+                .insert_field(FieldType::GridSquare, grid);
+            log.insert_record(record).unwrap();
+        }
+        let mut updated = history.clone();
+        log.update_history(&mut updated);
+        assert_eq!(4, updated.len());
+        assert_eq!(
+            vec![
+                (FieldType::Name, "Jörg".to_string()),
+                (FieldType::GridSquare, "JO62".to_string()),
+            ],
+            updated.fields("DL1ABC")
+        );
+        assert_eq!(history.fields("K1ABC"), updated.fields("K1ABC"));
+
+        let written = updated.to_file_string();
+        assert!(written.contains("!!Order!!,Call,Name,Sect,CK,UserText,Grid\n"));
+        assert!(written.contains("K1ABC,Joe,CT,72,met at Dayton,\n"));
+        assert_eq!(updated, CallHistory::parse(&written).unwrap());
+    }
+}
@@ -0,0 +1,151 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    ops::Range,
+    path::Path,
+};
+
+use anyhow::{Result, bail};
+use indexmap::IndexMap;
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::{FieldType, Log, LogHeader, LogRecord},
+    filter::Filter,
+    provenance::Source,
+};
+
+const JSON_FORMAT: &str = "veelog";
+const JSON_VERSION: u32 = 1;
+
+/// The JSON dump of a log. Records are objects keyed by `FieldType::export_name`,
+/// so every field, including unsupported ADIF fields and record ids, survives a round trip.
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonLog {
+    format: String,
+    version: u32,
+    header: LogHeader,
+    records: Vec<IndexMap<String, String>>,
+}
+
+impl LogRecord {
+    /// The record as a JSON object keyed by `FieldType::export_name`
+    pub fn json_fields(&self) -> IndexMap<String, String> {
+        self.iter()
+            .map(|(ty, val)| (ty.export_name(), val.clone()))
+            .collect()
+    }
+
+    /// The reverse of `json_fields`, checking the timestamp
+    pub fn from_json_fields(fields: impl IntoIterator<Item = (String, String)>) -> Result<Self> {
+        let mut record = LogRecord::new();
+        for (name, val) in fields {
+            let ty = FieldType::from_export_name(&name);
+            if ty == FieldType::Timestamp && val.parse::<Timestamp>().is_err() {
+                bail!("Invalid timestamp: {}", val)
+            }
+            record.insert_field(ty, &val);
+        }
+        Ok(record)
+    }
+}
+
+impl Log {
+    pub fn export_json(&self, path: &Path) -> Result<()> {
+        self.write_json(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn write_json(&self, writer: impl Write) -> Result<()> {
+        self.write_json_records(writer, self.iter_records())
+    }
+
+    /// Writes a dump of the records `filter` selects, returning where the export
+    /// watermark moves to
+    pub(crate) fn write_json_filtered(&self, writer: impl Write, filter: &Filter) -> Result<usize> {
+        let (records, end) = self.filter_records(filter)?;
+        self.write_json_records(writer, records)?;
+        Ok(end)
+    }
+
+    fn write_json_records(
+        &self,
+        writer: impl Write,
+        records: impl IntoIterator<Item = LogRecord>,
+    ) -> Result<()> {
+        let dump = JsonLog {
+            format: JSON_FORMAT.to_string(),
+            version: JSON_VERSION,
+            header: self.get_header()?,
+            records: records.into_iter().map(|r| r.json_fields()).collect(),
+        };
+        serde_json::to_writer_pretty(writer, &dump)?;
+        Ok(())
+    }
+
+    /// Appends the records of a JSON dump to this log in one transaction.
+    /// The dump's header is not applied.
+    pub fn import_json(&self, path: &Path) -> Result<()> {
+        let records = Self::json_records(BufReader::new(File::open(path)?))?;
+        self.insert_records_from(records, &Source::import(path))?;
+        Ok(())
+    }
+
+    /// Reads a JSON dump into this log, returning the indices of its records
+    pub fn read_json(&self, reader: impl Read) -> Result<Range<usize>> {
+        self.insert_records(Self::json_records(reader)?)
+    }
+
+    fn json_records(reader: impl Read) -> Result<Vec<LogRecord>> {
+        let dump: JsonLog = serde_json::from_reader(reader)?;
+        if dump.format != JSON_FORMAT || dump.version > JSON_VERSION {
+            bail!(
+                "Unsupported JSON log: {} version {}",
+                dump.format,
+                dump.version
+            )
+        }
+        dump.records
+            .into_iter()
+            .map(LogRecord::from_json_fields)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        data::{FieldType, LogRecord},
+        test_log,
+    };
+
+    #[test]
+    pub fn test_json_round_trip() {
+        let log = test_log();
+        let mut record = LogRecord::new();
+        record
+            .insert_timestamp("2025-07-28T02:48:13Z".parse().unwrap())
+            .insert_field(FieldType::WorkedCall, "W1AW")
+            .insert_field(FieldType::Submode, "FT4")
+            .insert_field(FieldType::Other("MY_SIG".into()), "POTA");
+        log.insert_record(record).unwrap();
+
+        let mut json = Vec::new();
+        log.write_json(&mut json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!("veelog", value["format"]);
+        assert_eq!("N0CALL", value["header"]["op_call"]);
+        assert_eq!("W1AW", value["records"][0]["CALL"]);
+        assert_eq!("2025-07-28T02:48:13Z", value["records"][0]["TIMESTAMP"]);
+        assert_eq!("POTA", value["records"][0]["MY_SIG"]);
+
+        let imported = test_log();
+        imported.read_json(json.as_slice()).unwrap();
+        // the record keeps its id, so it is identical
+        assert_eq!(log.get_record(0), imported.get_record(0));
+
+        let mut newer = value.clone();
+        newer["version"] = 2.into();
+        assert!(imported.read_json(newer.to_string().as_bytes()).is_err());
+    }
+}
@@ -1,29 +1,96 @@
+pub mod adx;
+pub mod awards;
+pub mod cabrillo;
+pub mod check;
+pub mod clublog;
+pub mod contest;
+pub mod country;
+pub(crate) mod crypt;
 pub mod data;
-pub mod util;
+pub mod delimited;
+pub mod draft;
+pub mod equipment;
+pub mod events;
+pub mod filter;
+pub mod fle;
+pub mod formats;
+pub mod goals;
+pub mod history;
+pub mod json;
+pub mod lookup;
+pub mod lotw;
+pub mod maintenance;
+pub mod normalize;
+pub mod notes;
+pub mod paths;
+pub mod provenance;
+pub mod rates;
+pub mod report;
+pub mod session;
+pub mod settings;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+pub mod sync;
+pub mod uploads;
+pub mod verify;
 
 pub(crate) const VEELOG_MAGIC: &[u8; 32] = b"D784CB9E58D279B42FDA4D0A5FC7DA80";
 
+/// An empty log in a temporary database, for tests
+#[cfg(test)]
+pub(crate) fn test_log() -> data::Log {
+    let db = sled::Config::new().temporary(true).open().unwrap();
+    data::Log::new_init(db, data::LogHeader::new("N0CALL", "")).unwrap()
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
+        collections::HashSet,
         env,
+        fmt::Display,
         fs::remove_dir_all,
         panic::UnwindSafe,
         path::Path,
+        sync::atomic::{AtomicUsize, Ordering},
         thread,
         time::Duration,
     };
 
-    use crate::data::{FieldType, Log, LogHeader, LogRecord};
+    use crate::{
+        VEELOG_MAGIC,
+        data::{ConflictError, FieldType, ImportPolicy, Log, LogHeader, LogRecord, RecordId},
+        draft::Draft,
+        equipment::{Equipment, EquipmentKind},
+        filter::Filter,
+        lookup::CallInfo,
+        notes::Note,
+        provenance::Source,
+        session::Session,
+        test_log,
+        uploads::Service,
+    };
+    use adif::{data::ADIFType, parse::parse_adif};
+    use jiff::{
+        Timestamp,
+        tz::{self, TimeZone},
+    };
     use sled::Db;
+    use util::freq::Frequency;
 
     #[test]
     pub fn db_playground() {
         test_with_db(|db| {
             let header = LogHeader::new("N0CALL", "");
-            let mut log = Log::new_init(db, header).unwrap();
+            let log = Log::new_init(db, header).unwrap();
 
-            log.import_adif_file("../testlog2.adi".into()).unwrap();
+            log.import_adif_file(
+                "../testlog2.adi".into(),
+                ImportPolicy::PreserveAll,
+                &TimeZone::UTC,
+            )
+            .unwrap();
 
             for record in log.get_records() {
                 for f in record.iter() {
@@ -37,7 +104,7 @@ mod tests {
     pub fn test_db() {
         test_with_db(|db| {
             let header = LogHeader::new("N0CALL", "");
-            let mut testlog = Log::new_init(db, header).unwrap();
+            let testlog = Log::new_init(db, header).unwrap();
             let mut record = LogRecord::new();
             record
                 .insert_field(FieldType::WorkedCall, "N0CALL")
@@ -62,8 +129,739 @@ mod tests {
         });
     }
 
+    #[test]
+    pub fn test_import_policy() {
+        let adif = parse_adif(
+            "<adif_ver:5>3.1.1<eoh>\
+             <call:6>N0CALL <qso_date:8>20250728 <time_on:6>024813 \
+             <my_gridsquare:4>AA00 <qsl_sent:1>Y <band:3>20m <eor>",
+        );
+        test_with_db(|db| {
+            let header = LogHeader::new("N0CALL", "");
+            let log = Log::new_init(db, header).unwrap();
+
+            assert!(log.import_adif(adif.clone(), ImportPolicy::Strict).is_err());
+            log.import_adif(adif, ImportPolicy::PreserveAll).unwrap();
+            let record = log.get_record(0).unwrap();
+            for (field, value) in [
+                ("MY_GRIDSQUARE", "AA00"),
+                ("QSL_SENT", "Y"),
+                ("BAND", "20m"),
+            ] {
+                assert_eq!(
+                    Some(value.to_string()),
+                    record.get_field(&FieldType::Other(field.into()))
+                );
+            }
+        });
+    }
+
+    #[test]
+    pub fn test_submode_round_trip() {
+        let adif = parse_adif(
+            "<adif_ver:5>3.1.1<eoh>\
+             <call:6>N0CALL <qso_date:8>20250728 <time_on:6>024813 \
+             <mode:4>MFSK <submode:3>FT4 <eor>",
+        );
+        test_with_db(|db| {
+            let header = LogHeader::new("N0CALL", "");
+            let log = Log::new_init(db, header).unwrap();
+            log.import_adif(adif, ImportPolicy::Strict).unwrap();
+
+            let record = log.get_record(0).unwrap();
+            assert_eq!(Some("MFSK/FT4".to_string()), record.display_mode());
+
+            let exported = log
+                .export_adif(&Filter::default())
+                .unwrap()
+                .serialize()
+                .unwrap();
+            let reparsed = parse_adif(&exported);
+            let fields = reparsed.body[0].0.clone();
+            for (name, value) in [
+                ("CALL", "N0CALL"),
+                ("QSO_DATE", "20250728"),
+                ("TIME_ON", "024813"),
+                ("MODE", "MFSK"),
+                ("SUBMODE", "FT4"),
+            ] {
+                assert!(fields.contains(&(name.to_string(), ADIFType::Str(value.to_string()))));
+            }
+        });
+    }
+
+    #[test]
+    pub fn test_tx_power() {
+        let adif = parse_adif(
+            "<adif_ver:5>3.1.1<eoh>\
+             <call:6>N0CALL <qso_date:8>20250728 <time_on:6>024813 <tx_pwr:4>100W <eor>\
+             <call:6>N0CALL <qso_date:8>20250728 <time_on:6>024913 <tx_pwr:3>2.5 <eor>",
+        );
+        test_with_db(|db| {
+            let header = LogHeader::new("N0CALL", "");
+            let log = Log::new_init(db, header).unwrap();
+            log.import_adif(adif, ImportPolicy::Strict).unwrap();
+            for (idx, power) in [(0, "100"), (1, "2.5")] {
+                let record = log.get_record(idx).unwrap();
+                assert_eq!(
+                    Some(power.to_string()),
+                    record.get_field(&FieldType::TxPower)
+                );
+            }
+            let exported = log
+                .export_adif(&Filter::default())
+                .unwrap()
+                .serialize()
+                .unwrap();
+            assert!(exported.contains("<TX_PWR:3>100"));
+
+            let bad = parse_adif(
+                "<call:6>N0CALL <qso_date:8>20250728 <time_on:6>025013 <tx_pwr:4>lots <eor>",
+            );
+            assert!(log.import_adif(bad, ImportPolicy::Strict).is_err());
+        });
+    }
+
+    #[test]
+    pub fn test_local_time_import() {
+        let adif = parse_adif(
+            "<adif_ver:5>3.1.1<eoh>\
+             <call:6>N0CALL <qso_date:8>20250728 <time_on:6>024813 <eor>",
+        );
+        test_with_db(|db| {
+            let header = LogHeader::new("N0CALL", "");
+            let log = Log::new_init(db, header).unwrap();
+            let cest = TimeZone::fixed(tz::offset(2));
+            log.import_adif_in(adif, ImportPolicy::Strict, &cest)
+                .unwrap();
+            let record = log.get_record(0).unwrap();
+            assert_eq!(
+                Some("2025-07-28T00:48:13Z".to_string()),
+                record.get_field(&FieldType::Timestamp)
+            );
+            assert_eq!(
+                Some("2025-07-28 00:48:13 UTC".to_string()),
+                record.display_time(&TimeZone::UTC)
+            );
+            assert_eq!(
+                Some("2025-07-28 02:48:13 +02".to_string()),
+                record.display_time(&cest)
+            );
+        });
+    }
+
+    #[test]
+    pub fn test_atomic_import() {
+        // the second record has a broken frequency, so nothing may be imported
+        let adif = parse_adif(
+            "<adif_ver:5>3.1.1<eoh>\
+             <call:6>N0CALL <qso_date:8>20250728 <time_on:6>024813 <freq:5>7.000 <eor>\
+             <call:5>W1ABC <qso_date:8>20250728 <time_on:6>025013 <freq:4>7.0x <eor>",
+        );
+        test_with_db(|db| {
+            let header = LogHeader::new("N0CALL", "");
+            let log = Log::new_init(db, header).unwrap();
+            assert!(log.import_adif(adif, ImportPolicy::PreserveAll).is_err());
+            assert_eq!(0, log.get_idx());
+            assert!(log.get_record(0).is_none());
+
+            let range = log
+                .insert_records(vec![LogRecord::new(), LogRecord::new()])
+                .unwrap();
+            assert_eq!(0..2, range);
+            assert_eq!(2, log.insert_record(LogRecord::new()).unwrap());
+            log.delete_record(1).unwrap();
+            assert!(log.delete_record(1).is_err());
+            assert_eq!(3, log.get_idx());
+            assert_eq!(2, log.get_records().len());
+            let ids = log
+                .iter_records_desc()
+                .map(|r| r.id())
+                .collect::<Vec<Option<RecordId>>>();
+            assert_eq!(vec![log.record_id(2), log.record_id(0)], ids);
+        });
+    }
+
+    #[test]
+    pub fn test_record_ids() {
+        test_with_db(|db| {
+            let header = LogHeader::new("N0CALL", "");
+            let log = Log::new_init(db, header).unwrap();
+            log.insert_records((0..3).map(|_| LogRecord::new()).collect())
+                .unwrap();
+            let ids = (0..3)
+                .map(|i| log.record_id(i).unwrap())
+                .collect::<Vec<RecordId>>();
+            assert!(ids.is_sorted_by(|a, b| a < b));
+
+            // ids survive deleting other records and modifying the record itself
+            log.delete_record(0).unwrap();
+            log.set_field(1, FieldType::Comment, "hi").unwrap();
+            let record = log.get_record_by_id(ids[1]).unwrap();
+            assert_eq!(Some(ids[1]), record.id());
+            assert_eq!(
+                Some("hi".to_string()),
+                record.get_field(&FieldType::Comment)
+            );
+
+            // a record carrying an id that is already taken gets a new one
+            let idx = log.insert_record(record).unwrap();
+            let id = log.record_id(idx).unwrap();
+            assert!(id > ids[2]);
+        });
+    }
+
+    #[test]
+    pub fn test_insert_records() {
+        test_with_db(|db| {
+            let header = LogHeader::new("N0CALL", "");
+            let log = Log::new_init(db, header).unwrap();
+            log.insert_record(LogRecord::new()).unwrap();
+            let taken = log.get_record(0).unwrap();
+            let other = test_log();
+            other.insert_record(LogRecord::new()).unwrap();
+            let carried = other.get_record(0).unwrap();
+
+            let calls = ["W1ABC", "DL1ABC", "JA1ABC"];
+            let mut records = calls
+                .iter()
+                .map(|call| {
+                    let mut record = LogRecord::new();
+                    record.insert_field(FieldType::WorkedCall, call);
+                    record
+                })
+                .collect::<Vec<_>>();
+            records.push(taken.clone());
+            records.push(carried.clone());
+            // the same id twice in one batch
+            records.push(carried.clone());
+            assert_eq!(1..7, log.insert_records(records).unwrap());
+            assert_eq!(7, log.get_idx());
+            for (i, call) in calls.iter().enumerate() {
+                assert_eq!(
+                    Some(call.to_string()),
+                    log.get_record(i + 1)
+                        .unwrap()
+                        .get_field(&FieldType::WorkedCall)
+                );
+            }
+            assert_ne!(taken.id(), log.record_id(4));
+            assert_eq!(carried.id(), log.record_id(5));
+            assert_ne!(carried.id(), log.record_id(6));
+            assert!(log.verify().unwrap().is_ok());
+
+            assert_eq!(7..7, log.insert_records(Vec::new()).unwrap());
+            assert_eq!(7, log.insert_record(LogRecord::new()).unwrap());
+        });
+    }
+
+    #[test]
+    pub fn test_migrate_layout() {
+        test_with_db(|db| {
+            // records stored directly under their index, as before records had ids
+            db.insert(b"MAGIC", VEELOG_MAGIC).unwrap();
+            db.insert(b"INDEX", &2usize.to_le_bytes()).unwrap();
+            for (idx, call) in ["N0CALL", "W1ABC"].iter().enumerate() {
+                let mut record = LogRecord::new();
+                record.insert_field(FieldType::WorkedCall, call);
+                db.insert(idx.to_le_bytes(), Log::encode_record(record).unwrap())
+                    .unwrap();
+            }
+            let log = Log::new(db).unwrap();
+            let record = log.get_record(1).unwrap();
+            assert_eq!(
+                Some("W1ABC".to_string()),
+                record.get_field(&FieldType::WorkedCall)
+            );
+            assert_eq!(log.record_id(1), record.id());
+            assert!(log.record_id(0) < log.record_id(1));
+        });
+    }
+
+    #[test]
+    pub fn test_migrate_tags() {
+        let log = test_log();
+        let db = log.db.clone();
+        log.insert_record(LogRecord::new()).unwrap();
+        let id = log.record_id(0).unwrap();
+        let enc = Log::encode_record(log.get_record(0).unwrap()).unwrap();
+        assert_eq!([0xff, 2], enc[..2]);
+
+        // a record encoded by FieldType variant index, as before layout 3:
+        // CALL is variant 1 and Other variant 17
+        let mut legacy = vec![2, 1, 7];
+        legacy.extend_from_slice(b"W1ABC/P");
+        legacy.extend_from_slice(&[17, 9]);
+        legacy.extend_from_slice(b"ARRL_SECT");
+        legacy.push(2);
+        legacy.extend_from_slice(b"CT");
+        let record: LogRecord = Log::decode_record(&legacy).unwrap();
+        assert_eq!(
+            Some("W1ABC/P".to_string()),
+            record.get_field(&FieldType::WorkedCall)
+        );
+        assert_eq!(
+            Some("CT".to_string()),
+            record.get_field(&FieldType::Other("ARRL_SECT".into()))
+        );
+
+        db.open_tree(b"RECORDS")
+            .unwrap()
+            .insert(id.to_bytes(), legacy)
+            .unwrap();
+        db.insert(b"LAYOUT", &[2]).unwrap();
+        let log = Log::new(db.clone()).unwrap();
+        assert_eq!(record, log.get_record(0).unwrap());
+        let enc = db
+            .open_tree(b"RECORDS")
+            .unwrap()
+            .get(id.to_bytes())
+            .unwrap();
+        assert_eq!([0xff, 2], enc.unwrap()[..2]);
+        assert_eq!(Some([4].as_slice().into()), db.get(b"LAYOUT").unwrap());
+        assert_eq!(Some(0), log.index_of(id).unwrap());
+    }
+
+    #[test]
+    pub fn test_frequency_hz() {
+        let mut record = LogRecord::new();
+        record
+            .insert_field(FieldType::Frequency, "14.07400")
+            .insert_frequency(FieldType::RxFrequency, Frequency::from_hz(14_076_000));
+        let mut typed = LogRecord::new();
+        typed
+            .insert_field(FieldType::Frequency, "14.074")
+            .insert_field(FieldType::RxFrequency, "14.076");
+        assert_eq!(typed, record);
+        assert_eq!(Some(Frequency::from_hz(14_074_000)), record.frequency());
+        assert_eq!(
+            Some("14.076".to_string()),
+            record.get_field(&FieldType::RxFrequency)
+        );
+        assert_eq!(Some("20m".to_string()), record.band());
+        let enc = Log::encode_record(&record).unwrap();
+        assert_eq!(record, Log::decode_record::<LogRecord>(&enc).unwrap());
+        let adif = record.to_adif().unwrap();
+        assert!(
+            adif.0
+                .contains(&("FREQ".to_string(), ADIFType::Str("14.074".to_string())))
+        );
+
+        // values that are no frequency are kept as typed
+        record.insert_field(FieldType::Frequency, "14.0.74");
+        assert_eq!(None, record.frequency());
+        assert_eq!(
+            Some("14.0.74".to_string()),
+            record.get_field(&FieldType::Frequency)
+        );
+
+        // version 1 records hold MHz strings
+        let mut v1 = vec![0xff, 1, 1, 9];
+        v1.extend_from_slice(b"Frequency");
+        v1.push(7);
+        v1.extend_from_slice(b"7.02500");
+        let record: LogRecord = Log::decode_record(&v1).unwrap();
+        assert_eq!(Some(Frequency::from_hz(7_025_000)), record.frequency());
+    }
+
+    #[test]
+    pub fn test_concurrent_inserts() {
+        let log = test_log();
+        let threads = (0..4)
+            .map(|_| {
+                let log = log.clone();
+                thread::spawn(move || {
+                    (0..25)
+                        .map(|_| log.insert_record(LogRecord::new()).unwrap())
+                        .collect::<Vec<usize>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut indices = threads
+            .into_iter()
+            .flat_map(|t| t.join().unwrap())
+            .collect::<Vec<usize>>();
+        indices.sort();
+        assert_eq!((0..100).collect::<Vec<usize>>(), indices);
+        assert_eq!(100, log.get_idx());
+        let ids = (0..100)
+            .filter_map(|idx| log.record_id(idx))
+            .collect::<HashSet<RecordId>>();
+        assert_eq!(100, ids.len());
+        assert!(log.verify().unwrap().is_ok());
+    }
+
+    #[test]
+    pub fn test_concurrent_batch_inserts() {
+        let log = test_log();
+        let threads = (0..4)
+            .map(|_| {
+                let log = log.clone();
+                thread::spawn(move || {
+                    (0..10)
+                        .flat_map(|_| log.insert_records(vec![LogRecord::new(); 10]).unwrap())
+                        .collect::<Vec<usize>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut indices = threads
+            .into_iter()
+            .flat_map(|t| t.join().unwrap())
+            .collect::<Vec<usize>>();
+        indices.sort();
+        assert_eq!((0..400).collect::<Vec<usize>>(), indices);
+        let ids = (0..400)
+            .filter_map(|idx| log.record_id(idx))
+            .collect::<HashSet<RecordId>>();
+        assert_eq!(400, ids.len());
+        assert!(log.verify().unwrap().is_ok());
+    }
+
+    #[test]
+    pub fn test_search() {
+        let log = test_log();
+        for (call, name, comment) in [
+            ("W1AW", "Hiram", ""),
+            ("DL1ABC", "Klaus", "worked on a hiking trip"),
+            ("JA1XYZ", "Taro", ""),
+        ] {
+            let mut record = LogRecord::new();
+            record
+                .insert_field(FieldType::WorkedCall, call)
+                .insert_field(FieldType::Name, name)
+                .insert_field(FieldType::Comment, comment);
+            log.insert_record(record).unwrap();
+        }
+        assert_eq!(vec![0, 1], log.search("hi"));
+        assert_eq!(vec![1], log.search(" dl1 "));
+        assert_eq!(vec![2], log.search("taro"));
+        assert!(log.search("").is_empty());
+        assert!(log.search("K1ABC").is_empty());
+    }
+
+    #[test]
+    pub fn test_field_names() {
+        for name in ["CALL", "FREQ_RX", "STATE", "APP_VEELOG_ID", "ARRL_SECT"] {
+            assert_eq!(Some(name), FieldType::from_adif_field(name).adif_name());
+        }
+        assert_eq!(None, FieldType::Timestamp.adif_name());
+        assert_eq!("RST Sent", FieldType::SentRST.label());
+        assert_eq!(
+            "ARRL_SECT",
+            FieldType::Other("ARRL_SECT".into()).to_string()
+        );
+        assert!(FieldType::RcvdRST.is_valid("-12"));
+        assert!(!FieldType::CQZ.is_valid("123"));
+        assert!(FieldType::SentSerial.is_valid(""));
+        assert!(!FieldType::WorkedCall.is_valid("W1 AW"));
+        assert!(FieldType::Other("CLASS".into()).is_valid("2A?"));
+        assert!(FieldType::GridSquare.is_valid("fn3"));
+        assert!(FieldType::GridSquare.is_valid("FN31pr12"));
+        assert!(!FieldType::GridSquare.is_valid("FS31"));
+        assert!(!FieldType::GridSquare.is_valid("FN31PZ"));
+    }
+
+    #[test]
+    pub fn test_enumerated_fields() {
+        let adif = parse_adif(
+            "<adif_ver:5>3.1.5<eoh>\
+             <call:5>G4ABC <qso_date:8>20250728 <time_on:6>024813 <band_rx:4>70CM \
+             <prop_mode:3>sat <iota:6>eu-005 <sota_ref:8>g/ld-001 <wwff_ref:9>gff-0123 \
+             <contest_id:8>cq-ww-cw <operator:5>k1abc <station_callsign:6>w1aw/p \
+             <ant_az:3>045 <my_iota:6>na-046 <my_sota_ref:10>w7a/az-001 \
+             <my_wwff_ref:9>kff-1234 <cnty:13>ma, Middlesex <eor>",
+        );
+        test_with_db(|db| {
+            let header = LogHeader::new("N0CALL", "");
+            let log = Log::new_init(db, header).unwrap();
+            log.import_adif(adif, ImportPolicy::Strict).unwrap();
+            let record = log.get_record(0).unwrap();
+            for (ty, value) in [
+                (FieldType::RxBand, "70cm"),
+                (FieldType::PropMode, "SAT"),
+                (FieldType::Iota, "EU-005"),
+                (FieldType::SotaRef, "G/LD-001"),
+                (FieldType::WwffRef, "GFF-0123"),
+                (FieldType::ContestId, "CQ-WW-CW"),
+                (FieldType::Operator, "K1ABC"),
+                (FieldType::StationCallsign, "W1AW/P"),
+                (FieldType::AntAz, "45"),
+                (FieldType::MyIota, "NA-046"),
+                (FieldType::MySotaRef, "W7A/AZ-001"),
+                (FieldType::MyWwffRef, "KFF-1234"),
+                (FieldType::SecondaryAdminSubdiv, "MA,Middlesex"),
+            ] {
+                assert_eq!(Some(value.to_string()), record.get_field(&ty));
+            }
+
+            let bad = parse_adif(
+                "<call:5>G4ABC <qso_date:8>20250728 <time_on:6>025013 <prop_mode:7>skywave <eor>",
+            );
+            assert!(log.import_adif(bad, ImportPolicy::Strict).is_err());
+            let bad = parse_adif(
+                "<call:5>G4ABC <qso_date:8>20250728 <time_on:6>025013 <my_sota_ref:6>W7A001 <eor>",
+            );
+            assert!(log.import_adif(bad, ImportPolicy::Strict).is_err());
+            // CNTY of an entity other than the US
+            let ja = parse_adif(
+                "<call:6>JA1XYZ <qso_date:8>20250728 <time_on:6>025013 <cnty:6>100101 <eor>",
+            );
+            let idx = log.import_adif(ja, ImportPolicy::Strict).unwrap().start;
+            assert_eq!(
+                Some("100101".to_string()),
+                log.get_record(idx)
+                    .unwrap()
+                    .get_field(&FieldType::SecondaryAdminSubdiv)
+            );
+
+            // other loggers' values are kept as they are and empty fields are skipped
+            let odd = parse_adif(
+                "<call:5>G4ABC <qso_date:8>20250728 <time_on:6>025013 <prop_mode:7>skywave \
+                 <iota:0> <contest_id:11>MY-CONTEST! <eor>",
+            );
+            let idx = log
+                .import_adif(odd, ImportPolicy::PreserveAll)
+                .unwrap()
+                .start;
+            let record = log.get_record(idx).unwrap();
+            assert_eq!(
+                Some("skywave".to_string()),
+                record.get_field(&FieldType::PropMode)
+            );
+            assert_eq!(
+                Some("MY-CONTEST!".to_string()),
+                record.get_field(&FieldType::ContestId)
+            );
+            assert_eq!(None, record.get_field(&FieldType::Iota));
+        });
+
+        // stored as an untyped field before SOTA_REF got its own type
+        let mut old = vec![0xff, 2, 1, 14];
+        old.extend_from_slice(b"Other:SOTA_REF");
+        old.extend_from_slice(&[0, 9]);
+        old.extend_from_slice(b"W2/WE-003");
+        let record: LogRecord = Log::decode_record(&old).unwrap();
+        assert_eq!(
+            Some("W2/WE-003".to_string()),
+            record.get_field(&FieldType::SotaRef)
+        );
+    }
+
+    #[test]
+    pub fn test_open_path() {
+        let path = env::temp_dir().join(format!("veelog-tests-open-{}", std::process::id()));
+        let _ = remove_dir_all(&path);
+        let log = Log::open_path(&path, LogHeader::new("N0CALL", ""), None).unwrap();
+        let mut record = LogRecord::new();
+        record.insert_field(FieldType::WorkedCall, "W1AW");
+        log.insert_record(record).unwrap();
+        drop(log);
+
+        // opening it again keeps what was logged
+        let log =
+            when_unlocked(|| Log::open_path(&path, LogHeader::new("N0CALL", ""), None)).unwrap();
+        assert_eq!(1, log.get_idx());
+        drop(log);
+        remove_dir_all(&path).unwrap();
+
+        // a database that is not a log is left alone
+        let db = sled::open(&path).unwrap();
+        db.insert(b"KEY", b"value").unwrap();
+        drop(db);
+        let opened = when_unlocked(|| Log::open_path(&path, LogHeader::new("N0CALL", ""), None));
+        assert!(opened.is_err());
+        let db = when_unlocked(|| sled::open(&path)).unwrap();
+        assert!(db.contains_key(b"KEY").unwrap());
+        drop(db);
+        remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    pub fn test_open_read_only() {
+        let path = env::temp_dir().join(format!("veelog-tests-ro-{}", std::process::id()));
+        let _ = remove_dir_all(&path);
+        assert!(Log::open_read_only(&path, None).is_err());
+        assert!(!path.exists());
+
+        let log = Log::open_path(&path, LogHeader::new("N0CALL", ""), None).unwrap();
+        let mut record = LogRecord::new();
+        record.insert_field(FieldType::WorkedCall, "W1AW");
+        log.insert_record(record.clone()).unwrap();
+        log.flush().unwrap();
+
+        // the log can be browsed while it is open for logging
+        let read_only = Log::open_read_only(&path, None).unwrap();
+        assert_eq!(1, read_only.get_idx());
+        drop(read_only);
+        drop(log);
+
+        let log = Log::open_read_only(&path, None).unwrap();
+        assert!(log.is_read_only());
+        assert_eq!(
+            Some("W1AW".to_string()),
+            log.get_record(0).unwrap().get_field(&FieldType::WorkedCall)
+        );
+        assert!(log.insert_record(record).is_err());
+        assert!(log.set_field(0, FieldType::Name, "Hiram").is_err());
+        assert!(log.delete_record(0).is_err());
+        assert!(log.repair().is_err());
+        assert!(log.cache_lookup(&CallInfo::default()).is_err());
+        drop(log);
+
+        let log =
+            when_unlocked(|| Log::open_path(&path, LogHeader::new("N0CALL", ""), None)).unwrap();
+        assert_eq!(None, log.get_record(0).unwrap().get_field(&FieldType::Name));
+        assert_eq!(1, log.get_idx());
+        drop(log);
+        remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    pub fn test_encrypted_log() {
+        let path = env::temp_dir().join(format!("veelog-tests-crypt-{}", std::process::id()));
+        let _ = remove_dir_all(&path);
+        let header = || LogHeader::new("N0CALL", "");
+        let log = Log::open_path(&path, header(), Some("correct horse")).unwrap();
+        assert!(log.is_encrypted());
+        let mut record = LogRecord::new();
+        record
+            .insert_field(FieldType::WorkedCall, "W1AW")
+            .insert_field(FieldType::Comment, "lives on Main St");
+        let source = Source::import(Path::new("Main.adi"));
+        let idx = log.insert_record_from(record, &source).unwrap();
+        let id = log.record_id(idx).unwrap();
+        log.add_note(id, &Note::new(Timestamp::now(), "phone 555-0100"))
+            .unwrap();
+        log.set_field(idx, FieldType::Name, "Hiram").unwrap();
+        log.queue_upload(Service::Eqsl, id).unwrap();
+        log.start_session(Session {
+            name: "Main St park".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let antenna = log
+            .add_equipment(Equipment::new(EquipmentKind::Antenna, "Main St vertical"))
+            .unwrap();
+        let report = parse_adif("<APP_LoTW_LASTQSL:19>2025-08-01 10:11:12\n<eoh>\n");
+        log.apply_lotw_report(&report).unwrap();
+        let draft = Draft {
+            fields: vec![(FieldType::WorkedCall, "W1AW".to_string())],
+            focused: Some(FieldType::WorkedCall),
+        };
+        log.save_draft(&draft).unwrap();
+        for name in log.db.tree_names() {
+            for entry in log.db.open_tree(name).unwrap().iter() {
+                let (_, enc) = entry.unwrap();
+                assert!(
+                    !enc.windows(4)
+                        .any(|w| w == b"Main" || w == b"W1AW" || w == b"555-" || w == b"2025")
+                );
+            }
+        }
+        drop(log);
+
+        assert!(when_unlocked(|| Log::open_path(&path, header(), None)).is_err());
+        let wrong = when_unlocked(|| Log::open_path(&path, header(), Some("wrong horse")));
+        assert!(wrong.is_err());
+        let log = Log::open_read_only(&path, Some("correct horse")).unwrap();
+        let record = log.get_record(idx).unwrap();
+        assert_eq!(
+            Some("lives on Main St".to_string()),
+            record.get_field(&FieldType::Comment)
+        );
+        assert_eq!(
+            Some("Hiram".to_string()),
+            record.get_field(&FieldType::Name)
+        );
+        assert_eq!("phone 555-0100", log.notes(id).unwrap()[0].text);
+        assert_eq!("Main St vertical", log.equipment().unwrap()[&antenna].name);
+        assert_eq!(
+            Some("2025-08-01 10:11:12".to_string()),
+            log.lotw_last_qsl().unwrap()
+        );
+        assert_eq!(draft, log.draft().unwrap());
+        assert_eq!(1, log.verify().unwrap().records);
+        drop(log);
+        remove_dir_all(&path).unwrap();
+
+        // a plain log has no passphrase
+        Log::open_path(&path, header(), None).unwrap();
+        let plain = when_unlocked(|| Log::open_path(&path, header(), Some("correct horse")));
+        assert!(plain.is_err());
+        remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    pub fn test_modify_conflict() {
+        let log = test_log();
+        let mut record = LogRecord::new();
+        record.insert_field(FieldType::WorkedCall, "W1AW");
+        let idx = log.insert_record(record).unwrap();
+
+        let (mut mine, revision) = log.get_record_revision(idx).unwrap();
+        assert_eq!(0, revision);
+        // a background insert of the same QSO updates it meanwhile
+        log.set_field(idx, FieldType::Name, "Hiram").unwrap();
+        mine.insert_field(FieldType::Comment, "old friend");
+        let err = log
+            .modify_record_checked(idx, mine.clone(), revision)
+            .unwrap_err();
+        let conflict = err.downcast_ref::<ConflictError>().unwrap();
+        assert_eq!(
+            (idx, 0, 1),
+            (conflict.idx, conflict.expected, conflict.revision)
+        );
+        assert_eq!(
+            Some("Hiram".to_string()),
+            conflict.current.get_field(&FieldType::Name)
+        );
+        assert_eq!(
+            None,
+            log.get_record(idx).unwrap().get_field(&FieldType::Comment)
+        );
+
+        // resolved by editing the current record
+        let mut merged = conflict.current.clone();
+        merged.insert_field(FieldType::Comment, "old friend");
+        assert_eq!(
+            2,
+            log.modify_record_checked(idx, merged, conflict.revision)
+                .unwrap()
+        );
+        let (record, revision) = log.get_record_revision(idx).unwrap();
+        assert_eq!(2, revision);
+        assert_eq!(
+            Some("Hiram".to_string()),
+            record.get_field(&FieldType::Name)
+        );
+        // blind writes still move the revision on
+        log.modify_record(idx, record).unwrap();
+        assert!(log.modify_record_checked(idx, mine, 2).is_err());
+    }
+
+    /// Opens a database once sled let go of it. Sled releases the lock of a dropped
+    /// database late, so opening it again straight away can fail while tests run in
+    /// parallel.
+    fn when_unlocked<T, E: Display>(open: impl Fn() -> Result<T, E>) -> Result<T, E> {
+        for _ in 0..50 {
+            match open() {
+                Err(e) if format!("{:#}", e).contains("could not acquire lock") => {
+                    thread::sleep(Duration::from_millis(100))
+                }
+                res => return res,
+            }
+        }
+        open()
+    }
+
     fn test_with_db(test: impl FnOnce(Db) + UnwindSafe) {
-        let path = env::temp_dir().join(Path::new("veelog-tests-db"));
+        // every test gets its own directory so they can run in parallel
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(Path::new(&format!(
+            "veelog-tests-db-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        )));
         let _ = remove_dir_all(&path);
 
         let db = sled::open(&path).unwrap();
@@ -0,0 +1,123 @@
+use anyhow::Result;
+use bincode::{Decode, Encode};
+use jiff::{SignedDuration, Timestamp};
+
+use crate::data::{FieldType, Log};
+
+const CACHE_TREE: &[u8] = b"LOOKUP_CACHE";
+
+/// Cached lookups older than this are fetched again
+pub const CACHE_MAX_AGE: SignedDuration = SignedDuration::from_hours(30 * 24);
+
+/// Operator details returned by an online callbook
+#[derive(Debug, Clone, Default, PartialEq, Encode, Decode)]
+pub struct CallInfo {
+    pub call: String,
+    pub name: Option<String>,
+    pub qth: Option<String>,
+    pub grid: Option<String>,
+    pub state: Option<String>,
+    pub dxcc: Option<String>,
+    pub cq_zone: Option<String>,
+    #[bincode(with_serde)]
+    pub fetched: Timestamp,
+}
+
+impl CallInfo {
+    /// The record fields this lookup can fill in
+    pub fn fields(&self) -> Vec<(FieldType, String)> {
+        [
+            (FieldType::Name, &self.name),
+            (FieldType::QTH, &self.qth),
+            (FieldType::GridSquare, &self.grid),
+            (FieldType::PrimaryAdminSubdiv, &self.state),
+            (FieldType::DXCC, &self.dxcc),
+            (FieldType::CQZ, &self.cq_zone),
+        ]
+        .into_iter()
+        .filter_map(|(ty, val)| val.clone().map(|v| (ty, v)))
+        .collect()
+    }
+}
+
+impl Log {
+    /// Returns the cached lookup for `call`, unless it is missing or expired
+    pub fn cached_lookup(&self, call: &str) -> Result<Option<CallInfo>> {
+        if self.is_encrypted() {
+            return Ok(None);
+        }
+        let tree = self.db.open_tree(CACHE_TREE)?;
+        let Some(enc) = tree.get(call.to_ascii_uppercase())? else {
+            return Ok(None);
+        };
+        // entries written before CallInfo gained a field no longer decode, look those up again
+        let Ok(info) = Self::decode_record::<CallInfo>(&enc) else {
+            return Ok(None);
+        };
+        match Timestamp::now().duration_since(info.fetched) > CACHE_MAX_AGE {
+            true => Ok(None),
+            false => Ok(Some(info)),
+        }
+    }
+
+    /// Caches a lookup, except in an encrypted log, whose cache would tell whom the
+    /// operator looked up by their call. Fails for a log opened read-only.
+    pub fn cache_lookup(&self, info: &CallInfo) -> Result<()> {
+        self.check_writable()?;
+        if self.is_encrypted() {
+            return Ok(());
+        }
+        let tree = self.db.open_tree(CACHE_TREE)?;
+        tree.insert(
+            info.call.to_ascii_uppercase(),
+            Self::encode_record(info.clone())?,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::{SignedDuration, Timestamp};
+
+    use super::CallInfo;
+    use crate::{
+        data::{FieldType, Log, LogHeader},
+        test_log,
+    };
+
+    #[test]
+    pub fn test_lookup_cache() {
+        let log = test_log();
+        let info = CallInfo {
+            call: "W1AW".to_string(),
+            name: Some("Hiram Percy Maxim".to_string()),
+            grid: Some("FN31pr".to_string()),
+            fetched: Timestamp::now(),
+            ..Default::default()
+        };
+        assert_eq!(None, log.cached_lookup("W1AW").unwrap());
+        log.cache_lookup(&info).unwrap();
+        assert_eq!(Some(&info), log.cached_lookup("w1aw").unwrap().as_ref());
+        assert_eq!(
+            vec![
+                (FieldType::Name, "Hiram Percy Maxim".to_string()),
+                (FieldType::GridSquare, "FN31pr".to_string())
+            ],
+            info.fields()
+        );
+
+        let stale = CallInfo {
+            fetched: Timestamp::now() - SignedDuration::from_hours(31 * 24),
+            ..info
+        };
+        log.cache_lookup(&stale).unwrap();
+        assert_eq!(None, log.cached_lookup("W1AW").unwrap());
+
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let log = Log::new_init_encrypted(db.clone(), LogHeader::new("N0CALL", ""), "pw").unwrap();
+        log.cache_lookup(&stale).unwrap();
+        assert_eq!(None, log.cached_lookup("W1AW").unwrap());
+        assert!(!db.tree_names().iter().any(|name| name == b"LOOKUP_CACHE"));
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+
+use adif::data::{ADIFFile, ADIFRecord};
+use anyhow::Result;
+use jiff::{SignedDuration, Timestamp, civil::DateTime};
+use util::{band::Band, mode::ModeClass};
+
+use crate::data::{ConflictError, FieldType, Log, LogRecord};
+
+/// Watermarks of the confirmations fetched so far, encrypted in an encrypted log
+const LOTW_TREE: &[u8] = b"LOTW";
+/// Time of the newest confirmation fetched, as LoTW reports it
+const LAST_QSL_KEY: &[u8] = b"last_qsl";
+/// Confirmations match QSOs logged this far apart, as LoTW matches uploads
+const MATCH_WINDOW: SignedDuration = SignedDuration::from_mins(30);
+
+/// What applying a LoTW report did
+#[derive(Debug, Default, PartialEq)]
+pub struct LotwMatches {
+    /// QSOs newly marked as confirmed
+    pub confirmed: usize,
+    /// Confirmations of QSOs already marked as confirmed
+    pub known: usize,
+    /// Calls of confirmations no QSO in the log matched
+    pub unmatched: Vec<String>,
+}
+
+/// A QSL from a lotwreport.adi file
+struct Confirmation {
+    call: String,
+    band: Option<Band>,
+    mode: Option<ModeClass>,
+    time: Timestamp,
+    /// Date LoTW matched the QSO, YYYYMMDD
+    date: Option<String>,
+    dxcc: Option<String>,
+}
+
+impl Confirmation {
+    fn from_adif(record: &ADIFRecord) -> Option<Self> {
+        let field = |name: &str| {
+            record
+                .0
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .and_then(|(_, v)| v.extract_value().ok())
+        };
+        if field("QSL_RCVD").as_deref() != Some("Y") {
+            return None;
+        }
+        // TIME_ON comes with or without seconds
+        let time = format!("{:0<6}", field("TIME_ON")?);
+        let time = DateTime::strptime("%Y%m%d%H%M%S", format!("{}{}", field("QSO_DATE")?, time))
+            .ok()?
+            .to_zoned(jiff::tz::TimeZone::UTC)
+            .ok()?
+            .timestamp();
+        Some(Self {
+            call: field("CALL")?.to_ascii_uppercase(),
+            band: field("BAND").and_then(|b| Band::from_name(&b)),
+            mode: field("MODE").map(|m| ModeClass::from_mode(&m)),
+            time,
+            date: field("QSLRDATE"),
+            dxcc: field("DXCC"),
+        })
+    }
+
+    fn matches(&self, record: &LogRecord) -> bool {
+        let band = record.band().and_then(|b| Band::from_name(&b));
+        let mode = record
+            .get_field(&FieldType::Mode)
+            .map(|m| ModeClass::from_mode(&m));
+        let time = record
+            .get_field(&FieldType::Timestamp)
+            .and_then(|t| t.parse::<Timestamp>().ok());
+        // a side without band or mode can't contradict the other
+        (band.is_none() || self.band.is_none() || band == self.band)
+            && (mode.is_none() || self.mode.is_none() || mode == self.mode)
+            && time.is_some_and(|t| t.duration_until(self.time).abs() <= MATCH_WINDOW)
+    }
+}
+
+impl Log {
+    /// Marks the QSOs confirmed by a LoTW report as received, matching them by call, band,
+    /// mode and time, and remembers the newest confirmation for the next download. QSOs
+    /// changed meanwhile, e.g. by a sync, are matched again as they are now.
+    pub fn apply_lotw_report(&self, report: &ADIFFile) -> Result<LotwMatches> {
+        self.check_writable()?;
+        let result = loop {
+            let (result, modified) = self.lotw_matches(report);
+            match self.modify_records_checked(modified) {
+                Err(e) if e.is::<ConflictError>() => continue,
+                res => break res.map(|_| result)?,
+            }
+        };
+        if let Some(last) = report
+            .header
+            .0
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case("APP_LOTW_LASTQSL"))
+            .and_then(|(_, v)| v.extract_value().ok())
+        {
+            self.db
+                .open_tree(LOTW_TREE)?
+                .insert(LAST_QSL_KEY, self.encrypt(last.into_bytes())?)?;
+        }
+        Ok(result)
+    }
+
+    /// Matches the confirmations of `report` to the QSOs of the log, returning the QSOs
+    /// to mark as confirmed with the revisions they were read at
+    fn lotw_matches(&self, report: &ADIFFile) -> (LotwMatches, Vec<(usize, LogRecord, u64)>) {
+        let mut by_call: HashMap<String, Vec<(usize, LogRecord, u64)>> = HashMap::new();
+        for idx in 0..self.get_idx() {
+            if let Some((record, revision)) = self.get_record_revision(idx)
+                && let Some(call) = record.get_field(&FieldType::WorkedCall)
+            {
+                by_call
+                    .entry(call.to_ascii_uppercase())
+                    .or_default()
+                    .push((idx, record, revision));
+            }
+        }
+        let mut result = LotwMatches::default();
+        let mut modified = Vec::new();
+        for qsl in report.body.iter().filter_map(Confirmation::from_adif) {
+            // a QSO not confirmed yet first, the station may have been worked twice
+            let best = by_call
+                .get_mut(&qsl.call)
+                .into_iter()
+                .flatten()
+                .filter(|(_, r, _)| qsl.matches(r))
+                .min_by_key(|(_, r, _)| r.get_field(&FieldType::LotwRcvd).as_deref() == Some("Y"));
+            let Some((idx, record, revision)) = best else {
+                result.unmatched.push(qsl.call);
+                continue;
+            };
+            if record.get_field(&FieldType::LotwRcvd).as_deref() == Some("Y") {
+                result.known += 1;
+                continue;
+            }
+            record.insert_field(FieldType::LotwRcvd, "Y");
+            if let Some(date) = &qsl.date {
+                record.insert_field(FieldType::from_adif_field("LOTW_QSLRDATE"), date);
+            }
+            if let Some(dxcc) = &qsl.dxcc
+                && record.get_field(&FieldType::DXCC).is_none()
+            {
+                record.insert_field(FieldType::DXCC, dxcc);
+            }
+            modified.push((*idx, record.clone(), *revision));
+            result.confirmed += 1;
+        }
+        (result, modified)
+    }
+
+    /// Time of the newest LoTW confirmation applied, downloads only need newer ones
+    pub fn lotw_last_qsl(&self) -> Result<Option<String>> {
+        match self.db.open_tree(LOTW_TREE)?.get(LAST_QSL_KEY)? {
+            Some(v) => Ok(Some(
+                String::from_utf8_lossy(&self.decrypt(&v)?).to_string(),
+            )),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use adif::parse::parse_adif;
+
+    use super::LotwMatches;
+    use crate::{
+        data::{ConflictError, FieldType, LogRecord},
+        test_log,
+    };
+
+    #[test]
+    pub fn test_apply_lotw_report() {
+        let log = test_log();
+        for (call, time, freq, mode) in [
+            ("JA1XYZ", "2025-07-28T02:48:00Z", "14.025", "CW"),
+            ("JA1XYZ", "2025-07-28T03:10:00Z", "7.025", "CW"),
+            ("DL1ABC", "2025-07-29T12:00:00Z", "14.074", "FT8"),
+        ] {
+            let mut record = LogRecord::new();
+            record
+                .insert_timestamp(time.parse().unwrap())
+                .insert_field(FieldType::WorkedCall, call)
+                .insert_field(FieldType::Frequency, freq)
+                .insert_field(FieldType::Mode, mode);
+            log.insert_record(record).unwrap();
+        }
+        assert_eq!(None, log.lotw_last_qsl().unwrap());
+
+        let report = parse_adif(
+            "ARRL Logbook of the World Status Report\n\
+             <APP_LoTW_LASTQSL:19>2025-08-01 10:11:12\n<eoh>\n\
+             <CALL:6>JA1XYZ<BAND:3>40M<MODE:2>CW<QSO_DATE:8>20250728<TIME_ON:6>031500\
+             <QSL_RCVD:1>Y<QSLRDATE:8>20250801<DXCC:3>339<eor>\n\
+             <CALL:6>DL1ABC<BAND:3>20M<MODE:3>FT8<QSO_DATE:8>20250729<TIME_ON:4>1400\
+             <QSL_RCVD:1>Y<eor>\n\
+             <CALL:6>W1AW/4<BAND:3>20M<MODE:3>SSB<QSO_DATE:8>20250729<TIME_ON:4>1400\
+             <QSL_RCVD:1>Y<eor>\n",
+        );
+        let result = log.apply_lotw_report(&report).unwrap();
+        assert_eq!(
+            LotwMatches {
+                confirmed: 1,
+                known: 0,
+                // two hours off
+                unmatched: vec!["DL1ABC".to_string(), "W1AW/4".to_string()],
+            },
+            result
+        );
+        assert_eq!(
+            None,
+            log.get_record(0).unwrap().get_field(&FieldType::LotwRcvd)
+        );
+        let confirmed = log.get_record(1).unwrap();
+        assert_eq!(
+            Some("Y".to_string()),
+            confirmed.get_field(&FieldType::LotwRcvd)
+        );
+        assert_eq!(
+            Some("339".to_string()),
+            confirmed.get_field(&FieldType::DXCC)
+        );
+        assert_eq!(
+            Some("2025-08-01 10:11:12".to_string()),
+            log.lotw_last_qsl().unwrap()
+        );
+        assert_eq!(1, log.apply_lotw_report(&report).unwrap().known);
+    }
+
+    #[test]
+    pub fn test_lotw_changed_meanwhile() {
+        let log = test_log();
+        let mut record = LogRecord::new();
+        record
+            .insert_timestamp("2025-07-28T03:10:00Z".parse().unwrap())
+            .insert_field(FieldType::WorkedCall, "JA1XYZ")
+            .insert_field(FieldType::Frequency, "7.025")
+            .insert_field(FieldType::Mode, "CW");
+        let idx = log.insert_record(record).unwrap();
+        let report = parse_adif(
+            "<eoh>\n<CALL:6>JA1XYZ<BAND:3>40M<MODE:2>CW<QSO_DATE:8>20250728<TIME_ON:4>0315\
+             <QSL_RCVD:1>Y<eor>\n",
+        );
+
+        let (_, modified) = log.lotw_matches(&report);
+        // a sync changes the QSO after it was read
+        log.set_field(idx, FieldType::Name, "Taro").unwrap();
+        let err = log.modify_records_checked(modified).unwrap_err();
+        assert!(err.is::<ConflictError>());
+
+        assert_eq!(1, log.apply_lotw_report(&report).unwrap().confirmed);
+        let record = log.get_record(idx).unwrap();
+        assert_eq!(
+            Some("Y".to_string()),
+            record.get_field(&FieldType::LotwRcvd)
+        );
+        assert_eq!(Some("Taro".to_string()), record.get_field(&FieldType::Name));
+    }
+}
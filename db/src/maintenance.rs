@@ -0,0 +1,131 @@
+use std::{
+    ffi::OsString,
+    fs::{remove_dir_all, rename},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Result, bail};
+use sled::Db;
+
+use crate::data::Log;
+
+/// How often `compact` tries to open the log while clones of it are still being dropped
+const OPEN_ATTEMPTS: u32 = 20;
+const OPEN_RETRY: Duration = Duration::from_millis(150);
+
+/// `path` with `suffix` appended to its last component
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Opens the database at `path`, waiting for others in this process to close it
+fn open_exclusive(path: &Path) -> Result<Db> {
+    let mut attempt = 1;
+    loop {
+        match sled::open(path) {
+            Ok(db) => return Ok(db),
+            Err(_) if attempt < OPEN_ATTEMPTS => {
+                attempt += 1;
+                thread::sleep(OPEN_RETRY);
+            }
+            Err(e) => bail!("The log is still in use: {}", e),
+        }
+    }
+}
+
+impl Log {
+    /// Bytes the log takes on disk, including space sled has yet to reclaim
+    pub fn size_on_disk(&self) -> Result<u64> {
+        Ok(self.db.size_on_disk()?)
+    }
+
+    /// Writes changes sled still buffers to disk, returning the bytes written. sled
+    /// flushes on its own every half second and when the log is dropped.
+    pub fn flush(&self) -> Result<usize> {
+        Ok(self.db.flush()?)
+    }
+
+    /// Rewrites the log at `path` into a fresh database and reopens it. sled keeps old
+    /// versions of changed values until it reuses their space, so a log that had many
+    /// edits or deletions takes far more space than its records need.
+    /// Every clone of the log must be dropped, e.g. those of background tasks, the log is
+    /// left closed if they are not soon. An encrypted log stays encrypted with the same key.
+    pub fn compact(self, path: &Path) -> Result<Self> {
+        self.check_writable()?;
+        let cipher = self.cipher.clone();
+        self.flush()?;
+        drop(self);
+
+        let old = open_exclusive(path)?;
+        let fresh = sibling(path, ".compacting");
+        let _ = remove_dir_all(&fresh);
+        let new = sled::open(&fresh)?;
+        new.import(old.export());
+        new.flush()?;
+        drop(new);
+        drop(old);
+        // keep the old log until the new one is in its place
+        let backup = sibling(path, ".old");
+        let _ = remove_dir_all(&backup);
+        rename(path, &backup)?;
+        rename(&fresh, path)?;
+        remove_dir_all(&backup)?;
+
+        Self::new_with_cipher(sled::open(path)?, cipher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs::remove_dir_all};
+
+    use super::sibling;
+    use crate::data::{FieldType, Log, LogHeader, LogRecord};
+
+    #[test]
+    pub fn test_compact() {
+        let path = env::temp_dir().join(format!("veelog-tests-compact-{}", std::process::id()));
+        let _ = remove_dir_all(&path);
+        let log = Log::open_path(&path, LogHeader::new("N0CALL", ""), None).unwrap();
+        let mut record = LogRecord::new();
+        record.insert_field(FieldType::WorkedCall, "W1AW");
+        let records = vec![record; 200];
+        let range = log.insert_records(records).unwrap();
+        for idx in range.clone().skip(1) {
+            for edit in 0..20 {
+                log.set_field(idx, FieldType::Comment, &edit.to_string().repeat(1000))
+                    .unwrap();
+            }
+            log.delete_record(idx).unwrap();
+        }
+        log.flush().unwrap();
+        let before = log.size_on_disk().unwrap();
+        assert!(before > 0);
+
+        // a clone still open keeps the log from being swapped
+        let clone = log.clone();
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(300));
+            drop(clone);
+        });
+        let log = log.compact(&path).unwrap();
+        handle.join().unwrap();
+        assert!(log.size_on_disk().unwrap() < before);
+        assert_eq!(1, log.get_records().len());
+        assert_eq!(range.end, log.get_idx());
+        assert_eq!(
+            Some("W1AW".to_string()),
+            log.get_record(range.start)
+                .unwrap()
+                .get_field(&FieldType::WorkedCall)
+        );
+        log.insert_record(LogRecord::new()).unwrap();
+        drop(log);
+        assert!(!sibling(&path, ".old").exists());
+        remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,229 @@
+use anyhow::Result;
+use util::{
+    callsign, freq,
+    geo::{GridStrictness, prettyvalidate_gridsquare},
+};
+
+use crate::data::{ConflictError, FieldType, Log, LogRecord};
+
+/// Which fixes `Log::normalize_all` applies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ruleset {
+    /// Upper case and trim calls
+    pub calls: bool,
+    /// Report frequencies that are no MHz value. Valid ones are stored in Hz and always
+    /// read back in their canonical form, e.g. `14.07400` as `14.074`.
+    pub frequencies: bool,
+    /// Case grids as `AA00aa`
+    pub grids: bool,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Self {
+            calls: true,
+            frequencies: true,
+            grids: true,
+        }
+    }
+}
+
+impl Ruleset {
+    /// The normalized value of a field, or None if no rule applies to it
+    fn normalize(&self, ty: &FieldType, val: &str) -> Option<Result<String>> {
+        match ty {
+            FieldType::WorkedCall if self.calls => Some(callsign::validate_callsign(val)),
+            FieldType::Frequency if self.frequencies => Some(freq::validate_frequency(val)),
+            FieldType::GridSquare if self.grids => Some(prettyvalidate_gridsquare(
+                val.trim(),
+                GridStrictness::Strict,
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// A field rewritten by normalization
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub idx: usize,
+    pub field: FieldType,
+    pub old: String,
+    pub new: String,
+}
+
+/// A field that failed validation and was left as is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub idx: usize,
+    pub field: FieldType,
+    pub value: String,
+    pub error: String,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct NormalizeReport {
+    pub changes: Vec<Change>,
+    pub problems: Vec<Problem>,
+}
+
+impl Log {
+    /// Reports what `normalize_all` would change without writing anything
+    pub fn normalize_preview(&self, ruleset: Ruleset) -> NormalizeReport {
+        self.normalized(ruleset).0
+    }
+
+    /// Normalizes every record in the log, writing all fixes in a single transaction.
+    /// If a record changed meanwhile, e.g. by a sync, the log is normalized again as it is
+    /// now rather than overwriting the change.
+    pub fn normalize_all(&self, ruleset: Ruleset) -> Result<NormalizeReport> {
+        loop {
+            let (report, records) = self.normalized(ruleset);
+            match self.modify_records_checked(records) {
+                Err(e) if e.is::<ConflictError>() => continue,
+                res => return res.map(|_| report),
+            }
+        }
+    }
+
+    /// Applies the ruleset to every record, returning the report and the changed records
+    /// with the revisions they were read at
+    fn normalized(&self, ruleset: Ruleset) -> (NormalizeReport, Vec<(usize, LogRecord, u64)>) {
+        let mut report = NormalizeReport::default();
+        let mut records = Vec::new();
+        for idx in 0..self.get_idx() {
+            let Some((mut record, revision)) = self.get_record_revision(idx) else {
+                continue;
+            };
+            let mut changed = false;
+            let fields = record
+                .iter()
+                .map(|(ty, val)| (ty.clone(), val.clone()))
+                .collect::<Vec<(FieldType, String)>>();
+            for (ty, old) in fields {
+                match ruleset.normalize(&ty, &old) {
+                    Some(Ok(new)) if new != old => {
+                        record.insert_field(ty.clone(), &new);
+                        report.changes.push(Change {
+                            idx,
+                            field: ty,
+                            old,
+                            new,
+                        });
+                        changed = true;
+                    }
+                    Some(Err(e)) => report.problems.push(Problem {
+                        idx,
+                        field: ty,
+                        value: old,
+                        error: e.to_string(),
+                    }),
+                    _ => {}
+                }
+            }
+            if changed {
+                records.push((idx, record, revision));
+            }
+        }
+        (report, records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Ruleset;
+    use crate::{
+        data::{ConflictError, FieldType, LogRecord},
+        test_log,
+    };
+
+    #[test]
+    pub fn test_normalize_all() {
+        let log = test_log();
+        let mut record = LogRecord::new();
+        record
+            .insert_field(FieldType::WorkedCall, "w1aw/p")
+            .insert_field(FieldType::Frequency, "14.07400")
+            .insert_field(FieldType::GridSquare, "fn31PR");
+        log.insert_record(record).unwrap();
+        let mut record = LogRecord::new();
+        record
+            .insert_field(FieldType::WorkedCall, "K1ABC")
+            .insert_field(FieldType::Frequency, "7,025")
+            .insert_field(FieldType::GridSquare, "FN3");
+        log.insert_record(record).unwrap();
+
+        let preview = log.normalize_preview(Ruleset::default());
+        assert_eq!(2, preview.changes.len());
+        assert_eq!(
+            vec![FieldType::Frequency, FieldType::GridSquare],
+            preview
+                .problems
+                .iter()
+                .map(|p| p.field.clone())
+                .collect::<Vec<_>>()
+        );
+        // the preview leaves the log alone
+        assert_eq!(
+            Some("w1aw/p".to_string()),
+            log.get_record(0).unwrap().get_field(&FieldType::WorkedCall)
+        );
+
+        let grids_only = Ruleset {
+            calls: false,
+            frequencies: false,
+            grids: true,
+        };
+        assert_eq!(1, log.normalize_preview(grids_only).changes.len());
+
+        let report = log.normalize_all(Ruleset::default()).unwrap();
+        assert_eq!(preview, report);
+        let fixed = log.get_record(0).unwrap();
+        assert_eq!(
+            Some("W1AW/P".to_string()),
+            fixed.get_field(&FieldType::WorkedCall)
+        );
+        assert_eq!(
+            Some("14.074".to_string()),
+            fixed.get_field(&FieldType::Frequency)
+        );
+        assert_eq!(
+            Some("FN31pr".to_string()),
+            fixed.get_field(&FieldType::GridSquare)
+        );
+        assert!(
+            log.normalize_all(Ruleset::default())
+                .unwrap()
+                .changes
+                .is_empty()
+        );
+    }
+
+    #[test]
+    pub fn test_normalize_changed_meanwhile() {
+        let log = test_log();
+        let mut record = LogRecord::new();
+        record.insert_field(FieldType::WorkedCall, "w1aw");
+        let idx = log.insert_record(record).unwrap();
+
+        let (_, records) = log.normalized(Ruleset::default());
+        // a sync changes the QSO after it was read
+        log.set_field(idx, FieldType::Name, "Hiram").unwrap();
+        let err = log.modify_records_checked(records).unwrap_err();
+        assert!(err.is::<ConflictError>());
+
+        assert_eq!(
+            1,
+            log.normalize_all(Ruleset::default()).unwrap().changes.len()
+        );
+        let record = log.get_record(idx).unwrap();
+        assert_eq!(
+            Some("W1AW".to_string()),
+            record.get_field(&FieldType::WorkedCall)
+        );
+        assert_eq!(
+            Some("Hiram".to_string()),
+            record.get_field(&FieldType::Name)
+        );
+    }
+}
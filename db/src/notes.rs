@@ -0,0 +1,165 @@
+use adif::data::{ADIFFile, ADIFType};
+use anyhow::Result;
+use bincode::{Decode, Encode};
+use jiff::Timestamp;
+
+use crate::{
+    data::{FieldType, Log, RecordId},
+    filter::Filter,
+    session::time_key,
+};
+
+/// Notes keyed by record id, then big endian millisecond time and a sequence number,
+/// so the notes of a record are one range in the order they were added
+const NOTES_TREE: &[u8] = b"NOTES";
+
+/// A timestamped remark on a QSO. Notes are only ever added, never edited.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct Note {
+    #[bincode(with_serde)]
+    pub time: Timestamp,
+    pub text: String,
+}
+
+impl Note {
+    pub fn new(time: Timestamp, text: &str) -> Self {
+        Self {
+            time,
+            text: text.trim().to_string(),
+        }
+    }
+
+    /// The note prefixed with its time, e.g. `2025-07-28 02:48Z worked him last year`
+    fn line(&self) -> String {
+        format!("{} {}", self.time.strftime("%Y-%m-%d %H:%MZ"), self.text)
+    }
+}
+
+/// The ADIF field notes are exported into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteExport {
+    /// Appended to COMMENT, which is a single line
+    Comment,
+    /// One line per note in the multiline NOTES field
+    Notes,
+}
+
+impl Log {
+    /// Appends a note to the record with id `id`
+    pub fn add_note(&self, id: RecordId, note: &Note) -> Result<()> {
+        self.check_writable()?;
+        let mut key = id.to_bytes().to_vec();
+        key.extend(time_key(note.time));
+        // notes added within the same millisecond keep their order
+        key.extend(self.db.generate_id()?.to_be_bytes());
+        self.db
+            .open_tree(NOTES_TREE)?
+            .insert(key, self.encode_log_record(note)?)?;
+        Ok(())
+    }
+
+    /// The notes of a record, oldest first
+    pub fn notes(&self, id: RecordId) -> Result<Vec<Note>> {
+        let mut notes = Vec::new();
+        for entry in self.db.open_tree(NOTES_TREE)?.scan_prefix(id.to_bytes()) {
+            let (_, enc) = entry?;
+            notes.push(self.decode_log_record(&enc)?);
+        }
+        Ok(notes)
+    }
+
+    pub(crate) fn remove_notes(&self, id: RecordId) -> Result<()> {
+        let tree = self.db.open_tree(NOTES_TREE)?;
+        for key in tree.scan_prefix(id.to_bytes()).keys() {
+            tree.remove(key?)?;
+        }
+        Ok(())
+    }
+
+    /// Exports the log like `export_adif`, with every record's notes added to `into`
+    pub fn export_adif_with_notes(&self, into: NoteExport) -> Result<ADIFFile> {
+        // notes are matched up with records by position
+        let mut adif = self.export_adif(&Filter::default())?;
+        let (name, separator) = match into {
+            NoteExport::Comment => ("COMMENT", "; "),
+            NoteExport::Notes => ("NOTES", "\r\n"),
+        };
+        let ty = FieldType::from_adif_field(name);
+        for (record, fields) in self.iter_records().zip(adif.body.iter_mut()) {
+            let Some(id) = record.id() else {
+                continue;
+            };
+            let notes = self.notes(id)?;
+            if notes.is_empty() {
+                continue;
+            }
+            let value = record
+                .get_field(&ty)
+                .into_iter()
+                .chain(notes.iter().map(Note::line))
+                .collect::<Vec<String>>()
+                .join(separator);
+            fields.0.retain(|(n, _)| n != name);
+            fields.0.push((name.to_string(), ADIFType::Str(value)));
+        }
+        Ok(adif)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use adif::data::ADIFType;
+
+    use super::{Note, NoteExport};
+    use crate::{
+        data::{FieldType, LogRecord},
+        test_log,
+    };
+
+    #[test]
+    pub fn test_notes() {
+        let log = test_log();
+        let mut record = LogRecord::new();
+        record
+            .insert_timestamp("2025-07-28T02:40:00Z".parse().unwrap())
+            .insert_field(FieldType::WorkedCall, "W1AW")
+            .insert_field(FieldType::Comment, "nice op");
+        let idx = log.insert_record(record).unwrap();
+        let id = log.record_id(idx).unwrap();
+        let time = "2025-07-28T02:48:13Z".parse().unwrap();
+        log.add_note(id, &Note::new(time, "QSB ")).unwrap();
+        log.add_note(id, &Note::new(time, "asked for a card"))
+            .unwrap();
+        assert_eq!(
+            vec!["QSB", "asked for a card"],
+            log.notes(id)
+                .unwrap()
+                .iter()
+                .map(|n| n.text.as_str())
+                .collect::<Vec<&str>>()
+        );
+
+        let field = |into, name: &str| {
+            let adif = log.export_adif_with_notes(into).unwrap();
+            adif.body[0]
+                .0
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| match v {
+                    ADIFType::Str(s) => s.clone(),
+                    _ => unreachable!(),
+                })
+        };
+        assert_eq!(
+            Some("nice op; 2025-07-28 02:48Z QSB; 2025-07-28 02:48Z asked for a card".to_string()),
+            field(NoteExport::Comment, "COMMENT")
+        );
+        assert_eq!(
+            Some("2025-07-28 02:48Z QSB\r\n2025-07-28 02:48Z asked for a card".to_string()),
+            field(NoteExport::Notes, "NOTES")
+        );
+
+        log.delete_record(idx).unwrap();
+        assert!(log.notes(id).unwrap().is_empty());
+    }
+}
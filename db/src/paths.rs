@@ -0,0 +1,172 @@
+use anyhow::Result;
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use crate::settings::Settings;
+
+/// Directory created for the program under the platform's config and data directories
+const APP_DIR: &str = "veelog";
+const SETTINGS_FILE: &str = "veelog.json";
+/// Log opened when none was opened before
+const DEFAULT_LOG: &str = "log";
+/// What was typed on the entry screen, see `draft::Draft`
+const DRAFT_FILE: &str = "draft.json";
+
+/// Where the settings, logs and other files the program keeps are stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paths {
+    /// Holds the settings file
+    pub config_dir: PathBuf,
+    /// Holds the logs, and files such as the solar data cache
+    pub data_dir: PathBuf,
+    /// Whether `data_dir` was given, e.g. by `--data-dir`, over the settings' `data_dir`
+    given: bool,
+}
+
+impl Paths {
+    /// The platform's directories: XDG on Linux and BSD, `%APPDATA%` on Windows and
+    /// `~/Library/Application Support` on macOS. A `data_dir` given keeps the settings
+    /// there too, for running from a USB stick.
+    pub fn new(data_dir: Option<PathBuf>) -> Self {
+        match data_dir {
+            Some(dir) => Self {
+                config_dir: dir.clone(),
+                data_dir: dir,
+                given: true,
+            },
+            None => {
+                let (config_dir, data_dir) =
+                    platform_dirs(env::consts::OS, |var| env::var_os(var).map(PathBuf::from));
+                Self {
+                    config_dir,
+                    data_dir,
+                    given: false,
+                }
+            }
+        }
+    }
+
+    /// Takes `--data-dir DIR` out of the command line arguments
+    pub fn from_args(args: &mut Vec<String>) -> Self {
+        let dir = match args.iter().position(|a| a == "--data-dir") {
+            Some(i) if i + 1 < args.len() => {
+                let dir = args.remove(i + 1);
+                args.remove(i);
+                Some(PathBuf::from(dir))
+            }
+            _ => None,
+        };
+        Self::new(dir)
+    }
+
+    pub fn settings_file(&self) -> PathBuf {
+        self.config_dir.join(SETTINGS_FILE)
+    }
+
+    /// Loads the settings and moves the data directory to theirs unless one was given.
+    /// Settings kept next to the program by earlier versions are picked up until saved
+    /// in their new place.
+    pub fn load_settings(&mut self) -> Result<Settings> {
+        let path = self.settings_file();
+        let settings = match !path.exists() && Path::new(SETTINGS_FILE).exists() {
+            true => Settings::load(Path::new(SETTINGS_FILE))?,
+            false => Settings::load(&path)?,
+        };
+        if !self.given && !settings.data_dir.is_empty() {
+            self.data_dir = PathBuf::from(&settings.data_dir);
+        }
+        Ok(settings)
+    }
+
+    /// `path` from the settings or typed in, relative paths are taken to be in the data
+    /// directory
+    pub fn data_file(&self, path: &str) -> PathBuf {
+        self.data_dir.join(path)
+    }
+
+    pub fn default_log(&self) -> PathBuf {
+        self.data_file(DEFAULT_LOG)
+    }
+
+    pub fn draft_file(&self) -> PathBuf {
+        self.data_file(DRAFT_FILE)
+    }
+}
+
+/// The config and data directories of the program on `os`, from environment variables
+/// read by `var`. Without a home directory they fall back to the current directory.
+fn platform_dirs(os: &str, var: impl Fn(&str) -> Option<PathBuf>) -> (PathBuf, PathBuf) {
+    let home = var("HOME").filter(|h| h.is_absolute());
+    let xdg = |name: &str, default: &str| {
+        var(name)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| home.as_ref().map(|h| h.join(default)))
+    };
+    let (config, data) = match os {
+        "windows" => {
+            let appdata = var("APPDATA");
+            (appdata.clone(), appdata)
+        }
+        "macos" => {
+            let support = home.as_ref().map(|h| h.join("Library/Application Support"));
+            (support.clone(), support)
+        }
+        _ => (
+            xdg("XDG_CONFIG_HOME", ".config"),
+            xdg("XDG_DATA_HOME", ".local/share"),
+        ),
+    };
+    let app_dir =
+        |dir: Option<PathBuf>| dir.map_or_else(|| PathBuf::from("."), |d| d.join(APP_DIR));
+    (app_dir(config), app_dir(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::PathBuf};
+
+    use super::{Paths, platform_dirs};
+
+    #[test]
+    pub fn test_platform_dirs() {
+        let env = HashMap::from([
+            ("HOME", "/home/ham"),
+            ("XDG_DATA_HOME", "/data"),
+            ("APPDATA", r"C:\Users\ham\AppData\Roaming"),
+        ]);
+        let var = |name: &str| env.get(name).map(PathBuf::from);
+        assert_eq!(
+            (
+                PathBuf::from("/home/ham/.config/veelog"),
+                PathBuf::from("/data/veelog")
+            ),
+            platform_dirs("linux", var)
+        );
+        let (config, data) = platform_dirs("macos", var);
+        assert_eq!(
+            PathBuf::from("/home/ham/Library/Application Support/veelog"),
+            config
+        );
+        assert_eq!(config, data);
+        let (config, _) = platform_dirs("windows", var);
+        assert_eq!(
+            PathBuf::from(r"C:\Users\ham\AppData\Roaming").join("veelog"),
+            config
+        );
+        assert_eq!(
+            (PathBuf::from("."), PathBuf::from(".")),
+            platform_dirs("linux", |_| None)
+        );
+
+        let mut args = ["veelog", "--data-dir", "/mnt/usb", "log"]
+            .map(String::from)
+            .to_vec();
+        let paths = Paths::from_args(&mut args);
+        assert_eq!(["veelog", "log"].map(String::from).to_vec(), args);
+        assert_eq!(PathBuf::from("/mnt/usb/veelog.json"), paths.settings_file());
+        assert_eq!(PathBuf::from("/mnt/usb/log"), paths.default_log());
+        assert_eq!(PathBuf::from("/tmp/other"), paths.data_file("/tmp/other"));
+    }
+}
@@ -0,0 +1,141 @@
+use std::{fmt::Display, path::Path};
+
+use anyhow::Result;
+use bincode::{Decode, Encode};
+use jiff::Timestamp;
+use sled::Tree;
+
+use crate::data::{Log, RecordId};
+
+/// Provenance keyed by record id
+const PROVENANCE_TREE: &[u8] = b"PROVENANCE";
+
+/// Where a QSO came from
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum Source {
+    /// Typed into the entry screen, the paper log or the terminal UI
+    Manual,
+    /// Logged after clicking a cluster spot
+    Cluster,
+    /// Imported from the file with this name
+    Import(String),
+    /// Pasted from the clipboard
+    Clipboard,
+    /// Logged by another program, e.g. `N1MM` for QSOs broadcast by N1MM Logger+
+    Program(String),
+    /// Received from the station the log is synced with
+    Sync,
+    /// Broadcast by WSJT-X when the QSO was logged there
+    Wsjtx,
+}
+
+impl Source {
+    /// Imported from the file at `path`, named without its directory
+    pub fn import(path: &Path) -> Self {
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        Self::Import(name.to_string_lossy().to_string())
+    }
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Manual => write!(f, "entered by hand"),
+            Source::Cluster => write!(f, "logged from a cluster spot"),
+            Source::Import(file) => write!(f, "imported from {}", file),
+            Source::Clipboard => write!(f, "pasted from the clipboard"),
+            Source::Program(name) => write!(f, "logged by {}", name),
+            Source::Sync => write!(f, "synced from another station"),
+            Source::Wsjtx => write!(f, "logged by WSJT-X"),
+        }
+    }
+}
+
+/// Where and when a QSO entered the log. Kept beside the record rather than in it,
+/// so it is not exported and survives edits.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct Provenance {
+    pub source: Source,
+    #[bincode(with_serde)]
+    pub inserted: Timestamp,
+}
+
+impl Log {
+    pub(crate) fn provenance_tree(&self) -> Result<Tree> {
+        Ok(self.db.open_tree(PROVENANCE_TREE)?)
+    }
+
+    /// The provenance of records inserted from `source` now, see `insert_records_from`
+    pub(crate) fn encode_provenance(&self, source: &Source) -> Result<Vec<u8>> {
+        self.encode_log_record(Provenance {
+            source: source.clone(),
+            inserted: Timestamp::now(),
+        })
+    }
+
+    /// Where a record came from, None for records inserted before provenance was kept
+    pub fn provenance(&self, id: RecordId) -> Result<Option<Provenance>> {
+        match self.provenance_tree()?.get(id.to_bytes())? {
+            Some(enc) => Ok(Some(self.decode_log_record(&enc)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn remove_provenance(&self, id: RecordId) -> Result<()> {
+        self.provenance_tree()?.remove(id.to_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use adif::parse::parse_adif;
+    use jiff::tz::TimeZone;
+
+    use super::Source;
+    use crate::{
+        data::{FieldType, ImportPolicy, LogRecord},
+        test_log,
+    };
+
+    #[test]
+    pub fn test_provenance() {
+        let log = test_log();
+        let mut record = LogRecord::new();
+        record
+            .insert_timestamp("2025-07-28T02:48:00Z".parse().unwrap())
+            .insert_field(FieldType::WorkedCall, "W1AW");
+        let idx = log.insert_record(record.clone()).unwrap();
+        let id = log.record_id(idx).unwrap();
+        assert_eq!(None, log.provenance(id).unwrap());
+        assert!(log.last_written(id).unwrap().is_some());
+        log.delete_record(idx).unwrap();
+
+        let idx = log.insert_record_from(record, &Source::Manual).unwrap();
+        let id = log.record_id(idx).unwrap();
+        let provenance = log.provenance(id).unwrap().unwrap();
+        assert_eq!(Source::Manual, provenance.source);
+        // edits keep it
+        log.set_field(idx, FieldType::Name, "Hiram").unwrap();
+        assert_eq!(Some(provenance), log.provenance(id).unwrap());
+
+        let adif = parse_adif(
+            "<CALL:6>DL1ABC<QSO_DATE:8>20250728<TIME_ON:6>030000<EOR>\
+             <CALL:6>JA1XYZ<QSO_DATE:8>20250728<TIME_ON:6>030100<EOR>",
+        );
+        let source = Source::import(Path::new("/home/op/logs/field-day.adi"));
+        let range = log
+            .import_adif_from(adif, ImportPolicy::PreserveAll, &TimeZone::UTC, &source)
+            .unwrap();
+        for idx in range {
+            let id = log.record_id(idx).unwrap();
+            assert_eq!(source, log.provenance(id).unwrap().unwrap().source);
+        }
+        assert_eq!("imported from field-day.adi", source.to_string());
+
+        log.delete_record(idx).unwrap();
+        assert_eq!(None, log.provenance(id).unwrap());
+    }
+}
@@ -0,0 +1,291 @@
+use std::{collections::BTreeMap, fmt::Write as _, fs::File, io::Write, path::Path};
+
+use anyhow::Result;
+use csv::WriterBuilder;
+use jiff::{SignedDuration, Timestamp};
+use util::{band::Band, mode::ModeClass};
+
+use crate::{
+    data::{FieldType, Log, LogRecord},
+    filter::Filter,
+};
+
+const MODE_CLASSES: [ModeClass; 3] = [ModeClass::Cw, ModeClass::Phone, ModeClass::Digital];
+/// Windows of the best rates in the summary, in minutes
+const BEST_RATE_MINUTES: [i64; 2] = [60, 10];
+
+/// QSO counts by UTC hour and band, and by band and mode, like the rate sheets and
+/// summaries of contest loggers, for write-ups after a contest
+#[derive(Debug, Default, PartialEq)]
+pub struct RateSheet {
+    /// QSOs on each band, keyed by the start of the hour they were made in.
+    /// QSOs without a known band are under None.
+    pub hours: BTreeMap<Timestamp, BTreeMap<Option<Band>, usize>>,
+    /// QSOs on each band by mode class, including those without a time
+    pub band_modes: BTreeMap<Option<Band>, BTreeMap<ModeClass, usize>>,
+    /// Times of the QSOs in ascending order, for the best rates
+    times: Vec<Timestamp>,
+    pub qsos: usize,
+}
+
+fn hour_of(time: Timestamp) -> Timestamp {
+    Timestamp::from_second(time.as_second().div_euclid(3600) * 3600).unwrap_or(time)
+}
+
+fn band_name(band: Option<Band>) -> &'static str {
+    band.map_or("?", |b| b.name())
+}
+
+impl RateSheet {
+    pub fn from_records<'a>(records: impl IntoIterator<Item = &'a LogRecord>) -> Self {
+        let mut sheet = Self::default();
+        for record in records {
+            sheet.add(record);
+        }
+        sheet
+    }
+
+    pub fn add(&mut self, record: &LogRecord) {
+        self.qsos += 1;
+        let band = record.frequency().and_then(Band::from_freq);
+        let mode = record
+            .get_field(&FieldType::Mode)
+            .map(|m| ModeClass::from_mode(&m))
+            .unwrap_or(ModeClass::Phone);
+        *self
+            .band_modes
+            .entry(band)
+            .or_default()
+            .entry(mode)
+            .or_default() += 1;
+        if let Some(time) = record
+            .get_field(&FieldType::Timestamp)
+            .and_then(|t| t.parse::<Timestamp>().ok())
+        {
+            *self
+                .hours
+                .entry(hour_of(time))
+                .or_default()
+                .entry(band)
+                .or_default() += 1;
+            let pos = self.times.partition_point(|t| *t <= time);
+            self.times.insert(pos, time);
+        }
+    }
+
+    /// Bands QSOs were made on, the columns of the hourly table
+    pub fn bands(&self) -> Vec<Option<Band>> {
+        self.band_modes.keys().copied().collect()
+    }
+
+    /// The hours QSOs were made in, with the QSOs on each band of `bands`. Hours off
+    /// the air are left out, so a log spanning months stays short.
+    pub fn hourly(&self) -> Vec<(Timestamp, Vec<usize>)> {
+        let bands = self.bands();
+        self.hours
+            .iter()
+            .map(|(hour, counts)| {
+                let row = bands
+                    .iter()
+                    .map(|b| counts.get(b).copied().unwrap_or(0))
+                    .collect();
+                (*hour, row)
+            })
+            .collect()
+    }
+
+    /// The most QSOs made within `minutes`, with the time of the first of them
+    pub fn best_rate(&self, minutes: i64) -> Option<(Timestamp, usize)> {
+        let window = SignedDuration::from_mins(minutes);
+        let mut best: Option<(Timestamp, usize)> = None;
+        let mut end = 0;
+        for (start, time) in self.times.iter().enumerate() {
+            while end < self.times.len() && self.times[end] < *time + window {
+                end += 1;
+            }
+            if best.is_none_or(|(_, qsos)| end - start > qsos) {
+                best = Some((*time, end - start));
+            }
+        }
+        best
+    }
+
+    /// The sheet as plain text tables, to paste into a write-up
+    pub fn to_text(&self) -> Result<String> {
+        let bands = self.bands();
+        let mut text = format!("{} QSOs\n\n{:<16}", self.qsos, "Hour (UTC)");
+        for band in &bands {
+            write!(text, "{:>7}", band_name(*band))?;
+        }
+        writeln!(text, "{:>7}{:>7}", "Total", "Cum")?;
+        let mut cumulative = 0;
+        for (hour, counts) in self.hourly() {
+            let total: usize = counts.iter().sum();
+            cumulative += total;
+            write!(text, "{:<16}", hour.strftime("%Y-%m-%d %H:%M"))?;
+            for count in counts {
+                write!(text, "{:>7}", count)?;
+            }
+            writeln!(text, "{:>7}{:>7}", total, cumulative)?;
+        }
+
+        write!(text, "\n{:<16}", "Band")?;
+        for mode in MODE_CLASSES {
+            write!(text, "{:>8}", mode.to_string())?;
+        }
+        writeln!(text, "{:>8}", "Total")?;
+        for (band, modes) in &self.band_modes {
+            write!(text, "{:<16}", band_name(*band))?;
+            for mode in MODE_CLASSES {
+                write!(text, "{:>8}", modes.get(&mode).copied().unwrap_or(0))?;
+            }
+            writeln!(text, "{:>8}", modes.values().sum::<usize>())?;
+        }
+
+        text += "\n";
+        for minutes in BEST_RATE_MINUTES {
+            if let Some((start, qsos)) = self.best_rate(minutes) {
+                writeln!(
+                    text,
+                    "Best {} minutes: {} QSOs ({}/h) from {}",
+                    minutes,
+                    qsos,
+                    qsos as i64 * 60 / minutes,
+                    start.strftime("%Y-%m-%d %H:%MZ")
+                )?;
+            }
+        }
+        Ok(text)
+    }
+
+    /// Writes the hourly table, then after an empty line the band and mode table, as CSV
+    /// for spreadsheets
+    pub fn write_csv(&self, mut out: impl Write) -> Result<()> {
+        let bands = self.bands();
+        let mut writer = WriterBuilder::new().from_writer(&mut out);
+        let mut header = vec!["Hour".to_string()];
+        header.extend(bands.iter().map(|b| band_name(*b).to_string()));
+        header.extend(["Total".to_string(), "Cumulative".to_string()]);
+        writer.write_record(&header)?;
+        let mut cumulative = 0;
+        for (hour, counts) in self.hourly() {
+            let total: usize = counts.iter().sum();
+            cumulative += total;
+            let mut row = vec![hour.strftime("%Y-%m-%d %H:%M").to_string()];
+            row.extend(counts.iter().map(usize::to_string));
+            row.extend([total.to_string(), cumulative.to_string()]);
+            writer.write_record(&row)?;
+        }
+        // the tables differ in columns, each gets its own writer
+        writer.flush()?;
+        drop(writer);
+        out.write_all(b"\n")?;
+        let mut writer = WriterBuilder::new().from_writer(&mut out);
+        let mut header = vec!["Band".to_string()];
+        header.extend(MODE_CLASSES.iter().map(ModeClass::to_string));
+        header.push("Total".to_string());
+        writer.write_record(&header)?;
+        for (band, modes) in &self.band_modes {
+            let mut row = vec![band_name(*band).to_string()];
+            row.extend(
+                MODE_CLASSES
+                    .iter()
+                    .map(|m| modes.get(m).copied().unwrap_or(0).to_string()),
+            );
+            row.push(modes.values().sum::<usize>().to_string());
+            writer.write_record(&row)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Log {
+    /// The rate sheet of the records `filter` selects, e.g. the QSOs of a contest session
+    pub fn rate_sheet(&self, filter: &Filter) -> Result<RateSheet> {
+        let (records, _) = self.filter_records(filter)?;
+        Ok(RateSheet::from_records(&records))
+    }
+
+    /// Writes the rate sheet to `path`, as CSV if the file name ends in `.csv` and as
+    /// text otherwise. The export watermark is left alone.
+    pub fn export_rate_sheet(&self, path: &Path, filter: &Filter) -> Result<()> {
+        let sheet = self.rate_sheet(filter)?;
+        match path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+        {
+            true => sheet.write_csv(File::create(path)?),
+            false => Ok(std::fs::write(path, sheet.to_text()?)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::Timestamp;
+    use util::band::Band;
+
+    use super::RateSheet;
+    use crate::data::{FieldType, LogRecord};
+
+    #[test]
+    pub fn test_rate_sheet() {
+        let qso = |time: &str, freq: &str, mode: &str| {
+            let mut record = LogRecord::new();
+            record
+                .insert_timestamp(time.parse().unwrap())
+                .insert_field(FieldType::Frequency, freq)
+                .insert_field(FieldType::Mode, mode);
+            record
+        };
+        let records = [
+            qso("2025-11-29T00:05:00Z", "14.025", "CW"),
+            qso("2025-11-29T00:50:00Z", "14.030", "CW"),
+            qso("2025-11-29T00:55:00Z", "7.025", "CW"),
+            qso("2025-11-29T01:02:00Z", "7.150", "SSB"),
+            qso("2025-11-29T03:10:00Z", "14.080", "FT8"),
+        ];
+        let sheet = RateSheet::from_records(&records);
+        assert_eq!(5, sheet.qsos);
+        assert_eq!(vec![Some(Band::M40), Some(Band::M20)], sheet.bands());
+        // the hour off the air is left out
+        let hourly = sheet.hourly();
+        assert_eq!(3, hourly.len());
+        assert_eq!(
+            "2025-11-29T00:00:00Z".parse::<Timestamp>().unwrap(),
+            hourly[0].0
+        );
+        assert_eq!(vec![1, 2], hourly[0].1);
+        assert_eq!(vec![0, 1], hourly[2].1);
+
+        let (start, qsos) = sheet.best_rate(60).unwrap();
+        assert_eq!(
+            (4, "2025-11-29T00:05:00Z".to_string()),
+            (qsos, start.to_string())
+        );
+        assert_eq!(3, sheet.best_rate(15).unwrap().1);
+        assert_eq!(None, RateSheet::default().best_rate(60));
+
+        let text = sheet.to_text().unwrap();
+        assert!(text.starts_with("5 QSOs\n"));
+        assert!(text.contains("2025-11-29 01:00      1      0      1      4\n"));
+        assert!(text.contains("40m                    1       1       0       2\n"));
+        assert!(text.contains("Best 60 minutes: 4 QSOs (4/h) from 2025-11-29 00:05Z\n"));
+        assert!(text.contains("Best 10 minutes: 2 QSOs (12/h) from 2025-11-29 00:50Z\n"));
+
+        let mut csv = Vec::new();
+        sheet.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            "Hour,40m,20m,Total,Cumulative\n\
+             2025-11-29 00:00,1,2,3,3\n\
+             2025-11-29 01:00,1,0,1,4\n\
+             2025-11-29 03:00,0,1,1,5\n\
+             \n\
+             Band,CW,Phone,Digital,Total\n\
+             40m,1,1,0,2\n\
+             20m,2,0,1,3\n",
+            String::from_utf8(csv).unwrap()
+        );
+    }
+}
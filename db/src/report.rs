@@ -0,0 +1,281 @@
+use std::{fmt::Write, fs, path::Path};
+
+use anyhow::Result;
+use indexmap::IndexMap;
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::{FieldType, Log, LogRecord},
+    filter::Filter,
+};
+
+/// Label sheets QSL labels can be printed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelLayout {
+    /// US letter, 3 x 10 labels of 66.7 x 25.4 mm
+    Avery5160,
+    /// A4, 3 x 7 labels of 63.5 x 38.1 mm
+    L7160,
+    /// A4, 2 x 7 labels of 99.1 x 38.1 mm
+    L7163,
+}
+
+/// Dimensions of a label sheet in mm
+struct Sheet {
+    page: (f32, f32),
+    /// Page margins to the first label, top and left
+    margin: (f32, f32),
+    label: (f32, f32),
+    /// Horizontal gap between labels
+    gap: f32,
+    columns: usize,
+    rows: usize,
+    /// QSOs fitting on one label
+    qsos: usize,
+}
+
+impl LabelLayout {
+    fn sheet(&self) -> Sheet {
+        match self {
+            LabelLayout::Avery5160 => Sheet {
+                page: (215.9, 279.4),
+                margin: (12.7, 4.8),
+                label: (66.7, 25.4),
+                gap: 3.2,
+                columns: 3,
+                rows: 10,
+                qsos: 2,
+            },
+            LabelLayout::L7160 => Sheet {
+                page: (210.0, 297.0),
+                margin: (15.1, 7.2),
+                label: (63.5, 38.1),
+                gap: 2.5,
+                columns: 3,
+                rows: 7,
+                qsos: 4,
+            },
+            LabelLayout::L7163 => Sheet {
+                page: (210.0, 297.0),
+                margin: (15.1, 4.7),
+                label: (99.1, 38.1),
+                gap: 2.5,
+                columns: 2,
+                rows: 7,
+                qsos: 4,
+            },
+        }
+    }
+}
+
+/// Escapes text for HTML element content
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Date and UTC time of a QSO as printed, e.g. `2025-07-28` and `0248`
+fn date_time(record: &LogRecord) -> (String, String) {
+    record
+        .get_field(&FieldType::Timestamp)
+        .and_then(|t| t.parse::<Timestamp>().ok())
+        .map(|t| {
+            (
+                t.strftime("%Y-%m-%d").to_string(),
+                t.strftime("%H%M").to_string(),
+            )
+        })
+        .unwrap_or_default()
+}
+
+fn field(record: &LogRecord, ty: FieldType) -> String {
+    escape(&record.get_field(&ty).unwrap_or_default())
+}
+
+fn page(title: &str, style: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        style,
+        body
+    )
+}
+
+impl Log {
+    /// The records `filter` selects as a printable HTML table. Browsers print it or save it
+    /// as PDF.
+    pub fn report_html(&self, title: &str, filter: &Filter) -> Result<String> {
+        let (records, _) = self.filter_records(filter)?;
+        let mut body = format!(
+            "<h1>{}</h1>\n<table>\n<tr><th>Date</th><th>UTC</th><th>Call</th><th>Band</th>\
+             <th>MHz</th><th>Mode</th><th>Sent</th><th>Rcvd</th><th>Name</th>\
+             <th>Comment</th></tr>\n",
+            escape(title)
+        );
+        for record in &records {
+            let (date, time) = date_time(record);
+            writeln!(
+                body,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+                 <td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                date,
+                time,
+                field(record, FieldType::WorkedCall),
+                escape(&record.band().unwrap_or_default()),
+                field(record, FieldType::Frequency),
+                escape(&record.display_mode().unwrap_or_default()),
+                field(record, FieldType::SentRST),
+                field(record, FieldType::RcvdRST),
+                field(record, FieldType::Name),
+                field(record, FieldType::Comment),
+            )?;
+        }
+        writeln!(body, "</table>\n<p>{} QSOs</p>", records.len())?;
+        let style = "body { font-family: sans-serif; font-size: 10pt; }\n\
+                     table { border-collapse: collapse; width: 100%; }\n\
+                     th, td { border: 1px solid #888; padding: 2px 4px; text-align: left; }\n\
+                     tr { break-inside: avoid; }\n";
+        Ok(page(title, style, &body))
+    }
+
+    /// QSL labels for the records `filter` selects, laid out for printing on `layout`
+    /// sheets at 100% scale. QSOs with the same station share labels.
+    pub fn qsl_labels_html(&self, filter: &Filter, layout: LabelLayout) -> Result<String> {
+        Ok(self.write_qsl_labels(filter, layout)?.0)
+    }
+
+    /// The labels page and where the export watermark moves to once it is printed
+    fn write_qsl_labels(&self, filter: &Filter, layout: LabelLayout) -> Result<(String, usize)> {
+        let (records, end) = self.filter_records(filter)?;
+        let mut by_call: IndexMap<String, Vec<&LogRecord>> = IndexMap::new();
+        for record in &records {
+            let Some(call) = record.get_field(&FieldType::WorkedCall) else {
+                continue;
+            };
+            by_call.entry(call).or_default().push(record);
+        }
+        let sheet = layout.sheet();
+        let mut labels = Vec::new();
+        for (call, qsos) in &by_call {
+            for chunk in qsos.chunks(sheet.qsos) {
+                let mut label = format!(
+                    "<div class=\"label\"><b>To Radio {}</b>\n<table>\n<tr><th>Date</th>\
+                     <th>UTC</th><th>Band</th><th>Mode</th><th>RST</th></tr>\n",
+                    escape(call)
+                );
+                for record in chunk {
+                    let (date, time) = date_time(record);
+                    writeln!(
+                        label,
+                        "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                        date,
+                        time,
+                        escape(&record.band().unwrap_or_default()),
+                        escape(&record.display_mode().unwrap_or_default()),
+                        field(record, FieldType::SentRST),
+                    )?;
+                }
+                label += "</table></div>\n";
+                labels.push(label);
+            }
+        }
+        let mut body = String::new();
+        for labels in labels.chunks(sheet.columns * sheet.rows) {
+            body += "<div class=\"sheet\">\n";
+            body.extend(labels.iter().map(String::as_str));
+            body += "</div>\n";
+        }
+        let style = format!(
+            "@page {{ size: {}mm {}mm; margin: 0; }}\n\
+             body {{ margin: 0; font-family: sans-serif; font-size: 7pt; }}\n\
+             .sheet {{ box-sizing: border-box; height: {}mm; padding: {}mm 0 0 {}mm; \
+             display: grid; grid-template-columns: repeat({}, {}mm); grid-auto-rows: {}mm; \
+             column-gap: {}mm; break-after: page; }}\n\
+             .label {{ box-sizing: border-box; padding: 1.5mm 2mm; overflow: hidden; }}\n\
+             .label table {{ border-collapse: collapse; width: 100%; }}\n\
+             .label td, .label th {{ border: 0.2mm solid #000; padding: 0 1mm; }}\n",
+            sheet.page.0,
+            sheet.page.1,
+            sheet.page.1,
+            sheet.margin.0,
+            sheet.margin.1,
+            sheet.columns,
+            sheet.label.0,
+            sheet.label.1,
+            sheet.gap,
+        );
+        Ok((page("QSL labels", &style, &body), end))
+    }
+
+    /// Writes the printable log to `path`
+    pub fn export_report(&self, path: &Path, title: &str, filter: &Filter) -> Result<()> {
+        fs::write(path, self.report_html(title, filter)?)?;
+        Ok(())
+    }
+
+    /// Writes QSL labels to `path`. Moves the export watermark if the filter names one,
+    /// so labels are only printed once for each QSO.
+    pub fn export_qsl_labels(
+        &self,
+        path: &Path,
+        filter: &Filter,
+        layout: LabelLayout,
+    ) -> Result<()> {
+        let (html, end) = self.write_qsl_labels(filter, layout)?;
+        fs::write(path, html)?;
+        self.mark_exported(filter, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LabelLayout;
+    use crate::{
+        data::{FieldType, LogRecord},
+        filter::Filter,
+        test_log,
+    };
+
+    #[test]
+    pub fn test_report() {
+        let log = test_log();
+        let qso = |call: &str, time: &str| {
+            let mut record = LogRecord::new();
+            record
+                .insert_timestamp(time.parse().unwrap())
+                .insert_field(FieldType::WorkedCall, call)
+                .insert_field(FieldType::Frequency, "14.025")
+                .insert_field(FieldType::Mode, "CW")
+                .insert_field(FieldType::SentRST, "599")
+                .insert_field(FieldType::Comment, "<b>rig</b> & wire");
+            record
+        };
+        log.insert_records(vec![
+            qso("W1AW", "2025-07-28T02:48:00Z"),
+            qso("DL1ABC", "2025-07-28T03:00:00Z"),
+            qso("W1AW", "2025-07-29T14:05:00Z"),
+        ])
+        .unwrap();
+
+        let report = log
+            .report_html("Log of N0CALL", &Filter::default())
+            .unwrap();
+        assert!(report.contains(
+            "<tr><td>2025-07-28</td><td>0248</td><td>W1AW</td><td>20m</td><td>14.025</td>\
+             <td>CW</td><td>599</td><td></td><td></td><td>&lt;b&gt;rig&lt;/b&gt; &amp; wire</td></tr>"
+        ));
+        assert!(report.contains("<p>3 QSOs</p>"));
+
+        let labels = log
+            .qsl_labels_html(&Filter::default(), LabelLayout::L7163)
+            .unwrap();
+        assert_eq!(2, labels.matches("class=\"label\"").count());
+        assert_eq!(1, labels.matches("To Radio W1AW").count());
+        assert!(labels.contains("<td>2025-07-29</td><td>1405</td><td>20m</td><td>CW</td>"));
+        assert!(labels.contains("size: 210mm 297mm"));
+    }
+}
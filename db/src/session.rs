@@ -0,0 +1,347 @@
+use std::{fmt::Display, ops::RangeFrom, str::FromStr};
+
+use anyhow::Result;
+use bincode::{Decode, Encode};
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+use util::{band::Band, freq::Frequency};
+
+use crate::{data::Log, equipment::EquipmentId, filter::Filter, stats::Stats};
+
+/// Rig history, keyed by big endian millisecond timestamps so it iterates in time order
+const SESSION_TREE: &[u8] = b"SESSION";
+/// Operating sessions keyed by `SessionId`, so they iterate in the order they were started
+const SESSIONS_TREE: &[u8] = b"SESSIONS";
+
+/// What an operating session is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionKind {
+    Contest,
+    /// A Parks on the Air activation
+    Pota,
+    #[default]
+    Casual,
+}
+
+impl SessionKind {
+    pub const ALL: [SessionKind; 3] =
+        [SessionKind::Contest, SessionKind::Pota, SessionKind::Casual];
+}
+
+impl Display for SessionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SessionKind::Contest => "Contest",
+            SessionKind::Pota => "POTA",
+            SessionKind::Casual => "Casual",
+        })
+    }
+}
+
+/// Stable identity of a session, stored in the `FieldType::Session` field of its QSOs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SessionId(Ulid);
+
+impl Display for SessionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for SessionId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self(Ulid::from_string(s)?))
+    }
+}
+
+/// A stretch of operating, such as a contest or a POTA activation, and the station used
+#[derive(Debug, Clone, PartialEq, Default, Encode, Decode)]
+pub struct Session {
+    pub kind: SessionKind,
+    /// Contest name or park reference, e.g. `CQ-WW-CW` or `US-1234`
+    pub name: String,
+    #[bincode(with_serde)]
+    pub start: Timestamp,
+    /// None while the session runs
+    #[bincode(with_serde)]
+    pub end: Option<Timestamp>,
+    pub location: String,
+    /// Our Maidenhead locator during the session
+    pub grid: String,
+    /// Station used, for QSOs that don't list their own `FieldType::Equipment`
+    pub equipment: Vec<EquipmentId>,
+    /// Transmit power in watts, 0 if unknown
+    pub power: u32,
+}
+
+/// What the rig was tuned to from `time` until the next sample
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct RigSample {
+    #[bincode(with_serde)]
+    pub time: Timestamp,
+    pub freq_hz: u64,
+    pub mode: String,
+}
+
+impl RigSample {
+    pub fn frequency(&self) -> Frequency {
+        Frequency::from_hz(self.freq_hz)
+    }
+
+    pub fn band(&self) -> Option<Band> {
+        Band::from_freq(self.frequency())
+    }
+}
+
+/// A stretch of time spent on one band, None when outside the amateur bands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BandSpan {
+    pub band: Option<Band>,
+    pub start: Timestamp,
+    pub end: Timestamp,
+}
+
+impl Log {
+    /// Records the rig's frequency and mode. Samples are only stored when the rig
+    /// was retuned or changed mode, returns whether this one was.
+    pub fn record_rig_sample(&self, time: Timestamp, freq: Frequency, mode: &str) -> Result<bool> {
+        self.check_writable()?;
+        let tree = self.db.open_tree(SESSION_TREE)?;
+        if let Some((_, last)) = tree.last()?
+            && let Ok(last) = self.decode_log_record::<RigSample>(&last)
+            && last.freq_hz == freq.hz()
+            && last.mode == mode
+        {
+            return Ok(false);
+        }
+        let sample = RigSample {
+            time,
+            freq_hz: freq.hz(),
+            mode: mode.to_string(),
+        };
+        tree.insert(time_key(time), self.encode_log_record(sample)?)?;
+        Ok(true)
+    }
+
+    /// Starts a session, ending the running one at the new session's start
+    pub fn start_session(&self, session: Session) -> Result<SessionId> {
+        self.check_writable()?;
+        self.end_session(session.start)?;
+        let tree = self.db.open_tree(SESSIONS_TREE)?;
+        let mut id = Ulid::new();
+        // ids made within the same millisecond don't sort by creation on their own
+        if let Some((last, _)) = tree.last()?
+            && let Ok(last) = <[u8; 16]>::try_from(last.as_ref()).map(Ulid::from_bytes)
+            && id <= last
+            && let Some(next) = last.increment()
+        {
+            id = next;
+        }
+        tree.insert(id.to_bytes(), self.encode_log_record(session)?)?;
+        Ok(SessionId(id))
+    }
+
+    /// Ends the running session, returning its id if there was one
+    pub fn end_session(&self, end: Timestamp) -> Result<Option<SessionId>> {
+        self.check_writable()?;
+        let Some((id, mut session)) = self.active_session()? else {
+            return Ok(None);
+        };
+        session.end = Some(end.max(session.start));
+        self.db
+            .open_tree(SESSIONS_TREE)?
+            .insert(id.0.to_bytes(), self.encode_log_record(session)?)?;
+        Ok(Some(id))
+    }
+
+    /// The session new QSOs belong to, if one is running
+    pub fn active_session(&self) -> Result<Option<(SessionId, Session)>> {
+        let Some((key, enc)) = self.db.open_tree(SESSIONS_TREE)?.last()? else {
+            return Ok(None);
+        };
+        let session: Session = self.decode_log_record(&enc)?;
+        match session.end {
+            Some(_) => Ok(None),
+            None => Ok(Some((
+                SessionId(Ulid::from_bytes(key.as_ref().try_into()?)),
+                session,
+            ))),
+        }
+    }
+
+    /// Every session, in the order they were started
+    pub fn sessions(&self) -> Result<Vec<(SessionId, Session)>> {
+        let mut sessions = Vec::new();
+        for entry in self.db.open_tree(SESSIONS_TREE)?.iter() {
+            let (key, enc) = entry?;
+            let id = SessionId(Ulid::from_bytes(key.as_ref().try_into()?));
+            sessions.push((id, self.decode_log_record(&enc)?));
+        }
+        Ok(sessions)
+    }
+
+    /// QSO counts of the QSOs logged in a session
+    pub fn session_stats(&self, id: SessionId) -> Result<Stats> {
+        let filter = Filter {
+            session: Some(id),
+            ..Default::default()
+        };
+        let (records, _) = self.filter_records(&filter)?;
+        Ok(Stats::from_records(&records))
+    }
+
+    /// The rig samples recorded since `range.start`, oldest first
+    pub fn rig_history(&self, range: RangeFrom<Timestamp>) -> Result<Vec<RigSample>> {
+        let tree = self.db.open_tree(SESSION_TREE)?;
+        let mut samples = Vec::new();
+        for entry in tree.range(time_key(range.start)..) {
+            let (_, enc) = entry?;
+            samples.push(self.decode_log_record(&enc)?);
+        }
+        Ok(samples)
+    }
+}
+
+pub(crate) fn time_key(time: Timestamp) -> [u8; 8] {
+    // flip the sign bit so that timestamps before 1970 still sort first
+    (time.as_millisecond() as u64 ^ (1 << 63)).to_be_bytes()
+}
+
+/// Merges consecutive samples on the same band into spans, the last one ending at `now`
+pub fn band_timeline(samples: &[RigSample], now: Timestamp) -> Vec<BandSpan> {
+    let mut spans: Vec<BandSpan> = Vec::new();
+    for sample in samples {
+        let band = sample.band();
+        if let Some(last) = spans.last_mut() {
+            last.end = sample.time;
+            if last.band == band {
+                continue;
+            }
+        }
+        spans.push(BandSpan {
+            band,
+            start: sample.time,
+            end: sample.time,
+        });
+    }
+    if let Some(last) = spans.last_mut() {
+        last.end = now.max(last.start);
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::Timestamp;
+    use util::{band::Band, freq::Frequency};
+
+    use super::{Session, SessionKind, band_timeline};
+    use crate::{
+        data::{FieldType, LogRecord},
+        filter::Filter,
+        test_log,
+    };
+
+    #[test]
+    pub fn test_band_timeline() {
+        let log = test_log();
+        let t = |s: &str| s.parse::<Timestamp>().unwrap();
+        let f = Frequency::from_hz;
+
+        let start = t("2025-07-26T12:00:00Z");
+        assert!(log.record_rig_sample(start, f(14_025_000), "CW").unwrap());
+        // polling the same frequency stores nothing
+        assert!(
+            !log.record_rig_sample(t("2025-07-26T12:00:01Z"), f(14_025_000), "CW")
+                .unwrap()
+        );
+        assert!(
+            log.record_rig_sample(t("2025-07-26T12:10:00Z"), f(14_030_000), "CW")
+                .unwrap()
+        );
+        assert!(
+            log.record_rig_sample(t("2025-07-26T12:30:00Z"), f(7_010_000), "CW")
+                .unwrap()
+        );
+        assert!(
+            log.record_rig_sample(t("2025-07-26T12:40:00Z"), f(7_010_000), "USB")
+                .unwrap()
+        );
+
+        let history = log.rig_history(start..).unwrap();
+        assert_eq!(4, history.len());
+        assert_eq!("USB", history[3].mode);
+        assert_eq!(
+            2,
+            log.rig_history(t("2025-07-26T12:20:00Z")..).unwrap().len()
+        );
+
+        let timeline = band_timeline(&history, t("2025-07-26T13:00:00Z"));
+        assert_eq!(2, timeline.len());
+        assert_eq!(Some(Band::M20), timeline[0].band);
+        assert_eq!(start, timeline[0].start);
+        assert_eq!(t("2025-07-26T12:30:00Z"), timeline[0].end);
+        assert_eq!(Some(Band::M40), timeline[1].band);
+        assert_eq!(t("2025-07-26T13:00:00Z"), timeline[1].end);
+        assert!(band_timeline(&[], start).is_empty());
+    }
+
+    #[test]
+    pub fn test_sessions() {
+        let log = test_log();
+        let t = |s: &str| s.parse::<Timestamp>().unwrap();
+        assert!(log.active_session().unwrap().is_none());
+
+        let pota = log
+            .start_session(Session {
+                kind: SessionKind::Pota,
+                name: "US-1234".to_string(),
+                start: t("2025-07-26T12:00:00Z"),
+                grid: "FN31pr".to_string(),
+                power: 5,
+                ..Default::default()
+            })
+            .unwrap();
+        let mut qso = LogRecord::new();
+        qso.insert_field(FieldType::Frequency, "14.062")
+            .insert_field(FieldType::Session, &pota.to_string());
+        log.insert_record(qso).unwrap();
+        log.insert_record(LogRecord::new()).unwrap();
+        let (id, session) = log.active_session().unwrap().unwrap();
+        assert_eq!(pota, id);
+        assert_eq!("US-1234", session.name);
+
+        // starting another session ends the running one
+        let contest = log
+            .start_session(Session {
+                kind: SessionKind::Contest,
+                start: t("2025-07-26T14:00:00Z"),
+                ..Default::default()
+            })
+            .unwrap();
+        let sessions = log.sessions().unwrap();
+        assert_eq!(
+            vec![pota, contest],
+            sessions.iter().map(|(id, _)| *id).collect::<Vec<_>>()
+        );
+        assert_eq!(Some(t("2025-07-26T14:00:00Z")), sessions[0].1.end);
+        assert_eq!(
+            Some(contest),
+            log.end_session(t("2025-07-26T15:00:00Z")).unwrap()
+        );
+        assert!(log.active_session().unwrap().is_none());
+        assert_eq!(None, log.end_session(t("2025-07-26T16:00:00Z")).unwrap());
+
+        assert_eq!(1, log.session_stats(pota).unwrap().qsos);
+        assert_eq!(0, log.session_stats(contest).unwrap().qsos);
+        let filter = Filter {
+            session: Some(pota),
+            ..Default::default()
+        };
+        assert_eq!(1, log.export_adif(&filter).unwrap().body.len());
+    }
+}
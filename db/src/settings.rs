@@ -0,0 +1,520 @@
+use anyhow::Result;
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+use util::bandplan::Region;
+
+use crate::{
+    contest::Contest,
+    data::FieldType,
+    goals::{Goal, GoalCount},
+    report::LabelLayout,
+    session::SessionKind,
+};
+
+/// Most logs kept in `Settings::recent_logs`
+const RECENT_LOGS: usize = 8;
+
+/// Application wide settings, stored as JSON in the config directory of `paths::Paths`.
+/// Missing keys fall back to their defaults so old settings files keep loading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub my_call: String,
+    /// Our Maidenhead locator, the center of the map
+    pub my_grid: String,
+    /// Display name of the UI theme
+    pub theme: String,
+    /// Scale of the entry screen's text and fields, 1.0 is the default size
+    pub entry_scale: f32,
+    /// Fetch solar flux and geomagnetic indices from hamqsl.com for the header
+    pub fetch_solar: bool,
+    /// Band plan the rig frequency is checked against
+    pub region: Region,
+    /// CW messages bound to F1-F8. See `ui::keyer` for the substitution tokens.
+    pub cw_macros: Vec<String>,
+    /// DX cluster node as host:port
+    pub cluster_node: String,
+    /// Path to the cty.dat prefix database used for entity lookups
+    pub cty_path: String,
+    /// Path to the MASTER.SCP database of known calls for partial call checks
+    pub scp_path: String,
+    /// N1MM call history file pre-filling the exchange of known calls, empty for none
+    pub call_history_path: String,
+    pub eqsl_user: String,
+    pub eqsl_password: String,
+    /// Upload every newly logged QSO to eQSL.cc
+    pub eqsl_auto_upload: bool,
+    /// Logbook of the World website login, for downloading confirmations
+    pub lotw_user: String,
+    pub lotw_password: String,
+    /// Callbooks queried for entered calls, first hit wins. Callbooks without credentials are skipped.
+    pub lookup_order: Vec<Callbook>,
+    /// QRZ.com XML callbook login
+    pub qrz_user: String,
+    pub qrz_password: String,
+    /// HamQTH.com login
+    pub hamqth_user: String,
+    pub hamqth_password: String,
+    /// Club Log API key, for the DXCC lookup and uploads
+    pub clublog_api_key: String,
+    /// Club Log login, QSOs are uploaded to the log of `my_call`
+    pub clublog_email: String,
+    pub clublog_password: String,
+    /// Upload newly logged QSOs to Club Log as they happen, and later their edits and deletion
+    pub clublog_upload: bool,
+    /// Address to receive N1MM Logger+ or DXLog contact broadcasts on, e.g. `0.0.0.0:12060`.
+    /// QSOs logged in WSJT-X are received too when its UDP server is set to this address.
+    /// Empty disables the listener.
+    pub n1mm_listen: String,
+    /// host:port destinations every logged QSO is sent to, e.g. `255.255.255.255:12060`
+    pub qso_broadcast: Vec<String>,
+    pub qso_broadcast_format: BroadcastFormat,
+    /// Record the rig audio and link each QSO to its clip. Needs the `audio` feature.
+    pub record_audio: bool,
+    /// Name of the sound card the rig audio comes in on, empty for the default input
+    pub audio_input: String,
+    /// Name of the sound card recordings are played on, empty for the default output
+    pub audio_output: String,
+    /// Directory the QSO recordings are written to
+    pub recordings_dir: String,
+    /// Decode CW heard on `audio_input` and show it on the entry screen. Needs the `audio`
+    /// feature.
+    pub cw_decoder: bool,
+    /// Pitch of the CW tones to decode in Hz, as set on the rig
+    pub cw_pitch: f32,
+    /// WAV files played by F1-F8 in phone modes, empty entries fall back to the CW macro.
+    /// Needs the `audio` feature.
+    pub voice_messages: Vec<String>,
+    /// Pause between auto CQ repeats, in seconds
+    pub auto_cq_pause: u64,
+    /// Contest being operated, adds its exchange to the entry screen and keeps score
+    pub contest: Option<Contest>,
+    /// Start of the contest period, e.g. `2025-11-29T00:00:00Z`. Score is only kept once
+    /// it is set, QSOs before it are not scored.
+    pub contest_start: Option<Timestamp>,
+    /// End of the contest period, None ends it the contest's duration after the start
+    pub contest_end: Option<Timestamp>,
+    /// CONTEST_ID logged with every QSO, e.g. `NAQP-CW`. Set without a `contest`, the
+    /// exchange received is logged as free text. Empty logs the `contest`'s own id.
+    pub contest_id: String,
+    /// Address the HTTP API listens on, e.g. `0.0.0.0:8080`. Empty disables it.
+    /// Needs the `http` feature.
+    pub http_listen: String,
+    /// Token HTTP API requests must carry, the API refuses to start without one
+    pub http_token: String,
+    /// host:port of the rigctld the terminal UI reads the rig from, e.g. `localhost:4532`.
+    /// Empty runs it without a rig.
+    pub rigctld: String,
+    /// Address to accept log sync connections from another station on, e.g. `0.0.0.0:7373`.
+    /// Empty disables it.
+    pub sync_listen: String,
+    /// host:port of another station's sync listener to keep the log in sync with.
+    /// Empty disables it.
+    pub sync_peer: String,
+    /// Token both stations must share to sync, sync refuses to start without one. The
+    /// records sent are encrypted with keys derived from it, so make it long and random.
+    pub sync_token: String,
+    /// SWR above which the meter warns, e.g. of a detuned antenna
+    pub swr_warning: f32,
+    /// Favorite frequencies the entry screen offers buttons to tune the rig to
+    pub memories: Vec<Memory>,
+    /// Hamlib model number of the rig, e.g. 3061 for an IC-7200. `--rig-model` overrides it.
+    pub rig_model: u32,
+    /// Serial port of the rig, or host:port for rigctld, empty until it is set.
+    /// `--rig-port` overrides it.
+    pub rig_path: String,
+    /// Hamlib model number of the second rig for SO2R, e.g. 3073 for an IC-7300
+    pub rig2_model: u32,
+    /// Serial port of the second rig, or host:port for rigctld. Empty runs a single rig.
+    pub rig2_path: String,
+    /// File the log list's export is written to, its extension follows the export format
+    pub adif_export_path: String,
+    /// Format of the log list's export, the name of one of `formats::EXPORTERS`,
+    /// e.g. `Cabrillo`
+    pub export_format: String,
+    /// Show QSO times in the computer's time zone instead of UTC
+    pub local_time: bool,
+    /// Hours ahead of UTC the times of imported ADIF files are, for logs kept in local time,
+    /// e.g. -5 or 5.5. ADIF times are UTC, so this is normally 0.
+    pub import_utc_offset: f32,
+    /// File the log list's printable log is written to, HTML to print from a browser
+    pub report_path: String,
+    /// File QSL labels are written to, HTML to print at 100% scale
+    pub qsl_labels_path: String,
+    /// File the rate sheet of the last session is written to, CSV if it ends in `.csv`
+    /// and text otherwise
+    pub rate_sheet_path: String,
+    /// File the log list's report of the US counties worked is written to
+    pub county_report_path: String,
+    /// Show the county field on the entry screen, for county hunting
+    pub county_field: bool,
+    /// File the diagnostic bundle for support requests is written to, relative paths are
+    /// in the data directory
+    pub diagnostics_path: String,
+    /// Label sheet QSL labels are laid out for
+    pub label_layout: LabelLayout,
+    /// Fast Log Entry text file the log list's FLE import reads, e.g. a typed up POTA log
+    pub fle_import_path: String,
+    /// Where our position comes from when operating portable: gpsd as host:port, e.g.
+    /// `localhost:2947`, or the serial device of an NMEA GPS, e.g. `/dev/ttyACM0`.
+    /// QSOs are stamped with the gridsquare. Empty disables it.
+    pub gps_source: String,
+    /// Operate as a VHF rover: log our changing grid with every QSO, warn when crossing
+    /// into another grid and count stations again from each grid in contests
+    pub rover: bool,
+    /// Reports and exchange filled in when switching to a mode. A template for the running
+    /// contest is preferred over one without a contest.
+    pub exchange_templates: Vec<ExchangeTemplate>,
+    /// Targets the running session's progress is shown against, e.g. the 10 QSOs a POTA
+    /// activation needs
+    pub goals: Vec<Goal>,
+    /// Abbreviations expanded in the entry screen's free text fields, e.g. the comment,
+    /// when a space is typed after them
+    pub snippets: Vec<Snippet>,
+    /// Show IOTA, SOTA and WWFF fields on the entry screen: the references of the
+    /// stations chased, and our own while activating
+    pub references: bool,
+    /// Our IOTA island group while activating, logged as MY_IOTA with every QSO.
+    /// Empty when not activating one, like `my_sota_ref` and `my_wwff_ref`.
+    pub my_iota: String,
+    pub my_sota_ref: String,
+    pub my_wwff_ref: String,
+    /// Paths of the logs opened lately, the last one first. It is opened at startup.
+    pub recent_logs: Vec<String>,
+    /// Directory new logs are created in, empty for the platform's data directory.
+    /// `--data-dir` on the command line overrides it.
+    pub data_dir: String,
+}
+
+/// A frequency and mode to jump the rig to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Memory {
+    /// Button label, e.g. `20m FT8`
+    pub name: String,
+    pub freq_khz: f64,
+    /// ADIF mode, also selected in the entry screen
+    pub mode: String,
+}
+
+impl Memory {
+    fn new(name: &str, freq_khz: f64, mode: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            freq_khz,
+            mode: mode.to_string(),
+        }
+    }
+}
+
+/// Text typed by its abbreviation, e.g. `,73` for a thank you note
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snippet {
+    /// What is typed, matched regardless of case
+    pub abbreviation: String,
+    /// What it expands to
+    pub text: String,
+}
+
+impl Snippet {
+    fn new(abbreviation: &str, text: &str) -> Self {
+        Self {
+            abbreviation: abbreviation.to_string(),
+            text: text.to_string(),
+        }
+    }
+}
+
+/// Entry fields filled in for a mode, e.g. 599 both ways on CW
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeTemplate {
+    /// ADIF mode, e.g. `CW` or `FT8`
+    pub mode: String,
+    /// Contest the template is for, None for any
+    pub contest: Option<Contest>,
+    /// Values by ADIF field name, e.g. `RST_SENT`. Fields already typed are kept.
+    pub fields: BTreeMap<String, String>,
+    /// What `{EXCH}` in a CW macro sends, e.g. `5NN {SERIAL}`
+    pub exchange: String,
+}
+
+impl ExchangeTemplate {
+    fn new(mode: &str, contest: Option<Contest>, rst: &str, exchange: &str) -> Self {
+        Self {
+            mode: mode.to_string(),
+            contest,
+            fields: BTreeMap::from([
+                ("RST_SENT".to_string(), rst.to_string()),
+                ("RST_RCVD".to_string(), rst.to_string()),
+            ]),
+            exchange: exchange.to_string(),
+        }
+    }
+}
+
+/// Online callbooks usable for call lookups
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Callbook {
+    Qrz,
+    HamQth,
+    Clublog,
+}
+
+/// Datagram format of outgoing QSO broadcasts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BroadcastFormat {
+    /// N1MM Logger+ `<contactinfo>` XML, understood by most contest tools
+    N1mm,
+    /// A single ADIF record, as sent by WSJT-X
+    Adif,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            my_call: "N0CALL".to_string(),
+            my_grid: String::new(),
+            theme: "Tokyo Night".to_string(),
+            entry_scale: 1.0,
+            fetch_solar: true,
+            region: Region::default(),
+            cw_macros: vec![
+                "CQ TEST {MYCALL} {MYCALL} TEST".to_string(),
+                "{RST} {SERIAL}".to_string(),
+                "TU {MYCALL}".to_string(),
+                "{MYCALL}".to_string(),
+                "{CALL}".to_string(),
+                "{RST} {SERIAL} {SERIAL}".to_string(),
+                "AGN?".to_string(),
+                "QRZ?".to_string(),
+            ],
+            cluster_node: "dxc.ve7cc.net:23".to_string(),
+            cty_path: "cty.dat".to_string(),
+            scp_path: "MASTER.SCP".to_string(),
+            call_history_path: String::new(),
+            eqsl_user: String::new(),
+            eqsl_password: String::new(),
+            eqsl_auto_upload: false,
+            lotw_user: String::new(),
+            lotw_password: String::new(),
+            lookup_order: vec![Callbook::Qrz, Callbook::HamQth, Callbook::Clublog],
+            qrz_user: String::new(),
+            qrz_password: String::new(),
+            hamqth_user: String::new(),
+            hamqth_password: String::new(),
+            clublog_api_key: String::new(),
+            clublog_email: String::new(),
+            clublog_password: String::new(),
+            clublog_upload: false,
+            n1mm_listen: String::new(),
+            qso_broadcast: Vec::new(),
+            qso_broadcast_format: BroadcastFormat::N1mm,
+            record_audio: false,
+            audio_input: String::new(),
+            audio_output: String::new(),
+            recordings_dir: "recordings".to_string(),
+            cw_decoder: false,
+            cw_pitch: 600.0,
+            voice_messages: Vec::new(),
+            auto_cq_pause: 3,
+            contest: None,
+            contest_start: None,
+            contest_end: None,
+            contest_id: String::new(),
+            http_listen: String::new(),
+            http_token: String::new(),
+            rigctld: String::new(),
+            sync_listen: String::new(),
+            sync_peer: String::new(),
+            sync_token: String::new(),
+            swr_warning: 2.0,
+            // the usual FT8 watering holes
+            memories: vec![
+                Memory::new("80m FT8", 3573.0, "FT8"),
+                Memory::new("40m FT8", 7074.0, "FT8"),
+                Memory::new("30m FT8", 10136.0, "FT8"),
+                Memory::new("20m FT8", 14074.0, "FT8"),
+                Memory::new("17m FT8", 18100.0, "FT8"),
+                Memory::new("15m FT8", 21074.0, "FT8"),
+                Memory::new("10m FT8", 28074.0, "FT8"),
+                Memory::new("6m FT8", 50313.0, "FT8"),
+            ],
+            rig_model: 3061,
+            rig_path: String::new(),
+            rig2_model: 0,
+            rig2_path: String::new(),
+            adif_export_path: "export.adi".to_string(),
+            export_format: "ADIF".to_string(),
+            local_time: false,
+            import_utc_offset: 0.0,
+            report_path: "log.html".to_string(),
+            qsl_labels_path: "labels.html".to_string(),
+            rate_sheet_path: "rates.txt".to_string(),
+            county_report_path: "counties.txt".to_string(),
+            county_field: false,
+            diagnostics_path: "veelog-diagnostics.txt".to_string(),
+            label_layout: LabelLayout::L7163,
+            fle_import_path: "log.fle".to_string(),
+            gps_source: String::new(),
+            rover: false,
+            exchange_templates: vec![
+                ExchangeTemplate::new("CW", None, "599", "5NN {SERIAL}"),
+                ExchangeTemplate::new("SSB", None, "59", ""),
+                // digital modes report the signal to noise ratio in dB
+                ExchangeTemplate::new("FT8", None, "-10", ""),
+            ],
+            goals: vec![Goal::new(
+                "valid activation",
+                Some(SessionKind::Pota),
+                GoalCount::Qsos,
+                10,
+            )],
+            snippets: vec![
+                Snippet::new(",73", "Thanks for the QSO, 73!"),
+                Snippet::new(",qsl", "QSL via bureau"),
+            ],
+            references: false,
+            my_iota: String::new(),
+            my_sota_ref: String::new(),
+            my_wwff_ref: String::new(),
+            recent_logs: Vec::new(),
+            data_dir: String::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Loads settings from `path`, returning the defaults if the file does not exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let data = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Moves the log at `path` to the front of the recent logs
+    pub fn log_opened(&mut self, path: &str) {
+        self.recent_logs.retain(|p| p != path);
+        self.recent_logs.insert(0, path.to_string());
+        self.recent_logs.truncate(RECENT_LOGS);
+    }
+
+    /// The exchange template for `mode`, preferring one for the running contest
+    pub fn exchange_template(&self, mode: &str) -> Option<&ExchangeTemplate> {
+        let templates = || {
+            self.exchange_templates
+                .iter()
+                .filter(|t| t.mode.eq_ignore_ascii_case(mode))
+        };
+        templates()
+            .find(|t| t.contest.is_some() && t.contest == self.contest)
+            .or_else(|| templates().find(|t| t.contest.is_none()))
+    }
+
+    /// Start and end of the period QSOs of the `contest` are scored in, None without a
+    /// contest or its start
+    pub fn contest_period(&self) -> Option<(Timestamp, Timestamp)> {
+        let contest = self.contest?;
+        let start = self.contest_start?;
+        let end = self
+            .contest_end
+            .unwrap_or_else(|| start.saturating_add(contest.duration()).unwrap_or(start));
+        Some((start, end))
+    }
+
+    /// Our references with the fields they are logged in, empty ones too
+    pub fn my_references(&self) -> [(FieldType, &str); 3] {
+        [
+            (FieldType::MyIota, &self.my_iota),
+            (FieldType::MySotaRef, &self.my_sota_ref),
+            (FieldType::MyWwffRef, &self.my_wwff_ref),
+        ]
+    }
+
+    /// `text` with the snippet abbreviation it ends in replaced by the snippet, if a space
+    /// was just typed after one. None if there is nothing to expand.
+    pub fn expand_snippet(&self, text: &str) -> Option<String> {
+        let typed = text.strip_suffix(' ')?;
+        let before = typed.trim_end_matches(|c: char| !c.is_whitespace());
+        let word = &typed[before.len()..];
+        let snippet = self
+            .snippets
+            .iter()
+            .find(|s| !word.is_empty() && s.abbreviation.eq_ignore_ascii_case(word))?;
+        Some(format!("{}{} ", before, snippet.text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RECENT_LOGS, Settings, Snippet};
+    use crate::contest::Contest;
+
+    #[test]
+    pub fn test_recent_logs() {
+        let mut settings = Settings::default();
+        for i in 0..RECENT_LOGS + 1 {
+            settings.log_opened(&format!("log{}", i));
+        }
+        assert_eq!(RECENT_LOGS, settings.recent_logs.len());
+        assert_eq!("log8", settings.recent_logs[0]);
+        assert_eq!("log1", settings.recent_logs[RECENT_LOGS - 1]);
+        settings.log_opened("log5");
+        assert_eq!(["log5", "log8", "log7"], settings.recent_logs[..3]);
+        assert_eq!(RECENT_LOGS, settings.recent_logs.len());
+    }
+
+    #[test]
+    pub fn test_contest_period() {
+        let mut settings = Settings {
+            contest: Some(Contest::FieldDay),
+            ..Settings::default()
+        };
+        assert_eq!(None, settings.contest_period());
+        let start = "2025-06-28T18:00:00Z".parse().unwrap();
+        settings.contest_start = Some(start);
+        let end = "2025-06-29T21:00:00Z".parse().unwrap();
+        assert_eq!(Some((start, end)), settings.contest_period());
+        let end = "2025-06-29T20:59:00Z".parse().unwrap();
+        settings.contest_end = Some(end);
+        assert_eq!(Some((start, end)), settings.contest_period());
+        settings.contest = None;
+        assert_eq!(None, settings.contest_period());
+    }
+
+    #[test]
+    pub fn test_expand_snippet() {
+        let settings = Settings {
+            snippets: vec![
+                Snippet::new(",73", "Thanks for the QSO, 73!"),
+                Snippet::new("", "never typed"),
+            ],
+            ..Settings::default()
+        };
+        assert_eq!(
+            Some("Thanks for the QSO, 73! ".to_string()),
+            settings.expand_snippet(",73 ")
+        );
+        assert_eq!(
+            Some("Nice chat. Thanks for the QSO, 73! ".to_string()),
+            settings.expand_snippet("Nice chat. ,73 ")
+        );
+        // only once the space is typed, and only whole words
+        assert_eq!(None, settings.expand_snippet(",73"));
+        assert_eq!(None, settings.expand_snippet("x,73 "));
+        assert_eq!(None, settings.expand_snippet("73 "));
+        assert_eq!(None, settings.expand_snippet("  "));
+        assert_eq!(None, settings.expand_snippet(""));
+    }
+}
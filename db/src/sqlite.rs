@@ -0,0 +1,140 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use indexmap::IndexMap;
+use rusqlite::{Connection, params_from_iter, types::Value};
+use util::band::Band;
+
+use crate::data::{FieldType, Log};
+
+/// Fields that get a column of their own in the `qsos` table, everything else
+/// ends up in the JSON `extras` column keyed by ADIF name
+const COLUMNS: [(&str, FieldType); 17] = [
+    ("timestamp", FieldType::Timestamp),
+    ("call", FieldType::WorkedCall),
+    ("freq", FieldType::Frequency),
+    ("mode", FieldType::Mode),
+    ("submode", FieldType::Submode),
+    ("rst_sent", FieldType::SentRST),
+    ("rst_rcvd", FieldType::RcvdRST),
+    ("stx", FieldType::SentSerial),
+    ("srx", FieldType::RcvdSerial),
+    ("name", FieldType::Name),
+    ("qth", FieldType::QTH),
+    ("gridsquare", FieldType::GridSquare),
+    ("state", FieldType::PrimaryAdminSubdiv),
+    ("dxcc", FieldType::DXCC),
+    ("cqz", FieldType::CQZ),
+    ("ituz", FieldType::ITUZ),
+    ("comment", FieldType::Comment),
+];
+
+impl Log {
+    /// Writes the log into a new SQLite database with a single `qsos` table.
+    /// Besides the field columns it holds the record id, the list position as `idx`,
+    /// the frequency in Hz and the band, so common queries need no parsing.
+    pub fn export_sqlite(&self, path: &Path) -> Result<()> {
+        if path.exists() {
+            bail!("{} already exists", path.display())
+        }
+        self.write_sqlite(&mut Connection::open(path)?)
+    }
+
+    pub fn write_sqlite(&self, conn: &mut Connection) -> Result<()> {
+        let columns = COLUMNS.map(|(name, _)| name);
+        let tx = conn.transaction()?;
+        tx.execute(
+            &format!(
+                "CREATE TABLE qsos (id TEXT PRIMARY KEY, idx INTEGER NOT NULL, {}, \
+                 freq_hz INTEGER, band TEXT, extras TEXT NOT NULL)",
+                columns.map(|c| format!("{} TEXT", c)).join(", ")
+            ),
+            (),
+        )?;
+        {
+            let mut insert = tx.prepare(&format!(
+                "INSERT INTO qsos (id, idx, {}, freq_hz, band, extras) VALUES ({})",
+                columns.join(", "),
+                vec!["?"; columns.len() + 5].join(", ")
+            ))?;
+            for (idx, record) in self.iter_records().enumerate() {
+                let mut values = vec![
+                    record.id().map(|id| id.to_string()).into(),
+                    Value::Integer(idx as i64),
+                ];
+                values.extend(COLUMNS.iter().map(|(_, ty)| record.get_field(ty).into()));
+                let freq = record.frequency();
+                values.push(freq.map(|f| f.hz() as i64).into());
+                values.push(
+                    freq.and_then(Band::from_freq)
+                        .map(|b| b.name().to_string())
+                        .into(),
+                );
+                let extras = record
+                    .iter()
+                    .filter(|(ty, _)| {
+                        **ty != FieldType::RecordId && !COLUMNS.iter().any(|(_, c)| c == *ty)
+                    })
+                    .map(|(ty, val)| (ty.export_name(), val))
+                    .collect::<IndexMap<String, String>>();
+                values.push(Value::Text(serde_json::to_string(&extras)?));
+                insert.execute(params_from_iter(values))?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::{
+        data::{FieldType, LogRecord},
+        test_log,
+    };
+
+    #[test]
+    pub fn test_sqlite_export() {
+        let log = test_log();
+        let mut record = LogRecord::new();
+        record
+            .insert_timestamp("2025-07-28T02:48:13Z".parse().unwrap())
+            .insert_field(FieldType::WorkedCall, "W1AW")
+            .insert_field(FieldType::Frequency, "14.074")
+            .insert_field(FieldType::POTARef, "US-0001")
+            .insert_field(FieldType::Other("MY_SIG".into()), "POTA");
+        log.insert_record(record).unwrap();
+        let mut record = LogRecord::new();
+        record.insert_field(FieldType::WorkedCall, "K1ABC");
+        log.insert_record(record).unwrap();
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        log.write_sqlite(&mut conn).unwrap();
+        let (call, freq_hz, band, extras): (String, i64, String, String) = conn
+            .query_row(
+                "SELECT call, freq_hz, band, extras FROM qsos WHERE idx = 0",
+                (),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!("W1AW", call);
+        assert_eq!(14_074_000, freq_hz);
+        assert_eq!("20m", band);
+        assert_eq!(r#"{"POTA_REF":"US-0001","MY_SIG":"POTA"}"#, extras);
+
+        let id: String = conn
+            .query_row("SELECT id FROM qsos WHERE call = 'K1ABC'", (), |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(log.get_record(1).unwrap().id().unwrap().to_string(), id);
+        let missing: Option<i64> = conn
+            .query_row("SELECT freq_hz FROM qsos WHERE idx = 1", (), |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(None, missing);
+    }
+}
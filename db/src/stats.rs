@@ -0,0 +1,82 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use util::band::Band;
+
+use crate::data::{FieldType, Log, LogRecord};
+
+/// QSO counts broken down by band and by mode, and the states and entities worked
+#[derive(Debug, Default, PartialEq)]
+pub struct Stats {
+    pub qsos: usize,
+    pub by_band: BTreeMap<Band, usize>,
+    /// Keyed by the combined mode string, e.g. "MFSK/FT4"
+    pub by_mode: BTreeMap<String, usize>,
+    /// STATE values worked by band, None for QSOs without one
+    pub states: BTreeMap<Option<Band>, BTreeSet<String>>,
+    /// DXCC entity numbers worked by band
+    pub entities: BTreeMap<Option<Band>, BTreeSet<String>>,
+}
+
+impl Stats {
+    pub fn from_records<'a>(records: impl IntoIterator<Item = &'a LogRecord>) -> Self {
+        let mut stats = Self::default();
+        for record in records {
+            stats.add(record);
+        }
+        stats
+    }
+
+    pub fn add(&mut self, record: &LogRecord) {
+        self.qsos += 1;
+        let band = record.frequency().and_then(Band::from_freq);
+        if let Some(band) = band {
+            *self.by_band.entry(band).or_default() += 1;
+        }
+        if let Some(mode) = record.display_mode() {
+            *self.by_mode.entry(mode).or_default() += 1;
+        }
+        if let Some(state) = record.get_field(&FieldType::from_adif_field("STATE"))
+            && !state.trim().is_empty()
+        {
+            let state = state.trim().to_ascii_uppercase();
+            self.states.entry(band).or_default().insert(state);
+        }
+        if let Some(dxcc) = record.get_field(&FieldType::DXCC)
+            && !dxcc.trim().is_empty()
+        {
+            let dxcc = dxcc.trim().to_string();
+            self.entities.entry(band).or_default().insert(dxcc);
+        }
+    }
+}
+
+impl Log {
+    pub fn stats(&self) -> Stats {
+        Stats::from_records(&self.get_records())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use util::band::Band;
+
+    use super::Stats;
+    use crate::data::{FieldType, LogRecord};
+
+    #[test]
+    pub fn test_stats() {
+        let mut ft4 = LogRecord::new();
+        ft4.insert_field(FieldType::Frequency, "14.080")
+            .insert_field(FieldType::Mode, "MFSK")
+            .insert_field(FieldType::Submode, "FT4");
+        let mut cw = LogRecord::new();
+        cw.insert_field(FieldType::Frequency, "7.025")
+            .insert_field(FieldType::Mode, "CW");
+
+        let stats = Stats::from_records([&ft4, &ft4, &cw]);
+        assert_eq!(3, stats.qsos);
+        assert_eq!(Some(&2), stats.by_band.get(&Band::M20));
+        assert_eq!(Some(&2), stats.by_mode.get("MFSK/FT4"));
+        assert_eq!(Some(&1), stats.by_mode.get("CW"));
+    }
+}
@@ -0,0 +1,537 @@
+use std::{
+    collections::HashMap,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    net::{Shutdown, TcpStream},
+    sync::mpsc::RecvTimeoutError,
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow, bail};
+use indexmap::IndexMap;
+use jiff::Timestamp;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crypt::SessionKey,
+    data::{Log, LogRecord, RecordId},
+    events::LogEvent,
+    provenance::Source,
+    session::time_key,
+};
+
+/// Last change of every record ever written, keyed by record id. Deleted records keep
+/// their entry as a tombstone.
+const CHANGES_TREE: &[u8] = b"CHANGES";
+/// How often a session checks whether it should stop while the log is quiet
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Bytes of the random hello each side opens a session with
+const HELLO_LEN: usize = 32;
+/// How long the other side has to prove it knows the token
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// The first frame of each side, which only opens with the same token
+const PROOF: &[u8] = b"veelog sync";
+/// Largest frame accepted, so a bogus length can't run us out of memory
+const MAX_FRAME: usize = 1 << 24;
+
+/// When a record was last written or deleted, to the millisecond as stored. Changes
+/// order by time, and a deletion wins over a write made in the same millisecond.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Change {
+    time: Timestamp,
+    deleted: bool,
+}
+
+impl Change {
+    fn new(time: Timestamp, deleted: bool) -> Self {
+        Self {
+            time: Timestamp::from_millisecond(time.as_millisecond()).unwrap_or(time),
+            deleted,
+        }
+    }
+
+    pub(crate) fn written(time: Timestamp) -> Self {
+        Self::new(time, false)
+    }
+
+    pub(crate) fn deleted(time: Timestamp) -> Self {
+        Self::new(time, true)
+    }
+
+    /// Stored as the big endian millisecond time followed by 1 for a deletion
+    pub(crate) fn to_bytes(self) -> [u8; 9] {
+        let mut bytes = [0; 9];
+        bytes[..8].copy_from_slice(&time_key(self.time));
+        bytes[8] = self.deleted as u8;
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let Ok(bytes) = <[u8; 9]>::try_from(bytes) else {
+            bail!("Invalid change entry")
+        };
+        let millis = (u64::from_be_bytes(bytes[..8].try_into()?) ^ (1 << 63)) as i64;
+        Ok(Self {
+            time: Timestamp::from_millisecond(millis)?,
+            deleted: bytes[8] == 1,
+        })
+    }
+}
+
+/// One message of the sync protocol, sent as JSON in an encrypted frame. Records travel
+/// with their id in APP_VEELOG_ID, so both stations know them by the same id whatever
+/// their index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SyncMessage {
+    Record {
+        modified: Timestamp,
+        fields: IndexMap<String, String>,
+    },
+    Deleted {
+        id: String,
+        modified: Timestamp,
+    },
+}
+
+impl Log {
+    pub(crate) fn changes(&self) -> Result<sled::Tree> {
+        Ok(self.db.open_tree(CHANGES_TREE)?)
+    }
+
+    /// The last change of a record, None for records never seen or written before
+    /// changes were kept
+    fn change(&self, id: RecordId) -> Result<Option<Change>> {
+        match self.changes()?.get(id.to_bytes())? {
+            Some(v) => Ok(Some(Change::from_bytes(&v)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// When a record was last written, None for records written before changes were kept
+    pub fn last_written(&self, id: RecordId) -> Result<Option<Timestamp>> {
+        Ok(self.change(id)?.map(|change| change.time))
+    }
+
+    /// When a record was last changed. Records from before changes were kept count as
+    /// changed at the epoch, so any edit on the other station wins.
+    fn modified(&self, id: RecordId) -> Result<Timestamp> {
+        Ok(self
+            .change(id)?
+            .map_or(Timestamp::UNIX_EPOCH, |change| change.time))
+    }
+
+    /// Whether `theirs` is newer than the last local change of a record
+    fn is_newer(&self, id: RecordId, theirs: Change) -> Result<bool> {
+        Ok(self.change(id)?.is_none_or(|ours| theirs > ours))
+    }
+
+    /// The index of the record with id `id`, None once it was deleted
+    pub fn index_of(&self, id: RecordId) -> Result<Option<usize>> {
+        match self.indices()?.get(id.to_bytes())? {
+            Some(idx) => Ok(Some(usize::from_le_bytes(idx.as_ref().try_into()?))),
+            None => Ok(None),
+        }
+    }
+
+    fn record_message(&self, id: RecordId, record: &LogRecord) -> Result<SyncMessage> {
+        Ok(SyncMessage::Record {
+            modified: self.modified(id)?,
+            fields: record.json_fields(),
+        })
+    }
+
+    /// Every record and deletion in the log, filling `ids` with the id at each index
+    fn snapshot(&self, ids: &mut HashMap<usize, RecordId>) -> Result<Vec<SyncMessage>> {
+        let mut messages = Vec::new();
+        for idx in 0..self.get_idx() {
+            if let Some(id) = self.record_id(idx)
+                && let Some(record) = self.get_record_by_id(id)
+            {
+                ids.insert(idx, id);
+                messages.push(self.record_message(id, &record)?);
+            }
+        }
+        for entry in self.changes()?.iter() {
+            let (id, v) = entry?;
+            let change = Change::from_bytes(&v)?;
+            if change.deleted {
+                messages.push(SyncMessage::Deleted {
+                    id: RecordId::from_bytes(&id)?.to_string(),
+                    modified: change.time,
+                });
+            }
+        }
+        Ok(messages)
+    }
+
+    /// The message telling a peer about a local change, None if the record is gone again.
+    /// `ids` tracks the id at each index, as a deleted index no longer has one.
+    fn sync_message(
+        &self,
+        event: LogEvent,
+        ids: &mut HashMap<usize, RecordId>,
+    ) -> Result<Option<SyncMessage>> {
+        match event {
+            LogEvent::Inserted(idx) | LogEvent::Modified(idx) => {
+                let Some(id) = self.record_id(idx) else {
+                    return Ok(None);
+                };
+                ids.insert(idx, id);
+                match self.get_record_by_id(id) {
+                    Some(record) => Ok(Some(self.record_message(id, &record)?)),
+                    None => Ok(None),
+                }
+            }
+            LogEvent::Deleted(idx) => {
+                let Some(id) = ids.remove(&idx) else {
+                    return Ok(None);
+                };
+                Ok(Some(SyncMessage::Deleted {
+                    id: id.to_string(),
+                    modified: self.modified(id)?,
+                }))
+            }
+        }
+    }
+
+    /// Applies a change made on another station if it is newer than ours, the last
+    /// writer wins. Returns whether the log changed.
+    pub fn apply_sync(&self, message: SyncMessage) -> Result<bool> {
+        self.check_writable()?;
+        match message {
+            SyncMessage::Record { modified, fields } => {
+                let record = LogRecord::from_json_fields(fields)?;
+                let Some(id) = record.id() else {
+                    bail!("Synced record has no id")
+                };
+                if !self.is_newer(id, Change::written(modified))? {
+                    return Ok(false);
+                }
+                match self.index_of(id)? {
+                    Some(idx) => self.modify_records_at(vec![(idx, record)], modified)?,
+                    None => {
+                        self.insert_records_at(vec![record], modified, Some(&Source::Sync))?;
+                    }
+                }
+            }
+            SyncMessage::Deleted { id, modified } => {
+                let id: RecordId = id.parse()?;
+                let change = Change::deleted(modified);
+                if !self.is_newer(id, change)? {
+                    return Ok(false);
+                }
+                match self.index_of(id)? {
+                    Some(idx) => self.delete_record_at(idx, modified)?,
+                    // never had it, remember the deletion for the next peer
+                    None => {
+                        self.changes()?.insert(id.to_bytes(), &change.to_bytes())?;
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Writes a frame: its length as a big endian u32, then the bytes
+fn write_frame(writer: &mut impl Write, frame: &[u8]) -> Result<()> {
+    writer.write_all(&(frame.len() as u32).to_be_bytes())?;
+    writer.write_all(frame)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads a frame, None once the other side hung up between frames
+fn read_frame(reader: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME {
+        bail!("The other station sent a frame of {} bytes", len);
+    }
+    let mut frame = vec![0; len];
+    reader.read_exact(&mut frame)?;
+    Ok(Some(frame))
+}
+
+fn send(writer: &mut impl Write, key: &mut SessionKey, message: &SyncMessage) -> Result<()> {
+    write_frame(writer, &key.seal(&serde_json::to_vec(message)?)?)
+}
+
+/// Opens a session: both sides send a random hello, derive the keys of the session from
+/// the hellos and the shared `token`, and send `PROOF` to show they got the same keys.
+/// Returns the keys to send and to receive with.
+fn handshake(stream: &TcpStream, token: &str) -> Result<(SessionKey, SessionKey)> {
+    if token.is_empty() {
+        bail!("Set sync_token before syncing the log");
+    }
+    let mut ours = [0; HELLO_LEN];
+    SystemRandom::new()
+        .fill(&mut ours)
+        .map_err(|_| anyhow!("No random numbers for the hello"))?;
+    let mut writer = stream;
+    writer.write_all(&ours)?;
+    // a peer that never answers must not hold up the listener
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut reader = stream;
+    let mut theirs = [0; HELLO_LEN];
+    reader.read_exact(&mut theirs)?;
+    if theirs == ours {
+        bail!("The other station sent our own hello back");
+    }
+    let hellos = match ours < theirs {
+        true => [ours, theirs].concat(),
+        false => [theirs, ours].concat(),
+    };
+    let mut send = SessionKey::derive(token, &hellos, &ours)?;
+    let mut recv = SessionKey::derive(token, &hellos, &theirs)?;
+    write_frame(&mut writer, &send.seal(PROOF)?)?;
+    let proof = read_frame(&mut reader)?;
+    if proof.and_then(|proof| recv.open(&proof).ok()).as_deref() != Some(PROOF) {
+        bail!("The other station's sync_token does not match ours");
+    }
+    stream.set_read_timeout(None)?;
+    Ok((send, recv))
+}
+
+/// Keeps the log in sync with another veelog on the other end of `stream` until
+/// `running` returns false or the connection drops. Both sides first prove they share
+/// `token`, then send their whole log and every change as it happens, encrypted with
+/// keys derived from the token. Either side may have accepted the connection.
+pub fn sync_with(
+    log: &Log,
+    stream: TcpStream,
+    token: &str,
+    running: impl Fn() -> bool,
+) -> Result<()> {
+    let (mut send_key, mut recv_key) = match handshake(&stream, token) {
+        Ok(keys) => keys,
+        Err(e) => {
+            let _ = stream.shutdown(Shutdown::Both);
+            return Err(e);
+        }
+    };
+    // subscribe first so nothing written while the snapshot is sent gets lost
+    let events = log.subscribe();
+    let peer_log = log.clone();
+    let mut peer = BufReader::new(stream.try_clone()?);
+    let reader = thread::spawn(move || -> Result<()> {
+        while let Some(frame) = read_frame(&mut peer)? {
+            let message = recv_key.open(&frame)?;
+            peer_log.apply_sync(serde_json::from_slice(&message)?)?;
+        }
+        Ok(())
+    });
+
+    let res = (|| {
+        let mut writer = BufWriter::new(&stream);
+        let mut ids = HashMap::new();
+        for message in log.snapshot(&mut ids)? {
+            send(&mut writer, &mut send_key, &message)?;
+        }
+        while running() && !reader.is_finished() {
+            match events.recv_timeout(POLL_INTERVAL) {
+                Ok(event) => {
+                    // changes applied from the peer come back here too, the peer
+                    // ignores them as they are not newer than its own
+                    if let Some(message) = log.sync_message(event, &mut ids)? {
+                        send(&mut writer, &mut send_key, &message)?;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        Ok(())
+    })();
+    let _ = stream.shutdown(Shutdown::Both);
+    let read = match reader.join() {
+        Ok(read) => read,
+        Err(_) => bail!("Sync reader panicked"),
+    };
+    res.and(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        io::Write,
+        net::{TcpListener, TcpStream},
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use jiff::{Timestamp, ToSpan};
+
+    use crate::{
+        data::{FieldType, Log, LogRecord},
+        test_log,
+    };
+
+    use super::{SyncMessage, sync_with};
+
+    fn qso(call: &str) -> LogRecord {
+        let mut record = LogRecord::new();
+        record.insert_field(FieldType::WorkedCall, call);
+        record
+    }
+
+    /// Calls by record id
+    fn calls(log: &Log) -> BTreeMap<String, String> {
+        log.iter_records()
+            .map(|r| {
+                (
+                    r.id().unwrap().to_string(),
+                    r.get_field(&FieldType::WorkedCall).unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    fn wait_for(cond: impl Fn() -> bool) {
+        let start = Instant::now();
+        while !cond() {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "logs did not converge"
+            );
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    pub fn test_apply_sync() {
+        let log = test_log();
+        let idx = log.insert_record(qso("W1AW")).unwrap();
+        let mut fields = log.get_record(idx).unwrap().json_fields();
+        let now = Timestamp::now();
+        fields.insert("CALL".to_string(), "K1ABC".to_string());
+        let older = SyncMessage::Record {
+            modified: now - 1.hour(),
+            fields: fields.clone(),
+        };
+        assert!(!log.apply_sync(older).unwrap());
+        let newer = SyncMessage::Record {
+            modified: now + 1.hour(),
+            fields,
+        };
+        assert!(log.apply_sync(newer.clone()).unwrap());
+        assert!(!log.apply_sync(newer).unwrap());
+        assert_eq!(
+            Some("K1ABC".to_string()),
+            log.get_record(idx)
+                .unwrap()
+                .get_field(&FieldType::WorkedCall)
+        );
+
+        let id = log.record_id(idx).unwrap();
+        assert_eq!(Some(idx), log.index_of(id).unwrap());
+        let deleted = SyncMessage::Deleted {
+            id: id.to_string(),
+            modified: now + 2.hours(),
+        };
+        assert!(log.apply_sync(deleted.clone()).unwrap());
+        assert!(log.get_record(idx).is_none());
+        assert_eq!(None, log.index_of(id).unwrap());
+        assert!(!log.apply_sync(deleted).unwrap());
+        assert!(
+            log.apply_sync(SyncMessage::Record {
+                modified: now,
+                fields: qso("JA1XYZ").json_fields(),
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    pub fn test_sync_with() {
+        let a = test_log();
+        let b = test_log();
+        a.insert_record(qso("W1AW")).unwrap();
+        b.insert_record(qso("DL1ABC")).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let running = Arc::new(AtomicBool::new(true));
+        let sessions = [
+            (a.clone(), None),
+            (b.clone(), Some(TcpStream::connect(addr).unwrap())),
+        ]
+        .map(|(log, stream)| {
+            let stream = stream.unwrap_or_else(|| listener.accept().unwrap().0);
+            let running = running.clone();
+            thread::spawn(move || {
+                sync_with(&log, stream, "s3cret", || running.load(Ordering::Relaxed))
+            })
+        });
+
+        wait_for(|| calls(&a).len() == 2 && calls(&a) == calls(&b));
+        b.insert_record(qso("JA1XYZ")).unwrap();
+        wait_for(|| calls(&a).len() == 3);
+        b.set_field(0, FieldType::WorkedCall, "DL2ABC").unwrap();
+        wait_for(|| calls(&a).values().any(|c| c == "DL2ABC"));
+        a.delete_record(0).unwrap();
+        wait_for(|| calls(&b).len() == 2);
+        assert_eq!(calls(&a), calls(&b));
+
+        running.store(false, Ordering::Relaxed);
+        for session in sessions {
+            session.join().unwrap().unwrap();
+        }
+    }
+
+    #[test]
+    pub fn test_sync_token() {
+        let a = test_log();
+        let b = test_log();
+        b.insert_record(qso("DL1ABC")).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let sessions = [
+            (a.clone(), None, "s3cret"),
+            (b.clone(), Some(TcpStream::connect(addr).unwrap()), "guess"),
+        ]
+        .map(|(log, stream, token)| {
+            let stream = stream.unwrap_or_else(|| listener.accept().unwrap().0);
+            thread::spawn(move || sync_with(&log, stream, token, || true))
+        });
+        for session in sessions {
+            assert!(session.join().unwrap().is_err());
+        }
+        assert!(calls(&a).is_empty());
+
+        // anyone else who connects can't write to the log
+        let intruder = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            let message = serde_json::to_vec(&SyncMessage::Record {
+                modified: Timestamp::now(),
+                fields: qso("W1AW").json_fields(),
+            })
+            .unwrap();
+            stream.write_all(&[0; 32]).unwrap();
+            stream
+                .write_all(&(message.len() as u32).to_be_bytes())
+                .unwrap();
+            stream.write_all(&message).unwrap();
+        });
+        let stream = listener.accept().unwrap().0;
+        assert!(sync_with(&a, stream, "s3cret", || true).is_err());
+        intruder.join().unwrap();
+        assert!(calls(&a).is_empty());
+
+        let stream = TcpStream::connect(addr).unwrap();
+        assert!(sync_with(&a, stream, "", || true).is_err());
+    }
+}
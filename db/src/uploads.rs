@@ -0,0 +1,286 @@
+use std::fmt::Display;
+
+use anyhow::Result;
+use bincode::{Decode, Encode};
+use jiff::{SignedDuration, Timestamp};
+
+use crate::data::{Log, RecordId};
+
+/// Uploads waiting to be sent, keyed by the service's tag and the record id
+const UPLOADS_TREE: &[u8] = b"UPLOADS";
+/// Delay before the first retry, doubled after every failed attempt
+const RETRY_BASE: SignedDuration = SignedDuration::from_secs(30);
+const RETRY_MAX: SignedDuration = SignedDuration::from_hours(1);
+
+/// Online logs QSOs are uploaded to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Service {
+    Eqsl,
+    Clublog,
+}
+
+impl Service {
+    pub const ALL: [Service; 2] = [Service::Eqsl, Service::Clublog];
+
+    fn tag(self) -> u8 {
+        match self {
+            Service::Eqsl => 0,
+            Service::Clublog => 1,
+        }
+    }
+
+    fn key(self, id: RecordId) -> Vec<u8> {
+        let mut key = vec![self.tag()];
+        key.extend(id.to_bytes());
+        key
+    }
+}
+
+impl Display for Service {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Service::Eqsl => write!(f, "eQSL"),
+            Service::Clublog => write!(f, "Club Log"),
+        }
+    }
+}
+
+/// Why an upload did not go through
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UploadError {
+    /// Sending failed, e.g. while offline or with a wrong password, so it is tried again
+    Failed(String),
+    /// The service refused the QSO itself, sending it again would be refused too
+    Rejected(String),
+}
+
+impl Display for UploadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UploadError::Failed(e) | UploadError::Rejected(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for UploadError {}
+
+/// Errors of an upload are failures unless they are an `UploadError::Rejected`
+impl From<anyhow::Error> for UploadError {
+    fn from(e: anyhow::Error) -> Self {
+        e.downcast::<UploadError>()
+            .unwrap_or_else(|e| UploadError::Failed(e.to_string()))
+    }
+}
+
+/// A record waiting to be uploaded
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct Upload {
+    /// Failed attempts so far
+    pub attempts: u32,
+    #[bincode(with_serde)]
+    pub queued: Timestamp,
+    #[bincode(with_serde)]
+    pub next_try: Timestamp,
+    /// Why the last attempt failed
+    pub error: Option<String>,
+}
+
+impl Log {
+    /// Queues record `id` for an upload to `service` right away. The queue is kept in the
+    /// log, so uploads missed while offline or closed are sent later.
+    pub fn queue_upload(&self, service: Service, id: RecordId) -> Result<()> {
+        self.check_writable()?;
+        let tree = self.db.open_tree(UPLOADS_TREE)?;
+        let key = service.key(id);
+        if !tree.contains_key(&key)? {
+            let now = Timestamp::now();
+            let upload = Upload {
+                attempts: 0,
+                queued: now,
+                next_try: now,
+                error: None,
+            };
+            tree.insert(key, self.encode_log_record(upload)?)?;
+        }
+        Ok(())
+    }
+
+    /// Uploads waiting for `service`, the next one due first
+    pub fn pending_uploads(&self, service: Service) -> Result<Vec<(RecordId, Upload)>> {
+        let mut pending = Vec::new();
+        for entry in self
+            .db
+            .open_tree(UPLOADS_TREE)?
+            .scan_prefix([service.tag()])
+        {
+            let (key, val) = entry?;
+            pending.push((
+                RecordId::from_bytes(&key[1..])?,
+                self.decode_log_record::<Upload>(&val)?,
+            ));
+        }
+        pending.sort_by_key(|(_, u)| u.next_try);
+        Ok(pending)
+    }
+
+    /// The record due next for an upload to `service` at `now`
+    pub fn next_upload(&self, service: Service, now: Timestamp) -> Result<Option<RecordId>> {
+        Ok(self
+            .pending_uploads(service)?
+            .into_iter()
+            .find(|(_, u)| u.next_try <= now)
+            .map(|(id, _)| id))
+    }
+
+    /// Removes a finished upload. Other uploads that failed keep waiting for their next
+    /// attempt, they may have failed for reasons of their own.
+    pub fn upload_succeeded(&self, service: Service, id: RecordId) -> Result<()> {
+        self.check_writable()?;
+        self.db.open_tree(UPLOADS_TREE)?.remove(service.key(id))?;
+        Ok(())
+    }
+
+    /// Removes an upload `service` refused, see `UploadError::Rejected`
+    pub fn upload_rejected(&self, service: Service, id: RecordId) -> Result<()> {
+        self.check_writable()?;
+        self.db.open_tree(UPLOADS_TREE)?.remove(service.key(id))?;
+        Ok(())
+    }
+
+    /// Schedules the next attempt of a failed upload, waiting longer after every failure
+    pub fn upload_failed(
+        &self,
+        service: Service,
+        id: RecordId,
+        error: &str,
+        now: Timestamp,
+    ) -> Result<()> {
+        self.check_writable()?;
+        let tree = self.db.open_tree(UPLOADS_TREE)?;
+        let key = service.key(id);
+        let Some(val) = tree.get(&key)? else {
+            return Ok(());
+        };
+        let mut upload: Upload = self.decode_log_record(&val)?;
+        let delay = RETRY_BASE * 2i32.saturating_pow(upload.attempts.min(16));
+        upload.attempts += 1;
+        upload.next_try = now.saturating_add(delay.min(RETRY_MAX))?;
+        upload.error = Some(error.to_string());
+        tree.insert(key, self.encode_log_record(upload)?)?;
+        Ok(())
+    }
+
+    /// Makes every upload waiting for `service` due now
+    pub fn retry_uploads(&self, service: Service) -> Result<()> {
+        self.check_writable()?;
+        let tree = self.db.open_tree(UPLOADS_TREE)?;
+        let now = Timestamp::now();
+        for entry in tree.scan_prefix([service.tag()]) {
+            let (key, val) = entry?;
+            let mut upload: Upload = self.decode_log_record(&val)?;
+            if upload.next_try > now {
+                upload.next_try = now;
+                tree.insert(key, self.encode_log_record(upload)?)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::{SignedDuration, Timestamp};
+
+    use anyhow::anyhow;
+
+    use super::{Service, UploadError};
+    use crate::{
+        data::{FieldType, LogRecord},
+        test_log,
+    };
+
+    #[test]
+    pub fn test_upload_queue() {
+        let log = test_log();
+        let mut ids = Vec::new();
+        for call in ["W1AW", "DL1ABC"] {
+            let mut record = LogRecord::new();
+            record.insert_field(FieldType::WorkedCall, call);
+            let idx = log.insert_record(record).unwrap();
+            ids.push(log.record_id(idx).unwrap());
+        }
+        for id in &ids {
+            log.queue_upload(Service::Eqsl, *id).unwrap();
+            log.queue_upload(Service::Eqsl, *id).unwrap();
+        }
+        log.queue_upload(Service::Clublog, ids[1]).unwrap();
+        assert_eq!(2, log.pending_uploads(Service::Eqsl).unwrap().len());
+        assert_eq!(1, log.pending_uploads(Service::Clublog).unwrap().len());
+
+        let now = Timestamp::now();
+        let first = log.next_upload(Service::Eqsl, now).unwrap().unwrap();
+        log.upload_failed(Service::Eqsl, first, "timed out", now)
+            .unwrap();
+        let second = log.next_upload(Service::Eqsl, now).unwrap().unwrap();
+        assert_ne!(first, second);
+        log.upload_failed(Service::Eqsl, second, "timed out", now)
+            .unwrap();
+        assert_eq!(None, log.next_upload(Service::Eqsl, now).unwrap());
+
+        // the first failure waits 30 seconds, the next one twice as long
+        let later = now + SignedDuration::from_secs(30);
+        assert_eq!(Some(first), log.next_upload(Service::Eqsl, later).unwrap());
+        log.upload_failed(Service::Eqsl, first, "timed out", now)
+            .unwrap();
+        let (_, upload) = &log.pending_uploads(Service::Eqsl).unwrap()[1];
+        assert_eq!(2, upload.attempts);
+        assert_eq!(now + SignedDuration::from_secs(60), upload.next_try);
+        assert_eq!(Some("timed out".to_string()), upload.error);
+
+        // an upload going through leaves the backoff of the others alone
+        log.upload_succeeded(Service::Eqsl, second).unwrap();
+        assert_eq!(None, log.next_upload(Service::Eqsl, later).unwrap());
+        let (id, upload) = &log.pending_uploads(Service::Eqsl).unwrap()[0];
+        assert_eq!((first, 2), (*id, upload.attempts));
+        // a QSO the service refused is not sent again
+        log.upload_rejected(Service::Eqsl, first).unwrap();
+        assert!(log.pending_uploads(Service::Eqsl).unwrap().is_empty());
+        assert_eq!(1, log.pending_uploads(Service::Clublog).unwrap().len());
+    }
+
+    #[test]
+    pub fn test_upload_error() {
+        let rejected = UploadError::Rejected("Bad record".to_string());
+        assert_eq!(
+            rejected,
+            UploadError::from(anyhow::Error::new(rejected.clone()))
+        );
+        assert_eq!(
+            UploadError::Failed("timed out".to_string()),
+            UploadError::from(anyhow!("timed out"))
+        );
+    }
+
+    #[test]
+    pub fn test_eqsl_sent() {
+        let log = test_log();
+        let mut record = LogRecord::new();
+        record
+            .insert_field(FieldType::WorkedCall, "W1AW")
+            .insert_field(FieldType::EqslSent, "Q");
+        assert_eq!(0, log.insert_record(record).unwrap());
+
+        // an upload that went through marks the QSO sent
+        log.set_field(0, FieldType::EqslSent, "Y").unwrap();
+        assert!(log.set_field(1, FieldType::EqslSent, "Y").is_err());
+        let record = log.get_record(0).unwrap();
+        assert_eq!(
+            Some("Y".to_string()),
+            record.get_field(&FieldType::EqslSent)
+        );
+        assert_eq!(
+            Some("W1AW".to_string()),
+            record.get_field(&FieldType::WorkedCall)
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use std::{collections::HashSet, fmt::Display};
+
+use anyhow::{Result, bail};
+use sled::{Transactional, transaction::ConflictableTransactionError};
+
+use crate::{
+    VEELOG_MAGIC,
+    data::{FieldType, Log, LogHeader, LogRecord, RecordId},
+};
+
+/// Undecodable records and header moved aside by `Log::repair`, under their original keys
+const CORRUPT_TREE: &[u8] = b"CORRUPT";
+
+/// What `Log::verify` found. Holes left by deleted records are expected and not a problem.
+#[derive(Debug, Default, PartialEq)]
+pub struct VerifyReport {
+    /// Problems with MAGIC, HEADER or INDEX
+    pub meta_errors: Vec<String>,
+    /// Records that decoded fine
+    pub records: usize,
+    /// Indices whose ordinal is unreadable, points to no record or lies beyond INDEX
+    pub dangling: Vec<usize>,
+    /// Records that no longer decode
+    pub undecodable: Vec<RecordId>,
+    /// Records no ordinal points to, so they are not part of the log
+    pub orphans: Vec<RecordId>,
+    /// Deleted records below INDEX
+    pub holes: usize,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.meta_errors.is_empty()
+            && self.dangling.is_empty()
+            && self.undecodable.is_empty()
+            && self.orphans.is_empty()
+    }
+}
+
+impl Display for VerifyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} records ok, {} dangling indices, {} undecodable and {} orphaned records, {} deleted",
+            self.records,
+            self.dangling.len(),
+            self.undecodable.len(),
+            self.orphans.len(),
+            self.holes
+        )?;
+        for e in &self.meta_errors {
+            write!(f, ", {}", e)?;
+        }
+        Ok(())
+    }
+}
+
+impl Log {
+    /// Checks the database without changing it
+    pub fn verify(&self) -> Result<VerifyReport> {
+        let mut report = VerifyReport::default();
+        match self.db.get(b"MAGIC")? {
+            Some(magic) if magic.to_ascii_uppercase().as_slice() == VEELOG_MAGIC => {}
+            Some(_) => report.meta_errors.push("MAGIC does not match".to_string()),
+            None => report.meta_errors.push("MAGIC is missing".to_string()),
+        }
+        match self.db.get(b"HEADER")? {
+            Some(enc) if Self::decode_record::<LogHeader>(&enc).is_ok() => {}
+            Some(_) => report
+                .meta_errors
+                .push("HEADER does not decode".to_string()),
+            None => report.meta_errors.push("HEADER is missing".to_string()),
+        }
+        let index = match self.db.get(b"INDEX")? {
+            Some(v) => match v.as_ref().try_into() {
+                Ok(bytes) => Some(usize::from_le_bytes(bytes)),
+                Err(_) => None,
+            },
+            None => None,
+        };
+        if index.is_none() {
+            report
+                .meta_errors
+                .push("INDEX is missing or invalid".to_string());
+        }
+
+        let records = self.records()?;
+        let ordinals = self.ordinals()?;
+        let mut referenced = HashSet::new();
+        let mut undecodable = HashSet::new();
+        for entry in ordinals.iter() {
+            let (key, id) = entry?;
+            let Ok(bytes) = key.as_ref().try_into() else {
+                continue;
+            };
+            let idx = usize::from_le_bytes(bytes);
+            let Ok(id) = RecordId::from_bytes(&id) else {
+                report.dangling.push(idx);
+                continue;
+            };
+            match records.get(id.to_bytes())? {
+                _ if index.is_some_and(|index| idx >= index) => report.dangling.push(idx),
+                Some(enc) => match self.decode_log_record::<LogRecord>(&enc) {
+                    Ok(_) => report.records += 1,
+                    Err(_) => {
+                        undecodable.insert(id);
+                    }
+                },
+                None => report.dangling.push(idx),
+            }
+            referenced.insert(id);
+        }
+        for idx in 0..index.unwrap_or_default() {
+            if !ordinals.contains_key(idx.to_le_bytes())? {
+                report.holes += 1;
+            }
+        }
+        for entry in records.iter() {
+            let (key, enc) = entry?;
+            let id = RecordId::from_bytes(&key)?;
+            if referenced.contains(&id) {
+                continue;
+            }
+            match self.decode_log_record::<LogRecord>(&enc) {
+                Ok(_) => report.orphans.push(id),
+                Err(_) => {
+                    undecodable.insert(id);
+                }
+            }
+        }
+        report.undecodable = undecodable.into_iter().collect();
+        report.undecodable.sort();
+        Ok(report)
+    }
+
+    /// Verifies the log and fixes what it can in one transaction: undecodable records
+    /// and header are moved to the CORRUPT tree, the index is rebuilt from the readable
+    /// records in their current order followed by the orphans, which closes the holes
+    /// left by deletions, and every record is re-encoded with the current schema.
+    /// Returns what was found before the repair.
+    pub fn repair(&self) -> Result<VerifyReport> {
+        self.check_writable()?;
+        let report = self.verify()?;
+        if report.meta_errors.iter().any(|e| e.starts_with("MAGIC")) {
+            bail!("Not a veelog database, refusing to repair it");
+        }
+        let records = self.records()?;
+        let ordinals = self.ordinals()?;
+        let indices = self.indices()?;
+        let corrupt = self.db.open_tree(CORRUPT_TREE)?;
+
+        let mut order = Vec::new();
+        for entry in ordinals.iter() {
+            let (_, id) = entry?;
+            if let Ok(id) = RecordId::from_bytes(&id)
+                && !order.contains(&id)
+            {
+                order.push(id);
+            }
+        }
+        order.extend(&report.orphans);
+        let mut entries = Vec::new();
+        let mut quarantined = Vec::new();
+        for id in order {
+            let Some(enc) = records.get(id.to_bytes())? else {
+                continue;
+            };
+            match self.decode_log_record::<LogRecord>(&enc) {
+                Ok(mut record) => {
+                    record.insert_field(FieldType::RecordId, &id.to_string());
+                    entries.push((id, self.encode_log_record(record)?));
+                }
+                Err(_) => quarantined.push((id, enc)),
+            }
+        }
+        // undecodable orphans were never in the ordinals
+        for id in &report.undecodable {
+            if !quarantined.iter().any(|(q, _)| q == id)
+                && let Some(enc) = records.get(id.to_bytes())?
+            {
+                quarantined.push((*id, enc));
+            }
+        }
+        let old_ordinals = ordinals.iter().keys().collect::<Result<Vec<_>, _>>()?;
+        let old_indices = indices.iter().keys().collect::<Result<Vec<_>, _>>()?;
+        let old_header = self.db.get(b"HEADER")?;
+        let header_ok = old_header
+            .as_ref()
+            .is_some_and(|enc| Self::decode_record::<LogHeader>(enc).is_ok());
+        let blank_header = Self::encode_record(LogHeader::new("", ""))?;
+
+        let trees = (&*self.db, &records, &ordinals, &corrupt, &indices);
+        trees.transaction(|(meta, recs, ords, bad, idxs)| {
+            for key in &old_ordinals {
+                ords.remove(key)?;
+            }
+            for key in &old_indices {
+                idxs.remove(key)?;
+            }
+            for (id, enc) in &quarantined {
+                bad.insert(&id.to_bytes(), enc)?;
+                recs.remove(&id.to_bytes())?;
+            }
+            for (idx, (id, enc)) in entries.iter().enumerate() {
+                recs.insert(&id.to_bytes(), enc.as_slice())?;
+                ords.insert(&idx.to_le_bytes(), &id.to_bytes())?;
+                idxs.insert(&id.to_bytes(), &idx.to_le_bytes())?;
+            }
+            meta.insert(b"INDEX", &entries.len().to_le_bytes())?;
+            if !header_ok {
+                if let Some(header) = &old_header {
+                    bad.insert(b"HEADER", header)?;
+                }
+                meta.insert(b"HEADER", blank_header.as_slice())?;
+            }
+            Ok::<_, ConflictableTransactionError<sled::Error>>(())
+        })?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        data::{FieldType, LogRecord},
+        test_log,
+    };
+
+    #[test]
+    pub fn test_verify_and_repair() {
+        let log = test_log();
+        let db = log.db.clone();
+        for call in ["W1AW", "K1ABC", "DL1ABC", "JA1XYZ"] {
+            let mut record = LogRecord::new();
+            record.insert_field(FieldType::WorkedCall, call);
+            log.insert_record(record).unwrap();
+        }
+        log.delete_record(0).unwrap();
+        let report = log.verify().unwrap();
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!((3, 1), (report.records, report.holes));
+
+        // corrupt K1ABC and orphan DL1ABC
+        let k1abc = log.record_id(1).unwrap();
+        let dl1abc = log.record_id(2).unwrap();
+        let records = db.open_tree(b"RECORDS").unwrap();
+        records.insert(k1abc.to_bytes(), &[0xff, 0xff][..]).unwrap();
+        db.open_tree(b"ORDINAL")
+            .unwrap()
+            .remove(2usize.to_le_bytes())
+            .unwrap();
+        let report = log.verify().unwrap();
+        assert_eq!(vec![k1abc], report.undecodable);
+        assert_eq!(vec![dl1abc], report.orphans);
+        assert!(!report.is_ok());
+
+        assert_eq!(report, log.repair().unwrap());
+        let report = log.verify().unwrap();
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!((2, 0), (report.records, report.holes));
+        let calls = log
+            .iter_records()
+            .filter_map(|r| r.get_field(&FieldType::WorkedCall))
+            .collect::<Vec<String>>();
+        assert_eq!(vec!["JA1XYZ", "DL1ABC"], calls);
+        for idx in 0..2 {
+            assert_eq!(
+                Some(idx),
+                log.index_of(log.record_id(idx).unwrap()).unwrap()
+            );
+        }
+        assert!(
+            db.open_tree(b"CORRUPT")
+                .unwrap()
+                .contains_key(k1abc.to_bytes())
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,415 @@
+use std::{collections::HashMap, sync::mpsc::Receiver};
+
+use anyhow::{Result, bail};
+use db::{
+    data::{FieldType, Log, LogRecord},
+    events::LogEvent,
+    provenance::Source,
+    settings::Settings,
+};
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    layout::{Constraint, Layout, Position},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Paragraph, Row, Table},
+};
+use util::{
+    band::Band,
+    callsign,
+    freq::Frequency,
+    geo::{GridStrictness, prettyvalidate_gridsquare},
+    mode::ModeClass,
+};
+
+use crate::rigctld::{self, RigReading};
+
+/// QSOs shown in the table below the entry line
+const RECENT_QSOS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    None,
+    Quit,
+}
+
+pub struct App {
+    log: Log,
+    events: Receiver<LogEvent>,
+    settings: Settings,
+    /// Entry fields with their width in columns
+    entry_fields: Vec<(FieldType, u16)>,
+    content: HashMap<FieldType, String>,
+    focused: usize,
+    error: Option<String>,
+    pub rig: Option<RigReading>,
+    /// The latest QSOs, newest first
+    recent: Vec<LogRecord>,
+}
+
+impl App {
+    pub fn new(log: Log, settings: Settings) -> Self {
+        let mut app = Self {
+            events: log.subscribe(),
+            log,
+            settings,
+            entry_fields: vec![
+                (FieldType::WorkedCall, 14),
+                (FieldType::SentRST, 6),
+                (FieldType::RcvdRST, 6),
+                (FieldType::Name, 16),
+                (FieldType::QTH, 16),
+                (FieldType::GridSquare, 10),
+                (FieldType::PrimaryAdminSubdiv, 7),
+                // typed in when there is no rig to read them from
+                (FieldType::Frequency, 12),
+                (FieldType::Mode, 8),
+            ],
+            content: HashMap::new(),
+            focused: 0,
+            error: None,
+            rig: None,
+            recent: Vec::new(),
+        };
+        app.reload_recent();
+        app
+    }
+
+    fn reload_recent(&mut self) {
+        self.recent = self.log.iter_records_desc().take(RECENT_QSOS).collect();
+    }
+
+    /// Reloads the recent QSOs if the log changed, e.g. through another frontend
+    pub fn poll_log(&mut self) {
+        if self.events.try_iter().count() > 0 {
+            self.reload_recent();
+        }
+    }
+
+    fn mode_class(&self) -> ModeClass {
+        match (self.content.get(&FieldType::Mode), &self.rig) {
+            (Some(mode), _) if !mode.is_empty() => ModeClass::from_mode(mode),
+            (_, Some(rig)) => match rig.mode.as_str() {
+                "PKTUSB" | "PKTLSB" | "PKTFM" => ModeClass::Digital,
+                mode => rigctld::adif_mode(mode)
+                    .map(ModeClass::from_mode)
+                    .unwrap_or(ModeClass::Phone),
+            },
+            _ => ModeClass::Phone,
+        }
+    }
+
+    pub fn key(&mut self, key: KeyEvent) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('c' | 'q') if ctrl => return Action::Quit,
+            KeyCode::Char('w') if ctrl => {
+                self.clear_entry();
+                self.content.remove(&FieldType::Mode);
+                self.content.remove(&FieldType::Frequency);
+            }
+            KeyCode::Char(c) if !ctrl => self.type_char(c),
+            KeyCode::Backspace => {
+                let ty = &self.entry_fields[self.focused].0;
+                if let Some(v) = self.content.get_mut(ty) {
+                    v.pop();
+                }
+            }
+            KeyCode::Tab => self.focused = (self.focused + 1) % self.entry_fields.len(),
+            KeyCode::BackTab => {
+                let fields = self.entry_fields.len();
+                self.focused = (self.focused + fields - 1) % fields;
+            }
+            KeyCode::Enter => match self.log_qso() {
+                Ok(_) => self.clear_entry(),
+                Err(e) => self.error = Some(e.to_string()),
+            },
+            KeyCode::Esc => self.clear_entry(),
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// Adds a typed character to the focused field, if the field accepts it
+    fn type_char(&mut self, c: char) {
+        let ty = self.entry_fields[self.focused].0.clone();
+        let mut v = self.content.get(&ty).cloned().unwrap_or_default();
+        match ty {
+            FieldType::WorkedCall => {
+                // a space jumps from the call to the exchange, like N1MM
+                if c == ' ' {
+                    self.focused += 1;
+                    return;
+                }
+                v.push(c.to_ascii_uppercase());
+                if !callsign::is_partial_callsign(&v) {
+                    return;
+                }
+            }
+            FieldType::SentRST | FieldType::RcvdRST => {
+                let class = self.mode_class();
+                v.push(c);
+                // digital modes may send signal to noise reports like -12
+                let digits = match class {
+                    ModeClass::Digital => v.trim_start_matches(['-', '+']),
+                    _ => &v,
+                };
+                if !digits.chars().all(|c| c.is_ascii_digit()) || v.len() > class.rst_len() {
+                    return;
+                }
+            }
+            FieldType::PrimaryAdminSubdiv | FieldType::Mode => {
+                v.push(c.to_ascii_uppercase());
+                if !ty.is_valid(&v) {
+                    return;
+                }
+            }
+            _ => {
+                v.push(c);
+                if !ty.is_valid(&v) {
+                    return;
+                }
+            }
+        }
+        self.content.insert(ty, v);
+    }
+
+    /// Clears the QSO fields, keeping the mode and frequency for the next QSO
+    fn clear_entry(&mut self) {
+        self.content
+            .retain(|ty, _| matches!(ty, FieldType::Mode | FieldType::Frequency));
+        self.focused = 0;
+        self.error = None;
+    }
+
+    fn log_qso(&mut self) -> Result<usize> {
+        let mut record = self.entry_record()?;
+        // sessions are started in the GUI, QSOs logged here join the running one
+        if let Some((id, _)) = self.log.active_session()? {
+            record.insert_field(FieldType::Session, &id.to_string());
+        }
+        let idx = self.log.insert_record_from(record, &Source::Manual)?;
+        self.poll_log();
+        Ok(idx)
+    }
+
+    /// Builds a record from the entry fields, filling in the mode and frequency from the rig
+    fn entry_record(&self) -> Result<LogRecord> {
+        let class = self.mode_class();
+        let mut record = LogRecord::new();
+        record.insert_timestamp(jiff::Timestamp::now());
+        for (f, _) in &self.entry_fields {
+            let value = match self.content.get(f) {
+                Some(v) if !v.is_empty() => v.to_string(),
+                _ => match f {
+                    FieldType::SentRST | FieldType::RcvdRST => class.default_rst().to_string(),
+                    _ => continue,
+                },
+            };
+            match f {
+                FieldType::WorkedCall => {
+                    record.insert_field(f.clone(), &callsign::validate_callsign(&value)?);
+                }
+                FieldType::SentRST | FieldType::RcvdRST => {
+                    util::mode::validate_rst(&value, class)?;
+                    record.insert_field(f.clone(), &value);
+                }
+                FieldType::GridSquare => {
+                    record.insert_field(
+                        f.clone(),
+                        &prettyvalidate_gridsquare(&value, GridStrictness::Strict)?,
+                    );
+                }
+                FieldType::Frequency => {
+                    record.insert_frequency(f.clone(), Frequency::parse_khz_or_mhz(&value)?);
+                }
+                _ => {
+                    record.insert_field(f.clone(), &f.validate(&value)?);
+                }
+            }
+        }
+        if record.get_field(&FieldType::WorkedCall).is_none() {
+            bail!("Enter a call before logging");
+        }
+        if let Some(rig) = &self.rig {
+            if record.get_field(&FieldType::Mode).is_none()
+                && let Some(mode) = rigctld::adif_mode(&rig.mode)
+            {
+                record.insert_field(FieldType::Mode, mode);
+            }
+            if record.get_field(&FieldType::Frequency).is_none() {
+                record.insert_frequency(FieldType::Frequency, rig.freq);
+            }
+        }
+        Ok(record)
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let [header, entry, status, table, help] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let rig = match &self.rig {
+            Some(rig) => {
+                let band = Band::from_freq(rig.freq)
+                    .map(|b| b.to_string())
+                    .unwrap_or_default();
+                format!("{} MHz {} {}", rig.freq, rig.mode, band)
+            }
+            None => "No rig".to_string(),
+        };
+        frame.render_widget(
+            Line::from(format!(
+                "{} {} | {} | {}",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION"),
+                self.settings.my_call,
+                rig
+            ))
+            .bold(),
+            header,
+        );
+
+        let fields = Layout::horizontal(
+            self.entry_fields
+                .iter()
+                .map(|(_, width)| Constraint::Length(*width)),
+        )
+        .split(entry);
+        for (i, ((ty, _), area)) in self.entry_fields.iter().zip(fields.iter()).enumerate() {
+            let value = self.content.get(ty).map(String::as_str).unwrap_or_default();
+            let mut block = Block::bordered().title(ty.label());
+            if i == self.focused {
+                block = block.border_style(Style::new().fg(Color::Yellow));
+                frame.set_cursor_position(Position::new(
+                    (area.x + 1 + value.len() as u16).min(area.right().saturating_sub(2)),
+                    area.y + 1,
+                ));
+            }
+            frame.render_widget(Paragraph::new(value).block(block), *area);
+        }
+
+        if let Some(e) = &self.error {
+            frame.render_widget(Line::from(e.as_str()).fg(Color::Red), status);
+        }
+
+        let columns = [
+            FieldType::Timestamp,
+            FieldType::WorkedCall,
+            FieldType::Frequency,
+            FieldType::Mode,
+            FieldType::SentRST,
+            FieldType::RcvdRST,
+            FieldType::Name,
+        ];
+        let rows = self.recent.iter().map(|record| {
+            Row::new(columns.iter().map(|ty| {
+                match ty {
+                    FieldType::Timestamp => record
+                        .get_field(ty)
+                        .and_then(|t| t.parse::<jiff::Timestamp>().ok())
+                        .map(|t| t.strftime("%Y-%m-%d %H:%M").to_string())
+                        .unwrap_or_default(),
+                    FieldType::Mode => record.display_mode().unwrap_or_default(),
+                    _ => record.get_field(ty).unwrap_or_default(),
+                }
+            }))
+        });
+        let widths = [
+            Constraint::Length(16),
+            Constraint::Length(12),
+            Constraint::Length(11),
+            Constraint::Length(10),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Fill(1),
+        ];
+        frame.render_widget(
+            Table::new(rows, widths)
+                .header(Row::new(columns.iter().map(|ty| ty.label())).bold())
+                .block(Block::bordered().title("Recent QSOs")),
+            table,
+        );
+
+        frame.render_widget(
+            Line::from(
+                "Tab/Shift+Tab fields | Enter log | Esc clear | Ctrl+W clear all | Ctrl+Q quit",
+            )
+            .dim(),
+            help,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use db::{
+        data::{FieldType, Log, LogHeader},
+        settings::Settings,
+    };
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use util::freq::Frequency;
+
+    use super::{Action, App};
+    use crate::rigctld::RigReading;
+
+    fn press(app: &mut App, code: KeyCode) -> Action {
+        app.key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn type_str(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    pub fn test_log_qso() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let log = Log::new_init(db, LogHeader::new("N0CALL", "")).unwrap();
+        let mut app = App::new(log.clone(), Settings::default());
+        app.rig = Some(RigReading {
+            freq: Frequency::from_hz(7_025_000),
+            mode: "CW".to_string(),
+        });
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(Some("Enter a call before logging"), app.error.as_deref());
+        // Shift+Tab from the first field wraps around to the last
+        press(&mut app, KeyCode::BackTab);
+        assert_eq!(app.entry_fields.len() - 1, app.focused);
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(0, app.focused);
+        // the space jumps to the sent report, which does not take letters
+        type_str(&mut app, "w1aw! 57x9");
+        press(&mut app, KeyCode::Backspace);
+        press(&mut app, KeyCode::Char('8'));
+        assert_eq!(Action::None, press(&mut app, KeyCode::Enter));
+        assert!(app.error.is_none());
+        assert_eq!(0, app.focused);
+
+        let record = log.get_record(0).unwrap();
+        let get = |ty| record.get_field(&ty);
+        assert_eq!(Some("W1AW".to_string()), get(FieldType::WorkedCall));
+        assert_eq!(Some("578".to_string()), get(FieldType::SentRST));
+        assert_eq!(Some("599".to_string()), get(FieldType::RcvdRST));
+        assert_eq!(Some("CW".to_string()), get(FieldType::Mode));
+        assert_eq!(Some("7.025".to_string()), get(FieldType::Frequency));
+        assert_eq!(1, app.recent.len());
+
+        // QSOs logged elsewhere show up too
+        log.insert_record(record).unwrap();
+        app.poll_log();
+        assert_eq!(2, app.recent.len());
+        assert_eq!(
+            Action::Quit,
+            app.key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL))
+        );
+    }
+}
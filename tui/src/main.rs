@@ -0,0 +1,78 @@
+use std::{
+    env,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use db::{
+    data::{Log, LogHeader},
+    paths::Paths,
+};
+use ratatui::{
+    DefaultTerminal,
+    crossterm::event::{self, Event, KeyEventKind},
+};
+use util::rigctld::Rigctld;
+
+use crate::app::{Action, App};
+
+mod app;
+mod rigctld;
+
+/// How often the rig is read
+const RIG_INTERVAL: Duration = Duration::from_millis(700);
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App, mut rig: Option<Rigctld>) -> Result<()> {
+    let mut rig_read = Instant::now();
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        if event::poll(Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && app.key(key) == Action::Quit
+        {
+            return Ok(());
+        }
+        app.poll_log();
+        if let Some(r) = &mut rig
+            && rig_read.elapsed() >= RIG_INTERVAL
+        {
+            rig_read = Instant::now();
+            // a rig that stops answering shows as no rig
+            app.rig = rigctld::read(r).ok();
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // settings are shared with the GUI, as is the log it used last
+    let mut paths = Paths::from_args(&mut args);
+    let settings = paths.load_settings()?;
+    let path = match args.as_slice() {
+        [] => settings
+            .recent_logs
+            .first()
+            .map_or_else(|| paths.default_log(), PathBuf::from),
+        [path] => PathBuf::from(path),
+        _ => {
+            eprintln!(
+                "Usage: {} [--data-dir <directory>] [log directory]",
+                env!("CARGO_PKG_NAME")
+            );
+            std::process::exit(2);
+        }
+    };
+    let log = Log::open_path(&path, LogHeader::new(&settings.my_call, ""), None)?;
+    let rig = match settings.rigctld.is_empty() {
+        true => None,
+        false => Some(Rigctld::connect(&settings.rigctld)?),
+    };
+    let mut app = App::new(log, settings);
+
+    let mut terminal = ratatui::init();
+    let res = run(&mut terminal, &mut app, rig);
+    ratatui::restore();
+    res
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use util::{freq::Frequency, rigctld::Rigctld};
+
+/// The rig as last read from rigctld
+#[derive(Debug, Clone, PartialEq)]
+pub struct RigReading {
+    pub freq: Frequency,
+    /// Hamlib's name for the mode, e.g. USB or PKTUSB
+    pub mode: String,
+}
+
+/// Reads the frequency and mode of the rig
+pub fn read(rig: &mut Rigctld) -> Result<RigReading> {
+    let freq = rig.freq()?;
+    let (mode, _) = rig.mode()?;
+    Ok(RigReading {
+        freq: Frequency::from_hz(freq.round() as u64),
+        mode,
+    })
+}
+
+/// The ADIF MODE matching a hamlib mode name. Data modes are ambiguous and return None.
+pub fn adif_mode(mode: &str) -> Option<&'static str> {
+    match mode {
+        "AM" => Some("AM"),
+        "CW" | "CWR" => Some("CW"),
+        "USB" | "LSB" => Some("SSB"),
+        "RTTY" | "RTTYR" => Some("RTTY"),
+        "FM" | "WFM" => Some("FM"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use util::{freq::Frequency, rigctld::Rigctld};
+
+    use super::{RigReading, read};
+
+    #[test]
+    pub fn test_read() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            for answer in ["14074000\n", "USB\n2400\n", "RPRT -11\n"] {
+                let mut cmd = String::new();
+                reader.read_line(&mut cmd).unwrap();
+                stream.write_all(answer.as_bytes()).unwrap();
+            }
+        });
+        let mut rig = Rigctld::connect(&addr).unwrap();
+        assert_eq!(
+            RigReading {
+                freq: Frequency::from_hz(14_074_000),
+                mode: "USB".to_string()
+            },
+            read(&mut rig).unwrap()
+        );
+        assert!(read(&mut rig).is_err());
+        server.join().unwrap();
+    }
+}
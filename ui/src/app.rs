@@ -0,0 +1,3516 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    fs,
+    io::ErrorKind,
+    net::SocketAddr,
+    path::Path,
+    sync::Arc,
+    time::Instant,
+};
+
+use anyhow::Result;
+use db::{
+    awards::{DxccProgress, VuccProgress},
+    check::ExportProblem,
+    clublog::{ClublogChange, ClublogStatus},
+    contest::{Contest, ContestScore},
+    country::CountryCache,
+    data::{ConflictError, FieldType, ImportPolicy, Log, LogHeader, LogRecord, RecordId},
+    draft::Draft,
+    events::LogEvent,
+    filter::Filter,
+    formats,
+    history::CallHistory,
+    lookup::CallInfo,
+    normalize::Ruleset,
+    notes::Note,
+    paths::Paths,
+    provenance::Source,
+    session::{Session, SessionId, SessionKind},
+    settings::Settings,
+    stats::Stats,
+    uploads::{Service, Upload, UploadError},
+};
+use iced::keyboard::{Key, Modifiers, key::Named};
+use jiff::tz::TimeZone;
+use log::error;
+use util::{
+    band::Band,
+    callsign,
+    dxcc::PrefixDb,
+    freq::Frequency,
+    geo::{self, GridStrictness, gridsquare_center},
+    mode::ModeClass,
+    scp::ScpDb,
+};
+
+use crate::{
+    ENTRY_SCALE_MAX, ENTRY_SCALE_MIN, ENTRY_SCALE_STEP, KeyEvent, Message, SOLAR_FILE,
+    backend::{self, Hamlib, RigBackend, optional},
+    bandmap, broadcast, cluster, crash, gps, keyer, lookup,
+    map::{MapPoint, PointKind},
+    n1mm,
+    paper::{self, PaperLog},
+    rig::{self, Meters},
+    solar, sync,
+    toast::{ToastKind, Toasts},
+};
+#[cfg(feature = "audio")]
+use std::time::Duration;
+
+#[cfg(feature = "http")]
+use crate::http;
+#[cfg(feature = "audio")]
+use crate::{CW_TEXT_LEN, audio};
+
+/// Longest value accepted in a free text entry field
+pub const MAX_TEXT_LEN: usize = 100;
+
+/// What the UI is to do after a message, carried out by `State` with iced. Those
+/// answered with a message name it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    /// Put the cursor in the entry field with this position
+    Focus(usize),
+    /// Look up the call typed, the cursor just left the call field. `Core` does this
+    /// itself, asking for `LookUp` if it has to go online.
+    LookUpCall,
+    /// Put the cursor in this column of the paper log
+    FocusPaper(usize),
+    /// Put the cursor in the quick note
+    FocusNote,
+    /// Look up a call in the online callbooks, `Message::LookupDone`
+    LookUp(String),
+    /// Fetch the solar indices, `Message::SolarFetched`
+    FetchSolar,
+    /// Wait for more typing, then `Message::SearchDue` with this search
+    DelaySearch(u64),
+    /// Search the log for the text, `Message::SearchDone`
+    Search(u64, String),
+    /// Read the clipboard, `Message::ADIFPasted`
+    ReadClipboard,
+    /// Compact `Core::compacting` into the log at this path, `Message::LogCompacted`
+    CompactLog(String),
+    /// Upload the record to eQSL, `Message::EqslUploaded`
+    UploadEqsl(RecordId, LogRecord),
+    /// Send the change of a record to Club Log, `Message::ClublogSent`
+    SendClublog(RecordId, ClublogChange),
+    /// Download the LoTW confirmations received since the date, `Message::LotwFetched`
+    FetchLotw(Option<String>),
+    /// Announce a logged QSO to `Core::broadcast_to`
+    Broadcast(String),
+    /// Exit, everything was shut down
+    Exit,
+}
+
+/// The QSO being entered: the entry fields, what is typed in them and which has the
+/// cursor. Kept apart from iced so what typing does can be tested without a window.
+#[derive(Debug, Default)]
+pub struct Entry {
+    /// Entry fields in the order they are shown
+    pub fields: Vec<FieldType>,
+    /// Text typed, also for fields such as the mode that are chosen elsewhere
+    pub content: HashMap<FieldType, String>,
+    /// Position of the field with the cursor
+    pub focused: usize,
+}
+
+impl Entry {
+    pub fn new(fields: Vec<FieldType>) -> Self {
+        Self {
+            fields,
+            ..Default::default()
+        }
+    }
+
+    /// The text typed in `field`, None if there is none
+    pub fn get(&self, field: &FieldType) -> Option<&str> {
+        self.content
+            .get(field)
+            .map(String::as_str)
+            .filter(|t| !t.is_empty())
+    }
+
+    /// Takes `text` as typed into `field`, in the case and length the field is logged in.
+    /// Text the field can't hold is refused and the field keeps what it had. `class` is
+    /// that of the mode operated, which sets how reports look.
+    pub fn edit(
+        &mut self,
+        field: FieldType,
+        mut text: String,
+        class: ModeClass,
+        settings: &Settings,
+    ) -> Vec<Effect> {
+        match field {
+            FieldType::WorkedCall => {
+                // a space jumps from the call to the exchange, like N1MM
+                if text.contains(' ') {
+                    text.retain(|c| c != ' ');
+                    if callsign::is_partial_callsign(&text) {
+                        self.content.insert(field, text.to_ascii_uppercase());
+                    }
+                    return self.focus(self.focused + 1);
+                }
+                if !callsign::is_partial_callsign(&text) {
+                    return Vec::new();
+                }
+                text.make_ascii_uppercase()
+            }
+            FieldType::SentRST | FieldType::RcvdRST => {
+                // digital modes may send signal to noise reports like -12
+                let digits = match class {
+                    ModeClass::Digital => text.trim_start_matches(['-', '+']),
+                    _ => &text,
+                };
+                if !digits.chars().all(|c| c.is_ascii_digit()) {
+                    return Vec::new();
+                }
+                text.truncate(class.rst_len());
+            }
+            FieldType::GridSquare => {
+                if !field.is_valid(&text) {
+                    return Vec::new();
+                }
+                // complete squares and subsquares get their usual case, e.g. FN31pr
+                if let Ok(grid) = geo::prettyvalidate_gridsquare(&text, GridStrictness::Strict) {
+                    text = grid;
+                }
+            }
+            FieldType::CQZ | FieldType::SentSerial | FieldType::RcvdSerial => {
+                if !field.is_valid(&text) {
+                    return Vec::new();
+                }
+            }
+            FieldType::PrimaryAdminSubdiv
+            | FieldType::Iota
+            | FieldType::SotaRef
+            | FieldType::WwffRef => {
+                if !field.is_valid(&text) {
+                    return Vec::new();
+                }
+                text.make_ascii_uppercase()
+            }
+            // kHz or MHz, or watts, normalized when the QSO is logged
+            FieldType::Frequency | FieldType::TxPower => {
+                if !text.chars().all(|c| c.is_ascii_digit() || c == '.')
+                    || text.matches('.').count() > 1
+                {
+                    return Vec::new();
+                }
+            }
+            // contest exchange fields such as ARRL_SECT or CLASS
+            FieldType::Other(_) => {
+                if !text.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    return Vec::new();
+                }
+                text.make_ascii_uppercase()
+            }
+            // free text such as the name, QTH or comment
+            _ => {
+                if let Some(expanded) = settings.expand_snippet(&text) {
+                    text = expanded;
+                }
+                if text.chars().count() > MAX_TEXT_LEN || !field.is_valid(&text) {
+                    return Vec::new();
+                }
+            }
+        };
+        self.content.insert(field, text);
+        Vec::new()
+    }
+
+    /// Moves the cursor to field `idx`, clamped to the last field.
+    /// Leaving the call field looks up the call.
+    pub fn focus(&mut self, idx: usize) -> Vec<Effect> {
+        let left_call = self.fields.get(self.focused) == Some(&FieldType::WorkedCall);
+        self.focused = idx.min(self.fields.len().saturating_sub(1));
+        let mut effects = vec![Effect::Focus(self.focused)];
+        if left_call && self.fields.get(self.focused) != Some(&FieldType::WorkedCall) {
+            effects.push(Effect::LookUpCall);
+        }
+        effects
+    }
+
+    /// Moves the cursor to the next field, from the last one back to the first
+    pub fn focus_next(&mut self) -> Vec<Effect> {
+        self.focus((self.focused + 1) % self.fields.len().max(1))
+    }
+
+    /// Clears the fields typed, keeping the selected mode, typed frequency and power
+    pub fn clear(&mut self) {
+        self.content.retain(|k, _| {
+            matches!(
+                k,
+                FieldType::Mode | FieldType::Frequency | FieldType::TxPower
+            )
+        });
+    }
+
+    /// What is typed, to be saved as the draft
+    pub fn draft(&self) -> Draft {
+        let mut fields: Vec<(FieldType, String)> = self
+            .content
+            .iter()
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(f, text)| (f.clone(), text.clone()))
+            .collect();
+        // the entry fields in their order, then the others by name so the order is stable
+        let order = |f: &FieldType| {
+            let shown = self.fields.iter().position(|e| e == f);
+            (
+                shown.unwrap_or(usize::MAX),
+                f.adif_name().map(str::to_string),
+            )
+        };
+        fields.sort_by_cached_key(|(f, _)| order(f));
+        Draft {
+            fields,
+            focused: self.fields.get(self.focused).cloned(),
+        }
+    }
+
+    /// Fills the fields from `draft`. If a QSO was being entered the cursor goes back
+    /// where it was.
+    pub fn restore(&mut self, draft: &Draft) -> Vec<Effect> {
+        self.content.extend(draft.fields.iter().cloned());
+        if self.get(&FieldType::WorkedCall).is_none() {
+            return Vec::new();
+        }
+        let focused = draft.focused.as_ref();
+        self.focused = self
+            .fields
+            .iter()
+            .position(|f| Some(f) == focused)
+            .unwrap_or(0);
+        vec![Effect::Focus(self.focused)]
+    }
+}
+
+/// Frequency, mode and meters of a rig as last read
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RigReading {
+    pub freq: f64,
+    pub mode: u64,
+    pub width: i64,
+    /// Output power in watts as set on the rig, None if the rig doesn't report it
+    pub power: Option<f64>,
+    pub meters: Meters,
+    pub split: bool,
+    /// Transmit frequency in Hz, the same as `freq` unless working split
+    pub tx_freq: f64,
+}
+
+impl RigReading {
+    /// Reads `rig`, failing if it doesn't tell its frequency and mode or the connection
+    /// to it broke
+    pub fn read(rig: &dyn RigBackend) -> Result<Self> {
+        let freq = rig.freq()?;
+        let (mode, width) = rig.mode()?;
+        let split_freq = optional(rig.split_freq())?.flatten();
+        Ok(Self {
+            freq,
+            mode,
+            width,
+            power: optional(rig.power(freq, mode))?,
+            meters: rig.meters()?,
+            split: split_freq.is_some(),
+            tx_freq: split_freq.unwrap_or(freq),
+        })
+    }
+}
+
+/// Where the app reads and writes its files, so the core can be tested without a disk
+pub trait Files {
+    /// The text of the file at `path`, None if there is none
+    fn read(&self, path: &Path) -> Result<Option<String>>;
+    /// Replaces the file at `path` with `text`, creating its directory
+    fn write(&self, path: &Path, text: &str) -> Result<()>;
+    /// Removes the file at `path`, if there is one
+    fn remove(&self, path: &Path) -> Result<()>;
+}
+
+/// The file system
+pub struct Disk;
+
+impl Files for Disk {
+    fn read(&self, path: &Path) -> Result<Option<String>> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Some(text)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes a temporary file first and moves it in place, so a crash while writing
+    /// leaves the old file whole
+    fn write(&self, path: &Path, text: &str) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Screen {
+    Entry,
+    LogList,
+    Cluster,
+    Map,
+    /// Grid for transcribing a paper log
+    Paper,
+    /// Calls worked in the contest, by band
+    DupeSheet,
+    /// Uploads waiting for the online logs
+    Uploads,
+    /// Size of the log on disk and compaction
+    Maintenance,
+}
+
+pub struct RigState {
+    pub rig: Option<Box<dyn RigBackend>>,
+    pub freq: f64,
+    pub mode: u64,
+    pub width: i64,
+    pub ptt: bool,
+    /// Output power in watts as set on the rig, None if the rig doesn't report it
+    pub power: Option<f64>,
+    pub meters: rig::Meters,
+    pub split: bool,
+    /// Transmit frequency in Hz, the same as `freq` unless working split
+    pub tx_freq: f64,
+}
+
+impl RigState {
+    fn new() -> Self {
+        Self {
+            rig: None,
+            freq: 0.0,
+            mode: 0,
+            width: 0,
+            ptt: false,
+            power: None,
+            meters: rig::Meters::default(),
+            split: false,
+            tx_freq: 0.0,
+        }
+    }
+
+    /// Reads the frequency, mode and meters of the rig, if open
+    fn poll(&mut self) -> anyhow::Result<()> {
+        let Some(rig) = &self.rig else {
+            return Ok(());
+        };
+        let reading = RigReading::read(rig.as_ref())?;
+        self.freq = reading.freq;
+        self.mode = reading.mode;
+        self.width = reading.width;
+        self.power = reading.power;
+        self.meters = reading.meters;
+        self.split = reading.split;
+        self.tx_freq = reading.tx_freq;
+        Ok(())
+    }
+}
+
+/// Date and time in UTC typed in for QSOs transcribed from a paper log
+pub struct ManualTime {
+    /// e.g. `2025-07-28`
+    pub date: String,
+    /// e.g. `1432` or `14:32`
+    pub time: String,
+}
+
+impl ManualTime {
+    fn new(ts: jiff::Timestamp) -> Self {
+        Self {
+            date: ts.strftime("%Y-%m-%d").to_string(),
+            time: ts.strftime("%H%M").to_string(),
+        }
+    }
+
+    fn timestamp(&self) -> anyhow::Result<jiff::Timestamp> {
+        paper::parse_utc(&self.date, &self.time)
+    }
+}
+
+/// A QSO of the log list being edited, written back only if nothing else changed it since
+pub struct RecordEdit {
+    pub idx: usize,
+    /// Revision of `record`, the QSO as it was read
+    pub revision: u64,
+    pub record: LogRecord,
+    /// The editable fields of the QSO as typed
+    pub fields: Vec<(FieldType, String)>,
+    /// The QSO as changed elsewhere during the edit, to be reloaded or overwritten
+    pub conflict: Option<ConflictError>,
+}
+
+impl RecordEdit {
+    fn new(idx: usize, record: LogRecord, revision: u64) -> Self {
+        let fields = record
+            .iter()
+            .filter(|(ty, _)| !matches!(ty, FieldType::RecordId | FieldType::Timestamp))
+            .map(|(ty, val)| (ty.clone(), val))
+            .collect();
+        Self {
+            idx,
+            revision,
+            record,
+            fields,
+            conflict: None,
+        }
+    }
+
+    /// The QSO read with the typed fields set, checked against the rules of each field
+    fn edited(&self) -> anyhow::Result<LogRecord> {
+        let mut record = self.record.clone();
+        for (ty, val) in &self.fields {
+            if record.get_field(ty).as_ref() == Some(val) {
+                continue;
+            }
+            let val = match ty {
+                FieldType::WorkedCall => callsign::validate_callsign(val)?,
+                ty => ty.validate(val)?,
+            };
+            record.insert_field(ty.clone(), &val);
+        }
+        Ok(record)
+    }
+
+    /// Fields the other change set differently from the QSO read, as shown with the conflict
+    pub fn changed_elsewhere(&self) -> Vec<String> {
+        let Some(conflict) = &self.conflict else {
+            return Vec::new();
+        };
+        conflict
+            .current
+            .iter()
+            .filter(|(ty, val)| self.record.get_field(ty).as_ref() != Some(val))
+            .map(|(ty, val)| format!("{}: {}", ty, val))
+            .collect()
+    }
+}
+
+/// The radio out of focus when running two (SO2R), with the QSO being entered on it
+pub struct OtherRadio {
+    pub rig_state: RigState,
+    pub content: HashMap<FieldType, String>,
+}
+
+pub struct Core {
+    pub paths: Paths,
+    /// Hamlib model and port of the rig opened by Open rig
+    pub rig_model: u32,
+    pub rig_path: String,
+    /// Browsing a log without changing it
+    pub read_only: bool,
+    pub hamlib: Option<Hamlib>,
+    /// The radio in focus, which the entry screen and rig controls act on
+    pub rig_state: RigState,
+    pub other_radio: OtherRadio,
+    /// Number of the radio in focus, 1 or 2
+    pub radio: u8,
+    pub cur_log: Option<Log>,
+    /// Path of the log to create or open, as typed in the log list
+    pub log_path: String,
+    /// Passphrase of an encrypted log to create or open, never saved
+    pub passphrase: String,
+    /// Counts the logs opened, so opening another one restarts the log event subscription
+    pub log_generation: u64,
+    /// Records of the current log by index, kept up to date by its change events
+    pub records: BTreeMap<usize, LogRecord>,
+    pub screen: Screen,
+    /// The QSO being entered
+    pub entry: Entry,
+    /// Where settings, the draft entry and reports are written
+    pub files: Box<dyn Files>,
+    pub entry_error: Option<String>,
+    /// The entry as last saved to the draft file, restored after a crash
+    pub draft: Draft,
+    /// What the panic hook reported when the app last crashed
+    pub crash_report: Option<String>,
+    pub settings: Settings,
+    pub cluster: ClusterState,
+    pub prefixes: Option<PrefixDb>,
+    pub scp: Option<ScpDb>,
+    pub call_history: Option<CallHistory>,
+    pub dxcc_progress: DxccProgress,
+    /// Uploads waiting for each online log, as last read from the log
+    pub uploads: BTreeMap<Service, Vec<(RecordId, Upload)>>,
+    /// Online logs an upload is on its way to, one at a time
+    pub uploading: HashSet<Service>,
+    pub lookups: Arc<lookup::LookupChain>,
+    /// Addresses of `qso_broadcast`, looked up when the settings are loaded
+    pub broadcast_to: Vec<SocketAddr>,
+    /// Result of the last operation on the whole log, shown above the log list
+    pub log_status: String,
+    /// Whether the last verification found problems, offering a repair
+    pub log_damaged: bool,
+    /// Bytes the log takes on disk, as last read for the maintenance screen
+    pub disk_usage: Option<u64>,
+    /// Our gridsquare as last read from the GPS, None without a fix
+    pub gps_grid: Option<String>,
+    /// Our grid as typed in when roving, used while the GPS has no fix
+    pub rover_grid: String,
+    /// The 4 character grid a rover was last in, to notice crossing into another
+    pub rover_square: Option<String>,
+    /// Shown when a rover crosses into another grid
+    pub grid_alert: Option<String>,
+    /// Mode the exchange template was last applied for
+    pub template_mode: Option<String>,
+    /// Entry fields as filled in by that template, replaced by the next mode's unless edited
+    pub template_values: HashMap<FieldType, String>,
+    /// QSOs the export check found problems with, waiting for the export to be confirmed
+    pub export_problems: Vec<ExportProblem>,
+    /// Index, fields and provenance of the QSO selected in the log list
+    pub record_details: Option<(usize, Vec<String>)>,
+    /// The selected QSO while its fields are being edited
+    pub record_edit: Option<RecordEdit>,
+    /// Call of the cluster spot last clicked, QSOs with it are logged as from the cluster
+    pub spot_call: Option<String>,
+    /// Stations worked in the last `bandmap::WORKED_MINUTES` and when, for the band map
+    pub worked_recently: Vec<(jiff::Timestamp, bandmap::Station)>,
+    /// Start of this run of the program, the band timeline covers the time since
+    pub session_start: jiff::Timestamp,
+    pub contest_score: Option<ContestScore>,
+    #[cfg(feature = "audio")]
+    pub recorder: Option<audio::Recorder>,
+    #[cfg(feature = "audio")]
+    pub playback: Option<audio::Playback>,
+    /// The voice message being transmitted
+    #[cfg(feature = "audio")]
+    pub voice: Option<audio::Playback>,
+    #[cfg(feature = "audio")]
+    pub cw_listener: Option<audio::CwListener>,
+    /// The latest CW heard by the decoder
+    pub cw_text: String,
+    pub auto_cq: keyer::AutoCq,
+    /// Worked grids and entities, gathered when the map is opened
+    pub map_points: Vec<MapPoint>,
+    /// VUCC grids per band, gathered with the map points
+    pub vucc: VuccProgress,
+    pub map_by_band: bool,
+    /// When the map's day/night terminator was last moved
+    pub map_time: jiff::Timestamp,
+    pub solar: solar::SolarCache,
+    /// The quick note being typed, opened with Ctrl+N
+    pub note: Option<String>,
+    /// Notes taken during the QSO being entered, added to it once it is logged
+    pub pending_notes: Vec<Note>,
+    pub search: String,
+    /// Counts search text edits, so only the latest edit starts a search and shows results
+    pub search_seq: u64,
+    /// Indices of the records matching `search`, None when not searching
+    pub search_results: Option<Vec<usize>>,
+    /// Continent the log list is narrowed to, None for every continent
+    pub continent: Option<String>,
+    pub countries: CountryCache,
+    /// Time QSOs are logged at instead of now, when transcribing a paper log
+    pub manual_time: Option<ManualTime>,
+    pub paper: PaperLog,
+    /// The running operating session, QSOs logged are linked to it
+    pub session: Option<(SessionId, Session)>,
+    /// Counts of the QSOs of the running session, for its goals
+    pub session_stats: Stats,
+    /// Kind and name of the next session to start
+    pub session_kind: SessionKind,
+    pub session_name: String,
+    /// Errors and notices shown over the screen
+    pub toasts: Toasts,
+    /// Question asked before going on with the message, shown over the screen
+    pub confirmation: Option<(String, Message)>,
+    /// The log being compacted, handed to the task that compacts it
+    pub compacting: Option<Log>,
+}
+
+#[derive(Default)]
+pub struct ClusterState {
+    pub enabled: bool,
+    pub status: String,
+    pub spots: Vec<cluster::Spot>,
+}
+
+impl Core {
+    /// The state before a log or rig is opened, keeping its files in `files`
+    pub fn new(paths: Paths, settings: Settings, files: Box<dyn Files>) -> Self {
+        let prefixes = match PrefixDb::load(Path::new(&settings.cty_path)) {
+            Ok(db) => Some(db),
+            Err(e) => {
+                error!(
+                    "Could not load prefix database {}: {}",
+                    settings.cty_path, e
+                );
+                None
+            }
+        };
+        let scp = match ScpDb::load(Path::new(&settings.scp_path)) {
+            Ok(db) => Some(db),
+            Err(e) => {
+                error!("Could not load SCP database {}: {}", settings.scp_path, e);
+                None
+            }
+        };
+        let call_history = match settings.call_history_path.as_str() {
+            "" => None,
+            path => CallHistory::load(Path::new(path))
+                .inspect_err(|e| error!("Could not load call history {}: {}", path, e))
+                .ok(),
+        };
+        let mut entry_fields = vec![
+            FieldType::WorkedCall,
+            FieldType::SentRST,
+            FieldType::RcvdRST,
+            FieldType::Name,
+            FieldType::QTH,
+            FieldType::GridSquare,
+            FieldType::PrimaryAdminSubdiv,
+            // typed in when there is no rig to read it from
+            FieldType::Frequency,
+            FieldType::TxPower,
+        ];
+        match settings.contest {
+            Some(contest) => {
+                for f in contest.exchange() {
+                    if !entry_fields.contains(&f) {
+                        entry_fields.push(f);
+                    }
+                }
+            }
+            // a contest without rules of its own takes the exchange as typed
+            None if !settings.contest_id.trim().is_empty() => {
+                entry_fields.push(FieldType::RcvdExchange);
+            }
+            None => {}
+        }
+        if settings.county_field {
+            let state = entry_fields
+                .iter()
+                .position(|f| *f == FieldType::PrimaryAdminSubdiv);
+            entry_fields.insert(state.map_or(0, |i| i + 1), FieldType::SecondaryAdminSubdiv);
+        }
+        if settings.references {
+            entry_fields.extend([FieldType::Iota, FieldType::SotaRef, FieldType::WwffRef]);
+        }
+        let lookups = Arc::new(lookup::LookupChain::from_settings(&settings));
+        let broadcast_to = broadcast::resolve(&settings.qso_broadcast)
+            .inspect_err(|e| error!("Could not look up the QSO broadcast destinations: {}", e))
+            .unwrap_or_default();
+        #[cfg(feature = "audio")]
+        let recorder = match settings.record_audio {
+            true => {
+                let dir = Path::new(&settings.recordings_dir);
+                audio::Recorder::start(&settings.audio_input, dir)
+                    .inspect_err(|e| error!("Could not start audio recording: {}", e))
+                    .ok()
+            }
+            false => None,
+        };
+        #[cfg(feature = "audio")]
+        let cw_listener = match settings.cw_decoder {
+            true => audio::CwListener::start(&settings.audio_input, settings.cw_pitch)
+                .inspect_err(|e| error!("Could not start the CW decoder: {}", e))
+                .ok(),
+            false => None,
+        };
+        Self {
+            rig_model: settings.rig_model,
+            rig_path: settings.rig_path.clone(),
+            read_only: false,
+            hamlib: None,
+            rig_state: RigState::new(),
+            other_radio: OtherRadio {
+                rig_state: RigState::new(),
+                content: HashMap::new(),
+            },
+            radio: 1,
+            cur_log: None,
+            log_path: settings
+                .recent_logs
+                .first()
+                .cloned()
+                .unwrap_or_else(|| paths.default_log().to_string_lossy().to_string()),
+            passphrase: String::new(),
+            log_generation: 0,
+            records: BTreeMap::new(),
+            screen: Screen::LogList,
+            entry: Entry::new(entry_fields),
+            files,
+            entry_error: None,
+            draft: Draft::default(),
+            crash_report: None,
+            settings,
+            cluster: ClusterState::default(),
+            prefixes,
+            scp,
+            call_history,
+            dxcc_progress: DxccProgress::default(),
+            uploads: BTreeMap::new(),
+            uploading: HashSet::new(),
+            lookups,
+            broadcast_to,
+            log_status: String::new(),
+            log_damaged: false,
+            disk_usage: None,
+            gps_grid: None,
+            rover_grid: String::new(),
+            rover_square: None,
+            grid_alert: None,
+            template_mode: None,
+            template_values: HashMap::new(),
+            export_problems: Vec::new(),
+            record_details: None,
+            record_edit: None,
+            spot_call: None,
+            worked_recently: Vec::new(),
+            session_start: jiff::Timestamp::now(),
+            contest_score: None,
+            #[cfg(feature = "audio")]
+            recorder,
+            #[cfg(feature = "audio")]
+            playback: None,
+            #[cfg(feature = "audio")]
+            voice: None,
+            #[cfg(feature = "audio")]
+            cw_listener,
+            cw_text: String::new(),
+            auto_cq: keyer::AutoCq::default(),
+            map_points: Vec::new(),
+            vucc: VuccProgress::default(),
+            map_by_band: false,
+            map_time: jiff::Timestamp::now(),
+            solar: solar::SolarCache::load(&paths.data_file(SOLAR_FILE)),
+            note: None,
+            pending_notes: Vec::new(),
+            search: String::new(),
+            search_seq: 0,
+            search_results: None,
+            continent: None,
+            countries: CountryCache::default(),
+            manual_time: None,
+            paper: PaperLog::default(),
+            session: None,
+            session_stats: Stats::default(),
+            session_kind: SessionKind::default(),
+            session_name: String::new(),
+            toasts: Toasts::default(),
+            confirmation: None,
+            compacting: None,
+            paths,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        let name = format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        match (&self.cur_log, self.settings.recent_logs.first()) {
+            (Some(_), Some(path)) if self.read_only => format!("{} - {} (read-only)", name, path),
+            (Some(_), Some(path)) => format!("{} - {}", name, path),
+            _ => name,
+        }
+    }
+
+    /// Fills the entry screen with the draft saved when the app last ran. A QSO that
+    /// was being entered is shown with the cursor back where it was.
+    pub fn restore_draft(&mut self) -> Vec<Effect> {
+        let draft = self
+            .files
+            .read(&self.paths.draft_file())
+            .and_then(|text| Ok(text.map(|t| serde_json::from_str(&t)).transpose()?));
+        match draft {
+            Ok(draft) => self.restore(draft.unwrap_or_default()),
+            Err(e) => {
+                error!("Could not read the draft entry: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Fills the entry screen with `draft`, going back to it if a QSO was being entered
+    fn restore(&mut self, draft: Draft) -> Vec<Effect> {
+        self.draft = draft;
+        let effects = self.entry.restore(&self.draft);
+        if effects.is_empty() {
+            return Vec::new();
+        }
+        self.screen = Screen::Entry;
+        self.notify("Restored the QSO being entered when veelog last ran".to_string());
+        self.run_effects(effects)
+    }
+
+    /// Saves what is typed on the entry screen, unless it is what was saved last
+    fn save_draft(&mut self) {
+        let draft = self.entry.draft();
+        if draft == self.draft {
+            return;
+        }
+        let path = self.paths.draft_file();
+        let saved = match &self.cur_log {
+            // an encrypted log keeps the draft in it, not in a plaintext file, and
+            // browsing one read-only keeps none
+            Some(log) if log.is_encrypted() && log.is_read_only() => Ok(()),
+            Some(log) if log.is_encrypted() => log
+                .save_draft(&draft)
+                .and_then(|()| self.files.remove(&path)),
+            // nothing typed leaves nothing to restore
+            _ if draft.is_empty() => self.files.remove(&path),
+            _ => serde_json::to_string_pretty(&draft)
+                .map_err(Into::into)
+                .and_then(|text| self.files.write(&path, &text)),
+        };
+        match saved {
+            Ok(()) => self.draft = draft,
+            Err(e) => error!("Could not save the draft entry: {}", e),
+        }
+    }
+
+    /// Whether `message` changes the log, which a log browsed read-only must not
+    fn writes_log(message: &Message) -> bool {
+        matches!(
+            message,
+            Message::Import(_)
+                | Message::ImportFLE
+                | Message::PasteADIF
+                | Message::ADIFPasted(_)
+                | Message::NormalizeLog
+                | Message::RepairLog
+                | Message::CompactLog
+                | Message::ToggleSession
+                | Message::RetryUploads
+                | Message::FetchLotw
+                | Message::EditRecord(_)
+                | Message::SaveRecord
+                | Message::OverwriteRecord
+        )
+    }
+
+    /// `message`, unless it would change a log open read-only. For the controls of
+    /// such messages, which are disabled then.
+    pub fn writable(&self, message: Message) -> Option<Message> {
+        (!self.read_only || !Self::writes_log(&message)).then_some(message)
+    }
+
+    pub fn log_read_only(&self) -> bool {
+        self.cur_log.as_ref().is_some_and(Log::is_read_only)
+    }
+
+    /// Opens the log at `path`, creating it if there is none yet, and switches to it.
+    /// Relative paths are in the data directory, with `--read-only` the log is only read.
+    /// With a passphrase typed a new log is encrypted, and an encrypted log is unlocked.
+    fn open_log(&mut self, path: String) {
+        let path = self.paths.data_file(&path).to_string_lossy().to_string();
+        // sled allows a database to be opened only once
+        if self.cur_log.is_some() && self.settings.recent_logs.first() == Some(&path) {
+            return;
+        }
+        let header = LogHeader::new(&self.settings.my_call, "");
+        let passphrase = Some(self.passphrase.as_str()).filter(|p| !p.is_empty());
+        let opened = match self.read_only {
+            true => Log::open_read_only(Path::new(&path), passphrase),
+            false => Log::open_path(Path::new(&path), header, passphrase),
+        };
+        match opened {
+            Ok(log) => {
+                self.passphrase.clear();
+                self.switch_log(log, path);
+            }
+            Err(e) => self.report_error(format!("Could not open the log {}: {}", path, e)),
+        }
+    }
+
+    /// Makes `log`, opened from `path`, the current log
+    pub fn switch_log(&mut self, log: Log, path: String) {
+        crash::watch_log(Some(log.clone()));
+        self.cur_log = Some(log);
+        self.log_generation += 1;
+        self.reload_records();
+        self.session = self
+            .cur_log
+            .as_ref()
+            .and_then(|log| log.active_session().ok().flatten());
+        self.refresh_session_stats();
+        self.uploading.clear();
+        if !self.log_read_only() {
+            self.queue_eqsl_uploads();
+            self.queue_clublog_uploads();
+        }
+        self.refresh_awards();
+        self.refresh_contest();
+        self.refresh_worked();
+        self.settings.log_opened(&path);
+        self.save_settings();
+        self.log_path = path;
+        // the draft of an encrypted log can only be read once it is unlocked
+        if let Some(log) = &self.cur_log
+            && log.is_encrypted()
+            && self.entry.draft().is_empty()
+        {
+            match log.draft() {
+                Ok(draft) => {
+                    self.restore(draft);
+                }
+                Err(e) => error!("Could not read the draft entry: {}", e),
+            }
+        }
+    }
+
+    /// Logs an error and shows it in a toast, for failures the operator should know about
+    pub fn report_error(&mut self, e: impl Display) {
+        error!("{}", e);
+        self.toasts
+            .push(ToastKind::Error, e.to_string(), Instant::now());
+    }
+
+    fn notify(&mut self, text: String) {
+        self.toasts.push(ToastKind::Info, text, Instant::now());
+    }
+
+    fn save_settings(&self) {
+        let saved = serde_json::to_string_pretty(&self.settings)
+            .map_err(Into::into)
+            .and_then(|text| self.files.write(&self.paths.settings_file(), &text));
+        if let Err(e) = saved {
+            error!("Could not save settings: {}", e);
+        }
+    }
+
+    /// Unkeys and closes the rigs, writes the log to disk and saves the draft entry and
+    /// settings before the app exits, rather than leaving it to whatever order things are
+    /// dropped in
+    fn shut_down(&mut self) {
+        if self.rig_state.ptt {
+            self.set_ptt(false);
+        }
+        // without a rig the poll timer has nothing left to ask
+        for (name, rig) in [
+            ("rig", self.rig_state.rig.take()),
+            ("second rig", self.other_radio.rig_state.rig.take()),
+        ] {
+            if let Some(mut rig) = rig
+                && let Err(e) = rig.close()
+            {
+                error!("Could not close the {}: {}", name, e);
+            }
+        }
+        if let Some(log) = &self.cur_log
+            && let Err(e) = log.flush()
+        {
+            error!("Could not write the log to disk: {}", e);
+        }
+        self.save_draft();
+        self.save_settings();
+    }
+
+    /// Counts the QSOs of the running session again, after they changed
+    fn refresh_session_stats(&mut self) {
+        self.session_stats = match &self.session {
+            Some((id, _)) => {
+                let filter = Filter {
+                    session: Some(*id),
+                    ..Default::default()
+                };
+                Stats::from_records(self.records.values().filter(|r| filter.matches(r)))
+            }
+            None => Stats::default(),
+        };
+    }
+
+    /// Reads all records of the current log, after which log events keep them up to date
+    fn reload_records(&mut self) {
+        self.records = match &self.cur_log {
+            Some(log) => (0..log.get_idx())
+                .filter_map(|idx| Some((idx, log.get_record(idx)?)))
+                .collect(),
+            None => BTreeMap::new(),
+        };
+    }
+
+    fn refresh_awards(&mut self) {
+        if let (Some(log), Some(prefixes)) = (&self.cur_log, &self.prefixes) {
+            self.dxcc_progress = log.dxcc_progress(prefixes);
+        }
+    }
+
+    /// Collects the stations worked lately from the last records of the log
+    fn refresh_worked(&mut self) {
+        self.worked_recently.clear();
+        let Some(log) = &self.cur_log else {
+            return;
+        };
+        let since = bandmap::worked_since();
+        for record in log.iter_records_desc().take(bandmap::WORKED_RECORDS) {
+            if let Some(call) = record.get_field(&FieldType::WorkedCall)
+                && let Some(freq) = record.frequency()
+                && let Some(time) = record
+                    .get_field(&FieldType::Timestamp)
+                    .and_then(|t| t.parse::<jiff::Timestamp>().ok())
+                && time >= since
+            {
+                let station = bandmap::Station {
+                    call,
+                    freq_khz: freq.khz(),
+                    kind: bandmap::StationKind::Worked,
+                };
+                self.worked_recently.push((time, station));
+            }
+        }
+    }
+
+    /// Drops the stations worked longer ago than `bandmap::WORKED_MINUTES`, which the log
+    /// changing would not do while no QSOs are logged
+    fn expire_worked(&mut self) {
+        let since = bandmap::worked_since();
+        self.worked_recently.retain(|(time, _)| *time >= since);
+    }
+
+    /// Collects the worked grids and entities, once per band they were worked on
+    fn refresh_map(&mut self) {
+        self.map_points.clear();
+        self.vucc = VuccProgress::default();
+        let Some(log) = &self.cur_log else {
+            return;
+        };
+        self.vucc = log.vucc_progress();
+        let mut seen = HashSet::new();
+        for record in log.iter_records() {
+            let band = record.frequency().and_then(Band::from_freq);
+            if let Some(grid) = record.get_field(&FieldType::GridSquare)
+                && let Some(square) = grid.get(..4)
+                && let Ok(pos) = gridsquare_center(square)
+                && seen.insert((PointKind::Grid, square.to_ascii_uppercase(), band))
+            {
+                self.map_points.push(MapPoint {
+                    kind: PointKind::Grid,
+                    pos,
+                    band,
+                });
+            }
+            if let Some(prefixes) = &self.prefixes
+                && let Some(call) = record.get_field(&FieldType::WorkedCall)
+                && let Some(m) = prefixes.lookup(&call)
+                && seen.insert((PointKind::Entity, m.entity.prefix.clone(), band))
+            {
+                self.map_points.push(MapPoint {
+                    kind: PointKind::Entity,
+                    pos: (m.entity.lat, m.entity.lon),
+                    band,
+                });
+            }
+        }
+    }
+
+    /// Our location: the configured grid, else our entity's, else 0N 0E
+    pub fn map_center(&self) -> (f64, f64) {
+        if let Ok(pos) = gridsquare_center(&self.settings.my_grid) {
+            return pos;
+        }
+        self.prefixes
+            .as_ref()
+            .and_then(|p| p.lookup(&self.settings.my_call))
+            .map(|m| (m.entity.lat, m.entity.lon))
+            .unwrap_or_default()
+    }
+
+    /// Rescores the contest from the QSOs in the log
+    fn refresh_contest(&mut self) {
+        let contest = self.settings.contest.zip(self.settings.contest_period());
+        self.contest_score = match (&self.cur_log, contest) {
+            (Some(log), Some((contest, period))) => Some(log.contest_score(
+                contest,
+                &self.settings.my_call,
+                period,
+                self.prefixes.as_ref(),
+                self.rover_grid().as_deref(),
+            )),
+            _ => None,
+        };
+    }
+
+    /// Our current grid, from the GPS or as typed in when roving
+    fn my_grid(&self) -> Option<String> {
+        match &self.gps_grid {
+            Some(grid) => Some(grid.clone()),
+            None if self.settings.rover => {
+                geo::prettyvalidate_gridsquare(&self.rover_grid, GridStrictness::Strict).ok()
+            }
+            None => None,
+        }
+    }
+
+    pub fn rover_grid(&self) -> Option<String> {
+        self.my_grid().filter(|_| self.settings.rover)
+    }
+
+    /// The mode of the QSO being entered: the manually selected one, otherwise the rig's
+    fn current_mode(&self) -> Option<&str> {
+        match self.entry.content.get(&FieldType::Mode) {
+            Some(mode) if !mode.is_empty() => Some(mode.as_str()),
+            _ => rig::adif_mode(self.rig_state.mode),
+        }
+    }
+
+    /// Fills in the current mode's exchange template. Fields the operator typed are kept,
+    /// those still holding the previous mode's template values are replaced.
+    fn apply_exchange_template(&mut self) {
+        let mode = self.current_mode().map(str::to_string);
+        for (ty, value) in self.template_values.drain() {
+            if self.entry.content.get(&ty) == Some(&value) {
+                self.entry.content.remove(&ty);
+            }
+        }
+        let template = mode
+            .as_deref()
+            .and_then(|m| self.settings.exchange_template(m));
+        for (name, value) in template.iter().flat_map(|t| &t.fields) {
+            let ty = FieldType::from_adif_field(name);
+            if self.entry.content.get(&ty).is_none_or(|v| v.is_empty()) {
+                self.entry.content.insert(ty.clone(), value.clone());
+                self.template_values.insert(ty, value.clone());
+            }
+        }
+        self.template_mode = mode;
+    }
+
+    /// Alerts a rover that crossed into another grid, where stations count again
+    fn grid_changed(&mut self) {
+        let now = self.rover_grid();
+        if let Some(square) = now.as_ref().and_then(|g| g.get(..4)) {
+            if let Some(from) = &self.rover_square
+                && from != square
+            {
+                self.grid_alert = Some(format!(
+                    "Entered {} from {}, stations worked from {} can be worked again",
+                    square, from, from
+                ));
+            }
+            self.rover_square = Some(square.to_string());
+        }
+        if let Some(score) = &mut self.contest_score {
+            score.rover_grid = now;
+        }
+    }
+
+    /// Whether the call being entered was already worked in the contest
+    pub fn entry_is_dupe(&self) -> bool {
+        let (Some(score), Some(call)) = (
+            &self.contest_score,
+            self.entry.content.get(&FieldType::WorkedCall),
+        ) else {
+            return false;
+        };
+        let band = Band::from_freq_mhz(self.rig_state.freq / 1e6);
+        !call.is_empty() && score.is_dupe(call, band, self.mode_class())
+    }
+
+    /// Queues records marked as waiting for an eQSL upload, e.g. imported ones
+    fn queue_eqsl_uploads(&mut self) {
+        if let Some(log) = &self.cur_log {
+            for record in log.iter_records() {
+                if record.get_field(&FieldType::EqslSent).as_deref() == Some("Q")
+                    && let Some(id) = record.id()
+                    && let Err(e) = log.queue_upload(Service::Eqsl, id)
+                {
+                    error!("Could not queue the eQSL upload of {}: {}", id, e);
+                }
+            }
+        }
+        self.refresh_uploads();
+    }
+
+    /// Queues the records Club Log is missing a change of, e.g. edits and deletions
+    fn queue_clublog_uploads(&mut self) {
+        if self.settings.clublog_upload
+            && let Some(log) = &self.cur_log
+        {
+            let res = log.clublog_pending().and_then(|pending| {
+                pending
+                    .into_iter()
+                    .try_for_each(|id| log.queue_upload(Service::Clublog, id))
+            });
+            if let Err(e) = res {
+                error!("Could not queue Club Log uploads: {}", e);
+            }
+        }
+        self.refresh_uploads();
+    }
+
+    /// Queues a record if Club Log is missing a change of it, without reading the others
+    fn queue_clublog_upload(&mut self, id: RecordId) {
+        if !self.settings.clublog_upload {
+            return;
+        }
+        let Some(log) = &self.cur_log else {
+            return;
+        };
+        match log.clublog_change(id) {
+            Ok(Some(_)) => match log.queue_upload(Service::Clublog, id) {
+                Ok(()) => self.refresh_uploads(),
+                Err(e) => error!("Could not queue the Club Log upload of {}: {}", id, e),
+            },
+            Ok(None) => {}
+            Err(e) => error!("Could not read the Club Log status of {}: {}", id, e),
+        }
+    }
+
+    /// Reloads the upload queues from the log
+    fn refresh_uploads(&mut self) {
+        self.uploads.clear();
+        let Some(log) = &self.cur_log else {
+            return;
+        };
+        for service in Service::ALL {
+            match log.pending_uploads(service) {
+                Ok(pending) => {
+                    self.uploads.insert(service, pending);
+                }
+                Err(e) => error!("Could not read the {} upload queue: {}", service, e),
+            }
+        }
+    }
+
+    /// Sends the change of record `id` that `service` is missing
+    fn start_upload(&mut self, service: Service, id: RecordId) -> Vec<Effect> {
+        let Some(log) = &self.cur_log else {
+            return Vec::new();
+        };
+        match service {
+            Service::Eqsl => {
+                let Some(record) = log.get_record_by_id(id) else {
+                    // deleted before it was uploaded
+                    self.upload_finished(service, id, Ok(()));
+                    return Vec::new();
+                };
+                self.uploading.insert(service);
+                vec![Effect::UploadEqsl(id, record)]
+            }
+            Service::Clublog => {
+                let change = match log.clublog_change(id) {
+                    Ok(Some(change)) => change,
+                    Ok(None) => {
+                        self.upload_finished(service, id, Ok(()));
+                        return Vec::new();
+                    }
+                    Err(e) => {
+                        self.upload_finished(service, id, Err(UploadError::Failed(e.to_string())));
+                        return Vec::new();
+                    }
+                };
+                self.uploading.insert(service);
+                vec![Effect::SendClublog(id, change)]
+            }
+        }
+    }
+
+    /// Takes a finished or refused upload off the queue, or schedules its next attempt
+    fn upload_finished(&mut self, service: Service, id: RecordId, res: Result<(), UploadError>) {
+        self.uploading.remove(&service);
+        if let Some(log) = self.cur_log.clone() {
+            let res = match res {
+                Ok(()) => log.upload_succeeded(service, id),
+                Err(UploadError::Rejected(e)) => {
+                    let call = log
+                        .get_record_by_id(id)
+                        .and_then(|r| r.get_field(&FieldType::WorkedCall))
+                        .unwrap_or_default();
+                    self.report_error(format!("{} refused the QSO with {}: {}", service, call, e));
+                    log.upload_rejected(service, id)
+                }
+                Err(UploadError::Failed(e)) => {
+                    error!("{} upload of record {} failed: {}", service, id, e);
+                    log.upload_failed(service, id, &e, jiff::Timestamp::now())
+                }
+            };
+            if let Err(e) = res {
+                error!("Could not update the {} upload queue: {}", service, e);
+            }
+        }
+        self.refresh_uploads();
+    }
+
+    /// Stores the rig's frequency and mode in the session history of the current log
+    fn record_rig_sample(&self) {
+        let (Some(log), Some(_)) = (&self.cur_log, &self.rig_state.rig) else {
+            return;
+        };
+        if self.rig_state.freq <= 0.0 || log.is_read_only() {
+            return;
+        }
+        let freq = Frequency::from_hz(self.rig_state.freq.round() as u64);
+        let mode = rig::mode_name(self.rig_state.mode);
+        if let Err(e) = log.record_rig_sample(jiff::Timestamp::now(), freq, mode) {
+            error!("Could not record rig history: {}", e);
+        }
+    }
+
+    fn set_ptt(&mut self, on: bool) {
+        let Some(rig) = self.rig() else {
+            return;
+        };
+        match rig.set_ptt(on) {
+            Ok(_) => self.rig_state.ptt = on,
+            Err(e) => self.report_error(format!("Could not set PTT: {}", e)),
+        }
+    }
+
+    /// Transmits `offset_khz` above the receive frequency on the other VFO, as when
+    /// calling a DX station listening up. None goes back to simplex.
+    fn set_split(&mut self, offset_khz: Option<u32>) {
+        let Some(rig) = self.rig() else {
+            return;
+        };
+        let tx = offset_khz.map(|khz| self.rig_state.freq + khz as f64 * 1e3);
+        if let Err(e) = rig.set_split(tx) {
+            self.report_error(format!("Could not set split: {}", e));
+        }
+    }
+
+    /// Transmits voice message `n`, keying the rig while it plays.
+    /// Returns false if no WAV file is assigned to it.
+    fn play_voice(&mut self, n: usize) -> bool {
+        let Some(path) = self
+            .settings
+            .voice_messages
+            .get(n)
+            .filter(|p| !p.is_empty())
+            .cloned()
+        else {
+            self.auto_cq.stop();
+            return false;
+        };
+        #[cfg(feature = "audio")]
+        {
+            self.set_ptt(true);
+            match audio::play(Path::new(&path), &self.settings.audio_output) {
+                Ok(playback) => self.voice = Some(playback),
+                Err(e) => {
+                    self.entry_error = Some(format!("Could not play {}: {}", path, e));
+                    // nothing is playing, so stop_voice would leave the rig keyed
+                    self.set_ptt(false);
+                    self.stop_voice();
+                }
+            }
+            true
+        }
+        #[cfg(not(feature = "audio"))]
+        {
+            self.entry_error = Some(format!("Built without audio support, cannot play {}", path));
+            self.auto_cq.stop();
+            false
+        }
+    }
+
+    /// Stops the voice keyer and auto CQ, unkeying the rig
+    fn stop_voice(&mut self) {
+        self.auto_cq.stop();
+        #[cfg(feature = "audio")]
+        if self.voice.take().is_some() {
+            self.set_ptt(false);
+        }
+    }
+
+    /// Whether a voice message is playing or auto CQ is waiting to repeat one
+    pub fn voice_active(&self) -> bool {
+        #[cfg(feature = "audio")]
+        if self.voice.is_some() {
+            return true;
+        }
+        self.auto_cq.is_running()
+    }
+
+    pub fn cw_decoding(&self) -> bool {
+        #[cfg(feature = "audio")]
+        if self.cw_listener.is_some() {
+            return true;
+        }
+        false
+    }
+
+    /// The mode class of the QSO being entered: the manually selected mode,
+    /// otherwise the rig's current mode, otherwise phone
+    pub fn mode_class(&self) -> ModeClass {
+        match self.entry.content.get(&FieldType::Mode) {
+            Some(mode) if !mode.is_empty() => ModeClass::from_mode(mode),
+            _ => rig::mode_class(self.rig_state.mode).unwrap_or(ModeClass::Phone),
+        }
+    }
+
+    /// Returns the open rig, if any
+    fn rig(&self) -> Option<&dyn RigBackend> {
+        self.rig_state.rig.as_deref()
+    }
+
+    /// Handles `message`, returning what the UI is to do for it
+    pub fn update(&mut self, message: Message) -> Vec<Effect> {
+        if self.read_only && Self::writes_log(&message) {
+            self.report_error("The log is open read-only");
+            return Vec::new();
+        }
+        match message {
+            Message::EntrySelected => self.screen = Screen::Entry,
+            Message::LogListSelected => self.screen = Screen::LogList,
+            Message::ClusterSelected => self.screen = Screen::Cluster,
+            Message::DupeSheetSelected => self.screen = Screen::DupeSheet,
+            Message::UploadsSelected => self.screen = Screen::Uploads,
+            Message::MaintenanceSelected => {
+                self.refresh_disk_usage();
+                self.screen = Screen::Maintenance;
+            }
+            Message::PaperSelected => {
+                self.screen = Screen::Paper;
+                return self.focus_paper(self.paper.focused);
+            }
+            Message::PaperChanged(col, value) => {
+                self.paper.row.0[col] = value;
+                self.paper.focused = col;
+            }
+            Message::MapSelected => {
+                self.refresh_map();
+                self.map_time = jiff::Timestamp::now();
+                self.screen = Screen::Map;
+            }
+            Message::MapByBand(by_band) => self.map_by_band = by_band,
+            Message::MapTick => self.map_time = jiff::Timestamp::now(),
+            Message::SolarTick => {
+                if !self.solar.start_fetch(Instant::now()) {
+                    return Vec::new();
+                }
+                return vec![Effect::FetchSolar];
+            }
+            Message::NoteChanged(text) => {
+                self.note = Some(self.settings.expand_snippet(&text).unwrap_or(text))
+            }
+            Message::SearchChanged(text) => {
+                self.search = text;
+                self.search_seq += 1;
+                if self.search.trim().is_empty() {
+                    self.search_results = None;
+                    return Vec::new();
+                }
+                return vec![Effect::DelaySearch(self.search_seq)];
+            }
+            Message::SearchDue(seq) => {
+                if self.cur_log.is_none() || seq != self.search_seq {
+                    return Vec::new();
+                }
+                return vec![Effect::Search(seq, self.search.clone())];
+            }
+            Message::SearchDone(seq, results) => {
+                if seq == self.search_seq {
+                    self.search_results = Some(results);
+                }
+            }
+            Message::LogChanged(event) => {
+                match event {
+                    LogEvent::Inserted(idx) | LogEvent::Modified(idx) => {
+                        if let Some(log) = &self.cur_log
+                            && let Some(record) = log.get_record(idx)
+                        {
+                            // a new QSO only adds to the session's counts
+                            if let (LogEvent::Inserted(_), Some((id, _))) = (&event, &self.session)
+                            {
+                                let filter = Filter {
+                                    session: Some(*id),
+                                    ..Default::default()
+                                };
+                                if filter.matches(&record) {
+                                    self.session_stats.add(&record);
+                                }
+                            }
+                            self.records.insert(idx, record);
+                        }
+                    }
+                    LogEvent::Deleted(idx) => {
+                        // edits and deletions made anywhere reach Club Log
+                        if let Some(id) = self.records.remove(&idx).and_then(|r| r.id()) {
+                            self.queue_clublog_upload(id);
+                        }
+                    }
+                }
+                if let LogEvent::Modified(idx) = event
+                    && let Some(id) = self.cur_log.as_ref().and_then(|log| log.record_id(idx))
+                {
+                    self.queue_clublog_upload(id);
+                }
+                if !matches!(event, LogEvent::Inserted(_)) {
+                    self.refresh_session_stats();
+                }
+            }
+            Message::SolarFetched(Ok(xml)) => {
+                if let Err(e) =
+                    self.solar
+                        .fetched(&xml, &self.paths.data_file(SOLAR_FILE), Instant::now())
+                {
+                    error!("Could not cache solar data: {}", e);
+                }
+            }
+            Message::SolarFetched(Err(e)) => {
+                error!("Could not fetch solar data: {}", e);
+                self.solar.failed();
+            }
+            Message::ThemeSelected(theme) => {
+                self.settings.theme = theme.to_string();
+                self.save_settings();
+            }
+            Message::RegionSelected(region) => {
+                self.settings.region = region;
+                self.save_settings();
+            }
+            Message::EntryScaled(scale) => {
+                // rounded so repeated steps do not drift, e.g. to 1.2000001
+                let scale = (scale / ENTRY_SCALE_STEP).round() * ENTRY_SCALE_STEP;
+                self.settings.entry_scale = scale.clamp(ENTRY_SCALE_MIN, ENTRY_SCALE_MAX);
+                self.save_settings();
+            }
+            Message::LogPathChanged(path) => self.log_path = path,
+            Message::PassphraseChanged(passphrase) => self.passphrase = passphrase,
+            Message::InitLog => {
+                let path = self.log_path.trim();
+                if path.is_empty() {
+                    self.report_error("Type the path of the new log first");
+                    return Vec::new();
+                }
+                // where `open_log` looks for it
+                let path = self.paths.data_file(path).to_string_lossy().to_string();
+                if Path::new(&path).exists() {
+                    // whatever is there is kept, a log is opened and anything else refused
+                    self.confirmation = Some((
+                        format!("{} already exists. Open it instead?", path),
+                        Message::OpenLog(path),
+                    ));
+                } else {
+                    self.open_log(path);
+                }
+            }
+            Message::OpenLog(path) => self.open_log(path),
+            Message::Import(path) => {
+                if let Some(log) = &self.cur_log {
+                    let offset = (self.settings.import_utc_offset * 3600.0).round() as i32;
+                    let tz = match jiff::tz::Offset::from_seconds(offset) {
+                        Ok(offset) => TimeZone::fixed(offset),
+                        Err(e) => {
+                            self.report_error(format!("Invalid import_utc_offset: {}", e));
+                            return Vec::new();
+                        }
+                    };
+                    let name = path.display().to_string();
+                    match log.import(&path, ImportPolicy::PreserveAll, &tz) {
+                        Ok(()) => self.notify(format!("Imported {}", name)),
+                        Err(e) => self.report_error(format!("Could not import {}: {}", name, e)),
+                    }
+                }
+                self.queue_eqsl_uploads();
+                self.refresh_awards();
+                self.refresh_contest();
+                self.refresh_worked();
+            }
+            Message::ImportFLE => {
+                if let Some(log) = &self.cur_log {
+                    let path = self.settings.fle_import_path.clone();
+                    match log.import_fle(Path::new(&path), ImportPolicy::PreserveAll) {
+                        Ok(()) => self.notify(format!("Imported {}", path)),
+                        Err(e) => self.report_error(format!("Could not import {}: {}", path, e)),
+                    }
+                }
+                self.queue_eqsl_uploads();
+                self.refresh_awards();
+                self.refresh_contest();
+                self.refresh_worked();
+            }
+            Message::PasteADIF => return vec![Effect::ReadClipboard],
+            Message::ADIFPasted(text) => {
+                let Some(log) = &self.cur_log else {
+                    return Vec::new();
+                };
+                let adif = adif::parse::parse_adif(&text.unwrap_or_default());
+                let count = adif.body.len();
+                self.log_status = match count {
+                    0 => "The clipboard holds no ADIF records".to_string(),
+                    _ => match log.import_adif_from(
+                        adif,
+                        ImportPolicy::PreserveAll,
+                        &TimeZone::UTC,
+                        &Source::Clipboard,
+                    ) {
+                        Ok(_) => format!("Pasted {} QSOs", count),
+                        Err(e) => format!("Could not import pasted ADIF: {}", e),
+                    },
+                };
+                self.queue_eqsl_uploads();
+                self.refresh_awards();
+                self.refresh_contest();
+                self.refresh_worked();
+            }
+            Message::UpdateCallHistory => {
+                let path = &self.settings.call_history_path;
+                if path.is_empty() {
+                    self.log_status = "Set call_history_path in the settings first".to_string();
+                } else if let Some(log) = &self.cur_log {
+                    match log.update_call_history_file(Path::new(path)) {
+                        Ok(history) => {
+                            self.log_status = format!("Wrote {} calls to {}", history.len(), path);
+                            self.call_history = Some(history);
+                        }
+                        Err(e) => self.log_status = format!("Could not update call history: {}", e),
+                    }
+                }
+            }
+            Message::PrintLog => {
+                if let Some(log) = &self.cur_log {
+                    let path = &self.settings.report_path;
+                    let title = format!("Log of {}", self.settings.my_call);
+                    let written = log
+                        .report_html(&title, &Filter::default())
+                        .and_then(|html| self.files.write(Path::new(path), &html));
+                    self.log_status = match written {
+                        Ok(()) => format!("Wrote the log to {}, print it from a browser", path),
+                        Err(e) => format!("Could not write printable log: {}", e),
+                    };
+                }
+            }
+            Message::PrintQslLabels => {
+                if let Some(log) = &self.cur_log {
+                    let path = &self.settings.qsl_labels_path;
+                    let filter = Filter {
+                        since_export: Some("qsl".to_string()),
+                        ..Default::default()
+                    };
+                    self.log_status = match log.export_qsl_labels(
+                        Path::new(path),
+                        &filter,
+                        self.settings.label_layout,
+                    ) {
+                        Ok(()) => format!("Wrote QSL labels to {}, print them at 100% scale", path),
+                        Err(e) => format!("Could not write QSL labels: {}", e),
+                    };
+                }
+            }
+            Message::RateSheet => {
+                if let Some(log) = &self.cur_log {
+                    let path = &self.settings.rate_sheet_path;
+                    // the whole log if there was no session
+                    let written = log.sessions().and_then(|sessions| {
+                        let filter = Filter {
+                            session: sessions.last().map(|(id, _)| *id),
+                            ..Default::default()
+                        };
+                        log.export_rate_sheet(Path::new(path), &filter)
+                    });
+                    self.log_status = match written {
+                        Ok(()) => format!("Wrote the rate sheet to {}", path),
+                        Err(e) => format!("Could not write the rate sheet: {}", e),
+                    };
+                }
+            }
+            Message::CountyReport => {
+                if let Some(log) = &self.cur_log {
+                    let path = &self.settings.county_report_path;
+                    let progress = log.county_progress();
+                    let written = progress
+                        .report()
+                        .and_then(|text| self.files.write(Path::new(path), &text));
+                    self.log_status = match written {
+                        Ok(()) => format!(
+                            "Wrote the {} counties worked to {}",
+                            progress.counties_worked(),
+                            path
+                        ),
+                        Err(e) => format!("Could not write the county report: {}", e),
+                    };
+                }
+            }
+            Message::ExportFormatSelected(format) => {
+                self.settings.export_format = format;
+                self.save_settings();
+            }
+            Message::Export => {
+                if let Some(log) = &self.cur_log {
+                    match log.check_export(&Filter::default()) {
+                        Ok(problems) if problems.is_empty() => self.export_log(Vec::new()),
+                        Ok(problems) => {
+                            self.log_status = format!(
+                                "{} QSOs would be rejected, fix them or export without them",
+                                problems.len()
+                            );
+                            self.export_problems = problems;
+                        }
+                        Err(e) => self.log_status = format!("Could not check log: {}", e),
+                    }
+                }
+            }
+            Message::ExportExcluding => {
+                let exclude = std::mem::take(&mut self.export_problems)
+                    .iter()
+                    .map(|p| p.idx)
+                    .collect();
+                self.export_log(exclude);
+            }
+            Message::CancelExport => self.export_problems.clear(),
+            Message::RecordSelected(idx) => match self.record_details(idx) {
+                Ok(details) => {
+                    self.record_details = Some((idx, details));
+                    self.record_edit = None;
+                }
+                Err(e) => self.log_status = format!("Could not read QSO {}: {}", idx, e),
+            },
+            Message::CloseRecord => {
+                self.record_details = None;
+                self.record_edit = None;
+            }
+            Message::EditRecord(idx) => {
+                let read = self
+                    .cur_log
+                    .as_ref()
+                    .and_then(|log| log.get_record_revision(idx));
+                match read {
+                    Some((record, revision)) => {
+                        self.record_edit = Some(RecordEdit::new(idx, record, revision));
+                    }
+                    None => self.report_error(format!("QSO {} was deleted", idx)),
+                }
+            }
+            Message::RecordFieldChanged(ty, val) => {
+                if let Some(edit) = &mut self.record_edit
+                    && let Some((_, typed)) = edit.fields.iter_mut().find(|(t, _)| *t == ty)
+                    && val.len() <= MAX_TEXT_LEN
+                {
+                    *typed = val;
+                }
+            }
+            Message::SaveRecord => {
+                if let Some(edit) = &self.record_edit {
+                    self.save_record(edit.revision);
+                }
+            }
+            Message::OverwriteRecord => {
+                if let Some(conflict) = self.record_edit.as_ref().and_then(|e| e.conflict.as_ref())
+                {
+                    self.save_record(conflict.revision);
+                }
+            }
+            Message::ReloadRecord => {
+                if let Some(edit) = &mut self.record_edit
+                    && let Some(conflict) = edit.conflict.take()
+                {
+                    *edit = RecordEdit::new(edit.idx, conflict.current, conflict.revision);
+                }
+            }
+            Message::CancelEdit => self.record_edit = None,
+            Message::ToggleManualTime(on) => {
+                self.manual_time = on.then(|| ManualTime::new(jiff::Timestamp::now()));
+            }
+            Message::ManualDateChanged(date) => {
+                if let Some(manual) = &mut self.manual_time
+                    && date.chars().all(|c| c.is_ascii_digit() || c == '-')
+                {
+                    manual.date = date;
+                }
+            }
+            Message::ManualTimeChanged(time) => {
+                if let Some(manual) = &mut self.manual_time
+                    && time.chars().all(|c| c.is_ascii_digit() || c == ':')
+                {
+                    manual.time = time;
+                }
+            }
+            Message::LocalTime(local) => {
+                self.settings.local_time = local;
+                self.save_settings();
+            }
+            Message::ContinentSelected(continent) => self.continent = continent,
+            Message::NormalizeLog => {
+                if let Some(log) = &self.cur_log {
+                    match log.normalize_all(Ruleset::default()) {
+                        Ok(report) => {
+                            for p in &report.problems {
+                                error!("Record {} {}: {}", p.idx, p.field, p.error);
+                            }
+                            self.log_status = format!(
+                                "Normalized {} fields, {} invalid fields left as is",
+                                report.changes.len(),
+                                report.problems.len()
+                            );
+                        }
+                        Err(e) => self.log_status = format!("Could not normalize log: {}", e),
+                    }
+                }
+                self.refresh_awards();
+                self.refresh_contest();
+                self.refresh_worked();
+            }
+            Message::VerifyLog => {
+                if let Some(log) = &self.cur_log {
+                    match log.verify() {
+                        Ok(report) => {
+                            self.log_damaged = !report.is_ok();
+                            self.log_status = format!("Verified log: {}", report);
+                        }
+                        Err(e) => self.log_status = format!("Could not verify log: {}", e),
+                    }
+                }
+            }
+            Message::RepairLog => {
+                if let Some(log) = &self.cur_log {
+                    match log.repair() {
+                        Ok(report) => {
+                            self.log_damaged = false;
+                            self.log_status = format!("Repaired log, found {}", report);
+                        }
+                        Err(e) => self.log_status = format!("Could not repair log: {}", e),
+                    }
+                }
+                // repairs renumber the records without sending events
+                self.reload_records();
+                self.refresh_awards();
+                self.refresh_contest();
+                self.refresh_worked();
+            }
+            Message::FlushLog => {
+                if let Some(log) = &self.cur_log {
+                    match log.flush() {
+                        Ok(bytes) => {
+                            self.notify(format!("Wrote {} to disk", size_text(bytes as u64)))
+                        }
+                        Err(e) => self.report_error(format!("Could not flush the log: {}", e)),
+                    }
+                }
+                self.refresh_disk_usage();
+            }
+            Message::ExportDiagnostics => {
+                let path = self.paths.data_file(&self.settings.diagnostics_path);
+                let verify = self.cur_log.as_ref().map(Log::verify);
+                let bundle = crash::bundle(&self.paths, self.crash_report.as_deref(), verify);
+                let written = bundle.and_then(|text| self.files.write(&path, &text));
+                match written {
+                    Ok(()) => self.notify(format!("Wrote the diagnostics to {}", path.display())),
+                    Err(e) => self.report_error(format!("Could not write the diagnostics: {}", e)),
+                }
+            }
+            Message::CompactLog => {
+                let (Some(log), Some(path)) = (
+                    self.cur_log.take(),
+                    self.settings.recent_logs.first().cloned(),
+                ) else {
+                    return Vec::new();
+                };
+                // the log event subscriptions drop their clones once there is no log
+                self.log_generation += 1;
+                crash::watch_log(None);
+                self.log_status = "Compacting the log...".to_string();
+                self.compacting = Some(log);
+                return vec![Effect::CompactLog(path)];
+            }
+            Message::LogCompacted(path, res) => {
+                let before = self.disk_usage;
+                match res {
+                    Ok(log) => self.switch_log(log, path),
+                    Err(e) => {
+                        self.report_error(format!("Could not compact the log: {}", e));
+                        self.open_log(path);
+                    }
+                }
+                self.refresh_disk_usage();
+                self.log_status = match (before, self.disk_usage) {
+                    (Some(before), Some(after)) if self.cur_log.is_some() => format!(
+                        "Compacted the log from {} to {}",
+                        size_text(before),
+                        size_text(after)
+                    ),
+                    _ => String::new(),
+                };
+            }
+            Message::InitHamlib => match backend::init_hamlib() {
+                Ok(lib) => self.hamlib = Some(lib),
+                Err(e) => self.report_error(e),
+            },
+            Message::OpenRig => {
+                let lib = self.hamlib.as_ref();
+                // opens the rigs not open yet, e.g. again after one stopped answering
+                let rig1 = self
+                    .rig_state
+                    .rig
+                    .is_none()
+                    .then(|| backend::open(lib, self.rig_model, &self.rig_path));
+                let rig2 = (self.other_radio.rig_state.rig.is_none()
+                    && !self.settings.rig2_path.is_empty())
+                .then(|| backend::open(lib, self.settings.rig2_model, &self.settings.rig2_path));
+                match rig1 {
+                    Some(Ok(rig)) => self.rig_state.rig = Some(rig),
+                    Some(Err(e)) => self.report_error(format!("Could not open the rig: {}", e)),
+                    None => {}
+                }
+                match rig2 {
+                    Some(Ok(rig)) => self.other_radio.rig_state.rig = Some(rig),
+                    Some(Err(e)) => {
+                        self.report_error(format!("Could not open the second rig: {}", e))
+                    }
+                    None => {}
+                }
+            }
+            Message::UpdateRig => {
+                self.expire_worked();
+                let first = self.rig_state.poll();
+                let second = self.other_radio.rig_state.poll();
+                // a rig that stops answering is closed rather than asked again every poll
+                if let Err(e) = first {
+                    self.rig_state.rig = None;
+                    self.report_error(format!("Lost the rig, open it again: {}", e));
+                }
+                if let Err(e) = second {
+                    self.other_radio.rig_state.rig = None;
+                    self.report_error(format!("Lost the second rig, open it again: {}", e));
+                }
+                if self.current_mode() != self.template_mode.as_deref() {
+                    self.apply_exchange_template();
+                }
+                self.record_rig_sample();
+            }
+            Message::TogglePtt => self.set_ptt(!self.rig_state.ptt),
+            Message::SetSplit(offset) => self.set_split(offset),
+            Message::MemorySelected(i) => {
+                let Some(memory) = self.settings.memories.get(i).cloned() else {
+                    return Vec::new();
+                };
+                self.entry
+                    .content
+                    .insert(FieldType::Mode, memory.mode.clone());
+                self.apply_exchange_template();
+                if let Some(rig) = self.rig() {
+                    let freq = memory.freq_khz * 1e3;
+                    let mode = rig::rig_mode(&memory.mode, freq);
+                    // 0 keeps the rig's normal passband for the mode
+                    if let Err(e) = rig.set_freq(freq).and_then(|_| rig.set_mode(mode, 0)) {
+                        self.report_error(format!("Could not tune rig to {}: {}", memory.name, e));
+                    }
+                }
+            }
+            Message::SendMacro(n) => {
+                // the operator took over
+                self.auto_cq.stop();
+                if self.mode_class() == ModeClass::Phone && self.play_voice(n) {
+                    return Vec::new();
+                }
+                let Some(template) = self.settings.cw_macros.get(n) else {
+                    return Vec::new();
+                };
+                let text = keyer::expand_macro(
+                    template,
+                    &keyer::MacroContext {
+                        my_call: &self.settings.my_call,
+                        content: &self.entry.content,
+                        exchange: self
+                            .current_mode()
+                            .and_then(|m| self.settings.exchange_template(m))
+                            .map_or("", |t| t.exchange.as_str()),
+                    },
+                );
+                if let Some(rig) = self.rig()
+                    && let Err(e) = rig.send_morse(&text)
+                {
+                    self.report_error(format!("Could not send CW message: {}", e));
+                }
+            }
+            Message::ToggleAutoCq => match self.auto_cq.is_running() {
+                true => self.stop_voice(),
+                false => self.auto_cq.start(0, Instant::now()),
+            },
+            Message::VoiceTick => {
+                let now = Instant::now();
+                #[cfg(feature = "audio")]
+                if self
+                    .voice
+                    .as_ref()
+                    .is_some_and(audio::Playback::is_finished)
+                {
+                    self.voice = None;
+                    self.set_ptt(false);
+                    self.auto_cq
+                        .finished(now, Duration::from_secs(self.settings.auto_cq_pause));
+                }
+                if let Some(n) = self.auto_cq.next_due(now) {
+                    self.play_voice(n);
+                }
+            }
+            Message::CwTick => {
+                #[cfg(feature = "audio")]
+                if let Some(listener) = &self.cw_listener {
+                    self.cw_text.push_str(&listener.take_text());
+                    // decoded CW is plain ASCII, so any byte is a character boundary
+                    let excess = self.cw_text.len().saturating_sub(CW_TEXT_LEN);
+                    self.cw_text.drain(..excess);
+                }
+            }
+            Message::DecodedCallSelected(call) => {
+                self.entry.content.insert(FieldType::WorkedCall, call);
+            }
+            Message::ToggleCluster => {
+                self.cluster.enabled = !self.cluster.enabled;
+                self.cluster.status = match self.cluster.enabled {
+                    true => format!("Connecting to {}", self.settings.cluster_node),
+                    false => "Disconnected".to_string(),
+                };
+            }
+            Message::Cluster(event) => match event {
+                cluster::Event::Connected => {
+                    self.cluster.status = format!("Connected to {}", self.settings.cluster_node)
+                }
+                cluster::Event::Disconnected(e) => {
+                    self.cluster.status = format!("Disconnected: {}", e)
+                }
+                cluster::Event::Spot(spot) => {
+                    self.cluster.spots.insert(0, spot);
+                    self.cluster.spots.truncate(cluster::MAX_SPOTS);
+                }
+            },
+            Message::Gps(event) => match event {
+                gps::Event::Fix(lat, lon) => {
+                    self.gps_grid = Some(geo::gridsquare(lat, lon));
+                    self.grid_changed();
+                }
+                gps::Event::Lost(e) => {
+                    if self.gps_grid.take().is_some() {
+                        self.log_status = format!("Lost the GPS position: {}", e);
+                    }
+                }
+            },
+            Message::RoverGridChanged(grid) => {
+                self.rover_grid = grid;
+                self.grid_changed();
+            }
+            Message::MyReferenceChanged(field, value) => {
+                if !field.is_valid(&value) {
+                    return Vec::new();
+                }
+                let value = value.to_ascii_uppercase();
+                match field {
+                    FieldType::MyIota => self.settings.my_iota = value,
+                    FieldType::MySotaRef => self.settings.my_sota_ref = value,
+                    FieldType::MyWwffRef => self.settings.my_wwff_ref = value,
+                    _ => return Vec::new(),
+                }
+                self.save_settings();
+            }
+            Message::DismissGridAlert => self.grid_alert = None,
+            Message::Confirm(question, message) => self.confirmation = Some((question, *message)),
+            Message::Confirmed => {
+                if let Some((_, message)) = self.confirmation.take() {
+                    return self.update(message);
+                }
+            }
+            Message::CancelConfirm => self.confirmation = None,
+            Message::CloseRequested => {
+                self.shut_down();
+                return vec![Effect::Exit];
+            }
+            Message::DismissToast(i) => self.toasts.dismiss(i),
+            Message::ToastTick => self.toasts.expire(Instant::now()),
+            Message::DraftTick => self.save_draft(),
+            Message::N1mm(event) => match event {
+                n1mm::Event::Listening => {
+                    self.log_status =
+                        format!("Receiving N1MM contacts on {}", self.settings.n1mm_listen)
+                }
+                n1mm::Event::Failed(e) => self.log_status = format!("N1MM listener failed: {}", e),
+                n1mm::Event::Contact(record, source) => {
+                    let call = record.get_field(&FieldType::WorkedCall).unwrap_or_default();
+                    match self.add_qso(record, source) {
+                        Ok(broadcast) => return broadcast,
+                        Err(e) => {
+                            let e = format!("Could not log contact with {}: {}", call, e);
+                            self.report_error(e);
+                        }
+                    }
+                }
+            },
+            #[cfg(feature = "http")]
+            Message::Http(event) => match event {
+                http::Event::Listening => {
+                    self.log_status = format!("Serving the log on {}", self.settings.http_listen)
+                }
+                http::Event::Failed(e) => self.log_status = format!("HTTP API failed: {}", e),
+                http::Event::Qso(record) => {
+                    let call = record.get_field(&FieldType::WorkedCall).unwrap_or_default();
+                    let source = Source::Program("the HTTP API".to_string());
+                    match self.add_qso(record, source) {
+                        Ok(broadcast) => return broadcast,
+                        Err(e) => self.report_error(format!(
+                            "Could not log QSO with {} from the HTTP API: {}",
+                            call, e
+                        )),
+                    }
+                }
+            },
+            Message::Sync(event) => match event {
+                sync::Event::Listening => {
+                    self.log_status = format!("Waiting for sync on {}", self.settings.sync_listen)
+                }
+                sync::Event::Failed(e) => self.log_status = format!("Log sync failed: {}", e),
+                sync::Event::Connected(peer) => {
+                    self.log_status = format!("Syncing the log with {}", peer)
+                }
+                sync::Event::Disconnected(e) => {
+                    self.log_status = format!("Log sync disconnected: {}", e)
+                }
+            },
+            Message::SpotSelected(i) => {
+                let Some(spot) = self.cluster.spots.get(i) else {
+                    return Vec::new();
+                };
+                self.work_station(spot.call.clone(), spot.freq_khz, true);
+                self.screen = Screen::Entry;
+            }
+            Message::BandMapSelected(call, freq_khz, kind) => {
+                self.work_station(call, freq_khz, kind == bandmap::StationKind::Spot);
+            }
+            Message::ScpSelected(call) => {
+                self.entry.content.insert(FieldType::WorkedCall, call);
+                // move on from the call field, which also looks the call up
+                self.entry.focused = self
+                    .entry
+                    .fields
+                    .iter()
+                    .position(|f| *f == FieldType::WorkedCall)
+                    .unwrap_or_default();
+                return self.focus_entry(self.entry.focused + 1);
+            }
+            Message::PlayAudio(path) => {
+                #[cfg(feature = "audio")]
+                match audio::play(Path::new(&path), &self.settings.audio_output) {
+                    Ok(playback) => self.playback = Some(playback),
+                    Err(e) => self.log_status = format!("Could not play {}: {}", path, e),
+                }
+                #[cfg(not(feature = "audio"))]
+                {
+                    self.log_status = format!("Built without audio support, cannot play {}", path);
+                }
+            }
+            Message::UploadTick => {
+                let Some(log) = &self.cur_log else {
+                    return Vec::new();
+                };
+                let now = jiff::Timestamp::now();
+                let mut due = Vec::new();
+                for service in Service::ALL {
+                    if self.uploading.contains(&service) {
+                        continue;
+                    }
+                    match log.next_upload(service, now) {
+                        Ok(Some(id)) => due.push((service, id)),
+                        Ok(None) => {}
+                        Err(e) => error!("Could not read the {} upload queue: {}", service, e),
+                    }
+                }
+                return due
+                    .into_iter()
+                    .flat_map(|(service, id)| self.start_upload(service, id))
+                    .collect();
+            }
+            Message::RetryUploads => {
+                if let Some(log) = self.cur_log.clone() {
+                    for service in Service::ALL {
+                        if let Err(e) = log.retry_uploads(service) {
+                            let e = format!("Could not retry {} uploads: {}", service, e);
+                            self.report_error(e);
+                        }
+                    }
+                }
+                self.refresh_uploads();
+                return self.update(Message::UploadTick);
+            }
+            Message::ClublogSent(id, change, res) => {
+                let res = res.and_then(|_| match &self.cur_log {
+                    Some(log) => log
+                        .clublog_sent(id, &change)
+                        .map_err(|e| UploadError::Failed(e.to_string())),
+                    None => Ok(()),
+                });
+                self.upload_finished(Service::Clublog, id, res);
+                // the record may have changed again while it was uploaded
+                self.queue_clublog_upload(id);
+            }
+            Message::FetchLotw => {
+                let Some(log) = &self.cur_log else {
+                    return Vec::new();
+                };
+                if self.settings.lotw_user.is_empty() {
+                    self.log_status = "Set lotw_user and lotw_password in the settings".to_string();
+                    return Vec::new();
+                }
+                let since = match log.lotw_last_qsl() {
+                    Ok(since) => since,
+                    Err(e) => {
+                        self.log_status = format!("Could not read the last LoTW download: {}", e);
+                        return Vec::new();
+                    }
+                };
+                self.log_status = "Downloading LoTW confirmations...".to_string();
+                return vec![Effect::FetchLotw(since)];
+            }
+            Message::LotwFetched(res) => {
+                let Some(log) = &self.cur_log else {
+                    return Vec::new();
+                };
+                self.log_status = match res
+                    .and_then(|r| log.apply_lotw_report(&r).map_err(|e| e.to_string()))
+                {
+                    Ok(matches) => {
+                        if !matches.unmatched.is_empty() {
+                            error!(
+                                "LoTW confirmed QSOs not in the log: {:?}",
+                                matches.unmatched
+                            );
+                        }
+                        format!(
+                            "LoTW confirmed {} QSOs, {} were already confirmed, {} not in the log",
+                            matches.confirmed,
+                            matches.known,
+                            matches.unmatched.len()
+                        )
+                    }
+                    Err(e) => format!("Could not download LoTW confirmations: {}", e),
+                };
+                self.refresh_awards();
+            }
+            Message::EqslUploaded(id, res) => {
+                let res = res.and_then(|_| match &self.cur_log {
+                    Some(log) => match log.index_of(id) {
+                        Ok(Some(idx)) => log
+                            .set_field(idx, FieldType::EqslSent, "Y")
+                            .map_err(|e| UploadError::Failed(e.to_string())),
+                        Ok(None) => Ok(()),
+                        Err(e) => Err(UploadError::Failed(e.to_string())),
+                    },
+                    None => Ok(()),
+                });
+                self.upload_finished(Service::Eqsl, id, res);
+            }
+            Message::LookupDone(call, res) => match res {
+                Ok(Some(info)) => {
+                    if let Some(log) = &self.cur_log
+                        && !log.is_read_only()
+                        && let Err(e) = log.cache_lookup(&info)
+                    {
+                        error!("Could not cache lookup of {}: {}", call, e);
+                    }
+                    self.fill_lookup(&call, &info);
+                }
+                Ok(None) => {}
+                Err(e) => error!("Lookup of {} failed: {}", call, e),
+            },
+            Message::ModeSelected(mode) => {
+                self.entry.content.insert(FieldType::Mode, mode);
+                self.apply_exchange_template();
+            }
+            Message::SessionKindSelected(kind) => self.session_kind = kind,
+            Message::SessionNameChanged(name) => self.session_name = name,
+            Message::ToggleSession => {
+                if let Err(e) = self.toggle_session() {
+                    self.entry_error = Some(format!("Could not start or end session: {}", e));
+                }
+                self.refresh_session_stats();
+            }
+            Message::ContentChanged((k, v)) => {
+                let effects = self.entry.edit(k, v, self.mode_class(), &self.settings);
+                return self.run_effects(effects);
+            }
+            Message::KeyPressed(event) => return self.key_pressed(event),
+        };
+        Vec::new()
+    }
+
+    fn key_pressed(&mut self, event: KeyEvent) -> Vec<Effect> {
+        if let Key::Named(named) = &event.key {
+            let fkeys = [
+                Named::F1,
+                Named::F2,
+                Named::F3,
+                Named::F4,
+                Named::F5,
+                Named::F6,
+                Named::F7,
+                Named::F8,
+            ];
+            if let Some(n) = fkeys.iter().position(|f| f == named) {
+                return self.update(Message::SendMacro(n));
+            }
+        }
+        if matches!(self.screen, Screen::Paper) {
+            return self.paper_key_pressed(event);
+        }
+        if matches!(self.screen, Screen::LogList) {
+            return match (event.key.as_ref(), event.modifiers) {
+                (Key::Character("v"), m) if m.control() && !event.captured => {
+                    self.update(Message::PasteADIF)
+                }
+                _ => Vec::new(),
+            };
+        }
+        if !matches!(self.screen, Screen::Entry) {
+            return Vec::new();
+        }
+        if self.note.is_some() {
+            return match event.key.as_ref() {
+                Key::Named(Named::Enter) => self.submit_note(),
+                Key::Named(Named::Escape) => {
+                    self.note = None;
+                    self.focus_entry(self.entry.focused)
+                }
+                _ => Vec::new(),
+            };
+        }
+        match (event.key.as_ref(), event.modifiers) {
+            (Key::Named(Named::Tab), Modifiers::SHIFT) if !event.captured => {
+                self.focus_entry(self.entry.focused.saturating_sub(1))
+            }
+            (Key::Named(Named::Tab), _) if !event.captured => {
+                let effects = self.entry.focus_next();
+                self.run_effects(effects)
+            }
+            (Key::Named(Named::Enter), _) => match self.log_qso() {
+                Ok(mut effects) => {
+                    self.clear_entry();
+                    effects.extend(self.focus_entry(0));
+                    effects
+                }
+                Err(e) => {
+                    self.entry_error = Some(e.to_string());
+                    Vec::new()
+                }
+            },
+            (Key::Named(Named::Escape), _) => {
+                self.stop_voice();
+                self.clear_entry();
+                self.focus_entry(0)
+            }
+            (Key::Character("n"), m) if m.control() => {
+                self.note = Some(String::new());
+                vec![Effect::FocusNote]
+            }
+            (Key::Character("w"), m) if m.control() => {
+                self.clear_entry();
+                self.entry.content.remove(&FieldType::Mode);
+                self.focus_entry(0)
+            }
+            (Key::Named(named @ (Named::ArrowUp | Named::ArrowDown)), m)
+                if m.control() && self.manual_time.is_some() =>
+            {
+                let minutes = match m.shift() {
+                    true => 60,
+                    false => 1,
+                };
+                match named {
+                    Named::ArrowUp => self.step_manual_time(minutes),
+                    _ => self.step_manual_time(-minutes),
+                }
+                Vec::new()
+            }
+            (Key::Character("r"), m) if m.control() => {
+                self.switch_radio();
+                self.focus_entry(self.entry.focused)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Writes the log to the export path in the export format, leaving out the records
+    /// at `exclude`
+    fn export_log(&mut self, exclude: Vec<usize>) {
+        let Some(log) = &self.cur_log else {
+            return;
+        };
+        let format = &self.settings.export_format;
+        let Some(exporter) = formats::exporter(format) else {
+            self.log_status = format!("Unknown export format {}", format);
+            return;
+        };
+        let filter = Filter {
+            exclude,
+            ..Default::default()
+        };
+        let path =
+            Path::new(&self.settings.adif_export_path).with_extension(exporter.extensions()[0]);
+        self.log_status = match exporter.export(log, &path, &filter) {
+            Ok(()) => format!("Exported the log to {}", path.display()),
+            Err(e) => format!("Could not export log: {}", e),
+        };
+    }
+
+    /// Moves the manual QSO time on by `minutes`, back if negative
+    fn step_manual_time(&mut self, minutes: i64) {
+        let Some(manual) = &mut self.manual_time else {
+            return;
+        };
+        match manual
+            .timestamp()
+            .and_then(|ts| Ok(ts.checked_add(jiff::SignedDuration::from_mins(minutes))?))
+        {
+            Ok(ts) => *manual = ManualTime::new(ts),
+            Err(e) => self.entry_error = Some(e.to_string()),
+        }
+    }
+
+    /// Moves the focus to the other radio, along with the QSO being entered on it
+    fn switch_radio(&mut self) {
+        if self.other_radio.rig_state.rig.is_none() {
+            return;
+        }
+        std::mem::swap(&mut self.rig_state, &mut self.other_radio.rig_state);
+        std::mem::swap(&mut self.entry.content, &mut self.other_radio.content);
+        self.radio = 3 - self.radio;
+        self.entry_error = None;
+    }
+
+    /// Keeps the quick note for the QSO being entered, or adds it to the last QSO
+    /// when the entry is empty
+    fn submit_note(&mut self) -> Vec<Effect> {
+        let note = Note::new(
+            jiff::Timestamp::now(),
+            &self.note.take().unwrap_or_default(),
+        );
+        if !note.text.is_empty() {
+            let in_qso = self
+                .entry
+                .content
+                .get(&FieldType::WorkedCall)
+                .is_some_and(|c| !c.is_empty());
+            match (in_qso, &self.cur_log) {
+                (true, _) => self.pending_notes.push(note),
+                (false, Some(log)) => {
+                    let last = log.get_idx().checked_sub(1).and_then(|i| log.record_id(i));
+                    match last.map(|id| log.add_note(id, &note)) {
+                        Some(Ok(())) => {}
+                        Some(Err(e)) => self.entry_error = Some(e.to_string()),
+                        None => self.entry_error = Some("No QSO to add the note to".to_string()),
+                    }
+                }
+                (false, None) => self.entry_error = Some("No log is open".to_string()),
+            }
+        }
+        self.focus_entry(self.entry.focused)
+    }
+
+    /// Focuses entry field `idx`, clamped to the last field.
+    /// Leaving the call field looks up the call.
+    fn focus_entry(&mut self, idx: usize) -> Vec<Effect> {
+        let effects = self.entry.focus(idx);
+        self.run_effects(effects)
+    }
+
+    /// Carries out the lookups the entry asks for after a change, leaving the rest to
+    /// the UI
+    fn run_effects(&mut self, effects: Vec<Effect>) -> Vec<Effect> {
+        effects
+            .into_iter()
+            .flat_map(|effect| match effect {
+                Effect::LookUpCall => self.lookup_call(),
+                effect => vec![effect],
+            })
+            .collect()
+    }
+
+    /// Fills the entry from the lookup cache, or starts an online lookup
+    fn lookup_call(&mut self) -> Vec<Effect> {
+        let Some(call) = self
+            .entry
+            .content
+            .get(&FieldType::WorkedCall)
+            .and_then(|c| callsign::validate_callsign(c).ok())
+        else {
+            return Vec::new();
+        };
+        // the history has what was sent in contests, so it comes before callbooks
+        if let Some(history) = &self.call_history {
+            self.fill_fields(&call, history.fields(&call));
+        }
+        // what the prefix tells, for the calls the history doesn't know
+        if let (Some(contest), Some(prefixes)) = (self.settings.contest, &self.prefixes) {
+            let guess = contest.guess_exchange(&call, prefixes);
+            self.fill_fields(&call, guess);
+        }
+        if let Some(log) = &self.cur_log {
+            match log.cached_lookup(&call) {
+                Ok(Some(info)) => {
+                    self.fill_lookup(&call, &info);
+                    return Vec::new();
+                }
+                Ok(None) => {}
+                Err(e) => error!("Could not read lookup cache: {}", e),
+            }
+        }
+        if self.lookups.is_empty() {
+            return Vec::new();
+        }
+        vec![Effect::LookUp(call)]
+    }
+
+    /// Fills empty entry fields from a lookup, if the entry still holds the looked up call
+    fn fill_lookup(&mut self, call: &str, info: &CallInfo) {
+        self.fill_fields(call, info.fields());
+    }
+
+    /// Fills empty entry fields, if the entry still holds `call`
+    fn fill_fields(&mut self, call: &str, fields: Vec<(FieldType, String)>) {
+        if self
+            .entry
+            .content
+            .get(&FieldType::WorkedCall)
+            .map(|c| c.to_ascii_uppercase())
+            != Some(call.to_string())
+        {
+            return;
+        }
+        for (ty, val) in fields {
+            let entry = self.entry.content.entry(ty).or_default();
+            if entry.is_empty() {
+                *entry = val;
+            }
+        }
+    }
+
+    /// Clears the typed entry fields, keeping the selected mode, typed frequency and power
+    fn clear_entry(&mut self) {
+        self.entry.clear();
+        self.entry_error = None;
+        self.pending_notes.clear();
+        self.spot_call = None;
+        self.apply_exchange_template();
+    }
+
+    /// Validates the entry fields and inserts them into the current log as a new QSO
+    fn log_qso(&mut self) -> anyhow::Result<Vec<Effect>> {
+        #[allow(unused_mut)]
+        let mut record = self.entry_record()?;
+        #[cfg(feature = "audio")]
+        self.link_recording(&mut record);
+        let source = match (
+            &self.spot_call,
+            self.entry.content.get(&FieldType::WorkedCall),
+        ) {
+            (Some(spot), Some(call)) if spot == call => Source::Cluster,
+            _ => Source::Manual,
+        };
+        self.add_qso(record, source)
+    }
+
+    /// Ends the running recording and links the QSO to it
+    #[cfg(feature = "audio")]
+    fn link_recording(&mut self, record: &mut LogRecord) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+        match recorder.split() {
+            Ok(path) => {
+                record.insert_field(FieldType::AudioRef, &path.to_string_lossy());
+            }
+            Err(e) => self.report_error(format!("Could not save QSO recording: {}", e)),
+        }
+    }
+
+    /// Tab moves through the paper log columns and Enter logs the row
+    fn paper_key_pressed(&mut self, event: KeyEvent) -> Vec<Effect> {
+        let focused = self.paper.focused;
+        match (event.key.as_ref(), event.modifiers) {
+            (Key::Named(Named::Tab), Modifiers::SHIFT) if !event.captured => {
+                self.focus_paper(focused.saturating_sub(1))
+            }
+            (Key::Named(Named::Tab), _) if !event.captured => {
+                self.focus_paper((focused + 1) % paper::COLUMNS.len())
+            }
+            (Key::Named(Named::Enter), _) => {
+                match self
+                    .paper
+                    .row
+                    .to_record()
+                    .and_then(|r| self.add_qso(r, Source::Manual))
+                {
+                    Ok(mut effects) => {
+                        self.paper.advance();
+                        effects.extend(self.focus_paper(self.paper.focused));
+                        return effects;
+                    }
+                    Err(e) => self.paper.error = Some(e.to_string()),
+                }
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn focus_paper(&mut self, col: usize) -> Vec<Effect> {
+        self.paper.focused = col;
+        vec![Effect::FocusPaper(col)]
+    }
+
+    /// Inserts a QSO into the current log, queueing its upload and updating the scores.
+    /// Returns the broadcast of it.
+    fn add_qso(&mut self, mut record: LogRecord, source: Source) -> anyhow::Result<Vec<Effect>> {
+        let Some(log) = &self.cur_log else {
+            anyhow::bail!("No log is open");
+        };
+        if self.settings.eqsl_auto_upload {
+            // Q marks the QSO as queued so the upload is retried after a restart
+            record.insert_field(FieldType::EqslSent, "Q");
+        }
+        // QSOs from before the session, e.g. off a paper log, are not part of it
+        let time: Option<jiff::Timestamp> = record
+            .get_field(&FieldType::Timestamp)
+            .and_then(|t| t.parse().ok());
+        if let Some((id, session)) = &self.session
+            && record.get_field(&FieldType::Session).is_none()
+            && time.is_none_or(|t| t >= session.start)
+        {
+            record.insert_field(FieldType::Session, &id.to_string());
+        }
+        let idx = log.insert_record_from(record, &source)?;
+        if let Some(id) = log.record_id(idx) {
+            for note in self.pending_notes.drain(..) {
+                log.add_note(id, &note)?;
+            }
+            if self.settings.eqsl_auto_upload {
+                log.queue_upload(Service::Eqsl, id)?;
+            }
+            if self.settings.clublog_upload {
+                log.queue_clublog(id)?;
+                log.queue_upload(Service::Clublog, id)?;
+            }
+        }
+        let mut effects = Vec::new();
+        if let Some(record) = log.get_record(idx) {
+            if let Some(score) = &mut self.contest_score {
+                score.add(&record, self.prefixes.as_ref());
+            }
+            effects = self.broadcast_qso(&record);
+        }
+        self.refresh_awards();
+        self.refresh_worked();
+        Ok(effects)
+    }
+
+    /// Fills in the call of a station picked from the cluster or the band map and tunes
+    /// the rig to it
+    fn work_station(&mut self, call: String, freq_khz: f64, spotted: bool) {
+        self.spot_call = spotted.then(|| call.clone());
+        self.entry.content.insert(FieldType::WorkedCall, call);
+        if let Some(rig) = self.rig()
+            && let Err(e) = rig.set_freq(freq_khz * 1e3)
+        {
+            self.report_error(format!("Could not tune rig to spot: {}", e));
+        }
+    }
+
+    /// Ends the running session, or starts a new one with the chosen kind and name
+    fn toggle_session(&mut self) -> anyhow::Result<()> {
+        let Some(log) = &self.cur_log else {
+            anyhow::bail!("No log is open");
+        };
+        let now = jiff::Timestamp::now();
+        if self.session.take().is_some() {
+            log.end_session(now)?;
+            return Ok(());
+        }
+        let mut name = self.session_name.trim().to_string();
+        if name.is_empty()
+            && self.session_kind == SessionKind::Contest
+            && let Some(contest) = self.settings.contest
+        {
+            name = contest.name().to_string();
+        }
+        let session = Session {
+            kind: self.session_kind,
+            name,
+            start: now,
+            grid: self.settings.my_grid.clone(),
+            ..Default::default()
+        };
+        self.session = Some((log.start_session(session.clone())?, session));
+        Ok(())
+    }
+
+    /// Announces a logged QSO to the companion apps configured in the settings
+    fn broadcast_qso(&self, record: &LogRecord) -> Vec<Effect> {
+        if self.broadcast_to.is_empty() {
+            return Vec::new();
+        }
+        let contest = self.settings.contest.map(|c| c.name()).unwrap_or_default();
+        let payload = match broadcast::payload(
+            record,
+            self.settings.qso_broadcast_format,
+            &self.settings.my_call,
+            contest,
+        ) {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!("Could not broadcast QSO: {}", e);
+                return Vec::new();
+            }
+        };
+        vec![Effect::Broadcast(payload)]
+    }
+
+    /// Builds a record from the entry fields, filling in defaults from the mode and rig
+    fn entry_record(&self) -> anyhow::Result<LogRecord> {
+        let class = self.mode_class();
+        let mut record = LogRecord::new();
+        let time = match &self.manual_time {
+            Some(manual) => manual.timestamp()?,
+            None => jiff::Timestamp::now(),
+        };
+        record.insert_timestamp(time);
+        for f in &self.entry.fields {
+            let value = match self.entry.content.get(f) {
+                Some(v) if !v.is_empty() => v.to_string(),
+                _ => match f {
+                    FieldType::SentRST | FieldType::RcvdRST => class.default_rst().to_string(),
+                    _ => continue,
+                },
+            };
+            match f {
+                FieldType::WorkedCall => {
+                    record.insert_field(f.clone(), &callsign::validate_callsign(&value)?);
+                }
+                FieldType::SentRST | FieldType::RcvdRST => {
+                    util::mode::validate_rst(&value, class)?;
+                    record.insert_field(f.clone(), &value);
+                }
+                FieldType::GridSquare => {
+                    let grid = geo::prettyvalidate_gridsquare(&value, GridStrictness::Strict)?;
+                    record.insert_field(f.clone(), &grid);
+                }
+                FieldType::Frequency => {
+                    let freq = Frequency::parse_khz_or_mhz(&value)?;
+                    record.insert_frequency(f.clone(), freq);
+                }
+                FieldType::TxPower => {
+                    record.insert_field(f.clone(), &db::data::parse_power(&value)?);
+                }
+                _ => {
+                    record.insert_field(f.clone(), &f.validate(&value)?);
+                }
+            }
+        }
+        if record.get_field(&FieldType::WorkedCall).is_none() {
+            anyhow::bail!("Enter a call before logging");
+        }
+        // filled in by lookups without an entry field of their own
+        for f in [FieldType::DXCC, FieldType::CQZ] {
+            if let Some(v) = self.entry.content.get(&f) {
+                record.insert_field(f, v);
+            }
+        }
+        if let Some(mode) = self.current_mode() {
+            record.insert_field(FieldType::Mode, mode);
+        }
+        if self.rig_state.rig.is_some() && record.get_field(&FieldType::Frequency).is_none() {
+            // FREQ is where we transmitted, FREQ_RX where we listened
+            let freq = Frequency::from_hz(self.rig_state.tx_freq.round() as u64);
+            record.insert_frequency(FieldType::Frequency, freq);
+            if self.rig_state.split {
+                let rx = Frequency::from_hz(self.rig_state.freq.round() as u64);
+                record.insert_frequency(FieldType::RxFrequency, rx);
+            }
+        }
+        if self.other_radio.rig_state.rig.is_some() {
+            record.insert_field(FieldType::Radio, &self.radio.to_string());
+        }
+        // a typed time is for an earlier QSO, made somewhere else for all we know
+        if let Some(grid) = self.my_grid()
+            && self.manual_time.is_none()
+        {
+            record.insert_field(FieldType::from_adif_field("MY_GRIDSQUARE"), &grid);
+        }
+        if self.settings.references {
+            for (field, value) in self.settings.my_references() {
+                if !value.is_empty() {
+                    record.insert_field(field.clone(), &field.validate(value)?);
+                }
+            }
+        }
+        if record.get_field(&FieldType::TxPower).is_none()
+            && let Some(power) = self.default_power()
+        {
+            record.insert_field(FieldType::TxPower, &power);
+        }
+        // contest QSOs carry the contest and the exchange sent, for LoTW and contest robots
+        if let Some(id) = self.contest_id() {
+            record.insert_field(FieldType::ContestId, &FieldType::ContestId.validate(&id)?);
+            if record.get_field(&FieldType::SentExchange).is_none()
+                && let Some(template) = self
+                    .current_mode()
+                    .and_then(|m| self.settings.exchange_template(m))
+                && !template.exchange.is_empty()
+            {
+                let ctx = keyer::MacroContext {
+                    my_call: &self.settings.my_call,
+                    content: &self.entry.content,
+                    exchange: "",
+                };
+                let sent = keyer::expand_macro(&template.exchange, &ctx);
+                record.insert_field(FieldType::SentExchange, &sent);
+            }
+            // the zone sent in CQ WW, which Cabrillo logs list with the exchange
+            let my_zone = FieldType::from_adif_field("MY_CQ_ZONE");
+            if self.settings.contest == Some(Contest::CqWw)
+                && record.get_field(&my_zone).is_none()
+                && let Some(m) = self
+                    .prefixes
+                    .as_ref()
+                    .and_then(|p| p.lookup(&self.settings.my_call))
+            {
+                record.insert_field(my_zone, &m.cq_zone.to_string());
+            }
+        }
+        Ok(record)
+    }
+
+    /// CONTEST_ID of QSOs logged now: the one set, else that of the contest operated
+    fn contest_id(&self) -> Option<String> {
+        match (self.settings.contest_id.trim(), self.settings.contest) {
+            ("", Some(contest)) => Some(contest.adif_id(self.mode_class()).to_string()),
+            ("", None) => None,
+            (id, _) => Some(id.to_string()),
+        }
+    }
+
+    /// Power logged when none is typed: as read from the rig, else that of the running session
+    pub fn default_power(&self) -> Option<String> {
+        if let Some(watts) = self.rig_state.power {
+            return Some(watts.round().to_string());
+        }
+        self.session
+            .as_ref()
+            .filter(|(_, session)| session.power > 0)
+            .map(|(_, session)| session.power.to_string())
+    }
+
+    /// Writes the edited QSO if it is still at `revision`. If it was changed elsewhere
+    /// meanwhile the edit is kept, asking to reload the QSO or overwrite the change.
+    fn save_record(&mut self, revision: u64) {
+        let (Some(log), Some(edit)) = (&self.cur_log, &mut self.record_edit) else {
+            return;
+        };
+        let saved = edit
+            .edited()
+            .and_then(|record| log.modify_record_checked(edit.idx, record, revision));
+        match saved {
+            Ok(_) => {
+                let idx = edit.idx;
+                self.record_edit = None;
+                match self.record_details(idx) {
+                    Ok(details) => self.record_details = Some((idx, details)),
+                    Err(e) => self.report_error(e),
+                }
+            }
+            Err(e) => match e.downcast::<ConflictError>() {
+                Ok(conflict) => {
+                    let call = edit.record.get_field(&FieldType::WorkedCall);
+                    edit.conflict = Some(conflict);
+                    self.report_error(format!(
+                        "The QSO with {} was changed elsewhere while you edited it, \
+                         reload it or overwrite the change",
+                        call.as_deref().unwrap_or("?")
+                    ));
+                }
+                Err(e) => self.report_error(format!("Could not save the QSO: {}", e)),
+            },
+        }
+    }
+
+    /// Every field of the QSO at `idx`, then where it came from and when it changed
+    fn record_details(&self, idx: usize) -> anyhow::Result<Vec<String>> {
+        let Some(log) = &self.cur_log else {
+            anyhow::bail!("No log is open");
+        };
+        let Some(record) = log.get_record(idx) else {
+            anyhow::bail!("The QSO was deleted");
+        };
+        let mut details: Vec<String> = record
+            .iter()
+            .map(|(ty, val)| format!("{}: {}", ty, val))
+            .collect();
+        let Some(id) = record.id() else {
+            return Ok(details);
+        };
+        let time = |t: jiff::Timestamp| t.strftime("%Y-%m-%d %H:%M:%S UTC").to_string();
+        details.push(match log.provenance(id)? {
+            Some(p) => format!("Source: {}, inserted {}", p.source, time(p.inserted)),
+            None => "Source: unknown, inserted before sources were kept".to_string(),
+        });
+        if let Some(written) = log.last_written(id)? {
+            details.push(format!("Last changed {}", time(written)));
+        }
+        if let Some(status) = log.clublog_status(id)? {
+            details.push(
+                match status {
+                    ClublogStatus::Queued => "Club Log: waiting for upload",
+                    ClublogStatus::Uploaded => "Club Log: uploaded",
+                    ClublogStatus::Modified => "Club Log: changed since the upload, waiting",
+                    ClublogStatus::Deleted => "Club Log: deleted, waiting",
+                }
+                .to_string(),
+            );
+        }
+        Ok(details)
+    }
+
+    fn refresh_disk_usage(&mut self) {
+        self.disk_usage = self
+            .cur_log
+            .as_ref()
+            .and_then(|log| log.size_on_disk().ok());
+    }
+}
+
+/// `bytes` in kB, MB or GB
+pub fn size_text(bytes: u64) -> String {
+    match bytes {
+        0..1_000_000 => format!("{:.1} kB", bytes as f64 / 1e3),
+        1_000_000..1_000_000_000 => format!("{:.1} MB", bytes as f64 / 1e6),
+        _ => format!("{:.2} GB", bytes as f64 / 1e9),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        env, fs, io,
+        path::{Path, PathBuf},
+        rc::Rc,
+    };
+
+    use anyhow::{Result, anyhow};
+    use db::{
+        data::{FieldType, Log, LogHeader, LogRecord},
+        draft::Draft,
+        paths::Paths,
+        settings::Settings,
+    };
+    use iced::keyboard::{Key, Modifiers, key::Named};
+    use util::mode::ModeClass;
+
+    use super::{Core, Effect, Entry, Files, RigReading, Screen};
+    use crate::{
+        KeyEvent, Message,
+        backend::{MockRig, RigBackend},
+        rig::{self, Meters},
+    };
+
+    fn entry() -> Entry {
+        Entry::new(vec![
+            FieldType::WorkedCall,
+            FieldType::SentRST,
+            FieldType::Name,
+        ])
+    }
+
+    #[test]
+    pub fn test_edit() {
+        let settings = Settings::default();
+        let mut entry = entry();
+        let mut edit = |field: FieldType, text: &str, class| {
+            let effects = entry.edit(field.clone(), text.to_string(), class, &settings);
+            (entry.get(&field).map(str::to_string), effects)
+        };
+        assert_eq!(
+            (Some("W1AW".to_string()), vec![]),
+            edit(FieldType::WorkedCall, "w1aw", ModeClass::Cw)
+        );
+        // refused, the call stays
+        assert_eq!(
+            (Some("W1AW".to_string()), vec![]),
+            edit(FieldType::WorkedCall, "W1AW!", ModeClass::Cw)
+        );
+        assert_eq!(
+            (Some("599".to_string()), vec![]),
+            edit(FieldType::SentRST, "5999", ModeClass::Cw)
+        );
+        assert_eq!(
+            (Some("59".to_string()), vec![]),
+            edit(FieldType::SentRST, "59", ModeClass::Phone)
+        );
+        assert_eq!(
+            (Some("-12".to_string()), vec![]),
+            edit(FieldType::SentRST, "-12", ModeClass::Digital)
+        );
+        assert_eq!(
+            (Some("-12".to_string()), vec![]),
+            edit(FieldType::SentRST, "-1x", ModeClass::Digital)
+        );
+        assert_eq!(
+            (Some("Thanks for the QSO, 73! ".to_string()), vec![]),
+            edit(FieldType::Name, ",73 ", ModeClass::Cw)
+        );
+        assert_eq!(
+            (Some("FN31pr".to_string()), vec![]),
+            edit(FieldType::GridSquare, "fn31PR", ModeClass::Cw)
+        );
+    }
+
+    #[test]
+    pub fn test_focus() {
+        let settings = Settings::default();
+        let mut entry = entry();
+        // a space after the call moves on to the exchange and looks the call up
+        assert_eq!(
+            vec![Effect::Focus(1), Effect::LookUpCall],
+            entry.edit(
+                FieldType::WorkedCall,
+                "k1abc ".to_string(),
+                ModeClass::Cw,
+                &settings
+            )
+        );
+        assert_eq!(Some("K1ABC"), entry.get(&FieldType::WorkedCall));
+        assert_eq!(vec![Effect::Focus(2)], entry.focus_next());
+        assert_eq!(vec![Effect::Focus(0)], entry.focus_next());
+        assert_eq!(vec![Effect::Focus(2), Effect::LookUpCall], entry.focus(10));
+        assert_eq!(vec![Effect::Focus(0)], Entry::default().focus_next());
+
+        entry.content.insert(FieldType::Mode, "CW".to_string());
+        entry.clear();
+        assert_eq!(None, entry.get(&FieldType::WorkedCall));
+        assert_eq!(Some("CW"), entry.get(&FieldType::Mode));
+    }
+
+    #[test]
+    pub fn test_draft() {
+        let mut entry = entry();
+        entry.content.insert(FieldType::Comment, "hi".to_string());
+        entry.content.insert(FieldType::Name, "Hiram".to_string());
+        entry
+            .content
+            .insert(FieldType::WorkedCall, "W1AW".to_string());
+        entry.content.insert(FieldType::SentRST, String::new());
+        entry.focused = 2;
+        let draft = entry.draft();
+        assert_eq!(
+            vec![
+                (FieldType::WorkedCall, "W1AW".to_string()),
+                (FieldType::Name, "Hiram".to_string()),
+                (FieldType::Comment, "hi".to_string()),
+            ],
+            draft.fields
+        );
+        assert_eq!(Some(FieldType::Name), draft.focused);
+
+        let mut restored = self::entry();
+        assert_eq!(vec![Effect::Focus(2)], restored.restore(&draft));
+        assert_eq!(draft, restored.draft());
+        // without a call there is no QSO to go back to
+        let mut other = self::entry();
+        let mode = Draft {
+            fields: vec![(FieldType::Mode, "FT8".to_string())],
+            focused: Some(FieldType::Name),
+        };
+        assert_eq!(Vec::<Effect>::new(), other.restore(&mode));
+        assert_eq!(0, other.focused);
+    }
+
+    #[test]
+    pub fn test_rig_reading() {
+        let rig = MockRig::new(14_025_000.0, "CW");
+        assert_eq!(
+            RigReading {
+                freq: 14_025_000.0,
+                mode: rig::rig_mode("CW", 14e6),
+                width: 2400,
+                power: Some(100.0),
+                meters: rig.meters().unwrap(),
+                split: false,
+                tx_freq: 14_025_000.0,
+            },
+            RigReading::read(&rig).unwrap()
+        );
+        rig.set_split(Some(14_030_000.0)).unwrap();
+        let reading = RigReading::read(&rig).unwrap();
+        assert!(reading.split);
+        assert_eq!(14_030_000.0, reading.tx_freq);
+    }
+
+    /// Files kept in memory, shared with the test, and a path that can't be written
+    #[derive(Clone, Default)]
+    struct MemoryFiles(Rc<RefCell<HashMap<PathBuf, String>>>);
+
+    impl MemoryFiles {
+        fn get(&self, path: &Path) -> Option<String> {
+            self.0.borrow().get(path).cloned()
+        }
+    }
+
+    impl Files for MemoryFiles {
+        fn read(&self, path: &Path) -> Result<Option<String>> {
+            Ok(self.get(path))
+        }
+
+        fn write(&self, path: &Path, text: &str) -> Result<()> {
+            if path.starts_with("/readonly") {
+                return Err(anyhow!("Permission denied"));
+            }
+            self.0
+                .borrow_mut()
+                .insert(path.to_path_buf(), text.to_string());
+            Ok(())
+        }
+
+        fn remove(&self, path: &Path) -> Result<()> {
+            self.0.borrow_mut().remove(path);
+            Ok(())
+        }
+    }
+
+    /// What was done to a `FakeRig`, shared with the test
+    #[derive(Default)]
+    struct FakeRigState {
+        freq: f64,
+        ptt: bool,
+        closed: bool,
+        /// Stops answering, as a rig switched off
+        broken: bool,
+    }
+
+    /// A rig on CW that the test can look into
+    #[derive(Clone, Default)]
+    struct FakeRig(Rc<RefCell<FakeRigState>>);
+
+    impl FakeRig {
+        fn answer(&self) -> Result<()> {
+            match self.0.borrow().broken {
+                true => Err(io::Error::from(io::ErrorKind::BrokenPipe).into()),
+                false => Ok(()),
+            }
+        }
+    }
+
+    impl RigBackend for FakeRig {
+        fn freq(&self) -> Result<f64> {
+            self.answer()?;
+            Ok(self.0.borrow().freq)
+        }
+
+        fn set_freq(&self, hz: f64) -> Result<()> {
+            self.answer()?;
+            self.0.borrow_mut().freq = hz;
+            Ok(())
+        }
+
+        fn mode(&self) -> Result<(u64, i64)> {
+            self.answer()?;
+            Ok((rig::rig_mode("CW", self.0.borrow().freq), 500))
+        }
+
+        fn set_mode(&self, _mode: u64, _width: i64) -> Result<()> {
+            self.answer()
+        }
+
+        fn set_ptt(&self, on: bool) -> Result<()> {
+            self.answer()?;
+            self.0.borrow_mut().ptt = on;
+            Ok(())
+        }
+
+        fn power(&self, _freq: f64, _mode: u64) -> Result<f64> {
+            Err(anyhow!("Not supported"))
+        }
+
+        fn meters(&self) -> Result<Meters> {
+            self.answer()?;
+            Ok(Meters::default())
+        }
+
+        fn split_freq(&self) -> Result<Option<f64>> {
+            Err(anyhow!("Not supported"))
+        }
+
+        fn set_split(&self, _tx_freq: Option<f64>) -> Result<()> {
+            Err(anyhow!("Not supported"))
+        }
+
+        fn send_morse(&self, _text: &str) -> Result<()> {
+            self.answer()
+        }
+
+        fn close(&mut self) -> Result<()> {
+            self.0.borrow_mut().closed = true;
+            Ok(())
+        }
+    }
+
+    /// A core with an empty log open, keeping its files in memory and working `rig`
+    fn core(files: &MemoryFiles, rig: &FakeRig) -> Core {
+        let dir = env::temp_dir().join(format!("veelog-tests-core-{}", std::process::id()));
+        let mut core = Core::new(
+            Paths::new(Some(dir)),
+            Settings::default(),
+            Box::new(files.clone()),
+        );
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let log = Log::new_init(db, LogHeader::new("N0CALL", "")).unwrap();
+        core.switch_log(log, "test.db".to_string());
+        rig.0.borrow_mut().freq = 14_025_000.0;
+        core.rig_state.rig = Some(Box::new(rig.clone()));
+        core
+    }
+
+    fn key(key: Key) -> Message {
+        Message::KeyPressed(KeyEvent {
+            key,
+            modifiers: Modifiers::empty(),
+            captured: false,
+        })
+    }
+
+    #[test]
+    pub fn test_core_log_qso() {
+        let (files, rig) = (MemoryFiles::default(), FakeRig::default());
+        let mut core = core(&files, &rig);
+        assert!(files.get(&core.paths.settings_file()).is_some());
+        core.update(Message::EntrySelected);
+        core.update(Message::UpdateRig);
+        assert_eq!(14_025_000.0, core.rig_state.freq);
+
+        let typed = (FieldType::WorkedCall, "w1aw".to_string());
+        assert_eq!(
+            Vec::<Effect>::new(),
+            core.update(Message::ContentChanged(typed))
+        );
+        assert_eq!(
+            vec![Effect::Focus(0)],
+            core.update(key(Key::Named(Named::Enter)))
+        );
+        let log = core.cur_log.clone().unwrap();
+        let record = log.get_record(0).unwrap();
+        assert_eq!(
+            Some("W1AW".to_string()),
+            record.get_field(&FieldType::WorkedCall)
+        );
+        assert_eq!(Some("CW".to_string()), record.get_field(&FieldType::Mode));
+        assert_eq!(
+            Some("599".to_string()),
+            record.get_field(&FieldType::SentRST)
+        );
+        assert_eq!(None, core.entry.get(&FieldType::WorkedCall));
+
+        // nothing typed is refused
+        assert_eq!(
+            Vec::<Effect>::new(),
+            core.update(key(Key::Named(Named::Enter)))
+        );
+        assert_eq!(
+            Some("Enter a call before logging".to_string()),
+            core.entry_error
+        );
+        assert_eq!(1, log.get_idx());
+    }
+
+    #[test]
+    pub fn test_core_rig() {
+        let (files, rig) = (MemoryFiles::default(), FakeRig::default());
+        let mut core = core(&files, &rig);
+        core.update(Message::TogglePtt);
+        assert!(rig.0.borrow().ptt);
+
+        // shutting down unkeys and closes the rig, and saves what was typed
+        core.update(Message::EntrySelected);
+        let typed = (FieldType::WorkedCall, "W1AW".to_string());
+        core.update(Message::ContentChanged(typed));
+        assert_eq!(vec![Effect::Exit], core.update(Message::CloseRequested));
+        assert!(!rig.0.borrow().ptt);
+        assert!(rig.0.borrow().closed);
+        assert!(core.rig_state.rig.is_none());
+        let draft = files.get(&core.paths.draft_file()).unwrap();
+        assert!(draft.contains("W1AW"));
+
+        // a rig that stops answering is dropped, the operator is told
+        let mut core = self::core(&files, &rig);
+        rig.0.borrow_mut().broken = true;
+        core.update(Message::UpdateRig);
+        assert!(core.rig_state.rig.is_none());
+        assert!(
+            core.toasts
+                .iter()
+                .any(|t| t.text.starts_with("Lost the rig"))
+        );
+    }
+
+    #[test]
+    pub fn test_core_draft() {
+        let (files, rig) = (MemoryFiles::default(), FakeRig::default());
+        let mut core = core(&files, &rig);
+        let draft = Draft {
+            fields: vec![
+                (FieldType::WorkedCall, "W1AW".to_string()),
+                (FieldType::Name, "Hiram".to_string()),
+            ],
+            focused: Some(FieldType::Name),
+        };
+        let path = core.paths.draft_file();
+        files
+            .write(&path, &serde_json::to_string(&draft).unwrap())
+            .unwrap();
+        let name = core.entry.fields.iter().position(|f| *f == FieldType::Name);
+        assert_eq!(vec![Effect::Focus(name.unwrap())], core.restore_draft());
+        assert!(matches!(core.screen, Screen::Entry));
+        assert_eq!(Some("Hiram"), core.entry.get(&FieldType::Name));
+
+        // logging it leaves nothing to restore
+        core.update(key(Key::Named(Named::Enter)));
+        core.update(Message::DraftTick);
+        assert_eq!(None, files.get(&path));
+        assert_eq!(Vec::<Effect>::new(), core.restore_draft());
+
+        // an encrypted log keeps the draft in it, restored once the log is unlocked
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let header = LogHeader::new("N0CALL", "");
+        let log = Log::new_init_encrypted(db, header, "correct horse").unwrap();
+        core.switch_log(log.clone(), "crypt.db".to_string());
+        core.update(Message::ContentChanged((
+            FieldType::WorkedCall,
+            "W1AW".to_string(),
+        )));
+        core.update(Message::DraftTick);
+        assert_eq!(None, files.get(&path));
+        let call = (FieldType::WorkedCall, "W1AW".to_string());
+        assert!(log.draft().unwrap().fields.contains(&call));
+        let mut unlocked = self::core(&files, &rig);
+        unlocked.switch_log(log, "crypt.db".to_string());
+        assert_eq!(Some("W1AW"), unlocked.entry.get(&FieldType::WorkedCall));
+    }
+
+    #[test]
+    pub fn test_core_reports() {
+        let (files, rig) = (MemoryFiles::default(), FakeRig::default());
+        let mut core = core(&files, &rig);
+        core.update(Message::PrintLog);
+        assert!(files.get(Path::new("log.html")).unwrap().contains("Log of"));
+
+        core.settings.fle_import_path = "/nonexistent/log.fle".to_string();
+        core.update(Message::ImportFLE);
+        assert!(
+            core.toasts
+                .iter()
+                .any(|t| t.text.starts_with("Could not import /nonexistent/log.fle"))
+        );
+
+        core.settings.county_report_path = "/readonly/counties.txt".to_string();
+        core.update(Message::CountyReport);
+        assert_eq!(
+            "Could not write the county report: Permission denied",
+            core.log_status
+        );
+    }
+
+    #[test]
+    pub fn test_core_init_log() {
+        let (files, rig) = (MemoryFiles::default(), FakeRig::default());
+        let mut core = core(&files, &rig);
+        let dir = env::temp_dir().join(format!("veelog-tests-init-{}", std::process::id()));
+        core.paths = Paths::new(Some(dir.clone()));
+        let existing = dir.join("existing.db");
+        fs::create_dir_all(&existing).unwrap();
+        // relative paths are in the data directory, not the current one
+        core.log_path = "existing.db".to_string();
+        core.update(Message::InitLog);
+        let (question, message) = core.confirmation.take().unwrap();
+        assert!(question.contains(&*existing.to_string_lossy()));
+        assert!(matches!(message, Message::OpenLog(path) if Path::new(&path) == existing));
+
+        core.log_path = "Cargo.toml".to_string();
+        core.update(Message::InitLog);
+        assert!(core.confirmation.is_none());
+        drop(core);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_core_search() {
+        let (files, rig) = (MemoryFiles::default(), FakeRig::default());
+        let mut core = core(&files, &rig);
+        assert_eq!(
+            vec![Effect::DelaySearch(1)],
+            core.update(Message::SearchChanged("w1".to_string()))
+        );
+        assert_eq!(
+            vec![Effect::DelaySearch(2)],
+            core.update(Message::SearchChanged("w1aw".to_string()))
+        );
+        // only the last edit is searched for
+        assert_eq!(Vec::<Effect>::new(), core.update(Message::SearchDue(1)));
+        assert_eq!(
+            vec![Effect::Search(2, "w1aw".to_string())],
+            core.update(Message::SearchDue(2))
+        );
+        core.update(Message::SearchDone(1, vec![3]));
+        assert_eq!(None, core.search_results);
+        core.update(Message::SearchDone(2, vec![0]));
+        assert_eq!(Some(vec![0]), core.search_results);
+    }
+
+    #[test]
+    pub fn test_core_edit_record() {
+        let (files, rig) = (MemoryFiles::default(), FakeRig::default());
+        let mut core = core(&files, &rig);
+        let mut record = LogRecord::new();
+        record
+            .insert_field(FieldType::WorkedCall, "W1AW")
+            .insert_field(FieldType::Name, "Hiram");
+        let log = core.cur_log.clone().unwrap();
+        let idx = log.insert_record(record).unwrap();
+
+        core.update(Message::RecordSelected(idx));
+        core.update(Message::EditRecord(idx));
+        core.update(Message::RecordFieldChanged(
+            FieldType::Name,
+            "Hiram Percy".to_string(),
+        ));
+        core.update(Message::SaveRecord);
+        assert!(core.record_edit.is_none());
+        let name = |log: &Log| log.get_record(idx).unwrap().get_field(&FieldType::Name);
+        assert_eq!(Some("Hiram Percy".to_string()), name(&log));
+
+        // a sync changes the QSO while it is edited
+        core.update(Message::EditRecord(idx));
+        core.update(Message::RecordFieldChanged(
+            FieldType::Name,
+            "Percy".to_string(),
+        ));
+        log.set_field(idx, FieldType::Name, "Maxim").unwrap();
+        core.update(Message::SaveRecord);
+        assert_eq!(Some("Maxim".to_string()), name(&log));
+        let edit = core.record_edit.as_ref().unwrap();
+        assert!(edit.conflict.is_some());
+        assert_eq!(vec!["Name: Maxim".to_string()], edit.changed_elsewhere());
+        assert!(
+            core.toasts
+                .iter()
+                .any(|t| t.text.contains("changed elsewhere"))
+        );
+
+        core.update(Message::ReloadRecord);
+        let edit = core.record_edit.as_ref().unwrap();
+        assert!(edit.conflict.is_none());
+        assert!(
+            edit.fields
+                .contains(&(FieldType::Name, "Maxim".to_string()))
+        );
+
+        core.update(Message::RecordFieldChanged(
+            FieldType::Name,
+            "Percy".to_string(),
+        ));
+        log.set_field(idx, FieldType::Name, "Hiram").unwrap();
+        core.update(Message::SaveRecord);
+        core.update(Message::OverwriteRecord);
+        assert!(core.record_edit.is_none());
+        assert_eq!(Some("Percy".to_string()), name(&log));
+    }
+}
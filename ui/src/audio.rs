@@ -0,0 +1,256 @@
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use anyhow::{Result, anyhow, bail};
+use cpal::{
+    Device, FromSample, Sample, SampleFormat, SampleRate, SizedSample, Stream, StreamConfig,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+};
+use hound::{WavReader, WavSpec, WavWriter};
+use jiff::Timestamp;
+use log::warn;
+
+use crate::cw::Decoder;
+
+type Writer = Arc<Mutex<Option<WavWriter<BufWriter<File>>>>>;
+
+/// Finds a sound card by name, or the default one when `name` is empty
+fn find_device(name: &str, input: bool) -> Result<Device> {
+    let host = cpal::default_host();
+    let device = match (name.is_empty(), input) {
+        (true, true) => host.default_input_device(),
+        (true, false) => host.default_output_device(),
+        (false, true) => host
+            .input_devices()?
+            .find(|d| d.name().is_ok_and(|n| n == name)),
+        (false, false) => host
+            .output_devices()?
+            .find(|d| d.name().is_ok_and(|n| n == name)),
+    };
+    device.ok_or_else(|| anyhow!("No sound card named {:?}", name))
+}
+
+/// Records the rig audio into WAV clips. Each logged QSO ends the running clip,
+/// which then holds everything heard since the previous QSO.
+pub struct Recorder {
+    _stream: Stream,
+    writer: Writer,
+    spec: WavSpec,
+    dir: PathBuf,
+    current: PathBuf,
+}
+
+impl Recorder {
+    pub fn start(device: &str, dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let device = find_device(device, true)?;
+        let config = device.default_input_config()?;
+        let spec = WavSpec {
+            channels: config.channels(),
+            sample_rate: config.sample_rate().0,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let current = clip_path(dir, Timestamp::now());
+        let writer = Arc::new(Mutex::new(Some(WavWriter::create(&current, spec)?)));
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => record::<f32>(&device, &config.into(), writer.clone())?,
+            SampleFormat::I16 => record::<i16>(&device, &config.into(), writer.clone())?,
+            SampleFormat::U16 => record::<u16>(&device, &config.into(), writer.clone())?,
+            format => bail!("Unsupported input sample format {}", format),
+        };
+        stream.play()?;
+        Ok(Self {
+            _stream: stream,
+            writer,
+            spec,
+            dir: dir.to_path_buf(),
+            current,
+        })
+    }
+
+    /// Finishes the running clip and starts the next one, returning the finished clip
+    pub fn split(&mut self) -> Result<PathBuf> {
+        let next = clip_path(&self.dir, Timestamp::now());
+        let new = WavWriter::create(&next, self.spec)?;
+        let old = self.writer.lock().unwrap().replace(new);
+        if let Some(old) = old {
+            old.finalize()?;
+        }
+        Ok(std::mem::replace(&mut self.current, next))
+    }
+}
+
+fn record<T>(device: &Device, config: &StreamConfig, writer: Writer) -> Result<Stream>
+where
+    T: SizedSample,
+    i16: FromSample<T>,
+{
+    Ok(device.build_input_stream(
+        config,
+        move |data: &[T], _| {
+            if let Ok(mut writer) = writer.lock()
+                && let Some(writer) = writer.as_mut()
+            {
+                for &sample in data {
+                    let _ = writer.write_sample(i16::from_sample(sample));
+                }
+            }
+        },
+        |e| warn!("Audio input failed: {}", e),
+        None,
+    )?)
+}
+
+/// A clip name sorting by time, e.g. `20250728-024813-120.wav`
+fn clip_path(dir: &Path, time: Timestamp) -> PathBuf {
+    dir.join(format!(
+        "{}-{:03}.wav",
+        time.strftime("%Y%m%d-%H%M%S"),
+        time.subsec_millisecond()
+    ))
+}
+
+/// Decodes CW heard on the rig audio, see `cw::Decoder`. Stops when dropped.
+pub struct CwListener {
+    _stream: Stream,
+    text: Arc<Mutex<String>>,
+}
+
+impl CwListener {
+    /// Listens on the named sound card for CW at `pitch` Hz
+    pub fn start(device: &str, pitch: f32) -> Result<Self> {
+        let device = find_device(device, true)?;
+        let config = device.default_input_config()?;
+        let decoder = Decoder::new(config.sample_rate().0, pitch);
+        let text = Arc::new(Mutex::new(String::new()));
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => decode::<f32>(&device, &config.into(), decoder, text.clone())?,
+            SampleFormat::I16 => decode::<i16>(&device, &config.into(), decoder, text.clone())?,
+            SampleFormat::U16 => decode::<u16>(&device, &config.into(), decoder, text.clone())?,
+            format => bail!("Unsupported input sample format {}", format),
+        };
+        stream.play()?;
+        Ok(Self {
+            _stream: stream,
+            text,
+        })
+    }
+
+    /// The text decoded since the last call
+    pub fn take_text(&self) -> String {
+        self.text
+            .lock()
+            .map(|mut text| std::mem::take(&mut *text))
+            .unwrap_or_default()
+    }
+}
+
+fn decode<T>(
+    device: &Device,
+    config: &StreamConfig,
+    mut decoder: Decoder,
+    text: Arc<Mutex<String>>,
+) -> Result<Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels as usize;
+    let mut mono = Vec::new();
+    Ok(device.build_input_stream(
+        config,
+        move |data: &[T], _| {
+            // the rig audio is the same on all channels, decode the first
+            mono.clear();
+            mono.extend(data.iter().step_by(channels).map(|&s| f32::from_sample(s)));
+            let decoded = decoder.feed(&mono);
+            if !decoded.is_empty()
+                && let Ok(mut text) = text.lock()
+            {
+                text.push_str(&decoded);
+            }
+        },
+        |e| warn!("Audio input failed: {}", e),
+        None,
+    )?)
+}
+
+/// A WAV file being played, stops when dropped
+pub struct Playback {
+    _stream: Stream,
+    finished: Arc<AtomicBool>,
+}
+
+impl Playback {
+    /// Whether the whole file was played
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+}
+
+/// Plays a WAV file on the named sound card, or the default one when `device` is empty
+pub fn play(path: &Path, device: &str) -> Result<Playback> {
+    let mut reader = WavReader::open(path)?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<f32>, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<Vec<f32>, _>>()?
+        }
+    };
+    let config = StreamConfig {
+        channels: spec.channels,
+        sample_rate: SampleRate(spec.sample_rate),
+        buffer_size: cpal::BufferSize::Default,
+    };
+    let finished = Arc::new(AtomicBool::new(false));
+    let done = finished.clone();
+    let mut pos = 0;
+    let stream = find_device(device, false)?.build_output_stream(
+        &config,
+        move |data: &mut [f32], _| {
+            for out in data.iter_mut() {
+                *out = samples.get(pos).copied().unwrap_or(Sample::EQUILIBRIUM);
+                pos += 1;
+            }
+            if pos >= samples.len() {
+                done.store(true, Ordering::Relaxed);
+            }
+        },
+        |e| warn!("Audio output failed: {}", e),
+        None,
+    )?;
+    stream.play()?;
+    Ok(Playback {
+        _stream: stream,
+        finished,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::clip_path;
+
+    #[test]
+    pub fn test_clip_path() {
+        let time = "2025-07-28T02:48:13.12Z".parse().unwrap();
+        assert_eq!(
+            Path::new("recordings/20250728-024813-120.wav"),
+            clip_path(Path::new("recordings"), time)
+        );
+    }
+}
@@ -0,0 +1,472 @@
+use std::{
+    io,
+    sync::{Mutex, MutexGuard},
+};
+
+use anyhow::{Result, anyhow, bail};
+#[cfg(feature = "hamlib")]
+use hamlib::types::{Level, PTT, VFO};
+use util::rigctld;
+
+use crate::rig::{self, Meters};
+
+/// Hamlib model of rigctld, which is reached over the network when hamlib isn't loaded
+pub const MODEL_RIGCTLD: u32 = 2;
+/// Hamlib model of its dummy rig, played by `MockRig` when hamlib isn't loaded
+pub const MODEL_DUMMY: u32 = 1;
+
+/// A rig the UI reads and controls, through hamlib, rigctld or a mock for tests
+pub trait RigBackend {
+    /// Frequency of the current VFO in Hz
+    fn freq(&self) -> Result<f64>;
+    fn set_freq(&self, hz: f64) -> Result<()>;
+    /// Hamlib mode and passband width in Hz
+    fn mode(&self) -> Result<(u64, i64)>;
+    /// Sets the hamlib mode, a width of 0 keeps the rig's normal passband for it
+    fn set_mode(&self, mode: u64, width: i64) -> Result<()>;
+    fn set_ptt(&self, on: bool) -> Result<()>;
+    /// Output power set on the rig in watts, for the frequency and mode operated
+    fn power(&self, freq: f64, mode: u64) -> Result<f64>;
+    /// Meters the rig can't read are None
+    fn meters(&self) -> Result<Meters>;
+    /// The transmit frequency in Hz when working split, None when simplex
+    fn split_freq(&self) -> Result<Option<f64>>;
+    /// Transmits on `tx_freq` from the other VFO, or goes back to simplex with None
+    fn set_split(&self, tx_freq: Option<f64>) -> Result<()>;
+    fn send_morse(&self, text: &str) -> Result<()>;
+    fn close(&mut self) -> Result<()>;
+}
+
+/// Hamlib, once loaded. Without the `hamlib` feature there is none to load and rigs are
+/// reached through rigctld.
+#[cfg(feature = "hamlib")]
+pub type Hamlib = std::sync::Arc<hamlib::lock::Hamlib>;
+#[cfg(not(feature = "hamlib"))]
+pub enum Hamlib {}
+
+/// Loads hamlib and its rig backends
+#[cfg(feature = "hamlib")]
+pub fn init_hamlib() -> Result<Hamlib> {
+    use hamlib::lock;
+
+    let lib = lock::Hamlib::new().map_err(|e| anyhow!("Could not load hamlib: {}", e))?;
+    unsafe { lock::Hamlib::init_hamlib() };
+    lock::set_log_level(&lib, hamlib::LogLevel::Trace);
+    lock::set_log_timestamps(&lib, true);
+    lock::load_rig_backends(&lib)
+        .map_err(|e| anyhow!("Could not load the hamlib rig backends: {}", e))?;
+    //params::init_params(lib);
+    Ok(std::sync::Arc::new(lib))
+}
+
+#[cfg(not(feature = "hamlib"))]
+pub fn init_hamlib() -> Result<Hamlib> {
+    bail!("veelog was built without hamlib, open rigs through rigctld (model 2) instead")
+}
+
+/// `res` as an Option, None if the rig could not tell. A connection that broke on the
+/// way is still an error, so the reading fails instead of showing what it last read.
+pub fn optional<T>(res: Result<T>) -> Result<Option<T>> {
+    match res {
+        Ok(v) => Ok(Some(v)),
+        Err(e) if e.downcast_ref::<io::Error>().is_some() => Err(e),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Opens rig `model` on the serial port or rigctld address `path`. With hamlib loaded
+/// it opens every rig, without it rigctld and the dummy rig can be opened.
+pub fn open(lib: Option<&Hamlib>, model: u32, path: &str) -> Result<Box<dyn RigBackend>> {
+    if path.is_empty() && model != MODEL_DUMMY {
+        bail!("Set rig_path to the rig's serial port or rigctld address first");
+    }
+    match (lib, model) {
+        #[cfg(feature = "hamlib")]
+        (Some(lib), _) => Ok(Box::new(HamlibRig::open(lib.clone(), model, path)?)),
+        (_, MODEL_RIGCTLD) => Ok(Box::new(Rigctld::connect(path)?)),
+        (_, MODEL_DUMMY) => Ok(Box::new(MockRig::new(14_074_000.0, "FT8"))),
+        _ => bail!("Init hamlib before opening rig model {}", model),
+    }
+}
+
+/// A rig opened with hamlib
+#[cfg(feature = "hamlib")]
+pub struct HamlibRig {
+    lib: Hamlib,
+    rig: hamlib::rig::Rig,
+}
+
+#[cfg(feature = "hamlib")]
+impl HamlibRig {
+    fn open(lib: Hamlib, model: u32, path: &str) -> Result<Self> {
+        let mut rig = hamlib::rig::Rig::new(&lib, model)?;
+        let path = std::ffi::CString::new(path)?;
+        rig.set_conf(&lib, hamlib::token::TOK_PATHNAME, &path)?;
+        rig.open(&lib)?;
+        Ok(Self { lib, rig })
+    }
+}
+
+#[cfg(feature = "hamlib")]
+impl RigBackend for HamlibRig {
+    fn freq(&self) -> Result<f64> {
+        self.rig.get_freq(&self.lib, VFO::RIG_VFO_CURR)
+    }
+
+    fn set_freq(&self, hz: f64) -> Result<()> {
+        self.rig.set_freq(&self.lib, VFO::RIG_VFO_CURR, hz)
+    }
+
+    fn mode(&self) -> Result<(u64, i64)> {
+        self.rig.get_mode(&self.lib, VFO::RIG_VFO_CURR)
+    }
+
+    fn set_mode(&self, mode: u64, width: i64) -> Result<()> {
+        self.rig.set_mode(&self.lib, VFO::RIG_VFO_CURR, mode, width)
+    }
+
+    fn set_ptt(&self, on: bool) -> Result<()> {
+        let ptt = match on {
+            true => PTT::RIG_PTT_ON,
+            false => PTT::RIG_PTT_OFF,
+        };
+        self.rig.set_ptt(&self.lib, VFO::RIG_VFO_CURR, ptt)
+    }
+
+    fn power(&self, freq: f64, mode: u64) -> Result<f64> {
+        let level = self
+            .rig
+            .get_level(&self.lib, VFO::RIG_VFO_CURR, Level::RIG_LEVEL_RFPOWER)?;
+        let mw = self.rig.power2mw(&self.lib, level, freq, mode)?;
+        Ok(mw as f64 / 1000.0)
+    }
+
+    fn meters(&self) -> Result<Meters> {
+        let level = |level| self.rig.get_level(&self.lib, VFO::RIG_VFO_CURR, level).ok();
+        Ok(Meters {
+            strength: level(Level::RIG_LEVEL_STRENGTH),
+            swr: level(Level::RIG_LEVEL_SWR),
+            alc: level(Level::RIG_LEVEL_ALC),
+        })
+    }
+
+    fn split_freq(&self) -> Result<Option<f64>> {
+        // rigs that can't tell are taken to be simplex
+        let split = self
+            .rig
+            .get_split_vfo(&self.lib, VFO::RIG_VFO_CURR)
+            .is_ok_and(|(split, _)| split);
+        match split {
+            true => Ok(Some(self.rig.get_split_freq(&self.lib, VFO::RIG_VFO_CURR)?)),
+            false => Ok(None),
+        }
+    }
+
+    fn set_split(&self, tx_freq: Option<f64>) -> Result<()> {
+        match tx_freq {
+            Some(tx) => {
+                self.rig.set_split_freq(&self.lib, VFO::RIG_VFO_CURR, tx)?;
+                self.rig
+                    .set_split_vfo(&self.lib, VFO::RIG_VFO_CURR, true, VFO::RIG_VFO_TX)
+            }
+            None => self
+                .rig
+                .set_split_vfo(&self.lib, VFO::RIG_VFO_CURR, false, VFO::RIG_VFO_CURR),
+        }
+    }
+
+    fn send_morse(&self, text: &str) -> Result<()> {
+        let text = std::ffi::CString::new(text)?;
+        self.rig.send_morse(&self.lib, VFO::RIG_VFO_CURR, &text)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.rig.close(&self.lib)
+    }
+}
+
+/// A rig reached through rigctld, without linking hamlib
+pub struct Rigctld {
+    conn: Mutex<rigctld::Rigctld>,
+}
+
+impl Rigctld {
+    /// Connects to rigctld at host:port, e.g. `localhost:4532`
+    pub fn connect(addr: &str) -> Result<Self> {
+        Ok(Self {
+            conn: Mutex::new(rigctld::Rigctld::connect(addr)?),
+        })
+    }
+
+    fn conn(&self) -> Result<MutexGuard<'_, rigctld::Rigctld>> {
+        self.conn
+            .lock()
+            .map_err(|_| anyhow!("The rigctld connection broke"))
+    }
+
+    fn command(&self, cmd: &str, lines: usize) -> Result<Vec<String>> {
+        self.conn()?.command(cmd, lines)
+    }
+
+    fn set(&self, cmd: &str) -> Result<()> {
+        self.conn()?.set(cmd)
+    }
+
+    fn level(&self, name: &str) -> Result<f32> {
+        Ok(self.command(&format!("l {}", name), 1)?[0].parse()?)
+    }
+}
+
+impl RigBackend for Rigctld {
+    fn freq(&self) -> Result<f64> {
+        self.conn()?.freq()
+    }
+
+    fn set_freq(&self, hz: f64) -> Result<()> {
+        self.set(&format!("F {:.0}", hz))
+    }
+
+    fn mode(&self) -> Result<(u64, i64)> {
+        let (name, width) = self.conn()?.mode()?;
+        Ok((rig::mode_from_name(&name), width))
+    }
+
+    fn set_mode(&self, mode: u64, width: i64) -> Result<()> {
+        self.set(&format!("M {} {}", rig::mode_name(mode), width))
+    }
+
+    fn set_ptt(&self, on: bool) -> Result<()> {
+        self.set(&format!("T {}", on as u8))
+    }
+
+    fn power(&self, freq: f64, mode: u64) -> Result<f64> {
+        let level = self.level("RFPOWER")?;
+        let cmd = format!("2 {} {:.0} {}", level, freq, rig::mode_name(mode));
+        let mw: f64 = self.command(&cmd, 1)?[0].parse()?;
+        Ok(mw / 1000.0)
+    }
+
+    fn meters(&self) -> Result<Meters> {
+        Ok(Meters {
+            strength: optional(self.level("STRENGTH"))?,
+            swr: optional(self.level("SWR"))?,
+            alc: optional(self.level("ALC"))?,
+        })
+    }
+
+    fn split_freq(&self) -> Result<Option<f64>> {
+        // the split state is followed by the transmit VFO
+        match self.command("s", 2)?[0].as_str() {
+            "1" => Ok(Some(self.command("i", 1)?[0].parse()?)),
+            _ => Ok(None),
+        }
+    }
+
+    fn set_split(&self, tx_freq: Option<f64>) -> Result<()> {
+        match tx_freq {
+            Some(tx) => {
+                self.set(&format!("I {:.0}", tx))?;
+                self.set("S 1 TX")
+            }
+            None => self.set("S 0 currVFO"),
+        }
+    }
+
+    fn send_morse(&self, text: &str) -> Result<()> {
+        self.set(&format!("b {}", text))
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.conn
+            .get_mut()
+            .map_err(|_| anyhow!("The rigctld connection broke"))?
+            .quit()
+    }
+}
+
+/// What `MockRig` is set to
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockState {
+    pub freq: f64,
+    pub mode: u64,
+    pub ptt: bool,
+    pub tx_freq: Option<f64>,
+    /// CW sent, one message after another
+    pub sent: Vec<String>,
+}
+
+/// A rig kept in memory, for tests and trying the UI without a radio. Its output power is
+/// 100W, and its meters read S9 with a good match.
+pub struct MockRig {
+    state: Mutex<MockState>,
+}
+
+impl MockRig {
+    /// A rig on `freq` Hz in ADIF `mode`
+    pub fn new(freq: f64, mode: &str) -> Self {
+        Self {
+            state: Mutex::new(MockState {
+                freq,
+                mode: rig::rig_mode(mode, freq),
+                ptt: false,
+                tx_freq: None,
+                sent: Vec::new(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        // a test that panicked while holding it left nothing half changed
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl RigBackend for MockRig {
+    fn freq(&self) -> Result<f64> {
+        Ok(self.lock().freq)
+    }
+
+    fn set_freq(&self, hz: f64) -> Result<()> {
+        self.lock().freq = hz;
+        Ok(())
+    }
+
+    fn mode(&self) -> Result<(u64, i64)> {
+        Ok((self.lock().mode, 2400))
+    }
+
+    fn set_mode(&self, mode: u64, _width: i64) -> Result<()> {
+        self.lock().mode = mode;
+        Ok(())
+    }
+
+    fn set_ptt(&self, on: bool) -> Result<()> {
+        self.lock().ptt = on;
+        Ok(())
+    }
+
+    fn power(&self, _freq: f64, _mode: u64) -> Result<f64> {
+        Ok(100.0)
+    }
+
+    fn meters(&self) -> Result<Meters> {
+        Ok(Meters {
+            strength: Some(0.0),
+            swr: Some(1.1),
+            alc: Some(0.0),
+        })
+    }
+
+    fn split_freq(&self) -> Result<Option<f64>> {
+        Ok(self.lock().tx_freq)
+    }
+
+    fn set_split(&self, tx_freq: Option<f64>) -> Result<()> {
+        self.lock().tx_freq = tx_freq;
+        Ok(())
+    }
+
+    fn send_morse(&self, text: &str) -> Result<()> {
+        self.lock().sent.push(text.to_string());
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::{MODEL_DUMMY, MODEL_RIGCTLD, MockRig, RigBackend, Rigctld, open};
+    use crate::rig::{self, Meters};
+
+    #[test]
+    pub fn test_rigctld() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut commands = Vec::new();
+            for answer in [
+                "14074000\n",
+                "PKTUSB\n3000\n",
+                "RPRT 0\n",
+                "RPRT 0\n",
+                "RPRT 0\n",
+                "1\nVFOB\n",
+                "14076000\n",
+                "RPRT -11\n",
+                "-12\n",
+                "RPRT -11\n",
+                "0.1\n",
+            ] {
+                let mut cmd = String::new();
+                reader.read_line(&mut cmd).unwrap();
+                commands.push(cmd.trim_end().to_string());
+                stream.write_all(answer.as_bytes()).unwrap();
+            }
+            commands
+        });
+        let rig = Rigctld::connect(&addr).unwrap();
+        assert_eq!(14_074_000.0, rig.freq().unwrap());
+        assert_eq!((rig::rig_mode("FT8", 14e6), 3000), rig.mode().unwrap());
+        rig.set_freq(7_030_000.0).unwrap();
+        rig.set_mode(rig::rig_mode("CW", 7e6), 0).unwrap();
+        rig.set_ptt(true).unwrap();
+        assert_eq!(Some(14_076_000.0), rig.split_freq().unwrap());
+        assert!(rig.send_morse("CQ").is_err());
+        // a meter the rig doesn't have reads as None
+        let meters = Meters {
+            strength: Some(-12.0),
+            swr: None,
+            alc: Some(0.1),
+        };
+        assert_eq!(meters, rig.meters().unwrap());
+        let commands = server.join().unwrap();
+        // a connection that broke is an error, not a rig without meters
+        assert!(rig.meters().is_err());
+        assert_eq!(
+            [
+                "f",
+                "m",
+                "F 7030000",
+                "M CW 0",
+                "T 1",
+                "s",
+                "i",
+                "b CQ",
+                "l STRENGTH",
+                "l SWR",
+                "l ALC"
+            ]
+            .to_vec(),
+            commands
+        );
+    }
+
+    #[test]
+    pub fn test_mock_rig() {
+        let rig = open(None, MODEL_DUMMY, "").unwrap();
+        assert_eq!(14_074_000.0, rig.freq().unwrap());
+        assert!(open(None, 3073, "/dev/ttyUSB0").is_err());
+        assert!(open(None, MODEL_RIGCTLD, "").is_err());
+
+        let rig = MockRig::new(7_030_000.0, "CW");
+        rig.set_split(Some(7_031_000.0)).unwrap();
+        rig.set_ptt(true).unwrap();
+        rig.send_morse("TEST").unwrap();
+        let state = rig.lock().clone();
+        assert_eq!(Some(7_031_000.0), state.tx_freq);
+        assert!(state.ptt);
+        assert_eq!(["TEST"].to_vec(), state.sent);
+        assert_eq!(rig::rig_mode("CW", 7e6), state.mode);
+    }
+}
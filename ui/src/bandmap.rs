@@ -0,0 +1,223 @@
+use iced::{
+    Color, Point, Rectangle, Renderer, Size, Theme, mouse,
+    widget::canvas::{self, Frame, Geometry, Path, Stroke},
+};
+use jiff::{SignedDuration, Timestamp};
+
+/// Stations worked this many minutes ago are still shown
+pub const WORKED_MINUTES: i64 = 30;
+/// Most recent records searched for stations worked lately
+pub const WORKED_RECORDS: usize = 500;
+/// Width of the band map around the rig frequency, in kHz
+pub const SPAN_KHZ: f64 = 40.0;
+/// Height of a call's label, labels closer than this are pushed apart
+const LABEL_HEIGHT: f32 = 16.0;
+/// Room left of the labels for the frequency scale
+const SCALE_WIDTH: f32 = 60.0;
+/// kHz between scale ticks
+const TICK_KHZ: f64 = 5.0;
+
+/// QSOs logged at or after this are shown as worked
+pub fn worked_since() -> Timestamp {
+    Timestamp::now() - SignedDuration::from_mins(WORKED_MINUTES)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StationKind {
+    /// Spotted on the cluster
+    Spot,
+    /// Worked by us recently
+    Worked,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Station {
+    pub call: String,
+    pub freq_khz: f64,
+    pub kind: StationKind,
+}
+
+/// Vertical band map of the stations around the rig frequency, lowest frequency on top.
+/// Clicking a station publishes `on_select` with it.
+pub struct BandMap<Message> {
+    /// Frequencies at the top and bottom edge in kHz
+    pub range_khz: (f64, f64),
+    pub rig_khz: f64,
+    /// Stations sorted by frequency, those outside the range are left out
+    pub stations: Vec<Station>,
+    pub on_select: fn(&Station) -> Message,
+}
+
+/// Window of `SPAN_KHZ` centered on the rig frequency, kept within the band edges
+pub fn range_khz(rig_khz: f64, band_khz: (f64, f64)) -> (f64, f64) {
+    let span = SPAN_KHZ.min(band_khz.1 - band_khz.0);
+    let low = (rig_khz - span / 2.0).clamp(band_khz.0, band_khz.1 - span);
+    (low, low + span)
+}
+
+/// Label positions of stations sorted by frequency: each where its frequency is on a map
+/// `height` high, moved down as far as needed not to cover the one above it
+pub fn layout(freqs: &[f64], range_khz: (f64, f64), height: f32) -> Vec<f32> {
+    let scale = height as f64 / (range_khz.1 - range_khz.0);
+    let mut next = 0.0_f32;
+    freqs
+        .iter()
+        .map(|f| {
+            let y = (((f - range_khz.0) * scale) as f32).max(next);
+            next = y + LABEL_HEIGHT;
+            y
+        })
+        .collect()
+}
+
+/// The station whose label is at height `y`, given the label positions from `layout`
+pub fn label_at(labels: &[f32], y: f32) -> Option<usize> {
+    labels
+        .iter()
+        .position(|top| (top - LABEL_HEIGHT / 2.0..top + LABEL_HEIGHT / 2.0).contains(&y))
+}
+
+impl<Message> BandMap<Message> {
+    fn labels(&self, height: f32) -> Vec<f32> {
+        let freqs: Vec<f64> = self.stations.iter().map(|s| s.freq_khz).collect();
+        layout(&freqs, self.range_khz, height)
+    }
+
+    fn station_at(&self, bounds: Rectangle, cursor: mouse::Cursor) -> Option<&Station> {
+        let pos = cursor.position_in(bounds)?;
+        if pos.x < SCALE_WIDTH {
+            return None;
+        }
+        let i = label_at(&self.labels(bounds.height), pos.y)?;
+        self.stations.get(i)
+    }
+}
+
+impl<Message> canvas::Program<Message> for BandMap<Message> {
+    type State = ();
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: canvas::Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (canvas::event::Status, Option<Message>) {
+        if let canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) = event
+            && let Some(station) = self.station_at(bounds, cursor)
+        {
+            return (
+                canvas::event::Status::Captured,
+                Some((self.on_select)(station)),
+            );
+        }
+        (canvas::event::Status::Ignored, None)
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let palette = theme.extended_palette();
+        let mut frame = Frame::new(renderer, bounds.size());
+        let (low, high) = self.range_khz;
+        let height = frame.height();
+        let to_y = |khz: f64| ((khz - low) / (high - low)) as f32 * height;
+        let text = |content: String, position: Point, color: Color| canvas::Text {
+            content,
+            position,
+            color,
+            size: 12.0.into(),
+            vertical_alignment: iced::alignment::Vertical::Center,
+            ..Default::default()
+        };
+
+        frame.fill_rectangle(
+            Point::ORIGIN,
+            Size::new(SCALE_WIDTH, height),
+            palette.background.weak.color,
+        );
+        let ticks = Stroke::default()
+            .with_color(palette.background.strong.color)
+            .with_width(1.0);
+        let mut tick = (low / TICK_KHZ).ceil() * TICK_KHZ;
+        while tick <= high {
+            let y = to_y(tick);
+            frame.stroke(
+                &Path::line(Point::new(SCALE_WIDTH - 8.0, y), Point::new(SCALE_WIDTH, y)),
+                ticks,
+            );
+            frame.fill_text(text(
+                format!("{:.0}", tick),
+                Point::new(4.0, y),
+                palette.background.base.text,
+            ));
+            tick += TICK_KHZ;
+        }
+
+        for (station, label) in self.stations.iter().zip(self.labels(height)) {
+            let color = match station.kind {
+                StationKind::Spot => palette.primary.strong.color,
+                StationKind::Worked => palette.background.strong.color,
+            };
+            let y = to_y(station.freq_khz);
+            // a leader from the frequency to a label pushed down by its neighbours
+            frame.stroke(
+                &Path::line(
+                    Point::new(SCALE_WIDTH, y),
+                    Point::new(SCALE_WIDTH + 10.0, label),
+                ),
+                Stroke::default().with_color(color).with_width(1.0),
+            );
+            frame.fill_text(text(
+                station.call.clone(),
+                Point::new(SCALE_WIDTH + 14.0, label),
+                color,
+            ));
+        }
+
+        let rig = to_y(self.rig_khz);
+        frame.stroke(
+            &Path::line(Point::new(0.0, rig), Point::new(frame.width(), rig)),
+            Stroke::default()
+                .with_color(palette.danger.strong.color)
+                .with_width(2.0),
+        );
+        vec![frame.into_geometry()]
+    }
+
+    fn mouse_interaction(
+        &self,
+        _state: &Self::State,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> mouse::Interaction {
+        match self.station_at(bounds, cursor) {
+            Some(_) => mouse::Interaction::Pointer,
+            None => mouse::Interaction::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{label_at, layout, range_khz};
+
+    #[test]
+    pub fn test_layout() {
+        assert_eq!((14010.0, 14050.0), range_khz(14030.0, (14000.0, 14350.0)));
+        assert_eq!((14000.0, 14040.0), range_khz(14001.0, (14000.0, 14350.0)));
+        assert_eq!((1810.0, 1850.0), range_khz(1849.0, (1810.0, 1850.0)));
+
+        // 10 pixels per kHz, the second label is pushed below the first
+        let labels = layout(&[14012.0, 14012.5, 14030.0], (14010.0, 14050.0), 400.0);
+        assert_eq!(vec![20.0, 36.0, 200.0], labels);
+        assert_eq!(Some(1), label_at(&labels, 40.0));
+        assert_eq!(Some(0), label_at(&labels, 14.0));
+        assert_eq!(None, label_at(&labels, 100.0));
+    }
+}
@@ -0,0 +1,78 @@
+use std::net::{SocketAddr, ToSocketAddrs};
+
+use anyhow::{Result, anyhow};
+use db::{data::LogRecord, settings::BroadcastFormat};
+use tokio::net::UdpSocket;
+
+use crate::n1mm;
+
+/// Builds the datagram announcing a logged QSO
+pub fn payload(
+    record: &LogRecord,
+    format: BroadcastFormat,
+    my_call: &str,
+    contest: &str,
+) -> Result<String> {
+    Ok(match format {
+        BroadcastFormat::N1mm => n1mm::contact_xml(record, my_call, contest),
+        BroadcastFormat::Adif => record.to_adif()?.serialize()?,
+    })
+}
+
+/// Looks up the host:port destinations once, when the settings are loaded, so logging a
+/// QSO does not wait for DNS
+pub fn resolve(destinations: &[String]) -> Result<Vec<SocketAddr>> {
+    destinations
+        .iter()
+        .map(|dest| {
+            dest.to_socket_addrs()?
+                .next()
+                .ok_or_else(|| anyhow!("{} has no address", dest))
+        })
+        .collect()
+}
+
+/// Sends `payload` to every destination. Run it as a task, off the UI thread.
+pub async fn send(destinations: Vec<SocketAddr>, payload: String) -> Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.set_broadcast(true)?;
+    for dest in destinations {
+        socket.send_to(payload.as_bytes(), dest).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::UdpSocket;
+
+    use db::{
+        data::{FieldType, LogRecord},
+        settings::BroadcastFormat,
+    };
+
+    use super::{payload, resolve, send};
+
+    #[test]
+    pub fn test_send_adif() {
+        let mut record = LogRecord::new();
+        record
+            .insert_field(FieldType::WorkedCall, "W1AW")
+            .insert_field(FieldType::Frequency, "14.074");
+        let adif = payload(&record, BroadcastFormat::Adif, "N0CALL", "").unwrap();
+        assert!(adif.contains("<CALL:4>W1AW"));
+
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let destinations = resolve(&[format!("127.0.0.1:{}", port)]).unwrap();
+        assert!(resolve(&["localhost".to_string()]).is_err());
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        rt.block_on(send(destinations, adif.clone())).unwrap();
+        let mut buf = [0; 1024];
+        let len = listener.recv(&mut buf).unwrap();
+        assert_eq!(adif.as_bytes(), &buf[..len]);
+    }
+}
@@ -0,0 +1,165 @@
+use adif::data::ADIFType;
+use anyhow::{Result, bail};
+use db::{
+    clublog::ClublogChange,
+    data::{FieldType, LogRecord},
+    uploads::UploadError,
+};
+use util::band::Band;
+
+const REALTIME_URL: &str = "https://clublog.org/realtime.php";
+const DELETE_URL: &str = "https://clublog.org/delete.php";
+
+/// Club Log login and API key, QSOs are uploaded to the log of `callsign`
+#[derive(Debug, Clone)]
+pub struct Account {
+    pub email: String,
+    pub password: String,
+    pub callsign: String,
+    pub api_key: String,
+}
+
+/// Sends a change of a record to Club Log's real-time API. This blocks on the network,
+/// so run it off the UI thread.
+pub fn send(account: &Account, change: &ClublogChange) -> Result<()> {
+    match change {
+        ClublogChange::Upload { record, replaces } => {
+            upload(account, record)?;
+            // Club Log replaces a QSO with the same call, time and band, an edit of those
+            // leaves the old one behind. Uploading again is harmless if this fails.
+            if let Some(old) = replaces
+                && delete_form(old)? != delete_form(record)?
+            {
+                delete(account, old)?;
+            }
+            Ok(())
+        }
+        ClublogChange::Delete(record) => delete(account, record),
+    }
+}
+
+fn upload(account: &Account, record: &LogRecord) -> Result<()> {
+    let mut adif = record.to_adif()?;
+    // Club Log requires BAND, derive it from the frequency if the record has none
+    if !adif.0.iter().any(|(name, _)| name == "BAND")
+        && let Some(band) = record.band()
+    {
+        adif.0.push(("BAND".to_string(), ADIFType::Str(band)));
+    }
+    post(
+        account,
+        REALTIME_URL,
+        &[
+            ("callsign", account.callsign.as_str()),
+            ("adif", &adif.serialize()?),
+        ],
+    )
+}
+
+fn delete(account: &Account, record: &LogRecord) -> Result<()> {
+    let (call, datetime, band) = delete_form(record)?;
+    post(
+        account,
+        DELETE_URL,
+        &[
+            ("callsign", account.callsign.as_str()),
+            ("dxcall", &call),
+            ("datetime", &datetime),
+            ("bandid", &band),
+        ],
+    )
+}
+
+fn post(account: &Account, url: &str, form: &[(&str, &str)]) -> Result<()> {
+    let mut form = form.to_vec();
+    form.extend([
+        ("email", account.email.as_str()),
+        ("password", account.password.as_str()),
+        ("api", account.api_key.as_str()),
+    ]);
+    let mut response = ureq::post(url)
+        .config()
+        .http_status_as_error(false)
+        .build()
+        .send_form(form)?;
+    let status = response.status().as_u16();
+    check_response(status, &response.body_mut().read_to_string()?)
+}
+
+/// Club Log answers with a status code and a line of text, e.g. "OK", "Dupe" or the
+/// reason a QSO was rejected. 400 refuses the QSO, other errors are tried again.
+fn check_response(status: u16, body: &str) -> Result<()> {
+    match status {
+        200..=299 => Ok(()),
+        400 => Err(UploadError::Rejected(format!(
+            "Club Log rejected the QSO: {}",
+            body.trim()
+        )))?,
+        403 => bail!("Club Log rejected the login: {}", body.trim()),
+        _ => bail!("Club Log failed ({}): {}", status, body.trim()),
+    }
+}
+
+/// The call, time and band Club Log identifies a QSO by, as delete.php takes them.
+/// Bands are numbered in meters or centimeters, without the unit.
+fn delete_form(record: &LogRecord) -> Result<(String, String, String)> {
+    let Some(call) = record.get_field(&FieldType::WorkedCall) else {
+        bail!("The QSO has no call");
+    };
+    let Some(time) = record
+        .get_field(&FieldType::Timestamp)
+        .and_then(|t| t.parse::<jiff::Timestamp>().ok())
+    else {
+        bail!("The QSO with {} has no time", call);
+    };
+    let Some(band) = record.band().and_then(|b| Band::from_name(&b)) else {
+        bail!("The QSO with {} has no band", call);
+    };
+    let band = band.name().trim_end_matches('m').trim_end_matches('c');
+    Ok((
+        call,
+        time.strftime("%Y-%m-%d %H:%M:%S").to_string(),
+        band.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use db::{
+        data::{FieldType, LogRecord},
+        uploads::UploadError,
+    };
+
+    use super::{check_response, delete_form};
+
+    #[test]
+    pub fn test_delete_form() {
+        let mut record = LogRecord::new();
+        record
+            .insert_timestamp("2025-07-28T02:48:05Z".parse().unwrap())
+            .insert_field(FieldType::WorkedCall, "JA1XYZ")
+            .insert_field(FieldType::Frequency, "432.100");
+        assert_eq!(
+            (
+                "JA1XYZ".to_string(),
+                "2025-07-28 02:48:05".to_string(),
+                "70".to_string()
+            ),
+            delete_form(&record).unwrap()
+        );
+        record.insert_field(FieldType::Frequency, "");
+        assert!(delete_form(&record).is_err());
+
+        assert!(check_response(200, "Dupe").is_ok());
+        let error = |status, body| UploadError::from(check_response(status, body).unwrap_err());
+        assert!(matches!(
+            error(403, "Invalid password"),
+            UploadError::Failed(_)
+        ));
+        assert!(matches!(
+            error(400, "Rejected: QSO date in the future"),
+            UploadError::Rejected(_)
+        ));
+        assert!(matches!(error(500, "Try later"), UploadError::Failed(_)));
+    }
+}
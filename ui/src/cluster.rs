@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use iced::{
+    Subscription,
+    futures::{SinkExt, channel::mpsc::Sender},
+};
+use log::warn;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+use util::mode::ModeClass;
+
+/// Maximum number of spots kept in memory, oldest are dropped first
+pub const MAX_SPOTS: usize = 200;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spot {
+    pub call: String,
+    /// Spotted frequency in kHz, as sent by the cluster
+    pub freq_khz: f64,
+    pub spotter: String,
+    pub comment: String,
+    /// Time of the spot as sent by the cluster, e.g. "1234Z"
+    pub time: String,
+}
+
+impl Spot {
+    /// Guesses the mode class from the spot comment, e.g. "CW 599 up 1" or "FT8 -12 dB"
+    pub fn mode_class(&self) -> Option<ModeClass> {
+        self.comment
+            .split_whitespace()
+            .find_map(|word| match word.to_ascii_uppercase().as_str() {
+                "CW" => Some(ModeClass::Cw),
+                "SSB" | "USB" | "LSB" | "AM" | "FM" => Some(ModeClass::Phone),
+                "FT8" | "FT4" | "RTTY" | "PSK" | "PSK31" | "JS8" | "JT65" | "MSK144" | "Q65"
+                | "DIGI" => Some(ModeClass::Digital),
+                _ => None,
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Connected,
+    Disconnected(String),
+    Spot(Spot),
+}
+
+/// Parses a standard DX cluster spot line:
+/// `DX de W3LPL:     14025.0  JA1ABC       CW 599 up 1                 1234Z`
+pub fn parse_spot(line: &str) -> Option<Spot> {
+    let rest = line.trim().strip_prefix("DX de ")?;
+    let (spotter, rest) = rest.split_once(':')?;
+    let mut tokens = rest.split_whitespace();
+    let freq_khz = tokens.next()?.parse::<f64>().ok()?;
+    let call = tokens.next()?.to_ascii_uppercase();
+    let mut tokens: Vec<&str> = tokens.collect();
+
+    // the time is the last token, optionally followed by the spotter's locator
+    let time_pos = tokens.iter().rposition(|t| is_spot_time(t))?;
+    let time = tokens[time_pos].to_string();
+    tokens.truncate(time_pos);
+
+    Some(Spot {
+        call,
+        freq_khz,
+        spotter: spotter.trim().to_ascii_uppercase(),
+        comment: tokens.join(" "),
+        time,
+    })
+}
+
+fn is_spot_time(token: &str) -> bool {
+    token.len() == 5 && token.ends_with('Z') && token[..4].chars().all(|c| c.is_ascii_digit())
+}
+
+/// Connects to the cluster `node` (host:port), logs in with `call` and emits parsed spots.
+/// Reconnects after a delay when the connection drops.
+pub fn connect(node: String, call: String) -> Subscription<Event> {
+    Subscription::run_with_id(
+        ("cluster", node.clone(), call.clone()),
+        iced::stream::channel(100, move |mut output| async move {
+            loop {
+                if let Err(e) = run(&node, &call, &mut output).await {
+                    warn!("Cluster connection to {} lost: {}", node, e);
+                    let _ = output.send(Event::Disconnected(e.to_string())).await;
+                }
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            }
+        }),
+    )
+}
+
+async fn run(node: &str, call: &str, output: &mut Sender<Event>) -> anyhow::Result<()> {
+    let stream = TcpStream::connect(node).await?;
+    let (reader, mut writer) = stream.into_split();
+    // every common cluster implementation asks for a call first, answer right away
+    writer.write_all(format!("{}\r\n", call).as_bytes()).await?;
+    output.send(Event::Connected).await?;
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if let Some(spot) = parse_spot(&line) {
+            output.send(Event::Spot(spot)).await?;
+        }
+    }
+    anyhow::bail!("connection closed by cluster")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_spot;
+
+    #[test]
+    pub fn test_parse_spot() {
+        let spot =
+            parse_spot("DX de W3LPL:     14025.0  JA1ABC       CW 599 up 1                 1234Z")
+                .unwrap();
+        assert_eq!("W3LPL", spot.spotter);
+        assert_eq!(14025.0, spot.freq_khz);
+        assert_eq!("JA1ABC", spot.call);
+        assert_eq!("CW 599 up 1", spot.comment);
+        assert_eq!("1234Z", spot.time);
+
+        let spot =
+            parse_spot("DX de K1TTT-#:   7074.0  VK2XYZ       FT8 -12 dB              0102Z FN32")
+                .unwrap();
+        assert_eq!("K1TTT-#", spot.spotter);
+        assert_eq!("FT8 -12 dB", spot.comment);
+        assert_eq!("0102Z", spot.time);
+
+        assert!(parse_spot("W3LPL de VE7CC 16-Oct-2026 1234Z dxspider >").is_none());
+    }
+}
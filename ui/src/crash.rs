@@ -0,0 +1,169 @@
+use std::{
+    backtrace::Backtrace,
+    fmt::Write,
+    fs, panic,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Result;
+use db::{data::Log, paths::Paths, verify::VerifyReport};
+use log::error;
+
+/// Report the panic hook leaves in the data directory, telling the next start of a crash
+const CRASH_FILE: &str = "crash.txt";
+/// Most of an app log put in a diagnostic bundle, the end of it where the crash is
+const MAX_LOG_BYTES: usize = 256 * 1024;
+
+/// Log the panic hook writes to disk. Holding it keeps it open, so it is let go while the
+/// log is compacted.
+static OPEN_LOG: Mutex<Option<Log>> = Mutex::new(None);
+
+/// The app log, warnings and errors of this run. Opening it empties it.
+pub fn app_log(paths: &Paths) -> PathBuf {
+    paths.data_file(&format!("{}.log", env!("CARGO_PKG_NAME")))
+}
+
+/// Copy of the app log of the last run that crashed
+fn crashed_log(paths: &Paths) -> PathBuf {
+    paths.data_file(&format!("{}.crashed.log", env!("CARGO_PKG_NAME")))
+}
+
+/// Sets the log the panic hook writes to disk
+pub fn watch_log(log: Option<Log>) {
+    if let Ok(mut open) = OPEN_LOG.lock() {
+        *open = log;
+    }
+}
+
+/// Installs a panic hook that logs the panic with its backtrace to the app log, writes the
+/// open log to disk and leaves the report for `take_report` on the next start
+pub fn install_hook(paths: &Paths) {
+    let crash_file = paths.data_file(CRASH_FILE);
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let report = format!(
+            "{} {} crashed: {}\n\n{}",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            info,
+            Backtrace::force_capture()
+        );
+        error!("{}", report);
+        // the panic may have happened while the lock was held
+        if let Ok(open) = OPEN_LOG.try_lock()
+            && let Some(log) = open.as_ref()
+            && let Err(e) = log.flush()
+        {
+            error!("Could not write the log to disk after the crash: {}", e);
+        }
+        if let Err(e) = fs::write(&crash_file, &report) {
+            error!("Could not save the crash report: {}", e);
+        }
+        default_hook(info);
+    }));
+}
+
+/// The report of a crash in the last run, if there was one. Its app log is copied aside,
+/// so this must be called before the app log is opened again.
+pub fn take_report(paths: &Paths) -> Option<String> {
+    let crash_file = paths.data_file(CRASH_FILE);
+    let report = fs::read_to_string(&crash_file).ok()?;
+    if let Err(e) = fs::copy(app_log(paths), crashed_log(paths)) {
+        eprintln!("Could not keep the app log of the crash: {}", e);
+    }
+    let _ = fs::remove_file(crash_file);
+    Some(report)
+}
+
+/// The end of the text file at `path`, at most `MAX_LOG_BYTES`
+fn tail(path: &Path) -> String {
+    let Ok(text) = fs::read(path) else {
+        return "(none)\n".to_string();
+    };
+    let start = text.len().saturating_sub(MAX_LOG_BYTES);
+    let mut tail = String::from_utf8_lossy(&text[start..]).to_string();
+    if start > 0 {
+        tail.insert_str(0, "...\n");
+    }
+    tail
+}
+
+/// A report for support requests: the program and system, the crash report, what
+/// verifying the log found and the app logs of this run and the last crash
+pub fn bundle(
+    paths: &Paths,
+    crash: Option<&str>,
+    verify: Option<Result<VerifyReport>>,
+) -> Result<String> {
+    let mut text = format!(
+        "{} {} on {} {}\nData directory: {}\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        paths.data_dir.display()
+    );
+    write!(
+        text,
+        "\n== Crash ==\n{}\n",
+        crash.unwrap_or("No crash since the program was started")
+    )?;
+    let verify = match verify {
+        Some(Ok(report)) => report.to_string(),
+        Some(Err(e)) => format!("Could not verify the log: {}", e),
+        None => "No log is open".to_string(),
+    };
+    write!(text, "\n== Log verification ==\n{}\n", verify)?;
+    write!(text, "\n== App log ==\n{}", tail(&app_log(paths)))?;
+    write!(
+        text,
+        "\n== App log of the last crash ==\n{}",
+        tail(&crashed_log(paths))
+    )?;
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use db::{paths::Paths, verify::VerifyReport};
+
+    use super::{CRASH_FILE, app_log, bundle, crashed_log, take_report};
+
+    #[test]
+    pub fn test_crash_report() {
+        let dir = env::temp_dir().join(format!("veelog-tests-crash-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let paths = Paths::new(Some(dir.clone()));
+        assert_eq!(None, take_report(&paths));
+
+        fs::write(app_log(&paths), "[ERROR] veelog crashed: oops\n").unwrap();
+        fs::write(dir.join(CRASH_FILE), "veelog crashed: oops").unwrap();
+        assert_eq!(
+            Some("veelog crashed: oops".to_string()),
+            take_report(&paths)
+        );
+        // reported once
+        assert_eq!(None, take_report(&paths));
+        assert_eq!(
+            "[ERROR] veelog crashed: oops\n",
+            fs::read_to_string(crashed_log(&paths)).unwrap()
+        );
+
+        fs::write(app_log(&paths), "").unwrap();
+        let report = VerifyReport {
+            records: 3,
+            ..VerifyReport::default()
+        };
+        let text = bundle(&paths, Some("veelog crashed: oops"), Some(Ok(report))).unwrap();
+        assert!(text.contains("== Crash ==\nveelog crashed: oops\n"));
+        assert!(text.contains("== Log verification ==\n3 records ok"));
+        assert!(text.contains("== App log of the last crash ==\n[ERROR] veelog crashed: oops\n"));
+        let text = bundle(&paths, None, None).unwrap();
+        assert!(text.contains("No crash since"));
+        assert!(text.contains("No log is open"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
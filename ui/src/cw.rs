@@ -0,0 +1,236 @@
+/// Length of the blocks the tone is detected in, in seconds
+const BLOCK_SECS: f32 = 0.005;
+/// Dot length assumed before any CW was heard, 20 WPM
+const START_DOT_SECS: f32 = 0.06;
+/// How much of the tone's peak level is lost per block, so the threshold follows fading
+const PEAK_DECAY: f32 = 0.999;
+/// How fast the noise level follows the level heard while the key is up
+const NOISE_RATE: f32 = 0.05;
+/// How far the tone must stand out of the noise to be taken for a signal
+const MIN_SNR: f32 = 3.0;
+
+const MORSE: &[(char, &str)] = &[
+    ('A', ".-"),
+    ('B', "-..."),
+    ('C', "-.-."),
+    ('D', "-.."),
+    ('E', "."),
+    ('F', "..-."),
+    ('G', "--."),
+    ('H', "...."),
+    ('I', ".."),
+    ('J', ".---"),
+    ('K', "-.-"),
+    ('L', ".-.."),
+    ('M', "--"),
+    ('N', "-."),
+    ('O', "---"),
+    ('P', ".--."),
+    ('Q', "--.-"),
+    ('R', ".-."),
+    ('S', "..."),
+    ('T', "-"),
+    ('U', "..-"),
+    ('V', "...-"),
+    ('W', ".--"),
+    ('X', "-..-"),
+    ('Y', "-.--"),
+    ('Z', "--.."),
+    ('0', "-----"),
+    ('1', ".----"),
+    ('2', "..---"),
+    ('3', "...--"),
+    ('4', "....-"),
+    ('5', "....."),
+    ('6', "-...."),
+    ('7', "--..."),
+    ('8', "---.."),
+    ('9', "----."),
+    ('/', "-..-."),
+    ('?', "..--.."),
+    ('=', "-...-"),
+    ('.', ".-.-.-"),
+    (',', "--..--"),
+];
+
+/// Power of the `freq` Hz component of `samples`
+fn goertzel(samples: &[f32], sample_rate: f32, freq: f32) -> f32 {
+    let coeff = 2.0 * (2.0 * std::f32::consts::PI * freq / sample_rate).cos();
+    let (mut s1, mut s2) = (0.0, 0.0);
+    for &x in samples {
+        let s = x + coeff * s1 - s2;
+        s2 = s1;
+        s1 = s;
+    }
+    s1 * s1 + s2 * s2 - coeff * s1 * s2
+}
+
+/// Decodes CW on one pitch from audio samples. The tone is detected block by block,
+/// the threshold follows the signal and noise levels and the speed follows the sender.
+pub struct Decoder {
+    sample_rate: f32,
+    pitch: f32,
+    block: Vec<f32>,
+    block_len: usize,
+    peak: f32,
+    noise: f32,
+    key_down: bool,
+    /// Blocks the key has been in its current state
+    blocks: usize,
+    /// Dot length in blocks, as heard lately
+    dot: f32,
+    /// Dots and dashes of the character being received
+    symbol: String,
+    /// Whether a word was received since the last word gap
+    in_word: bool,
+}
+
+impl Decoder {
+    pub fn new(sample_rate: u32, pitch: f32) -> Self {
+        let block_len = (sample_rate as f32 * BLOCK_SECS).round().max(1.0) as usize;
+        Self {
+            sample_rate: sample_rate as f32,
+            pitch,
+            block: Vec::with_capacity(block_len),
+            block_len,
+            peak: 0.0,
+            noise: f32::MAX,
+            key_down: false,
+            blocks: 0,
+            dot: START_DOT_SECS / BLOCK_SECS,
+            symbol: String::new(),
+            in_word: false,
+        }
+    }
+
+    /// Feeds mono samples, returning the characters completed by them. Word gaps come out
+    /// as a space.
+    pub fn feed(&mut self, samples: &[f32]) -> String {
+        let mut text = String::new();
+        for &sample in samples {
+            self.block.push(sample);
+            if self.block.len() == self.block_len {
+                let level = goertzel(&self.block, self.sample_rate, self.pitch).sqrt();
+                self.block.clear();
+                self.detect(level, &mut text);
+            }
+        }
+        text
+    }
+
+    fn detect(&mut self, level: f32, text: &mut String) {
+        self.peak = level.max(self.peak * PEAK_DECAY);
+        // the first block starts the noise level off
+        if self.noise == f32::MAX {
+            self.noise = level;
+        }
+        let down = level > (self.peak + self.noise) / 2.0 && self.peak > self.noise * MIN_SNR;
+        if !down {
+            self.noise += (level - self.noise) * NOISE_RATE;
+        }
+        if down == self.key_down {
+            self.blocks += 1;
+            if !down {
+                self.space(text);
+            }
+            return;
+        }
+        if self.key_down {
+            self.mark();
+        }
+        self.key_down = down;
+        self.blocks = 1;
+    }
+
+    /// A mark just ended: a dot or a dash, three dots long
+    fn mark(&mut self) {
+        let len = self.blocks as f32;
+        let dot = match len < self.dot * 2.0 {
+            true => {
+                self.symbol.push('.');
+                len
+            }
+            false => {
+                self.symbol.push('-');
+                len / 3.0
+            }
+        };
+        self.dot = (self.dot * 3.0 + dot) / 4.0;
+    }
+
+    /// The key is up: three dots end a character, seven a word
+    fn space(&mut self, text: &mut String) {
+        let len = self.blocks as f32;
+        if len >= self.dot * 2.0 && !self.symbol.is_empty() {
+            let symbol = std::mem::take(&mut self.symbol);
+            match MORSE.iter().find(|(_, s)| *s == symbol) {
+                Some((c, _)) => text.push(*c),
+                None => text.push('*'),
+            }
+            self.in_word = true;
+        } else if len >= self.dot * 5.0 && self.in_word {
+            text.push(' ');
+            self.in_word = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decoder, MORSE};
+
+    /// Keys `text` at `wpm` on a 600 Hz tone, with a word gap of silence on both sides
+    fn key(text: &str, wpm: f32, sample_rate: u32) -> Vec<f32> {
+        let dot = (1.2 / wpm * sample_rate as f32) as usize;
+        let mut samples = vec![0.0; dot * 7];
+        let mut t = 0;
+        let mut tone = |samples: &mut Vec<f32>, dots: usize, on: bool| {
+            for _ in 0..dots * dot {
+                let phase = 2.0 * std::f32::consts::PI * 600.0 * t as f32 / sample_rate as f32;
+                samples.push(if on { 0.5 * phase.sin() } else { 0.0 });
+                t += 1;
+            }
+        };
+        for c in text.chars() {
+            match MORSE.iter().find(|(m, _)| *m == c) {
+                Some((_, symbol)) => {
+                    for s in symbol.chars() {
+                        tone(&mut samples, if s == '.' { 1 } else { 3 }, true);
+                        tone(&mut samples, 1, false);
+                    }
+                    tone(&mut samples, 2, false);
+                }
+                None => tone(&mut samples, 4, false),
+            }
+        }
+        samples.extend(vec![0.0; dot * 7]);
+        samples
+    }
+
+    #[test]
+    pub fn test_decode() {
+        let mut decoder = Decoder::new(8000, 600.0);
+        let text: String = key("CQ TEST DL1ABC", 20.0, 8000)
+            .chunks(512)
+            .map(|chunk| decoder.feed(chunk))
+            .collect();
+        assert_eq!("CQ TEST DL1ABC ", text);
+
+        // the speed is picked up after a few characters
+        let mut decoder = Decoder::new(48000, 600.0);
+        let text = decoder.feed(&key("5NN 599 K1ABC/P", 32.0, 48000));
+        assert_eq!("5NN 599 K1ABC/P ", text);
+
+        // white noise from a linear congruential generator, for repeatable tests
+        let mut seed = 1u32;
+        let noisy: Vec<f32> = key("CQ TEST DL1ABC", 25.0, 8000)
+            .iter()
+            .map(|s| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                s + ((seed >> 16) as f32 / 65536.0 - 0.5) * 0.6
+            })
+            .collect();
+        let mut decoder = Decoder::new(8000, 600.0);
+        assert_eq!("CQ TEST DL1ABC ", decoder.feed(&noisy));
+    }
+}
@@ -0,0 +1,338 @@
+use std::{fs, io::ErrorKind, path::PathBuf, sync::Mutex};
+
+use anyhow::Result;
+use db::{
+    data::{FieldType, Log, LogHeader, LogRecord},
+    paths::Paths,
+    settings::Settings,
+};
+use jiff::{SignedDuration, Timestamp};
+use util::{band::Band, freq::Frequency, geo, mode::ModeClass};
+
+use crate::{
+    backend::{MockRig, RigBackend},
+    rig::{self, Meters},
+};
+
+/// Our call and grid in the demo
+pub const MY_CALL: &str = "N0CALL";
+const MY_GRID: &str = "FN31pr";
+/// QSOs in the sample log
+pub const SAMPLE_QSOS: usize = 1500;
+/// Days back the sample log reaches
+const SAMPLE_DAYS: i64 = 365;
+/// Most the simulated VFO wanders each time it is read
+const MAX_DRIFT_HZ: u64 = 40;
+/// One in this many reads of the simulated VFO jumps to another band
+const QSY_ODDS: u64 = 200;
+
+/// Where a station may be found, as (frequency in MHz, ADIF mode)
+const SPOTS: &[(f64, &str)] = &[
+    (3.530, "CW"),
+    (3.573, "FT8"),
+    (3.780, "SSB"),
+    (7.030, "CW"),
+    (7.074, "FT8"),
+    (7.180, "SSB"),
+    (10.120, "CW"),
+    (10.136, "FT8"),
+    (14.030, "CW"),
+    (14.074, "FT8"),
+    (14.250, "SSB"),
+    (18.100, "FT8"),
+    (21.030, "CW"),
+    (21.074, "FT8"),
+    (21.300, "SSB"),
+    (24.915, "FT8"),
+    (28.074, "FT8"),
+    (28.450, "SSB"),
+    (50.313, "FT8"),
+    (144.174, "FT8"),
+    (144.200, "SSB"),
+];
+
+/// Call prefixes of the sample log with the position their stations are around
+const PREFIXES: &[(&str, f64, f64)] = &[
+    ("W1", 42.4, -71.5),
+    ("K4", 35.2, -80.8),
+    ("N6", 37.4, -121.9),
+    ("W9", 41.9, -87.6),
+    ("VE3", 43.7, -79.4),
+    ("G4", 52.5, -1.9),
+    ("DL1", 50.1, 8.7),
+    ("F5", 48.9, 2.3),
+    ("I2", 45.5, 9.2),
+    ("EA4", 40.4, -3.7),
+    ("OH2", 60.2, 24.9),
+    ("SM5", 59.3, 18.1),
+    ("UA3", 55.8, 37.6),
+    ("JA1", 35.7, 139.7),
+    ("VK2", -33.9, 151.2),
+    ("ZL2", -41.3, 174.8),
+    ("PY2", -23.5, -46.6),
+    ("LU1", -34.6, -58.4),
+    ("ZS6", -26.2, 28.0),
+    ("KH6", 21.3, -157.9),
+];
+
+const NAMES: &[&str] = &[
+    "Al", "Bob", "Chen", "Dave", "Elena", "Frank", "Gita", "Hans", "Ines", "Jon", "Kenji", "Lena",
+    "Marco", "Nina", "Olle", "Pat", "Raul", "Sue", "Tom", "Yuki",
+];
+
+/// xorshift64*, random enough for made up QSOs and a wandering VFO
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // xorshift never leaves 0
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number in `0..n`
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+
+    /// A number in `-max..=max`
+    fn offset(&mut self, max: f64) -> f64 {
+        (self.below(2001) as f64 / 1000.0 - 1.0) * max
+    }
+}
+
+/// An empty data directory for the demo, so it never touches real settings and logs
+pub fn data_dir() -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("{}-demo", env!("CARGO_PKG_NAME")));
+    match fs::remove_dir_all(&dir) {
+        Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Settings of the demo station
+pub fn settings() -> Settings {
+    Settings {
+        my_call: MY_CALL.to_string(),
+        my_grid: MY_GRID.to_string(),
+        ..Settings::default()
+    }
+}
+
+/// `count` made up QSOs of the last year, the newest first, around the world on the usual
+/// HF and VHF spots. About a third of them are confirmed on LoTW.
+pub fn sample_records(count: usize, seed: u64, now: Timestamp) -> Vec<LogRecord> {
+    let mut rng = Rng::new(seed);
+    let step = SignedDuration::from_hours(SAMPLE_DAYS * 24) / count.max(1) as i32;
+    let mut time = now;
+    let mut records = Vec::with_capacity(count);
+    for _ in 0..count {
+        time -= step.mul_f64(0.5 + rng.below(100) as f64 / 100.0);
+        let (prefix, lat, lon) = *rng.pick(PREFIXES);
+        let suffix: String = (0..2 + rng.below(2))
+            .map(|_| (b'A' + rng.below(26) as u8) as char)
+            .collect();
+        let (mhz, mode) = *rng.pick(SPOTS);
+        let hz = (mhz * 1e6) as u64 + rng.below(3000);
+        let rst = match ModeClass::from_mode(mode) {
+            ModeClass::Phone => format!("5{}", 3 + rng.below(7)),
+            ModeClass::Cw => format!("5{}9", 3 + rng.below(7)),
+            ModeClass::Digital => format!("{:+03}", rng.below(35) as i64 - 24),
+        };
+        let grid = geo::gridsquare(lat + rng.offset(3.0), lon + rng.offset(5.0));
+        let lotw = match rng.below(3) {
+            0 => "Y",
+            _ => "N",
+        };
+        let mut record = LogRecord::new();
+        record
+            .insert_timestamp(time)
+            .insert_field(FieldType::WorkedCall, &format!("{}{}", prefix, suffix))
+            .insert_frequency(FieldType::Frequency, Frequency::from_hz(hz))
+            .insert_field(FieldType::Mode, mode)
+            .insert_field(FieldType::SentRST, &rst)
+            .insert_field(FieldType::RcvdRST, &rst)
+            .insert_field(FieldType::GridSquare, &grid)
+            .insert_field(FieldType::Name, rng.pick::<&str>(NAMES))
+            .insert_field(FieldType::LotwRcvd, lotw);
+        records.push(record);
+    }
+    records
+}
+
+/// Creates the sample log at the default log path, replacing any log there
+pub fn create_log(paths: &Paths, seed: u64) -> Result<(Log, String)> {
+    let path = paths.default_log();
+    if path.exists() {
+        fs::remove_dir_all(&path)?;
+    }
+    let log = Log::new_from_path(&path, LogHeader::new(MY_CALL, "veelog demo log"))?;
+    log.insert_records(sample_records(SAMPLE_QSOS, seed, Timestamp::now()))?;
+    Ok((log, path.to_string_lossy().to_string()))
+}
+
+/// A simulated rig whose VFO wanders around the band like a hand on the tuning knob and
+/// now and then jumps to another band, with a signal coming and going on the S meter
+pub struct DriftingRig {
+    rig: MockRig,
+    rng: Mutex<Rng>,
+}
+
+impl DriftingRig {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let (mhz, mode) = *rng.pick(SPOTS);
+        Self {
+            rig: MockRig::new(mhz * 1e6, mode),
+            rng: Mutex::new(rng),
+        }
+    }
+
+    fn rng(&self) -> std::sync::MutexGuard<'_, Rng> {
+        self.rng.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl RigBackend for DriftingRig {
+    fn freq(&self) -> Result<f64> {
+        let mut rng = self.rng();
+        let freq = match rng.below(QSY_ODDS) {
+            0 => {
+                let (mhz, mode) = *rng.pick(SPOTS);
+                self.rig.set_mode(rig::rig_mode(mode, mhz * 1e6), 0)?;
+                mhz * 1e6
+            }
+            _ => {
+                let freq = self.rig.freq()? + rng.offset(MAX_DRIFT_HZ as f64).round();
+                // turned up against the band edge
+                match Band::from_freq_mhz(freq / 1e6) {
+                    Some(_) => freq,
+                    None => self.rig.freq()?,
+                }
+            }
+        };
+        self.rig.set_freq(freq)?;
+        Ok(freq)
+    }
+
+    fn set_freq(&self, hz: f64) -> Result<()> {
+        self.rig.set_freq(hz)
+    }
+
+    fn mode(&self) -> Result<(u64, i64)> {
+        self.rig.mode()
+    }
+
+    fn set_mode(&self, mode: u64, width: i64) -> Result<()> {
+        self.rig.set_mode(mode, width)
+    }
+
+    fn set_ptt(&self, on: bool) -> Result<()> {
+        self.rig.set_ptt(on)
+    }
+
+    fn power(&self, freq: f64, mode: u64) -> Result<f64> {
+        self.rig.power(freq, mode)
+    }
+
+    fn meters(&self) -> Result<Meters> {
+        Ok(Meters {
+            strength: Some(self.rng().below(61) as f32 - 40.0),
+            ..self.rig.meters()?
+        })
+    }
+
+    fn split_freq(&self) -> Result<Option<f64>> {
+        self.rig.split_freq()
+    }
+
+    fn set_split(&self, tx_freq: Option<f64>) -> Result<()> {
+        self.rig.set_split(tx_freq)
+    }
+
+    fn send_morse(&self, text: &str) -> Result<()> {
+        self.rig.send_morse(text)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.rig.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use db::data::FieldType;
+    use jiff::{SignedDuration, Timestamp};
+    use util::{
+        band::Band,
+        callsign,
+        geo::{self, GridStrictness},
+    };
+
+    use super::{DriftingRig, SAMPLE_DAYS, sample_records};
+    use crate::backend::RigBackend;
+
+    #[test]
+    pub fn test_sample_records() {
+        let now = Timestamp::now();
+        let records = sample_records(200, 7, now);
+        assert_eq!(200, records.len());
+        assert_eq!(records, sample_records(200, 7, now));
+        let oldest = now - SignedDuration::from_hours(SAMPLE_DAYS * 24 * 3 / 2);
+        for record in &records {
+            let call = record.get_field(&FieldType::WorkedCall).unwrap();
+            assert!(callsign::validate_callsign(&call).is_ok(), "{}", call);
+            let grid = record.get_field(&FieldType::GridSquare).unwrap();
+            assert!(geo::prettyvalidate_gridsquare(&grid, GridStrictness::Strict).is_ok());
+            let freq: f64 = record
+                .get_field(&FieldType::Frequency)
+                .unwrap()
+                .parse()
+                .unwrap();
+            assert!(Band::from_freq_mhz(freq).is_some(), "{}", freq);
+            let time: Timestamp = record
+                .get_field(&FieldType::Timestamp)
+                .unwrap()
+                .parse()
+                .unwrap();
+            assert!(time < now && time > oldest);
+        }
+        let confirmed = records
+            .iter()
+            .filter(|r| r.get_field(&FieldType::LotwRcvd).as_deref() == Some("Y"))
+            .count();
+        assert!(confirmed > 30 && confirmed < 120, "{}", confirmed);
+    }
+
+    #[test]
+    pub fn test_drifting_rig() {
+        let rig = DriftingRig::new(3);
+        let mut bands = Vec::new();
+        for _ in 0..2000 {
+            let freq = rig.freq().unwrap();
+            let band = Band::from_freq_mhz(freq / 1e6).unwrap();
+            if !bands.contains(&band) {
+                bands.push(band);
+            }
+        }
+        // wandered off to other bands
+        assert!(bands.len() > 1);
+        rig.set_freq(7_074_000.0).unwrap();
+        let freq = rig.freq().unwrap();
+        assert!((freq - 7_074_000.0).abs() <= 40.0, "{}", freq);
+        let strength = rig.meters().unwrap().strength.unwrap();
+        assert!((-40.0..=20.0).contains(&strength));
+    }
+}
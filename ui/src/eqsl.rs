@@ -0,0 +1,111 @@
+use adif::data::{ADIFFile, ADIFHeader, ADIFType};
+use anyhow::{Result, bail};
+use db::{data::LogRecord, uploads::UploadError};
+use util::band::Band;
+
+const IMPORT_URL: &str = "https://www.eqsl.cc/qslcard/ImportADIF.cfm";
+
+/// Uploads a single QSO to eQSL.cc. This blocks on the network, so run it off the UI thread.
+pub fn upload(user: &str, password: &str, record: &LogRecord) -> Result<()> {
+    let adif = record_adif(record)?;
+    let mut response = ureq::post(IMPORT_URL).send_form([
+        ("EQSL_USER", user),
+        ("EQSL_PSWD", password),
+        ("ADIFData", adif.as_str()),
+    ])?;
+    parse_response(&response.body_mut().read_to_string()?)
+}
+
+/// Serializes a record as a one QSO ADIF file in the form eQSL expects
+fn record_adif(record: &LogRecord) -> Result<String> {
+    let mut adif = record.to_adif()?;
+    // our own upload status and application fields mean nothing to eQSL
+    adif.0
+        .retain(|(name, _)| name != "EQSL_QSL_SENT" && !name.starts_with("APP_VEELOG_"));
+    // eQSL requires BAND, derive it from the frequency if the record has none
+    if !adif.0.iter().any(|(name, _)| name == "BAND")
+        && let Some(band) = record.frequency().and_then(Band::from_freq)
+    {
+        adif.0
+            .push(("BAND".to_string(), ADIFType::Str(band.name().to_string())));
+    }
+    let header = ADIFHeader(vec![(
+        "PROGRAMID".to_string(),
+        ADIFType::Str("veelog".to_string()),
+    )]);
+    ADIFFile::new(header, vec![adif]).serialize()
+}
+
+/// eQSL answers with an HTML page containing a "Result: N out of M records added" line,
+/// preceded by "Error:" or "Warning:" lines. A duplicate counts as uploaded. Errors are
+/// about the account, a QSO not added with a warning was refused.
+fn parse_response(body: &str) -> Result<()> {
+    let lines = body.lines().map(strip_tags).collect::<Vec<String>>();
+    if let Some(error) = lines.iter().find(|l| l.starts_with("Error:")) {
+        bail!("eQSL rejected the upload: {}", error)
+    }
+    if lines.iter().any(|l| l.contains("Duplicate")) {
+        return Ok(());
+    }
+    let added = lines
+        .iter()
+        .find_map(|l| l.strip_prefix("Result:"))
+        .and_then(|r| r.split_whitespace().next())
+        .and_then(|n| n.parse::<usize>().ok());
+    match added {
+        Some(n) if n > 0 => Ok(()),
+        Some(_) => match lines.iter().find(|l| l.starts_with("Warning:")) {
+            Some(warning) => Err(UploadError::Rejected(format!(
+                "eQSL did not add the QSO: {}",
+                warning
+            )))?,
+            None => bail!("eQSL did not add the QSO"),
+        },
+        None => bail!("Unexpected response from eQSL"),
+    }
+}
+
+fn strip_tags(line: &str) -> String {
+    let mut out = String::new();
+    let mut in_tag = false;
+    for c in line.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use db::uploads::UploadError;
+
+    use super::parse_response;
+
+    #[test]
+    pub fn test_parse_response() {
+        assert!(parse_response("<BODY>\nResult: 1 out of 1 records added<BR>\n</BODY>").is_ok());
+        assert!(
+            parse_response(
+                "Warning: Y=2025 M=07 D=28 Bad record: Duplicate<BR>\nResult: 0 out of 1 records added<BR>"
+            )
+            .is_ok()
+        );
+        let error = |body| UploadError::from(parse_response(body).unwrap_err());
+        assert!(matches!(
+            error("Error: No match on eQSL_User/eQSL_Pswd<BR>"),
+            UploadError::Failed(_)
+        ));
+        assert!(matches!(
+            error(
+                "Warning: Y=2025 M=13 D=28 Bad record: invalid date<BR>\nResult: 0 out of 1 records added<BR>"
+            ),
+            UploadError::Rejected(_)
+        ));
+        assert!(parse_response("Result: 0 out of 1 records added<BR>").is_err());
+        assert!(parse_response("<HTML>maintenance</HTML>").is_err());
+    }
+}
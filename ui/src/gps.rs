@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use iced::{
+    Subscription,
+    futures::{SinkExt, channel::mpsc::Sender},
+};
+use log::warn;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+/// Wait before connecting to gpsd or opening the GPS device again
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+/// Asks gpsd to stream position reports as JSON
+const GPSD_WATCH: &[u8] = b"?WATCH={\"enable\":true,\"json\":true};\n";
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Latitude and longitude in degrees, north and east positive
+    Fix(f64, f64),
+    Lost(String),
+}
+
+/// Reads our position from gpsd at `source` given as host:port, or from the NMEA sentences
+/// of a serial GPS when `source` is a device such as `/dev/ttyACM0` or `COM3`
+pub fn watch(source: String) -> Subscription<Event> {
+    Subscription::run_with_id(
+        ("gps", source.clone()),
+        iced::stream::channel(100, move |mut output| async move {
+            loop {
+                let res = match is_device(&source) {
+                    true => read_nmea(&source, &mut output).await,
+                    false => read_gpsd(&source, &mut output).await,
+                };
+                let reason = match res {
+                    Ok(()) => "GPS closed".to_string(),
+                    Err(e) => {
+                        warn!("Could not read the position from {}: {}", source, e);
+                        e.to_string()
+                    }
+                };
+                let _ = output.send(Event::Lost(reason)).await;
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        }),
+    )
+}
+
+fn is_device(source: &str) -> bool {
+    source.starts_with('/') || source.to_ascii_uppercase().starts_with("COM")
+}
+
+async fn read_gpsd(addr: &str, output: &mut Sender<Event>) -> anyhow::Result<()> {
+    let mut stream = TcpStream::connect(addr).await?;
+    stream.write_all(GPSD_WATCH).await?;
+    let mut lines = BufReader::new(stream).lines();
+    while let Some(line) = lines.next_line().await? {
+        if let Some((lat, lon)) = parse_tpv(&line) {
+            output.send(Event::Fix(lat, lon)).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn read_nmea(path: &str, output: &mut Sender<Event>) -> anyhow::Result<()> {
+    let device = tokio::fs::File::open(path).await?;
+    let mut lines = BufReader::new(device).lines();
+    while let Some(line) = lines.next_line().await? {
+        if let Some((lat, lon)) = parse_nmea(&line) {
+            output.send(Event::Fix(lat, lon)).await?;
+        }
+    }
+    Ok(())
+}
+
+/// The position in a gpsd TPV report, None for other reports and TPVs without a fix
+pub fn parse_tpv(line: &str) -> Option<(f64, f64)> {
+    let report: serde_json::Value = serde_json::from_str(line).ok()?;
+    // mode 2 and 3 are 2D and 3D fixes
+    if report["class"] != "TPV" || report["mode"].as_u64()? < 2 {
+        return None;
+    }
+    Some((report["lat"].as_f64()?, report["lon"].as_f64()?))
+}
+
+/// The position in a GGA or RMC sentence, None for other sentences, bad checksums
+/// and sentences without a fix
+pub fn parse_nmea(line: &str) -> Option<(f64, f64)> {
+    let (body, checksum) = line.trim().strip_prefix('$')?.split_once('*')?;
+    if u8::from_str_radix(checksum, 16).ok()? != body.bytes().fold(0, |sum, b| sum ^ b) {
+        return None;
+    }
+    let fields: Vec<&str> = body.split(',').collect();
+    let field = |i: usize| fields.get(i).copied().unwrap_or_default();
+    // the first two letters name the satellite system, e.g. GP for GPS or GN for several
+    let (lat, ns, lon, ew) = match fields[0].get(2..)? {
+        "GGA" if !matches!(field(6), "" | "0") => (field(2), field(3), field(4), field(5)),
+        "RMC" if field(2) == "A" => (field(3), field(4), field(5), field(6)),
+        _ => return None,
+    };
+    let sign = |hemisphere: &str, negative: &str| match hemisphere == negative {
+        true => -1.0,
+        false => 1.0,
+    };
+    Some((
+        degrees(lat, 2)? * sign(ns, "S"),
+        degrees(lon, 3)? * sign(ew, "W"),
+    ))
+}
+
+/// Converts NMEA ddmm.mmmm, or dddmm.mmmm for longitudes, to degrees
+fn degrees(v: &str, degree_digits: usize) -> Option<f64> {
+    let deg: f64 = v.get(..degree_digits)?.parse().ok()?;
+    let min: f64 = v.get(degree_digits..)?.parse().ok()?;
+    Some(deg + min / 60.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_nmea, parse_tpv};
+
+    #[test]
+    pub fn test_parse_position() {
+        let gga = "$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47";
+        let (lat, lon) = parse_nmea(gga).unwrap();
+        assert!((lat - 48.1173).abs() < 0.0001 && (lon - 11.516_67).abs() < 0.0001);
+        assert_eq!("JN58sc", util::geo::gridsquare(lat, lon));
+        let rmc = "$GPRMC,123519,A,4807.038,S,01131.000,W,022.4,084.4,230394,003.1,W*65";
+        let (lat, lon) = parse_nmea(rmc).unwrap();
+        assert!(lat < -48.0 && lon < -11.0);
+        assert_eq!(None, parse_nmea(&gga.replace("*47", "*48")));
+        let no_fix = "$GPRMC,123519,V,,,,,,,230394,,*33";
+        assert_eq!(None, parse_nmea(no_fix));
+
+        let tpv = r#"{"class":"TPV","device":"/dev/ttyACM0","mode":3,"lat":41.729,"lon":-72.708}"#;
+        assert_eq!(Some((41.729, -72.708)), parse_tpv(tpv));
+        assert_eq!(None, parse_tpv(r#"{"class":"TPV","mode":1}"#));
+        assert_eq!(None, parse_tpv(r#"{"class":"SKY","satellites":[]}"#));
+    }
+}
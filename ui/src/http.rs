@@ -0,0 +1,278 @@
+use std::{collections::HashMap, fmt::Display};
+
+use anyhow::bail;
+use axum::{
+    Json, Router,
+    extract::{Query, Request, State},
+    http::{StatusCode, header},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+};
+use db::{
+    data::{FieldType, Log, LogRecord},
+    filter::Filter,
+};
+use iced::{
+    Subscription,
+    futures::{SinkExt, channel::mpsc::Sender},
+};
+use log::warn;
+use subtle::ConstantTimeEq;
+use tokio::net::TcpListener;
+use util::{callsign, freq::Frequency};
+
+/// QSOs listed when the request gives no limit
+const DEFAULT_LIMIT: usize = 50;
+/// Logging page for phone browsers
+const PAGE: &str = include_str!("http.html");
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Listening,
+    Failed(String),
+    /// A QSO posted to the API, logged like one entered in the app
+    Qso(LogRecord),
+}
+
+#[derive(Clone)]
+struct Api {
+    log: Log,
+    token: String,
+    events: Sender<Event>,
+}
+
+/// Serves the log on `addr`. `generation` restarts the server when another log is opened.
+pub fn serve(addr: String, token: String, log: Log, generation: u64) -> Subscription<Event> {
+    Subscription::run_with_id(
+        ("http", addr.clone(), token.clone(), generation),
+        iced::stream::channel(100, move |mut output| async move {
+            if let Err(e) = run(&addr, token, log, output.clone()).await {
+                warn!("HTTP API on {} stopped: {}", addr, e);
+                let _ = output.send(Event::Failed(e.to_string())).await;
+            }
+        }),
+    )
+}
+
+async fn run(addr: &str, token: String, log: Log, mut events: Sender<Event>) -> anyhow::Result<()> {
+    if token.is_empty() {
+        bail!("Set http_token before serving the log");
+    }
+    let listener = TcpListener::bind(addr).await?;
+    events.send(Event::Listening).await?;
+    axum::serve(listener, router(Api { log, token, events })).await?;
+    Ok(())
+}
+
+fn router(api: Api) -> Router {
+    Router::new()
+        .route("/api/qsos", get(list).post(insert))
+        .route("/api/export.adi", get(export))
+        .route_layer(middleware::from_fn_with_state(api.clone(), authorize))
+        .route("/", get(|| async { Html(PAGE) }))
+        .with_state(api)
+}
+
+/// Lets requests through that carry the token as `Authorization: Bearer <token>`
+/// or as a `token` query parameter. The token is compared in constant time, so timing
+/// answers doesn't give it away.
+async fn authorize(
+    State(api): State<Api>,
+    Query(query): Query<HashMap<String, String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    match bearer.or(query.get("token").map(String::as_str)) {
+        Some(token) if token.as_bytes().ct_eq(api.token.as_bytes()).into() => {
+            next.run(request).await
+        }
+        _ => (StatusCode::UNAUTHORIZED, "Wrong or missing token").into_response(),
+    }
+}
+
+fn bad_request(e: impl Display) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, e.to_string())
+}
+
+fn internal(e: impl Display) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+/// The newest QSOs first, only those matching `q` if given
+async fn list(
+    State(api): State<Api>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let limit = match query.get("limit") {
+        Some(limit) => limit.parse().map_err(bad_request)?,
+        None => DEFAULT_LIMIT,
+    };
+    let log = api.log;
+    let records = tokio::task::spawn_blocking(move || match query.get("q") {
+        Some(text) => log
+            .search(text)
+            .into_iter()
+            .rev()
+            .filter_map(|idx| log.get_record(idx))
+            .take(limit)
+            .collect::<Vec<LogRecord>>(),
+        None => log.iter_records_desc().take(limit).collect(),
+    })
+    .await
+    .map_err(internal)?;
+    Ok(Json(
+        records.iter().map(|r| r.json_fields()).collect::<Vec<_>>(),
+    ))
+}
+
+/// Takes a QSO as a JSON object keyed by export names, e.g. `{"CALL": "W1AW"}`.
+/// The time defaults to now.
+async fn insert(
+    State(mut api): State<Api>,
+    Json(fields): Json<HashMap<String, String>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let mut record = LogRecord::from_json_fields(fields).map_err(bad_request)?;
+    let Some(call) = record.get_field(&FieldType::WorkedCall) else {
+        return Err(bad_request("CALL is missing"));
+    };
+    let call = callsign::validate_callsign(&call).map_err(bad_request)?;
+    record.insert_field(FieldType::WorkedCall, &call);
+    if let Some(freq) = record.get_field(&FieldType::Frequency) {
+        let freq = Frequency::parse_khz_or_mhz(&freq).map_err(bad_request)?;
+        record.insert_frequency(FieldType::Frequency, freq);
+    }
+    if record.get_field(&FieldType::Timestamp).is_none() {
+        record.insert_timestamp(jiff::Timestamp::now());
+    }
+    api.events
+        .send(Event::Qso(record.clone()))
+        .await
+        .map_err(internal)?;
+    Ok((StatusCode::ACCEPTED, Json(record.json_fields())))
+}
+
+async fn export(State(api): State<Api>) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let log = api.log;
+    let adif =
+        tokio::task::spawn_blocking(move || log.export_adif(&Filter::default())?.serialize())
+            .await
+            .map_err(internal)?
+            .map_err(internal)?;
+    Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], adif))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::{self, Body},
+        http::{Request, StatusCode},
+    };
+    use db::data::{FieldType, Log, LogHeader, LogRecord};
+    use iced::futures::channel::mpsc;
+    use tower::ServiceExt;
+
+    use super::{Api, Event, router};
+
+    async fn get(api: &Api, uri: &str) -> (StatusCode, String) {
+        let res = router(api.clone())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = res.status();
+        let body = body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[test]
+    pub fn test_api() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let log = Log::new_init(db, LogHeader::new("N0CALL", "")).unwrap();
+        for call in ["W1AW", "DL1ABC"] {
+            let mut record = LogRecord::new();
+            record.insert_field(FieldType::WorkedCall, call);
+            log.insert_record(record).unwrap();
+        }
+        let (events, mut qsos) = mpsc::channel(10);
+        let api = Api {
+            log,
+            token: "secret".to_string(),
+            events,
+        };
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            assert_eq!(StatusCode::OK, get(&api, "/").await.0);
+            assert_eq!(StatusCode::UNAUTHORIZED, get(&api, "/api/qsos").await.0);
+            let (status, body) = get(&api, "/api/qsos?token=secret").await;
+            assert_eq!(StatusCode::OK, status);
+            assert!(body.find("DL1ABC").unwrap() < body.find("W1AW").unwrap());
+            let (_, body) = get(&api, "/api/qsos?token=secret&q=dl1&limit=5").await;
+            assert!(!body.contains("W1AW"));
+            let (_, body) = get(&api, "/api/export.adi?token=secret").await;
+            assert!(body.contains("<CALL:6>DL1ABC"));
+
+            let post = |json: &str| {
+                Request::post("/api/qsos")
+                    .header("Authorization", "Bearer secret")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(json.to_string()))
+                    .unwrap()
+            };
+            let res = router(api.clone())
+                .oneshot(post(r#"{"CALL": "ja1xyz", "FREQ": "14074"}"#))
+                .await
+                .unwrap();
+            assert_eq!(StatusCode::ACCEPTED, res.status());
+            let Ok(Event::Qso(record)) = qsos.try_recv() else {
+                panic!("QSO was not passed on");
+            };
+            assert_eq!(
+                Some("JA1XYZ".to_string()),
+                record.get_field(&FieldType::WorkedCall)
+            );
+            assert_eq!(
+                Some("14.074".to_string()),
+                record.get_field(&FieldType::Frequency)
+            );
+            assert!(record.get_field(&FieldType::Timestamp).is_some());
+            let res = router(api.clone())
+                .oneshot(post(r#"{"NAME": "Taro"}"#))
+                .await
+                .unwrap();
+            assert_eq!(StatusCode::BAD_REQUEST, res.status());
+        });
+    }
+
+    #[test]
+    pub fn test_token() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let (events, _qsos) = mpsc::channel(10);
+        let api = Api {
+            log: Log::new_init(db, LogHeader::new("N0CALL", "")).unwrap(),
+            token: "a&b+c%d".to_string(),
+            events,
+        };
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            // percent-encoded in the query, as browsers send it
+            let ok = "/api/qsos?limit=1&token=a%26b%2Bc%25d";
+            assert_eq!(StatusCode::OK, get(&api, ok).await.0);
+            for uri in [
+                "/api/qsos?token=a&b+c%d",
+                "/api/qsos?token=a%26b%2Bc",
+                "/api/qsos?token=",
+            ] {
+                assert_eq!(StatusCode::UNAUTHORIZED, get(&api, uri).await.0, "{}", uri);
+            }
+        });
+    }
+}
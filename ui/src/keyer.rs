@@ -0,0 +1,137 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use db::data::FieldType;
+
+/// Number of message macros, bound to F1-F8
+pub const MACRO_COUNT: usize = 8;
+
+/// Values substituted into a CW message macro
+pub struct MacroContext<'a> {
+    pub my_call: &'a str,
+    pub content: &'a HashMap<FieldType, String>,
+    /// The mode's exchange template, e.g. `5NN {SERIAL}`
+    pub exchange: &'a str,
+}
+
+impl MacroContext<'_> {
+    fn field(&self, ty: &FieldType, default: &str) -> String {
+        match self.content.get(ty) {
+            Some(v) if !v.is_empty() => v.to_string(),
+            _ => default.to_string(),
+        }
+    }
+}
+
+/// Expands `{MYCALL}`, `{CALL}`, `{RST}`, `{SERIAL}` and `{EXCH}` in a macro template.
+/// Unknown tokens are left alone so typos are audible rather than silently dropped.
+pub fn expand_macro(template: &str, ctx: &MacroContext) -> String {
+    template
+        .replace("{EXCH}", ctx.exchange)
+        .replace("{MYCALL}", ctx.my_call)
+        .replace("{CALL}", &ctx.field(&FieldType::WorkedCall, ""))
+        .replace("{RST}", &ctx.field(&FieldType::SentRST, "599"))
+        .replace("{SERIAL}", &ctx.field(&FieldType::SentSerial, "1"))
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_ascii_uppercase()
+}
+
+/// Repeats a message with a pause in between until stopped, e.g. calling CQ on phone
+#[derive(Debug, Default)]
+pub struct AutoCq {
+    message: Option<usize>,
+    due: Option<Instant>,
+}
+
+impl AutoCq {
+    /// Starts repeating message `n`, the first time right away
+    pub fn start(&mut self, n: usize, now: Instant) {
+        self.message = Some(n);
+        self.due = Some(now);
+    }
+
+    pub fn stop(&mut self) {
+        self.message = None;
+        self.due = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.message.is_some()
+    }
+
+    /// Schedules the next repeat once the message has finished playing
+    // only voice messages report when they are done, which needs the audio feature
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    pub fn finished(&mut self, now: Instant, pause: Duration) {
+        if self.message.is_some() {
+            self.due = Some(now + pause);
+        }
+    }
+
+    /// The message to play now, if a repeat is due
+    pub fn next_due(&mut self, now: Instant) -> Option<usize> {
+        match self.due {
+            Some(due) if due <= now => {
+                self.due = None;
+                self.message
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    };
+
+    use db::data::FieldType;
+
+    use super::{AutoCq, MacroContext, expand_macro};
+
+    #[test]
+    pub fn test_expand_exchange() {
+        let content = HashMap::from([
+            (FieldType::WorkedCall, "w1aw".to_string()),
+            (FieldType::SentSerial, "42".to_string()),
+        ]);
+        let ctx = MacroContext {
+            my_call: "DL1ABC",
+            content: &content,
+            exchange: "5NN {SERIAL}",
+        };
+        assert_eq!("W1AW 5NN 42", expand_macro("{CALL} {EXCH}", &ctx));
+        let ctx = MacroContext {
+            exchange: "",
+            ..ctx
+        };
+        assert_eq!("TU DL1ABC", expand_macro("{EXCH} TU {MYCALL}", &ctx));
+    }
+
+    #[test]
+    pub fn test_auto_cq() {
+        let mut cq = AutoCq::default();
+        let now = Instant::now();
+        assert_eq!(None, cq.next_due(now));
+        cq.start(0, now);
+        assert_eq!(Some(0), cq.next_due(now));
+        // nothing while the message plays
+        assert_eq!(None, cq.next_due(now + Duration::from_secs(10)));
+
+        let pause = Duration::from_secs(3);
+        cq.finished(now, pause);
+        assert_eq!(None, cq.next_due(now + Duration::from_secs(2)));
+        assert_eq!(Some(0), cq.next_due(now + pause));
+
+        cq.finished(now, pause);
+        cq.stop();
+        assert!(!cq.is_running());
+        assert_eq!(None, cq.next_due(now + pause));
+    }
+}
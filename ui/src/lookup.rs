@@ -0,0 +1,137 @@
+use anyhow::{Result, bail};
+use db::{
+    lookup::CallInfo,
+    settings::{Callbook, Settings},
+};
+
+mod clublog;
+mod hamqth;
+mod qrz;
+
+/// An online callbook that can look up operator details.
+/// Lookups block on the network, so run them off the UI thread.
+pub trait LookupProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Looks up `call`, returning None if the callbook does not know it
+    fn lookup(&self, call: &str) -> Result<Option<CallInfo>>;
+}
+
+/// Callbooks queried in order, the first one that knows the call wins
+#[derive(Default)]
+pub struct LookupChain {
+    providers: Vec<Box<dyn LookupProvider>>,
+}
+
+impl LookupChain {
+    /// Builds the chain in the configured order, skipping callbooks without credentials
+    pub fn from_settings(settings: &Settings) -> Self {
+        let mut chain = Self::default();
+        for callbook in &settings.lookup_order {
+            match callbook {
+                Callbook::Qrz if !settings.qrz_user.is_empty() => chain.push(Box::new(
+                    qrz::Qrz::new(&settings.qrz_user, &settings.qrz_password),
+                )),
+                Callbook::HamQth if !settings.hamqth_user.is_empty() => chain.push(Box::new(
+                    hamqth::HamQth::new(&settings.hamqth_user, &settings.hamqth_password),
+                )),
+                Callbook::Clublog if !settings.clublog_api_key.is_empty() => {
+                    chain.push(Box::new(clublog::Clublog::new(&settings.clublog_api_key)))
+                }
+                _ => {}
+            }
+        }
+        chain
+    }
+
+    pub fn push(&mut self, provider: Box<dyn LookupProvider>) {
+        self.providers.push(provider);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+}
+
+impl LookupProvider for LookupChain {
+    fn name(&self) -> &'static str {
+        "callbooks"
+    }
+
+    /// Tries every callbook in turn. Errors are only reported if no callbook answered.
+    fn lookup(&self, call: &str) -> Result<Option<CallInfo>> {
+        let mut errors = Vec::new();
+        let mut answered = false;
+        for provider in &self.providers {
+            match provider.lookup(call) {
+                Ok(Some(info)) => return Ok(Some(info)),
+                Ok(None) => answered = true,
+                Err(e) => errors.push(format!("{}: {}", provider.name(), e)),
+            }
+        }
+        match answered || errors.is_empty() {
+            true => Ok(None),
+            false => bail!(errors.join(", ")),
+        }
+    }
+}
+
+/// The unescaped text between `<tag>` and `</tag>`. Callbook responses are flat enough
+/// that a full XML parser is not needed.
+pub(crate) fn xml_value(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let len = xml[start..].find(&format!("</{}>", tag))?;
+    Some(
+        xml[start..start + len]
+            .trim()
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{Result, bail};
+    use db::lookup::CallInfo;
+
+    use super::{LookupChain, LookupProvider};
+
+    struct Fake(Option<&'static str>, bool);
+
+    impl LookupProvider for Fake {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn lookup(&self, call: &str) -> Result<Option<CallInfo>> {
+            if self.1 {
+                bail!("offline")
+            }
+            Ok(self.0.map(|name| CallInfo {
+                call: call.to_string(),
+                name: Some(name.to_string()),
+                ..Default::default()
+            }))
+        }
+    }
+
+    #[test]
+    pub fn test_lookup_chain() {
+        let mut chain = LookupChain::default();
+        assert_eq!(None, chain.lookup("W1AW").unwrap());
+
+        chain.push(Box::new(Fake(None, true)));
+        assert!(chain.lookup("W1AW").is_err());
+
+        chain.push(Box::new(Fake(None, false)));
+        assert_eq!(None, chain.lookup("W1AW").unwrap());
+
+        chain.push(Box::new(Fake(Some("first"), false)));
+        chain.push(Box::new(Fake(Some("second"), false)));
+        let info = chain.lookup("W1AW").unwrap().unwrap();
+        assert_eq!(Some("first"), info.name.as_deref());
+    }
+}
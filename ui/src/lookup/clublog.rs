@@ -0,0 +1,75 @@
+use anyhow::{Result, bail};
+use db::lookup::CallInfo;
+use serde_json::Value;
+
+use super::LookupProvider;
+
+const CLUBLOG_URL: &str = "https://clublog.org/dxcc";
+
+/// Club Log's DXCC lookup. It knows nothing about the operator, but resolves
+/// the entity and CQ zone using Club Log's exception lists.
+pub struct Clublog {
+    api_key: String,
+}
+
+impl Clublog {
+    pub fn new(api_key: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+        }
+    }
+}
+
+impl LookupProvider for Clublog {
+    fn name(&self) -> &'static str {
+        "Club Log"
+    }
+
+    fn lookup(&self, call: &str) -> Result<Option<CallInfo>> {
+        let json = ureq::get(CLUBLOG_URL)
+            .query("call", call)
+            .query("api", &self.api_key)
+            .query("full", "1")
+            .call()?
+            .body_mut()
+            .read_to_string()?;
+        parse_dxcc(call, &json)
+    }
+}
+
+/// Extracts the entity from a Club Log DXCC response. Unknown calls resolve to entity 0.
+fn parse_dxcc(call: &str, json: &str) -> Result<Option<CallInfo>> {
+    let Ok(value) = serde_json::from_str::<Value>(json) else {
+        bail!("Unexpected response from Club Log: {}", json.trim())
+    };
+    let number = |key| value.get(key).and_then(Value::as_u64).filter(|n| *n != 0);
+    let Some(dxcc) = number("DXCC") else {
+        return Ok(None);
+    };
+    Ok(Some(CallInfo {
+        call: call.to_ascii_uppercase(),
+        dxcc: Some(dxcc.to_string()),
+        cq_zone: number("CQZ").map(|z| z.to_string()),
+        fetched: jiff::Timestamp::now(),
+        ..Default::default()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_dxcc;
+
+    #[test]
+    pub fn test_parse_dxcc() {
+        let json = r#"{"Name":"CANARY ISLANDS","DXCC":29,"CQZ":33,"Continent":"AF","Lon":-15.6,"Lat":28.32}"#;
+        let info = parse_dxcc("ea8aaa", json).unwrap().unwrap();
+        assert_eq!("EA8AAA", info.call);
+        assert_eq!(Some("29"), info.dxcc.as_deref());
+        assert_eq!(Some("33"), info.cq_zone.as_deref());
+        assert_eq!(None, info.name);
+
+        let unknown = r#"{"Name":"Unknown","DXCC":0,"CQZ":0,"Continent":"","Lon":0,"Lat":0}"#;
+        assert_eq!(None, parse_dxcc("XX1XX", unknown).unwrap());
+        assert!(parse_dxcc("W1AW", "Invalid API key").is_err());
+    }
+}
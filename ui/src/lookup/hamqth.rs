@@ -0,0 +1,132 @@
+use std::sync::Mutex;
+
+use anyhow::{Result, bail};
+use db::lookup::CallInfo;
+
+use super::{LookupProvider, xml_value};
+
+const HAMQTH_URL: &str = "https://www.hamqth.com/xml.php";
+
+/// Client for the free HamQTH.com XML callbook
+pub struct HamQth {
+    user: String,
+    password: String,
+    /// Session id handed out by the login, valid for an hour
+    session: Mutex<Option<String>>,
+}
+
+impl HamQth {
+    pub fn new(user: &str, password: &str) -> Self {
+        Self {
+            user: user.to_string(),
+            password: password.to_string(),
+            session: Mutex::new(None),
+        }
+    }
+
+    fn login(&self) -> Result<String> {
+        let xml = ureq::get(HAMQTH_URL)
+            .query("u", &self.user)
+            .query("p", &self.password)
+            .call()?
+            .body_mut()
+            .read_to_string()?;
+        match xml_value(&xml, "session_id") {
+            Some(id) => {
+                *self.session.lock().unwrap() = Some(id.clone());
+                Ok(id)
+            }
+            None => bail!(
+                "HamQTH login failed: {}",
+                xml_value(&xml, "error").unwrap_or_default()
+            ),
+        }
+    }
+
+    fn query(&self, id: &str, call: &str) -> Result<String> {
+        Ok(ureq::get(HAMQTH_URL)
+            .query("id", id)
+            .query("callsign", call)
+            .query("prg", env!("CARGO_PKG_NAME"))
+            .call()?
+            .body_mut()
+            .read_to_string()?)
+    }
+}
+
+impl LookupProvider for HamQth {
+    fn name(&self) -> &'static str {
+        "HamQTH"
+    }
+
+    fn lookup(&self, call: &str) -> Result<Option<CallInfo>> {
+        let id = match self.session.lock().unwrap().clone() {
+            Some(id) => id,
+            None => self.login()?,
+        };
+        let mut xml = self.query(&id, call)?;
+        if xml_value(&xml, "error").is_some_and(|e| e.contains("Session")) {
+            let id = self.login()?;
+            xml = self.query(&id, call)?;
+        }
+        parse_search(&xml)
+    }
+}
+
+/// Extracts the operator details from a HamQTH search response
+fn parse_search(xml: &str) -> Result<Option<CallInfo>> {
+    let Some(search) = xml_value(xml, "search") else {
+        return match xml_value(xml, "error") {
+            Some(e) if e.contains("not found") => Ok(None),
+            Some(e) => bail!("HamQTH lookup failed: {}", e),
+            None => bail!("Unexpected response from HamQTH"),
+        };
+    };
+    let field = |tag| xml_value(&search, tag).filter(|v| !v.is_empty());
+    Ok(Some(CallInfo {
+        call: field("callsign").unwrap_or_default().to_ascii_uppercase(),
+        name: field("adr_name").or_else(|| field("nick")),
+        qth: field("qth"),
+        grid: field("grid"),
+        state: field("us_state"),
+        dxcc: field("adif"),
+        cq_zone: field("cq"),
+        fetched: jiff::Timestamp::now(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_search;
+
+    #[test]
+    pub fn test_parse_search() {
+        let xml = r#"<?xml version="1.0"?>
+<HamQTH version="2.8" xmlns="https://www.hamqth.com">
+<search>
+<callsign>ok7an</callsign>
+<nick>Petr</nick>
+<qth>Neratovice</qth>
+<country>Czech Republic</country>
+<adif>503</adif>
+<itu>28</itu>
+<cq>15</cq>
+<grid>jo70gg</grid>
+<adr_name>Petr Hlozek</adr_name>
+</search>
+</HamQTH>"#;
+        let info = parse_search(xml).unwrap().unwrap();
+        assert_eq!("OK7AN", info.call);
+        assert_eq!(Some("Petr Hlozek"), info.name.as_deref());
+        assert_eq!(Some("Neratovice"), info.qth.as_deref());
+        assert_eq!(Some("jo70gg"), info.grid.as_deref());
+        assert_eq!(None, info.state);
+        assert_eq!(Some("503"), info.dxcc.as_deref());
+
+        let not_found = "<HamQTH><session><error>Callsign not found</error></session></HamQTH>";
+        assert_eq!(None, parse_search(not_found).unwrap());
+        let expired =
+            "<HamQTH><session><error>Session does not exist or expired</error></session></HamQTH>";
+        assert!(parse_search(expired).is_err());
+    }
+}
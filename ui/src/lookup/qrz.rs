@@ -0,0 +1,139 @@
+use std::sync::Mutex;
+
+use anyhow::{Result, bail};
+use db::lookup::CallInfo;
+
+use super::{LookupProvider, xml_value};
+
+const QRZ_URL: &str = "https://xmldata.qrz.com/xml/current/";
+
+/// Client for the QRZ.com XML callbook. Needs a QRZ XML subscription.
+pub struct Qrz {
+    user: String,
+    password: String,
+    /// Session key handed out by the login, reused until QRZ expires it
+    session: Mutex<Option<String>>,
+}
+
+impl Qrz {
+    pub fn new(user: &str, password: &str) -> Self {
+        Self {
+            user: user.to_string(),
+            password: password.to_string(),
+            session: Mutex::new(None),
+        }
+    }
+
+    fn login(&self) -> Result<String> {
+        let xml = ureq::get(QRZ_URL)
+            .query("username", &self.user)
+            .query("password", &self.password)
+            .query("agent", env!("CARGO_PKG_NAME"))
+            .call()?
+            .body_mut()
+            .read_to_string()?;
+        match xml_value(&xml, "Key") {
+            Some(key) => {
+                *self.session.lock().unwrap() = Some(key.clone());
+                Ok(key)
+            }
+            None => bail!(
+                "QRZ login failed: {}",
+                xml_value(&xml, "Error").unwrap_or_default()
+            ),
+        }
+    }
+
+    fn query(&self, key: &str, call: &str) -> Result<String> {
+        Ok(ureq::get(QRZ_URL)
+            .query("s", key)
+            .query("callsign", call)
+            .call()?
+            .body_mut()
+            .read_to_string()?)
+    }
+}
+
+impl LookupProvider for Qrz {
+    fn name(&self) -> &'static str {
+        "QRZ"
+    }
+
+    fn lookup(&self, call: &str) -> Result<Option<CallInfo>> {
+        let key = match self.session.lock().unwrap().clone() {
+            Some(key) => key,
+            None => self.login()?,
+        };
+        let mut xml = self.query(&key, call)?;
+        // sessions time out after a day, log in again once
+        if xml_value(&xml, "Callsign").is_none() && xml_value(&xml, "Key").is_none() {
+            let key = self.login()?;
+            xml = self.query(&key, call)?;
+        }
+        parse_callsign(&xml)
+    }
+}
+
+/// Extracts the operator details from a QRZ callsign response
+fn parse_callsign(xml: &str) -> Result<Option<CallInfo>> {
+    let Some(callsign) = xml_value(xml, "Callsign") else {
+        return match xml_value(xml, "Error") {
+            Some(e) if e.starts_with("Not found") => Ok(None),
+            Some(e) => bail!("QRZ lookup failed: {}", e),
+            None => bail!("Unexpected response from QRZ"),
+        };
+    };
+    let field = |tag| xml_value(&callsign, tag).filter(|v| !v.is_empty());
+    let name = match (field("fname"), field("name")) {
+        (Some(first), Some(last)) => Some(format!("{} {}", first, last)),
+        (first, last) => first.or(last),
+    };
+    Ok(Some(CallInfo {
+        call: field("call").unwrap_or_default().to_ascii_uppercase(),
+        name,
+        qth: field("addr2"),
+        grid: field("grid"),
+        state: field("state"),
+        dxcc: field("dxcc"),
+        cq_zone: field("cqzone"),
+        fetched: jiff::Timestamp::now(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_callsign;
+
+    #[test]
+    pub fn test_parse_callsign() {
+        let xml = r#"<?xml version="1.0" ?>
+<QRZDatabase version="1.34">
+  <Callsign>
+    <call>AA7BQ</call>
+    <fname>FRED L</fname>
+    <name>LLOYD</name>
+    <addr1>8711 E PINNACLE PEAK RD 193</addr1>
+    <addr2>SCOTTSDALE</addr2>
+    <state>AZ</state>
+    <country>United States</country>
+    <grid>DM43bt</grid>
+  </Callsign>
+  <Session>
+    <Key>2331uf894c4bd29f3923f3bacf02c532d7bd9</Key>
+  </Session>
+</QRZDatabase>"#;
+        let info = parse_callsign(xml).unwrap().unwrap();
+        assert_eq!("AA7BQ", info.call);
+        assert_eq!(Some("FRED L LLOYD"), info.name.as_deref());
+        assert_eq!(Some("SCOTTSDALE"), info.qth.as_deref());
+        assert_eq!(Some("DM43bt"), info.grid.as_deref());
+        assert_eq!(Some("AZ"), info.state.as_deref());
+
+        let not_found =
+            "<QRZDatabase><Session><Error>Not found: XX1XX</Error></Session></QRZDatabase>";
+        assert_eq!(None, parse_callsign(not_found).unwrap());
+        let expired =
+            "<QRZDatabase><Session><Error>Session Timeout</Error></Session></QRZDatabase>";
+        assert!(parse_callsign(expired).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+use adif::{data::ADIFFile, parse::parse_adif};
+use anyhow::{Result, bail};
+
+const REPORT_URL: &str = "https://lotw.arrl.org/lotwuser/lotwreport.adi";
+
+/// Downloads the QSLs received since `since`, a LoTW time such as the last report's
+/// APP_LoTW_LASTQSL, or all of them. This blocks on the network, so run it off the UI thread.
+pub fn fetch_confirmations(user: &str, password: &str, since: Option<&str>) -> Result<ADIFFile> {
+    let mut request = ureq::get(REPORT_URL)
+        .query("login", user)
+        .query("password", password)
+        .query("qso_query", "1")
+        .query("qso_qsl", "yes")
+        .query("qso_qsldetail", "yes");
+    if let Some(since) = since {
+        request = request.query("qso_qslsince", since);
+    }
+    parse_report(&request.call()?.body_mut().read_to_string()?)
+}
+
+/// LoTW answers a bad login with an HTML page rather than an error status
+fn parse_report(body: &str) -> Result<ADIFFile> {
+    if !body.to_ascii_lowercase().contains("<eoh>") {
+        bail!("LoTW did not send a report, check the username and password");
+    }
+    Ok(parse_adif(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_report;
+
+    #[test]
+    pub fn test_parse_report() {
+        let report = parse_report(
+            "ARRL Logbook of the World Status Report\n\
+             <APP_LoTW_NUMREC:1>1\n<eoh>\n\
+             <CALL:6>JA1XYZ<BAND:3>40M<QSL_RCVD:1>Y<eor>\n",
+        )
+        .unwrap();
+        assert_eq!(1, report.body.len());
+        assert!(parse_report("<html><body>Username/password incorrect</body></html>").is_err());
+    }
+}
@@ -1,239 +1,1103 @@
-use hamlib::{
-    lock::{self, Hamlib},
-    rig::Rig,
-    sys::RIG_MODEL_IC7200,
-    token::TOK_PATHNAME,
-    types::VFO,
-};
-use iced::{alignment::Horizontal, event::{self, Status}, keyboard::{key::Named, Key, Modifiers}, widget::{self, button, column, container, row, scrollable, text_input, Column}, window, Element, Length, Task, Theme
+use adif::data::ADIFFile;
+use clap::Parser;
+use iced::{
+    Color, Element, Length, Subscription, Task, Theme,
+    alignment::{Horizontal, Vertical},
+    event::{self, Status},
+    futures::SinkExt,
+    keyboard::{Key, Modifiers},
+    widget::{
+        self, Column, button, canvas, center, column, container, mouse_area, opaque, pick_list,
+        progress_bar, row, scrollable, stack, text_input,
+    },
+    window,
 };
+use jiff::tz::TimeZone;
 use log::error;
-use std::{collections::HashMap, env, fs::remove_dir_all, path::Path, time::Duration};
+use std::{
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use db::data::{FieldType, Log, LogHeader};
+use db::{
+    awards::{Need, VuccProgress},
+    clublog::ClublogChange,
+    contest,
+    data::{FieldType, Log, LogRecord, RecordId},
+    events::LogEvent,
+    filter::Filter,
+    formats,
+    lookup::CallInfo,
+    paths::Paths,
+    session::{self, SessionKind},
+    settings::Settings,
+    stats::Stats,
+    uploads::{Service, UploadError},
+};
+use util::{
+    band::Band,
+    bandplan::{self, Allocation, Region, Segment},
+    callsign,
+    fields::CONTINENTS,
+    geo::gridsquare_center,
+};
 
-#[derive(Debug, Clone, Copy)]
-pub enum Screen {
-    Entry,
-    LogList,
-}
+use crate::{
+    app::{Core, Disk, Effect, RecordEdit, Screen, size_text},
+    lookup::LookupProvider,
+    map::PointKind,
+    toast::ToastKind,
+};
+
+mod app;
+#[cfg(feature = "audio")]
+mod audio;
+mod backend;
+mod bandmap;
+mod broadcast;
+mod clublog;
+mod cluster;
+mod crash;
+// the decoder is tested without the audio feature, it only needs samples
+#[cfg(any(feature = "audio", test))]
+mod cw;
+mod demo;
+mod eqsl;
+mod gps;
+#[cfg(feature = "http")]
+mod http;
+mod keyer;
+mod lookup;
+mod lotw;
+mod map;
+mod n1mm;
+mod paper;
+mod rig;
+mod solar;
+mod sync;
+mod theme;
+mod toast;
+mod wsjtx;
+
+/// Modes offered in the entry screen's mode picker
+const MODES: &[&str] = &[
+    "CW",
+    "SSB",
+    "AM",
+    "FM",
+    "RTTY",
+    "FT8",
+    "MFSK",
+    "PSK",
+    "JT65",
+    "DIGITALVOICE",
+];
+
+/// Limits and step of the entry screen's scale setting
+const ENTRY_SCALE_MIN: f32 = 0.5;
+const ENTRY_SCALE_MAX: f32 = 2.0;
+const ENTRY_SCALE_STEP: f32 = 0.1;
+
+/// The last solar indices fetched, kept in the data directory as the XML received
+const SOLAR_FILE: &str = "solar.xml";
+
+/// Time to wait for more typing before searching the log
+const SEARCH_DELAY: Duration = Duration::from_millis(300);
+
+/// Widget id of the quick note input
+const NOTE_INPUT: &str = "note";
+
+/// Most partial call matches shown below the call field
+const SCP_MATCHES: usize = 8;
+
+/// Most decoded CW characters kept for the decoder panel
+#[cfg(feature = "audio")]
+const CW_TEXT_LEN: usize = 120;
+
+/// kHz offsets of the quick split buttons, as DX stations usually listen up 1 to 5
+const SPLIT_OFFSETS: [u32; 3] = [1, 2, 5];
+
+/// Band colors of the session timeline and the map, cycled through in band order
+const BAND_COLORS: &[(u8, u8, u8)] = &[
+    (0x7a, 0xa2, 0xf7),
+    (0x9e, 0xce, 0x6a),
+    (0xe0, 0xaf, 0x68),
+    (0xbb, 0x9a, 0xf7),
+    (0x7d, 0xcf, 0xff),
+    (0xf7, 0x76, 0x8e),
+];
 
 #[derive(Debug, Clone)]
 pub enum Message {
     EntrySelected,
+    ThemeSelected(Theme),
+    RegionSelected(Region),
+    EntryScaled(f32),
+    LogListSelected,
+    ClusterSelected,
+    MapSelected,
+    PaperSelected,
+    DupeSheetSelected,
+    UploadsSelected,
+    MaintenanceSelected,
+    /// A column of the paper log row was edited
+    PaperChanged(usize, String),
+    MapByBand(bool),
+    MapTick,
+    SolarTick,
+    NoteChanged(String),
+    SearchChanged(String),
+    /// The search delay for the nth edit of the search text is over
+    SearchDue(u64),
+    SearchDone(u64, Vec<usize>),
+    LogChanged(LogEvent),
+    SolarFetched(Result<String, String>),
     ContentChanged((FieldType, String)),
-    KeyPressed(String),
+    ModeSelected(String),
+    SessionKindSelected(SessionKind),
+    SessionNameChanged(String),
+    ToggleSession,
+    KeyPressed(KeyEvent),
+    LogPathChanged(String),
+    PassphraseChanged(String),
+    /// Create a new log at the typed path
     InitLog,
-    ImportADIF,
+    /// Open the log at this path, creating it if there is none yet
+    OpenLog(String),
+    /// Import a file in the format its extension names, ADIF times in import_utc_offset
+    Import(PathBuf),
+    /// Import a paper log typed up in Fast Log Entry format
+    ImportFLE,
+    /// Import ADIF records from the clipboard, e.g. a QSO sent in a chat
+    PasteADIF,
+    ADIFPasted(Option<String>),
+    ExportFormatSelected(String),
+    /// Check the log and export it if every QSO passes
+    Export,
+    /// Export leaving out the QSOs the check found problems with
+    ExportExcluding,
+    CancelExport,
+    /// Show every field of a QSO and where it came from
+    RecordSelected(usize),
+    CloseRecord,
+    /// Edit the fields of the QSO at this index
+    EditRecord(usize),
+    RecordFieldChanged(FieldType, String),
+    /// Write the edited QSO, unless it was changed elsewhere meanwhile
+    SaveRecord,
+    /// Take the QSO as changed elsewhere, dropping the edit
+    ReloadRecord,
+    /// Write the edited QSO over the change made elsewhere
+    OverwriteRecord,
+    CancelEdit,
+    /// Write the log as a printable page
+    PrintLog,
+    /// Writes the calls of the log to the call history file
+    UpdateCallHistory,
+    /// Write labels for the QSOs no labels were printed for yet
+    PrintQslLabels,
+    /// Write the rates and band breakdown of the last session, for contest write-ups
+    RateSheet,
+    /// Write the US counties worked, for county hunting awards
+    CountyReport,
+    /// Show QSO times in local time instead of UTC
+    LocalTime(bool),
+    /// Show only the QSOs with stations on this continent in the log list, None for all
+    ContinentSelected(Option<String>),
+    /// Log QSOs at a typed time instead of now, for transcribing paper logs
+    ToggleManualTime(bool),
+    ManualDateChanged(String),
+    ManualTimeChanged(String),
+    NormalizeLog,
+    VerifyLog,
+    RepairLog,
+    FlushLog,
+    /// Closes the log, rewrites it into a fresh database to reclaim space and reopens it
+    CompactLog,
+    LogCompacted(String, Result<Log, String>),
+    /// Writes the crash report, log verification and app logs to one file to send along
+    /// with a support request
+    ExportDiagnostics,
     InitHamlib,
     OpenRig,
     UpdateRig,
+    TogglePtt,
+    /// Transmit this many kHz above the receive frequency, or simplex for None
+    SetSplit(Option<u32>),
+    /// Tune the rig to the memory at this index of the settings
+    MemorySelected(usize),
+    SendMacro(usize),
+    ToggleAutoCq,
+    VoiceTick,
+    ToggleCluster,
+    Cluster(cluster::Event),
+    N1mm(n1mm::Event),
+    Gps(gps::Event),
+    /// Our grid typed in when roving without a GPS
+    RoverGridChanged(String),
+    /// One of our own references while activating was edited, e.g. `FieldType::MySotaRef`
+    MyReferenceChanged(FieldType, String),
+    DismissGridAlert,
+    #[cfg(feature = "http")]
+    Http(http::Event),
+    Sync(sync::Event),
+    SpotSelected(usize),
+    /// A station on the band map was clicked, with its call and frequency in kHz
+    BandMapSelected(String, f64, bandmap::StationKind),
+    ScpSelected(String),
+    CwTick,
+    /// A call in the decoded CW was clicked
+    DecodedCallSelected(String),
+    PlayAudio(String),
+    UploadTick,
+    /// Makes the uploads waiting after failures due now
+    RetryUploads,
+    EqslUploaded(RecordId, Result<(), UploadError>),
+    ClublogSent(RecordId, ClublogChange, Result<(), UploadError>),
+    /// Downloads the LoTW confirmations received since the last download
+    FetchLotw,
+    LotwFetched(Result<ADIFFile, String>),
+    LookupDone(String, Result<Option<CallInfo>, String>),
+    /// Ask with this question before going on with the message, e.g. before wiping a log
+    Confirm(String, Box<Message>),
+    Confirmed,
+    CancelConfirm,
+    /// The window is being closed, the app shuts down cleanly before it exits
+    CloseRequested,
+    DismissToast(usize),
+    ToastTick,
+    /// Time to save what is typed on the entry screen, if it changed
+    DraftTick,
 }
 
-pub struct RigState {
-    rig: Option<Rig>,
-    freq: f64,
-    mode: u64,
-    width: i64,
+#[derive(Debug, Clone)]
+pub struct KeyEvent {
+    pub key: Key,
+    pub modifiers: Modifiers,
+    /// Whether a widget (usually a text input) already handled the key
+    pub captured: bool,
 }
 
+/// The app as iced runs it: the `Core` handling the messages, and the tasks carrying
+/// out what it asks for
 pub struct State {
-    hamlib: Option<Hamlib>,
-    rig_state: RigState,
-    cur_log: Option<Log>,
-    screen: Screen,
-    content: HashMap<FieldType, String>,
-    focused_entry: usize,
-    entry_fields: Vec<FieldType>,
+    core: Core,
 }
 
-impl Default for State {
-    fn default() -> Self {
-        let entry_fields = vec![
-            FieldType::WorkedCall,
-            FieldType::SentRST,
-            FieldType::RcvdRST,
-        ];
-        Self {
-            hamlib: None,
-            rig_state: RigState {
-                rig: None,
-                freq: 0.0,
-                mode: 0,
-                width: 0,
-            },
-            cur_log: None,
-            screen: Screen::LogList,
-            content: HashMap::new(),
-            focused_entry: 0,
-            entry_fields,
-        }
+impl Deref for State {
+    type Target = Core;
+
+    fn deref(&self) -> &Core {
+        &self.core
     }
 }
 
 impl State {
-    pub fn title(&self) -> String {
-        format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+    /// The state to start with, opening the log given on the command line or the one
+    /// used last, or the sample log and simulated rig of the demo. After a crash a
+    /// diagnostic bundle is offered.
+    fn new(
+        paths: Paths,
+        settings: Settings,
+        args: Args,
+        crash_report: Option<String>,
+    ) -> (Self, Task<Message>) {
+        let mut core = Core::new(paths, settings, Box::new(Disk));
+        if let Some(model) = args.rig_model {
+            core.rig_model = model;
+        }
+        if let Some(port) = args.rig_port {
+            core.rig_path = port;
+        }
+        core.read_only = args.read_only;
+        if args.demo {
+            let seed = jiff::Timestamp::now().as_nanosecond() as u64;
+            match demo::create_log(&core.paths, seed) {
+                Ok((log, path)) => core.switch_log(log, path),
+                Err(e) => core.report_error(format!("Could not create the demo log: {}", e)),
+            }
+            core.rig_state.rig = Some(Box::new(demo::DriftingRig::new(seed)));
+        }
+        // a log named on the command line is relative to the current directory
+        let log = args
+            .log
+            .map(|path| std::path::absolute(&path).unwrap_or(path))
+            .map(|path| path.to_string_lossy().to_string());
+        let mut task = match log.or_else(|| core.settings.recent_logs.first().cloned()) {
+            Some(path) => Task::done(Message::OpenLog(path)),
+            None => Task::none(),
+        };
+        if let Some(path) = args.import {
+            task = task.chain(Task::done(Message::Import(path)));
+        }
+        let restored = core.restore_draft();
+        if crash_report.is_some() {
+            core.crash_report = crash_report;
+            task = task.chain(Task::done(Message::Confirm(
+                "veelog crashed when it last ran. Write a diagnostic bundle to send with a \
+                 support request?"
+                    .to_string(),
+                Box::new(Message::ExportDiagnostics),
+            )));
+        }
+        let mut state = Self { core };
+        let restored = state.run_effects(restored);
+        (state, Task::batch([task, restored]))
     }
 
     pub fn update(&mut self, message: Message) -> Task<Message> {
-        match message {
-            Message::EntrySelected => self.screen = Screen::Entry,
-            Message::InitLog => {
-                let path = env::temp_dir().join(Path::new("veelog-tests-db"));
-                let _ = remove_dir_all(&path);
-                let header = LogHeader::new("N0CALL", "");
-                self.cur_log = Some(Log::new_from_path(&path, header).unwrap());
-            }
-            Message::ImportADIF => {
-                if let Some(log) = &mut self.cur_log {
-                    log.import_adif_file("testlog2.adi".into()).unwrap();
-                }
+        let effects = self.core.update(message);
+        self.run_effects(effects)
+    }
+
+    fn run_effects(&mut self, effects: Vec<Effect>) -> Task<Message> {
+        Task::batch(effects.into_iter().map(|effect| self.run_effect(effect)))
+    }
+
+    /// The task carrying out what the core asked for
+    fn run_effect(&mut self, effect: Effect) -> Task<Message> {
+        match effect {
+            Effect::Focus(idx) => text_input::focus(idx.to_string()),
+            // the core looks calls up itself
+            Effect::LookUpCall => Task::none(),
+            Effect::FocusPaper(col) => text_input::focus(format!("paper-{}", col)),
+            Effect::FocusNote => text_input::focus(NOTE_INPUT),
+            Effect::LookUp(call) => {
+                let lookups = self.core.lookups.clone();
+                Task::perform(
+                    {
+                        let call = call.clone();
+                        async move {
+                            tokio::task::spawn_blocking(move || {
+                                lookups.lookup(&call).map_err(|e| e.to_string())
+                            })
+                            .await
+                            .unwrap_or_else(|e| Err(e.to_string()))
+                        }
+                    },
+                    move |res| Message::LookupDone(call.clone(), res),
+                )
             }
-            Message::InitHamlib => {
-                let lib = Hamlib::new().unwrap();
-                unsafe { lock::Hamlib::init_hamlib() };
-                lock::set_log_level(&lib, hamlib::LogLevel::Trace);
-                lock::set_log_timestamps(&lib, true);
-                lock::load_rig_backends(&lib).unwrap();
-                //params::init_params(lib);
-                self.hamlib = Some(lib);
+            Effect::FetchSolar => Task::perform(
+                async {
+                    tokio::task::spawn_blocking(|| solar::fetch().map_err(|e| e.to_string()))
+                        .await
+                        .unwrap_or_else(|e| Err(e.to_string()))
+                },
+                Message::SolarFetched,
+            ),
+            Effect::DelaySearch(seq) => Task::perform(
+                async move {
+                    tokio::time::sleep(SEARCH_DELAY).await;
+                    seq
+                },
+                Message::SearchDue,
+            ),
+            Effect::Search(seq, text) => {
+                let Some(log) = self.core.cur_log.clone() else {
+                    return Task::none();
+                };
+                Task::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || log.search(&text))
+                            .await
+                            .unwrap_or_default()
+                    },
+                    move |results| Message::SearchDone(seq, results),
+                )
             }
-            Message::OpenRig => {
-                if self.rig_state.rig.is_some() {
+            Effect::ReadClipboard => iced::clipboard::read().map(Message::ADIFPasted),
+            Effect::CompactLog(path) => {
+                let Some(log) = self.core.compacting.take() else {
                     return Task::none();
-                }
-                if let Some(lib) = &self.hamlib {
-                    let mut my_rig = Rig::new(lib, RIG_MODEL_IC7200).unwrap();
-                    my_rig.set_conf(lib, TOK_PATHNAME, c"/dev/serial/by-id/usb-Silicon_Labs_CP2102_USB_to_UART_Bridge_Controller_IC-7200_0202084-if00-port0").unwrap();
-                    my_rig.open(lib).unwrap();
-                    self.rig_state.rig = Some(my_rig)
-                }
+                };
+                Task::perform(
+                    async move {
+                        let p = path.clone();
+                        let res = tokio::task::spawn_blocking(move || log.compact(Path::new(&p)))
+                            .await
+                            .map_err(|e| e.to_string())
+                            .and_then(|res| res.map_err(|e| e.to_string()));
+                        (path, res)
+                    },
+                    |(path, res)| Message::LogCompacted(path, res),
+                )
             }
-            Message::UpdateRig => {
-                if let Some(lib) = &self.hamlib {
-                    if let Some(rig) = &self.rig_state.rig {
-                        self.rig_state.freq = rig.get_freq(&lib, VFO::RIG_VFO_CURR).unwrap();
-                        let (m, w) = rig.get_mode(&lib, VFO::RIG_VFO_CURR).unwrap();
-                        self.rig_state.mode = m;
-                        self.rig_state.width = w;
-                    }
-                }
+            Effect::UploadEqsl(id, record) => {
+                let user = self.settings.eqsl_user.clone();
+                let password = self.settings.eqsl_password.clone();
+                Task::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || {
+                            eqsl::upload(&user, &password, &record).map_err(UploadError::from)
+                        })
+                        .await
+                        .unwrap_or_else(|e| Err(UploadError::Failed(e.to_string())))
+                    },
+                    move |res| Message::EqslUploaded(id, res),
+                )
             }
-            Message::ContentChanged((k, v)) => {
-                let mut v = v;
-                match k {
-                    FieldType::WorkedCall => {
-                        if !v.chars().all(char::is_alphanumeric) {
-                            return Task::none();
-                        }
-                        v.truncate(15);
-                        v.make_ascii_uppercase()
-                    }
-                    FieldType::SentRST => {
-                        if v.parse::<u32>().is_err() && v != "" {
-                            return Task::none();
-                        }
-                        v.truncate(3);
-                    }
-                    FieldType::RcvdRST => {
-                        if v.parse::<u32>().is_err() && v != "" {
-                            return Task::none();
-                        }
-                        v.truncate(3);
-                    }
-                    FieldType::GridSquare => todo!(),
-                    FieldType::PrimaryAdminSubdiv => todo!(),
-                    FieldType::SentSerial => {
-                        if v.parse::<u32>().is_err() && v != "" {
-                            return Task::none();
-                        }
-                    }
-                    FieldType::RcvdSerial => {
-                        if v.parse::<u32>().is_err() && v != "" {
-                            return Task::none();
-                        }
-                    }
-                    _ => todo!(),
+            Effect::SendClublog(id, change) => {
+                let account = clublog::Account {
+                    email: self.settings.clublog_email.clone(),
+                    password: self.settings.clublog_password.clone(),
+                    callsign: self.settings.my_call.clone(),
+                    api_key: self.settings.clublog_api_key.clone(),
                 };
-                *self.content.entry(k).or_insert("".to_string()) = v.to_string();
+                Task::perform(
+                    async move {
+                        let sent = change.clone();
+                        let res = tokio::task::spawn_blocking(move || {
+                            clublog::send(&account, &sent).map_err(UploadError::from)
+                        })
+                        .await
+                        .unwrap_or_else(|e| Err(UploadError::Failed(e.to_string())));
+                        (change, res)
+                    },
+                    move |(change, res)| Message::ClublogSent(id, change, res),
+                )
             }
-            Message::KeyPressed(key) => {
-                match key.as_str() {
-                    "Tab" => {
-                        self.focused_entry += 1;
-                        if self.focused_entry >= self.entry_fields.len() {
-                            self.focused_entry = 0;
-                        }
-                        return text_input::focus(self.focused_entry.to_string());
-                    }
-                    "TabShift" => {
-                        if self.focused_entry as isize - 1 < 0 {
-                            self.focused_entry = 0;
-                        } else {
-                            self.focused_entry -= 1;
-                        }
-                        return text_input::focus(self.focused_entry.to_string());
+            Effect::FetchLotw(since) => {
+                let user = self.settings.lotw_user.clone();
+                let password = self.settings.lotw_password.clone();
+                Task::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || {
+                            lotw::fetch_confirmations(&user, &password, since.as_deref())
+                                .map_err(|e| e.to_string())
+                        })
+                        .await
+                        .unwrap_or_else(|e| Err(e.to_string()))
+                    },
+                    Message::LotwFetched,
+                )
+            }
+            Effect::Broadcast(payload) => {
+                let destinations = self.broadcast_to.clone();
+                Task::future(async move {
+                    if let Err(e) = broadcast::send(destinations, payload).await {
+                        error!("Could not broadcast QSO: {}", e);
                     }
-                    _ => return Task::none(),
-                };
+                })
+                .discard()
             }
-        };
-        Task::none()
+            Effect::Exit => iced::exit(),
+        }
     }
 
     pub fn view(&self) -> Element<'_, Message> {
-        let controls = row![].push(button("Entry").on_press(Message::EntrySelected));
+        let scale = self.settings.entry_scale;
+        let controls = row![
+            button("Entry").on_press(Message::EntrySelected),
+            button("Log").on_press(Message::LogListSelected),
+            button("Cluster").on_press(Message::ClusterSelected),
+            button("Map").on_press(Message::MapSelected),
+            button("Paper").on_press(Message::PaperSelected),
+            button("Dupes").on_press(Message::DupeSheetSelected),
+            button("Uploads").on_press(Message::UploadsSelected),
+            button("Maintenance").on_press(Message::MaintenanceSelected),
+            pick_list(
+                theme::all(),
+                Some(theme::by_name(&self.settings.theme)),
+                Message::ThemeSelected
+            ),
+            pick_list(
+                Region::ALL,
+                Some(self.settings.region),
+                Message::RegionSelected
+            ),
+            button("A-").on_press(Message::EntryScaled(scale - ENTRY_SCALE_STEP)),
+            button("A+").on_press(Message::EntryScaled(scale + ENTRY_SCALE_STEP)),
+        ];
         let screen = match self.screen {
-            Screen::Entry => self.entry(),
+            Screen::Entry => row![self.entry(), self.band_map()].into(),
             Screen::LogList => self.log_list(),
+            Screen::Cluster => self.cluster(),
+            Screen::Map => self.map(),
+            Screen::Paper => self.paper_log(),
+            Screen::DupeSheet => self.dupe_sheet(),
+            Screen::Uploads => self.upload_status(),
+            Screen::Maintenance => self.maintenance(),
+        };
+        let split = match self.rig_state.split {
+            true => format!(
+                ", split TX {:.2}kHz ({:+.1})",
+                self.rig_state.tx_freq / 1e3,
+                (self.rig_state.tx_freq - self.rig_state.freq) / 1e3
+            ),
+            false => String::new(),
+        };
+        let radio = match self.other_radio.rig_state.rig {
+            Some(_) => format!("radio {} (Ctrl+R switches), ", self.radio),
+            None => String::new(),
+        };
+        let info = row![
+            widget::text(format!(
+                "{}rig freq: {:.2}kHz{}, mode: {}, width: {}",
+                radio,
+                self.rig_state.freq / 1e3,
+                split,
+                rig::mode_name(self.rig_state.mode),
+                self.rig_state.width
+            )),
+            self.band_info(),
+            self.meters(),
+            widget::text(match (&self.gps_grid, self.rover_grid()) {
+                (Some(grid), _) => format!("grid: {} (GPS)", grid),
+                (None, Some(grid)) => format!("grid: {}", grid),
+                (None, None) => String::new(),
+            }),
+            self.solar_info(),
+        ]
+        .spacing(10);
+
+        let content = column![controls, info, self.band_timeline(), screen,];
+
+        let content: Element<'_, Message> = match self.screen {
+            Screen::Entry | Screen::Map => content.into(),
+            Screen::LogList
+            | Screen::Cluster
+            | Screen::Paper
+            | Screen::DupeSheet
+            | Screen::Uploads
+            | Screen::Maintenance => container(scrollable(container(content))).into(),
+        };
+        let content = stack![content, self.toasts_view()];
+        match &self.confirmation {
+            Some((question, _)) => stack![content, confirm_dialog(question)].into(),
+            None => content.into(),
+        }
+    }
+
+    /// Errors and notices in the bottom right corner, dismissed by clicking them
+    fn toasts_view(&self) -> Element<'_, Message> {
+        let mut toasts = column![].spacing(5).width(360);
+        for (i, toast) in self.toasts.iter().enumerate() {
+            let color = match toast.kind {
+                ToastKind::Info => Color::from_rgb8(0x7a, 0xa2, 0xf7),
+                ToastKind::Error => Color::from_rgb8(0xf7, 0x76, 0x8e),
+            };
+            let text = match toast.count {
+                1 => toast.text.clone(),
+                n => format!("{} ({}x)", toast.text, n),
+            };
+            toasts = toasts.push(
+                button(widget::text(text).color(Color::BLACK))
+                    .on_press(Message::DismissToast(i))
+                    .style(move |_, _| button::Style {
+                        background: Some(color.into()),
+                        ..Default::default()
+                    })
+                    .width(Length::Fill),
+            );
+        }
+        container(toasts)
+            .padding(10)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .align_x(Horizontal::Right)
+            .align_y(Vertical::Bottom)
+            .into()
+    }
+
+    /// The band of the rig frequency, flagged when transmitting there would be out of band
+    /// or in a segment not permitting the current mode
+    fn band_info(&self) -> Element<'_, Message> {
+        if self.rig_state.rig.is_none() {
+            return row![].into();
+        }
+        let class = self.mode_class();
+        let freq = self.rig_state.freq / 1e6;
+        let region = self.settings.region;
+        let (text, color) = match bandplan::check(region, freq, class) {
+            Allocation::InBand(band) => match bandplan::segment(region, freq) {
+                Some(Segment {
+                    max_watts: Some(watts),
+                    ..
+                }) => (format!("{} (max {}W)", band, watts), None),
+                _ => (band.to_string(), None),
+            },
+            Allocation::WrongSegment(band) => (
+                format!("{} (not a {:?} segment)", band, class),
+                Some(Color::from_rgb8(0xff, 0x9e, 0x64)),
+            ),
+            Allocation::OutOfBand => (
+                "OUT OF BAND".to_string(),
+                Some(Color::from_rgb8(0xf7, 0x76, 0x8e)),
+            ),
         };
-        let info = row![widget::text(format!(
-            "rig freq: {:.2}kHz, mode: {}, width: {}",
-            self.rig_state.freq / 1e3,
-            self.rig_state.mode,
-            self.rig_state.width
-        ))];
+        widget::text(text).color_maybe(color).into()
+    }
 
-        let content = column![controls, info, screen,];
+    /// Small S-meter, SWR and ALC bars, with the SWR flagged above the warning threshold
+    fn meters(&self) -> Element<'_, Message> {
+        if self.rig_state.rig.is_none() {
+            return row![].into();
+        }
+        let meters = self.rig_state.meters;
+        let bar = |label: String, range, value, color| {
+            row![
+                widget::text(label).size(12).color_maybe(color),
+                progress_bar(range, value).width(60).height(8),
+            ]
+            .spacing(4)
+            .align_y(Vertical::Center)
+        };
+        let mut meters_row = row![].spacing(10);
+        if let Some(db) = meters.strength {
+            // S0 to S9+60
+            meters_row = meters_row.push(bar(rig::s_units(db), -54.0..=60.0, db, None));
+        }
+        if let Some(swr) = meters.swr {
+            let color =
+                (swr > self.settings.swr_warning).then(|| Color::from_rgb8(0xf7, 0x76, 0x8e));
+            meters_row = meters_row.push(bar(format!("SWR {:.1}", swr), 1.0..=5.0, swr, color));
+        }
+        if let Some(alc) = meters.alc {
+            meters_row = meters_row.push(bar("ALC".to_string(), 0.0..=1.0, alc, None));
+        }
+        meters_row.into()
+    }
 
-        match self.screen {
-            Screen::Entry => content.into(),
-            Screen::LogList => container(scrollable(container(content))).into(),
+    /// Solar flux and A/K indices, marked as cached when they could not be fetched
+    fn solar_info(&self) -> Element<'_, Message> {
+        let Some(data) = &self.solar.data else {
+            return row![].into();
+        };
+        let mut text = format!("SFI {} A {} K {}", data.sfi, data.a_index, data.k_index);
+        if self.solar.stale {
+            text += &format!(" (cached, {})", data.updated);
+        }
+        widget::text(text).into()
+    }
+
+    /// A bar of the bands used this session, each as wide as the time spent on it
+    fn band_timeline(&self) -> Element<'_, Message> {
+        let mut bar = row![].height(18).width(Length::Fill);
+        let Some(log) = &self.cur_log else {
+            return bar.into();
+        };
+        let samples = match log.rig_history(self.session_start..) {
+            Ok(samples) => samples,
+            Err(e) => {
+                error!("Could not read rig history: {}", e);
+                return bar.into();
+            }
+        };
+        let spans = session::band_timeline(&samples, jiff::Timestamp::now());
+        let total = spans
+            .iter()
+            .map(|s| s.end.duration_since(s.start).as_secs())
+            .sum::<i64>()
+            .max(1);
+        for span in spans {
+            let secs = span.end.duration_since(span.start).as_secs();
+            let color = band_color(span.band);
+            let label = span.band.map(|b| b.name()).unwrap_or_default();
+            bar = bar.push(
+                container(widget::text(label).size(12).color(Color::BLACK))
+                    .style(move |_| container::Style {
+                        background: Some(color.into()),
+                        ..Default::default()
+                    })
+                    .width(Length::FillPortion((secs * 1000 / total).max(1) as u16))
+                    .height(Length::Fill),
+            );
         }
+        bar.into()
     }
 
     pub fn entry(&self) -> Element<'_, Message> {
         let mut row = row![].spacing(10);
-        let mut i = 0;
-        for f in &self.entry_fields {
+        // operator details filled in by lookups go on a smaller second row
+        let mut details = row![].spacing(10);
+        let scale = self.settings.entry_scale;
+        // a log open read-only is only browsed
+        let writable = !self.log_read_only();
+        for (i, f) in self.entry.fields.iter().enumerate() {
             let width = match f {
                 FieldType::WorkedCall => 230,
                 FieldType::SentRST => 100,
                 FieldType::RcvdRST => 100,
+                FieldType::GridSquare => 110,
+                FieldType::PrimaryAdminSubdiv => 80,
+                FieldType::Frequency => 130,
+                FieldType::TxPower => 90,
                 _ => 300,
             };
+            let exchange = matches!(
+                f,
+                FieldType::WorkedCall
+                    | FieldType::SentRST
+                    | FieldType::RcvdRST
+                    | FieldType::RcvdExchange
+            ) || self
+                .settings
+                .contest
+                .is_some_and(|c| c.exchange().contains(f));
+            let size = match exchange {
+                true => 42.0,
+                false => 20.0,
+            } * scale;
             let placeholder = match f {
-                FieldType::SentRST => "59",
-                FieldType::RcvdRST => "59",
-                _ => "",
+                FieldType::SentRST | FieldType::RcvdRST => {
+                    self.mode_class().default_rst().to_string()
+                }
+                FieldType::TxPower => self.default_power().unwrap_or_default(),
+                _ => String::new(),
             };
-            let col = column![].push(widget::text(f.to_string())).push(
+            let edit = move |v| Message::ContentChanged((f.clone(), v));
+            let mut col = column![].push(widget::text(f.label())).push(
                 text_input(
                     "",
-                    self.content.get(&f).get_or_insert(&placeholder.to_string()),
+                    self.entry
+                        .content
+                        .get(f)
+                        .get_or_insert(&placeholder.to_string()),
                 )
                 .id(i.to_string())
-                .on_input(move |v| Message::ContentChanged((f.clone(), v)))
+                .on_input_maybe(writable.then_some(edit))
                 .align_x(Horizontal::Right)
-                .size(42)
-                .width(width),
+                .size(size)
+                .width(width as f32 * scale),
             );
-            i += 1;
-            row = row.push(col);
+            if *f == FieldType::WorkedCall {
+                col = col.push(self.scp_matches());
+            }
+            match exchange {
+                true => row = row.push(col),
+                false => details = details.push(col),
+            }
         }
 
-        container(row).center_x(Length::Fill).into()
+        let mut macros = row![].spacing(5);
+        for (n, template) in self
+            .settings
+            .cw_macros
+            .iter()
+            .take(keyer::MACRO_COUNT)
+            .enumerate()
+        {
+            macros = macros.push(
+                button(widget::text(format!("F{} {}", n + 1, template)).size(12))
+                    .on_press(Message::SendMacro(n)),
+            );
+        }
+        let ptt = button(match self.rig_state.ptt {
+            true => "PTT ON",
+            false => "PTT",
+        })
+        .on_press(Message::TogglePtt);
+        let auto_cq = button(match self.auto_cq.is_running() {
+            true => "Stop CQ",
+            false => "Auto CQ",
+        })
+        .on_press(Message::ToggleAutoCq);
+
+        let mode = pick_list(
+            MODES,
+            self.entry.content.get(&FieldType::Mode).map(String::as_str),
+            |m: &str| Message::ModeSelected(m.to_string()),
+        )
+        .placeholder(match self.rig_state.rig {
+            Some(_) => "Rig mode",
+            None => "Mode",
+        });
+
+        let error = widget::text(self.entry_error.clone().unwrap_or_default());
+
+        let notes = match &self.note {
+            Some(note) => column![
+                text_input("Note, Enter to add, Escape to cancel", note)
+                    .id(NOTE_INPUT)
+                    .on_input(Message::NoteChanged)
+            ],
+            None => column![],
+        }
+        .push_maybe((!self.pending_notes.is_empty()).then(|| {
+            widget::text(format!(
+                "{} note(s) will be added to this QSO",
+                self.pending_notes.len()
+            ))
+        }));
+
+        let score = widget::text(match &self.contest_score {
+            Some(score) => format!(
+                "{}: {} QSOs ({} dupes), {} points x {} mults = {}{}",
+                score.contest.name(),
+                score.qsos,
+                score.dupes,
+                score.points,
+                score.mults(),
+                score.score(),
+                match self.entry_is_dupe() {
+                    true => " | DUPE",
+                    false => "",
+                }
+            ),
+            None if self.settings.contest.is_some() => {
+                "Set contest_start in the settings to keep score".to_string()
+            }
+            None => String::new(),
+        });
+        let rate = widget::text(match &self.contest_score {
+            Some(score) => {
+                let now = jiff::Timestamp::now();
+                format!(
+                    "Rate: {}/h last {} min, {}/h last hour{}",
+                    score.rate(now, contest::SHORT_RATE_MINUTES),
+                    contest::SHORT_RATE_MINUTES,
+                    score.rate(now, 60),
+                    match score.run_frequency() {
+                        Some(freq) => format!(" | running on {:.1}kHz", freq.khz()),
+                        None => String::new(),
+                    }
+                )
+            }
+            None => String::new(),
+        });
+
+        container(
+            column![
+                row,
+                details,
+                notes,
+                error,
+                self.cw_panel(),
+                row![mode, ptt, auto_cq, self.split_controls(), macros].spacing(10),
+                self.memory_buttons(),
+                score,
+                rate,
+                self.manual_time_controls(),
+                self.rover_controls(),
+                self.activation_controls(),
+                self.session_controls()
+            ]
+            .spacing(10),
+        )
+        .center_x(Length::Fill)
+        .into()
+    }
+
+    /// Quick split offsets for pileups, and a button back to simplex while split
+    fn split_controls(&self) -> Element<'_, Message> {
+        if self.rig_state.rig.is_none() {
+            return row![].into();
+        }
+        let mut controls = row![].spacing(5);
+        for khz in SPLIT_OFFSETS {
+            let offset = button(widget::text(format!("+{}", khz)));
+            controls = controls.push(offset.on_press(Message::SetSplit(Some(khz))));
+        }
+        if self.rig_state.split {
+            controls = controls.push(button("Simplex").on_press(Message::SetSplit(None)));
+        }
+        controls.into()
+    }
+
+    /// A button per memory in the settings, tuning the rig to it
+    fn memory_buttons(&self) -> Element<'_, Message> {
+        if self.rig_state.rig.is_none() {
+            return row![].into();
+        }
+        let mut buttons = row![].spacing(5);
+        for (i, memory) in self.settings.memories.iter().enumerate() {
+            let label = widget::text(memory.name.as_str()).size(12);
+            buttons = buttons.push(button(label).on_press(Message::MemorySelected(i)));
+        }
+        buttons.into()
+    }
+
+    /// Rows logged from the paper log so far above the row being typed
+    fn paper_log(&self) -> Element<'_, Message> {
+        const WIDTHS: [f32; 7] = [110.0, 70.0, 100.0, 80.0, 130.0, 60.0, 60.0];
+        let cells = |values: Vec<Element<'static, Message>>| {
+            row(values
+                .into_iter()
+                .zip(WIDTHS)
+                .map(|(cell, width)| container(cell).width(width).into()))
+            .spacing(5)
+        };
+        let mut grid = column![cells(
+            paper::COLUMNS
+                .iter()
+                .map(|c| widget::text(*c).into())
+                .collect()
+        )]
+        .spacing(5);
+        for logged in &self.paper.logged {
+            grid = grid.push(cells(
+                logged
+                    .0
+                    .iter()
+                    .map(|v| widget::text(v.clone()).into())
+                    .collect(),
+            ));
+        }
+        let inputs = row(self.paper.row.0.iter().enumerate().map(|(col, value)| {
+            text_input(paper::COLUMNS[col], value)
+                .id(format!("paper-{}", col))
+                .on_input(move |v| Message::PaperChanged(col, v))
+                .width(WIDTHS[col])
+                .into()
+        }))
+        .spacing(5);
+        column![
+            widget::text("Times in UTC. Enter logs the row, date to mode carry over to the next.")
+                .size(12),
+            grid.push(inputs),
+            widget::text(self.paper.error.clone().unwrap_or_default()),
+        ]
+        .spacing(10)
+        .padding(10)
+        .into()
+    }
+
+    /// Our grid as a rover, typed in unless the GPS has a fix, and the alert for a new grid
+    fn rover_controls(&self) -> Element<'_, Message> {
+        if !self.settings.rover {
+            return row![].into();
+        }
+        let grid: Element<'_, Message> = match &self.gps_grid {
+            Some(grid) => widget::text(format!("Rover grid {} from GPS", grid)).into(),
+            None => row![
+                widget::text("Rover grid"),
+                text_input("FN31", &self.rover_grid)
+                    .on_input(Message::RoverGridChanged)
+                    .width(80),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center)
+            .into(),
+        };
+        let mut controls = row![grid].spacing(10).align_y(Vertical::Center);
+        if let Some(alert) = &self.grid_alert {
+            controls = controls
+                .push(widget::text(alert).color(Color::from_rgb8(0xe0, 0xaf, 0x68)))
+                .push(button("OK").on_press(Message::DismissGridAlert));
+        }
+        controls.into()
+    }
+
+    /// Our own IOTA, SOTA and WWFF references, logged with every QSO while activating
+    fn activation_controls(&self) -> Element<'_, Message> {
+        if !self.settings.references {
+            return row![].into();
+        }
+        let mut controls = row![widget::text("Activating")]
+            .spacing(10)
+            .align_y(Vertical::Center);
+        for (field, value) in self.settings.my_references() {
+            let placeholder = match field {
+                FieldType::MyIota => "NA-046",
+                FieldType::MySotaRef => "W7A/AZ-001",
+                _ => "KFF-1234",
+            };
+            let label = widget::text(field.label().to_string());
+            let edit = move |v| Message::MyReferenceChanged(field.clone(), v);
+            controls = controls
+                .push(label)
+                .push(text_input(placeholder, value).on_input(edit).width(110));
+        }
+        controls.into()
+    }
+
+    /// The switch to log at a typed time, and the date and time fields when it is on
+    fn manual_time_controls(&self) -> Element<'_, Message> {
+        let mut controls = row![
+            widget::checkbox("Manual time (UTC)", self.manual_time.is_some())
+                .on_toggle(Message::ToggleManualTime)
+        ]
+        .spacing(10)
+        .align_y(Vertical::Center);
+        if let Some(manual) = &self.manual_time {
+            controls = controls
+                .push(
+                    text_input("YYYY-MM-DD", &manual.date)
+                        .on_input(Message::ManualDateChanged)
+                        .width(120),
+                )
+                .push(
+                    text_input("HHMM", &manual.time)
+                        .on_input(Message::ManualTimeChanged)
+                        .width(70),
+                )
+                .push(widget::text("Ctrl+Up/Down: 1 minute, with Shift: 1 hour").size(12));
+        }
+        controls.into()
+    }
+
+    /// The running session, its goals and a button to end it, or the fields to start one
+    fn session_controls(&self) -> Element<'_, Message> {
+        match &self.session {
+            Some((_, session)) => {
+                let mut controls = row![
+                    widget::text(format!(
+                        "{} session {} since {}",
+                        session.kind,
+                        session.name,
+                        session.start.strftime("%H:%MZ")
+                    )),
+                    button("End session").on_press_maybe(self.writable(Message::ToggleSession)),
+                ];
+                let goals = self.settings.goals.iter();
+                for goal in goals.filter(|g| g.applies_to(session.kind)) {
+                    let progress = goal.progress(&self.session_stats);
+                    let color = progress
+                        .reached()
+                        .then(|| Color::from_rgb8(0x9e, 0xce, 0x6a));
+                    controls = controls.push(
+                        row![
+                            progress_bar(0.0..=progress.target as f32, progress.done as f32)
+                                .width(60)
+                                .height(8),
+                            widget::text(progress.to_string()).color_maybe(color),
+                        ]
+                        .spacing(4)
+                        .align_y(Vertical::Center),
+                    );
+                }
+                controls
+            }
+            None => row![
+                pick_list(
+                    SessionKind::ALL,
+                    Some(self.session_kind),
+                    Message::SessionKindSelected
+                ),
+                text_input("Contest or park", &self.session_name)
+                    .on_input(Message::SessionNameChanged)
+                    .width(200),
+                button("Start session").on_press_maybe(self.writable(Message::ToggleSession)),
+            ],
+        }
+        .spacing(10)
+        .into()
+    }
+
+    /// Known calls matching the partial call being entered, shown below the call field
+    fn scp_matches(&self) -> Element<'_, Message> {
+        let mut matches = column![];
+        let (Some(scp), Some(partial)) =
+            (&self.scp, self.entry.content.get(&FieldType::WorkedCall))
+        else {
+            return matches.into();
+        };
+        // single characters match nearly every call
+        if partial.chars().count() < 2 {
+            return matches.into();
+        }
+        for call in scp.matches(partial, SCP_MATCHES) {
+            matches = matches.push(
+                button(widget::text(call).size(14))
+                    .style(button::text)
+                    .padding(2)
+                    .on_press(Message::ScpSelected(call.to_string())),
+            );
+        }
+        matches.into()
     }
 
     pub fn log_list(&self) -> Element<'_, Message> {
@@ -250,19 +1114,160 @@ impl State {
         for f in &disp_fields {
             table.push(vec![widget::text(f.to_string()).into()]);
         }
-        if let Some(log) = &self.cur_log {
-            for record in log.get_records() {
+        // looked up from the call unless logged with the QSO
+        table.push(vec![widget::text("Country").into()]);
+        table.push(vec![widget::text("Cont").into()]);
+        // QSO recordings can only be played back with audio support
+        let audio = cfg!(feature = "audio");
+        if audio {
+            table.push(vec![widget::text("Audio").into()]);
+        }
+        let tz = match self.settings.local_time {
+            true => TimeZone::system(),
+            false => TimeZone::UTC,
+        };
+        let mut summary = String::new();
+        if self.cur_log.is_some() {
+            let mut stats = Stats::default();
+            let records: Box<dyn Iterator<Item = (&usize, &LogRecord)>> = match &self.search_results
+            {
+                Some(found) => Box::new(
+                    found
+                        .iter()
+                        .rev()
+                        .filter_map(|i| Some((i, self.records.get(i)?))),
+                ),
+                None => Box::new(self.records.iter().rev()),
+            };
+            let filter = Filter {
+                continents: self.continent.iter().cloned().collect(),
+                ..Default::default()
+            };
+            let prefixes = self.prefixes.as_ref();
+            let records =
+                records.filter(|(_, r)| filter.matches_with(r, &self.countries, prefixes));
+            for (&idx, record) in records {
+                stats.add(record);
                 for (i, ty) in disp_fields.iter().enumerate() {
-                    match record.get_field(ty) {
-                        Some(v) => table[i].push(widget::text(v.to_string()).into()),
-                        None => table[i].push(widget::text("").into()),
+                    let value = match ty {
+                        FieldType::Timestamp => record.display_time(&tz),
+                        FieldType::Mode => record.display_mode(),
+                        _ => record.get_field(ty),
+                    };
+                    match (value, ty) {
+                        // the call opens the QSO's details
+                        (Some(v), FieldType::WorkedCall) => table[i].push(
+                            button(widget::text(v))
+                                .padding(0)
+                                .style(button::text)
+                                .on_press(Message::RecordSelected(idx))
+                                .into(),
+                        ),
+                        (Some(v), _) => table[i].push(widget::text(v.to_string()).into()),
+                        (None, _) => table[i].push(widget::text("").into()),
                     }
                 }
+                let (country, continent) = self
+                    .countries
+                    .country(record, prefixes)
+                    .map(|c| (c.name, c.continent))
+                    .unwrap_or_default();
+                table[disp_fields.len()].push(widget::text(country).into());
+                table[disp_fields.len() + 1].push(widget::text(continent).into());
+                if audio {
+                    table[disp_fields.len() + 2].push(
+                        match record.get_field(&FieldType::AudioRef) {
+                            Some(path) => button(widget::text("Play"))
+                                .padding(0)
+                                .style(button::text)
+                                .on_press(Message::PlayAudio(path))
+                                .into(),
+                            None => widget::text("").into(),
+                        },
+                    );
+                }
+            }
+            summary = format!(
+                "{} QSOs | {} | {}",
+                stats.qsos,
+                stats
+                    .by_band
+                    .iter()
+                    .map(|(band, n)| format!("{}: {}", band, n))
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                stats
+                    .by_mode
+                    .iter()
+                    .map(|(mode, n)| format!("{}: {}", mode, n))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            );
+            if let Some((_, session)) = &self.session {
+                let qsos = self.session_stats.qsos;
+                summary += &format!(" | {} QSOs this {} session", qsos, session.kind);
+            }
+            for (service, pending) in &self.uploads {
+                if !pending.is_empty() {
+                    summary += &format!(" | {} waiting for {}", pending.len(), service);
+                }
+            }
+            if self.dxcc_progress.entities_worked() > 0 {
+                summary += &format!(
+                    " | DXCC {} worked, {} confirmed",
+                    self.dxcc_progress.entities_worked(),
+                    self.dxcc_progress.entities_confirmed()
+                );
             }
         }
+        let logs = row![
+            text_input("Log path", &self.log_path)
+                .on_input(Message::LogPathChanged)
+                .width(300),
+            text_input("Passphrase (encrypted logs)", &self.passphrase)
+                .on_input(Message::PassphraseChanged)
+                .secure(true)
+                .width(200),
+            button("New log").on_press(Message::InitLog),
+            button("Open log").on_press(Message::OpenLog(self.log_path.trim().to_string())),
+            pick_list(
+                self.settings.recent_logs.as_slice(),
+                None::<String>,
+                Message::OpenLog
+            )
+            .placeholder("Recent logs"),
+        ]
+        .spacing(10)
+        .align_y(Vertical::Center);
         let buttons = row![
-            button("Init new Log").on_press(Message::InitLog),
-            button("Import ADIF").on_press(Message::ImportADIF),
+            button("Import ADIF")
+                .on_press_maybe(self.writable(Message::Import("testlog2.adi".into()))),
+            button("Paste ADIF").on_press_maybe(self.writable(Message::PasteADIF)),
+            button("Import FLE").on_press_maybe(self.writable(Message::ImportFLE)),
+            pick_list(
+                formats::EXPORTERS
+                    .iter()
+                    .map(|e| e.name())
+                    .collect::<Vec<&str>>(),
+                Some(self.settings.export_format.as_str()),
+                |f: &str| Message::ExportFormatSelected(f.to_string()),
+            ),
+            button("Export").on_press(Message::Export),
+            button("Print log").on_press(Message::PrintLog),
+            button("Rate sheet").on_press(Message::RateSheet),
+            button("Counties").on_press(Message::CountyReport),
+            button("Update call history").on_press(Message::UpdateCallHistory),
+            button("LoTW QSLs").on_press_maybe(self.writable(Message::FetchLotw)),
+            button("QSL labels").on_press(Message::PrintQslLabels),
+            button("Normalize").on_press_maybe(self.writable(Message::NormalizeLog)),
+            button("Verify").on_press(Message::VerifyLog),
+            button("Repair").on_press_maybe((self.log_damaged && !self.read_only).then(|| {
+                Message::Confirm(
+                    "Repair the log? Unreadable records are moved aside and the QSOs renumbered."
+                        .to_string(),
+                    Box::new(Message::RepairLog),
+                )
+            })),
             button("Init hamlib").on_press(Message::InitHamlib),
             button("Open rig").on_press(Message::OpenRig)
         ];
@@ -271,7 +1276,463 @@ impl State {
             let y = Column::from_vec(x);
             row = row.push(y);
         }
-        column![buttons, row,].into()
+        let search = text_input("Search calls, names, QTH, comments...", &self.search)
+            .on_input(Message::SearchChanged)
+            .width(400);
+        let local_time =
+            widget::checkbox("Local time", self.settings.local_time).on_toggle(Message::LocalTime);
+        let continent = pick_list(
+            ["All"]
+                .into_iter()
+                .chain(CONTINENTS.iter().copied())
+                .collect::<Vec<&str>>(),
+            Some(self.continent.as_deref().unwrap_or("All")),
+            |c: &str| Message::ContinentSelected((c != "All").then(|| c.to_string())),
+        );
+        column![
+            logs,
+            buttons,
+            row![search, continent, local_time].spacing(10),
+            widget::text(&self.log_status),
+            self.export_problems(),
+            self.record_details_view(),
+            widget::text(summary),
+            row,
+        ]
+        .into()
+    }
+
+    /// The fields and provenance of the selected QSO
+    fn record_details_view(&self) -> Element<'_, Message> {
+        let Some((idx, details)) = &self.record_details else {
+            return column![].into();
+        };
+        if let Some(edit) = &self.record_edit {
+            return self.record_edit_view(edit);
+        }
+        let mut fields = column![].spacing(2);
+        for line in details {
+            fields = fields.push(widget::text(line));
+        }
+        let buttons = row![
+            button("Edit").on_press_maybe(self.writable(Message::EditRecord(*idx))),
+            button("Close").on_press(Message::CloseRecord),
+        ]
+        .spacing(10);
+        column![fields, buttons].spacing(5).into()
+    }
+
+    /// The fields of the QSO being edited, and after a change elsewhere what it changed
+    fn record_edit_view<'a>(&self, edit: &'a RecordEdit) -> Element<'a, Message> {
+        let mut fields = column![].spacing(2);
+        for (ty, val) in &edit.fields {
+            let ty = ty.clone();
+            fields = fields.push(
+                row![
+                    widget::text(format!("{}", ty)).width(200),
+                    text_input("", val)
+                        .on_input(move |val| Message::RecordFieldChanged(ty.clone(), val)),
+                ]
+                .spacing(10),
+            );
+        }
+        if edit.conflict.is_none() {
+            let buttons = row![
+                button("Save").on_press_maybe(self.writable(Message::SaveRecord)),
+                button("Cancel").on_press(Message::CancelEdit),
+            ]
+            .spacing(10);
+            return column![fields, buttons].spacing(5).into();
+        }
+        let mut changed = column![widget::text("Changed elsewhere meanwhile:")].spacing(2);
+        for line in edit.changed_elsewhere() {
+            changed = changed.push(widget::text(line));
+        }
+        let buttons = row![
+            button("Reload").on_press(Message::ReloadRecord),
+            button("Overwrite").on_press_maybe(self.writable(Message::OverwriteRecord)),
+            button("Cancel").on_press(Message::CancelEdit),
+        ]
+        .spacing(10);
+        column![fields, changed, buttons].spacing(5).into()
+    }
+
+    /// The QSOs the export check found problems with, and whether to export without them
+    fn export_problems(&self) -> Element<'_, Message> {
+        if self.export_problems.is_empty() {
+            return column![].into();
+        }
+        let mut problems = column![].spacing(2);
+        for p in &self.export_problems {
+            problems = problems.push(widget::text(format!(
+                "#{} {}: {}",
+                p.idx,
+                p.call.as_deref().unwrap_or("?"),
+                p.reasons.join(", ")
+            )));
+        }
+        column![
+            problems,
+            row![
+                button("Export without them").on_press(Message::ExportExcluding),
+                button("Cancel").on_press(Message::CancelExport),
+            ]
+            .spacing(10),
+        ]
+        .spacing(5)
+        .into()
+    }
+
+    pub fn map(&self) -> Element<'_, Message> {
+        let grids = self
+            .map_points
+            .iter()
+            .filter(|p| p.kind == PointKind::Grid)
+            .count();
+        let mut header = row![
+            widget::checkbox("Color by band", self.map_by_band).on_toggle(Message::MapByBand),
+            widget::text(format!(
+                "{} grid/band and {} entity/band points",
+                grids,
+                self.map_points.len() - grids
+            )),
+        ]
+        .spacing(10);
+        if self.map_by_band {
+            for band in Band::all() {
+                if self.map_points.iter().any(|p| p.band == Some(band)) {
+                    header = header.push(widget::text(band.name()).color(band_color(Some(band))));
+                }
+            }
+        }
+        if gridsquare_center(&self.settings.my_grid).is_err() {
+            header = header.push(widget::text(
+                "Set my_grid in the settings to center the map on your QTH",
+            ));
+        }
+        let mut vucc = row![widget::text("VUCC grids:")].spacing(15);
+        for band in self.vucc.bands() {
+            vucc = vucc.push(widget::text(format!(
+                "{} {} worked, {}/{} confirmed",
+                band.name(),
+                self.vucc.grids_worked(band),
+                self.vucc.grids_confirmed(band),
+                VuccProgress::target(band)
+            )));
+        }
+        if self.vucc.bands().next().is_none() {
+            vucc = vucc.push(widget::text("none worked on 6m and up"));
+        }
+        let map = map::WorldMap {
+            center: self.map_center(),
+            points: &self.map_points,
+            band_color: self
+                .map_by_band
+                .then_some(band_color as fn(Option<Band>) -> Color),
+            time: self.map_time,
+        };
+        column![
+            header,
+            vucc,
+            canvas(map).width(Length::Fill).height(Length::Fill)
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    /// The decoded CW, calls in it can be clicked to fill in the call field
+    fn cw_panel(&self) -> Element<'_, Message> {
+        let mut panel = row![].spacing(6);
+        if self.cw_text.is_empty() {
+            return panel.into();
+        }
+        panel = panel.push(widget::text("CW:"));
+        for word in self.cw_text.split_whitespace() {
+            panel = match callsign::validate_callsign(word) {
+                Ok(call) => panel.push(
+                    button(widget::text(word))
+                        .padding(0)
+                        .style(button::text)
+                        .on_press(Message::DecodedCallSelected(call)),
+                ),
+                Err(_) => panel.push(widget::text(word)),
+            };
+        }
+        panel.into()
+    }
+
+    /// Spots and stations worked lately around the rig frequency, worked ones are not
+    /// shown again as spots
+    fn band_map(&self) -> Element<'_, Message> {
+        let rig_khz = self.rig_state.freq / 1e3;
+        let Some(band) = Band::from_freq_mhz(rig_khz / 1e3) else {
+            return row![].into();
+        };
+        let (low, high) = band.range_mhz();
+        let range = bandmap::range_khz(rig_khz, (low * 1e3, high * 1e3));
+        let spots = self
+            .cluster
+            .spots
+            .iter()
+            .filter(|spot| {
+                !self
+                    .worked_recently
+                    .iter()
+                    .any(|(_, w)| w.call == spot.call)
+            })
+            .map(|spot| bandmap::Station {
+                call: spot.call.clone(),
+                freq_khz: spot.freq_khz,
+                kind: bandmap::StationKind::Spot,
+            });
+        let mut stations: Vec<bandmap::Station> = spots
+            .chain(self.worked_recently.iter().map(|(_, w)| w.clone()))
+            .filter(|s| (range.0..=range.1).contains(&s.freq_khz))
+            .collect();
+        stations.sort_by(|a, b| a.freq_khz.total_cmp(&b.freq_khz));
+        let map = bandmap::BandMap {
+            range_khz: range,
+            rig_khz,
+            stations,
+            on_select: |s| Message::BandMapSelected(s.call.clone(), s.freq_khz, s.kind),
+        };
+        canvas(map).width(220).height(Length::Fill).into()
+    }
+
+    /// Calls worked in the running contest in one column per band, dupes left out
+    pub fn dupe_sheet(&self) -> Element<'_, Message> {
+        let Some(score) = &self.contest_score else {
+            return widget::text(
+                "Choose a contest and its contest_start in the settings to keep a dupe sheet",
+            )
+            .into();
+        };
+        let mut bands = row![].spacing(20);
+        for (band, calls) in score.dupe_sheet() {
+            let name = band.map_or("?", |b| b.name());
+            let mut col = column![widget::text(format!("{} ({})", name, calls.len()))];
+            for call in calls {
+                col = col.push(widget::text(call));
+            }
+            bands = bands.push(col.width(100));
+        }
+        bands.into()
+    }
+
+    /// The uploads waiting for each online log, with why the failed ones failed
+    fn upload_status(&self) -> Element<'_, Message> {
+        let time = |t: jiff::Timestamp| t.strftime("%Y-%m-%d %H:%M:%S UTC").to_string();
+        let mut list = column![].spacing(2);
+        for service in Service::ALL {
+            let pending = self.uploads.get(&service).map_or(&[][..], Vec::as_slice);
+            let state = match (pending.len(), self.uploading.contains(&service)) {
+                (0, _) => "up to date".to_string(),
+                (n, true) => format!("{} waiting, uploading", n),
+                (n, false) => format!("{} waiting", n),
+            };
+            list = list.push(widget::text(format!("{}: {}", service, state)).size(18));
+            for (id, upload) in pending {
+                let call = self
+                    .cur_log
+                    .as_ref()
+                    .and_then(|log| log.get_record_by_id(*id))
+                    .and_then(|r| r.get_field(&FieldType::WorkedCall));
+                let mut line = format!(
+                    "{}, queued {}",
+                    call.as_deref().unwrap_or("deleted QSO"),
+                    time(upload.queued)
+                );
+                if let Some(error) = &upload.error {
+                    line += &format!(
+                        ", {} failed attempts, next at {}: {}",
+                        upload.attempts,
+                        time(upload.next_try),
+                        error
+                    );
+                }
+                list = list.push(widget::text(line));
+            }
+        }
+        let retry = button("Retry now").on_press_maybe(self.writable(Message::RetryUploads));
+        column![list, retry].spacing(10).into()
+    }
+
+    fn maintenance(&self) -> Element<'_, Message> {
+        let diagnostics = button("Diagnostics").on_press(Message::ExportDiagnostics);
+        let Some(log) = &self.cur_log else {
+            return column![
+                widget::text("No log is open"),
+                diagnostics,
+                widget::text(&self.log_status)
+            ]
+            .spacing(10)
+            .into();
+        };
+        let mut kind = Vec::new();
+        if log.is_encrypted() {
+            kind.push("encrypted, not the service passwords in the settings");
+        }
+        if log.is_read_only() {
+            kind.push("read-only, a copy in memory");
+        }
+        let path = self.settings.recent_logs.first().map_or("", String::as_str);
+        let info = column![
+            widget::text(match kind.is_empty() {
+                true => path.to_string(),
+                false => format!("{} ({})", path, kind.join(", ")),
+            })
+            .size(18),
+            widget::text(format!("{} QSOs", self.records.len())),
+            widget::text(match self.disk_usage {
+                Some(bytes) => format!("{} on disk", size_text(bytes)),
+                None => "Size on disk unknown".to_string(),
+            }),
+            widget::text(
+                "The log keeps old versions of edited and deleted QSOs until it reuses their \
+                 space. Compacting rewrites it without them."
+            ),
+        ]
+        .spacing(5);
+        let actions = row![
+            button("Refresh").on_press(Message::MaintenanceSelected),
+            button("Flush now").on_press(Message::FlushLog),
+            button("Compact now").on_press_maybe((!self.read_only).then(|| {
+                Message::Confirm(
+                    "Compact the log? It is closed until compacting is done.".to_string(),
+                    Box::new(Message::CompactLog),
+                )
+            })),
+            diagnostics,
+        ]
+        .spacing(10);
+        column![info, actions, widget::text(&self.log_status)]
+            .spacing(10)
+            .into()
+    }
+
+    pub fn cluster(&self) -> Element<'_, Message> {
+        let connect = button(match self.cluster.enabled {
+            true => "Disconnect",
+            false => "Connect",
+        })
+        .on_press(Message::ToggleCluster);
+        let header = row![connect, widget::text(&self.cluster.status)].spacing(10);
+
+        let mut spots = Column::new();
+        for (i, spot) in self.cluster.spots.iter().enumerate() {
+            let color = match self.spot_need(spot) {
+                Some(Need::NewOne) => Some(Color::from_rgb8(0xf7, 0x76, 0x8e)),
+                Some(Need::NewBand) => Some(Color::from_rgb8(0xff, 0x9e, 0x64)),
+                Some(Need::NewMode) => Some(Color::from_rgb8(0xe0, 0xaf, 0x68)),
+                Some(Need::Worked) | None => None,
+            };
+            let cell = |v: String| widget::text(v).color_maybe(color);
+            spots = spots.push(
+                button(
+                    row![
+                        cell(spot.time.clone()).width(60),
+                        cell(format!("{:.1}", spot.freq_khz)).width(90),
+                        cell(spot.call.clone()).width(120),
+                        cell(spot.comment.clone()).width(Length::Fill),
+                        cell(spot.spotter.clone()).width(120),
+                    ]
+                    .spacing(10),
+                )
+                .style(button::text)
+                .on_press(Message::SpotSelected(i)),
+            );
+        }
+        column![header, spots].spacing(10).into()
+    }
+
+    /// Checks a spot against the log's DXCC progress, None if the call can't be resolved
+    fn spot_need(&self, spot: &cluster::Spot) -> Option<Need> {
+        let entity = self.prefixes.as_ref()?.lookup(&spot.call)?.entity;
+        let band = Band::from_freq_mhz(spot.freq_khz / 1e3);
+        Some(
+            self.dxcc_progress
+                .need(&entity.prefix, band, spot.mode_class()),
+        )
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let mut subs = vec![
+            self.rig_update_timer(),
+            self.keyboard_listener(),
+            window::close_requests().map(|_| Message::CloseRequested),
+            iced::time::every(Duration::from_secs(5)).map(|_| Message::DraftTick),
+        ];
+        if self.voice_active() {
+            subs.push(iced::time::every(Duration::from_millis(100)).map(|_| Message::VoiceTick));
+        }
+        if self.cw_decoding() {
+            subs.push(iced::time::every(Duration::from_millis(200)).map(|_| Message::CwTick));
+        }
+        if self.settings.fetch_solar {
+            subs.push(iced::time::every(Duration::from_secs(60)).map(|_| Message::SolarTick));
+        }
+        if matches!(self.screen, Screen::Map) {
+            subs.push(iced::time::every(Duration::from_secs(60)).map(|_| Message::MapTick));
+        }
+        if self.uploads.values().any(|pending| !pending.is_empty()) {
+            subs.push(iced::time::every(Duration::from_secs(5)).map(|_| Message::UploadTick));
+        }
+        if !self.toasts.is_empty() {
+            subs.push(iced::time::every(Duration::from_secs(1)).map(|_| Message::ToastTick));
+        }
+        if let Some(log) = &self.cur_log {
+            subs.push(log_events(log.clone(), self.log_generation).map(Message::LogChanged));
+            #[cfg(feature = "http")]
+            if !self.settings.http_listen.is_empty() {
+                subs.push(
+                    http::serve(
+                        self.settings.http_listen.clone(),
+                        self.settings.http_token.clone(),
+                        log.clone(),
+                        self.log_generation,
+                    )
+                    .map(Message::Http),
+                );
+            }
+            if !self.settings.sync_listen.is_empty() {
+                subs.push(
+                    sync::listen(
+                        self.settings.sync_listen.clone(),
+                        self.settings.sync_token.clone(),
+                        log.clone(),
+                        self.log_generation,
+                    )
+                    .map(Message::Sync),
+                );
+            }
+            if !self.settings.sync_peer.is_empty() {
+                subs.push(
+                    sync::connect(
+                        self.settings.sync_peer.clone(),
+                        self.settings.sync_token.clone(),
+                        log.clone(),
+                        self.log_generation,
+                    )
+                    .map(Message::Sync),
+                );
+            }
+        }
+        if !self.settings.gps_source.is_empty() {
+            subs.push(gps::watch(self.settings.gps_source.clone()).map(Message::Gps));
+        }
+        if !self.settings.n1mm_listen.is_empty() {
+            subs.push(n1mm::listen(self.settings.n1mm_listen.clone()).map(Message::N1mm));
+        }
+        if self.cluster.enabled {
+            subs.push(
+                cluster::connect(
+                    self.settings.cluster_node.clone(),
+                    self.settings.my_call.clone(),
+                )
+                .map(Message::Cluster),
+            );
+        }
+        Subscription::batch(subs)
     }
 
     fn rig_update_timer(&self) -> iced::Subscription<Message> {
@@ -279,38 +1740,142 @@ impl State {
     }
 
     fn keyboard_listener(&self) -> iced::Subscription<Message> {
-        event::listen_with(|event, status, _| match (event, status) {
-            (
-                iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
-                    key: Key::Named(Named::Tab),
-                    modifiers: Modifiers::SHIFT,
-                    ..
-                }),
-                Status::Ignored,
-            ) => Some(Message::KeyPressed("TabShift".into())),
-            (
-                iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
-                    key: Key::Named(Named::Tab),
-                    ..
-                }),
-                Status::Ignored,
-            ) => Some(Message::KeyPressed("Tab".into())),
+        event::listen_with(|event, status, _| match event {
+            iced::Event::Keyboard(iced::keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+                Some(Message::KeyPressed(KeyEvent {
+                    key,
+                    modifiers,
+                    captured: status == Status::Captured,
+                }))
+            }
             _ => None,
         })
     }
 }
 
-fn theme(_state: &State) -> Theme {
-    Theme::TokyoNight
+/// Asks `question` over the dimmed screen, which takes no input until it is answered
+fn confirm_dialog(question: &str) -> Element<'_, Message> {
+    let dialog = container(
+        column![
+            widget::text(question),
+            row![
+                button("Confirm").on_press(Message::Confirmed),
+                button("Cancel").on_press(Message::CancelConfirm),
+            ]
+            .spacing(10),
+        ]
+        .spacing(10),
+    )
+    .padding(20)
+    .style(container::bordered_box);
+    opaque(
+        mouse_area(center(opaque(dialog)).style(|_| {
+            container::Style {
+                background: Some(
+                    Color {
+                        a: 0.7,
+                        ..Color::BLACK
+                    }
+                    .into(),
+                ),
+                ..Default::default()
+            }
+        }))
+        .on_press(Message::CancelConfirm),
+    )
+}
+
+/// Forwards the change events of `log`, from a blocking task since they arrive on a
+/// std channel. `generation` tells the logs opened during a run apart.
+fn log_events(log: Log, generation: u64) -> Subscription<LogEvent> {
+    Subscription::run_with_id(
+        ("log-events", generation),
+        iced::stream::channel(100, move |mut output| async move {
+            let mut events = log.subscribe();
+            while let Ok((rx, Ok(event))) = tokio::task::spawn_blocking(move || {
+                let event = events.recv();
+                (events, event)
+            })
+            .await
+            {
+                events = rx;
+                if output.send(event).await.is_err() {
+                    break;
+                }
+            }
+        }),
+    )
+}
+
+fn band_color(band: Option<Band>) -> Color {
+    match band {
+        Some(band) => {
+            let n = Band::all().position(|b| b == band).unwrap_or_default();
+            let (r, g, b) = BAND_COLORS[n % BAND_COLORS.len()];
+            Color::from_rgb8(r, g, b)
+        }
+        None => Color::from_rgb8(0x56, 0x5f, 0x89),
+    }
+}
+
+fn theme(state: &State) -> Theme {
+    theme::by_name(&state.settings.theme)
+}
+
+/// Logger for amateur radio operating
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// Keep the settings and logs in this directory instead of the platform's
+    #[arg(long)]
+    data_dir: Option<PathBuf>,
+    /// Log to open instead of the one used last, created if there is none
+    #[arg(long)]
+    log: Option<PathBuf>,
+    /// File to import into the log on startup, in any format of `formats::IMPORTERS`,
+    /// e.g. ADIF or ADX
+    #[arg(long)]
+    import: Option<PathBuf>,
+    /// Hamlib model number of the rig, e.g. 3073 for an IC-7300
+    #[arg(long)]
+    rig_model: Option<u32>,
+    /// Serial port of the rig, or host:port of rigctld
+    #[arg(long)]
+    rig_port: Option<String>,
+    /// Browse the log without changing it, e.g. a backup
+    #[arg(long)]
+    read_only: bool,
+    /// Try veelog with a simulated rig and a generated sample log, kept apart from the
+    /// real settings and logs and started afresh every time
+    #[arg(long, conflicts_with_all = ["data_dir", "log", "import", "read_only"])]
+    demo: bool,
 }
 
 fn main() -> anyhow::Result<()> {
-    simple_logging::log_to_file(
-        format!("{}.log", env!("CARGO_PKG_NAME")),
-        log::LevelFilter::Warn,
-    )?;
+    let args = Args::parse();
+    let data_dir = match args.demo {
+        true => Some(demo::data_dir()?),
+        false => args.data_dir.clone(),
+    };
+    let mut paths = Paths::new(data_dir);
+    let settings = match args.demo {
+        true => Ok(demo::settings()),
+        false => paths.load_settings(),
+    };
+    fs::create_dir_all(&paths.data_dir)?;
+    let crash_report = crash::take_report(&paths);
+    simple_logging::log_to_file(crash::app_log(&paths), log::LevelFilter::Warn)?;
+    crash::install_hook(&paths);
+    let settings = settings.unwrap_or_else(|e| {
+        error!("Could not load settings, using defaults: {}", e);
+        Settings::default()
+    });
 
-    let mut window = window::Settings::default();
+    let mut window = window::Settings {
+        // closing is handled by `Message::CloseRequested`
+        exit_on_close_request: false,
+        ..Default::default()
+    };
     match window::icon::from_file_data(
         include_bytes!("../../resources/images/veelog.ico"),
         Some(image::ImageFormat::Ico),
@@ -319,11 +1884,12 @@ fn main() -> anyhow::Result<()> {
         Err(_) => error!("Could not load window icon!"),
     }
 
-    Ok(iced::application(State::title, State::update, State::view)
-        .subscription(State::rig_update_timer)
-        .subscription(State::keyboard_listener)
-        .theme(theme)
-        .window(window)
-        .centered()
-        .run()?)
+    Ok(
+        iced::application(|state: &State| state.title(), State::update, State::view)
+            .subscription(State::subscription)
+            .theme(theme)
+            .window(window)
+            .centered()
+            .run_with(move || State::new(paths, settings, args, crash_report))?,
+    )
 }
@@ -0,0 +1,254 @@
+use std::f64::consts::PI;
+
+use iced::{
+    Color, Point, Rectangle, Renderer, Size, Theme, mouse,
+    widget::canvas::{self, Frame, Geometry, Path, Stroke},
+};
+use jiff::Timestamp;
+use util::band::Band;
+
+/// What a point on the map stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PointKind {
+    Grid,
+    /// The location cty.dat gives for a worked entity
+    Entity,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapPoint {
+    pub kind: PointKind,
+    /// Latitude and longitude in degrees
+    pub pos: (f64, f64),
+    pub band: Option<Band>,
+}
+
+/// Azimuthal equidistant world map centered on our QTH. Straight lines from the
+/// center are great circle paths and the distance from the center is true, so the
+/// edge of the map is the antipode.
+pub struct WorldMap<'a> {
+    pub center: (f64, f64),
+    pub points: &'a [MapPoint],
+    /// Colors points by band when set, otherwise all points get the theme's primary color
+    pub band_color: Option<fn(Option<Band>) -> Color>,
+    /// Time of the day/night terminator drawn over the map
+    pub time: Timestamp,
+}
+
+/// Cells per side of the grid the night side is shaded in
+const NIGHT_CELLS: usize = 80;
+
+/// Projects `pos` onto the unit disc of a map centered on `center`, x east and y north.
+/// Both are latitude and longitude in degrees.
+pub fn project(center: (f64, f64), pos: (f64, f64)) -> (f64, f64) {
+    let (lat0, lon0) = (center.0.to_radians(), center.1.to_radians());
+    let (lat, lon) = (pos.0.to_radians(), pos.1.to_radians());
+    let dlon = lon - lon0;
+    let cos_c = lat0.sin() * lat.sin() + lat0.cos() * lat.cos() * dlon.cos();
+    let c = cos_c.clamp(-1.0, 1.0).acos();
+    if c.sin().abs() < 1e-9 {
+        // the center itself, or its antipode, which is the whole edge of the map
+        return match c < 1.0 {
+            true => (0.0, 0.0),
+            false => (0.0, -1.0),
+        };
+    }
+    let k = c / c.sin() / PI;
+    (
+        k * lat.cos() * dlon.sin(),
+        k * (lat0.cos() * lat.sin() - lat0.sin() * lat.cos() * dlon.cos()),
+    )
+}
+
+/// The inverse of `project`, None outside of the unit disc
+pub fn unproject(center: (f64, f64), (x, y): (f64, f64)) -> Option<(f64, f64)> {
+    let rho = x.hypot(y);
+    if rho > 1.0 {
+        return None;
+    }
+    if rho < 1e-12 {
+        return Some(center);
+    }
+    let (lat0, lon0) = (center.0.to_radians(), center.1.to_radians());
+    let c = rho * PI;
+    let lat = (c.cos() * lat0.sin() + y * c.sin() * lat0.cos() / rho).asin();
+    let lon = lon0 + (x * c.sin()).atan2(rho * lat0.cos() * c.cos() - y * lat0.sin() * c.sin());
+    Some((lat.to_degrees(), normalize_lon(lon.to_degrees())))
+}
+
+fn normalize_lon(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// The point where the sun is overhead at `time`, as latitude and longitude in degrees.
+/// Uses the low precision formulas of the Astronomical Almanac, good to about 0.01°.
+pub fn subsolar_point(time: Timestamp) -> (f64, f64) {
+    // days since J2000.0
+    let n = time.as_millisecond() as f64 / 86_400_000.0 - 10_957.5;
+    let mean_lon = 280.460 + 0.985_647_4 * n;
+    let anomaly = (357.528 + 0.985_600_3 * n).to_radians();
+    let ecliptic_lon =
+        (mean_lon + 1.915 * anomaly.sin() + 0.020 * (2.0 * anomaly).sin()).to_radians();
+    let obliquity = (23.439 - 0.000_000_4 * n).to_radians();
+    let declination = (obliquity.sin() * ecliptic_lon.sin()).asin();
+    let right_ascension = (obliquity.cos() * ecliptic_lon.sin()).atan2(ecliptic_lon.cos());
+    let sidereal = 280.460_618_37 + 360.985_647_366_29 * n;
+    (
+        declination.to_degrees(),
+        normalize_lon(right_ascension.to_degrees() - sidereal),
+    )
+}
+
+/// Whether the sun is below the horizon at `pos`, given the subsolar point `sun`
+fn is_night(sun: (f64, f64), pos: (f64, f64)) -> bool {
+    let (lat0, lat) = (sun.0.to_radians(), pos.0.to_radians());
+    let dlon = (pos.1 - sun.1).to_radians();
+    lat0.sin() * lat.sin() + lat0.cos() * lat.cos() * dlon.cos() < 0.0
+}
+
+impl<Message> canvas::Program<Message> for WorldMap<'_> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let palette = theme.extended_palette();
+        let mut frame = Frame::new(renderer, bounds.size());
+        let center = frame.center();
+        let radius = (frame.width().min(frame.height()) / 2.0 - 10.0).max(1.0);
+        let to_screen = |pos| {
+            let (x, y) = project(self.center, pos);
+            Point::new(center.x + x as f32 * radius, center.y - y as f32 * radius)
+        };
+
+        frame.fill(&Path::circle(center, radius), palette.background.weak.color);
+        // rings every 5000 km
+        let rings = Stroke::default()
+            .with_color(palette.background.strong.color)
+            .with_width(1.0);
+        for ring in 1..4 {
+            frame.stroke(&Path::circle(center, radius * ring as f32 / 4.0), rings);
+        }
+        frame.stroke(&Path::circle(center, radius), rings.with_width(2.0));
+
+        // the night side, shaded cell by cell since the terminator is no simple shape here
+        let sun = subsolar_point(self.time);
+        let cell = 2.0 / NIGHT_CELLS as f64;
+        let night = Color {
+            a: 0.35,
+            ..Color::BLACK
+        };
+        for row in 0..NIGHT_CELLS {
+            for col in 0..NIGHT_CELLS {
+                let x = -1.0 + (col as f64 + 0.5) * cell;
+                let y = 1.0 - (row as f64 + 0.5) * cell;
+                if let Some(pos) = unproject(self.center, (x, y))
+                    && is_night(sun, pos)
+                {
+                    let size = cell as f32 * radius;
+                    frame.fill_rectangle(
+                        Point::new(
+                            center.x + x as f32 * radius - size / 2.0,
+                            center.y - y as f32 * radius - size / 2.0,
+                        ),
+                        Size::new(size, size),
+                        night,
+                    );
+                }
+            }
+        }
+        // the terminator is every point 90° from the subsolar point
+        let (lat0, lon0) = (sun.0.to_radians(), sun.1.to_radians());
+        let terminator = Path::new(|path| {
+            let mut last: Option<(f64, f64)> = None;
+            for step in 0..=360 {
+                let bearing = (step as f64).to_radians();
+                let lat = (lat0.cos() * bearing.cos()).asin();
+                let lon = lon0 + (bearing.sin() * lat0.cos()).atan2(-lat0.sin() * lat.sin());
+                let pos = (lat.to_degrees(), lon.to_degrees());
+                let xy = project(self.center, pos);
+                // don't draw across the map where the line passes the antipode
+                match last {
+                    Some(last) if (last.0 - xy.0).hypot(last.1 - xy.1) < 0.5 => {
+                        path.line_to(to_screen(pos))
+                    }
+                    _ => path.move_to(to_screen(pos)),
+                }
+                last = Some(xy);
+            }
+        });
+        frame.stroke(
+            &terminator,
+            Stroke::default()
+                .with_color(Color::from_rgb8(0xe0, 0xaf, 0x68))
+                .with_width(1.5),
+        );
+
+        let color = |band| match self.band_color {
+            Some(band_color) => band_color(band),
+            None => palette.primary.strong.color,
+        };
+        for point in self.points {
+            let pos = to_screen(point.pos);
+            match point.kind {
+                PointKind::Grid => frame.fill_rectangle(
+                    Point::new(pos.x - 2.0, pos.y - 2.0),
+                    Size::new(4.0, 4.0),
+                    color(point.band),
+                ),
+                PointKind::Entity => frame.stroke(
+                    &Path::circle(pos, 5.0),
+                    Stroke::default()
+                        .with_color(color(point.band))
+                        .with_width(1.5),
+                ),
+            }
+        }
+        frame.fill(&Path::circle(center, 4.0), palette.danger.strong.color);
+        vec![frame.into_geometry()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_night, project, subsolar_point, unproject};
+
+    fn close(a: (f64, f64), b: (f64, f64)) -> bool {
+        (a.0 - b.0).abs() < 1e-6 && (a.1 - b.1).abs() < 1e-6
+    }
+
+    #[test]
+    pub fn test_project() {
+        let center = (51.5, 1.0);
+        assert!(close((0.0, 0.0), project(center, center)));
+        // a quarter of the way around the world is halfway to the edge
+        assert!(close((0.0, 0.5), project((0.0, 0.0), (90.0, 0.0))));
+        assert!(close((-0.5, 0.0), project((0.0, 0.0), (0.0, -90.0))));
+        let (x, y) = project(center, (-51.5, -179.0));
+        assert!((x.hypot(y) - 1.0).abs() < 1e-6);
+        for pos in [(40.0, -75.0), (-33.9, 151.2), (35.7, 139.7)] {
+            let (lat, lon) = unproject(center, project(center, pos)).unwrap();
+            assert!(close(pos, (lat, lon)));
+        }
+        assert!(unproject(center, (0.8, 0.8)).is_none());
+    }
+
+    #[test]
+    pub fn test_subsolar_point() {
+        // June solstice, near local noon in Greenwich
+        let (lat, lon) = subsolar_point("2025-06-21T12:00:00Z".parse().unwrap());
+        assert!((lat - 23.44).abs() < 0.05, "{}", lat);
+        assert!(lon.abs() < 1.0, "{}", lon);
+        // March equinox, 09:01 UTC
+        let (lat, lon) = subsolar_point("2025-03-20T09:01:00Z".parse().unwrap());
+        assert!(lat.abs() < 0.05, "{}", lat);
+        assert!((lon - 46.5).abs() < 2.5, "{}", lon);
+        assert!(is_night((lat, lon), (0.0, lon + 180.0)));
+        assert!(!is_night((lat, lon), (45.0, lon)));
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::HashSet;
+
+use db::{
+    data::{FieldType, ImportPolicy, Log, LogRecord},
+    provenance::Source,
+};
+use iced::{
+    Subscription,
+    futures::{SinkExt, channel::mpsc::Sender},
+};
+use jiff::{civil::DateTime, tz::TimeZone};
+use log::warn;
+use tokio::net::UdpSocket;
+use util::{band::Band, freq::Frequency};
+
+use crate::{lookup::xml_value, wsjtx};
+
+/// Sent as `<app>` in our broadcasts, so the listener can skip them
+const APP_NAME: &str = env!("CARGO_PKG_NAME");
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Listening,
+    Failed(String),
+    /// A contact and the program that logged it
+    Contact(LogRecord, Source),
+}
+
+/// Receives N1MM Logger+ (and DXLog, which sends the same format) contact broadcasts
+/// on `addr` and emits each new contact as a record. QSOs WSJT-X logs are received too
+/// when its UDP server is set to `addr`.
+pub fn listen(addr: String) -> Subscription<Event> {
+    Subscription::run_with_id(
+        ("n1mm", addr.clone()),
+        iced::stream::channel(100, move |mut output| async move {
+            if let Err(e) = run(&addr, &mut output).await {
+                warn!("N1MM listener on {} stopped: {}", addr, e);
+                let _ = output.send(Event::Failed(e.to_string())).await;
+            }
+        }),
+    )
+}
+
+async fn run(addr: &str, output: &mut Sender<Event>) -> anyhow::Result<()> {
+    let socket = UdpSocket::bind(addr).await?;
+    output.send(Event::Listening).await?;
+    // N1MM repeats a contact when it is re-sent from another networked station
+    let mut seen = HashSet::new();
+    let mut buf = vec![0; 65536];
+    loop {
+        let len = socket.recv(&mut buf).await?;
+        if let Some(adif) = wsjtx::logged_adif(&buf[..len]) {
+            let adif = adif::parse::parse_adif(&adif);
+            match Log::adif_records(adif, ImportPolicy::PreserveAll, &TimeZone::UTC) {
+                Ok(records) => {
+                    for record in records {
+                        output.send(Event::Contact(record, Source::Wsjtx)).await?;
+                    }
+                }
+                Err(e) => warn!("Skipped a QSO from WSJT-X: {}", e),
+            }
+            continue;
+        }
+        let xml = String::from_utf8_lossy(&buf[..len]);
+        let Some((id, record)) = parse_contact(&xml) else {
+            continue;
+        };
+        if id.is_empty() || seen.insert(id) {
+            let source = Source::Program("N1MM".to_string());
+            output.send(Event::Contact(record, source)).await?;
+        }
+    }
+}
+
+/// Parses a `<contactinfo>` packet into the N1MM contact id and a record.
+/// Other packets, such as `<contactreplace>` or radio info, and our own broadcasts return None.
+pub fn parse_contact(xml: &str) -> Option<(String, LogRecord)> {
+    let contact = xml_value(xml, "contactinfo")?;
+    let field = |tag| xml_value(&contact, tag).filter(|v| !v.is_empty());
+    if field("app").as_deref() == Some(APP_NAME) {
+        return None;
+    }
+    // counters and zones are sent as 0 when the contest has none
+    let number = |tag| field(tag).filter(|v| v != "0");
+
+    let mut record = LogRecord::new();
+    let time = DateTime::strptime("%Y-%m-%d %H:%M:%S", field("timestamp")?).ok()?;
+    record.insert_timestamp(time.to_zoned(jiff::tz::TimeZone::UTC).ok()?.timestamp());
+    record.insert_field(FieldType::WorkedCall, &field("call")?.to_ascii_uppercase());
+    // frequencies are in units of 10 Hz
+    if let Some(freq) = field("rxfreq").and_then(|f| f.parse::<u64>().ok())
+        && freq > 0
+    {
+        let freq = Frequency::from_hz(freq * 10);
+        record.insert_frequency(FieldType::Frequency, freq);
+    }
+    if let Some(mode) = field("mode") {
+        let mode = match mode.as_str() {
+            "USB" | "LSB" => "SSB",
+            mode => mode,
+        };
+        record.insert_field(FieldType::Mode, mode);
+    }
+    let fields = [
+        (FieldType::SentRST, field("snt")),
+        (FieldType::RcvdRST, field("rcv")),
+        (FieldType::SentSerial, number("sntnr")),
+        (FieldType::RcvdSerial, number("rcvnr")),
+        (FieldType::GridSquare, field("gridsquare")),
+        (FieldType::CQZ, number("zone")),
+        (FieldType::Name, field("name")),
+        (FieldType::QTH, field("qth")),
+        (FieldType::Comment, field("comment")),
+        (FieldType::Other("ARRL_SECT".into()), field("section")),
+        (FieldType::Other("PRECEDENCE".into()), field("prec")),
+        (FieldType::Other("CHECK".into()), number("ck")),
+        (FieldType::RcvdExchange, field("exchange1")),
+    ];
+    for (ty, val) in fields {
+        if let Some(val) = val {
+            record.insert_field(ty, &val);
+        }
+    }
+    Some((field("ID").unwrap_or_default(), record))
+}
+
+/// Builds a `<contactinfo>` packet for a logged record, the reverse of `parse_contact`
+pub fn contact_xml(record: &LogRecord, my_call: &str, contest: &str) -> String {
+    let get = |ty: FieldType| record.get_field(&ty).unwrap_or_default();
+    let timestamp = get(FieldType::Timestamp)
+        .parse::<jiff::Timestamp>()
+        .map(|t| t.strftime("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default();
+    let freq = record.frequency();
+    let band = freq
+        .and_then(Band::from_freq)
+        .map(|b| b.range_mhz().0.to_string())
+        .unwrap_or_default();
+    let freq = freq.map(|f| (f.hz() / 10).to_string()).unwrap_or_default();
+    let fields = [
+        ("app", APP_NAME.to_string()),
+        ("contestname", contest.to_string()),
+        ("timestamp", timestamp),
+        ("mycall", my_call.to_string()),
+        ("band", band),
+        ("rxfreq", freq.clone()),
+        ("txfreq", freq),
+        ("mode", get(FieldType::Mode)),
+        ("call", get(FieldType::WorkedCall)),
+        ("snt", get(FieldType::SentRST)),
+        ("sntnr", get(FieldType::SentSerial)),
+        ("rcv", get(FieldType::RcvdRST)),
+        ("rcvnr", get(FieldType::RcvdSerial)),
+        ("gridsquare", get(FieldType::GridSquare)),
+        ("section", get(FieldType::Other("ARRL_SECT".into()))),
+        ("exchange1", get(FieldType::RcvdExchange)),
+        ("comment", get(FieldType::Comment)),
+        ("qth", get(FieldType::QTH)),
+        ("name", get(FieldType::Name)),
+        ("zone", get(FieldType::CQZ)),
+        (
+            "ID",
+            record.id().map(|id| id.to_string()).unwrap_or_default(),
+        ),
+    ];
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<contactinfo>\n");
+    for (tag, val) in fields {
+        xml += &format!("    <{}>{}</{}>\n", tag, xml_escape(&val), tag);
+    }
+    xml += "</contactinfo>\n";
+    xml
+}
+
+fn xml_escape(val: &str) -> String {
+    val.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use db::data::{FieldType, LogRecord};
+
+    use super::{contact_xml, parse_contact};
+
+    #[test]
+    pub fn test_parse_contact() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<contactinfo>
+    <app>N1MM</app>
+    <contestname>CQWWCW</contestname>
+    <timestamp>2025-11-29 16:43:38</timestamp>
+    <mycall>W2XYZ</mycall>
+    <band>14</band>
+    <rxfreq>1402512</rxfreq>
+    <txfreq>1402512</txfreq>
+    <mode>CW</mode>
+    <call>ja1abc</call>
+    <snt>599</snt>
+    <sntnr>0</sntnr>
+    <rcv>599</rcv>
+    <rcvnr>0</rcvnr>
+    <gridsquare></gridsquare>
+    <section></section>
+    <zone>25</zone>
+    <ck>0</ck>
+    <ID>d2a01e9a2a2148a1a9c8e1d7e2c3f4b5</ID>
+</contactinfo>"#;
+        let (id, record) = parse_contact(xml).unwrap();
+        assert_eq!("d2a01e9a2a2148a1a9c8e1d7e2c3f4b5", id);
+        let get = |ty| record.get_field(&ty);
+        assert_eq!(
+            Some("2025-11-29T16:43:38Z".to_string()),
+            get(FieldType::Timestamp)
+        );
+        assert_eq!(Some("JA1ABC".to_string()), get(FieldType::WorkedCall));
+        assert_eq!(Some("14.02512".to_string()), get(FieldType::Frequency));
+        assert_eq!(Some("25".to_string()), get(FieldType::CQZ));
+        assert_eq!(None, get(FieldType::SentSerial));
+        assert_eq!(None, get(FieldType::GridSquare));
+
+        let radio = "<RadioInfo><app>N1MM</app><Freq>1402512</Freq></RadioInfo>";
+        assert!(parse_contact(radio).is_none());
+    }
+
+    #[test]
+    pub fn test_contact_xml() {
+        let mut record = LogRecord::new();
+        record
+            .insert_timestamp("2025-11-29T16:43:38Z".parse().unwrap())
+            .insert_field(FieldType::WorkedCall, "JA1ABC")
+            .insert_field(FieldType::Frequency, "3.525")
+            .insert_field(FieldType::Comment, "<tnx> & 73");
+        let xml = contact_xml(&record, "W2XYZ", "CQ WW DX");
+        assert!(xml.contains("<timestamp>2025-11-29 16:43:38</timestamp>"));
+        assert!(xml.contains("<band>3.5</band>"));
+        assert!(xml.contains("<rxfreq>352500</rxfreq>"));
+        assert!(xml.contains("<comment>&lt;tnx&gt; &amp; 73</comment>"));
+        // our own broadcasts are not logged a second time
+        assert!(parse_contact(&xml).is_none());
+        let theirs = xml.replace("<app>veelog</app>", "<app>N1MM</app>");
+        let (_, parsed) = parse_contact(&theirs).unwrap();
+        assert_eq!(
+            record.get_field(&FieldType::Comment),
+            parsed.get_field(&FieldType::Comment)
+        );
+        assert_eq!(
+            record.get_field(&FieldType::Frequency),
+            parsed.get_field(&FieldType::Frequency)
+        );
+    }
+}
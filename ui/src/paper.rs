@@ -0,0 +1,147 @@
+use anyhow::{Result, anyhow, bail};
+use db::data::{FieldType, LogRecord};
+use jiff::{Timestamp, fmt::strtime, tz::TimeZone};
+use util::{
+    band::Band,
+    callsign,
+    freq::Frequency,
+    mode::{self, ModeClass},
+};
+
+/// Column headers of the paper log grid, in the order Tab moves through them
+pub const COLUMNS: [&str; 7] = ["Date", "Time", "Band/Freq", "Mode", "Call", "Sent", "Rcvd"];
+/// Column focused after a row is logged, as the columns before it carry forward
+pub const CALL_COLUMN: usize = 4;
+
+/// Parses a date like `2025-07-28` and a time like `1432` or `14:32`, both in UTC
+pub fn parse_utc(date: &str, time: &str) -> Result<Timestamp> {
+    let text = format!("{} {}", date.trim(), time.trim().replace(':', ""));
+    let ts = strtime::parse("%Y-%m-%d %H%M", &text)
+        .and_then(|t| t.to_datetime())
+        .and_then(|t| t.to_zoned(TimeZone::UTC))
+        .map_err(|_| anyhow!("Enter the date as YYYY-MM-DD and the time as HHMM"))?
+        .timestamp();
+    if ts > Timestamp::now() {
+        bail!("The QSO time is in the future");
+    }
+    Ok(ts)
+}
+
+/// A row of the paper log grid, each column as typed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PaperRow(pub [String; 7]);
+
+impl PaperRow {
+    /// The row below this one. Date, time, band and mode stay the same for a run of QSOs.
+    pub fn next(&self) -> Self {
+        let mut next = Self::default();
+        next.0[..CALL_COLUMN].clone_from_slice(&self.0[..CALL_COLUMN]);
+        next
+    }
+
+    /// The QSO for this row. Reports left empty default to the usual one for the mode.
+    pub fn to_record(&self) -> Result<LogRecord> {
+        let [date, time, band, mode, call, sent, rcvd] = &self.0;
+        let mut record = LogRecord::new();
+        record
+            .insert_timestamp(parse_utc(date, time)?)
+            .insert_field(FieldType::WorkedCall, &callsign::validate_callsign(call)?);
+        // paper logs often only note the band
+        match Band::from_name(band.trim()) {
+            Some(band) => record.insert_field(FieldType::from_adif_field("BAND"), band.name()),
+            None => record.insert_field(
+                FieldType::Frequency,
+                &Frequency::parse_khz_or_mhz(band.trim())?.to_string(),
+            ),
+        };
+        let mode = mode.trim().to_ascii_uppercase();
+        if mode.is_empty() {
+            bail!("Enter a mode");
+        }
+        let class = ModeClass::from_mode(&mode);
+        record.insert_field(FieldType::Mode, &mode);
+        for (ty, rst) in [(FieldType::SentRST, sent), (FieldType::RcvdRST, rcvd)] {
+            let rst = match rst.trim() {
+                "" => class.default_rst(),
+                rst => rst,
+            };
+            mode::validate_rst(rst, class)?;
+            record.insert_field(ty, rst);
+        }
+        Ok(record)
+    }
+}
+
+/// State of the paper log screen
+#[derive(Default)]
+pub struct PaperLog {
+    /// Rows logged since the screen was opened, oldest first
+    pub logged: Vec<PaperRow>,
+    pub row: PaperRow,
+    /// Column with the cursor, for Tab to move on from
+    pub focused: usize,
+    pub error: Option<String>,
+}
+
+impl PaperLog {
+    /// Starts the next row after the current one was logged
+    pub fn advance(&mut self) {
+        let next = self.row.next();
+        self.logged.push(std::mem::replace(&mut self.row, next));
+        self.focused = CALL_COLUMN;
+        self.error = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use db::data::FieldType;
+
+    use super::{PaperLog, PaperRow};
+
+    #[test]
+    pub fn test_paper_row() {
+        let row = |cols: [&str; 7]| PaperRow(cols.map(str::to_string));
+        let mut paper = PaperLog {
+            row: row(["1987-03-14", "21:05", "20m", "cw", "w1aw", "", "559"]),
+            ..Default::default()
+        };
+        let record = paper.row.to_record().unwrap();
+        let field = |ty: FieldType| record.get_field(&ty);
+        assert_eq!(Some("W1AW".to_string()), field(FieldType::WorkedCall));
+        assert_eq!(
+            Some("20m".to_string()),
+            field(FieldType::from_adif_field("BAND"))
+        );
+        assert_eq!(None, field(FieldType::Frequency));
+        assert_eq!(Some("CW".to_string()), field(FieldType::Mode));
+        assert_eq!(Some("599".to_string()), field(FieldType::SentRST));
+        assert_eq!(Some("559".to_string()), field(FieldType::RcvdRST));
+        assert_eq!(
+            "1987-03-14T21:05:00Z".parse::<jiff::Timestamp>().ok(),
+            record
+                .get_field(&FieldType::Timestamp)
+                .and_then(|t| t.parse().ok())
+        );
+
+        paper.advance();
+        assert_eq!(1, paper.logged.len());
+        assert_eq!(
+            row(["1987-03-14", "21:05", "20m", "cw", "", "", ""]),
+            paper.row
+        );
+
+        paper.row.0[2] = "7025".to_string();
+        paper.row.0[4] = "DL1ABC".to_string();
+        paper.row.0[6] = "5x9".to_string();
+        assert!(paper.row.to_record().is_err());
+        paper.row.0[6] = "579".to_string();
+        let record = paper.row.to_record().unwrap();
+        assert_eq!(
+            Some("7.025".to_string()),
+            record.get_field(&FieldType::Frequency)
+        );
+        paper.row.0[0] = "2999-01-01".to_string();
+        assert!(paper.row.to_record().is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use util::mode::ModeClass;
+
+// rmode_t bits from hamlib's rig.h
+const RIG_MODE_AM: u64 = 1 << 0;
+const RIG_MODE_CW: u64 = 1 << 1;
+const RIG_MODE_USB: u64 = 1 << 2;
+const RIG_MODE_LSB: u64 = 1 << 3;
+const RIG_MODE_RTTY: u64 = 1 << 4;
+const RIG_MODE_FM: u64 = 1 << 5;
+const RIG_MODE_WFM: u64 = 1 << 6;
+const RIG_MODE_CWR: u64 = 1 << 7;
+const RIG_MODE_RTTYR: u64 = 1 << 8;
+const RIG_MODE_PKTLSB: u64 = 1 << 10;
+const RIG_MODE_PKTUSB: u64 = 1 << 11;
+const RIG_MODE_PKTFM: u64 = 1 << 12;
+
+/// Hamlib's name for a rig mode, for display
+pub fn mode_name(mode: u64) -> &'static str {
+    match mode {
+        RIG_MODE_AM => "AM",
+        RIG_MODE_CW => "CW",
+        RIG_MODE_USB => "USB",
+        RIG_MODE_LSB => "LSB",
+        RIG_MODE_RTTY => "RTTY",
+        RIG_MODE_FM => "FM",
+        RIG_MODE_WFM => "WFM",
+        RIG_MODE_CWR => "CWR",
+        RIG_MODE_RTTYR => "RTTYR",
+        RIG_MODE_PKTLSB => "PKTLSB",
+        RIG_MODE_PKTUSB => "PKTUSB",
+        RIG_MODE_PKTFM => "PKTFM",
+        _ => "",
+    }
+}
+
+/// The rig mode named `name` by hamlib, e.g. by rigctld. 0 if hamlib has no such mode.
+pub fn mode_from_name(name: &str) -> u64 {
+    [
+        RIG_MODE_AM,
+        RIG_MODE_CW,
+        RIG_MODE_USB,
+        RIG_MODE_LSB,
+        RIG_MODE_RTTY,
+        RIG_MODE_FM,
+        RIG_MODE_WFM,
+        RIG_MODE_CWR,
+        RIG_MODE_RTTYR,
+        RIG_MODE_PKTLSB,
+        RIG_MODE_PKTUSB,
+        RIG_MODE_PKTFM,
+    ]
+    .into_iter()
+    .find(|mode| mode_name(*mode) == name)
+    .unwrap_or(0)
+}
+
+/// The ADIF MODE matching a rig mode. Data modes are ambiguous and return None.
+pub fn adif_mode(mode: u64) -> Option<&'static str> {
+    match mode {
+        RIG_MODE_AM => Some("AM"),
+        RIG_MODE_CW | RIG_MODE_CWR => Some("CW"),
+        RIG_MODE_USB | RIG_MODE_LSB => Some("SSB"),
+        RIG_MODE_RTTY | RIG_MODE_RTTYR => Some("RTTY"),
+        RIG_MODE_FM | RIG_MODE_WFM => Some("FM"),
+        _ => None,
+    }
+}
+
+/// The rig mode to operate an ADIF mode in: sideband by the usual convention of LSB
+/// below 10MHz, and data modes on USB with the rig's data input
+pub fn rig_mode(mode: &str, freq_hz: f64) -> u64 {
+    match mode.to_ascii_uppercase().as_str() {
+        "CW" => RIG_MODE_CW,
+        "AM" => RIG_MODE_AM,
+        "FM" => RIG_MODE_FM,
+        "RTTY" => RIG_MODE_RTTY,
+        _ if ModeClass::from_mode(mode) == ModeClass::Digital => RIG_MODE_PKTUSB,
+        _ if freq_hz < 10e6 => RIG_MODE_LSB,
+        _ => RIG_MODE_USB,
+    }
+}
+
+pub fn mode_class(mode: u64) -> Option<ModeClass> {
+    match mode {
+        RIG_MODE_PKTLSB | RIG_MODE_PKTUSB | RIG_MODE_PKTFM => Some(ModeClass::Digital),
+        _ => adif_mode(mode).map(ModeClass::from_mode),
+    }
+}
+
+/// Meter readings of the rig, None where the rig doesn't report them
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Meters {
+    /// Signal strength in dB relative to S9, as hamlib reports RIG_LEVEL_STRENGTH
+    pub strength: Option<f32>,
+    pub swr: Option<f32>,
+    /// ALC from 0 to 1
+    pub alc: Option<f32>,
+}
+
+/// Signal strength relative to S9 in S units, with 6dB per unit below S9
+pub fn s_units(db: f32) -> String {
+    match db.round() as i32 {
+        db if db > 0 => format!("S9+{}", db),
+        db => format!("S{}", (9 + db.div_euclid(6)).max(0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        RIG_MODE_CWR, RIG_MODE_LSB, RIG_MODE_PKTUSB, RIG_MODE_USB, mode_from_name, rig_mode,
+        s_units,
+    };
+
+    #[test]
+    pub fn test_rig_mode() {
+        assert_eq!(RIG_MODE_PKTUSB, rig_mode("FT8", 7.074e6));
+        assert_eq!(RIG_MODE_LSB, rig_mode("SSB", 7.150e6));
+        assert_eq!(RIG_MODE_USB, rig_mode("SSB", 14.250e6));
+        assert_eq!(RIG_MODE_CWR, mode_from_name("CWR"));
+        assert_eq!(0, mode_from_name("SAM"));
+    }
+
+    #[test]
+    pub fn test_s_units() {
+        assert_eq!("S9", s_units(0.0));
+        assert_eq!("S9+20", s_units(20.0));
+        assert_eq!("S8", s_units(-6.0));
+        assert_eq!("S7", s_units(-8.0));
+        assert_eq!("S0", s_units(-60.0));
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+
+use crate::lookup::xml_value;
+
+const SOLAR_URL: &str = "https://www.hamqsl.com/solarxml.php";
+
+/// How often the indices are fetched. hamqsl.com updates them every few hours.
+const FETCH_INTERVAL: Duration = Duration::from_secs(30 * 60);
+const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Solar flux and geomagnetic indices
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolarData {
+    pub sfi: String,
+    pub a_index: String,
+    pub k_index: String,
+    /// When the indices were published, as given by the source, e.g. "21 Jun 2025 1200 GMT"
+    pub updated: String,
+}
+
+impl SolarData {
+    pub fn parse(xml: &str) -> Option<Self> {
+        Some(Self {
+            sfi: xml_value(xml, "solarflux")?,
+            a_index: xml_value(xml, "aindex")?,
+            k_index: xml_value(xml, "kindex")?,
+            updated: xml_value(xml, "updated").unwrap_or_default(),
+        })
+    }
+}
+
+/// Fetches the current indices as XML. This blocks on the network, so run it off the UI thread.
+pub fn fetch() -> Result<String> {
+    let xml = ureq::get(SOLAR_URL).call()?.body_mut().read_to_string()?;
+    SolarData::parse(&xml).ok_or_else(|| anyhow!("No solar indices in the response"))?;
+    Ok(xml)
+}
+
+/// The last fetched indices, kept on disk so they are shown while offline
+pub struct SolarCache {
+    pub data: Option<SolarData>,
+    /// Whether `data` is from an earlier run or the last fetch failed
+    pub stale: bool,
+    due: Instant,
+}
+
+impl SolarCache {
+    pub fn load(path: &Path) -> Self {
+        Self {
+            data: fs::read_to_string(path)
+                .ok()
+                .and_then(|xml| SolarData::parse(&xml)),
+            stale: true,
+            due: Instant::now(),
+        }
+    }
+
+    /// Whether it is time to fetch again, and if so schedules the next attempt
+    pub fn start_fetch(&mut self, now: Instant) -> bool {
+        if now < self.due {
+            return false;
+        }
+        self.due = now + RETRY_INTERVAL;
+        true
+    }
+
+    pub fn fetched(&mut self, xml: &str, path: &Path, now: Instant) -> Result<()> {
+        self.data = SolarData::parse(xml);
+        self.stale = false;
+        self.due = now + FETCH_INTERVAL;
+        fs::write(path, xml)?;
+        Ok(())
+    }
+
+    pub fn failed(&mut self) {
+        self.stale = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{SolarCache, SolarData};
+
+    const XML: &str = r#"<?xml version="1.0" encoding="ISO-8859-1"?>
+<solar>
+<solardata>
+<source url="http://www.n0nbh.com">N0NBH</source>
+<updated> 21 Jun 2025 1200 GMT</updated>
+<solarflux>148</solarflux>
+<aindex> 5</aindex>
+<kindex>2</kindex>
+<xray>B5.2</xray>
+</solardata>
+</solar>"#;
+
+    #[test]
+    pub fn test_solar_cache() {
+        let data = SolarData::parse(XML).unwrap();
+        assert_eq!(
+            ("148", "5", "2", "21 Jun 2025 1200 GMT"),
+            (
+                data.sfi.as_str(),
+                data.a_index.as_str(),
+                data.k_index.as_str(),
+                data.updated.as_str()
+            )
+        );
+
+        let path = std::env::temp_dir().join(format!("veelog-solar-{}.xml", std::process::id()));
+        let mut cache = SolarCache::load(&path);
+        assert!(cache.data.is_none());
+        let now = Instant::now();
+        assert!(cache.start_fetch(now));
+        assert!(!cache.start_fetch(now));
+        cache.fetched(XML, &path, now).unwrap();
+        assert!(!cache.start_fetch(now + Duration::from_secs(60 * 10)));
+        // a new run shows the last values until the next fetch succeeds
+        let cache = SolarCache::load(&path);
+        assert_eq!(Some(data), cache.data);
+        assert!(cache.stale);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
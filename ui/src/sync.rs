@@ -0,0 +1,101 @@
+use std::{net::SocketAddr, time::Duration};
+
+use db::data::Log;
+use iced::{
+    Subscription,
+    futures::{SinkExt, channel::mpsc::Sender},
+};
+use log::warn;
+use tokio::net::{TcpListener, TcpStream};
+
+/// Wait before connecting to the other station again
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const NO_TOKEN: &str = "Set sync_token before syncing the log";
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Listening,
+    Failed(String),
+    Connected(SocketAddr),
+    Disconnected(String),
+}
+
+/// Waits on `addr` for the other station to connect and keeps the log in sync with it.
+/// `generation` restarts the listener when another log is opened.
+pub fn listen(addr: String, token: String, log: Log, generation: u64) -> Subscription<Event> {
+    Subscription::run_with_id(
+        ("sync-listen", addr.clone(), token.clone(), generation),
+        iced::stream::channel(100, move |mut output| async move {
+            if token.is_empty() {
+                let _ = output.send(Event::Failed(NO_TOKEN.to_string())).await;
+                return;
+            }
+            let listener = match TcpListener::bind(&addr).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    warn!("Could not listen for sync on {}: {}", addr, e);
+                    let _ = output.send(Event::Failed(e.to_string())).await;
+                    return;
+                }
+            };
+            let _ = output.send(Event::Listening).await;
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => session(stream, &token, &log, &mut output).await,
+                    Err(e) => warn!("Could not accept sync connection: {}", e),
+                }
+            }
+        }),
+    )
+}
+
+/// Connects to the other station at `peer` and keeps the log in sync with it,
+/// reconnecting after a delay when the connection drops
+pub fn connect(peer: String, token: String, log: Log, generation: u64) -> Subscription<Event> {
+    Subscription::run_with_id(
+        ("sync-connect", peer.clone(), token.clone(), generation),
+        iced::stream::channel(100, move |mut output| async move {
+            if token.is_empty() {
+                let _ = output.send(Event::Failed(NO_TOKEN.to_string())).await;
+                return;
+            }
+            loop {
+                match TcpStream::connect(&peer).await {
+                    Ok(stream) => session(stream, &token, &log, &mut output).await,
+                    Err(e) => {
+                        let _ = output.send(Event::Disconnected(e.to_string())).await;
+                    }
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }),
+    )
+}
+
+/// Syncs over `stream` until either side hangs up or the subscription is dropped
+async fn session(stream: TcpStream, token: &str, log: &Log, output: &mut Sender<Event>) {
+    let res = async {
+        let peer = stream.peer_addr()?;
+        let stream = stream.into_std()?;
+        // the sync runs on a blocking thread
+        stream.set_nonblocking(false)?;
+        output.send(Event::Connected(peer)).await?;
+        let log = log.clone();
+        let token = token.to_string();
+        let events = output.clone();
+        tokio::task::spawn_blocking(move || {
+            db::sync::sync_with(&log, stream, &token, || !events.is_closed())
+        })
+        .await??;
+        anyhow::Ok(())
+    }
+    .await;
+    let reason = match res {
+        Ok(()) => "connection closed".to_string(),
+        Err(e) => {
+            warn!("Sync session ended: {}", e);
+            e.to_string()
+        }
+    };
+    let _ = output.send(Event::Disconnected(reason)).await;
+}
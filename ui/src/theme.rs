@@ -0,0 +1,46 @@
+use iced::{Color, Theme, theme::Palette};
+
+/// Name of the high contrast theme, for use outdoors in bright sunlight
+const HIGH_CONTRAST: &str = "High Contrast";
+
+fn high_contrast() -> Theme {
+    Theme::custom(
+        HIGH_CONTRAST.to_string(),
+        Palette {
+            background: Color::BLACK,
+            text: Color::WHITE,
+            primary: Color::from_rgb8(0xff, 0xd7, 0x00),
+            success: Color::from_rgb8(0x00, 0xff, 0x5f),
+            danger: Color::from_rgb8(0xff, 0x30, 0x30),
+        },
+    )
+}
+
+/// Every selectable theme: the iced built-ins followed by our own
+pub fn all() -> Vec<Theme> {
+    let mut themes = Theme::ALL.to_vec();
+    themes.push(high_contrast());
+    themes
+}
+
+/// Finds a theme by its display name, falling back to Tokyo Night
+pub fn by_name(name: &str) -> Theme {
+    all()
+        .into_iter()
+        .find(|t| t.to_string() == name)
+        .unwrap_or(Theme::TokyoNight)
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::Theme;
+
+    use super::{HIGH_CONTRAST, by_name};
+
+    #[test]
+    pub fn test_by_name() {
+        assert_eq!(Theme::Dracula, by_name(&Theme::Dracula.to_string()));
+        assert_eq!(HIGH_CONTRAST, by_name(HIGH_CONTRAST).to_string());
+        assert_eq!(Theme::TokyoNight, by_name("Solarized Neon"));
+    }
+}
@@ -0,0 +1,119 @@
+use std::time::{Duration, Instant};
+
+/// How long toasts stay up unless dismissed, errors long enough to be read after a QSO
+const INFO_TIME: Duration = Duration::from_secs(5);
+const ERROR_TIME: Duration = Duration::from_secs(20);
+/// Most toasts shown at once, the oldest go first
+const MAX_TOASTS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Info,
+    Error,
+}
+
+/// A message shown over the screen for a while, without stopping the operator
+#[derive(Debug, Clone, PartialEq)]
+pub struct Toast {
+    pub kind: ToastKind,
+    pub text: String,
+    /// How often the same message came up while shown
+    pub count: usize,
+    expires: Instant,
+}
+
+/// The toasts shown, oldest first
+#[derive(Debug, Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+}
+
+impl Toasts {
+    /// Shows `text`. A message already shown is not repeated, it stays up longer instead.
+    pub fn push(&mut self, kind: ToastKind, text: String, now: Instant) {
+        let expires = now
+            + match kind {
+                ToastKind::Info => INFO_TIME,
+                ToastKind::Error => ERROR_TIME,
+            };
+        if let Some(toast) = self
+            .toasts
+            .iter_mut()
+            .find(|t| t.kind == kind && t.text == text)
+        {
+            toast.count += 1;
+            toast.expires = expires;
+            return;
+        }
+        self.toasts.push(Toast {
+            kind,
+            text,
+            count: 1,
+            expires,
+        });
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+    }
+
+    pub fn dismiss(&mut self, i: usize) {
+        if i < self.toasts.len() {
+            self.toasts.remove(i);
+        }
+    }
+
+    /// Takes down the toasts shown long enough at `now`
+    pub fn expire(&mut self, now: Instant) {
+        self.toasts.retain(|t| t.expires > now);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Toast> {
+        self.toasts.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.toasts.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{MAX_TOASTS, ToastKind, Toasts};
+
+    #[test]
+    pub fn test_toasts() {
+        let now = Instant::now();
+        let mut toasts = Toasts::default();
+        toasts.push(ToastKind::Info, "Imported 12 QSOs".to_string(), now);
+        toasts.push(ToastKind::Error, "Could not open rig".to_string(), now);
+        toasts.push(
+            ToastKind::Error,
+            "Could not open rig".to_string(),
+            now + Duration::from_secs(10),
+        );
+        let shown: Vec<_> = toasts.iter().map(|t| (t.text.as_str(), t.count)).collect();
+        assert_eq!(
+            vec![("Imported 12 QSOs", 1), ("Could not open rig", 2)],
+            shown
+        );
+
+        // the info is gone first, the repeated error stays up from its last time
+        toasts.expire(now + Duration::from_secs(6));
+        assert_eq!(1, toasts.iter().count());
+        toasts.expire(now + Duration::from_secs(21));
+        assert_eq!(1, toasts.iter().count());
+        toasts.expire(now + Duration::from_secs(31));
+        assert!(toasts.is_empty());
+
+        for i in 0..MAX_TOASTS + 2 {
+            toasts.push(ToastKind::Info, format!("Toast {}", i), now);
+        }
+        assert_eq!(MAX_TOASTS, toasts.iter().count());
+        assert_eq!("Toast 2", toasts.iter().next().unwrap().text);
+        toasts.dismiss(0);
+        toasts.dismiss(MAX_TOASTS);
+        assert_eq!("Toast 3", toasts.iter().next().unwrap().text);
+    }
+}
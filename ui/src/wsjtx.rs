@@ -0,0 +1,71 @@
+/// Starts every WSJT-X UDP message
+const MAGIC: u32 = 0xadbccbda;
+/// Sent when a QSO is logged, with the QSO as an ADIF record
+const LOGGED_ADIF: u32 = 12;
+
+/// The ADIF text of a WSJT-X "Logged ADIF" message. Other messages, such as status and
+/// decodes, return None.
+///
+/// Messages are big-endian: the magic number, the schema, the message type, then the id
+/// of the WSJT-X instance and the ADIF text, each a length-prefixed UTF-8 string.
+pub fn logged_adif(packet: &[u8]) -> Option<String> {
+    let mut rest = packet;
+    let mut u32 = || {
+        let (head, tail) = rest.split_first_chunk::<4>()?;
+        rest = tail;
+        Some(u32::from_be_bytes(*head))
+    };
+    if u32()? != MAGIC {
+        return None;
+    }
+    let _schema = u32()?;
+    if u32()? != LOGGED_ADIF {
+        return None;
+    }
+    let _id = utf8(&mut rest)?;
+    utf8(&mut rest)
+}
+
+/// Reads a length-prefixed string, where a length of 0xffffffff is a null string
+fn utf8(rest: &mut &[u8]) -> Option<String> {
+    let (len, tail) = rest.split_first_chunk::<4>()?;
+    let len = match u32::from_be_bytes(*len) {
+        u32::MAX => 0,
+        len => len as usize,
+    };
+    let text = tail.get(..len)?;
+    *rest = &tail[len..];
+    Some(String::from_utf8_lossy(text).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::logged_adif;
+
+    fn packet(ty: u32, strings: &[&str]) -> Vec<u8> {
+        let mut packet = Vec::new();
+        for n in [0xadbccbda, 3, ty] {
+            packet.extend_from_slice(&u32::to_be_bytes(n));
+        }
+        for s in strings {
+            packet.extend_from_slice(&(s.len() as u32).to_be_bytes());
+            packet.extend_from_slice(s.as_bytes());
+        }
+        packet
+    }
+
+    #[test]
+    pub fn test_logged_adif() {
+        let adif = "<call:4>W1AW <qso_date:8>20250728 <time_on:6>030000 <eor>";
+        assert_eq!(
+            Some(adif.to_string()),
+            logged_adif(&packet(12, &["WSJT-X", adif]))
+        );
+        // a heartbeat
+        assert_eq!(None, logged_adif(&packet(0, &["WSJT-X"])));
+        // cut short
+        let full = packet(12, &["WSJT-X", adif]);
+        assert_eq!(None, logged_adif(&full[..full.len() - 1]));
+        assert_eq!(None, logged_adif(b"<contactinfo></contactinfo>"));
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt::Display;
+
+use crate::freq::Frequency;
+
+/// Amateur bands as enumerated by the ADIF specification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Band {
+    M2190,
+    M630,
+    M560,
+    M160,
+    M80,
+    M60,
+    M40,
+    M30,
+    M20,
+    M17,
+    M15,
+    M12,
+    M10,
+    M8,
+    M6,
+    M5,
+    M4,
+    M2,
+    M1_25,
+    Cm70,
+    Cm33,
+    Cm23,
+    Cm13,
+    Cm9,
+    Cm6,
+    Cm3,
+    Cm1_25,
+    Mm6,
+    Mm4,
+    Mm2_5,
+    Mm2,
+    Mm1,
+    Submm,
+}
+
+/// (band, ADIF name, lower edge Hz, upper edge Hz)
+const BANDS: &[(Band, &str, u64, u64)] = &[
+    (Band::M2190, "2190m", 135_700, 137_800),
+    (Band::M630, "630m", 472_000, 479_000),
+    (Band::M560, "560m", 501_000, 504_000),
+    (Band::M160, "160m", 1_800_000, 2_000_000),
+    (Band::M80, "80m", 3_500_000, 4_000_000),
+    (Band::M60, "60m", 5_060_000, 5_450_000),
+    (Band::M40, "40m", 7_000_000, 7_300_000),
+    (Band::M30, "30m", 10_100_000, 10_150_000),
+    (Band::M20, "20m", 14_000_000, 14_350_000),
+    (Band::M17, "17m", 18_068_000, 18_168_000),
+    (Band::M15, "15m", 21_000_000, 21_450_000),
+    (Band::M12, "12m", 24_890_000, 24_990_000),
+    (Band::M10, "10m", 28_000_000, 29_700_000),
+    (Band::M8, "8m", 40_000_000, 45_000_000),
+    (Band::M6, "6m", 50_000_000, 54_000_000),
+    (Band::M5, "5m", 54_000_001, 69_900_000),
+    (Band::M4, "4m", 70_000_000, 71_000_000),
+    (Band::M2, "2m", 144_000_000, 148_000_000),
+    (Band::M1_25, "1.25m", 222_000_000, 225_000_000),
+    (Band::Cm70, "70cm", 420_000_000, 450_000_000),
+    (Band::Cm33, "33cm", 902_000_000, 928_000_000),
+    (Band::Cm23, "23cm", 1_240_000_000, 1_300_000_000),
+    (Band::Cm13, "13cm", 2_300_000_000, 2_450_000_000),
+    (Band::Cm9, "9cm", 3_300_000_000, 3_500_000_000),
+    (Band::Cm6, "6cm", 5_650_000_000, 5_925_000_000),
+    (Band::Cm3, "3cm", 10_000_000_000, 10_500_000_000),
+    (Band::Cm1_25, "1.25cm", 24_000_000_000, 24_250_000_000),
+    (Band::Mm6, "6mm", 47_000_000_000, 47_200_000_000),
+    (Band::Mm4, "4mm", 75_500_000_000, 81_000_000_000),
+    (Band::Mm2_5, "2.5mm", 119_980_000_000, 123_000_000_000),
+    (Band::Mm2, "2mm", 134_000_000_000, 149_000_000_000),
+    (Band::Mm1, "1mm", 241_000_000_000, 250_000_000_000),
+    (Band::Submm, "submm", 300_000_000_000, 7_500_000_000_000),
+];
+
+impl Band {
+    pub fn all() -> impl Iterator<Item = Band> {
+        BANDS.iter().map(|b| b.0)
+    }
+
+    /// Finds the band containing `freq`, band edges inclusive
+    pub fn from_freq(freq: Frequency) -> Option<Band> {
+        BANDS
+            .iter()
+            .find(|(_, _, low, high)| (*low..=*high).contains(&freq.hz()))
+            .map(|b| b.0)
+    }
+
+    /// Finds the band containing `freq` (in MHz), rounded to the Hz
+    pub fn from_freq_mhz(freq: f64) -> Option<Band> {
+        Self::from_freq(Frequency::from_hz((freq * 1e6).round() as u64))
+    }
+
+    /// Parses an ADIF band name such as "20m" or "70CM"
+    pub fn from_name(name: &str) -> Option<Band> {
+        BANDS
+            .iter()
+            .find(|b| b.1.eq_ignore_ascii_case(name))
+            .map(|b| b.0)
+    }
+
+    /// The ADIF name of this band
+    pub fn name(&self) -> &'static str {
+        self.entry().1
+    }
+
+    /// Lower and upper band edges in MHz
+    pub fn range_mhz(&self) -> (f64, f64) {
+        let b = self.entry();
+        (b.2 as f64 / 1e6, b.3 as f64 / 1e6)
+    }
+
+    fn entry(&self) -> &'static (Band, &'static str, u64, u64) {
+        BANDS
+            .iter()
+            .find(|b| b.0 == *self)
+            .expect("every band is in BANDS")
+    }
+}
+
+impl Display for Band {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Band;
+    use crate::freq::Frequency;
+
+    #[test]
+    pub fn test_band_lookup() {
+        assert_eq!(Some(Band::M20), Band::from_freq_mhz(14.074));
+        assert_eq!(Some(Band::M40), Band::from_freq_mhz(7.0));
+        assert_eq!(Some(Band::Cm70), Band::from_freq_mhz(432.1));
+        assert_eq!(None, Band::from_freq_mhz(14.5));
+        // edges are exact, 14.350 MHz is in 20m and 1 Hz above is not
+        assert_eq!(
+            Some(Band::M20),
+            Band::from_freq(Frequency::from_hz(14_350_000))
+        );
+        assert_eq!(None, Band::from_freq(Frequency::from_hz(14_350_001)));
+        assert_eq!(Some(Band::M2190), Band::from_freq_mhz(0.1357));
+        assert_eq!(Some(Band::M1_25), Band::from_name("1.25M"));
+        assert_eq!(Some(Band::Cm3), Band::from_freq_mhz(10_368.1));
+        assert_eq!(Some(Band::Mm6), Band::from_name("6MM"));
+        // 5m starts right above 6m
+        assert_eq!(Some(Band::M6), Band::from_freq_mhz(54.0));
+        assert_eq!(Some(Band::M5), Band::from_freq_mhz(54.000001));
+        assert_eq!("17m", Band::M17.to_string());
+        for band in Band::all() {
+            assert_eq!(Some(band), Band::from_name(band.name()));
+        }
+    }
+}
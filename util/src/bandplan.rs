@@ -0,0 +1,273 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{band::Band, mode::ModeClass};
+
+const CW_DATA: &[ModeClass] = &[ModeClass::Cw, ModeClass::Digital];
+const CW_PHONE: &[ModeClass] = &[ModeClass::Cw, ModeClass::Phone];
+const CW: &[ModeClass] = &[ModeClass::Cw];
+const ALL: &[ModeClass] = &[ModeClass::Cw, ModeClass::Phone, ModeClass::Digital];
+
+/// Band plans to check the rig frequency against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Region {
+    /// Europe, Africa, the Middle East and northern Asia
+    Iaru1,
+    /// The Americas, following the IARU plan
+    Iaru2,
+    /// The rest of Asia and the Pacific
+    Iaru3,
+    /// The US, following the FCC rules for Extra class licensees
+    #[default]
+    Us,
+}
+
+impl Region {
+    pub const ALL: [Region; 4] = [Region::Iaru1, Region::Iaru2, Region::Iaru3, Region::Us];
+
+    fn segments(&self) -> &'static [Segment] {
+        match self {
+            Region::Iaru1 => REGION_1,
+            Region::Iaru2 => REGION_2,
+            Region::Iaru3 => REGION_3,
+            Region::Us => US,
+        }
+    }
+}
+
+impl std::fmt::Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Region::Iaru1 => write!(f, "IARU Region 1"),
+            Region::Iaru2 => write!(f, "IARU Region 2"),
+            Region::Iaru3 => write!(f, "IARU Region 3"),
+            Region::Us => write!(f, "US (FCC)"),
+        }
+    }
+}
+
+/// A sub-band of a band plan
+#[derive(Debug, PartialEq)]
+pub struct Segment {
+    /// Lower and upper edges in MHz
+    pub low: f64,
+    pub high: f64,
+    pub modes: &'static [ModeClass],
+    /// Maximum output power in watts PEP, where the plan sets one
+    pub max_watts: Option<u32>,
+}
+
+const fn seg(low: f64, high: f64, modes: &'static [ModeClass], max_watts: Option<u32>) -> Segment {
+    Segment {
+        low,
+        high,
+        modes,
+        max_watts,
+    }
+}
+
+// Segments only cover the bands they list: a band missing from a plan allows every mode
+// across its whole range, while gaps inside a listed band are out of band.
+const REGION_1: &[Segment] = &[
+    seg(1.81, 1.84, CW_DATA, None),
+    seg(1.84, 2.0, ALL, None),
+    seg(3.5, 3.6, CW_DATA, None),
+    seg(3.6, 3.8, ALL, None),
+    seg(5.3515, 5.3665, ALL, Some(15)),
+    seg(7.0, 7.05, CW_DATA, None),
+    seg(7.05, 7.2, ALL, None),
+    seg(10.1, 10.15, CW_DATA, None),
+    seg(14.0, 14.1, CW_DATA, None),
+    seg(14.1, 14.35, ALL, None),
+    seg(18.068, 18.11, CW_DATA, None),
+    seg(18.11, 18.168, ALL, None),
+    seg(21.0, 21.15, CW_DATA, None),
+    seg(21.15, 21.45, ALL, None),
+    seg(24.89, 24.93, CW_DATA, None),
+    seg(24.93, 24.99, ALL, None),
+    seg(28.0, 28.3, CW_DATA, None),
+    seg(28.3, 29.7, ALL, None),
+    seg(50.0, 50.1, CW, None),
+    seg(50.1, 52.0, ALL, None),
+    seg(144.0, 144.1, CW, None),
+    seg(144.1, 146.0, ALL, None),
+];
+
+const REGION_2: &[Segment] = &[
+    seg(1.8, 1.84, CW_DATA, None),
+    seg(1.84, 2.0, ALL, None),
+    seg(3.5, 3.6, CW_DATA, None),
+    seg(3.6, 4.0, ALL, None),
+    seg(7.0, 7.05, CW_DATA, None),
+    seg(7.05, 7.3, ALL, None),
+    seg(10.1, 10.15, CW_DATA, None),
+    seg(14.0, 14.1, CW_DATA, None),
+    seg(14.1, 14.35, ALL, None),
+    seg(18.068, 18.11, CW_DATA, None),
+    seg(18.11, 18.168, ALL, None),
+    seg(21.0, 21.15, CW_DATA, None),
+    seg(21.15, 21.45, ALL, None),
+    seg(24.89, 24.93, CW_DATA, None),
+    seg(24.93, 24.99, ALL, None),
+    seg(28.0, 28.3, CW_DATA, None),
+    seg(28.3, 29.7, ALL, None),
+    seg(50.0, 50.1, CW, None),
+    seg(50.1, 54.0, ALL, None),
+    seg(144.0, 144.1, CW, None),
+    seg(144.1, 148.0, ALL, None),
+];
+
+const REGION_3: &[Segment] = &[
+    seg(1.8, 1.84, CW_DATA, None),
+    seg(1.84, 2.0, ALL, None),
+    seg(3.5, 3.535, CW_DATA, None),
+    seg(3.535, 3.9, ALL, None),
+    seg(7.0, 7.04, CW_DATA, None),
+    seg(7.04, 7.2, ALL, None),
+    seg(10.1, 10.15, CW_DATA, None),
+    seg(14.0, 14.1, CW_DATA, None),
+    seg(14.1, 14.35, ALL, None),
+    seg(18.068, 18.11, CW_DATA, None),
+    seg(18.11, 18.168, ALL, None),
+    seg(21.0, 21.15, CW_DATA, None),
+    seg(21.15, 21.45, ALL, None),
+    seg(24.89, 24.93, CW_DATA, None),
+    seg(24.93, 24.99, ALL, None),
+    seg(28.0, 28.3, CW_DATA, None),
+    seg(28.3, 29.7, ALL, None),
+    seg(50.0, 50.1, CW, None),
+    seg(50.1, 54.0, ALL, None),
+    seg(144.0, 144.1, CW, None),
+    seg(144.1, 148.0, ALL, None),
+];
+
+const US: &[Segment] = &[
+    seg(1.8, 2.0, ALL, Some(1500)),
+    seg(3.5, 3.6, CW_DATA, Some(1500)),
+    seg(3.6, 4.0, CW_PHONE, Some(1500)),
+    seg(5.3305, 5.4065, ALL, Some(100)),
+    seg(7.0, 7.125, CW_DATA, Some(1500)),
+    seg(7.125, 7.3, CW_PHONE, Some(1500)),
+    seg(10.1, 10.15, CW_DATA, Some(200)),
+    seg(14.0, 14.15, CW_DATA, Some(1500)),
+    seg(14.15, 14.35, CW_PHONE, Some(1500)),
+    seg(18.068, 18.11, CW_DATA, Some(1500)),
+    seg(18.11, 18.168, CW_PHONE, Some(1500)),
+    seg(21.0, 21.2, CW_DATA, Some(1500)),
+    seg(21.2, 21.45, CW_PHONE, Some(1500)),
+    seg(24.89, 24.93, CW_DATA, Some(1500)),
+    seg(24.93, 24.99, CW_PHONE, Some(1500)),
+    seg(28.0, 28.3, CW_DATA, Some(1500)),
+    seg(28.3, 29.7, CW_PHONE, Some(1500)),
+    seg(50.0, 50.1, CW, Some(1500)),
+    seg(144.0, 144.1, CW, Some(1500)),
+];
+
+/// Where a frequency falls in the band plan for a given mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Allocation {
+    /// Outside of every amateur band of the region
+    OutOfBand,
+    /// Inside a band, but in a segment not permitting the mode
+    WrongSegment(Band),
+    InBand(Band),
+}
+
+/// The segment of `region`'s plan containing `freq` (in MHz). The first match wins,
+/// so shared segment edges go to the lower segment.
+pub fn segment(region: Region, freq: f64) -> Option<&'static Segment> {
+    region
+        .segments()
+        .iter()
+        .find(|s| (s.low..=s.high).contains(&freq))
+}
+
+/// Checks whether `class` may be used on `freq` (in MHz) in `region`
+pub fn check(region: Region, freq: f64, class: ModeClass) -> Allocation {
+    let Some(band) = Band::from_freq_mhz(freq) else {
+        return Allocation::OutOfBand;
+    };
+    let modes = match segment(region, freq) {
+        Some(segment) => segment.modes,
+        None => {
+            let (low, high) = band.range_mhz();
+            let listed = region
+                .segments()
+                .iter()
+                .any(|s| s.low >= low && s.high <= high);
+            if listed {
+                return Allocation::OutOfBand;
+            }
+            ALL
+        }
+    };
+    match modes.contains(&class) {
+        true => Allocation::InBand(band),
+        false => Allocation::WrongSegment(band),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Allocation, Region, check, segment};
+    use crate::{band::Band, mode::ModeClass};
+
+    #[test]
+    pub fn test_check() {
+        let us = Region::Us;
+        assert_eq!(
+            Allocation::InBand(Band::M20),
+            check(us, 14.074, ModeClass::Digital)
+        );
+        assert_eq!(
+            Allocation::InBand(Band::M20),
+            check(us, 14.250, ModeClass::Phone)
+        );
+        assert_eq!(
+            Allocation::WrongSegment(Band::M20),
+            check(us, 14.025, ModeClass::Phone)
+        );
+        assert_eq!(
+            Allocation::WrongSegment(Band::M30),
+            check(us, 10.120, ModeClass::Phone)
+        );
+        assert_eq!(
+            Allocation::InBand(Band::M160),
+            check(us, 1.840, ModeClass::Phone)
+        );
+        assert_eq!(Allocation::OutOfBand, check(us, 14.400, ModeClass::Cw));
+        assert_eq!(Some(200), segment(us, 10.120).unwrap().max_watts);
+    }
+
+    #[test]
+    pub fn test_regions() {
+        // 80m ends at 3.8 MHz in region 1 and 40m at 7.2 MHz outside region 2
+        assert_eq!(
+            Allocation::OutOfBand,
+            check(Region::Iaru1, 3.850, ModeClass::Phone)
+        );
+        assert_eq!(
+            Allocation::InBand(Band::M80),
+            check(Region::Iaru2, 3.850, ModeClass::Phone)
+        );
+        assert_eq!(
+            Allocation::OutOfBand,
+            check(Region::Iaru3, 7.250, ModeClass::Phone)
+        );
+        // phone starts lower than in the US
+        assert_eq!(
+            Allocation::InBand(Band::M40),
+            check(Region::Iaru1, 7.080, ModeClass::Phone)
+        );
+        assert_eq!(
+            Allocation::WrongSegment(Band::M40),
+            check(Region::Us, 7.080, ModeClass::Phone)
+        );
+        // bands missing from a plan allow everything
+        assert_eq!(
+            Allocation::InBand(Band::Cm70),
+            check(Region::Iaru1, 432.2, ModeClass::Phone)
+        );
+        assert!(segment(Region::Iaru2, 432.2).is_none());
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::{Result, bail};
+
+/// Longest compound call accepted, e.g. `VP2E/KA1ABCD/QRP`
+pub const MAX_CALL_LEN: usize = 20;
+
+/// Portable and operating indicators that can follow a call
+const SUFFIXES: &[&str] = &["P", "M", "MM", "AM", "QRP", "A", "R"];
+
+/// Validates a callsign, including prefixes and suffixes such as `W1ABC/P` or `VP2E/K1ABC`.
+/// Returns the call in its canonical upper case form.
+pub fn validate_callsign(call: &str) -> Result<String> {
+    let call = call.trim().to_ascii_uppercase();
+    if call.is_empty() {
+        bail!("Callsign is empty")
+    }
+    if call.len() > MAX_CALL_LEN {
+        bail!("Callsign is too long: {}", call)
+    }
+    if !call.chars().all(|c| c.is_ascii_alphanumeric() || c == '/') {
+        bail!("Callsign contains invalid characters: {}", call)
+    }
+    let parts = call.split('/').collect::<Vec<&str>>();
+    if parts.len() > 3 || parts.iter().any(|p| p.is_empty()) {
+        bail!("Callsign has a malformed prefix or suffix: {}", call)
+    }
+    if !parts.iter().any(|p| is_base_call(p)) {
+        bail!("Callsign has no valid base call: {}", call)
+    }
+    Ok(call)
+}
+
+/// Checks a callsign that is still being typed: only the characters and separators are validated
+pub fn is_partial_callsign(call: &str) -> bool {
+    call.len() <= MAX_CALL_LEN
+        && !call.starts_with('/')
+        && !call.contains("//")
+        && call.chars().all(|c| c.is_ascii_alphanumeric() || c == '/')
+}
+
+/// Extracts the home call from a compound call, e.g. `K1ABC` from `VP2E/K1ABC/P`.
+/// Used for dupe and award matching. Returns the input if no part looks like a call.
+pub fn base_call(call: &str) -> &str {
+    call.split('/')
+        .filter(|p| is_base_call(p) && !SUFFIXES.contains(p))
+        .max_by_key(|p| p.len())
+        .unwrap_or(call)
+}
+
+/// A base call is a prefix ending in a digit followed by a letter suffix,
+/// e.g. `K1ABC`, `4U1UN` or `3DA0XYZ`
+fn is_base_call(call: &str) -> bool {
+    let chars = call.as_bytes();
+    if !(3..=10).contains(&chars.len()) || !chars.iter().all(u8::is_ascii_alphanumeric) {
+        return false;
+    }
+    // the last digit separates prefix and suffix
+    let Some(digit) = chars.iter().rposition(u8::is_ascii_digit) else {
+        return false;
+    };
+    let suffix = &chars[digit + 1..];
+    (1..=4).contains(&digit)
+        && (1..=5).contains(&suffix.len())
+        && suffix.iter().all(u8::is_ascii_alphabetic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base_call, is_partial_callsign, validate_callsign};
+
+    #[test]
+    pub fn test_validate_callsign() {
+        for call in [
+            "W1ABC",
+            "w1abc",
+            "W1ABC/P",
+            "VP2E/K1ABC",
+            "DL/W1ABC/P",
+            "4U1UN",
+            "3DA0XYZ",
+            "K1A",
+            "GB13COL",
+            "VK100ANZAC",
+        ] {
+            assert!(validate_callsign(call).is_ok(), "{}", call);
+        }
+        assert_eq!("W1ABC/P", validate_callsign(" w1abc/p ").unwrap());
+        for call in [
+            "",
+            "W1ABC//P",
+            "/W1ABC",
+            "W1-ABC",
+            "ABC",
+            "12345",
+            "W1ABC/P/QRP/M",
+        ] {
+            assert!(validate_callsign(call).is_err(), "{}", call);
+        }
+    }
+
+    #[test]
+    pub fn test_partial_callsign() {
+        assert!(is_partial_callsign("W1A"));
+        assert!(is_partial_callsign("VP2E/"));
+        assert!(!is_partial_callsign("/"));
+        assert!(!is_partial_callsign("W1 A"));
+    }
+
+    #[test]
+    pub fn test_base_call() {
+        assert_eq!("W1ABC", base_call("W1ABC"));
+        assert_eq!("W1ABC", base_call("W1ABC/P"));
+        assert_eq!("K1ABC", base_call("VP2E/K1ABC"));
+        assert_eq!("W1ABC", base_call("DL/W1ABC/QRP"));
+    }
+}
@@ -0,0 +1,195 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{Result, bail};
+
+/// A country/entity from the prefix database
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+    pub name: String,
+    /// Primary prefix, used as the stable key of the entity
+    pub prefix: String,
+    pub cq_zone: u8,
+    pub itu_zone: u8,
+    pub continent: String,
+    /// Latitude in degrees, north positive
+    pub lat: f64,
+    /// Longitude in degrees, east positive
+    pub lon: f64,
+    /// Offset from UTC in hours
+    pub utc_offset: f64,
+    /// False for entities that only count for WAE/CQ awards (marked with `*` in cty.dat)
+    pub dxcc: bool,
+}
+
+/// Per-prefix data that can override the entity defaults
+#[derive(Debug, Clone, Default)]
+struct PrefixInfo {
+    entity: usize,
+    cq_zone: Option<u8>,
+    itu_zone: Option<u8>,
+    continent: Option<String>,
+}
+
+/// The result of a callsign lookup, with any prefix specific overrides applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match<'a> {
+    pub entity: &'a Entity,
+    pub cq_zone: u8,
+    pub itu_zone: u8,
+    pub continent: &'a str,
+}
+
+/// Callsign prefix database in the AD1C cty.dat format
+#[derive(Debug, Default)]
+pub struct PrefixDb {
+    entities: Vec<Entity>,
+    prefixes: HashMap<String, PrefixInfo>,
+    exact: HashMap<String, PrefixInfo>,
+}
+
+impl PrefixDb {
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(data: &str) -> Result<Self> {
+        let mut db = PrefixDb::default();
+        for block in data.split(';') {
+            let block = block.trim();
+            if block.is_empty() {
+                continue;
+            }
+            let fields = block.splitn(9, ':').map(str::trim).collect::<Vec<&str>>();
+            if fields.len() != 9 {
+                bail!("Malformed cty.dat entry: {}", block);
+            }
+            let primary = fields[7];
+            let entity = Entity {
+                name: fields[0].to_string(),
+                prefix: primary.trim_start_matches('*').to_string(),
+                cq_zone: fields[1].parse()?,
+                itu_zone: fields[2].parse()?,
+                continent: fields[3].to_string(),
+                lat: fields[4].parse()?,
+                // cty.dat uses west positive longitudes
+                lon: -fields[5].parse::<f64>()?,
+                utc_offset: fields[6].parse()?,
+                dxcc: !primary.starts_with('*'),
+            };
+            let idx = db.entities.len();
+            db.entities.push(entity);
+
+            for prefix in fields[8]
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+            {
+                let (prefix, info) = Self::parse_prefix(prefix, idx);
+                match prefix.strip_prefix('=') {
+                    Some(call) => db.exact.insert(call.to_string(), info),
+                    None => db.prefixes.insert(prefix, info),
+                };
+            }
+        }
+        Ok(db)
+    }
+
+    /// Splits a prefix such as `=K1ABC(5)[8]{NA}` into the prefix and its overrides
+    fn parse_prefix(raw: &str, entity: usize) -> (String, PrefixInfo) {
+        let mut info = PrefixInfo {
+            entity,
+            ..Default::default()
+        };
+        let end = raw.find(['(', '[', '<', '{', '~']).unwrap_or(raw.len());
+        let overrides = &raw[end..];
+        let between = |open: char, close: char| {
+            let start = overrides.find(open)? + 1;
+            let len = overrides[start..].find(close)?;
+            Some(&overrides[start..start + len])
+        };
+        info.cq_zone = between('(', ')').and_then(|v| v.parse().ok());
+        info.itu_zone = between('[', ']').and_then(|v| v.parse().ok());
+        info.continent = between('{', '}').map(str::to_string);
+        (raw[..end].to_ascii_uppercase(), info)
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Resolves a callsign to its entity, preferring exact callsign matches over the longest prefix
+    pub fn lookup(&self, call: &str) -> Option<Match<'_>> {
+        let call = call.trim().to_ascii_uppercase();
+        if let Some(info) = self.exact.get(&call) {
+            return Some(self.to_match(info));
+        }
+        let prefix = lookup_part(&call);
+        (1..=prefix.len())
+            .rev()
+            .find_map(|len| self.prefixes.get(&prefix[..len]))
+            .map(|info| self.to_match(info))
+    }
+
+    fn to_match<'a>(&'a self, info: &'a PrefixInfo) -> Match<'a> {
+        let entity = &self.entities[info.entity];
+        Match {
+            entity,
+            cq_zone: info.cq_zone.unwrap_or(entity.cq_zone),
+            itu_zone: info.itu_zone.unwrap_or(entity.itu_zone),
+            continent: info.continent.as_deref().unwrap_or(&entity.continent),
+        }
+    }
+}
+
+/// Picks the part of a compound call that determines the entity, e.g. `VP2E` for `VP2E/K1ABC`
+fn lookup_part(call: &str) -> &str {
+    let parts = call
+        .split('/')
+        .filter(|p| !matches!(*p, "" | "P" | "M" | "MM" | "AM" | "QRP" | "A"))
+        .filter(|p| !(p.len() == 1 && p.chars().all(|c| c.is_ascii_digit())))
+        .collect::<Vec<&str>>();
+    match parts.as_slice() {
+        [] => call,
+        [one] => one,
+        [first, second, ..] => match second.len() < first.len() {
+            true => second,
+            false => first,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrefixDb;
+
+    const SAMPLE: &str = "\
+United States:            05:  08:  NA:   37.53:    91.67:     5.0:  K:
+    AA,K,N,W,=W1AW(5)[8],AH0K(27)[64]{OC};
+Japan:                    25:  45:  AS:   36.40:  -138.38:    -9.0:  JA:
+    7J,7K,JA,JE;
+British Virgin Islands:   08:  11:  NA:   18.43:    64.50:     4.0:  VP2V:
+    VP2V;
+Montserrat:               08:  11:  NA:   16.75:    62.18:     4.0:  VP2M:
+    VP2M;
+";
+
+    #[test]
+    pub fn test_prefix_lookup() {
+        let db = PrefixDb::parse(SAMPLE).unwrap();
+        assert_eq!(4, db.entities().len());
+
+        let m = db.lookup("w1abc").unwrap();
+        assert_eq!("United States", m.entity.name);
+        assert_eq!(-91.67, m.entity.lon);
+        assert_eq!(5, m.cq_zone);
+
+        let m = db.lookup("AH0K").unwrap();
+        assert_eq!(27, m.cq_zone);
+        assert_eq!("OC", m.continent);
+
+        assert_eq!("Japan", db.lookup("JA1ABC/P").unwrap().entity.name);
+        assert_eq!("VP2M", db.lookup("VP2M/K1ABC").unwrap().entity.prefix);
+        assert_eq!("VP2V", db.lookup("K1ABC/VP2V").unwrap().entity.prefix);
+        assert!(db.lookup("ZZ9ZZ").is_none());
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::{Result, bail};
+
+use crate::band::Band;
+
+/// The ADIF Propagation_Mode enumeration
+pub const PROP_MODES: &[(&str, &str)] = &[
+    ("AS", "Aircraft scatter"),
+    ("AUE", "Aurora-E"),
+    ("AUR", "Aurora"),
+    ("BS", "Back scatter"),
+    ("ECH", "EchoLink"),
+    ("EME", "Earth-Moon-Earth"),
+    ("ES", "Sporadic E"),
+    ("F2", "F2 reflection"),
+    ("FAI", "Field aligned irregularities"),
+    ("GWAVE", "Ground wave"),
+    ("INTERNET", "Internet-assisted"),
+    ("ION", "Ionoscatter"),
+    ("IRL", "IRLP"),
+    ("LOS", "Line of sight"),
+    ("MS", "Meteor scatter"),
+    ("RPT", "Terrestrial or atmospheric repeater or transponder"),
+    ("RS", "Rain scatter"),
+    ("SAT", "Satellite"),
+    ("TEP", "Trans-equatorial"),
+    ("TR", "Tropospheric ducting"),
+];
+
+/// Continents as used by IOTA references and the CONT field
+pub const CONTINENTS: &[&str] = &["AF", "AN", "AS", "EU", "NA", "OC", "SA"];
+
+/// An ADIF band name such as `20m` or `70CM`
+pub fn validate_band(band: &str) -> Result<String> {
+    match Band::from_name(band.trim()) {
+        Some(band) => Ok(band.name().to_string()),
+        None => bail!("Unknown band: {}", band),
+    }
+}
+
+/// A propagation mode from `PROP_MODES`, e.g. `ES` or `sat`
+pub fn validate_prop_mode(mode: &str) -> Result<String> {
+    let mode = mode.trim().to_ascii_uppercase();
+    match PROP_MODES.iter().any(|(m, _)| *m == mode) {
+        true => Ok(mode),
+        false => bail!("Unknown propagation mode: {}", mode),
+    }
+}
+
+/// An IOTA island group, a continent and three digits, e.g. `EU-005`
+pub fn validate_iota(iota: &str) -> Result<String> {
+    let iota = iota.trim().to_ascii_uppercase();
+    match iota.split_once('-') {
+        Some((continent, number)) if CONTINENTS.contains(&continent) && is_digits(number, 3) => {
+            Ok(iota)
+        }
+        _ => bail!("Invalid IOTA reference: {}", iota),
+    }
+}
+
+/// A SOTA summit, the association, region and three digits, e.g. `W2/WE-003` or `G/LD-001`
+pub fn validate_sota_ref(summit: &str) -> Result<String> {
+    let summit = summit.trim().to_ascii_uppercase();
+    let valid = summit.split_once('/').is_some_and(|(association, rest)| {
+        (1..=4).contains(&association.len())
+            && association.chars().all(|c| c.is_ascii_alphanumeric())
+            && rest.split_once('-').is_some_and(|(region, number)| {
+                region.len() == 2
+                    && region.chars().all(|c| c.is_ascii_alphanumeric())
+                    && is_digits(number, 3)
+            })
+    });
+    match valid {
+        true => Ok(summit),
+        false => bail!("Invalid SOTA reference: {}", summit),
+    }
+}
+
+/// A WWFF area, the program's prefix ending in FF and four digits, e.g. `KFF-1234` or
+/// `DLFF-0001`
+pub fn validate_wwff_ref(area: &str) -> Result<String> {
+    let area = area.trim().to_ascii_uppercase();
+    let valid = area.split_once("FF-").is_some_and(|(program, number)| {
+        (1..=4).contains(&program.len())
+            && program.chars().all(|c| c.is_ascii_alphanumeric())
+            && is_digits(number, 4)
+    });
+    match valid {
+        true => Ok(area),
+        false => bail!("Invalid WWFF reference: {}", area),
+    }
+}
+
+/// A contest identifier such as `CQ-WW-CW` or `ARRL-FD`. The ADIF Contest_ID enumeration
+/// is only a recommendation, so any identifier in its style is accepted.
+pub fn validate_contest_id(id: &str) -> Result<String> {
+    let id = id.trim().to_ascii_uppercase();
+    match !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        true => Ok(id),
+        false => bail!("Invalid contest identifier: {}", id),
+    }
+}
+
+/// A US county as the CNTY field has it, the state and the county separated by a comma,
+/// e.g. `MA,Middlesex`
+pub fn validate_county(county: &str) -> Result<String> {
+    match county.split_once(',') {
+        Some((state, name))
+            if state.trim().len() == 2
+                && state.trim().chars().all(|c| c.is_ascii_alphabetic())
+                && !name.trim().is_empty()
+                && !name.contains(',') =>
+        {
+            Ok(format!(
+                "{},{}",
+                state.trim().to_ascii_uppercase(),
+                name.trim()
+            ))
+        }
+        _ => bail!("Invalid county, expected state,county: {}", county.trim()),
+    }
+}
+
+/// A secondary subdivision as the CNTY field has it. Its form depends on the entity, e.g.
+/// `MA,Middlesex` for a US county or a JA city code, so anything but an empty value is
+/// kept. US counties are tidied up like `validate_county` does.
+pub fn validate_subdivision(subdiv: &str) -> Result<String> {
+    match subdiv.trim() {
+        "" => bail!("The county is empty"),
+        trimmed => Ok(validate_county(trimmed).unwrap_or_else(|_| trimmed.to_string())),
+    }
+}
+
+/// An antenna azimuth in degrees, 0 to 360
+pub fn validate_azimuth(az: &str) -> Result<String> {
+    let az = az.trim();
+    match az.parse::<f64>() {
+        Ok(deg) if (0.0..=360.0).contains(&deg) => Ok(deg.to_string()),
+        _ => bail!("Azimuth is not 0 to 360 degrees: {}", az),
+    }
+}
+
+fn is_digits(v: &str, len: usize) -> bool {
+    v.len() == len && v.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        validate_azimuth, validate_band, validate_contest_id, validate_county, validate_iota,
+        validate_prop_mode, validate_sota_ref, validate_subdivision, validate_wwff_ref,
+    };
+
+    #[test]
+    pub fn test_validate_fields() {
+        assert_eq!("70cm", validate_band("70CM").unwrap());
+        assert!(validate_band("21m").is_err());
+        assert_eq!("3cm", validate_band("3CM").unwrap());
+        assert_eq!("submm", validate_band("SUBMM").unwrap());
+        assert_eq!("SAT", validate_prop_mode(" sat").unwrap());
+        assert!(validate_prop_mode("SKYWAVE").is_err());
+        assert_eq!("EU-005", validate_iota("eu-005").unwrap());
+        assert!(validate_iota("EU-5").is_err());
+        assert!(validate_iota("XX-005").is_err());
+        assert_eq!("W2/WE-003", validate_sota_ref("w2/we-003").unwrap());
+        assert_eq!("G/LD-001", validate_sota_ref("G/LD-001").unwrap());
+        assert!(validate_sota_ref("W2-WE-003").is_err());
+        assert_eq!("KFF-1234", validate_wwff_ref("kff-1234").unwrap());
+        assert_eq!("DLFF-0001", validate_wwff_ref("DLFF-0001").unwrap());
+        assert!(validate_wwff_ref("K-1234").is_err());
+        assert_eq!("CQ-WW-CW", validate_contest_id("cq-ww-cw").unwrap());
+        assert!(validate_contest_id("CQ WW").is_err());
+        assert_eq!("270", validate_azimuth("270").unwrap());
+        assert_eq!("45.5", validate_azimuth("45.50").unwrap());
+        assert!(validate_azimuth("361").is_err());
+        assert!(validate_azimuth("-1").is_err());
+        assert_eq!("MA,Middlesex", validate_county("ma, Middlesex ").unwrap());
+        assert_eq!(
+            "MD,Prince George's",
+            validate_county("MD,Prince George's").unwrap()
+        );
+        assert!(validate_county("Middlesex").is_err());
+        assert!(validate_county("MASS,Middlesex").is_err());
+        assert!(validate_county("MA,").is_err());
+        assert!(validate_county("MA,Middlesex,Cambridge").is_err());
+        assert_eq!(
+            "MA,Middlesex",
+            validate_subdivision("ma, Middlesex").unwrap()
+        );
+        assert_eq!("100101", validate_subdivision(" 100101").unwrap());
+        assert!(validate_subdivision(" ").is_err());
+    }
+}
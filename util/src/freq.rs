@@ -0,0 +1,149 @@
+use anyhow::{Result, bail};
+
+use crate::band::Band;
+
+/// Digits after the decimal point of a MHz value, 1 Hz resolution
+const MHZ_DECIMALS: usize = 6;
+/// Digits after the decimal point of a kHz value, 1 Hz resolution
+const KHZ_DECIMALS: usize = 3;
+
+/// A frequency stored as an integer number of Hz, so that parsing and printing
+/// MHz values never picks up floating point noise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Frequency(u64);
+
+impl Frequency {
+    pub fn from_hz(hz: u64) -> Self {
+        Self(hz)
+    }
+
+    pub fn hz(&self) -> u64 {
+        self.0
+    }
+
+    pub fn khz(&self) -> f64 {
+        self.0 as f64 / 1e3
+    }
+
+    pub fn mhz(&self) -> f64 {
+        self.0 as f64 / 1e6
+    }
+
+    /// Parses a decimal MHz value as used by ADIF, e.g. `7.000`, `14.074` or `.1357`
+    pub fn parse_mhz(freq: &str) -> Result<Self> {
+        Self::parse_decimal(freq, MHZ_DECIMALS)
+    }
+
+    /// Parses a decimal kHz value as sent by clusters, e.g. `14205` or `7025.5`
+    pub fn parse_khz(freq: &str) -> Result<Self> {
+        Self::parse_decimal(freq, KHZ_DECIMALS)
+    }
+
+    /// Parses a frequency typed in either kHz or MHz, e.g. `14205`, `14205.5` or `14.205`.
+    /// Values below 1000 are MHz. Larger ones are kHz, unless only the MHz reading falls
+    /// in a band, like 1296 for 23cm.
+    pub fn parse_khz_or_mhz(freq: &str) -> Result<Self> {
+        let whole = freq.trim().split('.').next().unwrap_or_default();
+        if whole.parse::<u64>().is_ok_and(|whole| whole >= 1000) {
+            let khz = Self::parse_khz(freq)?;
+            if !khz.in_band()
+                && let Ok(mhz) = Self::parse_mhz(freq)
+                && mhz.in_band()
+            {
+                return Ok(mhz);
+            }
+            return Ok(khz);
+        }
+        Self::parse_mhz(freq)
+    }
+
+    fn in_band(&self) -> bool {
+        Band::from_freq(*self).is_some()
+    }
+
+    /// Parses a decimal value with `decimals` digits of 1 Hz resolution after the point
+    fn parse_decimal(freq: &str, decimals: usize) -> Result<Self> {
+        let freq = freq.trim();
+        let (whole, frac) = freq.split_once('.').unwrap_or((freq, ""));
+        if whole.is_empty() && frac.is_empty()
+            || !whole
+                .chars()
+                .chain(frac.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            bail!("Invalid frequency: {}", freq)
+        }
+        // digits past 1 Hz resolution must be zeros
+        let (frac, rest) = frac.split_at(frac.len().min(decimals));
+        if rest.chars().any(|c| c != '0') {
+            bail!("Frequency is more precise than 1 Hz: {}", freq)
+        }
+        let hz = format!("{}{:0<width$}", whole, frac, width = decimals)
+            .parse::<u64>()
+            .map_err(|_| anyhow::anyhow!("Frequency out of range: {}", freq))?;
+        if hz == 0 {
+            bail!("Frequency is zero: {}", freq)
+        }
+        Ok(Self(hz))
+    }
+}
+
+/// Validates a MHz frequency, returning it in its canonical form
+pub fn validate_frequency(freq: &str) -> Result<String> {
+    Ok(Frequency::parse_mhz(freq)?.to_string())
+}
+
+/// Formats as MHz with at least kHz resolution and trailing zeros trimmed after that,
+/// e.g. `7.000`, `10.100` or `14.0745`
+impl std::fmt::Display for Frequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let frac = format!("{:06}", self.0 % 1_000_000);
+        let frac = frac.trim_end_matches('0');
+        write!(f, "{}.{:0<3}", self.0 / 1_000_000, frac)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Frequency, validate_frequency};
+
+    #[test]
+    pub fn test_parse_mhz() {
+        assert_eq!(7_000_000, Frequency::parse_mhz("7.000").unwrap().hz());
+        assert_eq!(10_100_000, Frequency::parse_mhz("10.100").unwrap().hz());
+        assert_eq!(14_000_000, Frequency::parse_mhz("14").unwrap().hz());
+        assert_eq!(14_000_000, Frequency::parse_mhz("14.").unwrap().hz());
+        assert_eq!(135_700, Frequency::parse_mhz(".1357").unwrap().hz());
+        assert_eq!(
+            14_074_000,
+            Frequency::parse_mhz(" 14.074000000 ").unwrap().hz()
+        );
+        for freq in ["", ".", "14,074", "-7.0", "0.000", "14.0740001", "1e6"] {
+            assert!(Frequency::parse_mhz(freq).is_err(), "{}", freq);
+        }
+    }
+
+    #[test]
+    pub fn test_parse_khz_or_mhz() {
+        let hz = |freq| Frequency::parse_khz_or_mhz(freq).unwrap().hz();
+        assert_eq!(14_205_000, hz("14205"));
+        assert_eq!(14_205_000, hz("14.205"));
+        assert_eq!(14_205_500, hz("14205.5"));
+        assert_eq!(1_840_000, hz("1840"));
+        assert_eq!(144_300_000, hz("144.300"));
+        assert_eq!(432_100_000, hz("432.1"));
+        assert_eq!(1_296_200_000, hz("1296.2"));
+        for freq in ["", "14,205", "14205.0001", "0"] {
+            assert!(Frequency::parse_khz_or_mhz(freq).is_err(), "{}", freq);
+        }
+    }
+
+    #[test]
+    pub fn test_display() {
+        assert_eq!("7.000", validate_frequency("7").unwrap());
+        assert_eq!("10.100", validate_frequency("10.1").unwrap());
+        assert_eq!("14.0745", validate_frequency("14.074500").unwrap());
+        assert_eq!("0.1357", validate_frequency("0.1357").unwrap());
+        assert_eq!("144.300001", Frequency::from_hz(144_300_001).to_string());
+    }
+}
@@ -0,0 +1,267 @@
+use anyhow::Result;
+
+/// Mean radius of the earth
+pub const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// (first character, count) of the pairs of a Maidenhead gridsquare: fields are lettered A-R,
+/// squares numbered 0-9 and subsquares lettered A-X, then extended squares and subsquares
+const GRID_PAIRS: [(u8, u8); 5] = [(b'A', 18), (b'0', 10), (b'A', 24), (b'0', 10), (b'A', 24)];
+
+/// How closely `prettyvalidate_gridsquare` checks a gridsquare
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridStrictness {
+    /// Letter and digit pairs in their places, any letter. For grids other programs wrote.
+    Lenient,
+    /// Letters within the Maidenhead ranges too, e.g. no field past R
+    Strict,
+}
+
+/// Checks a 2, 4, 6, 8 or 10 character Maidenhead gridsquare and returns it in the usual
+/// case, field upper and subsquares lower case, e.g. `FN31pr` or `JN58td54oq`
+pub fn prettyvalidate_gridsquare(grid: &str, strictness: GridStrictness) -> Result<String> {
+    if !grid.is_ascii() {
+        anyhow::bail!("Gridsquare is not ASCII: {}", grid)
+    }
+    if !matches!(grid.len(), 2 | 4 | 6 | 8 | 10) {
+        anyhow::bail!("GRIDSQUARE is of invalid length {}: {}", grid.len(), grid)
+    }
+    let mut pretty = String::with_capacity(grid.len());
+    for (i, (pair, (base, count))) in grid.as_bytes().chunks(2).zip(GRID_PAIRS).enumerate() {
+        for &c in pair {
+            let c = c.to_ascii_uppercase();
+            let valid = match (base, strictness) {
+                (b'0', _) => c.is_ascii_digit(),
+                (_, GridStrictness::Lenient) => c.is_ascii_uppercase(),
+                (_, GridStrictness::Strict) => c.wrapping_sub(base) < count,
+            };
+            if !valid {
+                anyhow::bail!("Invalid GRIDSQUARE: {}", grid)
+            }
+            pretty.push(match i {
+                0 => c as char,
+                _ => c.to_ascii_lowercase() as char,
+            });
+        }
+    }
+    Ok(pretty)
+}
+
+/// South west corner and size in degrees, (lat, lon, height, width), of a gridsquare
+fn bounds(grid: &str) -> Result<(f64, f64, f64, f64)> {
+    let chars = grid.trim().to_ascii_uppercase().into_bytes();
+    if !matches!(chars.len(), 2 | 4 | 6 | 8 | 10) {
+        anyhow::bail!("GRIDSQUARE is of invalid length {}: {}", chars.len(), grid)
+    }
+    let (mut lat, mut lon) = (-90.0, -180.0);
+    let (mut height, mut width) = (180.0, 360.0);
+    for (pair, (base, count)) in chars.chunks(2).zip(GRID_PAIRS) {
+        let x = pair[0].wrapping_sub(base);
+        let y = pair[1].wrapping_sub(base);
+        if x >= count || y >= count {
+            anyhow::bail!("Invalid GRIDSQUARE: {}", grid)
+        }
+        width /= count as f64;
+        height /= count as f64;
+        lon += x as f64 * width;
+        lat += y as f64 * height;
+    }
+    Ok((lat, lon, height, width))
+}
+
+/// Latitude and longitude in degrees (north and east positive) of the center of a
+/// 2, 4, 6, 8 or 10 character Maidenhead gridsquare
+pub fn gridsquare_center(grid: &str) -> Result<(f64, f64)> {
+    let (lat, lon, height, width) = bounds(grid)?;
+    Ok((lat + height / 2.0, lon + width / 2.0))
+}
+
+/// South west and north east corners of a gridsquare, as latitude and longitude
+pub fn gridsquare_corners(grid: &str) -> Result<((f64, f64), (f64, f64))> {
+    let (lat, lon, height, width) = bounds(grid)?;
+    Ok(((lat, lon), (lat + height, lon + width)))
+}
+
+/// The 6 character Maidenhead gridsquare containing a position in degrees, north and
+/// east positive, e.g. `FN31pr`
+pub fn gridsquare(lat: f64, lon: f64) -> String {
+    // the north pole and the antimeridian fall into the last field
+    let mut lon = (lon + 180.0).clamp(0.0, 359.999_999);
+    let mut lat = (lat + 90.0).clamp(0.0, 179.999_999);
+    let (mut height, mut width) = (180.0, 360.0);
+    let mut grid = String::with_capacity(6);
+    for (i, (base, count)) in GRID_PAIRS[..3].iter().enumerate() {
+        let count = *count as f64;
+        width /= count;
+        height /= count;
+        let x = (lon / width).floor().min(count - 1.0);
+        let y = (lat / height).floor().min(count - 1.0);
+        for c in [base + x as u8, base + y as u8] {
+            grid.push(match i {
+                0 => c as char,
+                _ => c.to_ascii_lowercase() as char,
+            });
+        }
+        lon -= x * width;
+        lat -= y * height;
+    }
+    grid
+}
+
+/// Great circle distance in km between two positions in degrees
+pub fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat0, lat1) = (from.0.to_radians(), to.0.to_radians());
+    let dlat = lat1 - lat0;
+    let dlon = (to.1 - from.1).to_radians();
+    // haversine, well conditioned for short distances too
+    let a = (dlat / 2.0).sin().powi(2) + lat0.cos() * lat1.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+}
+
+/// Initial bearing in degrees clockwise from north, 0 up to 360, to point an antenna
+/// at `to` from `from`
+pub fn bearing(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat0, lat1) = (from.0.to_radians(), to.0.to_radians());
+    let dlon = (to.1 - from.1).to_radians();
+    let y = dlon.sin() * lat1.cos();
+    let x = lat0.cos() * lat1.sin() - lat0.sin() * lat1.cos() * dlon.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        GridStrictness::{Lenient, Strict},
+        bearing, distance_km, gridsquare, gridsquare_center, gridsquare_corners,
+        prettyvalidate_gridsquare,
+    };
+
+    #[test]
+    pub fn test_prettify_grid() {
+        let grid = "aA00aA".to_string();
+        assert_eq!(
+            "AA00aa".to_string(),
+            prettyvalidate_gridsquare(&grid, Strict).unwrap()
+        );
+        let grid = "aa00AA".to_string();
+        assert_eq!(
+            "AA00aa".to_string(),
+            prettyvalidate_gridsquare(&grid, Strict).unwrap()
+        );
+        let grid = "aa00".to_string();
+        assert_eq!(
+            "AA00".to_string(),
+            prettyvalidate_gridsquare(&grid, Strict).unwrap()
+        );
+        assert_eq!("JN", prettyvalidate_gridsquare("jn", Strict).unwrap());
+        assert_eq!(
+            "JN58td54",
+            prettyvalidate_gridsquare("JN58TD54", Strict).unwrap()
+        );
+        assert_eq!(
+            "JN58td54oq",
+            prettyvalidate_gridsquare("jn58td54OQ", Strict).unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_validate_grid() {
+        for grid in ["J", "JN5", "JN58td5", "JN58td54oq12"] {
+            assert!(
+                prettyvalidate_gridsquare(grid, Lenient).is_err(),
+                "{}",
+                grid
+            );
+        }
+        // letters and digits must alternate in pairs
+        for grid in ["J5", "JNA8", "JN58t4", "JN58tdx4", "JN58td54o1", "JN58-d"] {
+            assert!(
+                prettyvalidate_gridsquare(grid, Lenient).is_err(),
+                "{}",
+                grid
+            );
+        }
+        // fields end at R and subsquares at X, only checked when strict
+        for grid in ["JS01", "ZZ00", "JN58tz", "JN58td54oz"] {
+            assert!(prettyvalidate_gridsquare(grid, Strict).is_err(), "{}", grid);
+            assert!(prettyvalidate_gridsquare(grid, Lenient).is_ok(), "{}", grid);
+        }
+        assert!(prettyvalidate_gridsquare("FN31pr", Lenient).is_ok());
+    }
+
+    #[test]
+    pub fn test_gridsquare_center() {
+        assert_eq!((51.5, 1.0), gridsquare_center("JO01").unwrap());
+        let (lat, lon) = gridsquare_center("fn31pr").unwrap();
+        assert!((lat - 41.729).abs() < 0.001 && (lon + 72.708).abs() < 0.001);
+        assert_eq!((-85.0, -170.0), gridsquare_center("AA").unwrap());
+        assert!(gridsquare_center("JS01").is_err());
+        assert!(gridsquare_center("JO0").is_err());
+        let (lat, lon) = gridsquare_center("JN58td54oq").unwrap();
+        assert!((lat - 48.1445).abs() < 0.0001 && (lon - 11.6300).abs() < 0.0001);
+    }
+
+    #[test]
+    pub fn test_gridsquare() {
+        assert_eq!("FN31pr", gridsquare(41.729, -72.708));
+        assert_eq!("JO01mm", gridsquare(51.5, 1.0));
+        assert_eq!("QF56od", gridsquare(-33.86, 151.21));
+        assert_eq!("RR99xx", gridsquare(90.0, 180.0));
+        let (lat, lon) = gridsquare_center("KP20le").unwrap();
+        assert_eq!("KP20le", gridsquare(lat, lon));
+    }
+
+    #[test]
+    pub fn test_gridsquare_corners() {
+        assert_eq!(
+            ((51.0, 0.0), (52.0, 2.0)),
+            gridsquare_corners("JO01").unwrap()
+        );
+        assert_eq!(
+            ((-90.0, -180.0), (-80.0, -160.0)),
+            gridsquare_corners("AA").unwrap()
+        );
+        let ((south, west), (north, east)) = gridsquare_corners("JN58td54").unwrap();
+        assert!((north - south - 1.0 / 240.0).abs() < 1e-12);
+        assert!((east - west - 2.0 / 240.0).abs() < 1e-12);
+        assert!(gridsquare_corners("JN58td5").is_err());
+    }
+
+    #[test]
+    pub fn test_every_square() {
+        // every square and subsquare of a field is found again from its center
+        for field in ["AA", "JN", "RR"] {
+            for square in 0..100 {
+                let grid = format!("{}{:02}", field, square);
+                let (lat, lon) = gridsquare_center(&grid).unwrap();
+                assert_eq!(grid, gridsquare(lat, lon)[..4], "{}", grid);
+                for sub in 0..24 * 24 {
+                    let sub = [b'a' + sub as u8 % 24, b'a' + sub as u8 / 24];
+                    let grid = format!("{}{}", grid, String::from_utf8_lossy(&sub));
+                    let (lat, lon) = gridsquare_center(&grid).unwrap();
+                    assert_eq!(grid, gridsquare(lat, lon), "{}", grid);
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_distance_and_bearing() {
+        let london = (51.5074, -0.1278);
+        let new_york = (40.7128, -74.0060);
+        assert!((distance_km(london, new_york) - 5570.0).abs() < 5.0);
+        assert!((bearing(london, new_york) - 288.3).abs() < 0.1);
+        assert!((bearing(new_york, london) - 51.2).abs() < 0.1);
+        assert_eq!(0.0, distance_km(london, london));
+
+        // along the equator and the meridian
+        let quarter = std::f64::consts::PI * super::EARTH_RADIUS_KM / 2.0;
+        assert!((distance_km((0.0, 0.0), (0.0, 90.0)) - quarter).abs() < 1e-6);
+        assert!((bearing((0.0, 0.0), (0.0, 90.0)) - 90.0).abs() < 1e-9);
+        assert!((bearing((0.0, 0.0), (0.0, -90.0)) - 270.0).abs() < 1e-9);
+        assert!(bearing((0.0, 0.0), (45.0, 0.0)).abs() < 1e-9);
+        assert!((bearing((10.0, 0.0), (-45.0, 0.0)) - 180.0).abs() < 1e-9);
+        // antipodes are half way around, the antimeridian is no edge
+        assert!((distance_km((45.0, 10.0), (-45.0, -170.0)) - 2.0 * quarter).abs() < 1e-6);
+        assert!((distance_km((45.0, 179.0), (45.0, -179.0)) - 157.25).abs() < 0.01);
+    }
+}
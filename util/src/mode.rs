@@ -0,0 +1,87 @@
+use anyhow::{Result, bail};
+
+/// Broad mode groups used for awards and band plans
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ModeClass {
+    Cw,
+    Phone,
+    Digital,
+}
+
+impl ModeClass {
+    /// Classifies an ADIF MODE (or SUBMODE) value
+    pub fn from_mode(mode: &str) -> ModeClass {
+        match mode.to_ascii_uppercase().as_str() {
+            "CW" => ModeClass::Cw,
+            "SSB" | "USB" | "LSB" | "AM" | "FM" | "DIGITALVOICE" => ModeClass::Phone,
+            _ => ModeClass::Digital,
+        }
+    }
+
+    /// The customary report for a good signal: 59 on phone, 599 on CW and digital
+    pub fn default_rst(&self) -> &'static str {
+        match self {
+            ModeClass::Phone => "59",
+            ModeClass::Cw | ModeClass::Digital => "599",
+        }
+    }
+
+    /// Maximum number of characters in a report for this mode class
+    pub fn rst_len(&self) -> usize {
+        match self {
+            ModeClass::Phone => 2,
+            ModeClass::Cw | ModeClass::Digital => 3,
+        }
+    }
+}
+
+/// Validates a signal report: RS for phone, RST for CW and digital.
+/// Digital modes additionally accept signal to noise reports such as `-12` or `+05`.
+pub fn validate_rst(rst: &str, class: ModeClass) -> Result<()> {
+    if class == ModeClass::Digital
+        && let Some(db) = rst.strip_prefix(['-', '+'])
+    {
+        if (1..=2).contains(&db.len()) && db.chars().all(|c| c.is_ascii_digit()) {
+            return Ok(());
+        }
+        bail!("Invalid dB report: {}", rst)
+    }
+    let digits = rst.as_bytes();
+    let valid = digits.len() == class.rst_len()
+        && (b'1'..=b'5').contains(&digits[0])
+        && digits[1..].iter().all(|d| (b'1'..=b'9').contains(d));
+    match valid {
+        true => Ok(()),
+        false => bail!("Invalid {} report: {}", class, rst),
+    }
+}
+
+impl std::fmt::Display for ModeClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModeClass::Cw => write!(f, "CW"),
+            ModeClass::Phone => write!(f, "Phone"),
+            ModeClass::Digital => write!(f, "Digital"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ModeClass, validate_rst};
+
+    #[test]
+    pub fn test_validate_rst() {
+        assert!(validate_rst("59", ModeClass::Phone).is_ok());
+        assert!(validate_rst("599", ModeClass::Phone).is_err());
+        assert!(validate_rst("599", ModeClass::Cw).is_ok());
+        assert!(validate_rst("59", ModeClass::Cw).is_err());
+        assert!(validate_rst("579", ModeClass::Digital).is_ok());
+        assert!(validate_rst("-12", ModeClass::Digital).is_ok());
+        assert!(validate_rst("-12", ModeClass::Cw).is_err());
+        assert!(validate_rst("69", ModeClass::Phone).is_err());
+        assert!(validate_rst("509", ModeClass::Cw).is_err());
+        assert_eq!("59", ModeClass::from_mode("usb").default_rst());
+        assert_eq!("599", ModeClass::from_mode("FT8").default_rst());
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow, bail};
+
+/// How long to wait for rigctld to answer before giving up on the rig
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Client for hamlib's rigctld network daemon, so a rig can be read and controlled
+/// without linking hamlib.
+///
+/// Errors reading or writing the connection are `io::Error`s. After one the connection is
+/// dropped, as a late answer would be taken for the answer to the next command, and the
+/// next command connects again.
+pub struct Rigctld {
+    addr: String,
+    conn: Option<Connection>,
+}
+
+struct Connection {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl Connection {
+    fn open(addr: &str) -> Result<Self> {
+        let mut last = None;
+        for addr in addr.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, TIMEOUT) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(TIMEOUT))?;
+                    stream.set_write_timeout(Some(TIMEOUT))?;
+                    return Ok(Self {
+                        reader: BufReader::new(stream.try_clone()?),
+                        stream,
+                    });
+                }
+                Err(e) => last = Some(e),
+            }
+        }
+        Err(last.map_or_else(|| anyhow!("{} has no address", addr), Into::into))
+    }
+}
+
+impl Rigctld {
+    /// Connects to rigctld at host:port, e.g. `localhost:4532`
+    pub fn connect(addr: &str) -> Result<Self> {
+        Ok(Self {
+            conn: Some(Connection::open(addr)?),
+            addr: addr.to_string(),
+        })
+    }
+
+    /// Sends `cmd` and returns the `lines` lines of its answer
+    pub fn command(&mut self, cmd: &str, lines: usize) -> Result<Vec<String>> {
+        self.send(cmd)?;
+        let mut answer = Vec::with_capacity(lines);
+        for _ in 0..lines {
+            let line = self.line()?;
+            // failed commands answer with a hamlib error code instead
+            if let Some(code) = line.strip_prefix("RPRT ") {
+                bail!("rigctld returned error {}", code);
+            }
+            answer.push(line);
+        }
+        Ok(answer)
+    }
+
+    /// Sends `cmd`, which rigctld answers with its result code only
+    pub fn set(&mut self, cmd: &str) -> Result<()> {
+        self.send(cmd)?;
+        match self.line()?.as_str() {
+            "RPRT 0" => Ok(()),
+            line => bail!(
+                "rigctld returned error {}",
+                line.trim_start_matches("RPRT ")
+            ),
+        }
+    }
+
+    /// The frequency of the current VFO in Hz
+    pub fn freq(&mut self) -> Result<f64> {
+        Ok(self.command("f", 1)?[0].parse()?)
+    }
+
+    /// Hamlib's name for the current mode, e.g. USB or PKTUSB, and the passband width in Hz
+    pub fn mode(&mut self) -> Result<(String, i64)> {
+        // the mode is followed by the passband width
+        let mut answer = self.command("m", 2)?;
+        let width = answer[1].parse()?;
+        Ok((answer.swap_remove(0), width))
+    }
+
+    /// Says goodbye. rigctld keeps the rig open for its other clients and hangs up
+    /// without an answer.
+    pub fn quit(&mut self) -> Result<()> {
+        if self.conn.is_none() {
+            return Ok(());
+        }
+        self.send("q")?;
+        self.conn = None;
+        Ok(())
+    }
+
+    fn send(&mut self, cmd: &str) -> Result<()> {
+        let conn = match &mut self.conn {
+            Some(conn) => conn,
+            None => self.conn.insert(Connection::open(&self.addr)?),
+        };
+        if let Err(e) = conn.stream.write_all(format!("{}\n", cmd).as_bytes()) {
+            self.conn = None;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    fn line(&mut self) -> Result<String> {
+        let Some(conn) = &mut self.conn else {
+            bail!("rigctld is not connected");
+        };
+        let mut line = String::new();
+        let res = match conn.reader.read_line(&mut line) {
+            Ok(0) => Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "rigctld closed the connection",
+            )),
+            res => res,
+        };
+        if let Err(e) = res {
+            self.conn = None;
+            return Err(e.into());
+        }
+        Ok(line.trim_end().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+        time::Duration,
+    };
+
+    use super::Rigctld;
+
+    #[test]
+    pub fn test_rigctld() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut commands = Vec::new();
+            for answer in [
+                "14074000\n",
+                "USB\n2400\n",
+                "RPRT 0\n",
+                "RPRT -1\n",
+                "RPRT -11\n",
+            ] {
+                let mut cmd = String::new();
+                reader.read_line(&mut cmd).unwrap();
+                commands.push(cmd.trim_end().to_string());
+                stream.write_all(answer.as_bytes()).unwrap();
+            }
+            let mut cmd = String::new();
+            reader.read_line(&mut cmd).unwrap();
+            commands.push(cmd.trim_end().to_string());
+            commands
+        });
+        let mut rig = Rigctld::connect(&addr).unwrap();
+        assert_eq!(14_074_000.0, rig.freq().unwrap());
+        assert_eq!(("USB".to_string(), 2400), rig.mode().unwrap());
+        rig.set("T 1").unwrap();
+        assert!(rig.set("T 0").is_err());
+        assert!(rig.command("l SWR", 1).is_err());
+        rig.quit().unwrap();
+        assert_eq!(
+            ["f", "m", "T 1", "T 0", "l SWR", "q"].to_vec(),
+            server.join().unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            // the first answer comes too late
+            for (answer, delay) in [("14074000\n", 300), ("7030000\n", 0), ("3573000\n", 0)] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut cmd = String::new();
+                reader.read_line(&mut cmd).unwrap();
+                assert_eq!("f\n", cmd);
+                thread::sleep(Duration::from_millis(delay));
+                stream.write_all(answer.as_bytes()).unwrap();
+                // hangs up without answering the next command
+                let _ = reader.read_line(&mut cmd);
+            }
+        });
+        let mut rig = Rigctld::connect(&addr).unwrap();
+        let conn = rig.conn.as_ref().unwrap();
+        let timeout = Some(Duration::from_millis(100));
+        conn.stream.set_read_timeout(timeout).unwrap();
+        let err = rig.freq().unwrap_err();
+        assert!(err.downcast_ref::<io::Error>().is_some());
+        // connects again rather than reading the late answer
+        assert_eq!(7_030_000.0, rig.freq().unwrap());
+        // and again after rigctld hung up
+        let err = rig.freq().unwrap_err();
+        assert!(err.downcast_ref::<io::Error>().is_some());
+        assert_eq!(3_573_000.0, rig.freq().unwrap());
+        drop(rig);
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+use std::{collections::BTreeSet, fs, path::Path};
+
+use anyhow::Result;
+
+/// Super check partial database of known active calls, in the MASTER.SCP format:
+/// one call per line, lines starting with `#` are comments.
+///
+/// Partials match anywhere in a call, so every suffix of every call is indexed.
+/// The suffixes are sorted, making a partial a prefix search over them.
+#[derive(Debug, Default)]
+pub struct ScpDb {
+    calls: Vec<String>,
+    /// (call index, byte offset) of each suffix, sorted by the suffix
+    suffixes: Vec<(u32, u8)>,
+}
+
+impl ScpDb {
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn parse(data: &str) -> Self {
+        let mut calls = data
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(str::to_ascii_uppercase)
+            .collect::<Vec<String>>();
+        calls.sort();
+        calls.dedup();
+        let mut db = Self {
+            calls,
+            suffixes: Vec::new(),
+        };
+        for (idx, call) in db.calls.iter().enumerate() {
+            // suffixes start on characters, a file may hold more than ASCII
+            for (offset, _) in call
+                .char_indices()
+                .take_while(|(i, _)| *i < u8::MAX as usize)
+            {
+                db.suffixes.push((idx as u32, offset as u8));
+            }
+        }
+        let calls = &db.calls;
+        db.suffixes
+            .sort_by(|a, b| suffix(calls, *a).cmp(suffix(calls, *b)));
+        db
+    }
+
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Known calls containing `partial`, in alphabetical order, at most `limit` of them
+    pub fn matches(&self, partial: &str, limit: usize) -> Vec<&str> {
+        let partial = partial.trim().to_ascii_uppercase();
+        if partial.is_empty() {
+            return Vec::new();
+        }
+        let start = self
+            .suffixes
+            .partition_point(|s| suffix(&self.calls, *s) < partial.as_str());
+        let found = self.suffixes[start..]
+            .iter()
+            .take_while(|s| suffix(&self.calls, **s).starts_with(&partial))
+            .map(|(idx, _)| *idx)
+            .collect::<BTreeSet<u32>>();
+        found
+            .into_iter()
+            .take(limit)
+            .map(|idx| self.calls[idx as usize].as_str())
+            .collect()
+    }
+
+    pub fn contains(&self, call: &str) -> bool {
+        self.calls
+            .binary_search(&call.trim().to_ascii_uppercase())
+            .is_ok()
+    }
+}
+
+fn suffix(calls: &[String], (idx, offset): (u32, u8)) -> &str {
+    &calls[idx as usize][offset as usize..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScpDb;
+
+    #[test]
+    pub fn test_scp_matches() {
+        let db = ScpDb::parse(
+            "# Master.scp 2025-07-01\nK1ABC\nW1AW\nDL1ABC\nk1abd\nJA1ABC\n\nK1ABC\nVE3ABC/P\n",
+        );
+        assert_eq!(6, db.len());
+        assert_eq!(vec!["K1ABC", "K1ABD"], db.matches("k1ab", 10));
+        assert_eq!(
+            vec!["DL1ABC", "JA1ABC", "K1ABC", "VE3ABC/P"],
+            db.matches("ABC", 10)
+        );
+        assert_eq!(vec!["DL1ABC", "JA1ABC"], db.matches("1ABC", 2));
+        assert_eq!(vec!["VE3ABC/P"], db.matches("/P", 10));
+        assert!(db.matches("XYZ", 10).is_empty());
+        assert!(db.matches("", 10).is_empty());
+        assert!(db.contains("w1aw"));
+        assert!(!db.contains("W1A"));
+
+        let db = ScpDb::parse("OZ1ÆØ\nW1AW\n");
+        assert_eq!(vec!["OZ1ÆØ"], db.matches("Ø", 10));
+        assert_eq!(vec!["OZ1ÆØ"], db.matches("1Æ", 10));
+        assert!(db.matches("é", 10).is_empty());
+    }
+}